@@ -42,6 +42,10 @@ reftest: $(S)src/test/harness/reftest/reftest.rs servo
 	@$(call E, compile: $@)
 	$(Q)$(RUSTC) -L$(B)/src/support/png/rust-png/ -L$(B)/src/support/png/libpng/ -o $@ $<
 
+layouttest: $(S)src/test/harness/layouttest/layouttest.rs servo
+	@$(call E, compile: $@)
+	$(Q)$(RUSTC) -o $@ $<
+
 contenttest: $(S)src/test/harness/contenttest/contenttest.rs servo
 	@$(call E, compile: $@)
 	$(Q)$(RUSTC) $(RFLAGS_servo) -o $@ $< -L .
@@ -58,11 +62,11 @@ check-test:
 
 ifeq ($(CFG_OSTYPE),apple-darwin)
 .PHONY: check
-check: $(DEPS_CHECK_TARGETS_FAST) check-servo check-content check-ref tidy
+check: $(DEPS_CHECK_TARGETS_FAST) check-servo check-content check-ref check-layout tidy
 	@$(call E, check: all)
 
 .PHONY: check-all
-check-all: $(DEPS_CHECK_TARGETS_ALL) check-servo check-content check-ref tidy
+check-all: $(DEPS_CHECK_TARGETS_ALL) check-servo check-content check-ref check-layout tidy
 	@$(call E, check: all)
 else
 .PHONY: check
@@ -92,6 +96,11 @@ check-ref-gpu: reftest
 .PHONY: check-ref
 check-ref: check-ref-cpu check-ref-gpu
 
+.PHONY: check-layout
+check-layout: layouttest
+	@$(call E, check: layout tests)
+	$(Q)./layouttest $(S)src/test/layout/basic.list $(TESTNAME)
+
 .PHONY: check-content
 check-content: contenttest
 	@$(call E, check: contenttests)
@@ -26,6 +26,7 @@ use geom::point::{Point2D, TypedPoint2D};
 use geom::rect::Rect;
 use geom::size::{Size2D, TypedSize2D};
 use geom::scale_factor::ScaleFactor;
+use gfx::render_task::PrintMsg;
 use layers::layers::LayerBufferSet;
 use layers::platform::surface::NativeCompositingGraphicsContext;
 use layers::rendergl;
@@ -42,6 +43,7 @@ use servo_msg::constellation_msg;
 use servo_util::geometry::{DevicePixel, PagePx, ScreenPx, ViewportPx};
 use servo_util::memory::MemoryProfilerChan;
 use servo_util::opts::Opts;
+use servo_util::opts;
 use servo_util::time::{profile, TimeProfilerChan};
 use servo_util::{memory, time, url};
 use std::io::timer::sleep;
@@ -302,12 +304,14 @@ impl IOCompositor {
                 (Ok(CreateDescendantCompositorLayerIfNecessary(pipeline_id,
                                                                  layer_id,
                                                                  rect,
-                                                                 scroll_behavior)),
+                                                                 scroll_behavior,
+                                                                 content_size)),
                  NotShuttingDown) => {
                     self.create_descendant_compositor_layer_if_necessary(pipeline_id,
                                                                          layer_id,
                                                                          rect,
-                                                                         scroll_behavior);
+                                                                         scroll_behavior,
+                                                                         content_size);
                 }
 
                 (Ok(SetLayerPageSize(pipeline_id, layer_id, new_size, epoch)), NotShuttingDown) => {
@@ -423,7 +427,8 @@ impl IOCompositor {
                                                        pipeline_id: PipelineId,
                                                        layer_id: LayerId,
                                                        rect: Rect<f32>,
-                                                       scroll_policy: ScrollPolicy) {
+                                                       scroll_policy: ScrollPolicy,
+                                                       content_size: Option<Size2D<f32>>) {
         match self.scene.root {
             Some(ref root_layer) => {
                 let parent_layer_id = root_layer.extra_data.borrow().id;
@@ -436,7 +441,8 @@ impl IOCompositor {
                                                                layer_id,
                                                                rect,
                                                                page_size,
-                                                               scroll_policy);
+                                                               scroll_policy,
+                                                               content_size);
                     }
                     None => {
                         fail!("Compositor: couldn't find parent layer");
@@ -817,10 +823,28 @@ impl IOCompositor {
             }
         });
 
+        // PDF output is rendered into a PDF document by the renderer of the root pipeline.
+        let output_is_pdf = match self.opts.output_file {
+            Some(ref path) => opts::is_pdf_output(path.as_slice()),
+            None => false,
+        };
+        if self.load_complete && self.ready_state == FinishedLoading
+            && self.opts.output_file.is_some() && output_is_pdf {
+            let path = from_str::<Path>(self.opts.output_file.get_ref().as_slice()).unwrap();
+            let (response_chan, response_port) = channel();
+            self.root_pipeline.get_ref().render_chan.send(PrintMsg(path, response_chan));
+            response_port.recv();
+
+            debug!("shutting down the constellation after generating an output file");
+            let ConstellationChan(ref chan) = self.constellation_chan;
+            chan.send(ExitMsg);
+            self.shutdown_state = ShuttingDown;
+        }
+
         // Render to PNG. We must read from the back buffer (ie, before
         // self.window.present()) as OpenGL ES 2 does not have glReadBuffer().
         if self.load_complete && self.ready_state == FinishedLoading
-            && self.opts.output_file.is_some() {
+            && self.opts.output_file.is_some() && !output_is_pdf {
             let (width, height) = (self.window_size.width.get(), self.window_size.height.get());
             let path = from_str::<Path>(self.opts.output_file.get_ref().as_slice()).unwrap();
             let mut pixels = gl2::read_pixels(0, 0,
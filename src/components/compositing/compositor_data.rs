@@ -18,10 +18,10 @@ use layers::layers::{Layer, Flip, LayerBuffer, LayerBufferSet, NoFlip, TextureLa
 use layers::quadtree::{Tile, Normal, Hidden};
 use layers::platform::surface::{NativeCompositingGraphicsContext, NativeSurfaceMethods};
 use layers::texturegl::{Texture, TextureTarget};
-use script::dom::event::{ClickEvent, MouseDownEvent, MouseMoveEvent, MouseUpEvent};
+use script::dom::event::{ClickEvent, MouseDownEvent, MouseMoveEvent, MouseUpEvent, ScrollEvent};
 use script::script_task::{ScriptChan, SendEventMsg};
-use servo_msg::compositor_msg::{Epoch, FixedPosition, LayerId};
-use servo_msg::compositor_msg::ScrollPolicy;
+use servo_msg::compositor_msg::{Epoch, FixedPosition, LayerId, Scrollable};
+use servo_msg::compositor_msg::{ScrollPolicy, ScrollbarThumb, StickyPosition};
 use servo_msg::constellation_msg::PipelineId;
 use servo_util::geometry::PagePx;
 use std::rc::Rc;
@@ -45,6 +45,18 @@ pub struct CompositorData {
     /// top left corner of the page.
     pub scroll_offset: TypedPoint2D<PagePx, f32>,
 
+    /// If this layer is a scroll container (`overflow: scroll` or `overflow: auto`), the size of
+    /// its contents. The layer shows the part of the contents that it is scrolled to.
+    pub content_size: Option<Size2D<f32>>,
+
+    /// The point of the contents of a scroll container that shows at the top left corner of the
+    /// layer. This is always (0,0) for other layers.
+    pub content_scroll_position: TypedPoint2D<PagePx, f32>,
+
+    /// If this layer is the thumb of a scrollbar, the point that the contents of its scroll
+    /// container are scrolled to. This decides where along its track the thumb shows.
+    pub container_scroll_position: TypedPoint2D<PagePx, f32>,
+
     /// The bounds of this layer in terms of its parent (a.k.a. the scissor box).
     pub bounds: Rect<f32>,
 
@@ -113,6 +125,9 @@ impl CompositorData {
             pipeline: pipeline,
             id: layer_id,
             scroll_offset: TypedPoint2D(0f32, 0f32),
+            content_size: None,
+            content_scroll_position: TypedPoint2D(0f32, 0f32),
+            container_scroll_position: TypedPoint2D(0f32, 0f32),
             bounds: bounds,
             page_size: page_size,
             hidden: hidden,
@@ -145,38 +160,111 @@ impl CompositorData {
 
     /// Adds a child layer to the layer with the given ID and the given pipeline, if it doesn't
     /// exist yet. The child layer will have the same pipeline, tile size, memory limit, and CPU
-    /// painting status as its parent.
+    /// painting status as its parent. Child layers with a content size are scroll containers,
+    /// which want scroll events.
     pub fn add_child_if_necessary(layer: Rc<Layer<CompositorData>>,
                                   child_layer_id: LayerId,
                                   rect: Rect<f32>,
                                   page_size: Size2D<f32>,
-                                  scroll_policy: ScrollPolicy) {
-        // See if we've already made this child layer.
+                                  scroll_policy: ScrollPolicy,
+                                  content_size: Option<Size2D<f32>>) {
+        let wants_scroll_events = match content_size {
+            Some(_) => WantsScrollEvents,
+            None => DoesntWantScrollEvents,
+        };
+
+        // See if we've already made this child layer. If so, only its scroll policy and the size
+        // of its contents can have changed: the constraint of a sticky layer changes whenever the
+        // page is laid out again. A scroll container stays scrolled as far as it was.
         let pipeline_id = layer.extra_data.borrow().pipeline.id;
-        if layer.children().iter().any(|kid| {
-                    kid.extra_data.borrow().pipeline.id == pipeline_id &&
-                    kid.extra_data.borrow().id == child_layer_id
-                }) {
-            return;
-        }
-
-        let new_compositor_data = CompositorData::new(layer.extra_data.borrow().pipeline.clone(),
-                                                      child_layer_id,
-                                                      rect,
-                                                      Some(page_size),
-                                                      layer.extra_data.borrow().cpu_painting,
-                                                      DoesntWantScrollEvents,
-                                                      scroll_policy,
-                                                      false);
-        let new_kid = Rc::new(Layer::new(page_size,
+        let scroll_offset = layer.extra_data.borrow().scroll_offset;
+        for kid in layer.children().iter() {
+            if kid.extra_data.borrow().pipeline.id == pipeline_id &&
+                    kid.extra_data.borrow().id == child_layer_id {
+                let old_content_size = kid.extra_data.borrow().content_size;
+                {
+                    let mut kid_data = kid.extra_data.borrow_mut();
+                    kid_data.scroll_policy = scroll_policy;
+                    kid_data.wants_scroll_events = wants_scroll_events;
+                    kid_data.content_size = content_size;
+                }
+                match content_size {
+                    Some(size) if old_content_size != content_size => {
+                        let unused_buffers = Layer::resize(kid.clone(), size);
+                        if !unused_buffers.is_empty() {
+                            let msg = UnusedBufferMsg(unused_buffers);
+                            let _ = kid.extra_data.borrow().pipeline.render_chan.send_opt(msg);
+                        }
+                    }
+                    _ => {}
+                }
+                CompositorData::scroll(kid.clone(), scroll_offset);
+                CompositorData::move_all_scrollbar_thumbs(layer.clone());
+                return
+            }
+        }
+
+        let mut new_compositor_data =
+            CompositorData::new(layer.extra_data.borrow().pipeline.clone(),
+                                child_layer_id,
+                                rect,
+                                Some(page_size),
+                                layer.extra_data.borrow().cpu_painting,
+                                wants_scroll_events,
+                                scroll_policy,
+                                false);
+        new_compositor_data.content_size = content_size;
+
+        // The tiles of a scroll container cover all of its contents, so that they can be moved
+        // when it scrolls.
+        let tiled_size = match content_size {
+            Some(size) => size,
+            None => page_size,
+        };
+        let new_kid = Rc::new(Layer::new(tiled_size,
                                          Layer::tile_size(layer.clone()),
                                          new_compositor_data));
 
         new_kid.extra_data.borrow_mut().scissor = Some(rect);
         *new_kid.origin.borrow_mut() = rect.origin;
 
-        // Place the kid's layer in the container passed in.
+        // Place the kid's layer in the container passed in, scrolled along with its siblings.
         Layer::add_child(layer.clone(), new_kid.clone());
+        CompositorData::scroll(new_kid, scroll_offset);
+        CompositorData::move_all_scrollbar_thumbs(layer.clone());
+    }
+
+    /// Moves the scrollbar thumbs among the children of the given layer that belong to the given
+    /// scroll container to where its contents are scrolled to.
+    fn move_scrollbar_thumbs(layer: Rc<Layer<CompositorData>>,
+                             container: &Rc<Layer<CompositorData>>) {
+        let pipeline_id = container.extra_data.borrow().pipeline.id;
+        let container_id = container.extra_data.borrow().id;
+        let position = container.extra_data.borrow().content_scroll_position;
+        for kid in layer.children().iter() {
+            if kid.extra_data.borrow().pipeline.id != pipeline_id {
+                continue
+            }
+            let is_thumb = match kid.extra_data.borrow().scroll_policy {
+                ScrollbarThumb(ref constraint) => constraint.container == container_id,
+                _ => false,
+            };
+            if is_thumb {
+                kid.extra_data.borrow_mut().container_scroll_position = position;
+                let scroll_offset = kid.extra_data.borrow().scroll_offset;
+                CompositorData::scroll(kid.clone(), scroll_offset);
+            }
+        }
+    }
+
+    /// Moves the scrollbar thumbs among the children of the given layer to where the contents of
+    /// their scroll containers are scrolled to. Either may have just been added.
+    fn move_all_scrollbar_thumbs(layer: Rc<Layer<CompositorData>>) {
+        for kid in layer.children().iter() {
+            if kid.extra_data.borrow().content_size.is_some() {
+                CompositorData::move_scrollbar_thumbs(layer.clone(), kid)
+            }
+        }
     }
 
     /// Move the layer's descendants that don't want scroll events and scroll by a relative
@@ -199,9 +287,10 @@ impl CompositorData {
             return false
         }
 
-        // Allow children to scroll.
+        // Allow children to scroll, topmost first, so that the innermost scroll container under
+        // the cursor gets the event.
         let cursor = cursor - layer.extra_data.borrow().scroll_offset;
-        for child in layer.children().iter() {
+        for child in layer.children().iter().rev() {
             match child.extra_data.borrow().scissor {
                 None => {
                     error!("CompositorData: unable to perform cursor hit test for layer");
@@ -213,13 +302,20 @@ impl CompositorData {
                                                            delta,
                                                            cursor - rect.origin,
                                                            rect.size) {
+                        CompositorData::move_scrollbar_thumbs(layer.clone(), child);
                         return true
                     }
                 }
             }
         }
 
-        // This scroll event is mine!
+        // This scroll event is mine! A scroll container scrolls its contents, unless they are
+        // scrolled as far as they go already, in which case the parent gets the event.
+        if layer.extra_data.borrow().content_size.is_some() {
+            let old_position = layer.extra_data.borrow().content_scroll_position;
+            return CompositorData::scroll_contents(layer.clone(), old_position - delta)
+        }
+
         // Scroll this layer!
         let old_origin = layer.extra_data.borrow().scroll_offset.clone();
         layer.extra_data.borrow_mut().scroll_offset = old_origin + delta;
@@ -243,10 +339,60 @@ impl CompositorData {
             return false
         }
 
+        CompositorData::send_page_scroll_position(layer.clone());
         let offset = layer.extra_data.borrow().scroll_offset.clone();
         CompositorData::scroll(layer.clone(), offset)
     }
 
+    /// Tells script how far the page of the given layer is scrolled, so that hit testing can find
+    /// where the layers of the page show.
+    fn send_page_scroll_position(layer: Rc<Layer<CompositorData>>) {
+        let offset = layer.extra_data.borrow().scroll_offset.to_untyped();
+        let message = ScrollEvent(LayerId::null(), Point2D(-offset.x, -offset.y));
+        let ScriptChan(ref chan) = layer.extra_data.borrow().pipeline.script_chan;
+        let _ = chan.send_opt(SendEventMsg(layer.extra_data.borrow().pipeline.id.clone(), message));
+    }
+
+    /// Scrolls the contents of a scroll container layer to the given point, as far as they go.
+    /// The tiles of the layer are moved with the contents, and script is told how far they are
+    /// scrolled, so that it can answer `scrollTop` and `scrollLeft`. Returns true if the contents
+    /// moved.
+    fn scroll_contents(layer: Rc<Layer<CompositorData>>,
+                       position: TypedPoint2D<PagePx, f32>)
+                       -> bool {
+        let content_size = match layer.extra_data.borrow().content_size {
+            Some(size) => size,
+            None => fail!("CompositorData: tried to scroll the contents of a non-container"),
+        };
+        let layer_size = match layer.extra_data.borrow().scissor {
+            Some(scissor) => scissor.size,
+            None => fail!("CompositorData: tried to scroll a container with no clipping rect"),
+        };
+
+        let position = position.to_untyped();
+        let max_x = (content_size.width - layer_size.width).max(0.0);
+        let max_y = (content_size.height - layer_size.height).max(0.0);
+        let position = TypedPoint2D(position.x.max(0.0).min(max_x),
+                                    position.y.max(0.0).min(max_y));
+        if position == layer.extra_data.borrow().content_scroll_position {
+            return false
+        }
+        layer.extra_data.borrow_mut().content_scroll_position = position;
+
+        // The tiles cover the contents, so they are moved rather than rendered again. Only the
+        // tiles of the contents that come into view need rendering.
+        //
+        // FIXME(#2003): Like those of iframes, the tiles at the edges of the layer aren't clipped
+        // to it, because layers can't clip their contents.
+        let scroll_offset = layer.extra_data.borrow().scroll_offset;
+        CompositorData::scroll(layer.clone(), scroll_offset);
+
+        let message = ScrollEvent(layer.extra_data.borrow().id, position.to_untyped());
+        let ScriptChan(ref chan) = layer.extra_data.borrow().pipeline.script_chan;
+        let _ = chan.send_opt(SendEventMsg(layer.extra_data.borrow().pipeline.id.clone(), message));
+        true
+    }
+
     /// Actually scrolls the descendants of a layer that scroll. This is called by
     /// `handle_scroll_event` above when it determines that a layer wants to scroll.
     fn scroll(layer: Rc<Layer<CompositorData>>,
@@ -254,15 +400,33 @@ impl CompositorData {
               -> bool {
         let mut result = false;
 
-        // Only scroll this layer if it's not fixed-positioned.
-        if layer.extra_data.borrow().scroll_policy != FixedPosition {
-            // Scroll this layer!
-            layer.extra_data.borrow_mut().scroll_offset = scroll_offset;
-
-            let scroll_offset = layer.extra_data.borrow().scroll_offset.clone();
-            *layer.transform.borrow_mut() = identity().translate(scroll_offset.x.get(), scroll_offset.y.get(), 0.0);
-
-            result = true
+        // Only scroll this layer if it's not fixed-positioned. Sticky layers are then moved back
+        // into view as far as their constraint allows.
+        let translation = match layer.extra_data.borrow().scroll_policy {
+            FixedPosition => None,
+            Scrollable => Some(scroll_offset.to_untyped()),
+            StickyPosition(ref constraint) => {
+                let scroll_offset = scroll_offset.to_untyped();
+                let scroll_position = Point2D(-scroll_offset.x, -scroll_offset.y);
+                Some(scroll_offset + constraint.offset_for_scroll_position(scroll_position))
+            }
+            ScrollbarThumb(ref constraint) => {
+                let position = layer.extra_data.borrow().container_scroll_position.to_untyped();
+                Some(scroll_offset.to_untyped() + constraint.offset_for_scroll_position(position))
+            }
+        };
+        match translation {
+            None => {}
+            Some(translation) => {
+                // Scroll this layer! The tiles of a scroll container are moved by how far it has
+                // scrolled its contents as well.
+                let position = layer.extra_data.borrow().content_scroll_position.to_untyped();
+                layer.extra_data.borrow_mut().scroll_offset = scroll_offset;
+                *layer.transform.borrow_mut() = identity().translate(translation.x - position.x,
+                                                                     translation.y - position.y,
+                                                                     0.0);
+                result = true
+            }
         }
 
         for child in layer.children().iter() {
@@ -277,7 +441,18 @@ impl CompositorData {
     // page coordinates.
     pub fn send_mouse_event(layer: Rc<Layer<CompositorData>>,
                             event: MouseWindowEvent, cursor: TypedPoint2D<PagePx, f32>) {
+        CompositorData::send_mouse_event_to_layer(layer, event, cursor, TypedPoint2D(0f32, 0f32))
+    }
+
+    /// Sends a mouse event to the topmost layer under the cursor. `page_origin` is the position
+    /// of the layer in the page of its pipeline: the descendant layers of a pipeline are placed
+    /// relative to their parent, but script expects points in page coordinates.
+    fn send_mouse_event_to_layer(layer: Rc<Layer<CompositorData>>,
+                                 event: MouseWindowEvent,
+                                 cursor: TypedPoint2D<PagePx, f32>,
+                                 page_origin: TypedPoint2D<PagePx, f32>) {
         let cursor = cursor - layer.extra_data.borrow().scroll_offset;
+        let pipeline_id = layer.extra_data.borrow().pipeline.id;
         for child in layer.children().iter() {
             if child.extra_data.borrow().hidden {
                 continue;
@@ -290,18 +465,30 @@ impl CompositorData {
                 Some(rect) => {
                     let rect: TypedRect<PagePx, f32> = Rect::from_untyped(&rect);
                     if rect.contains(&cursor) {
-                        CompositorData::send_mouse_event(child.clone(), event, cursor - rect.origin);
+                        // A layer of another pipeline (an iframe) starts a new page.
+                        let child_page_origin = if child.extra_data.borrow().pipeline.id ==
+                                pipeline_id {
+                            page_origin + rect.origin
+                        } else {
+                            TypedPoint2D(0f32, 0f32)
+                        };
+                        CompositorData::send_mouse_event_to_layer(child.clone(),
+                                                                  event,
+                                                                  cursor - rect.origin,
+                                                                  child_page_origin);
                         return;
                     }
                 }
             }
         }
 
-        // This mouse event is mine!
+        // This mouse event is mine! Layout finds what is under the point, knowing how far the
+        // page and each scroll container in it are scrolled.
+        let point = (cursor + page_origin).to_untyped();
         let message = match event {
-            MouseWindowClickEvent(button, _) => ClickEvent(button, cursor.to_untyped()),
-            MouseWindowMouseDownEvent(button, _) => MouseDownEvent(button, cursor.to_untyped()),
-            MouseWindowMouseUpEvent(button, _) => MouseUpEvent(button, cursor.to_untyped()),
+            MouseWindowClickEvent(button, _) => ClickEvent(button, point),
+            MouseWindowMouseDownEvent(button, _) => MouseDownEvent(button, point),
+            MouseWindowMouseUpEvent(button, _) => MouseUpEvent(button, point),
         };
         let ScriptChan(ref chan) = layer.extra_data.borrow().pipeline.script_chan;
         let _ = chan.send_opt(SendEventMsg(layer.extra_data.borrow().pipeline.id.clone(), message));
@@ -321,7 +508,12 @@ impl CompositorData {
                                             window_rect: Rect<f32>,
                                             scale: f32)
                                             -> bool {
-        let (request, unused) = Layer::get_tile_rects_page(layer.clone(), window_rect, scale);
+        // The tiles of a scroll container cover its contents, of which the part that it is
+        // scrolled to shows.
+        let position = layer.extra_data.borrow().content_scroll_position.to_untyped();
+        let (request, unused) = Layer::get_tile_rects_page(layer.clone(),
+                                                           window_rect.translate(&position),
+                                                           scale);
         let redisplay = !unused.is_empty();
         if redisplay {
             // Send back unused tiles.
@@ -475,7 +667,11 @@ impl CompositorData {
         if layer.extra_data.borrow().pipeline.id != pipeline_id ||
            layer.extra_data.borrow().id != layer_id {
             return layer.children().iter().any(|kid| {
-                CompositorData::move(kid.clone(), pipeline_id, layer_id, origin, window_size)
+                if !CompositorData::move(kid.clone(), pipeline_id, layer_id, origin, window_size) {
+                    return false
+                }
+                CompositorData::move_scrollbar_thumbs(layer.clone(), kid);
+                true
             });
         }
 
@@ -483,6 +679,11 @@ impl CompositorData {
             return false
         }
 
+        // A scroll container scrolls its contents.
+        if layer.extra_data.borrow().content_size.is_some() {
+            return CompositorData::scroll_contents(layer.clone(), Point2D::from_untyped(&origin))
+        }
+
         // Scroll this layer!
         let old_origin = layer.extra_data.borrow().scroll_offset;
         layer.extra_data.borrow_mut().scroll_offset = Point2D::from_untyped(&(origin * -1.0));
@@ -505,6 +706,7 @@ impl CompositorData {
             return false;
         }
 
+        CompositorData::send_page_scroll_position(layer.clone());
         let offset = layer.extra_data.borrow().scroll_offset.clone();
         CompositorData::scroll(layer.clone(), offset)
     }
@@ -83,7 +83,8 @@ impl RenderListener for CompositorChan {
                                       epoch: Epoch) {
         // FIXME(#2004, pcwalton): This assumes that the first layer determines the page size, and
         // that all other layers are immediate children of it. This is sufficient to handle
-        // `position: fixed` but will not be sufficient to handle `overflow: scroll` or transforms.
+        // `position: fixed` and `overflow: scroll`, but layers inside a scroll container don't
+        // move along with its contents, and it will not be sufficient to handle transforms.
         let mut first = true;
         for metadata in metadata.iter() {
             let origin = Point2D(metadata.position.origin.x as f32,
@@ -91,6 +92,9 @@ impl RenderListener for CompositorChan {
             let size = Size2D(metadata.position.size.width as f32,
                               metadata.position.size.height as f32);
             let rect = Rect(origin, size);
+            let content_size = metadata.content_size.map(|content_size| {
+                Size2D(content_size.width as f32, content_size.height as f32)
+            });
             if first {
                 self.chan.send(CreateRootCompositorLayerIfNecessary(pipeline_id,
                                                                     metadata.id,
@@ -102,7 +106,8 @@ impl RenderListener for CompositorChan {
                     .send(CreateDescendantCompositorLayerIfNecessary(pipeline_id,
                                                                      metadata.id,
                                                                      rect,
-                                                                     metadata.scroll_policy));
+                                                                     metadata.scroll_policy,
+                                                                     content_size));
             }
 
             self.chan.send(SetUnRenderedColor(pipeline_id,
@@ -163,8 +168,12 @@ pub enum Msg {
     /// with that ID exists).
     CreateRootCompositorLayerIfNecessary(PipelineId, LayerId, Size2D<f32>, Color),
     /// Tells the compositor to create a descendant layer for a pipeline if necessary (i.e. if no
-    /// layer with that ID exists).
-    CreateDescendantCompositorLayerIfNecessary(PipelineId, LayerId, Rect<f32>, ScrollPolicy),
+    /// layer with that ID exists). Scroll containers also carry the size of their contents.
+    CreateDescendantCompositorLayerIfNecessary(PipelineId,
+                                               LayerId,
+                                               Rect<f32>,
+                                               ScrollPolicy,
+                                               Option<Size2D<f32>>),
     /// Alerts the compositor that the specified layer has changed size.
     SetLayerPageSize(PipelineId, LayerId, Size2D<f32>, Epoch),
     /// Alerts the compositor that the specified layer's clipping rect has changed.
@@ -237,7 +246,8 @@ impl CompositorTask {
                                                  memory_profiler_chan)
             }
             Headless => {
-                headless::NullCompositor::create(port,
+                headless::NullCompositor::create(opts,
+                                                 port,
                                                  constellation_chan.clone(),
                                                  time_profiler_chan,
                                                  memory_profiler_chan)
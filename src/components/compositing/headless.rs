@@ -7,36 +7,45 @@ use compositor_task::{SetIds, GetGraphicsMetadata, CreateRootCompositorLayerIfNe
 use compositor_task::{CreateDescendantCompositorLayerIfNecessary, SetLayerPageSize};
 use compositor_task::{SetLayerClipRect, Paint, ScrollFragmentPoint, LoadComplete};
 use compositor_task::{ShutdownComplete, ChangeRenderState};
+use pipeline::CompositionPipeline;
 
 use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
+use gfx::render_task::PrintMsg;
+use servo_msg::compositor_msg::{Blank, FinishedLoading};
 use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, ResizedWindowMsg, WindowSizeData};
 use servo_util::memory::MemoryProfilerChan;
 use servo_util::memory;
+use servo_util::opts::Opts;
+use servo_util::opts;
 use servo_util::time::TimeProfilerChan;
 use servo_util::time;
 
 /// Starts the compositor, which listens for messages on the specified port.
 ///
 /// This is the null compositor which doesn't draw anything to the screen.
-/// It's intended for headless testing.
+/// It's intended for headless testing, and for generating PDF documents from paginated layout.
 pub struct NullCompositor {
     /// The port on which we receive messages.
     pub port: Receiver<Msg>,
+    /// The command line options.
+    opts: Opts,
 }
 
 impl NullCompositor {
-    fn new(port: Receiver<Msg>) -> NullCompositor {
+    fn new(port: Receiver<Msg>, opts: Opts) -> NullCompositor {
         NullCompositor {
             port: port,
+            opts: opts,
         }
     }
 
-    pub fn create(port: Receiver<Msg>,
+    pub fn create(opts: Opts,
+                  port: Receiver<Msg>,
                   constellation_chan: ConstellationChan,
                   time_profiler_chan: TimeProfilerChan,
                   memory_profiler_chan: MemoryProfilerChan) {
-        let compositor = NullCompositor::new(port);
+        let compositor = NullCompositor::new(port, opts);
 
         // Tell the constellation about the initial fake size.
         {
@@ -63,6 +72,10 @@ impl NullCompositor {
     }
 
     fn handle_message(&self, constellation_chan: ConstellationChan) {
+        let mut root_pipeline = None;
+        let mut ready_state = Blank;
+        let mut load_complete = false;
+        let mut exiting = false;
         loop {
             match self.port.recv() {
                 Exit(chan) => {
@@ -81,20 +94,69 @@ impl NullCompositor {
                     chan.send(None);
                 }
 
-                SetIds(_, response_chan, _) => {
+                SetIds(frame_tree, response_chan, _) => {
+                    root_pipeline = Some(frame_tree.pipeline.clone());
                     response_chan.send(());
                 }
 
+                ChangeReadyState(new_ready_state) => {
+                    ready_state = new_ready_state;
+                }
+
+                LoadComplete(..) => {
+                    load_complete = true;
+                }
+
                 // Explicitly list ignored messages so that when we add a new one,
                 // we'll notice and think about whether it needs a response, like
                 // SetIds.
 
                 CreateRootCompositorLayerIfNecessary(..) |
                 CreateDescendantCompositorLayerIfNecessary(..) | SetLayerPageSize(..) |
-                SetLayerClipRect(..) | Paint(..) |
-                ChangeReadyState(..) | ChangeRenderState(..) | ScrollFragmentPoint(..) |
-                SetUnRenderedColor(..) | LoadComplete(..) => ()
+                SetLayerClipRect(..) | Paint(..) | ChangeRenderState(..) |
+                ScrollFragmentPoint(..) | SetUnRenderedColor(..) => ()
+            }
+
+            if load_complete && ready_state == FinishedLoading && !exiting {
+                exiting = self.print(&root_pipeline, &constellation_chan) ||
+                    self.exit_after_load(&constellation_chan);
             }
         }
     }
+
+    /// Renders the document into the output file and shuts down, if a PDF output file was
+    /// requested. Returns true if it did.
+    fn print(&self,
+             root_pipeline: &Option<CompositionPipeline>,
+             constellation_chan: &ConstellationChan)
+             -> bool {
+        let (path, root_pipeline) = match (&self.opts.output_file, root_pipeline) {
+            (&Some(ref path), &Some(ref root_pipeline)) if opts::is_pdf_output(path.as_slice()) => {
+                (path, root_pipeline)
+            }
+            _ => return false,
+        };
+
+        let path = from_str::<Path>(path.as_slice()).unwrap();
+        let (response_chan, response_port) = channel();
+        root_pipeline.render_chan.send(PrintMsg(path, response_chan));
+        response_port.recv();
+
+        debug!("shutting down the constellation after generating an output file");
+        let ConstellationChan(ref chan) = *constellation_chan;
+        chan.send(ExitMsg);
+        true
+    }
+
+    /// Shuts down once the document has loaded, if `-x` was given. Returns true if it did.
+    fn exit_after_load(&self, constellation_chan: &ConstellationChan) -> bool {
+        if !self.opts.exit_after_load {
+            return false
+        }
+
+        debug!("shutting down the constellation for exit_after_load");
+        let ConstellationChan(ref chan) = *constellation_chan;
+        chan.send(ExitMsg);
+        true
+    }
 }
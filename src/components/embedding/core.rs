@@ -57,9 +57,14 @@ pub extern "C" fn cef_run_message_loop() {
         //layout_threads: cmp::max(rt::default_sched_threads() * 3 / 4, 1),
         exit_after_load: false,
         output_file: None,
+        paginated: false,
+        dump_layout_tree: None,
         headless: false,
         hard_fail: false,
         bubble_widths_separately: false,
+        incremental_layout: true,
+        user_stylesheets: Vec::new(),
+        user_agent_stylesheet: None,
     };
     native::start(0, 0 as **u8, proc() {
        servo::run(opts);
@@ -19,16 +19,20 @@ use render_context::RenderContext;
 use text::glyph::CharIndex;
 use text::TextRun;
 
+use azure::AzFloat;
 use std::collections::Deque;
 use collections::dlist::DList;
 use collections::dlist;
 use geom::{Point2D, Rect, SideOffsets2D, Size2D};
+use geom::matrix2d::Matrix2D;
 use libc::uintptr_t;
 use servo_net::image::base::Image;
 use servo_util::geometry::Au;
+use servo_util::geometry;
 use servo_util::range::Range;
 use std::fmt;
 use std::mem;
+use std::num::Zero;
 use std::slice::Items;
 use style::computed_values::border_style;
 use sync::Arc;
@@ -259,6 +263,24 @@ impl DisplayList {
         debug!("Ending display list.");
     }
 
+    /// Draws only the text items of the display list, clipped as they would be by
+    /// `draw_into_context()`. This is used for PDF output, which draws everything else itself.
+    pub fn draw_text_into_context(&self, render_context: &mut RenderContext) {
+        for item in self.list.iter() {
+            match *item {
+                TextDisplayItemClass(_) => item.draw_into_context(render_context),
+                ClipDisplayItemClass(ref clip) => {
+                    render_context.draw_push_clip(&clip.base.bounds);
+                    clip.children.draw_text_into_context(render_context);
+                    render_context.draw_pop_clip();
+                }
+                SolidColorDisplayItemClass(_) | ImageDisplayItemClass(_) |
+                BorderDisplayItemClass(_) | LineDisplayItemClass(_) |
+                PseudoDisplayItemClass(_) => {}
+            }
+        }
+    }
+
     /// Returns a preorder iterator over the given display list.
     pub fn iter<'a>(&'a self) -> DisplayItemIterator<'a> {
         ParentDisplayItemIterator(self.list.iter())
@@ -321,6 +343,21 @@ impl DisplayList {
         result
     }
 
+    /// Moves this display list and all its subitems by the given offset. This allows the display
+    /// list of a flow that did not change to be reused after the flow moved.
+    pub fn translate(&mut self, delta: &Point2D<Au>) {
+        for item in self.list.mut_iter() {
+            {
+                let base = item.mut_base();
+                base.bounds = base.bounds.translate(delta);
+            }
+            match item.mut_sublist() {
+                None => {}
+                Some(sublist) => sublist.translate(delta),
+            }
+        }
+    }
+
     /// Sets the stacking level for this display list and all its subitems.
     fn set_stacking_level(&mut self, new_level: StackingLevel) {
         for item in self.list.mut_iter() {
@@ -411,6 +448,29 @@ pub struct TextDisplayItem {
 
     /// Text decorations in effect.
     pub text_decorations: TextDecorations,
+
+    /// The space added after each word-separating character by `text-align: justify`.
+    pub extra_word_spacing: Au,
+
+    /// Whether a hyphen is drawn after the text because the line was broken at a soft hyphen.
+    pub hyphenated: bool,
+
+    /// Whether the text is drawn right-to-left, because its bidirectional embedding level is odd.
+    pub right_to_left: bool,
+
+    /// How the glyphs are oriented, according to the writing mode and `text-orientation`.
+    pub orientation: TextOrientation,
+}
+
+/// The orientation of the glyphs of a text display item.
+#[deriving(Clone, PartialEq)]
+pub enum TextOrientation {
+    /// Glyphs advance left to right along a horizontal baseline.
+    HorizontalTextOrientation,
+    /// Glyphs are stacked top to bottom, each one upright.
+    VerticalUprightTextOrientation,
+    /// Glyphs are laid out as in horizontal text and then turned a quarter turn clockwise.
+    VerticalSidewaysTextOrientation,
 }
 
 /// Renders an image.
@@ -515,16 +575,59 @@ impl DisplayItem {
                 let font_metrics = {
                     font.borrow().metrics.clone()
                 };
-                let origin = text.base.bounds.origin;
+
+                // Vertical text is drawn in a coordinate system turned a quarter turn clockwise
+                // about the top right corner of the item, so that it can be laid out as if it
+                // were horizontal. `bounds` is the item in that coordinate system.
+                let draw_target = render_context.get_draw_target();
+                let old_transform = draw_target.get_transform();
+                let (bounds, transform) = match text.orientation {
+                    HorizontalTextOrientation => (text.base.bounds, old_transform),
+                    VerticalUprightTextOrientation | VerticalSidewaysTextOrientation => {
+                        let item_bounds = text.base.bounds;
+                        let right = item_bounds.origin.x + item_bounds.size.width;
+                        let rotation =
+                            Matrix2D::new(0.0, 1.0, -1.0, 0.0,
+                                          geometry::to_frac_px(right) as AzFloat,
+                                          geometry::to_frac_px(item_bounds.origin.y) as AzFloat);
+                        (Rect(Zero::zero(), Size2D(item_bounds.size.height,
+                                                   item_bounds.size.width)),
+                         old_transform.mul(&rotation))
+                    }
+                };
+
+                let origin = bounds.origin;
                 let baseline_origin = Point2D(origin.x, origin.y + font_metrics.ascent);
-                {
+                if text.orientation == VerticalUprightTextOrientation {
+                    // Upright glyphs are not turned, so they are drawn down the middle of the item
+                    // in the original coordinate system.
+                    let item_bounds = text.base.bounds;
+                    let column_origin = Point2D(item_bounds.origin.x +
+                                                    item_bounds.size.width.scale_by(0.5),
+                                                item_bounds.origin.y);
+                    font.borrow_mut().draw_text_into_context(render_context,
+                                                             &*text.text_run,
+                                                             &text.range,
+                                                             column_origin,
+                                                             text.text_color,
+                                                             text.extra_word_spacing,
+                                                             text.hyphenated,
+                                                             text.right_to_left,
+                                                             true);
+                }
+                draw_target.set_transform(&transform);
+                if text.orientation != VerticalUprightTextOrientation {
                     font.borrow_mut().draw_text_into_context(render_context,
                                                              &*text.text_run,
                                                              &text.range,
                                                              baseline_origin,
-                                                             text.text_color);
+                                                             text.text_color,
+                                                             text.extra_word_spacing,
+                                                             text.hyphenated,
+                                                             text.right_to_left,
+                                                             false);
                 }
-                let width = text.base.bounds.size.width;
+                let width = bounds.size.width;
                 let underline_size = font_metrics.underline_size;
                 let underline_offset = font_metrics.underline_offset;
                 let strikeout_size = font_metrics.strikeout_size;
@@ -549,6 +652,8 @@ impl DisplayItem {
                                                 Size2D(width, strikeout_size));
                     render_context.draw_solid_color(&strikeout_bounds, *line_through_color);
                 }
+
+                draw_target.set_transform(&old_transform);
             }
 
             ImageDisplayItemClass(ref image_item) => {
@@ -161,11 +161,15 @@ impl FontGroup {
         }
     }
 
-    pub fn create_textrun(&self, text: String, decoration: text_decoration::T) -> TextRun {
+    pub fn create_textrun(&self,
+                          text: String,
+                          decoration: text_decoration::T,
+                          soft_hyphens: Vec<CharIndex>)
+                          -> TextRun {
         assert!(self.fonts.len() > 0);
 
         // TODO(Issue #177): Actually fall back through the FontGroup when a font is unsuitable.
-        TextRun::new(&mut *self.fonts.get(0).borrow_mut(), text.clone(), decoration)
+        TextRun::new(&mut *self.fonts.get(0).borrow_mut(), text.clone(), decoration, soft_hyphens)
     }
 }
 
@@ -320,7 +324,11 @@ impl Font {
                               run: &Box<TextRun>,
                               range: &Range<CharIndex>,
                               baseline_origin: Point2D<Au>,
-                              color: Color) {
+                              color: Color,
+                              extra_word_spacing: Au,
+                              hyphenated: bool,
+                              right_to_left: bool,
+                              upright: bool) {
         use libc::types::common::c99::{uint16_t, uint32_t};
         use azure::{struct__AzDrawOptions,
                     struct__AzGlyph,
@@ -339,24 +347,68 @@ impl Font {
             fields: 0x0200 as uint16_t
         };
 
-        let mut origin = baseline_origin.clone();
-        let mut azglyphs = vec!();
-        azglyphs.reserve(range.length().to_uint());
-
+        // Gather the glyphs in logical order, along with their offsets and advances.
+        let mut glyph_infos = vec!();
         for (glyphs, _offset, slice_range) in run.iter_slices_for_range(range) {
+            // Justified text is spread out by widening the spaces between words.
+            let extra_advance = if glyphs.is_whitespace() {
+                extra_word_spacing
+            } else {
+                Au(0)
+            };
             for (_i, glyph) in glyphs.iter_glyphs_for_char_range(&slice_range) {
-                let glyph_advance = glyph.advance();
-                let glyph_offset = glyph.offset().unwrap_or(Zero::zero());
-
-                let azglyph = struct__AzGlyph {
-                    mIndex: glyph.id() as uint32_t,
-                    mPosition: struct__AzPoint {
-                        x: (origin.x + glyph_offset.x).to_nearest_px() as AzFloat,
-                        y: (origin.y + glyph_offset.y).to_nearest_px() as AzFloat
-                    }
-                };
-                origin = Point2D(origin.x + glyph_advance, origin.y);
-                azglyphs.push(azglyph)
+                glyph_infos.push((glyph.id(),
+                                  glyph.offset().unwrap_or(Zero::zero()),
+                                  glyph.advance() + extra_advance))
+            }
+        }
+
+        // A line broken at a soft hyphen ends with a visible hyphen.
+        if hyphenated {
+            for glyphs in run.hyphen_glyphs.iter() {
+                let hyphen_range = Range::new(CharIndex(0), CharIndex(1));
+                for (_i, glyph) in glyphs.iter_glyphs_for_char_range(&hyphen_range) {
+                    glyph_infos.push((glyph.id(),
+                                      glyph.offset().unwrap_or(Zero::zero()),
+                                      glyph.advance()))
+                }
+            }
+        }
+
+        // The shaper always lays glyphs out left-to-right, so right-to-left text is drawn by
+        // reversing them.
+        //
+        // FIXME: This places marks on the wrong side of their bases. We should shape
+        // right-to-left runs in the right direction instead.
+        if right_to_left {
+            glyph_infos.reverse()
+        }
+
+        // Upright glyphs in vertical text are stacked top to bottom, each centered on the
+        // vertical line through `baseline_origin`.
+        //
+        // FIXME: This advances by the horizontal advance of each glyph, to match the way text
+        // runs are measured. We should use the vertical metrics of the font instead.
+        let mut origin = baseline_origin.clone();
+        let mut azglyphs = vec!();
+        azglyphs.reserve(glyph_infos.len());
+        for &(glyph_id, glyph_offset, glyph_advance) in glyph_infos.iter() {
+            let glyph_origin = if upright {
+                Point2D(origin.x - glyph_advance.scale_by(0.5), origin.y + self.metrics.ascent)
+            } else {
+                origin
+            };
+            azglyphs.push(struct__AzGlyph {
+                mIndex: glyph_id as uint32_t,
+                mPosition: struct__AzPoint {
+                    x: (glyph_origin.x + glyph_offset.x).to_nearest_px() as AzFloat,
+                    y: (glyph_origin.y + glyph_offset.y).to_nearest_px() as AzFloat
+                }
+            });
+            origin = if upright {
+                Point2D(origin.x, origin.y + glyph_advance)
+            } else {
+                Point2D(origin.x + glyph_advance, origin.y)
             };
         }
 
@@ -380,7 +432,6 @@ impl Font {
     }
 
     pub fn measure_text(&self, run: &TextRun, range: &Range<CharIndex>) -> RunMetrics {
-        // TODO(Issue #199): alter advance direction for RTL
         // TODO(Issue #98): using inter-char and inter-word spacing settings  when measuring text
         let mut advance = Au(0);
         for (glyphs, _offset, slice_range) in run.iter_slices_for_range(range) {
@@ -16,6 +16,7 @@ extern crate log;
 extern crate debug;
 extern crate azure;
 extern crate collections;
+extern crate flate;
 extern crate geom;
 extern crate layers;
 extern crate libc;
@@ -61,6 +62,7 @@ pub mod color;
 #[path="display_list/mod.rs"]
 pub mod display_list;
 pub mod render_task;
+pub mod pdf;
 
 // Fonts
 pub mod font;
@@ -0,0 +1,512 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A minimal PDF writer for paginated output. Azure has no PDF backend, so display lists are
+//! translated into PDF drawing operators here: backgrounds, borders and images stay vector
+//! graphics. Text is rendered into an image over the rest of the page, since fonts are not
+//! embedded yet.
+
+use display_list::{BorderDisplayItemClass, ClipDisplayItemClass, DisplayList};
+use display_list::{ImageDisplayItemClass, LineDisplayItemClass, PseudoDisplayItemClass};
+use display_list::{SolidColorDisplayItemClass, TextDisplayItemClass};
+
+use azure::azure_hl::Color;
+use flate;
+use geom::point::Point2D;
+use geom::rect::Rect;
+use geom::side_offsets::SideOffsets2D;
+use geom::size::Size2D;
+use png;
+use servo_net::image::base::Image;
+use servo_util::geometry::Au;
+use servo_util::geometry;
+use std::io::{File, IoError, IoResult, MemWriter, OtherIoError};
+use style::computed_values::border_style;
+use sync::Arc;
+
+/// PDF points per CSS pixel: 72 points and 96 pixels per inch.
+static POINTS_PER_PX: f64 = 0.75;
+
+/// An image drawn on a page of a PDF document.
+pub struct PdfImage {
+    /// The size of the image in pixels.
+    pub size: Size2D<uint>,
+    /// The pixels of the image as 8-bit RGB triples, row by row from the top.
+    pub pixels: Vec<u8>,
+    /// The 8-bit alpha value of each pixel, or `None` if the image is opaque.
+    pub alpha: Option<Vec<u8>>,
+}
+
+/// One page of a PDF document.
+pub struct PdfPage {
+    /// The size of the page in points.
+    pub size: Size2D<f64>,
+    /// The content stream that draws the page. It refers to `images` as `/Im0`, `/Im1` and so on,
+    /// and to graphics states that set the fill opacities in `fill_alphas` as `/GS0`, `/GS1` and
+    /// so on.
+    pub contents: String,
+    pub images: Vec<PdfImage>,
+    pub fill_alphas: Vec<f32>,
+}
+
+impl PdfPage {
+    /// Creates an empty page of the given size. Drawing operators are then given in pixels, with
+    /// the origin at the top left corner of the page and the y axis pointing down.
+    pub fn new(size: Size2D<Au>) -> PdfPage {
+        let size = Size2D(geometry::to_pt(size.width), geometry::to_pt(size.height));
+        PdfPage {
+            size: size,
+            contents: format!("{} 0 0 {} 0 {} cm\n", number(POINTS_PER_PX),
+                              number(-POINTS_PER_PX), number(size.height)),
+            images: vec!(),
+            fill_alphas: vec!(),
+        }
+    }
+
+    /// Draws a display list, whose coordinates are offset so that `origin` is the top left corner
+    /// of the page.
+    pub fn draw_display_list(&mut self, display_list: &DisplayList, origin: Point2D<Au>) {
+        self.contents.push_str(format!("q 1 0 0 1 {} {} cm\n",
+                                       px(-origin.x), px(-origin.y)).as_slice());
+        self.draw_items(display_list);
+        self.contents.push_str("Q\n");
+    }
+
+    /// Draws an image over the whole page.
+    pub fn draw_image_over_page(&mut self, image: PdfImage) {
+        let (width, height) = (self.size.width / POINTS_PER_PX, self.size.height / POINTS_PER_PX);
+        let name = self.add_image(image);
+        self.draw_named_image(name.as_slice(), 0., 0., width, height);
+    }
+
+    /// Fills `bounds` with `color`.
+    pub fn fill_rect(&mut self, bounds: &Rect<Au>, color: Color) {
+        if !self.begin_fill(color) {
+            return
+        }
+        self.contents.push_str(format!("{} {} {} {} re f\nQ\n",
+                                       px(bounds.origin.x), px(bounds.origin.y),
+                                       px(bounds.size.width), px(bounds.size.height)).as_slice());
+    }
+
+    fn draw_items(&mut self, display_list: &DisplayList) {
+        for item in display_list.iter() {
+            match *item {
+                SolidColorDisplayItemClass(ref solid_color) => {
+                    self.fill_rect(&solid_color.base.bounds, solid_color.color)
+                }
+                ClipDisplayItemClass(ref clip) => {
+                    let bounds = clip.base.bounds;
+                    self.contents.push_str(format!("q {} {} {} {} re W n\n",
+                                                   px(bounds.origin.x), px(bounds.origin.y),
+                                                   px(bounds.size.width),
+                                                   px(bounds.size.height)).as_slice());
+                    self.draw_items(&clip.children);
+                    self.contents.push_str("Q\n");
+                }
+                ImageDisplayItemClass(ref image_item) => {
+                    let name = self.add_image(pdf_image(&image_item.image));
+                    let bounds = image_item.base.bounds;
+                    let stretch_size = image_item.stretch_size;
+                    if stretch_size.width <= Au(0) || stretch_size.height <= Au(0) {
+                        continue
+                    }
+                    let mut y_offset = Au(0);
+                    while y_offset < bounds.size.height {
+                        let mut x_offset = Au(0);
+                        while x_offset < bounds.size.width {
+                            self.draw_named_image(name.as_slice(),
+                                                  to_px(bounds.origin.x + x_offset),
+                                                  to_px(bounds.origin.y + y_offset),
+                                                  to_px(stretch_size.width),
+                                                  to_px(stretch_size.height));
+                            x_offset = x_offset + stretch_size.width;
+                        }
+                        y_offset = y_offset + stretch_size.height;
+                    }
+                }
+                BorderDisplayItemClass(ref border) => {
+                    self.draw_border(&border.base.bounds, border.border, border.color,
+                                     border.style)
+                }
+                LineDisplayItemClass(ref line) => {
+                    if line.style != border_style::none && line.style != border_style::hidden {
+                        self.fill_rect(&line.base.bounds, line.color)
+                    }
+                }
+                // Text is rendered separately; see `DisplayList::draw_text_into_context`.
+                TextDisplayItemClass(_) | PseudoDisplayItemClass(_) => {}
+            }
+        }
+    }
+
+    /// Draws each side of a border as a trapezoid, or as a dashed line for dotted and dashed
+    /// sides. Double, groove, ridge, inset and outset sides are drawn solid for now.
+    fn draw_border(&mut self,
+                   bounds: &Rect<Au>,
+                   border: SideOffsets2D<Au>,
+                   color: SideOffsets2D<Color>,
+                   style: SideOffsets2D<border_style::T>) {
+        let (left, top) = (to_px(bounds.origin.x), to_px(bounds.origin.y));
+        let (right, bottom) = (left + to_px(bounds.size.width), top + to_px(bounds.size.height));
+        let (top_width, right_width) = (to_px(border.top), to_px(border.right));
+        let (bottom_width, left_width) = (to_px(border.bottom), to_px(border.left));
+        let sides = [
+            (style.top, color.top, top_width,
+             [(left, top), (right, top), (right - right_width, top + top_width),
+              (left + left_width, top + top_width)],
+             ((left, top + top_width * 0.5), (right, top + top_width * 0.5))),
+            (style.right, color.right, right_width,
+             [(right, top), (right, bottom), (right - right_width, bottom - bottom_width),
+              (right - right_width, top + top_width)],
+             ((right - right_width * 0.5, top), (right - right_width * 0.5, bottom))),
+            (style.bottom, color.bottom, bottom_width,
+             [(left, bottom), (left + left_width, bottom - bottom_width),
+              (right - right_width, bottom - bottom_width), (right, bottom)],
+             ((right, bottom - bottom_width * 0.5), (left, bottom - bottom_width * 0.5))),
+            (style.left, color.left, left_width,
+             [(left, top), (left + left_width, top + top_width),
+              (left + left_width, bottom - bottom_width), (left, bottom)],
+             ((left + left_width * 0.5, bottom), (left + left_width * 0.5, top))),
+        ];
+        for &(side_style, color, width, ref trapezoid, (start, end)) in sides.iter() {
+            let dash_size = match side_style {
+                border_style::none | border_style::hidden => continue,
+                border_style::dotted => 1.,
+                border_style::dashed => 3.,
+                _ => 0.,
+            };
+            if width <= 0. || !self.begin_fill(color) {
+                continue
+            }
+            if dash_size == 0. {
+                let (start_x, start_y) = trapezoid[0];
+                let mut path = format!("{} {} m", number(start_x), number(start_y));
+                for &(x, y) in trapezoid.slice_from(1).iter() {
+                    path.push_str(format!(" {} {} l", number(x), number(y)).as_slice());
+                }
+                self.contents.push_str(format!("{} h f\nQ\n", path).as_slice());
+            } else {
+                let (start_x, start_y) = start;
+                let (end_x, end_y) = end;
+                let dash = number(width * dash_size);
+                self.contents.push_str(format!("{} {} {} RG {} w [{} {}] 0 d ",
+                                               color.r, color.g, color.b, number(width),
+                                               dash, dash).as_slice());
+                self.contents.push_str(format!("{} {} m {} {} l S\nQ\n",
+                                               number(start_x), number(start_y),
+                                               number(end_x), number(end_y)).as_slice());
+            }
+        }
+    }
+
+    /// Saves the graphics state and sets the fill color and opacity. Returns false, without
+    /// saving anything, if the color is transparent.
+    fn begin_fill(&mut self, color: Color) -> bool {
+        if color.a <= 0. {
+            return false
+        }
+        self.contents.push_str(format!("q {} {} {} rg", color.r, color.g, color.b).as_slice());
+        if color.a < 1. {
+            let index = match self.fill_alphas.iter().position(|alpha| *alpha == color.a) {
+                Some(index) => index,
+                None => {
+                    self.fill_alphas.push(color.a);
+                    self.fill_alphas.len() - 1
+                }
+            };
+            // The opacity applies to strokes too.
+            self.contents.push_str(format!(" /GS{} gs", index).as_slice());
+        }
+        self.contents.push_str("\n");
+        true
+    }
+
+    fn add_image(&mut self, image: PdfImage) -> String {
+        self.images.push(image);
+        format!("Im{}", self.images.len() - 1)
+    }
+
+    /// Draws the named image stretched over the given rectangle. Images are drawn into the unit
+    /// square with the y axis pointing up, hence the flip.
+    fn draw_named_image(&mut self, name: &str, x: f64, y: f64, width: f64, height: f64) {
+        self.contents.push_str(format!("q {} 0 0 {} {} {} cm /{} Do Q\n",
+                                       number(width), number(-height), number(x),
+                                       number(y + height), name).as_slice());
+    }
+}
+
+fn to_px(au: Au) -> f64 {
+    geometry::to_frac_px(au)
+}
+
+fn px(au: Au) -> String {
+    number(to_px(au))
+}
+
+/// Formats a number for a content stream, which does not allow exponents.
+fn number(value: f64) -> String {
+    format!("{:.3f}", value)
+}
+
+/// Converts a decoded image, whose color channels are in BGRA order, to a PDF image.
+fn pdf_image(image: &Arc<Box<Image>>) -> PdfImage {
+    let pixel_count = (image.width * image.height) as uint;
+    let mut pixels = Vec::with_capacity(pixel_count * 3);
+    let mut alpha = Vec::with_capacity(pixel_count);
+    match image.color_type {
+        png::RGBA8 => {
+            for pixel in image.pixels.as_slice().chunks(4) {
+                pixels.push_all(&[pixel[2], pixel[1], pixel[0]]);
+                alpha.push(pixel[3]);
+            }
+        }
+        png::K8 => {
+            for &gray in image.pixels.iter() {
+                pixels.push_all(&[gray, gray, gray]);
+                alpha.push(0xff);
+            }
+        }
+        png::KA8 => {
+            for pixel in image.pixels.as_slice().chunks(2) {
+                pixels.push_all(&[pixel[0], pixel[0], pixel[0]]);
+                alpha.push(pixel[1]);
+            }
+        }
+        _ => fail!("color type not supported"),
+    }
+    let opaque = alpha.iter().all(|&alpha| alpha == 0xff);
+    PdfImage {
+        size: Size2D(image.width as uint, image.height as uint),
+        pixels: pixels,
+        alpha: if opaque { None } else { Some(alpha) },
+    }
+}
+
+/// Writes `pages` to a PDF document at `path`.
+pub fn write_pdf(path: &Path, pages: &[PdfPage]) -> IoResult<()> {
+    let mut writer = PdfWriter {
+        out: MemWriter::new(),
+        offsets: vec!(),
+    };
+    try!(writer.out.write_str("%PDF-1.4\n"));
+
+    // Objects 1 and 2 are the catalog and the page tree. Every page then takes an object for
+    // itself, one for its content stream, and one for each image and each image mask.
+    let mut page_ids = vec!();
+    let mut next_id = 3;
+    for page in pages.iter() {
+        page_ids.push(next_id);
+        next_id += 2;
+        for image in page.images.iter() {
+            next_id += if image.alpha.is_some() { 2 } else { 1 };
+        }
+    }
+
+    try!(writer.begin_object(1));
+    try!(writer.out.write_str("<< /Type /Catalog /Pages 2 0 R >>\n"));
+    try!(writer.end_object());
+
+    try!(writer.begin_object(2));
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    try!(write!(&mut writer.out, "<< /Type /Pages /Kids [{}] /Count {} >>\n",
+                kids.connect(" "), pages.len()));
+    try!(writer.end_object());
+
+    for (page, &page_id) in pages.iter().zip(page_ids.iter()) {
+        // The images follow the content stream, each followed by its mask if it has one.
+        let mut image_ids = vec!();
+        let mut next_id = page_id + 2;
+        for image in page.images.iter() {
+            image_ids.push(next_id);
+            next_id += if image.alpha.is_some() { 2 } else { 1 };
+        }
+        let x_objects: Vec<String> = image_ids.iter().enumerate().map(|(index, id)| {
+            format!("/Im{} {} 0 R", index, id)
+        }).collect();
+        let graphics_states: Vec<String> = page.fill_alphas.iter().enumerate().map(|(i, alpha)| {
+            format!("/GS{} << /ca {} /CA {} >>", i, alpha, alpha)
+        }).collect();
+
+        try!(writer.begin_object(page_id));
+        try!(write!(&mut writer.out,
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                     /Resources << /XObject << {} >> /ExtGState << {} >> >> /Contents {} 0 R >>\n",
+                    page.size.width, page.size.height, x_objects.connect(" "),
+                    graphics_states.connect(" "), page_id + 1));
+        try!(writer.end_object());
+
+        try!(writer.begin_object(page_id + 1));
+        try!(writer.write_stream("", page.contents.as_bytes()));
+        try!(writer.end_object());
+
+        for (image, &image_id) in page.images.iter().zip(image_ids.iter()) {
+            let mask = match image.alpha {
+                Some(_) => format!(" /SMask {} 0 R", image_id + 1),
+                None => String::new(),
+            };
+            let dictionary = format!("/Type /XObject /Subtype /Image /Width {} /Height {} \
+                                      /ColorSpace /DeviceRGB /BitsPerComponent 8 \
+                                      /Filter /FlateDecode{}",
+                                     image.size.width, image.size.height, mask);
+            try!(writer.begin_object(image_id));
+            try!(writer.write_stream(dictionary.as_slice(),
+                                     try!(compress(image.pixels.as_slice())).as_slice()));
+            try!(writer.end_object());
+
+            match image.alpha {
+                Some(ref alpha) => {
+                    let dictionary = format!("/Type /XObject /Subtype /Image /Width {} \
+                                              /Height {} /ColorSpace /DeviceGray \
+                                              /BitsPerComponent 8 /Filter /FlateDecode",
+                                             image.size.width, image.size.height);
+                    try!(writer.begin_object(image_id + 1));
+                    try!(writer.write_stream(dictionary.as_slice(),
+                                             try!(compress(alpha.as_slice())).as_slice()));
+                    try!(writer.end_object());
+                }
+                None => {}
+            }
+        }
+    }
+
+    // The cross-reference table, which gives the byte offset of every object.
+    let xref_offset = writer.out.get_ref().len();
+    try!(write!(&mut writer.out, "xref\n0 {}\n", writer.offsets.len() + 1));
+    try!(writer.out.write_str("0000000000 65535 f \n"));
+    for offset in writer.offsets.iter() {
+        try!(write!(&mut writer.out, "{:010u} 00000 n \n", *offset));
+    }
+    try!(write!(&mut writer.out, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                writer.offsets.len() + 1, xref_offset));
+
+    let mut file = try!(File::create(path));
+    file.write(writer.out.get_ref())
+}
+
+fn compress(data: &[u8]) -> IoResult<Vec<u8>> {
+    match flate::deflate_bytes_zlib(data) {
+        Some(compressed) => Ok(Vec::from_slice(compressed.as_slice())),
+        None => {
+            Err(IoError {
+                kind: OtherIoError,
+                desc: "failed to compress page image",
+                detail: None,
+            })
+        }
+    }
+}
+
+struct PdfWriter {
+    out: MemWriter,
+    /// The byte offset of each object written so far. Object IDs start at 1.
+    offsets: Vec<uint>,
+}
+
+impl PdfWriter {
+    fn begin_object(&mut self, id: uint) -> IoResult<()> {
+        assert!(id == self.offsets.len() + 1, "PDF objects must be written in order");
+        self.offsets.push(self.out.get_ref().len());
+        write!(&mut self.out, "{} 0 obj\n", id)
+    }
+
+    fn end_object(&mut self) -> IoResult<()> {
+        self.out.write_str("endobj\n")
+    }
+
+    /// Writes a stream object's dictionary, with the entries in `dictionary` and the length of
+    /// `data`, followed by `data`.
+    fn write_stream(&mut self, dictionary: &str, data: &[u8]) -> IoResult<()> {
+        try!(write!(&mut self.out, "<< {} /Length {} >>\nstream\n", dictionary, data.len()));
+        try!(self.out.write(data));
+        self.out.write_str("\nendstream\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PdfImage, PdfPage, write_pdf};
+
+    use azure::azure_hl::Color;
+    use geom::point::Point2D;
+    use geom::rect::Rect;
+    use geom::size::Size2D;
+    use servo_util::geometry::Au;
+    use std::io::{File, TempDir};
+    use std::str;
+
+    fn position(data: &[u8], needle: &str) -> Option<uint> {
+        data.windows(needle.len()).position(|window| window == needle.as_bytes())
+    }
+
+    fn number_at(data: &[u8], start: uint, length: uint) -> uint {
+        let digits = str::from_utf8(data.slice(start, start + length)).unwrap();
+        from_str(digits.trim()).unwrap()
+    }
+
+    #[test]
+    fn test_fill_rect() {
+        let mut page = PdfPage::new(Size2D(Au::from_px(40), Au::from_px(20)));
+        assert!(page.size.width == 30. && page.size.height == 15.);
+        let bounds = Rect(Point2D(Au::from_px(2), Au::from_px(4)),
+                          Size2D(Au::from_px(10), Au::from_px(6)));
+        page.fill_rect(&bounds, Color::new(1., 0., 0., 1.));
+        page.fill_rect(&bounds, Color::new(0., 0., 1., 0.5));
+        page.fill_rect(&bounds, Color::new(0., 0., 1., 0.));
+        assert!(page.contents.as_slice().starts_with("0.750 0 0 -0.750 0 15.000 cm\n"));
+        let contents = page.contents.as_slice();
+        assert!(contents.contains("q 1 0 0 rg\n2.000 4.000 10.000 6.000 re f\nQ\n"));
+        assert!(contents.contains("q 0 0 1 rg /GS0 gs\n"));
+        // The transparent fill is skipped.
+        assert_eq!(contents.split_str("re f").count(), 3);
+        assert_eq!(page.fill_alphas, vec!(0.5));
+    }
+
+    #[test]
+    fn test_write_pdf() {
+        let image = |width: uint, height: uint, alpha: bool| {
+            PdfImage {
+                size: Size2D(width, height),
+                pixels: Vec::from_elem(width * height * 3, 0xff),
+                alpha: if alpha { Some(Vec::from_elem(width * height, 0x80)) } else { None },
+            }
+        };
+        let mut first_page = PdfPage::new(Size2D(Au::from_px(4), Au::from_px(4)));
+        first_page.draw_image_over_page(image(6, 4, true));
+        let mut second_page = PdfPage::new(Size2D(Au::from_px(4), Au::from_px(4)));
+        second_page.draw_image_over_page(image(4, 6, false));
+        let pages = [first_page, second_page];
+
+        let dir = TempDir::new("pdf").unwrap();
+        let path = dir.path().join("out.pdf");
+        write_pdf(&path, pages.as_slice()).unwrap();
+        let data = File::open(&path).read_to_end().unwrap();
+        let data = data.as_slice();
+
+        assert!(data.starts_with(b"%PDF-1.4\n"));
+        assert!(data.ends_with(b"%%EOF\n"));
+        // The first page has an image and its mask, and the second page only an image.
+        assert!(position(data, "/Type /Pages /Kids [3 0 R 7 0 R] /Count 2").is_some());
+        assert!(position(data, "/XObject << /Im0 5 0 R >>").is_some());
+        assert!(position(data, "/XObject << /Im0 9 0 R >>").is_some());
+        assert!(position(data, "/Width 6 /Height 4").is_some());
+        assert!(position(data, "/SMask 6 0 R").is_some());
+        assert!(position(data, "/Width 4 /Height 6").is_some());
+        assert!(position(data, "/Im0 Do").is_some());
+
+        // `startxref` gives the offset of the cross-reference table, which gives the offset of
+        // each of the nine objects.
+        let startxref = position(data, "startxref\n").unwrap() + "startxref\n".len();
+        let xref_offset = number_at(data, startxref, data.len() - startxref - "\n%%EOF\n".len());
+        assert!(data.slice_from(xref_offset).starts_with(b"xref\n0 10\n0000000000 65535 f \n"));
+        let first_entry = xref_offset + "xref\n0 10\n".len() + 20;
+        for id in range(1u, 10) {
+            let entry = first_entry + (id - 1) * 20;
+            assert!(data.slice(entry + 10, entry + 20) == b" 00000 n \n");
+            let offset = number_at(data, entry, 10);
+            let header = format!("{} 0 obj\n", id);
+            assert!(data.slice_from(offset).starts_with(header.as_bytes()));
+        }
+    }
+}
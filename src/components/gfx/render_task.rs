@@ -8,11 +8,13 @@ use buffer_map::BufferMap;
 use display_list::optimizer::DisplayListOptimizer;
 use display_list::DisplayList;
 use font_context::{FontContext, FontContextInfo};
+use pdf::{PdfImage, PdfPage, write_pdf};
 use render_context::RenderContext;
 
 use azure::azure_hl::{B8G8R8A8, Color, DrawTarget, StolenGLResources};
 use azure::AzFloat;
 use geom::matrix2d::Matrix2D;
+use geom::point::Point2D;
 use geom::rect::Rect;
 use geom::size::Size2D;
 use layers::platform::surface::{NativePaintingGraphicsContext, NativeSurface};
@@ -27,6 +29,7 @@ use servo_msg::compositor_msg::{LayerMetadata, RenderListener, RenderingRenderSt
 use servo_msg::constellation_msg::{ConstellationChan, Failure, FailureMsg, PipelineId};
 use servo_msg::constellation_msg::{RendererReadyMsg};
 use servo_msg::platform::surface::NativeSurfaceAzureMethods;
+use servo_util::geometry::Au;
 use servo_util::geometry;
 use servo_util::opts::Opts;
 use servo_util::smallvec::{SmallVec, SmallVec1};
@@ -47,14 +50,24 @@ pub struct RenderLayer {
     pub background_color: Color,
     /// The scrolling policy of this layer.
     pub scroll_policy: ScrollPolicy,
+    /// The size of the contents of the layer in pixels, if it is a scroll container. The layer
+    /// shows the part of the contents that the compositor has scrolled it to.
+    pub content_size: Option<Size2D<uint>>,
 }
 
 pub enum Msg {
     RenderMsg(SmallVec1<RenderLayer>),
+    /// Renders the given tiles of a layer at the given scale. The tiles of a scroll container
+    /// layer cover its contents, which start at the top left corner of the layer.
     ReRenderMsg(Vec<BufferRequest>, f32, LayerId, Epoch),
     UnusedBufferMsg(Vec<Box<LayerBuffer>>),
     PaintPermissionGranted,
     PaintPermissionRevoked,
+    /// Sets the pages that the document was split into, in the coordinates of the first layer, when
+    /// layout is paginated.
+    SetPagesMsg(Vec<Rect<Au>>),
+    /// Renders every page into a PDF document at the given path, then replies on the channel.
+    PrintMsg(Path, Sender<()>),
     ExitMsg(Option<Sender<()>>),
 }
 
@@ -113,8 +126,14 @@ pub struct RenderTask<C> {
 
     /// A data structure to store unused LayerBuffers
     buffer_map: BufferMap<Box<LayerBuffer>>,
+
+    /// The pages that the document was split into, if layout is paginated.
+    pages: Vec<Rect<Au>>,
 }
 
+/// The resolution at which text is rendered for PDF output, in device pixels per px.
+static PRINT_DEVICE_PIXELS_PER_PX: f32 = 2.0;
+
 // If we implement this as a function, we get borrowck errors from borrowing
 // the whole RenderTask struct.
 macro_rules! native_graphics_context(
@@ -134,6 +153,7 @@ fn initialize_layers<C:RenderListener>(
             position: render_layer.position,
             background_color: render_layer.background_color,
             scroll_policy: render_layer.scroll_policy,
+            content_size: render_layer.content_size,
         }
     }).collect();
     compositor.initialize_layers_for_pipeline(pipeline_id, metadata, epoch);
@@ -193,6 +213,7 @@ impl<C:RenderListener + Send> RenderTask<C> {
                     paint_permission: false,
                     epoch: Epoch(0),
                     buffer_map: BufferMap::new(10000000),
+                    pages: vec!(),
                 };
 
                 render_task.start();
@@ -259,6 +280,13 @@ impl<C:RenderListener + Send> RenderTask<C> {
                 PaintPermissionRevoked => {
                     self.paint_permission = false;
                 }
+                SetPagesMsg(pages) => {
+                    self.pages = pages;
+                }
+                PrintMsg(path, response_chan) => {
+                    self.print(&path);
+                    response_chan.send(());
+                }
                 ExitMsg(response_ch) => {
                     debug!("render_task: exitmsg response send");
                     response_ch.map(|ch| ch.send(()));
@@ -268,11 +296,107 @@ impl<C:RenderListener + Send> RenderTask<C> {
         }
     }
 
+    /// Writes each page of a paginated document to a PDF document at `path`.
+    fn print(&mut self, path: &Path) {
+        let mut pdf_pages = vec!();
+        for page in self.pages.iter() {
+            let mut pdf_page = PdfPage::new(page.size);
+
+            // FIXME: Fonts are not embedded in the PDF yet, so text is rendered into an image that
+            // is drawn over everything else on the page. Content painted over text therefore ends
+            // up under it.
+            let width = (geometry::to_frac_px(page.size.width) *
+                         PRINT_DEVICE_PIXELS_PER_PX as f64).ceil() as uint;
+            let height = (geometry::to_frac_px(page.size.height) *
+                          PRINT_DEVICE_PIXELS_PER_PX as f64).ceil() as uint;
+            let draw_target = DrawTarget::new(self.opts.render_backend,
+                                              Size2D(width as i32, height as i32),
+                                              B8G8R8A8);
+
+            for (index, render_layer) in self.render_layers.iter().enumerate() {
+                let layer_origin = Point2D(Au::from_px(render_layer.position.origin.x as int),
+                                           Au::from_px(render_layer.position.origin.y as int));
+                let page_rect = Rect(page.origin - layer_origin, page.size);
+                let optimizer = DisplayListOptimizer::new(render_layer.display_list.clone(),
+                                                          page_rect);
+                let display_list = optimizer.optimize();
+
+                // The background of the first layer is the background of the canvas, which
+                // covers the whole page.
+                if index == 0 {
+                    pdf_page.fill_rect(&Rect(Point2D(Au(0), Au(0)), page.size),
+                                       render_layer.background_color);
+                }
+                pdf_page.draw_display_list(&display_list, page_rect.origin);
+
+                let page_origin_px = Point2D(geometry::to_frac_px(page_rect.origin.x) as f32,
+                                             geometry::to_frac_px(page_rect.origin.y) as f32);
+                let page_size_px = Size2D(geometry::to_frac_px(page_rect.size.width) as f32,
+                                          geometry::to_frac_px(page_rect.size.height) as f32);
+                let mut ctx = RenderContext {
+                    draw_target: &draw_target,
+                    font_ctx: &mut self.font_ctx,
+                    opts: &self.opts,
+                    page_rect: Rect(page_origin_px, page_size_px),
+                    screen_rect: Rect(Point2D(0u, 0u), Size2D(width, height)),
+                };
+
+                let matrix: Matrix2D<AzFloat> = Matrix2D::identity();
+                let matrix = matrix.scale(PRINT_DEVICE_PIXELS_PER_PX as AzFloat,
+                                          PRINT_DEVICE_PIXELS_PER_PX as AzFloat);
+                let matrix = matrix.translate(-page_origin_px.x as AzFloat,
+                                              -page_origin_px.y as AzFloat);
+                ctx.draw_target.set_transform(&matrix);
+                if index == 0 {
+                    ctx.clear();
+                }
+
+                display_list.draw_text_into_context(&mut ctx);
+                ctx.draw_target.flush();
+            }
+
+            // Azure gives us premultiplied BGRA, and PDF images are not premultiplied.
+            let mut pixels = Vec::with_capacity(width * height * 3);
+            let mut alpha = Vec::with_capacity(width * height);
+            draw_target.snapshot().get_data_surface().with_data(|data| {
+                for pixel in data.chunks(4) {
+                    let unpremultiply = |value: u8| {
+                        if pixel[3] == 0 {
+                            0
+                        } else {
+                            (value as uint * 0xff / pixel[3] as uint) as u8
+                        }
+                    };
+                    pixels.push(unpremultiply(pixel[2]));
+                    pixels.push(unpremultiply(pixel[1]));
+                    pixels.push(unpremultiply(pixel[0]));
+                    alpha.push(pixel[3]);
+                }
+            });
+            if alpha.iter().any(|&alpha| alpha != 0) {
+                pdf_page.draw_image_over_page(PdfImage {
+                    size: Size2D(width, height),
+                    pixels: pixels,
+                    alpha: Some(alpha),
+                });
+            }
+            pdf_pages.push(pdf_page);
+        }
+
+        match write_pdf(path, pdf_pages.as_slice()) {
+            Ok(()) => {}
+            Err(error) => error!("failed to write {}: {}", path.display(), error),
+        }
+    }
+
     /// Renders one layer and sends the tiles back to the layer.
     ///
     /// FIXME(pcwalton): We will probably want to eventually send all layers belonging to a page in
     /// one transaction, to avoid the user seeing inconsistent states.
-    fn render(&mut self, tiles: Vec<BufferRequest>, scale: f32, layer_id: LayerId) {
+    fn render(&mut self,
+              tiles: Vec<BufferRequest>,
+              scale: f32,
+              layer_id: LayerId) {
         time::profile(time::RenderingCategory, self.time_profiler_chan.clone(), || {
             // FIXME: Try not to create a new array here.
             let mut new_buffers = vec!();
@@ -285,10 +409,16 @@ impl<C:RenderListener + Send> RenderTask<C> {
 
             self.compositor.set_render_state(RenderingRenderState);
 
+            // The tiles are in the coordinate system of the layer, and the display list is in that
+            // of the page.
+            let layer_origin = Point2D(render_layer.position.origin.x as f32,
+                                       render_layer.position.origin.y as f32);
+
             // Divide up the layer into tiles.
             for tile in tiles.iter() {
                 // Optimize the display list for this tile.
-                let page_rect_au = geometry::f32_rect_to_au_rect(tile.page_rect);
+                let page_rect_au =
+                    geometry::f32_rect_to_au_rect(tile.page_rect.translate(&layer_origin));
                 let optimizer = DisplayListOptimizer::new(render_layer.display_list.clone(),
                                                           page_rect_au);
                 let display_list = optimizer.optimize();
@@ -329,8 +459,8 @@ impl<C:RenderListener + Send> RenderTask<C> {
                     let matrix = matrix.scale(scale as AzFloat, scale as AzFloat);
                     let matrix = matrix.translate(-(tile.page_rect.origin.x) as AzFloat,
                                                   -(tile.page_rect.origin.y) as AzFloat);
-                    let matrix = matrix.translate(-(render_layer.position.origin.x as AzFloat),
-                                                  -(render_layer.position.origin.y as AzFloat));
+                    let matrix = matrix.translate(-(layer_origin.x as AzFloat),
+                                                  -(layer_origin.y as AzFloat));
 
                     ctx.draw_target.set_transform(&matrix);
 
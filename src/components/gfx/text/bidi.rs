@@ -0,0 +1,394 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The Unicode Bidirectional Algorithm (UAX #9).
+//!
+//! Explicit embeddings and overrides come from the CSS `unicode-bidi` and `direction` properties,
+//! so the explicit formatting characters (LRE, RLE, PDF, LRO, RLO and the isolates) are ignored
+//! like boundary neutrals. Mirrored glyphs (rule L4) are not yet implemented.
+//!
+//! See http://www.unicode.org/reports/tr9/.
+
+use std::cmp;
+
+/// The maximum explicit embedding level.
+pub static MAX_EMBEDDING_LEVEL: u8 = 125;
+
+/// A bidirectional character type, as assigned by UAX #9.
+#[deriving(Clone, PartialEq, Show)]
+pub enum BidiClass {
+    /// Strong left-to-right characters (L).
+    LeftToRight,
+    /// Strong right-to-left characters, such as Hebrew letters (R).
+    RightToLeft,
+    /// Strong right-to-left Arabic letters (AL).
+    ArabicLetter,
+    /// European digits (EN).
+    EuropeanNumber,
+    /// Plus and minus signs (ES).
+    EuropeanSeparator,
+    /// Currency and percent signs, which terminate European numbers (ET).
+    EuropeanTerminator,
+    /// Arabic-Indic digits (AN).
+    ArabicNumber,
+    /// Separators used inside numbers, such as `,` and `.` (CS).
+    CommonSeparator,
+    /// Nonspacing marks, which take the type of the preceding character (NSM).
+    NonspacingMark,
+    /// Characters with no effect on the algorithm, such as control characters (BN).
+    BoundaryNeutral,
+    /// Paragraph separators, such as newlines (B).
+    ParagraphSeparator,
+    /// Segment separators, such as tabs (S).
+    SegmentSeparator,
+    /// Whitespace (WS).
+    Whitespace,
+    /// All other neutrals, such as most punctuation (ON).
+    OtherNeutral,
+}
+
+/// Returns the bidirectional type of the given character.
+pub fn bidi_class(ch: char) -> BidiClass {
+    match ch {
+        '\n' | '\r' | '\x1c'..'\x1e' | '\u0085' | '\u2029' => ParagraphSeparator,
+        '\t' | '\x0b' | '\x1f' => SegmentSeparator,
+        ' ' | '\x0c' | '\u1680' | '\u2000'..'\u200a' | '\u2028' | '\u205f' | '\u3000' => Whitespace,
+        '\x00'..'\x08' | '\x0e'..'\x1b' | '\x7f'..'\u0084' | '\u0086'..'\u009f' | '\u00ad' |
+        '\u200b'..'\u200d' | '\u202a'..'\u202e' | '\u2060'..'\u2069' | '\ufeff' => BoundaryNeutral,
+        '\u200e' => LeftToRight,
+        '\u200f' => RightToLeft,
+        '\u061c' => ArabicLetter,
+        '0'..'9' | '\u00b2' | '\u00b3' | '\u00b9' | '\u06f0'..'\u06f9' | '\u2070' |
+        '\u2074'..'\u2079' | '\u2080'..'\u2089' | '\uff10'..'\uff19' => EuropeanNumber,
+        '+' | '-' | '\u207a' | '\u207b' | '\u208a' | '\u208b' | '\u2212' | '\ufb29' | '\ufe62' |
+        '\ufe63' | '\uff0b' | '\uff0d' => EuropeanSeparator,
+        '#'..'%' | '\u00a2'..'\u00a5' | '\u00b0' | '\u00b1' | '\u058f' | '\u0609' | '\u060a' |
+        '\u066a' | '\u2030'..'\u2034' | '\u20a0'..'\u20cf' | '\u212e' | '\u2213' | '\ufe5f' |
+        '\ufe69' | '\ufe6a' | '\uff03'..'\uff05' | '\uffe0' | '\uffe1' | '\uffe5' | '\uffe6' => {
+            EuropeanTerminator
+        }
+        '\u0600'..'\u0605' | '\u0660'..'\u0669' | '\u066b' | '\u066c' | '\u06dd' => ArabicNumber,
+        ',' | '.' | '/' | ':' | '\u00a0' | '\u060c' | '\u202f' | '\u2044' | '\ufe50' | '\ufe52' |
+        '\ufe55' | '\uff0c' | '\uff0e' | '\uff0f' | '\uff1a' => CommonSeparator,
+        '\u0300'..'\u036f' | '\u0483'..'\u0489' | '\u0591'..'\u05bd' | '\u05bf' | '\u05c1' |
+        '\u05c2' | '\u05c4' | '\u05c5' | '\u05c7' | '\u0610'..'\u061a' | '\u064b'..'\u065f' |
+        '\u0670' | '\u06d6'..'\u06dc' | '\u06df'..'\u06e4' | '\u06e7' | '\u06e8' |
+        '\u06ea'..'\u06ed' | '\u0711' | '\u0730'..'\u074a' | '\u0900'..'\u0902' | '\u093c' |
+        '\u0941'..'\u0948' | '\u094d' | '\u20d0'..'\u20f0' | '\ufb1e' | '\ufe00'..'\ufe0f' |
+        '\ufe20'..'\ufe2f' => NonspacingMark,
+        '\u0590'..'\u05ff' | '\u07c0'..'\u085f' | '\ufb1d'..'\ufb4f' => RightToLeft,
+        '\u0600'..'\u07bf' | '\u08a0'..'\u08ff' | '\ufb50'..'\ufdff' | '\ufe70'..'\ufefe' => {
+            ArabicLetter
+        }
+        '!' | '"' | '&'..'*' | ';'..'@' | '['..'`' | '{'..'~' | '\u00a1' | '\u00a6'..'\u00a9' |
+        '\u00ab' | '\u00ac' | '\u00ae' | '\u00af' | '\u00b4' | '\u00b6'..'\u00b8' |
+        '\u00bb'..'\u00bf' | '\u00d7' | '\u00f7' | '\u2010'..'\u2027' | '\u2035'..'\u2043' |
+        '\u2045'..'\u205e' | '\u2190'..'\u2bff' | '\u3001'..'\u3004' | '\u3008'..'\u3020' |
+        '\ufe30'..'\ufe4f' | '\ufffc' | '\ufffd' => OtherNeutral,
+        _ => LeftToRight,
+    }
+}
+
+/// Returns true if the given embedding level is right-to-left.
+#[inline]
+pub fn is_right_to_left(level: u8) -> bool {
+    level % 2 == 1
+}
+
+/// Returns the level of an embedding of the given direction nested inside the given level, per
+/// rules X2 through X5. Embeddings nested too deeply are ignored.
+pub fn next_embedding_level(level: u8, right_to_left: bool) -> u8 {
+    let next_level = if right_to_left == is_right_to_left(level) {
+        level + 2
+    } else {
+        level + 1
+    };
+    if next_level > MAX_EMBEDDING_LEVEL {
+        level
+    } else {
+        next_level
+    }
+}
+
+/// Resolves the embedding level of each character of a paragraph.
+///
+/// `classes` holds the bidirectional type of each character, with any directional override
+/// already applied, and `levels` holds the explicit embedding level of each character. On return,
+/// `levels` holds the resolved levels.
+pub fn resolve_levels(classes: &[BidiClass], levels: &mut [u8], paragraph_level: u8) {
+    assert!(classes.len() == levels.len());
+
+    // X9: Boundary neutrals are removed from the rest of the algorithm.
+    let indices: Vec<uint> = range(0, classes.len()).filter(|&i| {
+        classes[i] != BoundaryNeutral
+    }).collect();
+
+    // X10: Resolve the weak and neutral types of each run of characters at the same level.
+    let mut resolved_classes = Vec::from_fn(classes.len(), |i| classes[i]);
+    let mut run_start = 0;
+    while run_start < indices.len() {
+        let level = levels[indices[run_start]];
+        let mut run_end = run_start + 1;
+        while run_end < indices.len() && levels[indices[run_end]] == level {
+            run_end += 1
+        }
+
+        let previous_level = if run_start == 0 {
+            paragraph_level
+        } else {
+            levels[indices[run_start - 1]]
+        };
+        let next_level = if run_end == indices.len() {
+            paragraph_level
+        } else {
+            levels[indices[run_end]]
+        };
+        let sos = strong_class_for_level(cmp::max(previous_level, level));
+        let eos = strong_class_for_level(cmp::max(next_level, level));
+
+        let run = indices.slice(run_start, run_end);
+        resolve_weak_types(run, resolved_classes.as_mut_slice(), sos);
+        resolve_neutral_types(run, resolved_classes.as_mut_slice(), sos, eos, level);
+        resolve_implicit_levels(run, resolved_classes.as_slice(), levels);
+
+        run_start = run_end
+    }
+
+    // L1: Separators, and any whitespace before them or at the end of the paragraph, are reset
+    // to the paragraph level. Boundary neutrals take the level of the preceding character.
+    let mut in_trailing_whitespace = true;
+    for i in range(0, classes.len()).rev() {
+        match classes[i] {
+            ParagraphSeparator | SegmentSeparator => {
+                levels[i] = paragraph_level;
+                in_trailing_whitespace = true
+            }
+            Whitespace | BoundaryNeutral if in_trailing_whitespace => levels[i] = paragraph_level,
+            _ => in_trailing_whitespace = false,
+        }
+    }
+    for i in range(0, classes.len()) {
+        if classes[i] == BoundaryNeutral && i > 0 {
+            levels[i] = levels[i - 1]
+        }
+    }
+}
+
+fn strong_class_for_level(level: u8) -> BidiClass {
+    if is_right_to_left(level) {
+        RightToLeft
+    } else {
+        LeftToRight
+    }
+}
+
+/// Applies rules W1 through W7 to one level run, given as indices into `classes`.
+fn resolve_weak_types(run: &[uint], classes: &mut [BidiClass], sos: BidiClass) {
+    // W1: Nonspacing marks take the type of the previous character.
+    let mut previous_class = sos;
+    for &i in run.iter() {
+        if classes[i] == NonspacingMark {
+            classes[i] = previous_class
+        }
+        previous_class = classes[i]
+    }
+
+    // W2: European numbers after Arabic letters are Arabic numbers. W3: Arabic letters are
+    // right-to-left.
+    let mut last_strong_class = sos;
+    for &i in run.iter() {
+        match classes[i] {
+            LeftToRight | RightToLeft | ArabicLetter => last_strong_class = classes[i],
+            EuropeanNumber if last_strong_class == ArabicLetter => classes[i] = ArabicNumber,
+            _ => {}
+        }
+    }
+    for &i in run.iter() {
+        if classes[i] == ArabicLetter {
+            classes[i] = RightToLeft
+        }
+    }
+
+    // W4: A single separator between two numbers of the same type takes their type.
+    for j in range(1, run.len()) {
+        if j + 1 == run.len() {
+            break
+        }
+        match (classes[run[j - 1]], classes[run[j]], classes[run[j + 1]]) {
+            (EuropeanNumber, EuropeanSeparator, EuropeanNumber) |
+            (EuropeanNumber, CommonSeparator, EuropeanNumber) => {
+                classes[run[j]] = EuropeanNumber
+            }
+            (ArabicNumber, CommonSeparator, ArabicNumber) => classes[run[j]] = ArabicNumber,
+            _ => {}
+        }
+    }
+
+    // W5: Terminators adjacent to European numbers become European numbers.
+    let mut j = 0;
+    while j < run.len() {
+        if classes[run[j]] != EuropeanTerminator {
+            j += 1;
+            continue
+        }
+        let start = j;
+        while j < run.len() && classes[run[j]] == EuropeanTerminator {
+            j += 1
+        }
+        let adjacent_to_number = (start > 0 && classes[run[start - 1]] == EuropeanNumber) ||
+            (j < run.len() && classes[run[j]] == EuropeanNumber);
+        if adjacent_to_number {
+            for k in range(start, j) {
+                classes[run[k]] = EuropeanNumber
+            }
+        }
+    }
+
+    // W6: Remaining separators and terminators are neutral.
+    for &i in run.iter() {
+        match classes[i] {
+            EuropeanSeparator | EuropeanTerminator | CommonSeparator => classes[i] = OtherNeutral,
+            _ => {}
+        }
+    }
+
+    // W7: European numbers after left-to-right text are left-to-right.
+    let mut last_strong_class = sos;
+    for &i in run.iter() {
+        match classes[i] {
+            LeftToRight | RightToLeft => last_strong_class = classes[i],
+            EuropeanNumber if last_strong_class == LeftToRight => classes[i] = LeftToRight,
+            _ => {}
+        }
+    }
+}
+
+/// Applies rules N1 and N2 to one level run, given as indices into `classes`.
+fn resolve_neutral_types(run: &[uint],
+                         classes: &mut [BidiClass],
+                         sos: BidiClass,
+                         eos: BidiClass,
+                         level: u8) {
+    // Numbers count as right-to-left text for the purposes of resolving neutrals.
+    fn direction_of(class: BidiClass) -> Option<BidiClass> {
+        match class {
+            LeftToRight => Some(LeftToRight),
+            RightToLeft | EuropeanNumber | ArabicNumber => Some(RightToLeft),
+            _ => None,
+        }
+    }
+
+    let mut j = 0;
+    while j < run.len() {
+        if direction_of(classes[run[j]]).is_some() {
+            j += 1;
+            continue
+        }
+        let start = j;
+        while j < run.len() && direction_of(classes[run[j]]).is_none() {
+            j += 1
+        }
+
+        // N1: Neutrals between two characters of the same direction take that direction. N2:
+        // Other neutrals take the embedding direction.
+        let before = if start == 0 { sos } else { direction_of(classes[run[start - 1]]).unwrap() };
+        let after = if j == run.len() { eos } else { direction_of(classes[run[j]]).unwrap() };
+        let class = if before == after { before } else { strong_class_for_level(level) };
+        for k in range(start, j) {
+            classes[run[k]] = class
+        }
+    }
+}
+
+/// Applies rules I1 and I2 to one level run, given as indices into `classes` and `levels`.
+fn resolve_implicit_levels(run: &[uint], classes: &[BidiClass], levels: &mut [u8]) {
+    for &i in run.iter() {
+        let level = levels[i];
+        levels[i] = match (is_right_to_left(level), classes[i]) {
+            (false, RightToLeft) => level + 1,
+            (false, ArabicNumber) | (false, EuropeanNumber) => level + 2,
+            (true, LeftToRight) | (true, ArabicNumber) | (true, EuropeanNumber) => level + 1,
+            _ => level,
+        }
+    }
+}
+
+/// Returns the indices of the given items in visual order, from left to right, given their
+/// resolved levels in logical order, per rule L2.
+pub fn visual_order(levels: &[u8]) -> Vec<uint> {
+    let mut order = Vec::from_fn(levels.len(), |i| i);
+    let highest_level = levels.iter().fold(0, |highest, &level| {
+        if level > highest { level } else { highest }
+    });
+    let lowest_odd_level = levels.iter().fold(MAX_EMBEDDING_LEVEL + 1, |lowest, &level| {
+        if is_right_to_left(level) && level < lowest { level } else { lowest }
+    });
+
+    // Reverse every sequence at each level or higher, from the highest level down to the lowest
+    // odd level.
+    let mut level = highest_level;
+    while level >= lowest_odd_level && level > 0 {
+        let mut i = 0;
+        while i < levels.len() {
+            if levels[order[i]] < level {
+                i += 1;
+                continue
+            }
+            let start = i;
+            while i < levels.len() && levels[order[i]] >= level {
+                i += 1
+            }
+            order.mut_slice(start, i).reverse();
+        }
+        level -= 1
+    }
+    order
+}
+
+#[cfg(test)]
+fn levels_for_str(text: &str, paragraph_level: u8) -> Vec<u8> {
+    let classes: Vec<BidiClass> = text.chars().map(bidi_class).collect();
+    let mut levels = Vec::from_elem(classes.len(), paragraph_level);
+    resolve_levels(classes.as_slice(), levels.as_mut_slice(), paragraph_level);
+    levels
+}
+
+#[test]
+fn test_resolve_levels_ltr_paragraph() {
+    assert_eq!(levels_for_str("abc", 0), vec!(0, 0, 0));
+    assert_eq!(levels_for_str("ab אב cd", 0), vec!(0, 0, 0, 1, 1, 0, 0, 0));
+    assert_eq!(levels_for_str("א 12", 0), vec!(1, 1, 2, 2));
+    assert_eq!(levels_for_str("ا 12", 0), vec!(1, 1, 2, 2));
+}
+
+#[test]
+fn test_resolve_levels_rtl_paragraph() {
+    assert_eq!(levels_for_str("אב", 1), vec!(1, 1));
+    assert_eq!(levels_for_str("א ab ב", 1), vec!(1, 1, 2, 2, 1, 1));
+    assert_eq!(levels_for_str("ab.", 1), vec!(2, 2, 1));
+    assert_eq!(levels_for_str("1.5", 1), vec!(2, 2, 2));
+}
+
+#[test]
+fn test_resolve_levels_trailing_whitespace() {
+    assert_eq!(levels_for_str("א ", 0), vec!(1, 0));
+    assert_eq!(levels_for_str("a\tא", 1), vec!(2, 1, 1));
+}
+
+#[test]
+fn test_next_embedding_level() {
+    assert_eq!(next_embedding_level(0, true), 1);
+    assert_eq!(next_embedding_level(0, false), 2);
+    assert_eq!(next_embedding_level(1, true), 3);
+    assert_eq!(next_embedding_level(1, false), 2);
+    assert_eq!(next_embedding_level(MAX_EMBEDDING_LEVEL, true), MAX_EMBEDDING_LEVEL);
+}
+
+#[test]
+fn test_visual_order() {
+    assert_eq!(visual_order(&[0, 0, 0]), vec!(0, 1, 2));
+    assert_eq!(visual_order(&[1, 1, 1]), vec!(2, 1, 0));
+    assert_eq!(visual_order(&[0, 1, 1, 0]), vec!(0, 2, 1, 3));
+    assert_eq!(visual_order(&[1, 2, 2, 1]), vec!(3, 1, 2, 0));
+}
@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Line break opportunities, following the Unicode Line Breaking Algorithm (UAX #14).
+//!
+//! Spaces, tabs and newlines are handled by the text run itself, so this only decides whether a
+//! line may be broken between two adjacent non-whitespace characters. The rules with
+//! intervening spaces and the rarer classes (regional indicators, Hangul jamo sequences, emoji
+//! modifiers) are not implemented.
+//!
+//! See http://www.unicode.org/reports/tr14/ and Gecko's `nsLineBreaker`.
+
+/// A simplified line breaking class, as assigned by UAX #14.
+#[deriving(Clone, PartialEq, Show)]
+pub enum BreakClass {
+    /// Alphabetic and symbols used with them.
+    Alphabetic,
+    /// Combining marks, which take the class of the character they attach to.
+    CombiningMark,
+    /// Non-breaking ("glue") characters, such as no-break space and word joiner.
+    Glue,
+    /// The zero-width space, which is a break opportunity after itself.
+    ZeroWidthSpace,
+    /// Opening punctuation, such as `(` and `「`.
+    OpenPunctuation,
+    /// Closing punctuation, such as `}` and the ideographic full stop.
+    ClosePunctuation,
+    /// Closing parentheses, which differ from other closing punctuation before letters.
+    CloseParenthesis,
+    /// Quotation marks, which may be opening or closing.
+    Quotation,
+    /// Exclamation and interrogation marks.
+    Exclamation,
+    /// Infix separators inside numbers and words, such as `.` and `,`.
+    InfixSeparator,
+    /// The solidus `/`, which allows a break after it, as in URLs.
+    Symbol,
+    /// Hyphen-minus, which allows a break after it unless a number follows.
+    Hyphen,
+    /// Characters that allow a break after them, such as dashes.
+    BreakAfter,
+    /// Characters that allow a break before them, such as the acute accent.
+    BreakBefore,
+    /// Characters that cannot start a line, such as small kana and iteration marks.
+    Nonstarter,
+    /// Digits.
+    Numeric,
+    /// Prefix numeric characters, such as currency signs.
+    PrefixNumeric,
+    /// Postfix numeric characters, such as `%`.
+    PostfixNumeric,
+    /// Ideographs and syllabaries, which allow a break on either side.
+    Ideographic,
+}
+
+/// Returns the line breaking class of the given character.
+pub fn break_class(ch: char) -> BreakClass {
+    match ch {
+        '\u00a0' | '\u2007' | '\u2011' | '\u202f' | '\u2060' | '\ufeff' => Glue,
+        '\u200b' => ZeroWidthSpace,
+        '\u0300'..'\u036f' | '\u0483'..'\u0489' | '\u0591'..'\u05bd' | '\u0610'..'\u061a' |
+        '\u064b'..'\u065f' | '\u0900'..'\u0903' | '\u093a'..'\u094f' | '\u1ab0'..'\u1aff' |
+        '\u1dc0'..'\u1dff' | '\u200c'..'\u200f' | '\u20d0'..'\u20ff' | '\u3099' | '\u309a' |
+        '\ufe00'..'\ufe0f' | '\ufe20'..'\ufe2f' => CombiningMark,
+        '(' | '[' | '{' | '\u00a1' | '\u00bf' | '\u201a' | '\u201e' | '\u2045' | '\u207d' |
+        '\u208d' | '\u2329' | '\u3008' | '\u300a' | '\u300c' | '\u300e' | '\u3010' |
+        '\u3014' | '\u3016' | '\u3018' | '\u301a' | '\u301d' | '\uff08' | '\uff3b' |
+        '\uff5b' | '\uff5f' | '\uff62' => OpenPunctuation,
+        ')' | ']' => CloseParenthesis,
+        '}' | '\u2046' | '\u207e' | '\u208e' | '\u232a' | '\u3001' | '\u3002' | '\u3009' |
+        '\u300b' | '\u300d' | '\u300f' | '\u3011' | '\u3015' | '\u3017' | '\u3019' |
+        '\u301b' | '\u301e' | '\u301f' | '\ufe50' | '\ufe52' | '\uff09' | '\uff0c' |
+        '\uff0e' | '\uff3d' | '\uff5d' | '\uff60' | '\uff61' | '\uff63' | '\uff64' => {
+            ClosePunctuation
+        }
+        '"' | '\'' | '\u00ab' | '\u00bb' | '\u2018' | '\u2019' | '\u201b' | '\u201c' |
+        '\u201d' | '\u201f' | '\u2039' | '\u203a' => Quotation,
+        '!' | '?' | '\u05c6' | '\u061b' | '\u061f' | '\u06d4' | '\ufe15' | '\ufe16' |
+        '\ufe56' | '\ufe57' | '\uff01' | '\uff1f' => Exclamation,
+        ',' | '.' | ':' | ';' | '\u037e' | '\u0589' | '\u060c' | '\u060d' | '\u07f8' |
+        '\u2044' | '\ufe10' | '\ufe13' | '\ufe14' => InfixSeparator,
+        '/' => Symbol,
+        '-' => Hyphen,
+        '\t' | '\u00ad' | '\u058a' | '\u05be' | '\u1680' | '\u2000'..'\u2006' |
+        '\u2008'..'\u200a' | '\u2010' | '\u2012'..'\u2014' | '\u2027' | '\u205f' => {
+            BreakAfter
+        }
+        '\u00b4' | '\u02c8' | '\u02cc' | '\u02df' => BreakBefore,
+        '\u17d6' | '\u203c' | '\u203d' | '\u2047'..'\u2049' | '\u3005' | '\u301c' |
+        '\u303b' | '\u303c' | '\u309b'..'\u309e' | '\u30a0' | '\u30fb' | '\u30fd' |
+        '\u30fe' | '\ua015' | '\ufe54' | '\ufe55' | '\uff1a' | '\uff1b' | '\uff65' |
+        '\uff9e' | '\uff9f' | '\u3041' | '\u3043' | '\u3045' | '\u3047' | '\u3049' |
+        '\u3063' | '\u3083' | '\u3085' | '\u3087' | '\u308e' | '\u3095' | '\u3096' |
+        '\u30a1' | '\u30a3' | '\u30a5' | '\u30a7' | '\u30a9' | '\u30c3' | '\u30e3' |
+        '\u30e5' | '\u30e7' | '\u30ee' | '\u30f5' | '\u30f6' | '\u30fc' |
+        '\u31f0'..'\u31ff' | '\uff67'..'\uff70' => Nonstarter,
+        '0'..'9' | '\u0660'..'\u0669' | '\u06f0'..'\u06f9' | '\u0966'..'\u096f' => Numeric,
+        '$' | '+' | '\\' | '\u00a3'..'\u00a5' | '\u00b1' | '\u20a0'..'\u20a6' |
+        '\u20a8'..'\u20b5' | '\u2116' | '\u2212' | '\u2213' | '\uffe1' | '\uffe5' |
+        '\uffe6' => PrefixNumeric,
+        '%' | '\u00a2' | '\u00b0' | '\u2030'..'\u2037' | '\u2103' | '\u2109' | '\u20a7' |
+        '\uff05' | '\uffe0' => PostfixNumeric,
+        '\u1100'..'\u115f' | '\u2e80'..'\u2fff' | '\u3003' | '\u3004' | '\u3006' |
+        '\u3007' | '\u3012' | '\u3013' | '\u3020'..'\u3029' | '\u3030'..'\u303a' |
+        '\u303d'..'\u303f' | '\u3042' | '\u3044' | '\u3046' | '\u3048' | '\u304a'..'\u3062' |
+        '\u3064'..'\u3082' | '\u3084' | '\u3086' | '\u3088'..'\u308d' | '\u308f'..'\u3094' |
+        '\u309f' | '\u30a2' | '\u30a4' | '\u30a6' | '\u30a8' | '\u30aa'..'\u30c2' |
+        '\u30c4'..'\u30e2' | '\u30e4' | '\u30e6' | '\u30e8'..'\u30ed' | '\u30ef'..'\u30f4' |
+        '\u30f7'..'\u30fa' | '\u30ff' | '\u3105'..'\u31ef' | '\u3200'..'\u4dbf' |
+        '\u4e00'..'\u9fff' | '\ua000'..'\ua014' | '\ua016'..'\ua48f' | '\uac00'..'\ud7a3' |
+        '\uf900'..'\ufaff' | '\ufe30'..'\ufe4f' | '\uff02'..'\uff04' | '\uff06' |
+        '\uff07' | '\uff0a' | '\uff0b' | '\uff0d' | '\uff0f'..'\uff19' |
+        '\uff1c'..'\uff1e' | '\uff20'..'\uff3a' | '\uff3c' | '\uff3e'..'\uff5a' |
+        '\uff5c' | '\uff5e' | '\uffe2'..'\uffe4' | '\U00020000'..'\U0003fffd' => Ideographic,
+        _ => Alphabetic,
+    }
+}
+
+/// Returns true if a line may be broken between two adjacent non-whitespace characters with the
+/// given classes. Combining marks should already have been resolved to the class of their base
+/// character.
+pub fn is_break_opportunity(before: BreakClass, after: BreakClass) -> bool {
+    match (before, after) {
+        // LB8: Break after zero-width spaces.
+        (ZeroWidthSpace, _) => true,
+        // LB9: Do not separate combining marks from their base.
+        (_, CombiningMark) => false,
+        // LB11, LB12, LB12a: Do not break around glue.
+        (Glue, _) | (_, Glue) => false,
+        // LB13: Do not break before closing punctuation, `!` or separators.
+        (_, ClosePunctuation) | (_, CloseParenthesis) | (_, Exclamation) |
+        (_, InfixSeparator) | (_, Symbol) => false,
+        // LB14: Do not break after opening punctuation.
+        (OpenPunctuation, _) => false,
+        // LB19: Do not break on either side of ambiguous quotation marks.
+        (Quotation, _) | (_, Quotation) => false,
+        // LB21: Do not break before hyphens and nonstarters, or after break-before characters.
+        (_, BreakAfter) | (_, Hyphen) | (_, Nonstarter) | (BreakBefore, _) => false,
+        // LB23, LB23a, LB24: Do not break between letters, numbers and their prefixes and
+        // postfixes.
+        (Alphabetic, Numeric) | (Numeric, Alphabetic) |
+        (PrefixNumeric, Ideographic) | (Ideographic, PostfixNumeric) |
+        (PrefixNumeric, Alphabetic) | (PostfixNumeric, Alphabetic) |
+        (Alphabetic, PrefixNumeric) | (Alphabetic, PostfixNumeric) => false,
+        // LB25: Do not break inside numbers.
+        (ClosePunctuation, PostfixNumeric) | (ClosePunctuation, PrefixNumeric) |
+        (CloseParenthesis, PostfixNumeric) | (CloseParenthesis, PrefixNumeric) |
+        (Numeric, PostfixNumeric) | (Numeric, PrefixNumeric) |
+        (PostfixNumeric, OpenPunctuation) | (PostfixNumeric, Numeric) |
+        (PrefixNumeric, OpenPunctuation) | (PrefixNumeric, Numeric) |
+        (Hyphen, Numeric) | (InfixSeparator, Numeric) | (Numeric, Numeric) |
+        (Symbol, Numeric) => false,
+        // LB28, LB29: Do not break between letters, or after infix separators before letters.
+        (Alphabetic, Alphabetic) | (InfixSeparator, Alphabetic) => false,
+        // LB30: Do not break between letters or numbers and parentheses.
+        (Alphabetic, OpenPunctuation) | (Numeric, OpenPunctuation) |
+        (CloseParenthesis, Alphabetic) | (CloseParenthesis, Numeric) => false,
+        // LB31: Break everywhere else.
+        _ => true,
+    }
+}
+
+#[test]
+fn test_break_between_ideographs() {
+    assert!(is_break_opportunity(break_class('中'), break_class('文')));
+    assert!(!is_break_opportunity(break_class('文'), break_class('。')));
+    assert!(!is_break_opportunity(break_class('「'), break_class('文')));
+    assert!(!is_break_opportunity(break_class('カ'), break_class('ー')));
+}
+
+#[test]
+fn test_break_in_urls() {
+    assert!(!is_break_opportunity(break_class('p'), break_class(':')));
+    assert!(!is_break_opportunity(break_class(':'), break_class('/')));
+    assert!(!is_break_opportunity(break_class('.'), break_class('c')));
+    assert!(is_break_opportunity(break_class('/'), break_class('p')));
+}
+
+#[test]
+fn test_break_around_punctuation() {
+    assert!(!is_break_opportunity(break_class('a'), break_class('b')));
+    assert!(is_break_opportunity(break_class('-'), break_class('b')));
+    assert!(!is_break_opportunity(break_class('-'), break_class('1')));
+    assert!(!is_break_opportunity(break_class('a'), break_class('-')));
+    assert!(!is_break_opportunity(break_class('a'), break_class('!')));
+    assert!(!is_break_opportunity(break_class('('), break_class('a')));
+    assert!(!is_break_opportunity(break_class('$'), break_class('1')));
+    assert!(!is_break_opportunity(break_class('1'), break_class('%')));
+    assert!(!is_break_opportunity(break_class('a'), break_class('\u00a0')));
+}
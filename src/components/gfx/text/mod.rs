@@ -11,7 +11,9 @@ servo.rc. This is not ideal and may be changed in the future. */
 pub use text::shaping::Shaper;
 pub use text::text_run::TextRun;
 
+pub mod bidi;
 pub mod glyph;
+pub mod line_break;
 #[path="shaping/mod.rs"] pub mod shaping;
 pub mod text_run;
 pub mod util;
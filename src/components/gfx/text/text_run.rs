@@ -5,11 +5,12 @@
 use font::{Font, FontDescriptor, RunMetrics, FontStyle, FontMetrics};
 use servo_util::geometry::Au;
 use servo_util::range::Range;
-use servo_util::vec::{Comparator, FullBinarySearchMethods};
+use servo_util::vec::{BinarySearchMethods, Comparator, FullBinarySearchMethods};
 use std::slice::Items;
 use style::computed_values::text_decoration;
 use sync::Arc;
 use text::glyph::{CharIndex, GlyphStore};
+use text::line_break::{Alphabetic, CombiningMark, break_class, is_break_opportunity};
 
 /// A single "paragraph" of text in one font size and style.
 #[deriving(Clone)]
@@ -21,6 +22,12 @@ pub struct TextRun {
     pub decoration: text_decoration::T,
     /// The glyph runs that make up this text run.
     pub glyphs: Arc<Vec<GlyphRun>>,
+    /// The indices of the characters that followed soft hyphens in the original text, in
+    /// ascending order. Lines may be broken before these characters.
+    pub soft_hyphens: Arc<Vec<CharIndex>>,
+    /// The glyph displayed at the end of a line that was broken at a soft hyphen, if this run
+    /// has any soft hyphens.
+    pub hyphen_glyphs: Option<Arc<GlyphStore>>,
 }
 
 /// A single series of glyphs within a text run.
@@ -117,8 +124,17 @@ impl<'a> Iterator<Range<CharIndex>> for LineIterator<'a> {
 }
 
 impl<'a> TextRun {
-    pub fn new(font: &mut Font, text: String, decoration: text_decoration::T) -> TextRun {
-        let glyphs = TextRun::break_and_shape(font, text.as_slice());
+    pub fn new(font: &mut Font,
+               text: String,
+               decoration: text_decoration::T,
+               soft_hyphens: Vec<CharIndex>)
+               -> TextRun {
+        let glyphs = TextRun::break_and_shape(font, text.as_slice(), soft_hyphens.as_slice());
+        let hyphen_glyphs = if soft_hyphens.is_empty() {
+            None
+        } else {
+            Some(font.shape_text("-".to_string(), false))
+        };
 
         let run = TextRun {
             text: Arc::new(text),
@@ -127,39 +143,54 @@ impl<'a> TextRun {
             font_descriptor: font.get_descriptor(),
             decoration: decoration,
             glyphs: Arc::new(glyphs),
+            soft_hyphens: Arc::new(soft_hyphens),
+            hyphen_glyphs: hyphen_glyphs,
         };
         return run;
     }
 
-    pub fn break_and_shape(font: &mut Font, text: &str) -> Vec<GlyphRun> {
-        // TODO(Issue #230): do a better job. See Gecko's LineBreaker.
-
+    pub fn break_and_shape(font: &mut Font, text: &str, soft_hyphens: &[CharIndex])
+                           -> Vec<GlyphRun> {
         let mut glyphs = vec!();
         let (mut byte_i, mut char_i) = (0u, CharIndex(0));
         let mut cur_slice_is_whitespace = false;
+        let mut prev_break_class = None;
+        let mut soft_hyphens = soft_hyphens.iter().peekable();
         let (mut byte_last_boundary, mut char_last_boundary) = (0, CharIndex(0));
         while byte_i < text.len() {
             let range = text.char_range_at(byte_i);
             let ch = range.ch;
             let next = range.next;
 
-            // Slices alternate between whitespace and non-whitespace,
-            // representing line break opportunities.
-            let can_break_before = if cur_slice_is_whitespace {
-                match ch {
-                    ' ' | '\t' | '\n' => false,
-                    _ => {
-                        cur_slice_is_whitespace = false;
-                        true
+            let mut follows_soft_hyphen = false;
+            loop {
+                match soft_hyphens.peek() {
+                    Some(&&index) if index <= char_i => {
+                        follows_soft_hyphen = follows_soft_hyphen || index == char_i;
+                        soft_hyphens.next();
                     }
+                    _ => break,
                 }
+            }
+
+            // Slices alternate between whitespace and non-whitespace, representing line break
+            // opportunities. Non-whitespace slices are further divided wherever UAX #14 allows a
+            // break between two characters, or a soft hyphen was removed.
+            let is_whitespace = match ch {
+                ' ' | '\t' | '\n' => true,
+                _ => false,
+            };
+            let class = break_class(ch);
+            let can_break_before = if is_whitespace != cur_slice_is_whitespace {
+                true
+            } else if is_whitespace {
+                false
             } else {
-                match ch {
-                    ' ' | '\t' | '\n' => {
-                        cur_slice_is_whitespace = true;
-                        true
-                    },
-                    _ => false
+                match prev_break_class {
+                    Some(prev_class) => {
+                        follows_soft_hyphen || is_break_opportunity(prev_class, class)
+                    }
+                    None => false,
                 }
             };
 
@@ -167,15 +198,23 @@ impl<'a> TextRun {
             if can_break_before && byte_i > byte_last_boundary {
                 let slice = text.slice(byte_last_boundary, byte_i).to_string();
                 debug!("creating glyph store for slice {} (ws? {}), {} - {} in run {}",
-                        slice, !cur_slice_is_whitespace, byte_last_boundary, byte_i, text);
+                        slice, cur_slice_is_whitespace, byte_last_boundary, byte_i, text);
                 glyphs.push(GlyphRun {
-                    glyph_store: font.shape_text(slice, !cur_slice_is_whitespace),
+                    glyph_store: font.shape_text(slice, cur_slice_is_whitespace),
                     range: Range::new(char_last_boundary, char_i - char_last_boundary),
                 });
                 byte_last_boundary = byte_i;
                 char_last_boundary = char_i;
             }
 
+            cur_slice_is_whitespace = is_whitespace;
+            prev_break_class = match (is_whitespace, class) {
+                (true, _) => None,
+                // Combining marks take the class of their base character, if there is one.
+                (false, CombiningMark) => prev_break_class.or(Some(Alphabetic)),
+                (false, class) => Some(class),
+            };
+
             byte_i = next;
             char_i = char_i + CharIndex(1);
         }
@@ -220,7 +259,6 @@ impl<'a> TextRun {
     }
 
     pub fn advance_for_range(&self, range: &Range<CharIndex>) -> Au {
-        // TODO(Issue #199): alter advance direction for RTL
         // TODO(Issue #98): using inter-char and inter-word spacing settings  when measuring text
         self.iter_slices_for_range(range)
             .fold(Au(0), |advance, (glyphs, _, slice_range)| {
@@ -228,6 +266,18 @@ impl<'a> TextRun {
             })
     }
 
+    /// Returns the number of places in the given range where text can be expanded for
+    /// justification: the characters that separate words.
+    pub fn expansion_opportunities_for_range(&self, range: &Range<CharIndex>) -> uint {
+        self.iter_slices_for_range(range).fold(0, |count, (glyphs, _, slice_range)| {
+            if glyphs.is_whitespace() {
+                count + slice_range.length().to_uint()
+            } else {
+                count
+            }
+        })
+    }
+
     pub fn metrics_for_range(&self, range: &Range<CharIndex>) -> RunMetrics {
         RunMetrics::new(self.advance_for_range(range),
                         self.font_metrics.ascent,
@@ -242,12 +292,28 @@ impl<'a> TextRun {
 
     pub fn min_width_for_range(&self, range: &Range<CharIndex>) -> Au {
         debug!("iterating outer range {:?}", range);
-        self.iter_slices_for_range(range).fold(Au(0), |max_piece_width, (_, offset, slice_range)| {
+        self.iter_slices_for_range(range).fold(Au(0), |max_piece_width, (glyphs, offset, slice_range)| {
             debug!("iterated on {:?}[{:?}]", offset, slice_range);
-            Au::max(max_piece_width, self.advance_for_range(&slice_range))
+            Au::max(max_piece_width, glyphs.advance_for_char_range(&slice_range))
         })
     }
 
+    /// Returns true if a line may be broken before the character at the given index because a
+    /// soft hyphen preceded it. A hyphen is displayed at the end of a line broken there.
+    pub fn is_soft_hyphen_break(&self, index: CharIndex) -> bool {
+        self.soft_hyphens.as_slice().binary_search_index(&index).is_some()
+    }
+
+    /// Returns the advance of the hyphen displayed at the end of a line broken at a soft hyphen.
+    pub fn hyphen_advance(&self) -> Au {
+        match self.hyphen_glyphs {
+            None => Au(0),
+            Some(ref glyphs) => {
+                glyphs.advance_for_char_range(&Range::new(CharIndex(0), CharIndex(1)))
+            }
+        }
+    }
+
     /// Returns the index of the first glyph run containing the given character index.
     fn index_of_first_glyph_run_containing(&self, index: CharIndex) -> Option<uint> {
         self.glyphs.as_slice().binary_search_index_by(&index, CharIndexComparator)
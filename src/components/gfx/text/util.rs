@@ -4,6 +4,9 @@
 
 use text::glyph::CharIndex;
 
+/// U+00AD SOFT HYPHEN, which marks a place where a word may be hyphenated.
+pub static SOFT_HYPHEN: char = '\u00ad';
+
 #[deriving(PartialEq)]
 pub enum CompressionMode {
     CompressNone,
@@ -22,16 +25,24 @@ pub enum CompressionMode {
 // * Issue #114: record skipped and kept chars for mapping original to new text
 //
 // * Untracked: various edge cases for bidi, CJK, etc.
+//
+// Soft hyphens are discarded; the index in the output of the character following each one is
+// pushed onto `soft_hyphen_pos`, since they remain line break opportunities.
 pub fn transform_text(text: &str, mode: CompressionMode,
                       incoming_whitespace: bool,
-                      new_line_pos: &mut Vec<CharIndex>) -> (String, bool) {
+                      new_line_pos: &mut Vec<CharIndex>,
+                      soft_hyphen_pos: &mut Vec<CharIndex>) -> (String, bool) {
     let mut out_str = String::new();
+    let mut out_char_count = CharIndex(0);
     let out_whitespace = match mode {
         CompressNone | DiscardNewline => {
             let mut new_line_index = CharIndex(0);
             for ch in text.chars() {
                 if is_discardable_char(ch, mode) {
                     // TODO: record skipped char
+                    if ch == SOFT_HYPHEN {
+                        soft_hyphen_pos.push(out_char_count);
+                    }
                 } else {
                     // TODO: record kept char
                     if ch == '\t' {
@@ -47,6 +58,7 @@ pub fn transform_text(text: &str, mode: CompressionMode,
                         new_line_index = new_line_index + CharIndex(1);
                     }
                     out_str.push_char(ch);
+                    out_char_count = out_char_count + CharIndex(1);
                 }
             }
             text.len() > 0 && is_in_whitespace(text.char_at_reverse(0), mode)
@@ -63,9 +75,13 @@ pub fn transform_text(text: &str, mode: CompressionMode,
                         // revert whitespace setting, since this char was discarded
                         next_in_whitespace = in_whitespace;
                         // TODO: record skipped char
+                        if ch == SOFT_HYPHEN {
+                            soft_hyphen_pos.push(out_char_count);
+                        }
                     } else {
                         // TODO: record kept char
                         out_str.push_char(ch);
+                        out_char_count = out_char_count + CharIndex(1);
                     }
                 } else { /* next_in_whitespace; possibly add a space char */
                     if in_whitespace {
@@ -73,6 +89,7 @@ pub fn transform_text(text: &str, mode: CompressionMode,
                     } else {
                         // TODO: record kept char
                         out_str.push_char(' ');
+                        out_char_count = out_char_count + CharIndex(1);
                     }
                 }
                 // save whitespace context for next char
@@ -103,9 +120,9 @@ pub fn transform_text(text: &str, mode: CompressionMode,
         }
     }
 
-    fn is_always_discardable_char(_ch: char) -> bool {
-        // TODO: check for bidi control chars, soft hyphens.
-        false
+    fn is_always_discardable_char(ch: char) -> bool {
+        // TODO: check for bidi control chars.
+        ch == SOFT_HYPHEN
     }
 }
 
@@ -135,6 +152,26 @@ pub fn true_type_tag(a: char, b: char, c: char, d: char) -> u32 {
     (a << 24 | b << 16 | c << 8 | d) as u32
 }
 
+/// Returns true if the given character is set upright in vertical text under
+/// `text-orientation: mixed`. This is an approximation of the `U` and `Tu` values of the Unicode
+/// Vertical_Orientation property (UAX #50) that covers the CJK and fullwidth ranges.
+pub fn is_upright_in_vertical_text(ch: char) -> bool {
+    match ch {
+        '\u1100'..'\u11ff' |     // Hangul Jamo
+        '\u2e80'..'\u2fdf' |     // CJK Radicals and Kangxi Radicals
+        '\u3000'..'\u30ff' |     // CJK Symbols and Punctuation, Hiragana and Katakana
+        '\u3100'..'\u31ff' |     // Bopomofo, Hangul Compatibility Jamo and Katakana extensions
+        '\u3200'..'\u4dbf' |     // Enclosed CJK, CJK Compatibility and CJK Extension A
+        '\u4e00'..'\u9fff' |     // CJK Unified Ideographs
+        '\ua960'..'\ua97f' |     // Hangul Jamo Extended-A
+        '\uac00'..'\ud7ff' |     // Hangul Syllables and Jamo Extended-B
+        '\uf900'..'\ufaff' |     // CJK Compatibility Ideographs
+        '\ufe30'..'\ufe4f' |     // CJK Compatibility Forms
+        '\uff00'..'\uffef' => true, // Halfwidth and Fullwidth Forms
+        _ => false,
+    }
+}
+
 #[test]
 fn test_true_type_tag() {
     assert_eq!(true_type_tag('c', 'm', 'a', 'p'), 0x_63_6D_61_70_u32);
@@ -155,7 +192,9 @@ fn test_transform_compress_none() {
 
     for test in test_strs.iter() {
         let mut new_line_pos = vec!();
-        let (trimmed_str, _out) = transform_text(*test, mode, true, &mut new_line_pos);
+        let mut soft_hyphen_pos = vec!();
+        let (trimmed_str, _out) = transform_text(*test, mode, true, &mut new_line_pos,
+                                                 &mut soft_hyphen_pos);
         assert_eq!(trimmed_str.as_slice(), *test)
     }
 }
@@ -187,7 +226,9 @@ fn test_transform_discard_newline() {
 
     for (test, oracle) in test_strs.iter().zip(oracle_strs.iter()) {
         let mut new_line_pos = vec!();
-        let (trimmed_str, _out) = transform_text(*test, mode, true, &mut new_line_pos);
+        let mut soft_hyphen_pos = vec!();
+        let (trimmed_str, _out) = transform_text(*test, mode, true, &mut new_line_pos,
+                                                 &mut soft_hyphen_pos);
         assert_eq!(trimmed_str.as_slice(), *oracle)
     }
 }
@@ -279,7 +320,57 @@ fn test_transform_compress_whitespace_newline_no_incoming() {
 
     for (test, oracle) in test_strs.iter().zip(oracle_strs.iter()) {
         let mut new_line_pos = vec!();
-        let (trimmed_str, _out) = transform_text(*test, mode, false, &mut new_line_pos);
+        let mut soft_hyphen_pos = vec!();
+        let (trimmed_str, _out) = transform_text(*test, mode, false, &mut new_line_pos,
+                                                 &mut soft_hyphen_pos);
         assert_eq!(trimmed_str.as_slice(), *oracle)
     }
 }
+
+#[test]
+fn test_transform_discard_soft_hyphens() {
+    let test_strs = vec!(
+        "hy\u00adphen",
+        "\u00adfoo bar\u00ad",
+        "foo \u00ad bar",
+        "a\u00adb\u00adc"
+    );
+
+    let oracle_strs = vec!(
+        "hyphen",
+        "foo bar",
+        "foo bar",
+        "abc"
+    );
+
+    let oracle_positions = vec!(
+        vec!(CharIndex(2)),
+        vec!(CharIndex(0), CharIndex(7)),
+        vec!(CharIndex(4)),
+        vec!(CharIndex(1), CharIndex(2))
+    );
+
+    assert_eq!(test_strs.len(), oracle_strs.len());
+    let mode = CompressWhitespaceNewline;
+
+    for ((test, oracle), positions) in test_strs.iter().zip(oracle_strs.iter())
+                                                .zip(oracle_positions.iter()) {
+        let mut new_line_pos = vec!();
+        let mut soft_hyphen_pos = vec!();
+        let (trimmed_str, _out) = transform_text(*test, mode, true, &mut new_line_pos,
+                                                 &mut soft_hyphen_pos);
+        assert_eq!(trimmed_str.as_slice(), *oracle);
+        assert_eq!(soft_hyphen_pos, *positions)
+    }
+}
+
+#[test]
+fn test_is_upright_in_vertical_text() {
+    assert!(is_upright_in_vertical_text('\u65e5'));
+    assert!(is_upright_in_vertical_text('\u3042'));
+    assert!(is_upright_in_vertical_text('\u3002'));
+    assert!(is_upright_in_vertical_text('\uff21'));
+    assert!(!is_upright_in_vertical_text('a'));
+    assert!(!is_upright_in_vertical_text('1'));
+    assert!(!is_upright_in_vertical_text('\u05d0'));
+}
@@ -20,11 +20,14 @@ use floats::{ClearBoth, ClearLeft, ClearRight, FloatKind, Floats, PlacementInfo}
 use flow::{BaseFlow, BlockFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use flow::{MutableFlowUtils, PreorderFlowTraversal, PostorderFlowTraversal, mut_base};
 use flow;
-use fragment::{Fragment, ImageFragment, ScannedTextFragment};
+use fragment::{Fragment, FormControlFragment, ImageFragment, InlineBlockFragment};
+use fragment::{ScannedTextFragment};
+use inline;
 use model::{Auto, IntrinsicWidths, MarginCollapseInfo, MarginsCollapse};
 use model::{MarginsCollapseThrough, MaybeAuto, NoCollapsibleMargins, Specified, specified};
 use model::{specified_or_none};
 use model;
+use table;
 use wrapper::ThreadSafeLayoutNode;
 use style::ComputedValues;
 use style::computed_values::{clear, position};
@@ -33,26 +36,38 @@ use collections::Deque;
 use collections::dlist::DList;
 use geom::{Point2D, Rect, Size2D};
 use gfx::color;
-use gfx::display_list::{BackgroundAndBorderLevel, BlockLevel, ContentStackingLevel, DisplayList};
+use gfx::display_list::{BackgroundAndBorderLevel, BaseDisplayItem, BlockLevel};
+use gfx::display_list::{ClipDisplayItemClass, ContentStackingLevel, DisplayList};
+use gfx::display_list::{SolidColorDisplayItem, SolidColorDisplayItemClass};
 use gfx::display_list::{FloatStackingLevel, PositionedDescendantStackingLevel};
-use gfx::display_list::{RootOfStackingContextLevel};
+use gfx::display_list::{RootOfStackingContextLevel, StackingLevel};
 use gfx::render_task::RenderLayer;
-use servo_msg::compositor_msg::{FixedPosition, LayerId, Scrollable};
-use servo_util::geometry::Au;
+use servo_msg::compositor_msg::{FixedPosition, LayerId, Scrollable, StickyConstraint};
+use servo_msg::compositor_msg::{ScrollbarThumb, ScrollbarThumbConstraint, StickyPosition};
+use servo_util::geometry::{Au, LogicalMargin, LogicalPoint, LogicalRect, LogicalSize};
 use servo_util::geometry;
+use std::cmp;
 use std::fmt;
 use std::mem;
-use std::num::Zero;
-use style::computed_values::{LPA_Auto, LPA_Length, LPA_Percentage, LPN_Length, LPN_None};
+use style::computed_values::{LengthOrPercentageOrAuto, LPA_Auto, LPA_Length, LPA_Percentage};
+use style::computed_values::{LPN_Length, LPN_None};
 use style::computed_values::{LPN_Percentage, LP_Length, LP_Percentage};
-use style::computed_values::{display, direction, float, overflow};
+use style::computed_values::{display, direction, float, overflow, page_break_after};
+use style::computed_values::{page_break_before, page_break_inside};
 use sync::Arc;
 
+/// The thickness of the scrollbars of scroll containers, in px.
+static SCROLLBAR_WIDTH: int = 12;
+
+/// The shortest that the thumb of a scrollbar gets, in px, so that it stays visible.
+static MIN_SCROLLBAR_THUMB_LENGTH: int = 8;
+
 /// Information specific to floated blocks.
 pub struct FloatedBlockInfo {
     pub containing_width: Au,
 
-    /// Offset relative to where the parent tried to position this flow
+    /// Offset relative to where the parent tried to position this flow, in the flow-relative
+    /// coordinates that `Floats` uses (`x` along the inline axis and `y` along the block axis).
     pub rel_pos: Point2D<Au>,
 
     /// Index into the fragment list for inline floats
@@ -301,21 +316,21 @@ impl CandidateHeightIterator {
         // the containing block. If that is not determined yet by the time we need to resolve
         // `min-height` and `max-height`, percentage values are ignored.
 
-        let height = match (style.get_box().height, block_container_height) {
+        let height = match (style.content_block_size(), block_container_height) {
             (LPA_Percentage(percent), Some(block_container_height)) => {
                 Specified(block_container_height.scale_by(percent))
             }
             (LPA_Percentage(_), None) | (LPA_Auto, _) => Auto,
             (LPA_Length(length), _) => Specified(length),
         };
-        let max_height = match (style.get_box().max_height, block_container_height) {
+        let max_height = match (style.max_block_size(), block_container_height) {
             (LPN_Percentage(percent), Some(block_container_height)) => {
                 Some(block_container_height.scale_by(percent))
             }
             (LPN_Percentage(_), None) | (LPN_None, _) => None,
             (LPN_Length(length), _) => Some(length),
         };
-        let min_height = match (style.get_box().min_height, block_container_height) {
+        let min_height = match (style.min_block_size(), block_container_height) {
             (LP_Percentage(percent), Some(block_container_height)) => {
                 block_container_height.scale_by(percent)
             }
@@ -447,6 +462,8 @@ enum BlockType {
     AbsoluteNonReplacedType,
     FloatReplacedType,
     FloatNonReplacedType,
+    InlineBlockNonReplacedType,
+    FlexItemType,
 }
 
 #[deriving(Clone, PartialEq)]
@@ -505,6 +522,15 @@ pub struct BlockFlow {
     /// block formatting contexts.
     previous_float_width: Option<Au>,
 
+    /// The border box width that the flex container has assigned to this block, if this block is
+    /// a flex item. This overrides the usual width computation.
+    pub flex_item_width: Option<Au>,
+
+    /// The column rules to paint, relative to the border box of this block, if this block is a
+    /// multi-column container. These are computed by the `MulticolFlow` during height
+    /// assignment.
+    pub column_rules: Vec<LogicalRect<Au>>,
+
     /// Additional floating flow members.
     pub float: Option<Box<FloatedBlockInfo>>
 }
@@ -517,6 +543,8 @@ impl BlockFlow {
             is_root: false,
             static_y_offset: Au::new(0),
             previous_float_width: None,
+            flex_item_width: None,
+            column_rules: Vec::new(),
             float: None
         }
     }
@@ -528,6 +556,8 @@ impl BlockFlow {
             is_root: false,
             static_y_offset: Au::new(0),
             previous_float_width: None,
+            flex_item_width: None,
+            column_rules: Vec::new(),
             float: None
         }
     }
@@ -542,6 +572,8 @@ impl BlockFlow {
             is_root: false,
             static_y_offset: Au::new(0),
             previous_float_width: None,
+            flex_item_width: None,
+            column_rules: Vec::new(),
             float: Some(box FloatedBlockInfo::new(float_kind))
         }
     }
@@ -557,12 +589,16 @@ impl BlockFlow {
             } else {
                 AbsoluteNonReplacedType
             }
+        } else if self.is_flex_item() {
+            FlexItemType
         } else if self.is_float() {
             if self.is_replaced_content() {
                 FloatReplacedType
             } else {
                 FloatNonReplacedType
             }
+        } else if self.is_inline_block() {
+            InlineBlockNonReplacedType
         } else {
             if self.is_replaced_content() {
                 BlockReplacedType
@@ -572,8 +608,36 @@ impl BlockFlow {
         }
     }
 
+    /// Returns true if this block is laid out by a flex container.
+    pub fn is_flex_item(&self) -> bool {
+        self.flex_item_width.is_some()
+    }
+
+    /// Returns true if this block lays out the contents of an inline block or of an inline-level
+    /// flex container.
+    pub fn is_inline_block(&self) -> bool {
+        match self.fragment.style().get_box().display {
+            display::inline_block | display::inline_flex => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if this block scrolls its contents within its padding box: that is, if it has
+    /// `overflow: scroll` or `overflow: auto` and is in the normal flow.
+    ///
+    /// FIXME(#2003): The root, floats, inline blocks, and absolutely positioned and sticky blocks
+    /// only clip their contents.
+    pub fn is_scroll_container(&self) -> bool {
+        match self.fragment.style().get_box().overflow {
+            overflow::scroll | overflow::auto => {}
+            overflow::visible | overflow::hidden => return false,
+        }
+        !self.is_root() && !self.is_float() && !self.is_inline_block() &&
+            !self.is_absolutely_positioned() && !self.is_sticky()
+    }
+
     /// Compute the used value of width for this Block.
-    fn compute_used_width(&mut self, ctx: &mut LayoutContext, containing_block_width: Au) {
+    pub fn compute_used_width(&mut self, ctx: &mut LayoutContext, containing_block_width: Au) {
         let block_type = self.block_type();
         match block_type {
             AbsoluteReplacedType => {
@@ -592,6 +656,10 @@ impl BlockFlow {
                 let width_computer = FloatNonReplaced;
                 width_computer.compute_used_width(self, ctx, containing_block_width);
             }
+            InlineBlockNonReplacedType => {
+                let width_computer = InlineBlockNonReplaced;
+                width_computer.compute_used_width(self, ctx, containing_block_width);
+            }
             BlockReplacedType => {
                 let width_computer = BlockReplaced;
                 width_computer.compute_used_width(self, ctx, containing_block_width);
@@ -600,6 +668,10 @@ impl BlockFlow {
                 let width_computer = BlockNonReplaced;
                 width_computer.compute_used_width(self, ctx, containing_block_width);
             }
+            FlexItemType => {
+                let width_computer = FlexItem;
+                width_computer.compute_used_width(self, ctx, containing_block_width);
+            }
         }
     }
 
@@ -624,16 +696,33 @@ impl BlockFlow {
     /// Note: Assume this is called in a top-down traversal, so it is ok to
     /// reference the CB.
     #[inline]
-    pub fn containing_block_size(&mut self, viewport_size: Size2D<Au>) -> Size2D<Au> {
+    pub fn containing_block_size(&mut self, viewport_size: Size2D<Au>) -> LogicalSize<Au> {
         assert!(self.is_absolutely_positioned());
         if self.is_fixed() {
             // Initial containing block is the CB for the root
-            viewport_size
+            LogicalSize::from_physical(self.base.writing_mode, viewport_size)
         } else {
             self.base.absolute_cb.generated_containing_block_rect().size
         }
     }
 
+    /// Returns the logical rectangle, relative to the parent flow, within which the fragment and
+    /// children of this flow are positioned. For floats, this is the margin box at the position
+    /// the float was placed at; for other blocks, it is just `base.position`.
+    pub fn logical_frame(&self) -> LogicalRect<Au> {
+        match self.float {
+            None => self.base.position,
+            Some(ref float) => {
+                LogicalRect::new(self.base.position.start.i + float.rel_pos.x,
+                                 self.base.position.start.b + float.rel_pos.y,
+                                 self.fragment.border_box.size.inline +
+                                    self.fragment.margin.inline_start_end(),
+                                 self.fragment.border_box.size.block +
+                                    self.fragment.margin.block_start_end())
+            }
+        }
+    }
+
     /// Traverse the Absolute flow tree in preorder.
     ///
     /// Traverse all your direct absolute descendants, who will then traverse
@@ -654,7 +743,7 @@ impl BlockFlow {
             return false
         }
 
-        let cb_top_edge_offset = flow.generated_containing_block_rect().origin.y;
+        let cb_top_edge_offset = flow.generated_containing_block_rect().start.b;
         let mut descendant_offset_iter = mut_base(flow).abs_descendants.iter_with_offset();
         // Pass in the respective static y offset for each descendant.
         for (ref mut descendant_link, ref y_offset) in descendant_offset_iter {
@@ -693,11 +782,11 @@ impl BlockFlow {
 
     /// Return true if this has a replaced fragment.
     ///
-    /// The only two types of replaced fragments currently are text fragments
-    /// and image fragments.
+    /// The only types of replaced fragments currently are text fragments, image fragments and
+    /// form control fragments.
     fn is_replaced_content(&self) -> bool {
         match self.fragment.specific {
-            ScannedTextFragment(_) | ImageFragment(_) => true,
+            ScannedTextFragment(_) | ImageFragment(_) | FormControlFragment(_) => true,
             _ => false,
         }
     }
@@ -746,7 +835,7 @@ impl BlockFlow {
                 // Consume all the static y-offsets bubbled up by kid.
                 for y_offset in offsets.move_iter() {
                     // The offsets are wrt the kid flow box. Translate them to current flow.
-                    abs_descendant_y_offsets.push(y_offset + kid_base.position.origin.y);
+                    abs_descendant_y_offsets.push(y_offset + kid_base.position.start.b);
                 }
             }
         }
@@ -775,13 +864,14 @@ impl BlockFlow {
         if top_margin_value != Au(0) {
             for kid in self.base.child_iter() {
                 let kid_base = flow::mut_base(kid);
-                kid_base.position.origin.y = kid_base.position.origin.y + top_margin_value
+                kid_base.position.start.b = kid_base.position.start.b + top_margin_value
             }
         }
 
-        self.base.position.size.height = self.base.position.size.height + top_margin_value +
+        self.base.position.size.block = self.base.position.size.block + top_margin_value +
             bottom_margin_value;
-        self.fragment.border_box.size.height = self.fragment.border_box.size.height + top_margin_value +
+        self.fragment.border_box.size.block = self.fragment.border_box.size.block +
+            top_margin_value +
             bottom_margin_value;
     }
 
@@ -815,16 +905,16 @@ impl BlockFlow {
         }
 
         let mut margin_collapse_info = MarginCollapseInfo::new();
-        self.base.floats.translate(Point2D(-self.fragment.left_offset(), Au(0)));
+        self.base.floats.translate(Point2D(-self.fragment.inline_start_offset(), Au(0)));
 
         // The sum of our top border and top padding.
-        let top_offset = self.fragment.border_padding.top;
+        let top_offset = self.fragment.border_padding.block_start;
         translate_including_floats(&mut cur_y, top_offset, &mut self.base.floats);
 
         let can_collapse_top_margin_with_kids =
             margins_may_collapse == MarginsMayCollapse &&
             !self.is_absolutely_positioned() &&
-            self.fragment.border_padding.top == Au(0);
+            self.fragment.border_padding.block_start == Au(0);
         margin_collapse_info.initialize_top_margin(&self.fragment,
                                                    can_collapse_top_margin_with_kids);
 
@@ -835,7 +925,7 @@ impl BlockFlow {
             if kid.is_absolutely_positioned() {
                 // Assume that the *hypothetical box* for an absolute flow starts immediately after
                 // the bottom border edge of the previous flow.
-                flow::mut_base(kid).position.origin.y = cur_y;
+                flow::mut_base(kid).position.start.b = cur_y;
                 kid.assign_height_for_inorder_child_if_necessary(layout_context);
                 propagate_layer_flag_from_child(&mut layers_needed_for_descendants, kid);
 
@@ -847,9 +937,9 @@ impl BlockFlow {
             // Assign height now for the child if it was impacted by floats and we couldn't before.
             flow::mut_base(kid).floats = floats.clone();
             if kid.is_float() {
-                // FIXME(pcwalton): Using `position.origin.y` to mean the float ceiling is a
+                // FIXME(pcwalton): Using `position.start.b` to mean the float ceiling is a
                 // bit of a hack.
-                flow::mut_base(kid).position.origin.y =
+                flow::mut_base(kid).position.start.b =
                     margin_collapse_info.current_float_ceiling();
                 propagate_layer_flag_from_child(&mut layers_needed_for_descendants, kid);
 
@@ -858,7 +948,7 @@ impl BlockFlow {
                 assert!(kid_was_impacted_by_floats);    // As it was a float itself...
 
                 let kid_base = flow::mut_base(kid);
-                kid_base.position.origin.y = cur_y;
+                kid_base.position.start.b = cur_y;
                 floats = kid_base.floats.clone();
                 continue
             }
@@ -897,7 +987,7 @@ impl BlockFlow {
             cur_y = cur_y + clearance;
 
             // At this point, `cur_y` is at the border edge of the child.
-            flow::mut_base(kid).position.origin.y = cur_y;
+            flow::mut_base(kid).position.start.b = cur_y;
 
             // Now pull out the child's outgoing floats. We didn't do this immediately after the
             // `assign_height_for_inorder_child_if_necessary` call because clearance on a block
@@ -909,7 +999,7 @@ impl BlockFlow {
             // Move past the child's border box. Do not use the `translate_including_floats`
             // function here because the child has already translated floats past its border box.
             let kid_base = flow::mut_base(kid);
-            cur_y = cur_y + kid_base.position.size.height;
+            cur_y = cur_y + kid_base.position.size.block;
 
             // Handle any (possibly collapsed) bottom margin.
             let delta = margin_collapse_info.advance_bottom_margin(&kid_base.collapsible_margins);
@@ -920,6 +1010,18 @@ impl BlockFlow {
         // (CSS 2.1, Appendix E).
         self.base.flags.set_layers_needed_for_descendants(layers_needed_for_descendants);
 
+        // Sticky flows get layers, so that the compositor can move them as the page scrolls.
+        if self.is_sticky() {
+            self.base.flags.set_needs_layer(true)
+        }
+
+        // So do scroll containers, so that the compositor can scroll their contents. Anything
+        // positioned that follows must then be layerized too in order to paint above them.
+        if self.is_scroll_container() {
+            self.base.flags.set_needs_layer(true);
+            self.base.flags.set_layers_needed_for_descendants(true)
+        }
+
         // Collect various offsets needed by absolutely positioned descendants.
         self.collect_static_y_offsets_from_kids();
 
@@ -927,7 +1029,7 @@ impl BlockFlow {
         let can_collapse_bottom_margin_with_kids =
             margins_may_collapse == MarginsMayCollapse &&
             !self.is_absolutely_positioned() &&
-            self.fragment.border_padding.bottom == Au(0);
+            self.fragment.border_padding.block_end == Au(0);
         let (collapsible_margins, delta) =
             margin_collapse_info.finish_and_compute_collapsible_margins(
             &self.fragment,
@@ -935,13 +1037,12 @@ impl BlockFlow {
         self.base.collapsible_margins = collapsible_margins;
         translate_including_floats(&mut cur_y, delta, &mut floats);
 
-        // FIXME(#2003, pcwalton): The max is taken here so that you can scroll the page, but this
-        // is not correct behavior according to CSS 2.1 § 10.5. Instead I think we should treat the
-        // root element as having `overflow: scroll` and use the layers-based scrolling
-        // infrastructure to make it scrollable.
         let mut height = cur_y - top_offset;
-        if self.is_root() {
-            height = Au::max(layout_context.screen_size.height, height)
+
+        // Like absolutely positioned boxes, inline blocks grow to contain their floats. See CSS
+        // 2.1 § 10.6.7.
+        if self.is_inline_block() {
+            height = height + floats.clearance(ClearBoth);
         }
 
         if self.is_absolutely_positioned() {
@@ -956,12 +1057,13 @@ impl BlockFlow {
 
             // Store the content height for use in calculating the absolute flow's dimensions
             // later.
-            self.fragment.border_box.size.height = height;
+            self.fragment.border_box.size.block = height;
             return
         }
 
-        let mut candidate_height_iterator = CandidateHeightIterator::new(self.fragment.style(),
-                                                                         None);
+        let mut candidate_height_iterator =
+            CandidateHeightIterator::new(self.fragment.style(),
+                                         self.root_containing_block_height(layout_context));
         for (candidate_height, new_candidate_height) in candidate_height_iterator {
             *new_candidate_height = match candidate_height {
                 Auto => height,
@@ -975,14 +1077,14 @@ impl BlockFlow {
         translate_including_floats(&mut cur_y, delta, &mut floats);
 
         // Compute content height and noncontent height.
-        let bottom_offset = self.fragment.border_padding.bottom;
+        let bottom_offset = self.fragment.border_padding.block_end;
         translate_including_floats(&mut cur_y, bottom_offset, &mut floats);
 
         // Now that `cur_y` is at the bottom of the border box, compute the final border box
         // position.
-        self.fragment.border_box.size.height = cur_y;
-        self.fragment.border_box.origin.y = Au(0);
-        self.base.position.size.height = cur_y;
+        self.fragment.border_box.size.block = cur_y;
+        self.fragment.border_box.start.b = Au(0);
+        self.base.position.size.block = cur_y;
 
         self.base.floats = floats.clone();
         self.adjust_fragments_for_collapsed_margins_if_root();
@@ -1000,6 +1102,190 @@ impl BlockFlow {
         }
     }
 
+    /// Returns the distance from the top of the border box of this block to the baseline of the
+    /// last line box in its normal flow, if there is one.
+    pub fn baseline_offset_of_last_line_box(&mut self) -> Option<Au> {
+        let mut baseline_offset = None;
+        for kid in self.base.child_iter() {
+            if kid.is_absolutely_positioned() || kid.is_float() {
+                continue
+            }
+            let kid_baseline_offset = if kid.is_inline_flow() {
+                kid.as_inline().baseline_offset_of_last_line
+            } else if kid.is_block_flow() {
+                kid.as_block().baseline_offset_of_last_line_box()
+            } else {
+                None
+            };
+            match kid_baseline_offset {
+                Some(kid_baseline_offset) => {
+                    baseline_offset = Some(flow::base(kid).position.start.b + kid_baseline_offset)
+                }
+                None => {}
+            }
+        }
+        baseline_offset
+    }
+
+    /// Returns the distance from the top of the border box of this block to the baseline of the
+    /// first line box in its normal flow, if there is one.
+    pub fn baseline_offset_of_first_line_box(&mut self) -> Option<Au> {
+        for kid in self.base.child_iter() {
+            if kid.is_absolutely_positioned() || kid.is_float() {
+                continue
+            }
+            let kid_baseline_offset = if kid.is_inline_flow() {
+                kid.as_inline().baseline_offset_of_first_line
+            } else if kid.is_block_flow() {
+                kid.as_block().baseline_offset_of_first_line_box()
+            } else {
+                None
+            };
+            match kid_baseline_offset {
+                Some(kid_baseline_offset) => {
+                    return Some(flow::base(kid).position.start.b + kid_baseline_offset)
+                }
+                None => {}
+            }
+        }
+        None
+    }
+
+    /// Returns the used height of the content box of this block for the given height of its
+    /// contents, taking `height`, `min-height` and `max-height` into account.
+    pub fn used_content_height(&self, layout_context: &LayoutContext, content_height: Au) -> Au {
+        let mut candidate_height_iterator =
+            CandidateHeightIterator::new(self.fragment.style(),
+                                         self.root_containing_block_height(layout_context));
+        for (candidate_height, new_candidate_height) in candidate_height_iterator {
+            *new_candidate_height = match candidate_height {
+                Auto => content_height,
+                Specified(value) => value
+            }
+        }
+        candidate_height_iterator.candidate_value
+    }
+
+    /// Returns the height of the initial containing block if this is the root flow, or `None`
+    /// otherwise. Percentage heights on the root element resolve against the viewport (CSS 2.1 §
+    /// 10.1); the page itself becomes scrollable through the overflow of the root, not its height.
+    fn root_containing_block_height(&self, layout_context: &LayoutContext) -> Option<Au> {
+        if !self.is_root() {
+            return None
+        }
+        let screen_size = LogicalSize::from_physical(self.base.writing_mode,
+                                                     layout_context.screen_size);
+        Some(screen_size.block)
+    }
+
+    /// Assigns the height of a block whose in-flow children have already been positioned by a
+    /// layout algorithm other than block layout, such as flex layout. The children are assumed
+    /// to be positioned relative to the top border edge, and `content_height` is the height that
+    /// they occupy. Margins never collapse through such a block.
+    pub fn assign_height_for_positioned_children(&mut self,
+                                                 layout_context: &mut LayoutContext,
+                                                 content_height: Au) {
+        // Mark ourselves for layerization if that will be necessary to paint in the proper order
+        // (CSS 2.1, Appendix E).
+        let mut layers_needed_for_descendants = false;
+        for kid in self.base.child_iter() {
+            propagate_layer_flag_from_child(&mut layers_needed_for_descendants, kid);
+        }
+        self.base.flags.set_layers_needed_for_descendants(layers_needed_for_descendants);
+
+        // Collect various offsets needed by absolutely positioned descendants.
+        self.collect_static_y_offsets_from_kids();
+
+        let mut margin_collapse_info = MarginCollapseInfo::new();
+        margin_collapse_info.initialize_top_margin(&self.fragment, false);
+        let (collapsible_margins, _) =
+            margin_collapse_info.finish_and_compute_collapsible_margins(&self.fragment, false);
+        self.base.collapsible_margins = collapsible_margins;
+
+        if self.is_absolutely_positioned() {
+            // Fixed position layers get layers.
+            if self.is_fixed() {
+                self.base.flags.set_needs_layer(true)
+            }
+
+            // Store the content height for use in calculating the absolute flow's dimensions
+            // later.
+            self.fragment.border_box.size.block = content_height;
+            return
+        }
+
+        let border_box_height = self.used_content_height(layout_context, content_height) +
+            self.fragment.border_padding.block_start_end();
+
+        if self.is_float() {
+            // Floats are laid out relative to their top margin edge. See `assign_height_float`.
+            let top_margin = self.fragment.margin.block_start;
+            for kid in self.base.child_iter() {
+                let kid_base = flow::mut_base(kid);
+                kid_base.position.start.b = kid_base.position.start.b + top_margin
+            }
+            self.fragment.border_box.start.b = top_margin;
+            self.fragment.border_box.size.block = border_box_height;
+            return
+        }
+
+        self.fragment.border_box.size.block = border_box_height;
+        self.fragment.border_box.start.b = Au(0);
+        self.base.position.size.block = border_box_height;
+
+        // This block is a formatting context, so no floats escape it.
+        self.base.floats = Floats::new();
+        self.adjust_fragments_for_collapsed_margins_if_root();
+
+        if self.is_root_of_absolute_flow_tree() {
+            // Assign heights for all flows in this Absolute flow tree.
+            self.traverse_preorder_absolute_flows(&mut AbsoluteAssignHeightsTraversal(
+                    layout_context));
+            // Store overflow for all absolute descendants.
+            self.traverse_postorder_absolute_flows(&mut AbsoluteStoreOverflowTraversal {
+                layout_context: layout_context,
+            });
+        }
+    }
+
+    /// Returns the extents along the block axis, relative to the top border edge of this block,
+    /// of the pieces of its in-flow content that are never split between fragmentainers (column
+    /// boxes or pages), in document order. Heights must already have been assigned.
+    ///
+    /// Line boxes are unbreakable. Block containers are entered so that their content can be
+    /// split; any other flow is a single unbreakable piece.
+    pub fn unbreakable_extents(&mut self) -> Vec<(Au, Au)> {
+        self.unbreakable_pieces().iter().map(|piece| (piece.start, piece.end)).collect()
+    }
+
+    /// Returns the same pieces of content as `unbreakable_extents`, along with whether
+    /// `page-break-before`, `page-break-after`, `page-break-inside`, `orphans` and `widows`
+    /// allow, avoid or force a page break before each of them.
+    pub fn unbreakable_pieces(&mut self) -> Vec<UnbreakablePiece> {
+        let mut collector = UnbreakablePieceCollector {
+            pieces: Vec::new(),
+            pending_break: AllowedPageBreak,
+        };
+        collect_unbreakable_pieces(self, Au(0), false, false, &mut collector);
+        collector.pieces
+    }
+
+    /// Fragments the in-flow content of this block, which has been laid out as if into a single
+    /// fragmentainer of unlimited block size, by moving each unbreakable piece of it into the
+    /// fragmentainer that `breaks` assigns it to. `breaks` holds the position at which each
+    /// fragmentainer starts, as returned by `fragmentainer_breaks`, and the fragmentainer at
+    /// index `n` is `n` times `fragmentainer_offset` away from the first one. The overflow of
+    /// every flow whose content moved is recomputed.
+    ///
+    /// FIXME: The borders and backgrounds of the block flows that are entered aren't split, and
+    /// floats and the static positions of absolutely positioned descendants don't move.
+    pub fn move_content_into_fragmentainers(&mut self,
+                                            layout_context: &mut LayoutContext,
+                                            breaks: &[Au],
+                                            fragmentainer_offset: LogicalSize<Au>) {
+        move_unbreakable_pieces(self, layout_context, Au(0), breaks, fragmentainer_offset)
+    }
+
     /// Add placement information about current float flow for use by the parent.
     ///
     /// Also, use information given by parent about other floats to find out our relative position.
@@ -1011,18 +1297,18 @@ impl BlockFlow {
     /// already called on this kid flow by the traversal function. So, the values used are
     /// well-defined.
     pub fn place_float(&mut self) {
-        let height = self.fragment.border_box.size.height;
+        let height = self.fragment.border_box.size.block;
         let clearance = match self.fragment.clear() {
             None => Au(0),
             Some(clear) => self.base.floats.clearance(clear),
         };
 
-        let margin_height = self.fragment.margin.vertical();
+        let margin_height = self.fragment.margin.block_start_end();
         let info = PlacementInfo {
-            size: Size2D(self.base.position.size.width + self.fragment.margin.horizontal() +
-                         self.fragment.border_padding.horizontal(),
+            size: Size2D(self.base.position.size.inline + self.fragment.margin.inline_start_end() +
+                         self.fragment.border_padding.inline_start_end(),
                          height + margin_height),
-            ceiling: clearance + self.base.position.origin.y,
+            ceiling: clearance + self.base.position.start.b,
             max_width: self.float.get_ref().containing_width,
             kind: self.float.get_ref().float_kind,
         };
@@ -1053,22 +1339,23 @@ impl BlockFlow {
         // Floats establish a block formatting context, so we discard the output floats here.
         drop(floats);
 
-        let top_offset = self.fragment.margin.top + self.fragment.border_padding.top;
+        let top_offset = self.fragment.margin.block_start +
+            self.fragment.border_padding.block_start;
         let mut cur_y = top_offset;
 
         // cur_y is now at the top content edge
 
         for kid in self.base.child_iter() {
             let child_base = flow::mut_base(kid);
-            child_base.position.origin.y = cur_y;
+            child_base.position.start.b = cur_y;
             // cur_y is now at the bottom margin edge of kid
-            cur_y = cur_y + child_base.position.size.height;
+            cur_y = cur_y + child_base.position.size.block;
         }
 
         let content_height = cur_y - top_offset;
 
         // The associated fragment has the border box of this flow.
-        self.fragment.border_box.origin.y = self.fragment.margin.top;
+        self.fragment.border_box.start.b = self.fragment.margin.block_start;
 
         // Calculate content height, taking `min-height` and `max-height` into account.
         let mut candidate_height_iterator = CandidateHeightIterator::new(self.fragment.style(), None);
@@ -1080,14 +1367,13 @@ impl BlockFlow {
         }
 
         let content_height = candidate_height_iterator.candidate_value;
-        let noncontent_height = self.fragment.border_padding.vertical();
+        let noncontent_height = self.fragment.border_padding.block_start_end();
         debug!("assign_height_float -- height: {}", content_height + noncontent_height);
-        self.fragment.border_box.size.height = content_height + noncontent_height;
+        self.fragment.border_box.size.block = content_height + noncontent_height;
     }
 
     fn build_display_list_block_common(&mut self,
                                        layout_context: &LayoutContext,
-                                       offset: Point2D<Au>,
                                        background_border_level: BackgroundAndBorderLevel) {
         let rel_offset =
             self.fragment.relative_position(&self.base
@@ -1097,13 +1383,27 @@ impl BlockFlow {
 
         // Add the box that starts the block context.
         let mut display_list = DisplayList::new();
+        let flow_origin = self.base.abs_position + rel_offset;
+        let flow_size = self.logical_frame().size.to_physical(self.base.writing_mode);
         let mut accumulator =
             self.fragment.build_display_list(&mut display_list,
                                              layout_context,
-                                             self.base.abs_position + rel_offset + offset,
+                                             flow_origin,
+                                             flow_size,
                                              background_border_level,
                                              None);
 
+        // Column rules are painted along with the borders of the multi-column container.
+        if !self.column_rules.is_empty() {
+            let level = StackingLevel::from_background_and_border_level(background_border_level);
+            self.fragment.build_display_list_for_column_rules(&mut display_list,
+                                                              layout_context,
+                                                              flow_origin,
+                                                              flow_size,
+                                                              self.column_rules.as_slice(),
+                                                              level);
+        }
+
         let mut child_layers = DList::new();
         for kid in self.base.child_iter() {
             if kid.is_absolutely_positioned() {
@@ -1138,16 +1438,17 @@ impl BlockFlow {
             self.build_display_list_float(layout_context)
         } else if self.is_absolutely_positioned() {
             self.build_display_list_abs(layout_context)
+        } else if self.is_sticky() {
+            self.build_display_list_sticky(layout_context)
+        } else if self.is_scroll_container() {
+            self.build_display_list_scroll_container(layout_context)
         } else {
-            self.build_display_list_block_common(layout_context, Zero::zero(), BlockLevel)
+            self.build_display_list_block_common(layout_context, BlockLevel)
         }
     }
 
     pub fn build_display_list_float(&mut self, layout_context: &LayoutContext) {
-        let float_offset = self.float.get_ref().rel_pos;
-        self.build_display_list_block_common(layout_context,
-                                             float_offset,
-                                             RootOfStackingContextLevel);
+        self.build_display_list_block_common(layout_context, RootOfStackingContextLevel);
         self.base.display_list = mem::replace(&mut self.base.display_list,
                                               DisplayList::new()).flatten(FloatStackingLevel)
     }
@@ -1160,34 +1461,35 @@ impl BlockFlow {
     /// + y-coordinate of the flow wrt its Containing Block.
     /// + height, vertical margins, and y-coordinate for the flow's box.
     fn calculate_abs_height_and_margins(&mut self, ctx: &LayoutContext) {
-        let containing_block_height = self.containing_block_size(ctx.screen_size).height;
+        let containing_block_height = self.containing_block_size(ctx.screen_size).block;
         let static_y_offset = self.static_y_offset;
 
         // This is the stored content height value from assign-height
-        let content_height = self.fragment.content_box().size.height;
+        let content_height = self.fragment.content_box().size.block;
 
         let mut solution = None;
         {
             // Non-auto margin-top and margin-bottom values have already been
             // calculated during assign-width.
-            let margin_top = match self.fragment.style().get_margin().margin_top {
+            let margin_top = match self.fragment.style().logical_margin().block_start {
                 LPA_Auto => Auto,
-                _ => Specified(self.fragment.margin.top)
+                _ => Specified(self.fragment.margin.block_start)
             };
-            let margin_bottom = match self.fragment.style().get_margin().margin_bottom {
+            let margin_bottom = match self.fragment.style().logical_margin().block_end {
                 LPA_Auto => Auto,
-                _ => Specified(self.fragment.margin.bottom)
+                _ => Specified(self.fragment.margin.block_end)
             };
 
             let top;
             let bottom;
             {
-                let position_style = self.fragment.style().get_positionoffsets();
-                top = MaybeAuto::from_style(position_style.top, containing_block_height);
-                bottom = MaybeAuto::from_style(position_style.bottom, containing_block_height);
+                let position_style = self.fragment.style().logical_position();
+                top = MaybeAuto::from_style(position_style.block_start, containing_block_height);
+                bottom = MaybeAuto::from_style(position_style.block_end, containing_block_height);
             }
 
-            let available_height = containing_block_height - self.fragment.border_padding.vertical();
+            let available_height = containing_block_height -
+                self.fragment.border_padding.block_start_end();
             if self.is_replaced_content() {
                 // Calculate used value of height just like we do for inline replaced elements.
                 // TODO: Pass in the containing block height when Fragment's
@@ -1196,7 +1498,7 @@ impl BlockFlow {
                 // TODO: Right now, this content height value includes the
                 // margin because of erroneous height calculation in fragment.
                 // Check this when that has been fixed.
-                let height_used_val = self.fragment.border_box.size.height;
+                let height_used_val = self.fragment.border_box.size.block;
                 solution = Some(HeightConstraintSolution::solve_vertical_constraints_abs_replaced(
                         height_used_val,
                         margin_top,
@@ -1229,20 +1531,20 @@ impl BlockFlow {
         }
 
         let solution = solution.unwrap();
-        self.fragment.margin.top = solution.margin_top;
-        self.fragment.margin.bottom = solution.margin_bottom;
-        self.fragment.border_box.origin.y = Au(0);
-        self.fragment.border_box.size.height = solution.height + self.fragment.border_padding.vertical();
+        self.fragment.margin.block_start = solution.margin_top;
+        self.fragment.margin.block_end = solution.margin_bottom;
+        self.fragment.border_box.start.b = Au(0);
+        self.fragment.border_box.size.block = solution.height +
+            self.fragment.border_padding.block_start_end();
 
-        self.base.position.origin.y = solution.top + self.fragment.margin.top;
-        self.base.position.size.height = solution.height + self.fragment.border_padding.vertical();
+        self.base.position.start.b = solution.top + self.fragment.margin.block_start;
+        self.base.position.size.block = solution.height +
+            self.fragment.border_padding.block_start_end();
     }
 
     /// Add display items for Absolutely Positioned flow.
     fn build_display_list_abs(&mut self, layout_context: &LayoutContext) {
-        self.build_display_list_block_common(layout_context,
-                                             Zero::zero(),
-                                             RootOfStackingContextLevel);
+        self.build_display_list_block_common(layout_context, RootOfStackingContextLevel);
 
         if !self.base.absolute_position_info.layers_needed_for_positioned_flows &&
                 !self.base.flags.needs_layer() {
@@ -1256,7 +1558,7 @@ impl BlockFlow {
         }
 
         // If we got here, then we need a new layer.
-        let layer_rect = self.base.position.union(&self.base.overflow);
+        let layer_rect = self.base.overflow.translate(&self.base.abs_position);
         let size = Size2D(layer_rect.size.width.to_nearest_px() as uint,
                           layer_rect.size.height.to_nearest_px() as uint);
         let origin = Point2D(layer_rect.origin.x.to_nearest_px() as uint,
@@ -1273,10 +1575,211 @@ impl BlockFlow {
             position: Rect(origin, size),
             background_color: color::rgba(255.0, 255.0, 255.0, 0.0),
             scroll_policy: scroll_policy,
+            content_size: None,
         };
         self.base.layers.push_back(new_layer)
     }
 
+    /// Add display items for a sticky flow, which always gets its own layer. The compositor moves
+    /// the layer within the constraint computed here whenever the page scrolls.
+    ///
+    /// FIXME(#2003): Only the viewport scrolls, so the viewport is assumed to be the nearest
+    /// scrolling ancestor. Hit testing also doesn't know how far the compositor moved the layer.
+    fn build_display_list_sticky(&mut self, layout_context: &LayoutContext) {
+        self.build_display_list_block_common(layout_context, RootOfStackingContextLevel);
+
+        let writing_mode = self.base.writing_mode;
+        let flow_size = self.logical_frame().size.to_physical(writing_mode);
+        let border_box = self.fragment.border_box.to_physical(writing_mode, flow_size)
+                                                 .translate(&self.base.abs_position);
+        let viewport_size = self.base.absolute_position_info.viewport_size;
+        let offsets = self.fragment.style().get_positionoffsets();
+        let inset = |value: LengthOrPercentageOrAuto, viewport_length: Au| -> Option<f32> {
+            match MaybeAuto::from_style(value, viewport_length) {
+                Auto => None,
+                Specified(length) => Some(geometry::to_frac_px(length) as f32),
+            }
+        };
+        let constraint = StickyConstraint {
+            rect: geometry::au_rect_to_f32_rect(border_box),
+            containing_block: geometry::au_rect_to_f32_rect(
+                self.base.absolute_position_info.sticky_containing_block),
+            viewport_size: Size2D(geometry::to_frac_px(viewport_size.width) as f32,
+                                  geometry::to_frac_px(viewport_size.height) as f32),
+            top: inset(offsets.top, viewport_size.height),
+            right: inset(offsets.right, viewport_size.width),
+            bottom: inset(offsets.bottom, viewport_size.height),
+            left: inset(offsets.left, viewport_size.width),
+        };
+
+        let layer_rect = self.base.overflow.translate(&self.base.abs_position);
+        let size = Size2D(layer_rect.size.width.to_nearest_px() as uint,
+                          layer_rect.size.height.to_nearest_px() as uint);
+        let origin = Point2D(layer_rect.origin.x.to_nearest_px() as uint,
+                             layer_rect.origin.y.to_nearest_px() as uint);
+        let display_list = mem::replace(&mut self.base.display_list, DisplayList::new());
+        let new_layer = RenderLayer {
+            id: self.layer_id(0),
+            display_list: Arc::new(display_list.flatten(ContentStackingLevel)),
+            position: Rect(origin, size),
+            background_color: color::rgba(255.0, 255.0, 255.0, 0.0),
+            scroll_policy: StickyPosition(constraint),
+            content_size: None,
+        };
+        self.base.layers.push_back(new_layer)
+    }
+
+    /// Add display items for a scroll container. Its background and borders stay in the parent
+    /// layer, together with its scrollbars, while its contents go into a layer of their own that
+    /// the compositor scrolls. The layer covers the padding box, less the scrollbars.
+    ///
+    /// Scrollbars are painted over the edge of the padding box rather than taking space from the
+    /// contents. The thumb is sized to the visible part of the contents, and goes into a layer
+    /// that the compositor moves along the track as it scrolls the contents.
+    ///
+    /// FIXME(#2003): Layers inside the contents don't scroll along with them.
+    fn build_display_list_scroll_container(&mut self, layout_context: &LayoutContext) {
+        self.build_display_list_block_common(layout_context, BlockLevel);
+
+        // The clipped contents are the last item in the display list.
+        let contents = match self.base.display_list.list.pop_back() {
+            Some(ClipDisplayItemClass(clip_display_item)) => clip_display_item.children,
+            _ => fail!("scroll container didn't clip its contents"),
+        };
+
+        let writing_mode = self.base.writing_mode;
+        let flow_size = self.logical_frame().size.to_physical(writing_mode);
+        let rel_offset =
+            self.fragment.relative_position(&self.base
+                                             .absolute_position_info
+                                             .relative_containing_block_size,
+                                        None);
+        let border_box = self.fragment
+                             .border_box
+                             .to_physical(writing_mode, flow_size)
+                             .translate(&(self.base.abs_position + rel_offset));
+        let border = model::border_from_style(self.fragment.style());
+        let padding_box = Rect(Point2D(border_box.origin.x + border.left,
+                                       border_box.origin.y + border.top),
+                               Size2D(border_box.size.width - border.left - border.right,
+                                      border_box.size.height - border.top - border.bottom));
+
+        // The contents can be scrolled to their right and bottom edges, but not past the top left
+        // corner of the padding box.
+        let mut contents_rect = padding_box;
+        for item in contents.iter() {
+            contents_rect = contents_rect.union(&item.bounds())
+        }
+        let contents_size = Size2D(contents_rect.origin.x + contents_rect.size.width -
+                                       padding_box.origin.x,
+                                   contents_rect.origin.y + contents_rect.size.height -
+                                       padding_box.origin.y);
+
+        // `overflow: scroll` always shows both scrollbars; `overflow: auto` shows them only if
+        // the contents overflow along that axis.
+        let (show_horizontal_scrollbar, show_vertical_scrollbar) =
+            match self.fragment.style().get_box().overflow {
+                overflow::scroll => (true, true),
+                _ => (contents_size.width > padding_box.size.width,
+                      contents_size.height > padding_box.size.height),
+            };
+        let scrollbar_width = Au::min(Au::from_px(SCROLLBAR_WIDTH),
+                                      Au::min(padding_box.size.width, padding_box.size.height));
+        let horizontal_scrollbar_height = if show_horizontal_scrollbar {
+            scrollbar_width
+        } else {
+            Au(0)
+        };
+        let vertical_scrollbar_width = if show_vertical_scrollbar {
+            scrollbar_width
+        } else {
+            Au(0)
+        };
+        let visible_rect = Rect(padding_box.origin,
+                                Size2D(padding_box.size.width - vertical_scrollbar_width,
+                                       padding_box.size.height - horizontal_scrollbar_height));
+
+        // The thumbs go into layers of their own, which the compositor moves along their tracks
+        // as it scrolls the contents.
+        let node = self.fragment.node;
+        let solid_color = |rect: Rect<Au>, color: color::Color| {
+            SolidColorDisplayItemClass(box SolidColorDisplayItem {
+                base: BaseDisplayItem::new(rect, node, ContentStackingLevel),
+                color: color,
+            })
+        };
+        let track_color = color::rgb(230, 230, 230);
+        let thumb_color = color::rgb(160, 160, 160);
+        let mut thumbs = vec!();
+        if show_vertical_scrollbar {
+            let track = Rect(Point2D(visible_rect.origin.x + visible_rect.size.width,
+                                     visible_rect.origin.y),
+                             Size2D(vertical_scrollbar_width, visible_rect.size.height));
+            let thumb_height = scrollbar_thumb_length(track.size.height,
+                                                      visible_rect.size.height,
+                                                      contents_size.height);
+            let thumb = Rect(track.origin, Size2D(track.size.width, thumb_height));
+            self.base.display_list.push(solid_color(track, track_color));
+            thumbs.push((thumb,
+                         true,
+                         track.size.height - thumb_height,
+                         contents_size.height - visible_rect.size.height))
+        }
+        if show_horizontal_scrollbar {
+            let track = Rect(Point2D(visible_rect.origin.x,
+                                     visible_rect.origin.y + visible_rect.size.height),
+                             Size2D(visible_rect.size.width, horizontal_scrollbar_height));
+            let thumb_width = scrollbar_thumb_length(track.size.width,
+                                                     visible_rect.size.width,
+                                                     contents_size.width);
+            let thumb = Rect(track.origin, Size2D(thumb_width, track.size.height));
+            self.base.display_list.push(solid_color(track, track_color));
+            thumbs.push((thumb,
+                         false,
+                         track.size.width - thumb_width,
+                         contents_size.width - visible_rect.size.width))
+        }
+        for (i, (thumb, vertical, travel, scroll_range)) in thumbs.move_iter().enumerate() {
+            let mut display_list = DisplayList::new();
+            display_list.push(solid_color(thumb, thumb_color));
+            let constraint = ScrollbarThumbConstraint {
+                container: self.layer_id(0),
+                vertical: vertical,
+                travel: geometry::to_frac_px(travel) as f32,
+                scroll_range: geometry::to_frac_px(Au::max(scroll_range, Au(0))) as f32,
+            };
+            let new_layer = RenderLayer {
+                id: self.layer_id(i + 1),
+                display_list: Arc::new(display_list),
+                position: Rect(Point2D(thumb.origin.x.to_nearest_px() as uint,
+                                       thumb.origin.y.to_nearest_px() as uint),
+                               Size2D(thumb.size.width.to_nearest_px() as uint,
+                                      thumb.size.height.to_nearest_px() as uint)),
+                background_color: color::rgba(255.0, 255.0, 255.0, 0.0),
+                scroll_policy: ScrollbarThumb(constraint),
+                content_size: None,
+            };
+            self.base.layers.push_back(new_layer)
+        }
+
+        // The layer goes below the layers of any descendants.
+        let size = Size2D(visible_rect.size.width.to_nearest_px() as uint,
+                          visible_rect.size.height.to_nearest_px() as uint);
+        let origin = Point2D(visible_rect.origin.x.to_nearest_px() as uint,
+                             visible_rect.origin.y.to_nearest_px() as uint);
+        let content_size = Size2D(contents_size.width.to_nearest_px() as uint,
+                                  contents_size.height.to_nearest_px() as uint);
+        let new_layer = RenderLayer {
+            id: self.layer_id(0),
+            display_list: Arc::new(contents.flatten(ContentStackingLevel)),
+            position: Rect(origin, size),
+            background_color: color::rgba(255.0, 255.0, 255.0, 0.0),
+            scroll_policy: Scrollable,
+            content_size: Some(content_size),
+        };
+        self.base.layers.push_front(new_layer)
+    }
+
     /// Return the top outer edge of the hypothetical box for an absolute flow.
     ///
     /// This is wrt its parent flow box.
@@ -1285,7 +1788,7 @@ impl BlockFlow {
     /// roughly set to its static position (the position it would have had in
     /// the normal flow).
     fn get_hypothetical_top_edge(&self) -> Au {
-        self.base.position.origin.y
+        self.base.position.start.b
     }
 
     /// Assigns the computed left content edge and width to all the children of this block flow.
@@ -1305,7 +1808,9 @@ impl BlockFlow {
         let absolute_static_x_offset = if self.is_positioned() {
             // This flow is the containing block. The static X offset will be the left padding
             // edge.
-            self.fragment.border_padding.left - model::border_from_style(self.fragment.style()).left
+            let border = model::border_from_style(self.fragment.style());
+            let border = LogicalMargin::from_physical(self.base.writing_mode, border);
+            self.fragment.border_padding.inline_start - border.inline_start
         } else {
             // For kids, the left margin edge will be at our left content edge. The current static
             // offset is at our left margin edge. So move in to the left content edge.
@@ -1324,12 +1829,17 @@ impl BlockFlow {
         // seen before it.)
         let mut last_float_width = None;
 
-        for (i, kid) in self.base.child_iter().enumerate() {
-            if kid.is_block_flow() {
+        for kid in self.base.child_iter() {
+            if kid.is_block_like() {
                 let kid_block = kid.as_block();
                 kid_block.base.absolute_static_x_offset = absolute_static_x_offset;
                 kid_block.base.fixed_static_x_offset = fixed_static_x_offset;
 
+                // A flex container sets this again after propagating widths. Clear it so that a
+                // flow reused under a parent that is no longer a flex container isn't laid out as
+                // a flex item.
+                kid_block.flex_item_width = None;
+
                 if kid_block.is_float() {
                     last_float_width = Some(kid_block.base.intrinsic_widths.preferred_width)
                 } else {
@@ -1339,8 +1849,8 @@ impl BlockFlow {
 
             // The left margin edge of the child flow is at our left content edge, and its width
             // is our content width.
-            flow::mut_base(kid).position.origin.x = left_content_edge;
-            flow::mut_base(kid).position.size.width = content_width;
+            flow::mut_base(kid).position.start.i = left_content_edge;
+            flow::mut_base(kid).position.size.inline = content_width;
 
             // Determine float impaction.
             match kid.float_clearance() {
@@ -1366,7 +1876,6 @@ impl BlockFlow {
             match opt_col_widths {
                 Some(ref col_widths) => {
                     propagate_column_widths_to_child(kid,
-                                                     i,
                                                      content_width,
                                                      col_widths.as_slice(),
                                                      &mut left_margin_edge)
@@ -1385,11 +1894,12 @@ impl BlockFlow {
     /// `FormattingContextType`.
     fn formatting_context_type(&self) -> FormattingContextType {
         let style = self.fragment.style();
-        if style.get_box().float != float::none {
+        if style.get_box().float != float::none || self.is_flex_item() {
             return OtherFormattingContext
         }
         match style.get_box().display {
-            display::table_cell | display::table_caption | display::inline_block => {
+            display::table_cell | display::table_caption | display::inline_block |
+            display::inline_flex => {
                 OtherFormattingContext
             }
             _ if style.get_box().position == position::static_ &&
@@ -1422,7 +1932,6 @@ impl Flow for BlockFlow {
     /// must decide minimum/preferred widths based on its children's widths and the dimensions of
     /// any fragments it is responsible for flowing.
     ///
-    /// TODO(pcwalton): Inline blocks.
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
         let mut flags = self.base.flags;
         flags.set_has_left_floated_descendants(false);
@@ -1433,6 +1942,8 @@ impl Flow for BlockFlow {
         for child_ctx in self.base.child_iter() {
             assert!(child_ctx.is_block_flow() ||
                     child_ctx.is_inline_flow() ||
+                    child_ctx.is_flex() ||
+                    child_ctx.is_multicol() ||
                     child_ctx.is_table_kind());
 
             let child_base = flow::mut_base(child_ctx);
@@ -1477,8 +1988,10 @@ impl Flow for BlockFlow {
 
         if self.is_root() {
             debug!("Setting root position");
-            self.base.position.origin = Zero::zero();
-            self.base.position.size.width = layout_context.screen_size.width;
+            self.base.position.start = LogicalPoint::zero();
+            self.base.position.size.inline =
+                LogicalSize::from_physical(self.base.writing_mode,
+                                           layout_context.screen_size).inline;
             self.base.floats = Floats::new();
 
             // The root element is never impacted by floats.
@@ -1488,7 +2001,7 @@ impl Flow for BlockFlow {
 
         // Our width was set to the width of the containing block by the flow's parent. Now compute
         // the real value.
-        let containing_block_width = self.base.position.size.width;
+        let containing_block_width = self.base.position.size.inline;
         self.compute_used_width(layout_context, containing_block_width);
         if self.is_float() {
             self.float.get_mut_ref().containing_width = containing_block_width;
@@ -1507,8 +2020,8 @@ impl Flow for BlockFlow {
                 match self.previous_float_width {
                     None => {}
                     Some(previous_float_width) => {
-                        self.fragment.border_box.size.width =
-                            self.fragment.border_box.size.width - previous_float_width
+                        self.fragment.border_box.size.inline =
+                            self.fragment.border_box.size.inline - previous_float_width
                     }
                 }
             }
@@ -1519,12 +2032,17 @@ impl Flow for BlockFlow {
         }
 
         // Move in from the left border edge
-        let left_content_edge = self.fragment.border_box.origin.x + self.fragment.border_padding.left;
-        let padding_and_borders = self.fragment.border_padding.horizontal();
-        let content_width = self.fragment.border_box.size.width - padding_and_borders;
+        let left_content_edge = self.fragment.border_box.start.i +
+            self.fragment.border_padding.inline_start;
+        let padding_and_borders = self.fragment.border_padding.inline_start_end();
+        let content_width = self.fragment.border_box.size.inline - padding_and_borders;
 
         if self.is_float() {
-            self.base.position.size.width = content_width;
+            self.base.position.size.inline = content_width;
+        } else if self.is_inline_block() {
+            // Inline blocks don't fill their containing block.
+            self.base.position.size.inline = self.fragment.border_box.size.inline +
+                self.fragment.margin.inline_start_end();
         }
 
         self.propagate_assigned_width_to_children(left_content_edge, content_width, None);
@@ -1557,6 +2075,14 @@ impl Flow for BlockFlow {
         if self.is_float() {
             debug!("assign_height_float: assigning height for float");
             self.assign_height_float(ctx);
+        } else if self.is_flex_item() {
+            // Flex items establish new formatting contexts, so margins don't collapse through
+            // them.
+            debug!("assign_height: assigning height for flex item");
+            self.assign_height_block_base(ctx, MarginsMayNotCollapse);
+        } else if self.is_inline_block() {
+            debug!("assign_height: assigning height for inline block");
+            self.assign_height_block_base(ctx, MarginsMayNotCollapse);
         } else {
             debug!("assign_height: assigning height for block");
             self.assign_height_block_base(ctx, MarginsMayCollapse);
@@ -1565,16 +2091,26 @@ impl Flow for BlockFlow {
 
     fn compute_absolute_position(&mut self) {
         if self.is_absolutely_positioned() {
+            // FIXME: This assumes that the containing block has the same writing mode as this
+            // flow.
+            let containing_block_size = if self.is_fixed() {
+                self.base.absolute_position_info.viewport_size
+            } else {
+                self.base.absolute_position_info.absolute_containing_block_size
+            };
+            let position = self.base
+                               .position
+                               .to_physical(self.base.writing_mode, containing_block_size)
+                               .origin;
             self.base
                 .absolute_position_info
                 .absolute_containing_block_position = if self.is_fixed() {
                 // The viewport is initially at (0, 0).
-                self.base.position.origin
+                position
             } else {
                 // Absolute position of the containing block + position of absolute flow w/r/t the
                 // containing block.
-                self.base.absolute_position_info.absolute_containing_block_position +
-                    self.base.position.origin
+                self.base.absolute_position_info.absolute_containing_block_position + position
             };
 
             // Set the absolute position, which will be passed down later as part
@@ -1591,32 +2127,48 @@ impl Flow for BlockFlow {
                                                  .absolute_position_info
                                                  .relative_containing_block_size,
                                             None);
+        let writing_mode = self.base.writing_mode;
+        let this_size = self.logical_frame().size.to_physical(writing_mode);
+        let mut absolute_position_info = self.base.absolute_position_info;
         if self.is_positioned() {
-            self.base.absolute_position_info.absolute_containing_block_position =
-                self.base.abs_position +
-                self.generated_containing_block_rect().origin +
-                relative_offset
+            let containing_block_rect = self.generated_containing_block_rect()
+                                            .to_physical(writing_mode, this_size);
+            absolute_position_info.absolute_containing_block_position =
+                self.base.abs_position + containing_block_rect.origin + relative_offset;
+            absolute_position_info.absolute_containing_block_size = containing_block_rect.size;
         }
 
-        let float_offset = if self.is_float() {
-            self.float.get_ref().rel_pos
-        } else {
-            Zero::zero()
-        };
-
         // Compute absolute position info for children.
-        let mut absolute_position_info = self.base.absolute_position_info;
-        absolute_position_info.relative_containing_block_size = self.fragment.content_box().size;
+        absolute_position_info.relative_containing_block_size =
+            self.fragment.content_box().size.to_physical(writing_mode);
         absolute_position_info.layers_needed_for_positioned_flows =
             self.base.flags.layers_needed_for_descendants();
 
-        // Process children.
+        // Sticky children stay within our content box. Table rows and row groups don't count,
+        // though: sticky rows and cells stay within the whole table.
         let this_position = self.base.abs_position;
+        match self.fragment.style().get_box().display {
+            display::table_row | display::table_row_group | display::table_header_group |
+            display::table_footer_group => {}
+            _ => {
+                let content_box = self.fragment.content_box().to_physical(writing_mode, this_size);
+                absolute_position_info.sticky_containing_block =
+                    content_box.translate(&(this_position + relative_offset));
+            }
+        }
+
+        // Process children. Floats are positioned by the rectangle they were placed at, since
+        // their physical position depends on their size in vertical writing modes.
         for kid in self.base.child_iter() {
             if !kid.is_absolutely_positioned() {
+                let kid_frame = if kid.is_float() {
+                    kid.as_block().logical_frame()
+                } else {
+                    flow::base(kid).position
+                };
                 let kid_base = flow::mut_base(kid);
-                kid_base.abs_position = this_position + kid_base.position.origin +
-                    relative_offset + float_offset;
+                kid_base.abs_position = this_position +
+                    kid_frame.to_physical(writing_mode, this_size).origin + relative_offset;
                 kid_base.absolute_position_info = absolute_position_info
             }
         }
@@ -1655,16 +2207,18 @@ impl Flow for BlockFlow {
     ///
     /// It has to be either relatively positioned or the Root flow.
     fn is_root_of_absolute_flow_tree(&self) -> bool {
-        self.is_relatively_positioned() || self.is_root()
+        self.is_relatively_positioned() || self.is_sticky() || self.is_root()
     }
 
     /// Return the dimensions of the containing block generated by this flow for absolutely-
     /// positioned descendants. For block flows, this is the padding box.
-    fn generated_containing_block_rect(&self) -> Rect<Au> {
-        let border = model::border_from_style(self.fragment.style());
-        Rect(self.fragment.border_box.origin + Point2D(border.left, border.top),
-             Size2D(self.fragment.border_box.size.width - border.horizontal(),
-                    self.fragment.border_box.size.height - border.vertical()))
+    fn generated_containing_block_rect(&self) -> LogicalRect<Au> {
+        let border = LogicalMargin::from_physical(self.base.writing_mode,
+                                                  model::border_from_style(self.fragment.style()));
+        LogicalRect::new(self.fragment.border_box.start.i + border.inline_start,
+                         self.fragment.border_box.start.b + border.block_start,
+                         self.fragment.border_box.size.inline - border.inline_start_end(),
+                         self.fragment.border_box.size.block - border.block_start_end())
     }
 
     fn layer_id(&self, fragment_index: uint) -> LayerId {
@@ -1677,6 +2231,10 @@ impl Flow for BlockFlow {
     fn is_absolute_containing_block(&self) -> bool {
         self.is_positioned()
     }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        iterator(&self.fragment)
+    }
 }
 
 impl fmt::Show for BlockFlow {
@@ -1786,14 +2344,17 @@ pub trait WidthAndMarginsComputer {
         // The text alignment of a block flow is the text alignment of its box's style.
         block.base.flags.set_text_align(style.get_inheritedtext().text_align);
 
+        let margin = style.logical_margin();
         let (margin_left, margin_right) =
-            (MaybeAuto::from_style(style.get_margin().margin_left, containing_block_width),
-             MaybeAuto::from_style(style.get_margin().margin_right, containing_block_width));
+            (MaybeAuto::from_style(margin.inline_start, containing_block_width),
+             MaybeAuto::from_style(margin.inline_end, containing_block_width));
 
+        let position = style.logical_position();
         let (left, right) =
-            (MaybeAuto::from_style(style.get_positionoffsets().left, containing_block_width),
-             MaybeAuto::from_style(style.get_positionoffsets().right, containing_block_width));
-        let available_width = containing_block_width - block.fragment.border_padding.horizontal();
+            (MaybeAuto::from_style(position.inline_start, containing_block_width),
+             MaybeAuto::from_style(position.inline_end, containing_block_width));
+        let available_width = containing_block_width -
+            block.fragment.border_padding.inline_start_end();
         return WidthConstraintInput::new(computed_width,
                                          margin_left,
                                          margin_right,
@@ -1818,22 +2379,22 @@ pub trait WidthAndMarginsComputer {
         let width;
         {
             let fragment = block.fragment();
-            fragment.margin.left = solution.margin_left;
-            fragment.margin.right = solution.margin_right;
+            fragment.margin.inline_start = solution.margin_left;
+            fragment.margin.inline_end = solution.margin_right;
 
             // The associated fragment has the border box of this flow.
             // Left border edge.
-            fragment.border_box.origin.x = fragment.margin.left;
+            fragment.border_box.start.i = fragment.margin.inline_start;
             // Border box width.
-            width = solution.width + fragment.border_padding.horizontal();
-            fragment.border_box.size.width = width;
+            width = solution.width + fragment.border_padding.inline_start_end();
+            fragment.border_box.size.inline = width;
         }
 
         // We also resize the block itself, to ensure that overflow is not calculated
         // as the width of our parent. We might be smaller and we might be larger if we
         // overflow.
         let flow = flow::mut_base(block);
-        flow.position.size.width = width;
+        flow.position.size.inline = width;
     }
 
     /// Set the x coordinate of the given flow if it is absolutely positioned.
@@ -1850,7 +2411,7 @@ pub trait WidthAndMarginsComputer {
                               parent_flow_width: Au,
                               ctx: &mut LayoutContext)
                               -> MaybeAuto {
-        MaybeAuto::from_style(block.fragment().style().get_box().width,
+        MaybeAuto::from_style(block.fragment().style().content_inline_size(),
                               self.containing_block_width(block, parent_flow_width, ctx))
     }
 
@@ -1877,7 +2438,8 @@ pub trait WidthAndMarginsComputer {
 
         // If the tentative used width is greater than 'max-width', width should be recalculated,
         // but this time using the computed value of 'max-width' as the computed value for 'width'.
-        match specified_or_none(block.fragment().style().get_box().max_width, containing_block_width) {
+        match specified_or_none(block.fragment().style().max_inline_size(),
+                                containing_block_width) {
             Some(max_width) if max_width < solution.width => {
                 input.computed_width = Specified(max_width);
                 solution = self.solve_width_constraints(block, &input);
@@ -1887,7 +2449,7 @@ pub trait WidthAndMarginsComputer {
 
         // If the resulting width is smaller than 'min-width', width should be recalculated,
         // but this time using the value of 'min-width' as the computed value for 'width'.
-        let computed_min_width = specified(block.fragment().style().get_box().min_width,
+        let computed_min_width = specified(block.fragment().style().min_inline_size(),
                                            containing_block_width);
         if computed_min_width > solution.width {
             input.computed_width = Specified(computed_min_width);
@@ -1981,6 +2543,8 @@ struct BlockNonReplaced;
 struct BlockReplaced;
 struct FloatNonReplaced;
 struct FloatReplaced;
+struct InlineBlockNonReplaced;
+struct FlexItem;
 
 impl WidthAndMarginsComputer for AbsoluteNonReplaced {
     /// Solve the horizontal constraint equation for absolute non-replaced elements.
@@ -2007,9 +2571,8 @@ impl WidthAndMarginsComputer for AbsoluteNonReplaced {
             ..
         } = input;
 
-        // TODO: Check for direction of parent flow (NOT Containing Block)
-        // when right-to-left is implemented.
-        // Assume direction is 'ltr' for now
+        // TODO: The static position should depend on the direction of the parent flow (NOT the
+        // Containing Block). Assume it is 'ltr' for now.
 
         // Distance from the left edge of the Absolute Containing Block to the
         // left margin edge of a hypothetical box that would have been the
@@ -2035,9 +2598,11 @@ impl WidthAndMarginsComputer for AbsoluteNonReplaced {
                     (Auto, Auto) => {
                         let total_margin_val = available_width - left - right - width;
                         if total_margin_val < Au(0) {
-                            // margin-left becomes 0 because direction is 'ltr'.
-                            // TODO: Handle 'rtl' when it is implemented.
-                            (left, right, width, Au(0), total_margin_val)
+                            // The start margin becomes 0 and the end margin is solved for.
+                            match input.direction {
+                                direction::ltr => (left, right, width, Au(0), total_margin_val),
+                                direction::rtl => (left, right, width, total_margin_val, Au(0)),
+                            }
                         } else {
                             // Equal margins
                             (left, right, width,
@@ -2054,11 +2619,18 @@ impl WidthAndMarginsComputer for AbsoluteNonReplaced {
                         (left, right, width, available_width - sum, margin_r)
                     }
                     (Specified(margin_l), Specified(margin_r)) => {
-                        // Values are over-constrained.
-                        // Ignore value for 'right' cos direction is 'ltr'.
-                        // TODO: Handle 'rtl' when it is implemented.
-                        let sum = left + width + margin_l + margin_r;
-                        (left, available_width - sum, width, margin_l, margin_r)
+                        // Values are over-constrained. Ignore the value for 'right' if direction
+                        // is 'ltr' and the value for 'left' if it is 'rtl'.
+                        match input.direction {
+                            direction::ltr => {
+                                let sum = left + width + margin_l + margin_r;
+                                (left, available_width - sum, width, margin_l, margin_r)
+                            }
+                            direction::rtl => {
+                                let sum = right + width + margin_l + margin_r;
+                                (available_width - sum, right, width, margin_l, margin_r)
+                            }
+                        }
                     }
                 }
             }
@@ -2108,8 +2680,8 @@ impl WidthAndMarginsComputer for AbsoluteNonReplaced {
             (Auto, Auto, Specified(width)) => {
                 let margin_l = left_margin.specified_or_zero();
                 let margin_r = right_margin.specified_or_zero();
-                // Setting 'left' to static position because direction is 'ltr'.
-                // TODO: Handle 'rtl' when it is implemented.
+                // Setting 'left' to static position because the direction of the parent flow is
+                // assumed to be 'ltr'.
                 let left = static_position_left;
                 let sum = left + width + margin_l + margin_r;
                 (left, available_width - sum, width, margin_l, margin_r)
@@ -2119,14 +2691,14 @@ impl WidthAndMarginsComputer for AbsoluteNonReplaced {
     }
 
     fn containing_block_width(&self, block: &mut BlockFlow, _: Au, ctx: &mut LayoutContext) -> Au {
-        block.containing_block_size(ctx.screen_size).width
+        block.containing_block_size(ctx.screen_size).inline
     }
 
     fn set_flow_x_coord_if_necessary(&self,
                                      block: &mut BlockFlow,
                                      solution: WidthConstraintSolution) {
         // Set the x-coordinate of the absolute flow wrt to its containing block.
-        block.base.position.origin.x = solution.left;
+        block.base.position.start.i = solution.left;
     }
 }
 
@@ -2155,11 +2727,9 @@ impl WidthAndMarginsComputer for AbsoluteReplaced {
             static_x_offset,
             ..
         } = input;
-        // TODO: Check for direction of static-position Containing Block (aka
-        // parent flow, _not_ the actual Containing Block) when right-to-left
-        // is implemented
-        // Assume direction is 'ltr' for now
-        // TODO: Handle all the cases for 'rtl' direction.
+        // TODO: The static position should depend on the direction of the static-position
+        // Containing Block (aka parent flow, _not_ the actual Containing Block). Assume it is
+        // 'ltr' for now.
 
         let width = match computed_width {
             Specified(w) => w,
@@ -2199,8 +2769,11 @@ impl WidthAndMarginsComputer for AbsoluteReplaced {
                     (Auto, Auto) => {
                         let total_margin_val = available_width - left - right - width;
                         if total_margin_val < Au(0) {
-                            // margin-left becomes 0 because direction is 'ltr'.
-                            (left, right, width, Au(0), total_margin_val)
+                            // The start margin becomes 0 and the end margin is solved for.
+                            match input.direction {
+                                direction::ltr => (left, right, width, Au(0), total_margin_val),
+                                direction::rtl => (left, right, width, total_margin_val, Au(0)),
+                            }
                         } else {
                             // Equal margins
                             (left, right, width,
@@ -2217,10 +2790,18 @@ impl WidthAndMarginsComputer for AbsoluteReplaced {
                         (left, right, width, available_width - sum, margin_r)
                     }
                     (Specified(margin_l), Specified(margin_r)) => {
-                        // Values are over-constrained.
-                        // Ignore value for 'right' cos direction is 'ltr'.
-                        let sum = left + width + margin_l + margin_r;
-                        (left, available_width - sum, width, margin_l, margin_r)
+                        // Values are over-constrained. Ignore the value for 'right' if direction
+                        // is 'ltr' and the value for 'left' if it is 'rtl'.
+                        match input.direction {
+                            direction::ltr => {
+                                let sum = left + width + margin_l + margin_r;
+                                (left, available_width - sum, width, margin_l, margin_r)
+                            }
+                            direction::rtl => {
+                                let sum = right + width + margin_l + margin_r;
+                                (available_width - sum, right, width, margin_l, margin_r)
+                            }
+                        }
                     }
                 }
             }
@@ -2234,21 +2815,21 @@ impl WidthAndMarginsComputer for AbsoluteReplaced {
                               _: Au,
                               ctx: &mut LayoutContext)
                               -> MaybeAuto {
-        let containing_block_width = block.containing_block_size(ctx.screen_size).width;
+        let containing_block_width = block.containing_block_size(ctx.screen_size).inline;
         let fragment = block.fragment();
         fragment.assign_replaced_width_if_necessary(containing_block_width, None);
         // For replaced absolute flow, the rest of the constraint solving will
         // take width to be specified as the value computed here.
-        Specified(fragment.content_width())
+        Specified(fragment.content_inline_size())
     }
 
     fn containing_block_width(&self, block: &mut BlockFlow, _: Au, ctx: &mut LayoutContext) -> Au {
-        block.containing_block_size(ctx.screen_size).width
+        block.containing_block_size(ctx.screen_size).inline
     }
 
     fn set_flow_x_coord_if_necessary(&self, block: &mut BlockFlow, solution: WidthConstraintSolution) {
         // Set the x-coordinate of the absolute flow wrt to its containing block.
-        block.base.position.origin.x = solution.left;
+        block.base.position.start.i = solution.left;
     }
 }
 
@@ -2288,7 +2869,7 @@ impl WidthAndMarginsComputer for BlockReplaced {
         fragment.assign_replaced_width_if_necessary(parent_flow_width, None);
         // For replaced block flow, the rest of the constraint solving will
         // take width to be specified as the value computed here.
-        Specified(fragment.content_width())
+        Specified(fragment.content_inline_size())
     }
 
 }
@@ -2344,12 +2925,66 @@ impl WidthAndMarginsComputer for FloatReplaced {
         fragment.assign_replaced_width_if_necessary(parent_flow_width, None);
         // For replaced block flow, the rest of the constraint solving will
         // take width to be specified as the value computed here.
-        Specified(fragment.content_width())
+        Specified(fragment.content_inline_size())
     }
 }
 
+impl WidthAndMarginsComputer for InlineBlockNonReplaced {
+    /// CSS Section 10.3.9
+    ///
+    /// If width is computed as 'auto', the used value is the 'shrink-to-fit' width. Auto margins
+    /// become zero.
+    fn solve_width_constraints(&self,
+                               block: &mut BlockFlow,
+                               input: &WidthConstraintInput)
+                               -> WidthConstraintSolution {
+        let margin_left = input.left_margin.specified_or_zero();
+        let margin_right = input.right_margin.specified_or_zero();
+        let available_width = input.available_width - margin_left - margin_right;
+        let shrink_to_fit = block.get_shrink_to_fit_width(available_width);
+        let width = input.computed_width.specified_or_default(shrink_to_fit);
+        WidthConstraintSolution::new(width, margin_left, margin_right)
+    }
+}
+
+impl WidthAndMarginsComputer for FlexItem {
+    /// The flex container has already resolved the width of this item, taking `min-width` and
+    /// `max-width` into account, so just use that.
+    fn compute_used_width(&self,
+                          block: &mut BlockFlow,
+                          ctx: &mut LayoutContext,
+                          parent_flow_width: Au) {
+        let input = self.compute_width_constraint_inputs(block, parent_flow_width, ctx);
+        let solution = self.solve_width_constraints(block, &input);
+        self.set_width_constraint_solutions(block, solution);
+    }
+
+    /// Auto margins on flex items are treated as zero.
+    fn solve_width_constraints(&self,
+                               block: &mut BlockFlow,
+                               input: &WidthConstraintInput)
+                               -> WidthConstraintSolution {
+        let width = block.flex_item_width.unwrap() -
+            block.fragment.border_padding.inline_start_end();
+        WidthConstraintSolution::new(width,
+                                     input.left_margin.specified_or_zero(),
+                                     input.right_margin.specified_or_zero())
+    }
+}
+
+/// Returns the length of the thumb of a scrollbar with a track of the given length, for contents of
+/// the given length of which the given length is visible. The length is rounded to whole px.
+fn scrollbar_thumb_length(track_length: Au, visible_length: Au, contents_length: Au) -> Au {
+    if contents_length <= visible_length {
+        return track_length
+    }
+    let ratio = geometry::to_frac_px(visible_length) / geometry::to_frac_px(contents_length);
+    let length = Au::from_px((geometry::to_frac_px(track_length) * ratio).round() as int);
+    let length = Au::max(length, Au::from_px(MIN_SCROLLBAR_THUMB_LENGTH));
+    Au::min(length, track_length)
+}
+
 fn propagate_column_widths_to_child(kid: &mut Flow,
-                                    child_index: uint,
                                     content_width: Au,
                                     column_widths: &[Au],
                                     left_margin_edge: &mut Au) {
@@ -2364,21 +2999,372 @@ fn propagate_column_widths_to_child(kid: &mut Flow,
         content_width
     } else if kid.is_table_cell() {
         // If kid is table_cell, the x offset and width for each cell should be
-        // calculated from parent's column widths info.
-        *left_margin_edge = if child_index == 0 {
-            Au(0)
-        } else {
-            *left_margin_edge + column_widths[child_index - 1]
+        // calculated from parent's column widths info: the cell starts after the columns before
+        // its first column and is as wide as the columns it spans.
+        // In the separated borders model, the horizontal `border-spacing` goes between the
+        // columns and at the edges of the row.
+        let (start, column_span, spacing) = {
+            let cell = kid.as_table_cell();
+            (cell.column_index,
+             cell.column_span,
+             table::border_spacing(cell.fragment().style()).horizontal)
         };
-
-        column_widths[child_index]
+        let start = cmp::min(start, column_widths.len());
+        let end = cmp::min(start + column_span, column_widths.len());
+        *left_margin_edge = column_widths.slice_to(start)
+                                         .iter()
+                                         .fold(spacing, |sum, &width| sum + width + spacing);
+
+        column_widths.slice(start, end).iter().fold(Au(0), |sum, &width| sum + width) +
+            spacing * Au::new(cmp::max(end - start, 1) as i32 - 1)
     } else {
         // Width of kid flow is our content width.
         content_width
     };
 
     let kid_base = flow::mut_base(kid);
-    kid_base.position.origin.x = *left_margin_edge;
-    kid_base.position.size.width = width;
+    kid_base.position.start.i = *left_margin_edge;
+    kid_base.position.size.inline = width;
+}
+
+/// A piece of in-flow content that is never split between fragmentainers. See
+/// `BlockFlow::unbreakable_pieces`.
+pub struct UnbreakablePiece {
+    /// Where the piece starts along the block axis.
+    pub start: Au,
+    /// Where the piece ends along the block axis.
+    pub end: Au,
+    /// Whether a page break just before this piece is allowed, avoided or forced.
+    pub break_before: PageBreak,
+}
+
+/// What the page break properties say about a page break at some position.
+#[deriving(Clone, PartialEq, Show)]
+pub enum PageBreak {
+    AllowedPageBreak,
+    AvoidedPageBreak,
+    ForcedPageBreak,
+    /// A forced break after which the content continues on a left page.
+    ForcedLeftPageBreak,
+    /// A forced break after which the content continues on a right page.
+    ForcedRightPageBreak,
 }
 
+impl PageBreak {
+    fn from_page_break_before(value: page_break_before::T) -> PageBreak {
+        match value {
+            page_break_before::auto => AllowedPageBreak,
+            page_break_before::avoid => AvoidedPageBreak,
+            page_break_before::always => ForcedPageBreak,
+            page_break_before::left => ForcedLeftPageBreak,
+            page_break_before::right => ForcedRightPageBreak,
+        }
+    }
+
+    fn from_page_break_after(value: page_break_after::T) -> PageBreak {
+        match value {
+            page_break_after::auto => AllowedPageBreak,
+            page_break_after::avoid => AvoidedPageBreak,
+            page_break_after::always => ForcedPageBreak,
+            page_break_after::left => ForcedLeftPageBreak,
+            page_break_after::right => ForcedRightPageBreak,
+        }
+    }
+
+    /// Combines the values that apply at the same position. Forced breaks win over avoided ones,
+    /// which win over allowed ones (CSS 2.1 § 13.3.3).
+    fn combine(self, other: PageBreak) -> PageBreak {
+        match (self, other) {
+            (_, ForcedPageBreak) | (_, ForcedLeftPageBreak) | (_, ForcedRightPageBreak) => other,
+            (ForcedPageBreak, _) | (ForcedLeftPageBreak, _) | (ForcedRightPageBreak, _) => self,
+            (AvoidedPageBreak, _) | (_, AvoidedPageBreak) => AvoidedPageBreak,
+            (AllowedPageBreak, AllowedPageBreak) => AllowedPageBreak,
+        }
+    }
+}
+
+/// Chooses where to break content with the given unbreakable extents between fragmentainers of
+/// block size `fragmentainer_size`, the first of which starts at `content_start`. Returns the
+/// position at which each fragmentainer starts. A piece that doesn't fit into an empty
+/// fragmentainer gets one of its own and overflows it.
+pub fn fragmentainer_breaks(extents: &[(Au, Au)], content_start: Au, fragmentainer_size: Au)
+                            -> Vec<Au> {
+    let mut breaks = vec!(content_start);
+    for &(start, end) in extents.iter() {
+        let current_break = *breaks.last().unwrap();
+        if start > current_break && end - current_break > fragmentainer_size {
+            breaks.push(start)
+        }
+    }
+    breaks
+}
+
+/// Chooses where to break content with the given unbreakable pieces between pages whose page area
+/// has block size `page_size`, like `fragmentainer_breaks` but honoring the page break properties
+/// where possible. A forced break to a left or right page that has to leave a page blank appears
+/// twice in the result. The first page is a right page.
+pub fn page_breaks(pieces: &[UnbreakablePiece], content_start: Au, page_size: Au) -> Vec<Au> {
+    let mut breaks = vec!(content_start);
+    // The last piece on the current page before which a break is allowed.
+    let mut last_allowed_break = None;
+    let mut index = 0;
+    while index < pieces.len() {
+        let piece = &pieces[index];
+        let current_break = *breaks.last().unwrap();
+        if piece.start > current_break {
+            match piece.break_before {
+                ForcedPageBreak | ForcedLeftPageBreak | ForcedRightPageBreak => {
+                    push_page_break(&mut breaks, piece.start, piece.break_before);
+                    last_allowed_break = None;
+                    index += 1;
+                    continue
+                }
+                AllowedPageBreak => last_allowed_break = Some(index),
+                AvoidedPageBreak => {}
+            }
+            if piece.end - current_break > page_size {
+                // Break before the last piece where that is allowed, or before this one if there
+                // is no such piece, and carry on from there.
+                let break_index = last_allowed_break.unwrap_or(index);
+                push_page_break(&mut breaks, pieces[break_index].start, AllowedPageBreak);
+                last_allowed_break = None;
+                index = break_index;
+            }
+        }
+        index += 1;
+    }
+    breaks
+}
+
+fn push_page_break(breaks: &mut Vec<Au>, position: Au, page_break: PageBreak) {
+    // Pages with even indices are right pages.
+    let next_page_is_right = breaks.len() % 2 == 0;
+    let leave_blank_page = match page_break {
+        ForcedLeftPageBreak => next_page_is_right,
+        ForcedRightPageBreak => !next_page_is_right,
+        _ => false,
+    };
+    if leave_blank_page {
+        breaks.push(position)
+    }
+    breaks.push(position)
+}
+
+/// Returns true if fragmentation enters the given flow to split its content, rather than treating
+/// the flow as an unbreakable piece. Only block containers are entered.
+fn is_fragmented_through(flow: &mut Flow) -> bool {
+    flow.is_block_flow() && flow.child_count() != 0 && !flow.as_block().is_replaced_content()
+}
+
+/// Accumulates unbreakable pieces of content, and the page break properties that apply before the
+/// next one.
+struct UnbreakablePieceCollector {
+    pieces: Vec<UnbreakablePiece>,
+    /// The combined `page-break-after` and `page-break-before` values seen since the last piece.
+    pending_break: PageBreak,
+}
+
+impl UnbreakablePieceCollector {
+    /// Adds a piece. `avoid_break` is true if a break before it would be inside a block with
+    /// `page-break-inside: avoid` or would leave fewer lines than `orphans` or `widows` allow.
+    fn push(&mut self, start: Au, end: Au, avoid_break: bool) {
+        let mut break_before = mem::replace(&mut self.pending_break, AllowedPageBreak);
+        if avoid_break {
+            break_before = break_before.combine(AvoidedPageBreak)
+        }
+        self.pieces.push(UnbreakablePiece {
+            start: start,
+            end: end,
+            break_before: break_before,
+        })
+    }
+}
+
+/// Appends the unbreakable pieces of the in-flow content of `block`, whose top border edge is at
+/// `offset`, to `collector`. `avoid_first_break` is true if an ancestor avoids a break before the
+/// first of them, and `avoid_inner_breaks` is true if an ancestor avoids breaks inside itself.
+fn collect_unbreakable_pieces(block: &mut BlockFlow,
+                              offset: Au,
+                              avoid_first_break: bool,
+                              avoid_inner_breaks: bool,
+                              collector: &mut UnbreakablePieceCollector) {
+    let (orphans, widows, avoid_inner_breaks) = {
+        let style = block.fragment.style();
+        (style.get_inheritedbox().orphans as uint,
+         style.get_inheritedbox().widows as uint,
+         avoid_inner_breaks || style.get_box().page_break_inside == page_break_inside::avoid)
+    };
+    let mut is_first_piece = true;
+    for kid in block.base.child_iter() {
+        if kid.is_absolutely_positioned() || kid.is_float() {
+            continue
+        }
+        let kid_offset = offset + flow::base(kid).position.start.b;
+        let avoid_break_before_kid = if is_first_piece {
+            avoid_first_break
+        } else {
+            avoid_inner_breaks
+        };
+        let piece_count = collector.pieces.len();
+        if kid.is_inline_flow() {
+            let lines = &kid.as_inline().lines;
+            let line_count = lines.len();
+            for (index, line) in lines.iter().enumerate() {
+                let avoid_break = if index == 0 {
+                    avoid_break_before_kid
+                } else {
+                    avoid_inner_breaks || index < orphans || line_count - index < widows
+                };
+                let start = kid_offset + line.bounds.origin.y;
+                collector.push(start, start + line.bounds.size.height, avoid_break)
+            }
+        } else {
+            let (break_before, break_after) = {
+                let style = kid.as_block().fragment.style();
+                (PageBreak::from_page_break_before(style.get_box().page_break_before),
+                 PageBreak::from_page_break_after(style.get_box().page_break_after))
+            };
+            collector.pending_break = collector.pending_break.combine(break_before);
+            if is_fragmented_through(kid) {
+                collect_unbreakable_pieces(kid.as_block(),
+                                           kid_offset,
+                                           avoid_break_before_kid,
+                                           avoid_inner_breaks,
+                                           collector)
+            } else {
+                collector.push(kid_offset,
+                               kid_offset + flow::base(kid).position.size.block,
+                               avoid_break_before_kid)
+            }
+            collector.pending_break = collector.pending_break.combine(break_after);
+        }
+        if collector.pieces.len() != piece_count {
+            is_first_piece = false
+        }
+    }
+}
+
+/// Returns how far a piece of content that starts at `start` has to move to get into the
+/// fragmentainer that `breaks` assigns it to.
+fn fragmentainer_translation(breaks: &[Au], fragmentainer_offset: LogicalSize<Au>, start: Au)
+                             -> LogicalSize<Au> {
+    let index = breaks.iter().skip(1).take_while(|break_| **break_ <= start).count();
+    LogicalSize::new(fragmentainer_offset.inline.scale_by(index as f64),
+                     fragmentainer_offset.block.scale_by(index as f64) + breaks[0] - breaks[index])
+}
+
+/// Moves the unbreakable pieces of the in-flow content of `block`, whose top border edge is at
+/// `offset`, into their fragmentainers. See `BlockFlow::move_content_into_fragmentainers`.
+fn move_unbreakable_pieces(block: &mut BlockFlow,
+                           layout_context: &mut LayoutContext,
+                           offset: Au,
+                           breaks: &[Au],
+                           fragmentainer_offset: LogicalSize<Au>) {
+    for kid in block.base.child_iter() {
+        if kid.is_absolutely_positioned() || kid.is_float() {
+            continue
+        }
+        let kid_offset = offset + flow::base(kid).position.start.b;
+        if kid.is_inline_flow() {
+            let inline_flow = kid.as_inline();
+            for line in inline_flow.lines.mut_iter() {
+                let delta = fragmentainer_translation(breaks,
+                                                      fragmentainer_offset,
+                                                      kid_offset + line.bounds.origin.y);
+                line.bounds.origin.x = line.bounds.origin.x + delta.inline;
+                line.bounds.origin.y = line.bounds.origin.y + delta.block;
+                for fragment_index in inline::each_fragment_index(&line.range) {
+                    let fragment = inline_flow.fragments.get_mut(fragment_index.to_uint());
+                    fragment.border_box.start.i = fragment.border_box.start.i + delta.inline;
+                    fragment.border_box.start.b = fragment.border_box.start.b + delta.block;
+                    match fragment.specific {
+                        InlineBlockFragment(ref mut info) => {
+                            let position = &mut flow::mut_base(info.flow_ref.get_mut()).position;
+                            position.start.i = position.start.i + delta.inline;
+                            position.start.b = position.start.b + delta.block;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            // Moved lines no longer lie within the inline flow itself.
+            let writing_mode = inline_flow.base.writing_mode;
+            let size = inline_flow.base.position.size.to_physical(writing_mode);
+            let mut overflow = Rect(Point2D(Au(0), Au(0)), size);
+            for line in inline_flow.lines.iter() {
+                let bounds = LogicalRect::new(line.bounds.origin.x,
+                                              line.bounds.origin.y,
+                                              line.bounds.size.width,
+                                              line.bounds.size.height);
+                overflow = overflow.union(&bounds.to_physical(writing_mode, size))
+            }
+            inline_flow.base.overflow = overflow;
+        } else if is_fragmented_through(kid) {
+            move_unbreakable_pieces(kid.as_block(),
+                                    layout_context,
+                                    kid_offset,
+                                    breaks,
+                                    fragmentainer_offset);
+            kid.store_overflow(layout_context)
+        } else {
+            let delta = fragmentainer_translation(breaks, fragmentainer_offset, kid_offset);
+            let position = &mut flow::mut_base(kid).position;
+            position.start.i = position.start.i + delta.inline;
+            position.start.b = position.start.b + delta.block;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fragmentainer_breaks;
+
+    use servo_util::geometry::Au;
+
+    fn extents(pieces: &[(i32, i32)]) -> Vec<(Au, Au)> {
+        pieces.iter().map(|&(start, end)| (Au(start), Au(end))).collect()
+    }
+
+    #[test]
+    fn test_fragmentainer_breaks_no_content() {
+        assert_eq!(fragmentainer_breaks(&[], Au(0), Au(100)), vec!(Au(0)));
+    }
+
+    #[test]
+    fn test_fragmentainer_breaks_content_fits() {
+        let pieces = extents(&[(0, 10), (10, 20), (20, 30)]);
+        assert_eq!(fragmentainer_breaks(pieces.as_slice(), Au(0), Au(30)), vec!(Au(0)));
+    }
+
+    #[test]
+    fn test_fragmentainer_breaks_between_pieces() {
+        let pieces = extents(&[(0, 10), (10, 20), (20, 30), (30, 40), (40, 50)]);
+        assert_eq!(fragmentainer_breaks(pieces.as_slice(), Au(0), Au(20)),
+                   vec!(Au(0), Au(20), Au(40)));
+
+        // A piece that would straddle the end of a fragmentainer starts the next one.
+        let pieces = extents(&[(0, 15), (15, 30)]);
+        assert_eq!(fragmentainer_breaks(pieces.as_slice(), Au(0), Au(20)),
+                   vec!(Au(0), Au(15)));
+    }
+
+    #[test]
+    fn test_fragmentainer_breaks_content_start() {
+        let pieces = extents(&[(5, 15), (15, 25)]);
+        assert_eq!(fragmentainer_breaks(pieces.as_slice(), Au(5), Au(10)),
+                   vec!(Au(5), Au(15)));
+    }
+
+    #[test]
+    fn test_fragmentainer_breaks_tall_piece() {
+        // A piece taller than a fragmentainer overflows a fragmentainer of its own.
+        let pieces = extents(&[(0, 50), (50, 60), (60, 70)]);
+        assert_eq!(fragmentainer_breaks(pieces.as_slice(), Au(0), Au(20)),
+                   vec!(Au(0), Au(50)));
+
+        let pieces = extents(&[(0, 10), (10, 60), (60, 70)]);
+        assert_eq!(fragmentainer_breaks(pieces.as_slice(), Au(0), Au(20)),
+                   vec!(Au(0), Au(10), Au(60)));
+    }
+}
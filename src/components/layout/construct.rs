@@ -11,28 +11,36 @@
 //! maybe it's an absolute or fixed position thing that hasn't found its containing block yet.
 //! Construction items bubble up the tree from children to parents until they find their homes.
 //!
-//! TODO(pcwalton): There is no incremental reflow yet. This scheme requires that nodes either have
-//! weak references to flows or that there be some mechanism to efficiently (O(1) time) "blow
-//! apart" a flow tree and have the flows migrate "home" to their respective DOM nodes while we
-//! perform flow tree construction. The precise mechanism for this will take some experimentation
-//! to get right.
+//! Each node remembers the flow it produced during the last flow construction. A node is dirty if
+//! its restyle damage, or that of any of its descendants, is nonempty; the damage of every node is
+//! folded into that of its parent as the traversal goes up the tree. Ancestors of a changed node
+//! therefore always get new flows, into which the flows of their undamaged children are moved.
+//!
+//! Flows whose subtrees contain absolutely positioned descendants with a containing block outside
+//! of them are never reused, because those descendants must be handed to the new containing block.
 
 #![deny(unsafe_block)]
 
 use css::node_style::StyledNode;
+use css::node_util::NodeUtil;
 use block::BlockFlow;
 use context::LayoutContext;
 use floats::FloatKind;
 use flow::{Flow, ImmutableFlowUtils, MutableOwnedFlowUtils};
 use flow::{Descendants, AbsDescendants};
 use flow;
+use flex::FlexFlow;
+use flow_list::FlowList;
 use flow_ref::FlowRef;
-use fragment::{Fragment, GenericFragment, IframeFragment, IframeFragmentInfo};
-use fragment::{ImageFragment, ImageFragmentInfo, SpecificFragmentInfo, TableFragment};
+use fragment::{Fragment, FormControlFragment, FormControlFragmentInfo, GenericFragment};
+use fragment::{IframeFragment, IframeFragmentInfo};
+use fragment::{ImageFragment, ImageFragmentInfo, InlineBlockFragment, InlineBlockFragmentInfo};
+use fragment::{SpecificFragmentInfo, TableFragment};
 use fragment::{TableCellFragment, TableColumnFragment, TableColumnFragmentInfo};
 use fragment::{TableRowFragment, TableWrapperFragment, UnscannedTextFragment};
 use fragment::{UnscannedTextFragmentInfo};
 use inline::{FragmentIndex, InlineFragments, InlineFlow};
+use multicol::MulticolFlow;
 use parallel;
 use table_wrapper::TableWrapperFlow;
 use table::TableFlow;
@@ -49,7 +57,8 @@ use wrapper::{Before, BeforeBlock, After, AfterBlock, Normal};
 use gfx::display_list::OpaqueNode;
 use gfx::font_context::FontContext;
 use script::dom::element::{HTMLIFrameElementTypeId, HTMLImageElementTypeId};
-use script::dom::element::{HTMLObjectElementTypeId};
+use script::dom::element::{HTMLInputElementTypeId, HTMLObjectElementTypeId};
+use script::dom::element::{HTMLSelectElementTypeId, HTMLTextAreaElementTypeId};
 use script::dom::element::{HTMLTableColElementTypeId, HTMLTableDataCellElementTypeId};
 use script::dom::element::{HTMLTableElementTypeId, HTMLTableHeaderCellElementTypeId};
 use script::dom::element::{HTMLTableRowElementTypeId, HTMLTableSectionElementTypeId};
@@ -61,8 +70,9 @@ use servo_util::range::Range;
 use servo_util::url::{is_image_data, parse_url};
 use std::mem;
 use std::sync::atomics::Relaxed;
-use style::ComputedValues;
-use style::computed_values::{display, position, float};
+use style::{ComputedValues, cascade_anonymous};
+use style::computed_values::{caption_side, column_count, column_width, display, float};
+use style::computed_values::{position};
 use sync::Arc;
 use url::Url;
 
@@ -248,6 +258,11 @@ impl<'a> FlowConstructor<'a> {
             Some(ElementNodeTypeId(HTMLIFrameElementTypeId)) => {
                 IframeFragment(IframeFragmentInfo::new(node))
             }
+            Some(ElementNodeTypeId(HTMLInputElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLSelectElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLTextAreaElementTypeId)) => {
+                FormControlFragment(FormControlFragmentInfo::new(node, self.font_context()))
+            }
             Some(ElementNodeTypeId(HTMLObjectElementTypeId)) => {
                 let data = node.get_object_data(&self.layout_context.url);
                 self.build_fragment_info_for_image(node, data)
@@ -280,6 +295,17 @@ impl<'a> FlowConstructor<'a> {
         let mut fragments = fragment_accumulator.finish();
         if fragments.is_empty() { return };
 
+        // Whitespace between table-internal boxes, or directly inside a table, row group, or row,
+        // is irrelevant per CSS 2.1 § 17.2.1, step 1.
+        let parent_is_table_box = {
+            let flow = flow.get();
+            flow.is_table() || flow.is_table_rowgroup() || flow.is_table_row()
+        };
+        if (parent_is_table_box || !flow_list.is_empty()) &&
+                fragments.fragments.iter().all(|fragment| fragment.is_whitespace_only()) {
+            return
+        }
+
         match whitespace_stripping {
             NoWhitespaceStripping => {}
             StripWhitespaceFromStart => {
@@ -298,16 +324,62 @@ impl<'a> FlowConstructor<'a> {
         inline_flow.minimum_depth_below_baseline = descent;
         let mut inline_flow = inline_flow as Box<Flow>;
         TextRunScanner::new().scan_for_runs(self.font_context(), inline_flow);
+        inline_flow.as_inline().resolve_bidi_levels(&**node.style());
+        inline_flow.as_inline().split_text_by_orientation();
         let mut inline_flow = FlowRef::new(inline_flow);
+
+        // The block flows of inline blocks are children of the inline flow, so that they get laid
+        // out before their fragments are placed on lines.
+        let mut inline_block_flows = vec!();
+        for (fragment, _) in inline_flow.get_mut().as_inline().fragments.iter() {
+            match fragment.specific {
+                InlineBlockFragment(ref info) => inline_block_flows.push(info.flow_ref.clone()),
+                _ => {}
+            }
+        }
+        for inline_block_flow in inline_block_flows.move_iter() {
+            inline_flow.add_new_child(inline_block_flow)
+        }
+
         inline_flow.finish(self.layout_context);
 
         if flow.get().need_anonymous_flow(inline_flow.get()) {
             flow_list.push(inline_flow)
         } else {
+            self.flush_anonymous_missing_children(flow_list, flow, node);
             flow.add_new_child(inline_flow)
         }
     }
 
+    /// Wraps any pending flows that need an anonymous parent in one and adds it to `flow`.
+    fn flush_anonymous_missing_children(&mut self,
+                                        flow_list: &mut Vec<FlowRef>,
+                                        flow: &mut FlowRef,
+                                        node: &ThreadSafeLayoutNode) {
+        if !flow_list.is_empty() {
+            let flow_list = mem::replace(flow_list, vec!());
+            self.generate_anonymous_missing_child(flow_list, flow, node);
+        }
+    }
+
+    /// Builds an anonymous column group flow around a table column that is not inside one, per
+    /// CSS 2.1 § 17.2.1, step 3.
+    fn build_anonymous_colgroup_flow(&mut self,
+                                     node: &ThreadSafeLayoutNode,
+                                     col_fragment: Fragment)
+                                     -> FlowRef {
+        let specific = TableColumnFragment(TableColumnFragmentInfo {
+            span: None,
+        });
+        let fragment = Fragment::new_anonymous_table_fragment(node, specific);
+        let flow = box TableColGroupFlow::from_node_and_fragments(node,
+                                                                  fragment,
+                                                                  vec!(col_fragment));
+        let mut flow = FlowRef::new(flow as Box<Flow>);
+        flow.finish(self.layout_context);
+        flow
+    }
+
     fn build_block_flow_using_children_construction_result(&mut self,
                                                            flow: &mut FlowRef,
                                                            consecutive_siblings: &mut Vec<FlowRef>,
@@ -317,7 +389,14 @@ impl<'a> FlowConstructor<'a> {
                                                            &mut InlineFragmentsAccumulator,
                                                            abs_descendants: &mut Descendants,
                                                            first_fragment: &mut bool) {
-        match kid.swap_out_construction_result() {
+        let construction_result = match kid.swap_out_construction_result() {
+            ConstructionItemConstructionResult(TableColumnFragmentConstructionItem(fragment)) => {
+                let colgroup_flow = self.build_anonymous_colgroup_flow(node, fragment);
+                FlowConstructionResult(colgroup_flow, Descendants::new())
+            }
+            construction_result => construction_result,
+        };
+        match construction_result {
             NoConstructionResult => {}
             FlowConstructionResult(kid_flow, kid_abs_descendants) => {
                 // If kid_flow is TableCaptionFlow, kid_flow should be added under
@@ -327,6 +406,14 @@ impl<'a> FlowConstructor<'a> {
                             kid_flow,
                             Descendants::new()))
                 } else if flow.get().need_anonymous_flow(kid_flow.get()) {
+                    // Flush any inline fragments first so that they stay in order with the
+                    // anonymous flow that will be generated around this one.
+                    self.flush_inline_fragments_to_flow_or_list(
+                        mem::replace(inline_fragment_accumulator, InlineFragmentsAccumulator::new()),
+                        flow,
+                        consecutive_siblings,
+                        NoWhitespaceStripping,
+                        node);
                     consecutive_siblings.push(kid_flow)
                 } else {
                     // Strip ignorable whitespace from the start of this flow per CSS 2.1 §
@@ -348,12 +435,7 @@ impl<'a> FlowConstructor<'a> {
                         consecutive_siblings,
                         whitespace_stripping,
                         node);
-                    if !consecutive_siblings.is_empty() {
-                        let consecutive_siblings = mem::replace(consecutive_siblings, vec!());
-                        self.generate_anonymous_missing_child(consecutive_siblings,
-                                                              flow,
-                                                              node);
-                    }
+                    self.flush_anonymous_missing_children(consecutive_siblings, flow, node);
                     flow.add_new_child(kid_flow);
                 }
                 abs_descendants.push_descendants(kid_abs_descendants);
@@ -399,6 +481,7 @@ impl<'a> FlowConstructor<'a> {
                     if flow.get().need_anonymous_flow(kid_flow.get()) {
                         consecutive_siblings.push(kid_flow)
                     } else {
+                        self.flush_anonymous_missing_children(consecutive_siblings, flow, node);
                         flow.add_new_child(kid_flow)
                     }
                 }
@@ -411,8 +494,7 @@ impl<'a> FlowConstructor<'a> {
                 // Nothing to do here.
             }
             ConstructionItemConstructionResult(TableColumnFragmentConstructionItem(_)) => {
-                // TODO: Implement anonymous table objects for missing parents
-                // CSS 2.1 § 17.2.1, step 3-2
+                fail!("table columns should have been wrapped in column groups above")
             }
         }
     }
@@ -433,7 +515,15 @@ impl<'a> FlowConstructor<'a> {
 
         // List of absolute descendants, in tree order.
         let mut abs_descendants = Descendants::new();
+
+        // Replaced elements such as `select` and `textarea` draw their children themselves, if
+        // at all, so the flows of their children are thrown away.
+        let is_replaced_element = node.node_is_element() && node.is_replaced_content();
         for kid in node.children() {
+            if is_replaced_element {
+                drop(kid.swap_out_construction_result());
+                continue
+            }
             if kid.get_pseudo_element_type() != Normal {
                 self.process(&kid);
             }
@@ -454,9 +544,7 @@ impl<'a> FlowConstructor<'a> {
                                                 &mut consecutive_siblings,
                                                 StripWhitespaceFromEnd,
                                                 node);
-        if !consecutive_siblings.is_empty() {
-            self.generate_anonymous_missing_child(consecutive_siblings, &mut flow, node);
-        }
+        self.flush_anonymous_missing_children(&mut consecutive_siblings, &mut flow, node);
 
         // The flow is done.
         flow.finish(self.layout_context);
@@ -494,6 +582,36 @@ impl<'a> FlowConstructor<'a> {
         self.build_flow_using_children(FlowRef::new(flow), node)
     }
 
+    /// Builds a flow for a node with `display: flex`. This yields a `FlexFlow` whose children are
+    /// the flex items.
+    fn build_flow_for_flex(&mut self, node: &ThreadSafeLayoutNode) -> ConstructionResult {
+        let flow = box FlexFlow::from_node(self, node) as Box<Flow>;
+        self.build_flow_using_children(FlowRef::new(flow), node)
+    }
+
+    /// Builds the flow for a flex container with `float: {left|right}`.
+    fn build_flow_for_floated_flex(&mut self, node: &ThreadSafeLayoutNode, float_kind: FloatKind)
+                                   -> ConstructionResult {
+        let flow = box FlexFlow::float_from_node(self, node, float_kind) as Box<Flow>;
+        self.build_flow_using_children(FlowRef::new(flow), node)
+    }
+
+    /// Builds a flow for a block with `column-count` or `column-width` set. This yields a
+    /// `MulticolFlow` whose children are laid out in columns.
+    fn build_flow_for_multicol(&mut self, node: &ThreadSafeLayoutNode) -> ConstructionResult {
+        let flow = box MulticolFlow::from_node(self, node) as Box<Flow>;
+        self.build_flow_using_children(FlowRef::new(flow), node)
+    }
+
+    /// Builds the flow for a multi-column block with `float: {left|right}`.
+    fn build_flow_for_floated_multicol(&mut self,
+                                       node: &ThreadSafeLayoutNode,
+                                       float_kind: FloatKind)
+                                       -> ConstructionResult {
+        let flow = box MulticolFlow::float_from_node(self, node, float_kind) as Box<Flow>;
+        self.build_flow_using_children(FlowRef::new(flow), node)
+    }
+
     /// Concatenates the fragments of kids, adding in our own borders/padding/margins if necessary.
     /// Returns the `InlineFragmentsConstructionResult`, if any. There will be no
     /// `InlineFragmentsConstructionResult` if this node consisted entirely of ignorable whitespace.
@@ -503,14 +621,39 @@ impl<'a> FlowConstructor<'a> {
         let mut fragment_accumulator = InlineFragmentsAccumulator::from_inline_node(node);
         let mut abs_descendants = Descendants::new();
 
+        // Table-internal kids get an anonymous inline table around them, per CSS 2.1 § 17.2.1,
+        // step 3. Whitespace after them is held back, since it is dropped if another
+        // table-internal kid follows.
+        let mut table_kid_flows = vec!();
+        let mut table_trailing_whitespace = None;
+
         // Concatenate all the fragments of our kids, creating {ib} splits as necessary.
         for kid in node.children() {
             if kid.get_pseudo_element_type() != Normal {
                 self.process(&kid);
             }
-            match kid.swap_out_construction_result() {
+            let construction_result = match kid.swap_out_construction_result() {
+                ConstructionItemConstructionResult(
+                        TableColumnFragmentConstructionItem(fragment)) => {
+                    let colgroup_flow = self.build_anonymous_colgroup_flow(node, fragment);
+                    FlowConstructionResult(colgroup_flow, Descendants::new())
+                }
+                construction_result => construction_result,
+            };
+            match construction_result {
                 NoConstructionResult => {}
                 FlowConstructionResult(flow, kid_abs_descendants) => {
+                    if flow.get().is_proper_table_child() || flow.get().is_table_cell() {
+                        table_kid_flows.push(flow);
+                        table_trailing_whitespace = None;
+                        abs_descendants.push_descendants(kid_abs_descendants);
+                        continue
+                    }
+                    self.flush_anonymous_inline_table(&mut table_kid_flows,
+                                                      &mut table_trailing_whitespace,
+                                                      &mut fragment_accumulator,
+                                                      node);
+
                     // {ib} split. Flush the accumulator to our new split and make a new
                     // accumulator to hold any subsequent fragments we come across.
                     let split = InlineBlockSplit {
@@ -528,6 +671,10 @@ impl<'a> FlowConstructor<'a> {
                             fragments: successors,
                             abs_descendants: kid_abs_descendants,
                         })) => {
+                    self.flush_anonymous_inline_table(&mut table_kid_flows,
+                                                      &mut table_trailing_whitespace,
+                                                      &mut fragment_accumulator,
+                                                      node);
 
                     // Bubble up {ib} splits.
                     for split in splits.move_iter() {
@@ -554,19 +701,23 @@ impl<'a> FlowConstructor<'a> {
                 ConstructionItemConstructionResult(WhitespaceConstructionItem(whitespace_node,
                                                                               whitespace_style))
                         => {
-                    // Instantiate the whitespace fragment.
-                    let fragment_info = UnscannedTextFragment(UnscannedTextFragmentInfo::from_text(" ".to_string()));
-                    let fragment = Fragment::from_opaque_node_and_style(whitespace_node,
-                                                                        whitespace_style.clone(),
-                                                                        fragment_info);
-                    fragment_accumulator.fragments.push(fragment, whitespace_style)
+                    if table_kid_flows.is_empty() {
+                        push_whitespace_fragment(&mut fragment_accumulator,
+                                                 whitespace_node,
+                                                 whitespace_style)
+                    } else {
+                        table_trailing_whitespace = Some((whitespace_node, whitespace_style))
+                    }
                 }
                 ConstructionItemConstructionResult(TableColumnFragmentConstructionItem(_)) => {
-                    // TODO: Implement anonymous table objects for missing parents
-                    // CSS 2.1 § 17.2.1, step 3-2
+                    fail!("table columns should have been wrapped in column groups above")
                 }
             }
         }
+        self.flush_anonymous_inline_table(&mut table_kid_flows,
+                                          &mut table_trailing_whitespace,
+                                          &mut fragment_accumulator,
+                                          node);
 
         // Finally, make a new construction result.
         if opt_inline_block_splits.len() > 0 || fragment_accumulator.fragments.len() > 0
@@ -582,6 +733,39 @@ impl<'a> FlowConstructor<'a> {
         }
     }
 
+    /// Wraps the pending table-internal kids of an inline in an anonymous inline table, per CSS
+    /// 2.1 § 17.2.1, step 3, followed by any whitespace that was held back after them.
+    fn flush_anonymous_inline_table(&mut self,
+                                    table_kid_flows: &mut Vec<FlowRef>,
+                                    trailing_whitespace:
+                                    &mut Option<(OpaqueNode, Arc<ComputedValues>)>,
+                                    fragment_accumulator: &mut InlineFragmentsAccumulator,
+                                    node: &ThreadSafeLayoutNode) {
+        if table_kid_flows.is_empty() {
+            return
+        }
+
+        let table_fragment = Fragment::new_anonymous_table_fragment(node, TableFragment);
+        let table_flow = box TableFlow::from_node_and_fragment(node, table_fragment);
+        let table_flow = FlowRef::new(table_flow as Box<Flow>);
+        let wrapper_flow = self.build_anonymous_table_object(mem::replace(table_kid_flows, vec!()),
+                                                             table_flow,
+                                                             node);
+
+        // The wrapper flow is laid out like the block flow of an inline block.
+        let fragment_info = InlineBlockFragment(InlineBlockFragmentInfo::new(wrapper_flow));
+        let fragment = Fragment::new_anonymous_table_fragment(node, fragment_info);
+        let style = fragment.style.clone();
+        fragment_accumulator.fragments.push(fragment, style);
+
+        match trailing_whitespace.take() {
+            Some((whitespace_node, whitespace_style)) => {
+                push_whitespace_fragment(fragment_accumulator, whitespace_node, whitespace_style)
+            }
+            None => {}
+        }
+    }
+
     /// Creates an `InlineFragmentsConstructionResult` for replaced content. Replaced content doesn't
     /// render its children, so this just nukes a child's fragments and creates a `Fragment`.
     fn build_fragments_for_replaced_inline_content(&mut self, node: &ThreadSafeLayoutNode)
@@ -625,31 +809,93 @@ impl<'a> FlowConstructor<'a> {
         }
     }
 
-    /// TableCaptionFlow is populated underneath TableWrapperFlow
+    /// Builds an `InlineFragmentsConstructionResult` for a node with `display: inline-block` or
+    /// `display: inline-flex`. The contents are laid out by a `BlockFlow` or a `FlexFlow` that is
+    /// referenced by the resulting fragment.
+    fn build_fragment_for_inline_block(&mut self, node: &ThreadSafeLayoutNode)
+                                       -> ConstructionResult {
+        let construction_result = if node.style().get_box().display == display::inline_flex {
+            self.build_flow_for_flex(node)
+        } else {
+            self.build_flow_for_block(node)
+        };
+        let (block_flow, abs_descendants) = match construction_result {
+            FlowConstructionResult(block_flow, abs_descendants) => (block_flow, abs_descendants),
+            _ => fail!("building an inline block should have produced a flow"),
+        };
+        node.set_last_flow(block_flow.clone());
+
+        let fragment_info = InlineBlockFragment(InlineBlockFragmentInfo::new(block_flow));
+        let fragment = Fragment::new_from_specific_info(node, fragment_info);
+
+        // The block flow draws the borders and padding of the inline block, so don't give the
+        // fragment range a style that has them.
+        let mut fragments = InlineFragments::new();
+        fragments.push(fragment, Arc::new(cascade_anonymous(&**node.style())));
+
+        let construction_item = InlineFragmentsConstructionItem(InlineFragmentsConstructionResult {
+            splits: Vec::new(),
+            fragments: fragments,
+            abs_descendants: abs_descendants,
+        });
+        ConstructionItemConstructionResult(construction_item)
+    }
+
+    /// TableCaptionFlow is populated underneath TableWrapperFlow. The captions with
+    /// `caption-side: bottom` go after the table, so they are returned instead.
     fn place_table_caption_under_table_wrapper(&mut self,
                                                table_wrapper_flow: &mut FlowRef,
-                                               node: &ThreadSafeLayoutNode) {
+                                               node: &ThreadSafeLayoutNode)
+                                               -> Vec<FlowRef> {
+        let mut bottom_captions = vec!();
         for kid in node.children() {
             match kid.swap_out_construction_result() {
                 NoConstructionResult | ConstructionItemConstructionResult(_) => {}
-                FlowConstructionResult(kid_flow, _) => {
+                FlowConstructionResult(mut kid_flow, _) => {
                     // Only kid flows with table-caption are matched here.
                     assert!(kid_flow.get().is_table_caption());
-                    table_wrapper_flow.add_new_child(kid_flow);
+                    if is_bottom_caption(&mut kid_flow) {
+                        bottom_captions.push(kid_flow)
+                    } else {
+                        table_wrapper_flow.add_new_child(kid_flow)
+                    }
                 }
             }
         }
+        bottom_captions
     }
 
-    /// Generates an anonymous table flow according to CSS 2.1 § 17.2.1, step 2.
+    /// Generates an anonymous table flow according to CSS 2.1 § 17.2.1, steps 2 and 3.
     /// If necessary, generate recursively another anonymous table flow.
     fn generate_anonymous_missing_child(&mut self,
                                         child_flows: Vec<FlowRef>,
                                         flow: &mut FlowRef,
                                         node: &ThreadSafeLayoutNode) {
-        let mut anonymous_flow = flow.get().generate_missing_child_flow(node);
+        let anonymous_flow = flow.get().generate_missing_child_flow(node);
+        let anonymous_flow = self.build_anonymous_table_object(child_flows, anonymous_flow, node);
+        flow.add_new_child(anonymous_flow);
+    }
+
+    /// Adds `child_flows` to the anonymous table object `anonymous_flow`, generating further
+    /// anonymous table objects between them as needed. An anonymous table is returned inside its
+    /// anonymous table wrapper.
+    fn build_anonymous_table_object(&mut self,
+                                    child_flows: Vec<FlowRef>,
+                                    mut anonymous_flow: FlowRef,
+                                    node: &ThreadSafeLayoutNode)
+                                    -> FlowRef {
         let mut consecutive_siblings = vec!();
-        for kid_flow in child_flows.move_iter() {
+        let mut captions = vec!();
+        let mut bottom_captions = vec!();
+        for mut kid_flow in child_flows.move_iter() {
+            if anonymous_flow.get().is_table() && kid_flow.get().is_table_caption() {
+                if is_bottom_caption(&mut kid_flow) {
+                    bottom_captions.push(kid_flow)
+                } else {
+                    captions.push(kid_flow)
+                }
+                continue;
+            }
             if anonymous_flow.get().need_anonymous_flow(kid_flow.get()) {
                 consecutive_siblings.push(kid_flow);
                 continue;
@@ -667,7 +913,23 @@ impl<'a> FlowConstructor<'a> {
         }
         // The flow is done.
         anonymous_flow.finish(self.layout_context);
-        flow.add_new_child(anonymous_flow);
+
+        // An anonymous table needs an anonymous table wrapper, which also holds the captions.
+        if anonymous_flow.get().is_table() {
+            let fragment = Fragment::new_anonymous_table_fragment(node, TableWrapperFragment);
+            let wrapper_flow = box TableWrapperFlow::from_node_and_fragment(node, fragment);
+            let mut wrapper_flow = FlowRef::new(wrapper_flow as Box<Flow>);
+            for caption in captions.move_iter() {
+                wrapper_flow.add_new_child(caption);
+            }
+            wrapper_flow.add_new_child(anonymous_flow);
+            for caption in bottom_captions.move_iter() {
+                wrapper_flow.add_new_child(caption);
+            }
+            wrapper_flow.finish(self.layout_context);
+            anonymous_flow = wrapper_flow;
+        }
+        anonymous_flow
     }
 
     /// Builds a flow for a node with `display: table`. This yields a `TableWrapperFlow` with possibly
@@ -685,13 +947,14 @@ impl<'a> FlowConstructor<'a> {
         // We then populate the TableWrapperFlow with TableCaptionFlow, and attach
         // the TableFlow to the TableWrapperFlow
         let construction_result = self.build_flow_using_children(table_flow, node);
-        self.place_table_caption_under_table_wrapper(&mut wrapper_flow, node);
+        let bottom_captions = self.place_table_caption_under_table_wrapper(&mut wrapper_flow, node);
 
         let mut abs_descendants = Descendants::new();
         let mut fixed_descendants = Descendants::new();
 
         // NOTE: The order of captions and table are not the same order as in the DOM tree.
-        // All caption blocks are placed before the table flow
+        // Caption blocks are placed before the table flow, or after it if they have
+        // `caption-side: bottom`.
         match construction_result {
             FlowConstructionResult(table_flow, table_abs_descendants) => {
                 wrapper_flow.add_new_child(table_flow);
@@ -699,6 +962,9 @@ impl<'a> FlowConstructor<'a> {
             }
             _ => {}
         }
+        for caption in bottom_captions.move_iter() {
+            wrapper_flow.add_new_child(caption);
+        }
 
         // The flow is done.
         wrapper_flow.finish(self.layout_context);
@@ -812,6 +1078,32 @@ impl<'a> PostorderNodeMutTraversal for FlowConstructor<'a> {
     // reason LLVM's inlining heuristics go awry here.
     #[inline(always)]
     fn process(&mut self, node: &ThreadSafeLayoutNode) -> bool {
+        // Reuse the flow from the last flow construction if nothing in this subtree changed.
+        if node.get_pseudo_element_type() == Normal {
+            let mut damage = node.restyle_damage();
+            for kid in node.children() {
+                damage.insert(kid.restyle_damage().propagate_up())
+            }
+            node.set_restyle_damage(damage);
+
+            if damage.is_empty() && self.layout_context.reuse_flows {
+                match node.reusable_flow() {
+                    Some(flow) => {
+                        for kid in node.children() {
+                            drop(kid.swap_out_construction_result())
+                        }
+                        node.set_flow_construction_result(FlowConstructionResult(
+                                flow,
+                                Descendants::new()));
+                        return true
+                    }
+                    None => {}
+                }
+            }
+
+            node.detach_last_flow();
+        }
+
         // Get the `display` property for this node, and determine whether this node is floated.
         let (display, float, positioning) = match node.type_id() {
             None => {
@@ -851,6 +1143,25 @@ impl<'a> PostorderNodeMutTraversal for FlowConstructor<'a> {
                 node.set_flow_construction_result(construction_result)
             }
 
+            // Flex containers contribute flex flow construction results.
+            (display::flex, float::none, _) => {
+                let construction_result = self.build_flow_for_flex(node);
+                node.set_flow_construction_result(construction_result)
+            }
+
+            // Inline-level flex containers are atomic inline-level content, like inline blocks.
+            (display::inline_flex, float::none, _) => {
+                let construction_result = self.build_fragment_for_inline_block(node);
+                node.set_flow_construction_result(construction_result)
+            }
+
+            // Floated flex containers contribute floated flex flow construction results.
+            (display::flex, float_value, _) | (display::inline_flex, float_value, _) => {
+                let float_kind = FloatKind::from_property(float_value);
+                let construction_result = self.build_flow_for_floated_flex(node, float_kind);
+                node.set_flow_construction_result(construction_result)
+            }
+
             // Absolutely positioned elements will have computed value of
             // `float` as 'none' and `display` as per the table.
             // Only match here for block items. If an item is absolutely
@@ -858,7 +1169,12 @@ impl<'a> PostorderNodeMutTraversal for FlowConstructor<'a> {
             // flow here - instead, let it match the inline case
             // below.
             (display::block, _, position::absolute) | (_, _, position::fixed) => {
-                node.set_flow_construction_result(self.build_flow_for_block(node))
+                let construction_result = if is_multicol(node) {
+                    self.build_flow_for_multicol(node)
+                } else {
+                    self.build_flow_for_block(node)
+                };
+                node.set_flow_construction_result(construction_result)
             }
 
             // Inline items contribute inline fragment construction results.
@@ -867,6 +1183,17 @@ impl<'a> PostorderNodeMutTraversal for FlowConstructor<'a> {
                 node.set_flow_construction_result(construction_result)
             }
 
+            // Inline blocks contribute inline fragment construction results too. Replaced inline
+            // blocks are just like replaced inlines.
+            (display::inline_block, float::none, _) => {
+                let construction_result = if node.is_replaced_content() {
+                    self.build_fragments_for_replaced_inline_content(node)
+                } else {
+                    self.build_fragment_for_inline_block(node)
+                };
+                node.set_flow_construction_result(construction_result)
+            }
+
             // Table items contribute table flow construction results.
             (display::table_caption, _, _) => {
                 let construction_result = self.build_flow_for_table_caption(node);
@@ -910,21 +1237,57 @@ impl<'a> PostorderNodeMutTraversal for FlowConstructor<'a> {
             // properties separately.
 
             (_, float::none, _) => {
-                node.set_flow_construction_result(self.build_flow_for_block(node))
+                let construction_result = if is_multicol(node) {
+                    self.build_flow_for_multicol(node)
+                } else {
+                    self.build_flow_for_block(node)
+                };
+                node.set_flow_construction_result(construction_result)
             }
 
             // Floated flows contribute float flow construction results.
             (_, float_value, _) => {
                 let float_kind = FloatKind::from_property(float_value);
-                node.set_flow_construction_result(
-                    self.build_flow_for_floated_block(node, float_kind))
+                let construction_result = if is_multicol(node) {
+                    self.build_flow_for_floated_multicol(node, float_kind)
+                } else {
+                    self.build_flow_for_floated_block(node, float_kind)
+                };
+                node.set_flow_construction_result(construction_result)
             }
         }
 
+        if node.get_pseudo_element_type() == Normal {
+            node.remember_last_flow();
+        }
         true
     }
 }
 
+/// Returns true if the given block is a multi-column container.
+/// Adds a fragment for collapsible whitespace to the fragments of an inline.
+fn push_whitespace_fragment(fragment_accumulator: &mut InlineFragmentsAccumulator,
+                            whitespace_node: OpaqueNode,
+                            whitespace_style: Arc<ComputedValues>) {
+    let fragment_info =
+        UnscannedTextFragment(UnscannedTextFragmentInfo::from_text(" ".to_string()));
+    let fragment = Fragment::from_opaque_node_and_style(whitespace_node,
+                                                        whitespace_style.clone(),
+                                                        fragment_info);
+    fragment_accumulator.fragments.push(fragment, whitespace_style)
+}
+
+fn is_multicol(node: &ThreadSafeLayoutNode) -> bool {
+    let column = node.style().get_column();
+    column.column_count != column_count::Auto || column.column_width != column_width::Auto
+}
+
+/// Returns true if the given table caption flow has `caption-side: bottom`.
+fn is_bottom_caption(caption_flow: &mut FlowRef) -> bool {
+    let style = caption_flow.get_mut().as_block().fragment.style();
+    style.get_inheritedtable().caption_side == caption_side::bottom
+}
+
 /// A utility trait with some useful methods for node queries.
 trait NodeUtils {
     /// Returns true if this node doesn't render its kids and false otherwise.
@@ -936,6 +1299,24 @@ trait NodeUtils {
     /// Replaces the flow construction result in a node with `NoConstructionResult` and returns the
     /// old value.
     fn swap_out_construction_result(&self) -> ConstructionResult;
+
+    /// Returns the flow that this node produced during the last flow construction, if it can be
+    /// reused.
+    fn reusable_flow(&self) -> Option<FlowRef>;
+
+    /// Empties the child list of the flow that this node produced during the last flow
+    /// construction and forgets that flow. This must happen before a new flow is built for the
+    /// node, because dropping a flow list rewrites the sibling links of the flows in it, some of
+    /// which are about to be moved into the new flow.
+    fn detach_last_flow(&self);
+
+    /// Remembers the flow that this node just produced, if any, for the next flow construction.
+    fn remember_last_flow(&self);
+
+    /// Remembers a flow that this node produced but that is not its flow construction result, such
+    /// as the block flow of an inline block, so that its children can be detached later. Such
+    /// flows are never reused.
+    fn set_last_flow(&self, flow: FlowRef);
 }
 
 impl<'ln> NodeUtils for ThreadSafeLayoutNode<'ln> {
@@ -948,7 +1329,10 @@ impl<'ln> NodeUtils for ThreadSafeLayoutNode<'ln> {
             Some(DocumentFragmentNodeTypeId) |
             Some(DocumentNodeTypeId) |
             None |
-            Some(ElementNodeTypeId(HTMLImageElementTypeId)) => true,
+            Some(ElementNodeTypeId(HTMLImageElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLInputElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLSelectElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLTextAreaElementTypeId)) => true,
             Some(ElementNodeTypeId(HTMLObjectElementTypeId)) => self.has_object_data(),
             Some(ElementNodeTypeId(_)) => false,
         }
@@ -996,6 +1380,58 @@ impl<'ln> NodeUtils for ThreadSafeLayoutNode<'ln> {
             &None => fail!("no layout data"),
         }
     }
+
+    fn reusable_flow(&self) -> Option<FlowRef> {
+        let layout_data_ref = self.borrow_layout_data();
+        match &*layout_data_ref {
+            &Some(ref layout_data) if layout_data.data.last_flow_is_reusable => {
+                layout_data.data.last_flow.clone()
+            }
+            &Some(_) => None,
+            &None => fail!("no layout data"),
+        }
+    }
+
+    fn detach_last_flow(&self) {
+        let last_flow = {
+            let mut layout_data_ref = self.mutate_layout_data();
+            match &mut *layout_data_ref {
+                &Some(ref mut layout_data) => layout_data.data.last_flow.take(),
+                &None => fail!("no layout data"),
+            }
+        };
+        match last_flow {
+            Some(mut flow) => flow::mut_base(flow.get_mut()).children = FlowList::new(),
+            None => {}
+        }
+    }
+
+    fn remember_last_flow(&self) {
+        let mut layout_data_ref = self.mutate_layout_data();
+        match &mut *layout_data_ref {
+            &Some(ref mut layout_data) => {
+                match layout_data.data.flow_construction_result {
+                    FlowConstructionResult(ref flow, ref abs_descendants) => {
+                        layout_data.data.last_flow = Some(flow.clone());
+                        layout_data.data.last_flow_is_reusable = abs_descendants.len() == 0
+                    }
+                    _ => {}
+                }
+            }
+            &None => fail!("no layout data"),
+        }
+    }
+
+    fn set_last_flow(&self, flow: FlowRef) {
+        let mut layout_data_ref = self.mutate_layout_data();
+        match &mut *layout_data_ref {
+            &Some(ref mut layout_data) => {
+                layout_data.data.last_flow = Some(flow);
+                layout_data.data.last_flow_is_reusable = false
+            }
+            &None => fail!("no layout data"),
+        }
+    }
 }
 
 /// Methods for interacting with HTMLObjectElement nodes
@@ -1051,7 +1487,10 @@ impl FlowConstructionUtils for FlowRef {
     /// This must not be public because only the layout constructor can do this.
     fn add_new_child(&mut self, mut new_child: FlowRef) {
         {
+            // A reused flow still has the sibling links of the flow tree it was taken from.
             let kid_base = flow::mut_base(new_child.get_mut());
+            kid_base.next_sibling = None;
+            kid_base.prev_sibling = None;
             kid_base.parallel.parent = parallel::mut_owned_flow_to_unsafe_flow(self);
         }
 
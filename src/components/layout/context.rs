@@ -86,6 +86,14 @@ pub struct LayoutContext {
 
     /// The dirty rectangle, used during display list building.
     pub dirty: Rect<Au>,
+
+    /// Whether flow construction may reuse the flows of nodes whose subtrees have no restyle
+    /// damage. This is false if the content changed in a way that styles do not reflect.
+    pub reuse_flows: bool,
+
+    /// Whether every node has to be restyled. If not, only the nodes that restyle hints point at
+    /// and the nodes whose inherited style changed are matched and cascaded again.
+    pub restyle_all: bool,
 }
 
 #[cfg(not(target_os="android"))]
@@ -8,6 +8,7 @@ use css::node_style::StyledNode;
 use construct::FlowConstructor;
 use context::LayoutContext;
 use extra::LayoutAuxMethods;
+use incremental::{RestyleDamage, compute_damage};
 use util::{LayoutDataAccess, LayoutDataWrapper};
 use wrapper::{LayoutElement, LayoutNode, PostorderNodeMutTraversal, ThreadSafeLayoutNode};
 
@@ -20,6 +21,8 @@ use std::mem;
 use std::hash::{Hash, sip};
 use std::slice::Items;
 use style::{After, Before, ComputedValues, MatchedProperty, Stylist, TElement, TNode, cascade};
+use style::{RestyleHint, RestyleSelf, RestyleDescendants, RestyleLaterSiblings};
+use style::{PropertyDeclaration, synthesize_presentational_hints};
 use sync::Arc;
 
 pub struct ApplicableDeclarations {
@@ -167,6 +170,9 @@ pub struct StyleSharingCandidate {
     pub local_name: DOMString,
 
     pub class: Option<DOMString>,
+
+    /// The `dir` attribute, which the user agent style sheet matches on.
+    pub dir: Option<DOMString>,
 }
 
 impl PartialEq for StyleSharingCandidate {
@@ -174,7 +180,8 @@ impl PartialEq for StyleSharingCandidate {
         arc_ptr_eq(&self.style, &other.style) &&
             arc_ptr_eq(&self.parent_style, &other.parent_style) &&
             self.local_name == other.local_name &&
-            self.class == other.class
+            self.class == other.class &&
+            self.dir == other.dir
     }
 }
 
@@ -227,6 +234,8 @@ impl StyleSharingCandidate {
             local_name: element.get_local_name().to_str(),
             class: element.get_attr(&Null, "class")
                           .map(|string| string.to_str()),
+            dir: element.get_attr(&Null, "dir")
+                        .map(|string| string.to_str()),
         })
     }
 
@@ -241,6 +250,9 @@ impl StyleSharingCandidate {
             }
             (&Some(_), Some(_)) | (&None, None) => {}
         }
+        if element.get_attr(&Null, "dir") != self.dir.as_ref().map(|dir| dir.as_slice()) {
+            return false
+        }
         true
     }
 }
@@ -280,6 +292,32 @@ pub enum StyleSharingResult<'ln> {
     StyleWasShared(uint),
 }
 
+/// The styles a node had before it was restyled. These are compared against the new styles to
+/// determine how much of the node's layout can be reused.
+pub struct PreviousStyles {
+    style: Option<Arc<ComputedValues>>,
+    before_style: Option<Arc<ComputedValues>>,
+    after_style: Option<Arc<ComputedValues>>,
+}
+
+/// Computes the damage from a change from `old` to `new`. Gaining or losing a style (for example,
+/// a pseudo-element that starts or stops matching) requires everything to be redone.
+fn compute_damage_for_styles(old: &Option<Arc<ComputedValues>>,
+                             new: &Option<Arc<ComputedValues>>)
+                             -> RestyleDamage {
+    match (old, new) {
+        (&None, &None) => RestyleDamage::empty(),
+        (&Some(ref old), &Some(ref new)) => {
+            if arc_ptr_eq(old, new) {
+                RestyleDamage::empty()
+            } else {
+                compute_damage(&**old, &**new)
+            }
+        }
+        _ => RestyleDamage::all(),
+    }
+}
+
 pub trait MatchMethods {
     /// Performs aux initialization, selector matching, cascading, and flow construction
     /// sequentially.
@@ -293,8 +331,13 @@ pub trait MatchMethods {
                                 parent: Option<LayoutNode>)
                                 -> Box<FontContext>;
 
+    /// Returns the presentational hints of this node, if it is an element. These are computed
+    /// once and then passed to both `share_style_if_possible()` and `match_node()`.
+    fn presentational_hints(&self) -> Vec<PropertyDeclaration>;
+
     fn match_node(&self,
                   stylist: &Stylist,
+                  presentational_hints: Vec<PropertyDeclaration>,
                   applicable_declarations: &mut ApplicableDeclarations,
                   shareable: &mut bool);
 
@@ -304,13 +347,31 @@ pub trait MatchMethods {
     unsafe fn share_style_if_possible(&self,
                                       style_sharing_candidate_cache:
                                         &mut StyleSharingCandidateCache,
-                                      parent: Option<LayoutNode>)
+                                      parent: Option<LayoutNode>,
+                                      presentational_hints: &[PropertyDeclaration])
                                       -> StyleSharingResult;
 
     unsafe fn cascade_node(&self,
                            parent: Option<LayoutNode>,
                            applicable_declarations: &ApplicableDeclarations,
                            applicable_declarations_cache: &mut ApplicableDeclarationsCache);
+
+    /// Returns the styles this node had before restyling. Must be called before the node is
+    /// cascaded or shares a style.
+    fn previous_styles(&self) -> PreviousStyles;
+
+    /// Compares the node's new styles with `previous` and stores the resulting restyle damage.
+    /// Nodes that script marked dirty, and nodes that have never been styled, get full damage.
+    fn store_restyle_damage(&self, previous: PreviousStyles);
+
+    /// Adds a restyle hint that a change to this node produced. Later siblings are restyled
+    /// along with their descendants.
+    fn add_restyle_hint(&self, hint: RestyleHint);
+
+    /// Returns true if this node has to be matched and cascaded again: because every node has
+    /// to be, because a restyle hint says so, or because the style it inherits changed. Passes
+    /// `RestyleDescendants` hints on to the children and clears the hint of this node.
+    fn needs_restyle(&self, parent: Option<LayoutNode>, restyle_all: bool) -> bool;
 }
 
 trait PrivateMatchMethods {
@@ -408,8 +469,17 @@ impl<'ln> PrivateMatchMethods for LayoutNode<'ln> {
 }
 
 impl<'ln> MatchMethods for LayoutNode<'ln> {
+    fn presentational_hints(&self) -> Vec<PropertyDeclaration> {
+        if self.is_element() {
+            synthesize_presentational_hints(self)
+        } else {
+            vec!()
+        }
+    }
+
     fn match_node(&self,
                   stylist: &Stylist,
+                  presentational_hints: Vec<PropertyDeclaration>,
                   applicable_declarations: &mut ApplicableDeclarations,
                   shareable: &mut bool) {
         let style_attribute = self.as_element().style_attribute().as_ref();
@@ -417,14 +487,17 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
         applicable_declarations.normal_shareable =
             stylist.push_applicable_declarations(self,
                                                  style_attribute,
+                                                 presentational_hints,
                                                  None,
                                                  &mut applicable_declarations.normal);
         stylist.push_applicable_declarations(self,
                                              None,
+                                             vec!(),
                                              Some(Before),
                                              &mut applicable_declarations.before);
         stylist.push_applicable_declarations(self,
                                              None,
+                                             vec!(),
                                              Some(After),
                                              &mut applicable_declarations.after);
 
@@ -434,7 +507,8 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
     unsafe fn share_style_if_possible(&self,
                                       style_sharing_candidate_cache:
                                         &mut StyleSharingCandidateCache,
-                                      parent: Option<LayoutNode>)
+                                      parent: Option<LayoutNode>,
+                                      presentational_hints: &[PropertyDeclaration])
                                       -> StyleSharingResult {
         if !self.is_element() {
             return CannotShare(false)
@@ -443,7 +517,9 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
             let element = self.as_element();
             element.style_attribute().is_none() && element.get_attr(&Null, "id").is_none()
         };
-        if !ok {
+        // Presentational hints can depend on attributes of ancestors other than the parent, so
+        // elements that have any never share.
+        if !ok || !presentational_hints.is_empty() {
             return CannotShare(false)
         }
 
@@ -472,35 +548,46 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
                                 parent: Option<LayoutNode>)
                                 -> Box<FontContext> {
         self.initialize_layout_data(layout_context.layout_chan.clone());
+        let previous_styles = self.previous_styles();
+
+        if self.needs_restyle(parent.clone(), layout_context.restyle_all) {
+            // First, check to see whether we can share a style with someone.
+            let presentational_hints = self.presentational_hints();
+            let sharing_result = unsafe {
+                self.share_style_if_possible(style_sharing_candidate_cache,
+                                             parent.clone(),
+                                             presentational_hints.as_slice())
+            };
 
-        // First, check to see whether we can share a style with someone.
-        let sharing_result = unsafe {
-            self.share_style_if_possible(style_sharing_candidate_cache, parent.clone())
-        };
-
-        // Otherwise, match and cascade selectors.
-        match sharing_result {
-            CannotShare(mut shareable) => {
-                if self.is_element() {
-                    self.match_node(stylist, applicable_declarations, &mut shareable)
-                }
+            // Otherwise, match and cascade selectors.
+            match sharing_result {
+                CannotShare(mut shareable) => {
+                    if self.is_element() {
+                        self.match_node(stylist,
+                                        presentational_hints,
+                                        applicable_declarations,
+                                        &mut shareable)
+                    }
 
-                unsafe {
-                    self.cascade_node(parent,
-                                      applicable_declarations,
-                                      applicable_declarations_cache)
-                }
+                    unsafe {
+                        self.cascade_node(parent,
+                                          applicable_declarations,
+                                          applicable_declarations_cache)
+                    }
 
-                applicable_declarations.clear();
+                    applicable_declarations.clear();
 
-                // Add ourselves to the LRU cache.
-                if shareable {
-                    style_sharing_candidate_cache.insert_if_possible(self)
+                    // Add ourselves to the LRU cache.
+                    if shareable {
+                        style_sharing_candidate_cache.insert_if_possible(self)
+                    }
                 }
+                StyleWasShared(index) => style_sharing_candidate_cache.touch(index),
             }
-            StyleWasShared(index) => style_sharing_candidate_cache.touch(index),
         }
 
+        self.store_restyle_damage(previous_styles);
+
         for kid in self.children() {
             font_context = kid.recalc_style_for_subtree(stylist,
                                                         layout_context,
@@ -558,6 +645,8 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
                                                      &mut layout_data.data.before_style,
                                                      applicable_declarations_cache,
                                                      false);
+                } else {
+                    layout_data.data.before_style = None
                 }
                 if applicable_declarations.after.len() > 0 {
                     self.cascade_node_pseudo_element(parent_style,
@@ -565,6 +654,109 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
                                                      &mut layout_data.data.after_style,
                                                      applicable_declarations_cache,
                                                      false);
+                } else {
+                    layout_data.data.after_style = None
+                }
+            }
+        }
+    }
+
+    fn previous_styles(&self) -> PreviousStyles {
+        let layout_data_ref = self.borrow_layout_data();
+        match &*layout_data_ref {
+            &None => fail!("no layout data"),
+            &Some(ref layout_data) => {
+                PreviousStyles {
+                    style: layout_data.shared_data.style.clone(),
+                    before_style: layout_data.data.before_style.clone(),
+                    after_style: layout_data.data.after_style.clone(),
+                }
+            }
+        }
+    }
+
+    fn store_restyle_damage(&self, previous: PreviousStyles) {
+        let dirty = self.is_dirty();
+        if dirty {
+            self.clear_dirty()
+        }
+
+        let mut layout_data_ref = self.mutate_layout_data();
+        match &mut *layout_data_ref {
+            &None => fail!("no layout data"),
+            &Some(ref mut layout_data) => {
+                let damage = if dirty || previous.style.is_none() {
+                    RestyleDamage::all()
+                } else {
+                    compute_damage_for_styles(&previous.style, &layout_data.shared_data.style) |
+                    compute_damage_for_styles(&previous.before_style,
+                                              &layout_data.data.before_style) |
+                    compute_damage_for_styles(&previous.after_style,
+                                              &layout_data.data.after_style)
+                };
+                layout_data.data.restyle_damage = Some(damage)
+            }
+        }
+    }
+
+    fn add_restyle_hint(&self, hint: RestyleHint) {
+        if hint.contains(RestyleLaterSiblings) {
+            let mut sibling = self.next_sibling();
+            loop {
+                match sibling {
+                    None => break,
+                    Some(node) => {
+                        node.add_restyle_hint(RestyleSelf | RestyleDescendants);
+                        sibling = node.next_sibling()
+                    }
+                }
+            }
+        }
+
+        let mut layout_data_ref = self.mutate_layout_data();
+        match &mut *layout_data_ref {
+            // A node that was never styled is restyled anyway.
+            &None => {}
+            &Some(ref mut layout_data) => {
+                layout_data.data.restyle_hint.insert(hint & (RestyleSelf | RestyleDescendants))
+            }
+        }
+    }
+
+    fn needs_restyle(&self, parent: Option<LayoutNode>, restyle_all: bool) -> bool {
+        let (hint, was_styled) = {
+            let mut layout_data_ref = self.mutate_layout_data();
+            match &mut *layout_data_ref {
+                &None => fail!("no layout data"),
+                &Some(ref mut layout_data) => {
+                    (mem::replace(&mut layout_data.data.restyle_hint, RestyleHint::empty()),
+                     layout_data.shared_data.style.is_some())
+                }
+            }
+        };
+
+        if hint.contains(RestyleDescendants) {
+            for kid in self.children() {
+                kid.add_restyle_hint(RestyleSelf | RestyleDescendants)
+            }
+        }
+
+        if restyle_all || !was_styled || hint.contains(RestyleSelf) {
+            return true
+        }
+
+        // The parent has already been restyled in this traversal, and its restyle damage is
+        // empty only if its style stayed the same.
+        match parent {
+            None => false,
+            Some(parent) => {
+                unsafe {
+                    match *parent.borrow_layout_data_unchecked() {
+                        None => fail!("no parent data?!"),
+                        Some(ref parent_layout_data) => {
+                            parent_layout_data.data.restyle_damage != Some(RestyleDamage::empty())
+                        }
+                    }
                 }
             }
         }
@@ -2,17 +2,31 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use style::{Stylesheet, Stylist, UserAgentOrigin, with_errors_silenced};
+use servo_util::opts::Opts;
+use style::{Device, Print, Screen, Stylesheet, Stylist, UserAgentOrigin, UserOrigin};
+use style::with_errors_silenced;
 use url;
 
 
-pub fn new_stylist() -> Stylist {
-    let mut stylist = Stylist::new();
-    let ua_stylesheet = with_errors_silenced(|| Stylesheet::from_bytes(
-        include_bin!("user-agent.css"),
-        url::from_str("chrome:///user-agent.css").unwrap(),
-        None,
-        None));
-    stylist.add_stylesheet(ua_stylesheet, UserAgentOrigin);
+pub fn new_stylist(opts: &Opts) -> Stylist {
+    let media_type = if opts.paginated { Print } else { Screen };
+    let mut stylist = Stylist::new(Device { media_type: media_type });
+    let ua_stylesheet = match opts.user_agent_stylesheet {
+        // Errors are reported for style sheets given on the command line, since whoever passed
+        // them is likely to want to know about them.
+        Some((ref contents, ref url)) => {
+            Stylesheet::from_bytes(contents.as_slice(), url.clone(), None, None)
+        }
+        None => with_errors_silenced(|| Stylesheet::from_bytes(
+            include_bin!("user-agent.css"),
+            url::from_str("chrome:///user-agent.css").unwrap(),
+            None,
+            None)),
+    };
+    stylist.add_stylesheet(&ua_stylesheet, UserAgentOrigin);
+    for &(ref contents, ref url) in opts.user_stylesheets.iter() {
+        let user_stylesheet = Stylesheet::from_bytes(contents.as_slice(), url.clone(), None, None);
+        stylist.add_stylesheet(&user_stylesheet, UserOrigin);
+    }
     stylist
 }
@@ -0,0 +1,961 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! CSS flexible box layout. See http://dev.w3.org/csswg/css-flexbox/.
+//!
+//! Flex containers are laid out as part of the ordinary flow traversals: widths are bubbled up and
+//! assigned top-down, so the main sizes of the items on a horizontal main axis are resolved while
+//! assigning widths, and everything that depends on the heights of the items is resolved
+//! bottom-up while assigning heights. Nothing here requires an in-order traversal, so flex
+//! containers lay out in parallel like any other formatting context.
+
+#![deny(unsafe_block)]
+
+use block::BlockFlow;
+use construct::FlowConstructor;
+use context::LayoutContext;
+use floats::{FloatKind, Floats};
+use flow::{FlexFlowClass, FlowClass, Flow, ImmutableFlowUtils};
+use flow;
+use fragment::Fragment;
+use model::{IntrinsicWidths, MaybeAuto, Specified, Auto, specified, specified_or_none};
+use model;
+use wrapper::ThreadSafeLayoutNode;
+
+use servo_msg::compositor_msg::LayerId;
+use servo_util::geometry::{Au, LogicalMargin, LogicalPoint, LogicalRect, LogicalSize};
+use servo_util::geometry;
+use std::fmt;
+use style::ComputedValues;
+use style::computed_values::{LPA_Auto, LPA_Length, LPA_Percentage, align_content, align_items};
+use style::computed_values::{align_self, clear, flex_direction, flex_wrap, float};
+use style::computed_values::{justify_content, position};
+use style::CSSFloat;
+use sync::Arc;
+
+/// The information about a flex item that is needed to resolve its size along the main axis and
+/// to position it.
+struct FlexItem {
+    /// The index of the item's flow among the children of the flex container.
+    index: uint,
+
+    /// The `order` property of the item.
+    order: i32,
+
+    /// The `flex-grow` property of the item.
+    grow: CSSFloat,
+
+    /// The `flex-shrink` property of the item.
+    shrink: CSSFloat,
+
+    /// The flex base size of the item's content box.
+    base_size: Au,
+
+    /// The minimum size of the item's content box along the main axis.
+    min_size: Au,
+
+    /// The maximum size of the item's content box along the main axis, if any.
+    max_size: Option<Au>,
+
+    /// The sum of the item's borders and padding along the main axis.
+    border_padding: Au,
+
+    /// The item's top or left margin, depending on the main axis.
+    margin_before: Au,
+
+    /// The item's bottom or right margin, depending on the main axis.
+    margin_after: Au,
+
+    /// The used size of the item's content box along the main axis.
+    main_size: Au,
+
+    /// The position of the item's margin box along the main axis, relative to the main-start
+    /// content edge of the flex container.
+    main_position: Au,
+
+    /// Whether `main_size` is final. Only used while resolving flexible lengths.
+    frozen: bool,
+}
+
+impl FlexItem {
+    /// Creates the flex item for a child of a flex container. `style` is `None` for anonymous
+    /// items wrapping inline content. `is_row` tells whether the main axis is horizontal.
+    /// `container_width` is the content width of the flex container, against which margins and
+    /// padding percentages resolve; `container_main_size` is the size of the flex container along
+    /// the main axis, if it is definite. `content_size` is the size of the content of the item
+    /// along the main axis.
+    fn new(index: uint,
+           style: Option<Arc<ComputedValues>>,
+           is_row: bool,
+           container_width: Au,
+           container_main_size: Option<Au>,
+           content_size: Au)
+           -> FlexItem {
+        let style = match style {
+            None => {
+                return FlexItem {
+                    index: index,
+                    order: 0,
+                    grow: 0.,
+                    shrink: 1.,
+                    base_size: content_size,
+                    min_size: Au(0),
+                    max_size: None,
+                    border_padding: Au(0),
+                    margin_before: Au(0),
+                    margin_after: Au(0),
+                    main_size: content_size,
+                    main_position: Au(0),
+                    frozen: false,
+                }
+            }
+            Some(style) => style,
+        };
+
+        let writing_mode = style.writing_mode();
+        let border_padding = LogicalMargin::from_physical(
+            writing_mode,
+            model::border_from_style(&*style) + model::padding_from_style(&*style,
+                                                                          container_width));
+        let margin = style.logical_margin();
+        let (border_padding, margin_before, margin_after, size, min_size, max_size) = if is_row {
+            (border_padding.inline_start_end(),
+             margin.inline_start,
+             margin.inline_end,
+             style.content_inline_size(),
+             style.min_inline_size(),
+             style.max_inline_size())
+        } else {
+            (border_padding.block_start_end(),
+             margin.block_start,
+             margin.block_end,
+             style.content_block_size(),
+             style.min_block_size(),
+             style.max_block_size())
+        };
+
+        // Percentages along the main axis only apply if the size of the container is definite.
+        let resolve = |length| {
+            match (length, container_main_size) {
+                (LPA_Length(length), _) => Specified(length),
+                (LPA_Percentage(percent), Some(container_main_size)) => {
+                    Specified(container_main_size.scale_by(percent))
+                }
+                (LPA_Percentage(_), None) | (LPA_Auto, _) => Auto,
+            }
+        };
+        let flex = style.get_flex();
+        let base_size = match resolve(flex.flex_basis) {
+            Specified(base_size) => base_size,
+            Auto => resolve(size).specified_or_default(content_size),
+        };
+        let min_size = match container_main_size {
+            Some(container_main_size) => specified(min_size, container_main_size),
+            None => specified(min_size, Au(0)),
+        };
+        let max_size = match container_main_size {
+            Some(container_main_size) => specified_or_none(max_size, container_main_size),
+            None => specified_or_none(max_size, Au(0)),
+        };
+
+        FlexItem {
+            index: index,
+            order: flex.order,
+            grow: flex.flex_grow,
+            shrink: flex.flex_shrink,
+            base_size: base_size,
+            min_size: min_size,
+            max_size: max_size,
+            border_padding: border_padding,
+            margin_before: MaybeAuto::from_style(margin_before, container_width).specified_or_zero(),
+            margin_after: MaybeAuto::from_style(margin_after, container_width).specified_or_zero(),
+            main_size: base_size,
+            main_position: Au(0),
+            frozen: false,
+        }
+    }
+
+    /// Clamps the given content size by the minimum and maximum sizes of this item.
+    fn clamp(&self, size: Au) -> Au {
+        let size = match self.max_size {
+            Some(max_size) => geometry::min(size, max_size),
+            None => size,
+        };
+        geometry::max(size, self.min_size)
+    }
+
+    /// The hypothetical main size of the content box of this item.
+    fn hypothetical_size(&self) -> Au {
+        self.clamp(self.base_size)
+    }
+
+    /// Returns the size of the margin box of this item given the size of its content box.
+    fn outer_size(&self, size: Au) -> Au {
+        size + self.border_padding + self.margin_before + self.margin_after
+    }
+}
+
+/// A line of flex items, in `order`-modified document order.
+struct FlexLine {
+    items: Vec<FlexItem>,
+}
+
+/// A flow that lays out its children according to the flexible box layout model.
+pub struct FlexFlow {
+    /// Data common to all block flows.
+    pub block_flow: BlockFlow,
+
+    /// The lines of items, for flex containers with a horizontal main axis. These are computed
+    /// during width assignment.
+    lines: Vec<FlexLine>,
+}
+
+impl FlexFlow {
+    pub fn from_node(constructor: &mut FlowConstructor, node: &ThreadSafeLayoutNode) -> FlexFlow {
+        FlexFlow {
+            block_flow: BlockFlow::from_node(constructor, node),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn float_from_node(constructor: &mut FlowConstructor,
+                           node: &ThreadSafeLayoutNode,
+                           float_kind: FloatKind)
+                           -> FlexFlow {
+        FlexFlow {
+            block_flow: BlockFlow::float_from_node(constructor, node, float_kind),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Returns true if the main axis of this flex container is horizontal.
+    fn is_row(&self) -> bool {
+        match self.block_flow.fragment.style().get_flex().flex_direction {
+            flex_direction::row | flex_direction::row_reverse => true,
+            flex_direction::column | flex_direction::column_reverse => false,
+        }
+    }
+
+    /// Returns true if the items of this flex container may be broken into several lines.
+    ///
+    /// FIXME: Only horizontal main axes are broken into lines. The lines of a vertical main axis
+    /// depend on the heights of the items, which are only known after the widths of the items,
+    /// and so the widths of the lines, have been assigned; such containers are always laid out as
+    /// a single line, whatever `flex-wrap` says.
+    fn is_multi_line(&self) -> bool {
+        self.is_row() && self.block_flow.fragment.style().get_flex().flex_wrap != flex_wrap::nowrap
+    }
+
+    /// Returns true if items are laid out from the main-end edge of this flex container.
+    fn is_main_axis_reversed(&self) -> bool {
+        match self.block_flow.fragment.style().get_flex().flex_direction {
+            flex_direction::row_reverse | flex_direction::column_reverse => true,
+            flex_direction::row | flex_direction::column => false,
+        }
+    }
+
+    /// Assigns widths to the items of a flex container with a horizontal main axis, breaking
+    /// them into lines if necessary.
+    fn assign_widths_row(&mut self, left_content_edge: Au, content_width: Au) {
+        let multi_line = self.is_multi_line();
+        let justification = self.block_flow.fragment.style().get_flex().justify_content;
+        let reversed = self.is_main_axis_reversed();
+
+        let mut items = Vec::new();
+        for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+            if kid.is_absolutely_positioned() {
+                continue
+            }
+            let content_size = flow::base(kid).intrinsic_widths.preferred_width;
+            items.push(FlexItem::new(index,
+                                     flex_item_style(kid),
+                                     true,
+                                     content_width,
+                                     Some(content_width),
+                                     content_size))
+        }
+        items.sort_by(|a, b| a.order.cmp(&b.order));
+
+        // Collect the items into lines (§ 9.3).
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        let mut line_size = Au(0);
+        for item in items.move_iter() {
+            let item_size = item.outer_size(item.hypothetical_size());
+            if multi_line && !line.is_empty() && line_size + item_size > content_width {
+                lines.push(FlexLine {
+                    items: line,
+                });
+                line = Vec::new();
+                line_size = Au(0);
+            }
+            line_size = line_size + item_size;
+            line.push(item)
+        }
+        if !line.is_empty() {
+            lines.push(FlexLine {
+                items: line,
+            })
+        }
+
+        for line in lines.mut_iter() {
+            resolve_flexible_lengths(line.items.as_mut_slice(), content_width);
+            justify_items(line.items.as_mut_slice(), content_width, justification);
+        }
+
+        let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+        for line in lines.iter() {
+            for item in line.items.iter() {
+                let kid = &mut **kids.get_mut(item.index);
+                let width = item.main_size + item.border_padding;
+                let outer_width = item.outer_size(item.main_size);
+                let x = if reversed {
+                    content_width - item.main_position - outer_width
+                } else {
+                    item.main_position
+                };
+                if kid.is_inline_flow() {
+                    let kid_base = flow::mut_base(kid);
+                    kid_base.position.start.i = left_content_edge + x;
+                    kid_base.position.size.inline = width;
+                } else {
+                    // The containing block of the item stays the content box of the container.
+                    kid.as_block().flex_item_width = Some(width);
+                    flow::mut_base(kid).position.start.i = left_content_edge + x;
+                }
+            }
+        }
+
+        self.lines = lines
+    }
+
+    /// Assigns widths to the items of a flex container with a vertical main axis. This aligns
+    /// each item along the cross axis; sizes along the main axis are resolved when assigning
+    /// heights. The items always form a single line; see `is_multi_line`.
+    fn assign_widths_column(&mut self, left_content_edge: Au, content_width: Au) {
+        let align_items = self.block_flow.fragment.style().get_flex().align_items;
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_absolutely_positioned() {
+                continue
+            }
+
+            let style = match flex_item_style(kid) {
+                None => continue,
+                Some(style) => style,
+            };
+            let border_padding = LogicalMargin::from_physical(
+                style.writing_mode(),
+                model::border_from_style(&*style) + model::padding_from_style(&*style,
+                                                                              content_width));
+            let border_padding = border_padding.inline_start_end();
+            let margin = style.logical_margin();
+            let margin_left =
+                MaybeAuto::from_style(margin.inline_start, content_width).specified_or_zero();
+            let margin_right =
+                MaybeAuto::from_style(margin.inline_end, content_width).specified_or_zero();
+            let available_width = content_width - margin_left - margin_right;
+
+            let alignment = used_alignment(style.get_flex().align_self, align_items);
+            let width = match MaybeAuto::from_style(style.content_inline_size(), content_width) {
+                Specified(width) => width + border_padding,
+                Auto if alignment == align_items::stretch => available_width,
+                Auto => {
+                    // Shrink-to-fit, as for floats.
+                    let intrinsic_widths = &flow::base(kid).intrinsic_widths;
+                    geometry::min(intrinsic_widths.preferred_width + border_padding,
+                                  geometry::max(intrinsic_widths.minimum_width + border_padding,
+                                                available_width))
+                }
+            };
+            let width = match specified_or_none(style.max_inline_size(), content_width) {
+                Some(max_width) => geometry::min(width, max_width + border_padding),
+                None => width,
+            };
+            let width = geometry::max(width,
+                                      specified(style.min_inline_size(), content_width) +
+                                      border_padding);
+
+            let x = align_in_cross_axis(alignment,
+                                        content_width,
+                                        width + margin_left + margin_right,
+                                        false);
+            kid.as_block().flex_item_width = Some(width);
+            flow::mut_base(kid).position.start.i = left_content_edge + x;
+        }
+    }
+
+    /// Assigns heights to the items of a flex container with a horizontal main axis, stretching
+    /// and aligning them within their lines.
+    fn assign_height_row(&mut self, layout_context: &mut LayoutContext) {
+        let top_content_edge = self.block_flow.fragment.border_padding.block_start;
+        let style = self.block_flow.fragment.style.clone();
+        let flex_style = style.get_flex();
+        let wrap_reversed = flex_style.flex_wrap == flex_wrap::wrap_reverse;
+        let multi_line = self.is_multi_line();
+
+        let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+
+        // Determine the cross size of each line (§ 9.4). Items aligned by their baselines share
+        // the baseline of the line, which is as far from the top of the line as the baseline of
+        // the item that reaches furthest above it. With `wrap-reverse` the cross-start edge of
+        // the line is at the bottom, so they are aligned like `flex-start` instead.
+        let mut baselines: Vec<Option<Au>> = Vec::from_elem(kids.len(), None);
+        let mut line_baselines = Vec::new();
+        let mut line_sizes = Vec::new();
+        for line in self.lines.iter() {
+            let (mut line_size, mut ascent, mut descent) = (Au(0), Au(0), Au(0));
+            for item in line.items.iter() {
+                let kid = &mut **kids.get_mut(item.index);
+                let outer_height = flow::base(kid).position.size.block + vertical_margins(kid);
+                let (alignment, _) = cross_axis_alignment(kid, flex_style.align_items);
+                if alignment == align_items::baseline && !wrap_reversed {
+                    let baseline = first_baseline_offset(kid);
+                    *baselines.get_mut(item.index) = Some(baseline);
+                    ascent = geometry::max(ascent, baseline);
+                    descent = geometry::max(descent, outer_height - baseline);
+                } else {
+                    line_size = geometry::max(line_size, outer_height);
+                }
+            }
+            line_baselines.push(ascent);
+            line_sizes.push(geometry::max(line_size, ascent + descent));
+        }
+        let content_height = line_sizes.iter().fold(Au(0), |sum, size| sum + *size);
+        let used_content_height = self.block_flow.used_content_height(layout_context,
+                                                                      content_height);
+
+        // Distribute any extra space among the lines per `align-content`. A single-line
+        // container always gives its line the whole cross size.
+        let free_space = used_content_height - content_height;
+        let mut line_position = Au(0);
+        let mut line_gap = Au(0);
+        if !multi_line {
+            match line_sizes.mut_iter().next() {
+                Some(line_size) => *line_size = used_content_height,
+                None => {}
+            }
+        } else if flex_style.align_content == align_content::stretch {
+            if free_space > Au(0) && !line_sizes.is_empty() {
+                let extra = free_space.scale_by(1.0 / line_sizes.len() as f64);
+                for line_size in line_sizes.mut_iter() {
+                    *line_size = *line_size + extra
+                }
+            }
+        } else {
+            let justification = match flex_style.align_content {
+                align_content::flex_end => justify_content::flex_end,
+                align_content::center => justify_content::center,
+                align_content::space_between => justify_content::space_between,
+                align_content::space_around => justify_content::space_around,
+                align_content::flex_start | align_content::stretch => justify_content::flex_start,
+            };
+            let (offset, gap) = distribute_free_space(free_space, line_sizes.len(), justification);
+            line_position = offset;
+            line_gap = gap;
+        }
+
+        for (line, (line_size, line_baseline)) in
+                self.lines.iter().zip(line_sizes.iter().zip(line_baselines.iter())) {
+            let (line_size, line_baseline) = (*line_size, *line_baseline);
+            let line_y = if wrap_reversed {
+                used_content_height - line_position - line_size
+            } else {
+                line_position
+            };
+
+            for item in line.items.iter() {
+                let kid = &mut **kids.get_mut(item.index);
+                let margins = vertical_margins(kid);
+                let (alignment, stretchable) = cross_axis_alignment(kid, flex_style.align_items);
+
+                if alignment == align_items::stretch && stretchable {
+                    let height = line_size - margins;
+                    if !kid.is_inline_flow() {
+                        kid.as_block().fragment.border_box.size.block = height;
+                    }
+                    flow::mut_base(kid).position.size.block = height;
+                }
+
+                let outer_height = flow::base(kid).position.size.block + margins;
+                let y = match *baselines.get(item.index) {
+                    Some(baseline) => line_y + line_baseline - baseline,
+                    None => {
+                        line_y + align_in_cross_axis(alignment,
+                                                     line_size,
+                                                     outer_height,
+                                                     wrap_reversed)
+                    }
+                };
+                let top_margin = if kid.is_inline_flow() {
+                    Au(0)
+                } else {
+                    kid.as_block().fragment.margin.block_start
+                };
+                flow::mut_base(kid).position.start.b = top_content_edge + y + top_margin;
+            }
+
+            line_position = line_position + line_size + line_gap;
+        }
+
+        position_absolute_kids_statically(kids.as_mut_slice(), top_content_edge);
+        self.block_flow.assign_height_for_positioned_children(layout_context, content_height);
+    }
+
+    /// Assigns heights to the items of a flex container with a vertical main axis, resolving
+    /// their flexible lengths.
+    fn assign_height_column(&mut self, layout_context: &mut LayoutContext) {
+        let top_content_edge = self.block_flow.fragment.border_padding.block_start;
+        let content_width = self.block_flow.fragment.border_box.size.inline -
+            self.block_flow.fragment.border_padding.inline_start_end();
+        let justification = self.block_flow.fragment.style().get_flex().justify_content;
+        let reversed = self.is_main_axis_reversed();
+
+        // The height of the container is definite only if it was specified as a length.
+        let container_height = match self.block_flow.fragment.style().content_block_size() {
+            LPA_Length(_) => Some(self.block_flow.used_content_height(layout_context, Au(0))),
+            LPA_Percentage(_) | LPA_Auto => None,
+        };
+
+        let mut kids: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+        let mut items = Vec::new();
+        for (index, kid) in kids.mut_iter().enumerate() {
+            let kid = &mut **kid;
+            if kid.is_absolutely_positioned() {
+                continue
+            }
+            let border_padding = if kid.is_inline_flow() {
+                Au(0)
+            } else {
+                kid.as_block().fragment.border_padding.block_start_end()
+            };
+            let content_size = flow::base(kid).position.size.block - border_padding;
+            items.push(FlexItem::new(index,
+                                     flex_item_style(kid),
+                                     false,
+                                     content_width,
+                                     container_height,
+                                     content_size))
+        }
+        items.sort_by(|a, b| a.order.cmp(&b.order));
+
+        let content_height = items.iter().fold(Au(0), |sum, item| {
+            sum + item.outer_size(item.hypothetical_size())
+        });
+        let available_height = self.block_flow.used_content_height(layout_context,
+                                                                    content_height);
+        resolve_flexible_lengths(items.as_mut_slice(), available_height);
+        justify_items(items.as_mut_slice(), available_height, justification);
+
+        for item in items.iter() {
+            let kid = &mut **kids.get_mut(item.index);
+            let height = item.main_size + item.border_padding;
+            let outer_height = item.outer_size(item.main_size);
+            let y = if reversed {
+                available_height - item.main_position - outer_height
+            } else {
+                item.main_position
+            };
+            if !kid.is_inline_flow() {
+                kid.as_block().fragment.border_box.size.block = height;
+            }
+            let kid_base = flow::mut_base(kid);
+            kid_base.position.size.block = height;
+            kid_base.position.start.b = top_content_edge + y + item.margin_before;
+        }
+
+        position_absolute_kids_statically(kids.as_mut_slice(), top_content_edge);
+        self.block_flow.assign_height_for_positioned_children(layout_context, content_height);
+    }
+
+    pub fn build_display_list_flex(&mut self, layout_context: &LayoutContext) {
+        debug!("build_display_list_flex: same process as block flow");
+        self.block_flow.build_display_list_block(layout_context)
+    }
+}
+
+impl Flow for FlexFlow {
+    fn class(&self) -> FlowClass {
+        FlexFlowClass
+    }
+
+    fn as_flex<'a>(&'a mut self) -> &'a mut FlexFlow {
+        self
+    }
+
+    fn as_block<'a>(&'a mut self) -> &'a mut BlockFlow {
+        &mut self.block_flow
+    }
+
+    /// Computes the minimum and preferred widths of the flex container from those of its items.
+    /// Items on a single horizontal line add up; otherwise the widest item wins.
+    fn bubble_widths(&mut self, _: &mut LayoutContext) {
+        let is_row = self.is_row();
+        let multi_line = self.is_multi_line();
+
+        let mut intrinsic_widths = IntrinsicWidths::new();
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_absolutely_positioned() {
+                continue
+            }
+            let kid_widths = &flow::base(kid).intrinsic_widths;
+            intrinsic_widths.minimum_width = if is_row && !multi_line {
+                intrinsic_widths.minimum_width + kid_widths.total_minimum_width()
+            } else {
+                geometry::max(intrinsic_widths.minimum_width, kid_widths.total_minimum_width())
+            };
+            intrinsic_widths.preferred_width = if is_row {
+                intrinsic_widths.preferred_width + kid_widths.total_preferred_width()
+            } else {
+                geometry::max(intrinsic_widths.preferred_width,
+                              kid_widths.total_preferred_width())
+            };
+        }
+
+        let fragment_intrinsic_widths = self.block_flow.fragment.intrinsic_widths(None);
+        intrinsic_widths.minimum_width = geometry::max(intrinsic_widths.minimum_width,
+                                                       fragment_intrinsic_widths.minimum_width);
+        intrinsic_widths.preferred_width = geometry::max(intrinsic_widths.preferred_width,
+                                                         fragment_intrinsic_widths.preferred_width);
+        intrinsic_widths.surround_width = fragment_intrinsic_widths.surround_width;
+        self.block_flow.base.intrinsic_widths = intrinsic_widths;
+
+        // Flex containers are formatting contexts, so floats inside them don't escape.
+        let flags = &mut self.block_flow.base.flags;
+        flags.set_has_left_floated_descendants(false);
+        flags.set_has_right_floated_descendants(false);
+        match self.block_flow.fragment.style().get_box().float {
+            float::none => {}
+            float::left => flags.set_has_left_floated_descendants(true),
+            float::right => flags.set_has_right_floated_descendants(true),
+        }
+    }
+
+    fn assign_widths(&mut self, layout_context: &mut LayoutContext) {
+        debug!("assign_widths({}): assigning width for flow", "flex");
+
+        if self.block_flow.is_root() {
+            let writing_mode = self.block_flow.base.writing_mode;
+            self.block_flow.base.position.start = LogicalPoint::zero();
+            self.block_flow.base.position.size.inline =
+                LogicalSize::from_physical(writing_mode, layout_context.screen_size).inline;
+            self.block_flow.base.floats = Floats::new();
+        }
+
+        // Our width was set to the width of the containing block by the flow's parent. Now compute
+        // the real value.
+        let containing_block_width = self.block_flow.base.position.size.inline;
+        self.block_flow.compute_used_width(layout_context, containing_block_width);
+        if self.block_flow.is_float() {
+            self.block_flow.float.get_mut_ref().containing_width = containing_block_width;
+        }
+
+        // Flex containers are never impacted by floats.
+        self.block_flow.base.flags.set_impacted_by_left_floats(false);
+        self.block_flow.base.flags.set_impacted_by_right_floats(false);
+
+        let left_content_edge = self.block_flow.fragment.border_box.start.i +
+            self.block_flow.fragment.border_padding.inline_start;
+        let content_width = self.block_flow.fragment.border_box.size.inline -
+            self.block_flow.fragment.border_padding.inline_start_end();
+        if self.block_flow.is_float() {
+            self.block_flow.base.position.size.inline = content_width;
+        } else if self.block_flow.is_inline_block() {
+            // Inline-level flex containers don't fill their containing block.
+            self.block_flow.base.position.size.inline =
+                self.block_flow.fragment.border_box.size.inline +
+                self.block_flow.fragment.margin.inline_start_end();
+        }
+
+        self.block_flow.propagate_assigned_width_to_children(left_content_edge,
+                                                             content_width,
+                                                             None);
+
+        // Each flex item establishes a new formatting context, so floats never impact them.
+        for kid in self.block_flow.base.child_iter() {
+            let kid_base = flow::mut_base(kid);
+            kid_base.flags.set_impacted_by_left_floats(false);
+            kid_base.flags.set_impacted_by_right_floats(false);
+        }
+
+        if self.is_row() {
+            self.assign_widths_row(left_content_edge, content_width)
+        } else {
+            self.assign_widths_column(left_content_edge, content_width)
+        }
+    }
+
+    fn assign_height_for_inorder_child_if_necessary(&mut self, layout_context: &mut LayoutContext)
+                                                    -> bool {
+        if self.block_flow.is_float() {
+            self.block_flow.place_float();
+            return true
+        }
+
+        let impacted = self.block_flow.base.flags.impacted_by_floats();
+        if impacted {
+            self.assign_height(layout_context);
+        }
+        impacted
+    }
+
+    fn assign_height(&mut self, layout_context: &mut LayoutContext) {
+        debug!("assign_height: assigning height for flex flow");
+        if self.is_row() {
+            self.assign_height_row(layout_context)
+        } else {
+            self.assign_height_column(layout_context)
+        }
+    }
+
+    fn compute_absolute_position(&mut self) {
+        self.block_flow.compute_absolute_position()
+    }
+
+    fn float_clearance(&self) -> clear::T {
+        self.block_flow.float_clearance()
+    }
+
+    fn mark_as_root(&mut self) {
+        self.block_flow.mark_as_root()
+    }
+
+    fn is_store_overflow_delayed(&mut self) -> bool {
+        self.block_flow.is_store_overflow_delayed()
+    }
+
+    fn is_root(&self) -> bool {
+        self.block_flow.is_root()
+    }
+
+    fn is_float(&self) -> bool {
+        self.block_flow.is_float()
+    }
+
+    fn positioning(&self) -> position::T {
+        self.block_flow.positioning()
+    }
+
+    fn is_root_of_absolute_flow_tree(&self) -> bool {
+        self.block_flow.is_root_of_absolute_flow_tree()
+    }
+
+    fn generated_containing_block_rect(&self) -> LogicalRect<Au> {
+        self.block_flow.generated_containing_block_rect()
+    }
+
+    fn layer_id(&self, fragment_index: uint) -> LayerId {
+        self.block_flow.layer_id(fragment_index)
+    }
+
+    fn is_absolute_containing_block(&self) -> bool {
+        self.block_flow.is_absolute_containing_block()
+    }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        self.block_flow.iterate_through_fragments(iterator)
+    }
+}
+
+impl fmt::Show for FlexFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FlexFlow: {}", self.block_flow)
+    }
+}
+
+/// Returns the style of the given flex item, or `None` if it is an anonymous item wrapping
+/// inline content.
+fn flex_item_style(kid: &mut Flow) -> Option<Arc<ComputedValues>> {
+    if kid.is_inline_flow() {
+        None
+    } else {
+        Some(kid.as_block().fragment.style.clone())
+    }
+}
+
+/// Returns the sum of the top and bottom margins of the given flex item.
+fn vertical_margins(kid: &mut Flow) -> Au {
+    if kid.is_inline_flow() {
+        Au(0)
+    } else {
+        kid.as_block().fragment.margin.block_start_end()
+    }
+}
+
+/// Returns the alignment of the given flex item along the cross axis, and whether it may be
+/// stretched: that is, whether its cross size is `auto`.
+fn cross_axis_alignment(kid: &mut Flow, align_items: align_items::T) -> (align_items::T, bool) {
+    match flex_item_style(kid) {
+        None => (align_items, true),
+        Some(kid_style) => {
+            (used_alignment(kid_style.get_flex().align_self, align_items),
+             kid_style.content_block_size() == LPA_Auto)
+        }
+    }
+}
+
+/// Returns the distance from the top of the margin box of the given flex item to its first
+/// baseline. Items without line boxes get one synthesized from the bottom of their border box
+/// (§ 8.5).
+fn first_baseline_offset(kid: &mut Flow) -> Au {
+    if kid.is_inline_flow() {
+        let height = flow::base(kid).position.size.block;
+        return kid.as_inline().baseline_offset_of_first_line.unwrap_or(height)
+    }
+    let block = kid.as_block();
+    let baseline_offset = match block.baseline_offset_of_first_line_box() {
+        Some(baseline_offset) => baseline_offset,
+        None => block.fragment.border_box.size.block,
+    };
+    block.fragment.margin.block_start + baseline_offset
+}
+
+/// Resolves `align-self: auto` to the `align-items` value of the flex container.
+fn used_alignment(align_self: align_self::T, align_items: align_items::T) -> align_items::T {
+    match align_self {
+        align_self::auto => align_items,
+        align_self::stretch => align_items::stretch,
+        align_self::flex_start => align_items::flex_start,
+        align_self::flex_end => align_items::flex_end,
+        align_self::center => align_items::center,
+        align_self::baseline => align_items::baseline,
+    }
+}
+
+/// Returns the offset of an item with the given outer cross size within a line of the given
+/// cross size. If `reversed` is true, the cross-start edge of the line is at the bottom.
+///
+/// Baseline alignment is handled by `assign_height_row`. Anywhere else, such as along the
+/// horizontal cross axis of a column, it is equivalent to `flex-start` (§ 8.3).
+fn align_in_cross_axis(alignment: align_items::T, line_size: Au, outer_size: Au, reversed: bool)
+                       -> Au {
+    let offset = match alignment {
+        align_items::stretch | align_items::flex_start | align_items::baseline => Au(0),
+        align_items::flex_end => line_size - outer_size,
+        align_items::center => (line_size - outer_size).scale_by(0.5),
+    };
+    if reversed {
+        line_size - outer_size - offset
+    } else {
+        offset
+    }
+}
+
+/// Returns the offset of the first of `count` boxes and the extra space between consecutive
+/// boxes when distributing `free_space` according to `justify-content`.
+fn distribute_free_space(free_space: Au, count: uint, justification: justify_content::T)
+                         -> (Au, Au) {
+    match justification {
+        justify_content::flex_start => (Au(0), Au(0)),
+        justify_content::flex_end => (free_space, Au(0)),
+        justify_content::center => (free_space.scale_by(0.5), Au(0)),
+        justify_content::space_between => {
+            if free_space < Au(0) || count < 2 {
+                (Au(0), Au(0))
+            } else {
+                (Au(0), free_space.scale_by(1.0 / (count - 1) as f64))
+            }
+        }
+        justify_content::space_around => {
+            if free_space < Au(0) || count == 0 {
+                (free_space.scale_by(0.5), Au(0))
+            } else {
+                let gap = free_space.scale_by(1.0 / count as f64);
+                (gap.scale_by(0.5), gap)
+            }
+        }
+    }
+}
+
+/// Positions the items of a line along the main axis per `justify-content`.
+fn justify_items(items: &mut [FlexItem], available_size: Au, justification: justify_content::T) {
+    let used_size = items.iter().fold(Au(0), |sum, item| sum + item.outer_size(item.main_size));
+    let (mut position, gap) = distribute_free_space(available_size - used_size,
+                                                    items.len(),
+                                                    justification);
+    for item in items.mut_iter() {
+        item.main_position = position;
+        position = position + item.outer_size(item.main_size) + gap;
+    }
+}
+
+/// Resolves the main sizes of the items of a line so that they fill `available_size` as far as
+/// their flex factors and minimum and maximum sizes allow (§ 9.7).
+fn resolve_flexible_lengths(items: &mut [FlexItem], available_size: Au) {
+    let hypothetical_size = items.iter().fold(Au(0), |sum, item| {
+        sum + item.outer_size(item.hypothetical_size())
+    });
+    let growing = hypothetical_size < available_size;
+    let flex_factor = |item: &FlexItem| if growing { item.grow } else { item.shrink };
+
+    // Size inflexible items.
+    for item in items.mut_iter() {
+        let hypothetical_size = item.hypothetical_size();
+        item.main_size = hypothetical_size;
+        item.frozen = flex_factor(item) == 0. ||
+            (growing && item.base_size > hypothetical_size) ||
+            (!growing && item.base_size < hypothetical_size);
+    }
+
+    let remaining_free_space = |items: &[FlexItem]| {
+        available_size - items.iter().fold(Au(0), |sum, item| {
+            sum + item.outer_size(if item.frozen { item.main_size } else { item.base_size })
+        })
+    };
+    let initial_free_space = remaining_free_space(items);
+
+    let mut violations = Vec::from_elem(items.len(), Au(0));
+    while items.iter().any(|item| !item.frozen) {
+        let mut free_space = remaining_free_space(items);
+        let unfrozen = items.iter().filter(|item| !item.frozen);
+        let factor_sum = unfrozen.map(|item| flex_factor(item)).fold(0., |sum, factor| sum + factor);
+        if factor_sum < 1. {
+            let limit = initial_free_space.scale_by(factor_sum);
+            if geometry::max(limit, -limit) < geometry::max(free_space, -free_space) {
+                free_space = limit
+            }
+        }
+        let scaled_shrink_sum = items.iter().filter(|item| !item.frozen).fold(0., |sum, item| {
+            sum + item.shrink * item.base_size.to_f64().unwrap()
+        });
+
+        // Distribute the free space in proportion to the flex factors, then clamp.
+        let mut total_violation = Au(0);
+        for (item, violation) in items.mut_iter().zip(violations.mut_iter()) {
+            if item.frozen {
+                continue
+            }
+            let target_size = if growing && factor_sum > 0. {
+                item.base_size + free_space.scale_by(item.grow / factor_sum)
+            } else if !growing && scaled_shrink_sum > 0. {
+                let scaled_shrink = item.shrink * item.base_size.to_f64().unwrap();
+                item.base_size + free_space.scale_by(scaled_shrink / scaled_shrink_sum)
+            } else {
+                item.base_size
+            };
+            item.main_size = item.clamp(target_size);
+            *violation = item.main_size - target_size;
+            total_violation = total_violation + *violation;
+        }
+
+        // Freeze the items whose sizes are now final.
+        for (item, violation) in items.mut_iter().zip(violations.iter()) {
+            if total_violation == Au(0) ||
+                    (total_violation > Au(0) && *violation > Au(0)) ||
+                    (total_violation < Au(0) && *violation < Au(0)) {
+                item.frozen = true
+            }
+        }
+    }
+}
+
+/// Places absolutely-positioned children of a flex container at their static position, the top
+/// content edge of the container.
+fn position_absolute_kids_statically(kids: &mut [&mut Flow], top_content_edge: Au) {
+    for kid in kids.mut_iter() {
+        let kid = &mut **kid;
+        if kid.is_absolutely_positioned() {
+            flow::mut_base(kid).position.start.b = top_content_edge
+        }
+    }
+}
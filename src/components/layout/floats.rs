@@ -140,6 +140,9 @@ fn range_intersect(top_1: Au, bottom_1: Au, top_2: Au, bottom_2: Au) -> (Au, Au)
 
 /// Encapsulates information about floats. This is optimized to avoid allocation if there are
 /// no floats, and to avoid copying when translating the list of floats downward.
+///
+/// Floats work in flow-relative coordinates: `x` and widths run along the inline axis and `y` and
+/// heights along the block axis, so "left" means line-left in vertical writing modes.
 #[deriving(Clone)]
 pub struct Floats {
     /// The list of floats.
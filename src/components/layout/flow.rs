@@ -28,13 +28,15 @@
 use css::node_style::StyledNode;
 use block::BlockFlow;
 use context::LayoutContext;
+use flex::FlexFlow;
 use floats::Floats;
 use flow_list::{FlowList, Link, FlowListIterator, MutFlowListIterator};
 use flow_ref::FlowRef;
-use fragment::{Fragment, TableRowFragment, TableCellFragment};
+use fragment::{Fragment, TableCellFragment, TableFragment, TableRowFragment};
 use incremental::RestyleDamage;
 use inline::InlineFlow;
 use model::{CollapsibleMargins, IntrinsicWidths, MarginCollapseInfo};
+use multicol::MulticolFlow;
 use parallel::FlowParallelInfo;
 use table_wrapper::TableWrapperFlow;
 use table::TableFlow;
@@ -52,7 +54,7 @@ use geom::size::Size2D;
 use gfx::display_list::DisplayList;
 use gfx::render_task::RenderLayer;
 use servo_msg::compositor_msg::LayerId;
-use servo_util::geometry::Au;
+use servo_util::geometry::{Au, LogicalRect, WritingMode};
 use std::mem;
 use std::fmt;
 use std::iter::Zip;
@@ -125,6 +127,16 @@ pub trait Flow: fmt::Show + ToStr + Share {
         fail!("called as_table_cell() on a non-tablecell flow")
     }
 
+    /// If this is a flex flow, returns the underlying object. Fails otherwise.
+    fn as_flex<'a>(&'a mut self) -> &'a mut FlexFlow {
+        fail!("called as_flex() on a non-flex flow")
+    }
+
+    /// If this is a multi-column flow, returns the underlying object. Fails otherwise.
+    fn as_multicol<'a>(&'a mut self) -> &'a mut MulticolFlow {
+        fail!("called as_multicol() on a non-multicol flow")
+    }
+
     /// If this is a table row or table rowgroup or table flow, returns column widths.
     /// Fails otherwise.
     fn col_widths<'a>(&'a mut self) -> &'a mut Vec<Au> {
@@ -232,8 +244,13 @@ pub trait Flow: fmt::Show + ToStr + Share {
         self.positioning() == position::fixed
     }
 
+    /// Return true if this flow has position 'sticky'.
+    fn is_sticky(&self) -> bool {
+        self.positioning() == position::sticky
+    }
+
     fn is_positioned(&self) -> bool {
-        self.is_relatively_positioned() || self.is_absolutely_positioned()
+        self.is_relatively_positioned() || self.is_sticky() || self.is_absolutely_positioned()
     }
 
     fn is_relatively_positioned(&self) -> bool {
@@ -256,10 +273,14 @@ pub trait Flow: fmt::Show + ToStr + Share {
 
     /// Return the dimensions of the containing block generated by this flow for absolutely-
     /// positioned descendants. For block flows, this is the padding box.
-    fn generated_containing_block_rect(&self) -> Rect<Au> {
+    fn generated_containing_block_rect(&self) -> LogicalRect<Au> {
         fail!("generated_containing_block_position not yet implemented for this flow")
     }
 
+    /// Calls `iterator` on each fragment that this flow lays out itself, in order. Fragments of
+    /// child flows are not included.
+    fn iterate_through_fragments(&self, _iterator: |&Fragment|) {}
+
     /// Returns a layer ID for the given fragment.
     fn layer_id(&self, fragment_id: uint) -> LayerId {
         unsafe {
@@ -348,6 +369,12 @@ pub trait ImmutableFlowUtils {
     /// Returns true if this flow is an inline flow.
     fn is_inline_flow(self) -> bool;
 
+    /// Returns true if this flow is a flex flow.
+    fn is_flex(self) -> bool;
+
+    /// Returns true if this flow is a multi-column flow.
+    fn is_multicol(self) -> bool;
+
     /// Dumps the flow tree for debugging.
     fn dump(self);
 
@@ -371,6 +398,18 @@ pub trait MutableFlowUtils {
 
     /// Builds the display lists for this flow.
     fn build_display_list(self, layout_context: &LayoutContext);
+
+    /// Returns true if the display list retained from the last layout can stand in for the display
+    /// lists of this flow and all of its descendants.
+    fn can_reuse_display_list(self) -> bool;
+
+    /// Replaces the display list of this flow with the one retained from the last layout,
+    /// translated to the current absolute position of the flow.
+    fn reuse_display_list(self);
+
+    /// Retains a copy of the display list just built for this flow, if it can be reused, and
+    /// clears the restyle damage of the flow, which is now completely laid out.
+    fn retain_display_list(self);
 }
 
 pub trait MutableOwnedFlowUtils {
@@ -384,6 +423,8 @@ pub trait MutableOwnedFlowUtils {
 pub enum FlowClass {
     BlockFlowClass,
     InlineFlowClass,
+    FlexFlowClass,
+    MulticolFlowClass,
     TableWrapperFlowClass,
     TableFlowClass,
     TableColGroupFlowClass,
@@ -428,13 +469,13 @@ pub trait PostorderFlowTraversal {
 
 /// Flags used in flows, tightly packed to save space.
 #[deriving(Clone)]
-pub struct FlowFlags(pub u8);
+pub struct FlowFlags(pub u16);
 
 /// The bitmask of flags that represent the `has_left_floated_descendants` and
 /// `has_right_floated_descendants` fields.
 ///
 /// NB: If you update this field, you must update the bitfields below.
-static HAS_FLOATED_DESCENDANTS_BITMASK: u8 = 0b0000_0011;
+static HAS_FLOATED_DESCENDANTS_BITMASK: u16 = 0b0000_0011;
 
 // Whether this flow has descendants that float left in the same block formatting context.
 bitfield!(FlowFlags, has_left_floated_descendants, set_has_left_floated_descendants, 0b0000_0001)
@@ -453,23 +494,23 @@ bitfield!(FlowFlags, impacted_by_right_floats, set_impacted_by_right_floats, 0b0
 /// The bitmask of flags that represent the text alignment field.
 ///
 /// NB: If you update this field, you must update the bitfields below.
-static TEXT_ALIGN_BITMASK: u8 = 0b0011_0000;
+static TEXT_ALIGN_BITMASK: u16 = 0b0111_0000;
 
 /// The number of bits we must shift off to handle the text alignment field.
 ///
 /// NB: If you update this field, you must update the bitfields below.
-static TEXT_ALIGN_SHIFT: u8 = 4;
+static TEXT_ALIGN_SHIFT: u16 = 4;
 
 // Whether this flow contains a flow that has its own layer within the same absolute containing
 // block.
 bitfield!(FlowFlags,
           layers_needed_for_descendants,
           set_layers_needed_for_descendants,
-          0b0100_0000)
+          0b1000_0000)
 
 // Whether this flow must have its own layer. Even if this flag is not set, it might get its own
 // layer if it's deemed to be likely to overlap flows with their own layer.
-bitfield!(FlowFlags, needs_layer, set_needs_layer, 0b1000_0000)
+bitfield!(FlowFlags, needs_layer, set_needs_layer, 0b1_0000_0000)
 
 impl FlowFlags {
     /// Creates a new set of flow flags.
@@ -488,13 +529,13 @@ impl FlowFlags {
     #[inline]
     pub fn text_align(self) -> text_align::T {
         let FlowFlags(ff) = self;
-        FromPrimitive::from_u8((ff & TEXT_ALIGN_BITMASK) >> TEXT_ALIGN_SHIFT).unwrap()
+        FromPrimitive::from_u16((ff & TEXT_ALIGN_BITMASK) >> TEXT_ALIGN_SHIFT).unwrap()
     }
 
     #[inline]
     pub fn set_text_align(&mut self, value: text_align::T) {
         let FlowFlags(ff) = *self;
-        *self = FlowFlags((ff & !TEXT_ALIGN_BITMASK) | ((value as u8) << TEXT_ALIGN_SHIFT))
+        *self = FlowFlags((ff & !TEXT_ALIGN_BITMASK) | ((value as u16) << TEXT_ALIGN_SHIFT))
     }
 
     #[inline]
@@ -599,6 +640,14 @@ pub struct AbsolutePositionInfo {
     pub relative_containing_block_size: Size2D<Au>,
     /// The position of the absolute containing block.
     pub absolute_containing_block_position: Point2D<Au>,
+    /// The physical size of the absolute containing block, needed to map the logical position of
+    /// absolutely-positioned descendants onto physical coordinates.
+    pub absolute_containing_block_size: Size2D<Au>,
+    /// The physical size of the viewport, which is the containing block of fixed descendants.
+    pub viewport_size: Size2D<Au>,
+    /// The content box of the parent flow in absolute coordinates, which sticky descendants may
+    /// not be moved out of. Table rows and row groups pass on the content box of their table.
+    pub sticky_containing_block: Rect<Au>,
     /// Whether the absolute containing block forces positioned descendants to be layerized.
     ///
     /// FIXME(pcwalton): Move into `FlowFlags`.
@@ -612,6 +661,9 @@ impl AbsolutePositionInfo {
         AbsolutePositionInfo {
             relative_containing_block_size: Size2D::zero(),
             absolute_containing_block_position: Zero::zero(),
+            absolute_containing_block_size: Size2D::zero(),
+            viewport_size: Size2D::zero(),
+            sticky_containing_block: Rect::zero(),
             layers_needed_for_positioned_flows: false,
         }
     }
@@ -636,20 +688,28 @@ pub struct BaseFlow {
     // layout; maybe combine into a single enum to save space.
     pub intrinsic_widths: IntrinsicWidths,
 
-    /// The upper left corner of the box representing this flow, relative to the box representing
-    /// its parent flow.
+    /// The logical start corner of the box representing this flow, relative to the box
+    /// representing its parent flow, in the writing mode of this flow.
     ///
     /// For absolute flows, this represents the position with respect to its *containing block*.
     ///
     /// This does not include margins in the block flow direction, because those can collapse. So
     /// for the block direction (usually vertical), this represents the *border box*. For the
     /// inline direction (usually horizontal), this represents the *margin box*.
-    pub position: Rect<Au>,
+    pub position: LogicalRect<Au>,
 
-    /// The amount of overflow of this flow, relative to the containing block. Must include all the
-    /// pixels of all the display list items for correct invalidation.
+    /// The amount of overflow of this flow, in physical coordinates relative to the top left
+    /// corner of this flow. Must include all the pixels of all the display list items for correct
+    /// invalidation.
     pub overflow: Rect<Au>,
 
+    /// The writing mode of this flow, which determines how `position` and the logical geometry of
+    /// its fragments map onto physical coordinates.
+    ///
+    /// FIXME: Orthogonal flows, whose writing mode differs from that of their parent, are not yet
+    /// supported; their children are laid out as though the modes matched.
+    pub writing_mode: WritingMode,
+
     /// Data used during parallel traversals.
     ///
     /// TODO(pcwalton): Group with other transient data to save space.
@@ -687,6 +747,11 @@ pub struct BaseFlow {
     /// The unflattened display items for this flow.
     pub display_list: DisplayList,
 
+    /// A copy of the display list built for this flow during the last layout, together with the
+    /// absolute position of the flow at the time. Reused, translated to the new absolute position,
+    /// if the flow has no restyle damage.
+    pub retained_display_list: Option<(Point2D<Au>, DisplayList)>,
+
     /// Any layers that we're bubbling up, in a linked list.
     pub layers: DList<RenderLayer>,
 
@@ -706,18 +771,21 @@ impl Drop for BaseFlow {
 impl BaseFlow {
     #[inline]
     pub fn new(node: ThreadSafeLayoutNode) -> BaseFlow {
+        let writing_mode = node.style().writing_mode();
         BaseFlow {
             ref_count: AtomicUint::new(1),
 
-            restyle_damage: node.restyle_damage(),
+            // A new flow has never been laid out.
+            restyle_damage: RestyleDamage::all(),
 
             children: FlowList::new(),
             next_sibling: None,
             prev_sibling: None,
 
             intrinsic_widths: IntrinsicWidths::new(),
-            position: Rect::zero(),
+            position: LogicalRect::zero(),
             overflow: Rect::zero(),
+            writing_mode: writing_mode,
 
             parallel: FlowParallelInfo::new(),
 
@@ -729,6 +797,7 @@ impl BaseFlow {
             fixed_static_x_offset: Au::new(0),
             absolute_cb: ContainingBlockLink::new(),
             display_list: DisplayList::new(),
+            retained_display_list: None,
             layers: DList::new(),
             absolute_position_info: AbsolutePositionInfo::new(),
 
@@ -749,7 +818,7 @@ impl<'a> ImmutableFlowUtils for &'a Flow {
     /// Returns true if this flow is a block or a float flow.
     fn is_block_like(self) -> bool {
         match self.class() {
-            BlockFlowClass => true,
+            BlockFlowClass | FlexFlowClass | MulticolFlowClass => true,
             _ => false,
         }
     }
@@ -830,6 +899,11 @@ impl<'a> ImmutableFlowUtils for &'a Flow {
             TableFlowClass => !child.is_proper_table_child(),
             TableRowGroupFlowClass => !child.is_table_row(),
             TableRowFlowClass => !child.is_table_cell(),
+            // Table-internal flows outside of a table get an anonymous table around them, per
+            // CSS 2.1 § 17.2.1, step 3.
+            BlockFlowClass | TableCellFlowClass | TableCaptionFlowClass => {
+                child.is_proper_table_child() || child.is_table_cell()
+            }
             _ => false
         }
     }
@@ -843,7 +917,12 @@ impl<'a> ImmutableFlowUtils for &'a Flow {
             },
             TableRowFlowClass => {
                 let fragment = Fragment::new_anonymous_table_fragment(node, TableCellFragment);
-                box TableCellFlow::from_node_and_fragment(node, fragment) as Box<Flow>
+                box TableCellFlow::anonymous_from_node_and_fragment(node, fragment) as Box<Flow>
+            },
+            BlockFlowClass | TableCellFlowClass | TableCaptionFlowClass => {
+                // The flow constructor wraps this in an anonymous table wrapper flow.
+                let fragment = Fragment::new_anonymous_table_fragment(node, TableFragment);
+                box TableFlow::from_node_and_fragment(node, fragment) as Box<Flow>
             },
             _ => {
                 fail!("no need to generate a missing child")
@@ -870,8 +949,8 @@ impl<'a> ImmutableFlowUtils for &'a Flow {
     /// containers.
     fn is_block_container(self) -> bool {
         match self.class() {
-            // TODO: Change this when inline-blocks are supported.
-            BlockFlowClass | TableCaptionFlowClass | TableCellFlowClass => {
+            BlockFlowClass | FlexFlowClass | MulticolFlowClass | TableCaptionFlowClass |
+            TableCellFlowClass => {
                 // FIXME: Actually check the type of the node
                 self.child_count() != 0
             }
@@ -895,6 +974,22 @@ impl<'a> ImmutableFlowUtils for &'a Flow {
         }
     }
 
+    /// Returns true if this flow is a flex flow.
+    fn is_flex(self) -> bool {
+        match self.class() {
+            FlexFlowClass => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if this flow is a multi-column flow.
+    fn is_multicol(self) -> bool {
+        match self.class() {
+            MulticolFlowClass => true,
+            _ => false,
+        }
+    }
+
     /// Dumps the flow tree for debugging.
     fn dump(self) {
         self.dump_with_level(0)
@@ -962,8 +1057,9 @@ impl<'a> MutableFlowUtils for &'a mut Flow {
     /// already been set.
     /// Assumption: Absolute descendants have had their overflow calculated.
     fn store_overflow(self, _: &mut LayoutContext) {
-        let my_position = mut_base(self).position;
-        let mut overflow = my_position;
+        let writing_mode = base(self).writing_mode;
+        let my_size = mut_base(self).position.size.to_physical(writing_mode);
+        let mut overflow = Rect(Zero::zero(), my_size);
 
         if self.is_block_container() {
             for kid in child_iter(self) {
@@ -972,15 +1068,21 @@ impl<'a> MutableFlowUtils for &'a mut Flow {
                     // their CB, they will show up in `abs_descendants`.
                     continue;
                 }
-                let mut kid_overflow = base(kid).overflow;
-                kid_overflow = kid_overflow.translate(&my_position.origin);
+                let kid_origin = base(kid).position.to_physical(writing_mode, my_size).origin;
+                let kid_overflow = base(kid).overflow.translate(&kid_origin);
                 overflow = overflow.union(&kid_overflow)
             }
 
-            // FIXME(#2004, pcwalton): This is wrong for `position: fixed`.
+            // Fixed descendants are positioned relative to the viewport and paint into layers
+            // that don't scroll, so they never make their containing block (or the page) larger.
             for descendant_link in mut_base(self).abs_descendants.iter() {
-                let mut kid_overflow = base(descendant_link).overflow;
-                kid_overflow = kid_overflow.translate(&my_position.origin);
+                if descendant_link.is_fixed() {
+                    continue
+                }
+                let kid_origin = base(descendant_link).position
+                                                      .to_physical(writing_mode, my_size)
+                                                      .origin;
+                let kid_overflow = base(descendant_link).overflow.translate(&kid_origin);
                 overflow = overflow.union(&kid_overflow)
             }
         }
@@ -1001,6 +1103,10 @@ impl<'a> MutableFlowUtils for &'a mut Flow {
         match self.class() {
             BlockFlowClass => self.as_block().build_display_list_block(layout_context),
             InlineFlowClass => self.as_inline().build_display_list_inline(layout_context),
+            FlexFlowClass => self.as_flex().build_display_list_flex(layout_context),
+            MulticolFlowClass => {
+                self.as_multicol().build_display_list_multicol(layout_context)
+            }
             TableWrapperFlowClass => {
                 self.as_table_wrapper().build_display_list_table_wrapper(layout_context)
             }
@@ -1020,6 +1126,30 @@ impl<'a> MutableFlowUtils for &'a mut Flow {
             }
         }
     }
+
+    fn can_reuse_display_list(self) -> bool {
+        let base = base(self);
+        base.restyle_damage.is_empty() && base.abs_descendants.len() == 0 &&
+            base.retained_display_list.is_some()
+    }
+
+    fn reuse_display_list(self) {
+        let base = mut_base(self);
+        let (position, mut display_list) = base.retained_display_list.get_ref().clone();
+        display_list.translate(&(base.abs_position - position));
+        base.display_list = display_list
+    }
+
+    fn retain_display_list(self) {
+        let base = mut_base(self);
+        base.retained_display_list = if base.layers.is_empty() {
+            Some((base.abs_position, base.display_list.clone()))
+        } else {
+            // Layers are not retained, so neither is a display list without their contents.
+            None
+        };
+        base.restyle_damage = RestyleDamage::empty()
+    }
 }
 
 impl MutableOwnedFlowUtils for FlowRef {
@@ -1076,7 +1206,7 @@ impl ContainingBlockLink {
     }
 
     #[inline]
-    pub fn generated_containing_block_rect(&mut self) -> Rect<Au> {
+    pub fn generated_containing_block_rect(&mut self) -> LogicalRect<Au> {
         match self.link {
             None => fail!("haven't done it"),
             Some(ref mut link) => link.get_mut().generated_containing_block_rect(),
@@ -12,6 +12,7 @@ use context::LayoutContext;
 use floats::{ClearBoth, ClearLeft, ClearRight, ClearType};
 use flow::Flow;
 use flow;
+use flow_ref::FlowRef;
 use inline::{InlineFragmentContext, InlineMetrics};
 use model::{Auto, IntrinsicWidths, MaybeAuto, Specified, specified};
 use model;
@@ -29,18 +30,27 @@ use gfx::display_list::{ImageDisplayItemClass, LineDisplayItem};
 use gfx::display_list::{LineDisplayItemClass, OpaqueNode, PseudoDisplayItemClass};
 use gfx::display_list::{SolidColorDisplayItem, SolidColorDisplayItemClass, StackingLevel};
 use gfx::display_list::{TextDecorations, TextDisplayItem, TextDisplayItemClass};
+use gfx::display_list::{HorizontalTextOrientation, TextOrientation};
+use gfx::display_list::{VerticalSidewaysTextOrientation, VerticalUprightTextOrientation};
 use gfx::font::FontStyle;
+use gfx::font_context::FontContext;
+use gfx::text::bidi;
 use gfx::text::glyph::CharIndex;
 use gfx::text::text_run::TextRun;
+use text_util = gfx::text::util;
+use script::dom::element::{HTMLOptGroupElementTypeId, HTMLOptionElementTypeId};
+use script::dom::element::{HTMLSelectElementTypeId, HTMLTextAreaElementTypeId};
+use script::dom::node::ElementNodeTypeId;
 use servo_msg::constellation_msg::{ConstellationChan, FrameRectMsg, PipelineId, SubpageId};
 use servo_net::image::holder::ImageHolder;
 use servo_net::local_image_cache::LocalImageCache;
-use servo_util::geometry::Au;
+use servo_util::geometry::{Au, LogicalMargin, LogicalRect, LogicalSize};
 use servo_util::geometry;
 use servo_util::range::*;
 use servo_util::namespace;
 use servo_util::smallvec::SmallVec;
 use servo_util::str::is_whitespace;
+use std::ascii::StrAsciiExt;
 use std::fmt;
 use std::from_str::FromStr;
 use std::iter::AdditiveIterator;
@@ -48,8 +58,11 @@ use std::mem;
 use std::num::Zero;
 use style::{ComputedValues, TElement, TNode, cascade_anonymous};
 use style::computed_values::{LengthOrPercentageOrAuto, overflow, LPA_Auto, background_attachment};
-use style::computed_values::{background_repeat, border_style, clear, position, text_align};
-use style::computed_values::{text_decoration, vertical_align, visibility, white_space};
+use style::computed_values::{LengthOrPercentage, LPA_Percentage, LP_Length, LP_Percentage};
+use style::computed_values::{LPN_Length, LPN_None, LPN_Percentage, object_fit};
+use style::computed_values::{background_repeat, border_collapse, border_style, clear, position};
+use style::computed_values::{text_align, text_decoration, vertical_align, visibility};
+use style::computed_values::{direction, display, hyphens, text_orientation, white_space};
 use sync::{Arc, Mutex};
 use url::Url;
 
@@ -81,16 +94,16 @@ pub struct Fragment {
     /// The CSS style of this fragment.
     pub style: Arc<ComputedValues>,
 
-    /// The position of this fragment relative to its owning flow.
-    /// The size includes padding and border, but not margin.
-    pub border_box: Rect<Au>,
+    /// The position of this fragment relative to its owning flow, in the writing mode of that
+    /// flow. The size includes padding and border, but not margin.
+    pub border_box: LogicalRect<Au>,
 
     /// The sum of border and padding; i.e. the distance from the edge of the border box to the
     /// content edge of the fragment.
-    pub border_padding: SideOffsets2D<Au>,
+    pub border_padding: LogicalMargin<Au>,
 
     /// The margin of the content box.
-    pub margin: SideOffsets2D<Au>,
+    pub margin: LogicalMargin<Au>,
 
     /// Info specific to the kind of fragment. Keep this enum small.
     pub specific: SpecificFragmentInfo,
@@ -99,14 +112,21 @@ pub struct Fragment {
     ///
     /// FIXME(#2260, pcwalton): This is very inefficient; remove.
     pub new_line_pos: Vec<CharIndex>,
+
+    /// The resolved bidirectional embedding level of this fragment, per UAX #9. Odd levels are
+    /// right-to-left. Text fragments are split wherever the level changes, so the whole fragment
+    /// has the same level.
+    pub bidi_level: u8,
 }
 
 /// Info specific to the kind of fragment. Keep this enum small.
 #[deriving(Clone)]
 pub enum SpecificFragmentInfo {
     GenericFragment,
+    FormControlFragment(FormControlFragmentInfo),
     ImageFragment(ImageFragmentInfo),
     IframeFragment(IframeFragmentInfo),
+    InlineBlockFragment(InlineBlockFragmentInfo),
     ScannedTextFragment(ScannedTextFragmentInfo),
     TableFragment,
     TableCellFragment,
@@ -116,6 +136,28 @@ pub enum SpecificFragmentInfo {
     UnscannedTextFragment(UnscannedTextFragmentInfo),
 }
 
+/// A fragment that represents an inline-block element. It is atomic for the purposes of line
+/// breaking; its contents are laid out by a block flow that is a child of the inline flow that
+/// contains this fragment.
+#[deriving(Clone)]
+pub struct InlineBlockFragmentInfo {
+    /// The block flow that lays out the contents of the inline block.
+    pub flow_ref: FlowRef,
+
+    /// The distance from the top margin edge of the inline block to its baseline. This is
+    /// computed during height assignment.
+    pub baseline_offset: Au,
+}
+
+impl InlineBlockFragmentInfo {
+    pub fn new(flow_ref: FlowRef) -> InlineBlockFragmentInfo {
+        InlineBlockFragmentInfo {
+            flow_ref: flow_ref,
+            baseline_offset: Au(0),
+        }
+    }
+}
+
 /// A fragment that represents a replaced content image and its accompanying borders, shadows, etc.
 #[deriving(Clone)]
 pub struct ImageFragmentInfo {
@@ -183,6 +225,21 @@ impl ImageFragmentInfo {
             }
         }
     }
+    /// Computes and stores the used width and height of the image from its style, its `width` and
+    /// `height` attributes and its intrinsic size.
+    pub fn compute_used_size(&mut self, style: &ComputedValues, container_width: Au) {
+        let intrinsic_size = Size2D(self.image_width(), self.image_height());
+        let has_intrinsic_ratio = intrinsic_size.width > Au(0) && intrinsic_size.height > Au(0);
+        let size = replaced_used_size_from_style(style,
+                                                 container_width,
+                                                 self.dom_width,
+                                                 self.dom_height,
+                                                 intrinsic_size,
+                                                 has_intrinsic_ratio);
+        self.computed_width = Some(size.width);
+        self.computed_height = Some(size.height);
+    }
+
     /// Returns the calculated height of the image, accounting for the height attribute.
     pub fn computed_height(&self) -> Au {
         match self.computed_height {
@@ -198,6 +255,202 @@ impl ImageFragmentInfo {
     }
 }
 
+/// Returns the used size of a replaced element with the given style, `width` and `height`
+/// attributes and intrinsic size.
+fn replaced_used_size_from_style(style: &ComputedValues,
+                                 container_width: Au,
+                                 dom_width: Option<Au>,
+                                 dom_height: Option<Au>,
+                                 intrinsic_size: Size2D<Au>,
+                                 has_intrinsic_ratio: bool)
+                                 -> Size2D<Au> {
+    let box_style = style.get_box();
+    let width = ImageFragmentInfo::style_length(box_style.width, dom_width, container_width);
+
+    // FIXME(ksh8281): We don't know the height of the containing block yet, so percentage
+    // heights behave as `auto` (CSS 2.1 § 10.5).
+    let style_height = match box_style.height {
+        LPA_Percentage(_) => LPA_Auto,
+        style_height => style_height,
+    };
+    let height = ImageFragmentInfo::style_length(style_height, dom_height, Au(0));
+
+    let min_width = model::specified(box_style.min_width, container_width);
+    let max_width = model::specified_or_none(box_style.max_width, container_width);
+    let min_height = match box_style.min_height {
+        LP_Length(length) => length,
+        LP_Percentage(_) => Au(0),
+    };
+    let max_height = match box_style.max_height {
+        LPN_Length(length) => Some(length),
+        LPN_Percentage(_) | LPN_None => None,
+    };
+
+    replaced_used_size(width,
+                       height,
+                       intrinsic_size,
+                       has_intrinsic_ratio,
+                       Size2D(min_width, min_height),
+                       max_width,
+                       max_height)
+}
+
+/// Returns the used size of a replaced element per CSS 2.1 § 10.3.2 and § 10.6.2, with `min-width`,
+/// `max-width`, `min-height` and `max-height` applied as described in § 10.4 and § 10.7. If the
+/// element has an intrinsic ratio, it is preserved wherever the constraints allow; both
+/// dimensions of `intrinsic_size` must then be nonzero.
+fn replaced_used_size(width: MaybeAuto,
+                      height: MaybeAuto,
+                      intrinsic_size: Size2D<Au>,
+                      has_intrinsic_ratio: bool,
+                      min_size: Size2D<Au>,
+                      max_width: Option<Au>,
+                      max_height: Option<Au>)
+                      -> Size2D<Au> {
+    // A maximum that is smaller than the corresponding minimum is raised to it.
+    let max_width = max_width.map(|max_width| Au::max(max_width, min_size.width));
+    let max_height = max_height.map(|max_height| Au::max(max_height, min_size.height));
+    let clamp_width = |width: Au| Au::max(min_size.width, min_or_none(width, max_width));
+    let clamp_height = |height: Au| Au::max(min_size.height, min_or_none(height, max_height));
+
+    match (width, height) {
+        (Specified(width), Specified(height)) => Size2D(clamp_width(width), clamp_height(height)),
+        (Specified(width), Auto) => {
+            let width = clamp_width(width);
+            let height = if has_intrinsic_ratio {
+                scale(intrinsic_size.height, width, intrinsic_size.width)
+            } else {
+                intrinsic_size.height
+            };
+            Size2D(width, clamp_height(height))
+        }
+        (Auto, Specified(height)) => {
+            let height = clamp_height(height);
+            let width = if has_intrinsic_ratio {
+                scale(intrinsic_size.width, height, intrinsic_size.height)
+            } else {
+                intrinsic_size.width
+            };
+            Size2D(clamp_width(width), height)
+        }
+        (Auto, Auto) if !has_intrinsic_ratio => {
+            Size2D(clamp_width(intrinsic_size.width), clamp_height(intrinsic_size.height))
+        }
+        (Auto, Auto) => {
+            constrain_preserving_ratio(intrinsic_size, min_size, max_width, max_height)
+        }
+    }
+}
+
+/// Resolves the constraint violations of a replaced element whose width and height are both
+/// `auto`, using the table in CSS 2.1 § 10.4. Both dimensions of `size` must be nonzero, and the
+/// maximums must be no smaller than the minimums.
+fn constrain_preserving_ratio(size: Size2D<Au>,
+                              min_size: Size2D<Au>,
+                              max_width: Option<Au>,
+                              max_height: Option<Au>)
+                              -> Size2D<Au> {
+    let (width, height) = (size.width, size.height);
+    let (min_width, min_height) = (min_size.width, min_size.height);
+    let too_wide = max_width.map_or(false, |max_width| width > max_width);
+    let too_tall = max_height.map_or(false, |max_height| height > max_height);
+    let too_narrow = width < min_width;
+    let too_short = height < min_height;
+
+    match (too_wide, too_narrow, too_tall, too_short) {
+        (true, _, true, _) => {
+            let (max_width, max_height) = (max_width.unwrap(), max_height.unwrap());
+            if ratio(max_width, width) <= ratio(max_height, height) {
+                Size2D(max_width, Au::max(min_height, scale(height, max_width, width)))
+            } else {
+                Size2D(Au::max(min_width, scale(width, max_height, height)), max_height)
+            }
+        }
+        (_, true, _, true) => {
+            if ratio(min_width, width) <= ratio(min_height, height) {
+                Size2D(min_or_none(scale(width, min_height, height), max_width), min_height)
+            } else {
+                Size2D(min_width, min_or_none(scale(height, min_width, width), max_height))
+            }
+        }
+        (_, true, true, _) => Size2D(min_width, max_height.unwrap()),
+        (true, _, _, true) => Size2D(max_width.unwrap(), min_height),
+        (true, _, _, _) => {
+            let max_width = max_width.unwrap();
+            Size2D(max_width, Au::max(scale(height, max_width, width), min_height))
+        }
+        (_, true, _, _) => {
+            Size2D(min_width, min_or_none(scale(height, min_width, width), max_height))
+        }
+        (_, _, true, _) => {
+            let max_height = max_height.unwrap();
+            Size2D(Au::max(scale(width, max_height, height), min_width), max_height)
+        }
+        (_, _, _, true) => {
+            Size2D(min_or_none(scale(width, min_height, height), max_width), min_height)
+        }
+        (false, false, false, false) => size,
+    }
+}
+
+/// Returns the rectangle that an image of the given natural size is drawn into within the content
+/// box `bounds` of its replaced element, according to `object-fit` and `object-position`.
+fn object_bounds(style: &ComputedValues, bounds: Rect<Au>, image_size: Size2D<Au>) -> Rect<Au> {
+    let box_style = style.get_box();
+    let has_intrinsic_ratio = image_size.width > Au(0) && image_size.height > Au(0);
+    let size = if !has_intrinsic_ratio {
+        bounds.size
+    } else {
+        // The factors by which the image has to be scaled to be as wide and as tall as the box.
+        let width_scale = ratio(bounds.size.width, image_size.width);
+        let height_scale = ratio(bounds.size.height, image_size.height);
+        let contain_scale = width_scale.min(height_scale);
+        let scale = match box_style.object_fit {
+            object_fit::fill => None,
+            object_fit::contain => Some(contain_scale),
+            object_fit::cover => Some(width_scale.max(height_scale)),
+            object_fit::none => Some(1.0),
+            object_fit::scale_down => Some(contain_scale.min(1.0)),
+        };
+        match scale {
+            None => bounds.size,
+            Some(scale) => Size2D(image_size.width.scale_by(scale),
+                                  image_size.height.scale_by(scale)),
+        }
+    };
+
+    // Percentages in `object-position` align that point of the image with the same point of the
+    // box, as in `background-position`.
+    let offset = |position: LengthOrPercentage, free_space: Au| {
+        match position {
+            LP_Length(length) => length,
+            LP_Percentage(percent) => free_space.scale_by(percent),
+        }
+    };
+    let position = box_style.object_position;
+    let origin = Point2D(bounds.origin.x + offset(position.horizontal,
+                                                  bounds.size.width - size.width),
+                         bounds.origin.y + offset(position.vertical,
+                                                  bounds.size.height - size.height));
+    Rect(origin, size)
+}
+
+/// Returns `length` multiplied by `numerator / denominator`.
+fn scale(length: Au, numerator: Au, denominator: Au) -> Au {
+    length.scale_by(ratio(numerator, denominator))
+}
+
+fn ratio(numerator: Au, denominator: Au) -> f64 {
+    geometry::to_frac_px(numerator) / geometry::to_frac_px(denominator)
+}
+
+fn min_or_none(length: Au, max: Option<Au>) -> Au {
+    match max {
+        None => length,
+        Some(max) => Au::min(length, max),
+    }
+}
+
 /// A fragment that represents an inline frame (iframe). This stores the pipeline ID so that the size
 /// of this iframe can be communicated via the constellation to the iframe's own layout task.
 #[deriving(Clone)]
@@ -219,6 +472,214 @@ impl IframeFragmentInfo {
     }
 }
 
+/// The kinds of native form controls, along with the state that their appearance depends on.
+#[deriving(Clone)]
+pub enum FormControlKind {
+    /// A single-line text field, such as `<input type="text">` or `<input type="password">`.
+    TextFieldControl,
+    /// A multi-line text field: `<textarea>`.
+    TextAreaControl,
+    /// A checkbox, which is checked if the flag is true.
+    CheckboxControl(bool),
+    /// A radio button, which is checked if the flag is true.
+    RadioControl(bool),
+    /// A push button with a label, such as `<input type="submit">`.
+    ButtonControl,
+    /// A drop-down list box that shows its selected option: `<select>`.
+    SelectControl,
+}
+
+/// A fragment that represents a native form control: an `input`, `select` or `textarea` element.
+/// Form controls are replaced content; layout draws them from the state of the element.
+#[deriving(Clone)]
+pub struct FormControlFragmentInfo {
+    /// The kind of control.
+    pub kind: FormControlKind,
+    /// The lines of text that the control shows: the value of a text field, the label of a button
+    /// or the selected option of a select box.
+    pub lines: Vec<Arc<Box<TextRun>>>,
+    /// The size of the content box of the control if its `width` and `height` are `auto`. This is
+    /// derived from the font, so that controls grow with the text in them.
+    pub intrinsic_size: Size2D<Au>,
+    pub computed_width: Option<Au>,
+    pub computed_height: Option<Au>,
+}
+
+impl FormControlFragmentInfo {
+    /// Creates the information specific to a form control fragment.
+    pub fn new(node: &ThreadSafeLayoutNode, font_context: &mut FontContext)
+               -> FormControlFragmentInfo {
+        let style = node.style();
+        let font_size = style.get_font().font_size;
+        let line_height = text::line_height_from_style(&**style, font_size);
+        let decoration = style.get_text().text_decoration;
+        let fontgroup = font_context.get_resolved_font_for_style(
+            &text::computed_style_to_font_style(&**style));
+        let create_run = |text: String| -> Arc<Box<TextRun>> {
+            Arc::new(box fontgroup.borrow().create_textrun(text, decoration, vec!()))
+        };
+
+        // The advance of "0", which is what the `ch` unit measures. Text fields and text areas are
+        // as wide as that many characters.
+        let ch = run_advance(&**create_run("0".to_string()));
+
+        let element = node.as_element();
+        let uint_attribute = |name: &str, default: uint| {
+            let value: Option<uint> = element.get_attr(&namespace::Null, name).and_then(|string| {
+                FromStr::from_str(string.trim())
+            });
+            match value {
+                Some(value) if value > 0 => value,
+                _ => default,
+            }
+        };
+
+        let (kind, lines, intrinsic_size) = match node.type_id() {
+            Some(ElementNodeTypeId(HTMLTextAreaElementTypeId)) => {
+                let text = node.text_content();
+                let lines = text.as_slice().split('\n').map(|line| {
+                    create_run(line.to_string())
+                }).collect();
+                let size = Size2D(ch.scale_by(uint_attribute("cols", 20) as f64),
+                                  line_height.scale_by(uint_attribute("rows", 2) as f64));
+                (TextAreaControl, lines, size)
+            }
+            Some(ElementNodeTypeId(HTMLSelectElementTypeId)) => {
+                let options = select_options(node);
+
+                // The box is wide enough for the longest option, so that it doesn't change size
+                // when the selection does. The drop-down arrow takes up a square next to it.
+                let widest = options.iter().fold(Au(0), |widest, &(ref label, _)| {
+                    Au::max(widest, run_advance(&**create_run(label.clone())))
+                });
+                let selected = options.iter()
+                                      .find(|&&(_, selected)| selected)
+                                      .or(options.iter().next());
+                let lines = selected.map(|&(ref label, _)| create_run(label.clone()))
+                                    .move_iter()
+                                    .collect();
+                (SelectControl, lines, Size2D(widest + line_height, line_height))
+            }
+            _ => {
+                let input_type = element.get_attr(&namespace::Null, "type").unwrap_or("text");
+                let input_type = input_type.to_ascii_lower();
+                match input_type.as_slice() {
+                    "checkbox" | "radio" => {
+                        let checked = node.input_checked();
+                        let kind = if input_type.as_slice() == "checkbox" {
+                            CheckboxControl(checked)
+                        } else {
+                            RadioControl(checked)
+                        };
+                        let length = font_size.scale_by(CHECKBOX_SIZE);
+                        (kind, vec!(), Size2D(length, length))
+                    }
+                    "button" | "reset" | "submit" => {
+                        let label = match element.get_attr(&namespace::Null, "value") {
+                            Some(value) => value.to_string(),
+                            None => default_button_label(input_type.as_slice()).to_string(),
+                        };
+                        let run = create_run(label);
+                        let size = Size2D(run_advance(&**run), line_height);
+                        (ButtonControl, vec!(run), size)
+                    }
+                    _ => {
+                        let value = node.input_value();
+                        let value = if input_type.as_slice() == "password" {
+                            value.as_slice().chars().map(|_| PASSWORD_MASK_CHAR).collect()
+                        } else {
+                            value
+                        };
+                        let size = Size2D(ch.scale_by(uint_attribute("size", 20) as f64),
+                                          line_height);
+                        (TextFieldControl, vec!(create_run(value)), size)
+                    }
+                }
+            }
+        };
+
+        FormControlFragmentInfo {
+            kind: kind,
+            lines: lines,
+            intrinsic_size: intrinsic_size,
+            computed_width: None,
+            computed_height: None,
+        }
+    }
+
+    /// Computes and stores the used width and height of the control from its style and its
+    /// intrinsic size. Form controls have no intrinsic ratio.
+    pub fn compute_used_size(&mut self, style: &ComputedValues, container_width: Au) {
+        let size = replaced_used_size_from_style(style,
+                                                 container_width,
+                                                 None,
+                                                 None,
+                                                 self.intrinsic_size,
+                                                 false);
+        self.computed_width = Some(size.width);
+        self.computed_height = Some(size.height);
+    }
+
+    /// Returns the used width of the control.
+    pub fn computed_width(&self) -> Au {
+        self.computed_width.expect("form control width is not computed yet!")
+    }
+
+    /// Returns the used height of the control.
+    pub fn computed_height(&self) -> Au {
+        self.computed_height.expect("form control height is not computed yet!")
+    }
+}
+
+/// The size of checkboxes and radio buttons, relative to the font size.
+static CHECKBOX_SIZE: f64 = 0.8;
+
+/// The character that stands in for each character of the value of a password field.
+static PASSWORD_MASK_CHAR: char = '\u25cf';
+
+/// Returns the label of an `input` element of the given button type with no `value` attribute.
+fn default_button_label(input_type: &str) -> &'static str {
+    match input_type {
+        "reset" => "Reset",
+        "submit" => "Submit",
+        _ => "",
+    }
+}
+
+/// Returns the label and selectedness of each option of a `select` element, including the options
+/// in option groups. At most one option is selected.
+fn select_options(node: &ThreadSafeLayoutNode) -> Vec<(String, bool)> {
+    fn collect(node: &ThreadSafeLayoutNode, options: &mut Vec<(String, bool)>) {
+        for kid in node.children() {
+            match kid.type_id() {
+                Some(ElementNodeTypeId(HTMLOptionElementTypeId)) => {
+                    let selected = kid.as_element().get_attr(&namespace::Null, "selected");
+                    let label = kid.text_content().as_slice().trim().to_string();
+                    options.push((label, selected.is_some()))
+                }
+                Some(ElementNodeTypeId(HTMLOptGroupElementTypeId)) => collect(&kid, options),
+                _ => {}
+            }
+        }
+    }
+
+    let mut options = vec!();
+    collect(node, &mut options);
+
+    // Only the last option with a `selected` attribute is selected, as in HTML.
+    match options.iter().rposition(|&(_, selected)| selected) {
+        None => options,
+        Some(index) => {
+            options.move_iter().enumerate().map(|(i, (label, _))| (label, i == index)).collect()
+        }
+    }
+}
+
+/// Returns the advance of the whole of the given text run.
+fn run_advance(run: &TextRun) -> Au {
+    run.advance_for_range(&Range::new(CharIndex(0), run.char_len()))
+}
+
 /// A scanned text fragment represents a single run of text with a distinct style. A `TextFragment`
 /// may be split into two or more fragments across line breaks. Several `TextFragment`s may
 /// correspond to a single DOM text node. Split text fragments are implemented by referring to
@@ -230,6 +691,14 @@ pub struct ScannedTextFragmentInfo {
 
     /// The range within the above text run that this represents.
     pub range: Range<CharIndex>,
+
+    /// The space added after each word-separating character in the range by
+    /// `text-align: justify`. This is included in the width of the fragment.
+    pub extra_word_spacing: Au,
+
+    /// Whether a hyphen is displayed after the range because the line was broken at a soft
+    /// hyphen. The hyphen is included in the width of the fragment.
+    pub hyphenated: bool,
 }
 
 impl ScannedTextFragmentInfo {
@@ -238,6 +707,8 @@ impl ScannedTextFragmentInfo {
         ScannedTextFragmentInfo {
             run: run,
             range: range,
+            extra_word_spacing: Au(0),
+            hyphenated: false,
         }
     }
 }
@@ -248,6 +719,8 @@ pub struct SplitInfo {
     // currently needed for splitting in the `inline::try_append_*` functions.
     pub range: Range<CharIndex>,
     pub width: Au,
+    /// Whether the split ends at a soft hyphen, so that a hyphen must be displayed after it.
+    pub hyphenated: bool,
 }
 
 impl SplitInfo {
@@ -255,6 +728,15 @@ impl SplitInfo {
         SplitInfo {
             range: range,
             width: info.run.advance_for_range(&range),
+            hyphenated: false,
+        }
+    }
+
+    fn new_hyphenated(range: Range<CharIndex>, info: &ScannedTextFragmentInfo) -> SplitInfo {
+        SplitInfo {
+            range: range,
+            width: info.run.advance_for_range(&range) + info.run.hyphen_advance(),
+            hyphenated: true,
         }
     }
 }
@@ -320,11 +802,12 @@ impl Fragment {
         Fragment {
             node: OpaqueNodeMethods::from_thread_safe_layout_node(node),
             style: node.style().clone(),
-            border_box: Rect::zero(),
-            border_padding: Zero::zero(),
-            margin: Zero::zero(),
+            border_box: LogicalRect::zero(),
+            border_padding: LogicalMargin::zero(),
+            margin: LogicalMargin::zero(),
             specific: constructor.build_specific_fragment_info_for_node(node),
             new_line_pos: vec!(),
+            bidi_level: 0,
         }
     }
 
@@ -333,11 +816,12 @@ impl Fragment {
         Fragment {
             node: OpaqueNodeMethods::from_thread_safe_layout_node(node),
             style: node.style().clone(),
-            border_box: Rect::zero(),
-            border_padding: Zero::zero(),
-            margin: Zero::zero(),
+            border_box: LogicalRect::zero(),
+            border_padding: LogicalMargin::zero(),
+            margin: LogicalMargin::zero(),
             specific: specific,
             new_line_pos: vec!(),
+            bidi_level: 0,
         }
     }
 
@@ -356,11 +840,12 @@ impl Fragment {
         Fragment {
             node: OpaqueNodeMethods::from_thread_safe_layout_node(node),
             style: Arc::new(node_style),
-            border_box: Rect::zero(),
-            border_padding: Zero::zero(),
-            margin: Zero::zero(),
+            border_box: LogicalRect::zero(),
+            border_padding: LogicalMargin::zero(),
+            margin: LogicalMargin::zero(),
             specific: specific,
             new_line_pos: vec!(),
+            bidi_level: 0,
         }
     }
 
@@ -372,11 +857,12 @@ impl Fragment {
         Fragment {
             node: node,
             style: style,
-            border_box: Rect::zero(),
-            border_padding: Zero::zero(),
-            margin: Zero::zero(),
+            border_box: LogicalRect::zero(),
+            border_padding: LogicalMargin::zero(),
+            margin: LogicalMargin::zero(),
             specific: specific,
             new_line_pos: vec!(),
+            bidi_level: 0,
         }
     }
 
@@ -388,15 +874,19 @@ impl Fragment {
 
     /// Transforms this fragment into another fragment of the given type, with the given size, preserving all
     /// the other data.
-    pub fn transform(&self, size: Size2D<Au>, specific: SpecificFragmentInfo) -> Fragment {
+    pub fn transform(&self, size: LogicalSize<Au>, specific: SpecificFragmentInfo) -> Fragment {
         Fragment {
             node: self.node,
             style: self.style.clone(),
-            border_box: Rect(self.border_box.origin, size),
+            border_box: LogicalRect {
+                start: self.border_box.start,
+                size: size,
+            },
             border_padding: self.border_padding,
             margin: self.margin,
             specific: specific,
             new_line_pos: self.new_line_pos.clone(),
+            bidi_level: self.bidi_level,
         }
     }
 
@@ -404,7 +894,9 @@ impl Fragment {
     /// replaced elements.
     fn style_specified_intrinsic_width(&self) -> IntrinsicWidths {
         let (use_margins, use_padding) = match self.specific {
-            GenericFragment | IframeFragment(_) | ImageFragment(_) => (true, true),
+            GenericFragment | FormControlFragment(_) | IframeFragment(_) | ImageFragment(_) => {
+                (true, true)
+            }
             TableFragment | TableCellFragment => (false, true),
             TableWrapperFragment => (true, false),
             TableRowFragment => (false, false),
@@ -412,29 +904,36 @@ impl Fragment {
                 // Styles are irrelevant for these kinds of fragments.
                 return IntrinsicWidths::new()
             }
+            InlineBlockFragment(_) => {
+                // The block flow accounts for the style of the inline block.
+                return IntrinsicWidths::new()
+            }
         };
 
         let style = self.style();
-        let width = MaybeAuto::from_style(style.get_box().width, Au::new(0)).specified_or_zero();
+        let width = MaybeAuto::from_style(style.content_inline_size(), Au::new(0))
+            .specified_or_zero();
 
-        let (margin_left, margin_right) = if use_margins {
-            (MaybeAuto::from_style(style.get_margin().margin_left, Au(0)).specified_or_zero(),
-             MaybeAuto::from_style(style.get_margin().margin_right, Au(0)).specified_or_zero())
+        let (margin_start, margin_end) = if use_margins {
+            let margin = style.logical_margin();
+            (MaybeAuto::from_style(margin.inline_start, Au(0)).specified_or_zero(),
+             MaybeAuto::from_style(margin.inline_end, Au(0)).specified_or_zero())
         } else {
             (Au(0), Au(0))
         };
 
-        let (padding_left, padding_right) = if use_padding {
-            (model::specified(style.get_padding().padding_left, Au(0)),
-             model::specified(style.get_padding().padding_right, Au(0)))
+        let (padding_start, padding_end) = if use_padding {
+            let padding = style.logical_padding();
+            (model::specified(padding.inline_start, Au(0)),
+             model::specified(padding.inline_end, Au(0)))
         } else {
             (Au(0), Au(0))
         };
 
         // FIXME(#2261, pcwalton): This won't work well for inlines: is this OK?
-        let border = self.border_width(None);
-        let surround_width = margin_left + margin_right + padding_left + padding_right +
-                border.horizontal();
+        let border = LogicalMargin::from_physical(style.writing_mode(), self.border_width(None));
+        let surround_width = margin_start + margin_end + padding_start + padding_end +
+                border.inline_start_end();
 
         IntrinsicWidths {
             minimum_width: width,
@@ -461,26 +960,27 @@ impl Fragment {
         }
     }
 
-    /// Computes the border, padding, and vertical margins from the containing block width and the
-    /// style. After this call, the `border_padding` and the vertical direction of the `margin`
-    /// field will be correct.
+    /// Computes the border, padding, and block-direction margins from the containing block width
+    /// and the style. After this call, the `border_padding` and the block direction of the
+    /// `margin` field will be correct.
     pub fn compute_border_padding_margins(&mut self,
                                           containing_block_width: Au,
                                           inline_fragment_context: Option<InlineFragmentContext>) {
-        // Compute vertical margins. Note that this value will be ignored by layout if the style
-        // specifies `auto`.
+        // Compute block-direction margins. Note that this value will be ignored by layout if the
+        // style specifies `auto`.
         match self.specific {
             TableFragment | TableCellFragment | TableRowFragment | TableColumnFragment(_) => {
-                self.margin.top = Au(0);
-                self.margin.bottom = Au(0)
+                self.margin.block_start = Au(0);
+                self.margin.block_end = Au(0)
             }
             _ => {
                 // NB: Percentages are relative to containing block width (not height) per CSS 2.1.
-                self.margin.top =
-                    MaybeAuto::from_style(self.style().get_margin().margin_top,
+                let margin = self.style().logical_margin();
+                self.margin.block_start =
+                    MaybeAuto::from_style(margin.block_start,
                                           containing_block_width).specified_or_zero();
-                self.margin.bottom =
-                    MaybeAuto::from_style(self.style().get_margin().margin_bottom,
+                self.margin.block_end =
+                    MaybeAuto::from_style(margin.block_end,
                                           containing_block_width).specified_or_zero()
             }
         }
@@ -506,7 +1006,8 @@ impl Fragment {
             }
         };
 
-        self.border_padding = border + padding
+        self.border_padding = LogicalMargin::from_physical(self.style().writing_mode(),
+                                                           border + padding)
     }
 
     // Return offset from original position because of `position: relative`.
@@ -515,16 +1016,20 @@ impl Fragment {
                              inline_fragment_context: Option<InlineFragmentContext>)
                              -> Point2D<Au> {
         fn left_right(style: &ComputedValues, block_width: Au) -> Au {
-            // TODO(ksh8281) : consider RTL(right-to-left) culture
-            match (style.get_positionoffsets().left, style.get_positionoffsets().right) {
-                (LPA_Auto, _) => {
-                    -MaybeAuto::from_style(style.get_positionoffsets().right, block_width)
-                        .specified_or_zero()
-                }
-                (_, _) => {
-                    MaybeAuto::from_style(style.get_positionoffsets().left, block_width)
-                        .specified_or_zero()
-                }
+            // If both are specified, 'right' is ignored if direction is 'ltr' and 'left' is
+            // ignored if it is 'rtl'.
+            let use_right = match (style.get_positionoffsets().left,
+                                   style.get_positionoffsets().right) {
+                (LPA_Auto, _) => true,
+                (_, LPA_Auto) => false,
+                (_, _) => style.get_inheritedbox().direction == direction::rtl,
+            };
+            if use_right {
+                -MaybeAuto::from_style(style.get_positionoffsets().right, block_width)
+                    .specified_or_zero()
+            } else {
+                MaybeAuto::from_style(style.get_positionoffsets().left, block_width)
+                    .specified_or_zero()
             }
         }
 
@@ -614,16 +1119,47 @@ impl Fragment {
         self.style().get_text().text_decoration
     }
 
-    /// Returns the left offset from margin edge to content edge.
+    /// Returns the `hyphens` property of this fragment's style.
+    pub fn hyphens(&self) -> hyphens::T {
+        self.style().get_inheritedtext().hyphens
+    }
+
+    /// Returns the inline-start offset from margin edge to content edge.
     ///
     /// FIXME(#2262, pcwalton): I think this method is pretty bogus, because it won't work for
     /// inlines.
-    pub fn left_offset(&self) -> Au {
+    pub fn inline_start_offset(&self) -> Au {
         match self.specific {
-            TableWrapperFragment => self.margin.left,
-            TableFragment | TableCellFragment | TableRowFragment => self.border_padding.left,
+            TableWrapperFragment => self.margin.inline_start,
+            TableFragment | TableCellFragment | TableRowFragment => {
+                self.border_padding.inline_start
+            }
             TableColumnFragment(_) => Au(0),
-            _ => self.margin.left + self.border_padding.left,
+            _ => self.margin.inline_start + self.border_padding.inline_start,
+        }
+    }
+
+    /// Returns the orientation of the glyphs of the given text fragment, which is this fragment,
+    /// according to the writing mode and the `text-orientation` property.
+    fn text_orientation(&self, text_fragment: &ScannedTextFragmentInfo) -> TextOrientation {
+        if !self.style().writing_mode().is_vertical() {
+            return HorizontalTextOrientation
+        }
+        match self.style().get_inheritedbox().text_orientation {
+            text_orientation::upright => VerticalUprightTextOrientation,
+            text_orientation::sideways => VerticalSidewaysTextOrientation,
+            text_orientation::mixed => {
+                // `InlineFlow::split_text_by_orientation` splits text fragments where the
+                // orientation changes, so the first character stands for the whole fragment.
+                let first_char = text_fragment.run.text.as_slice().chars()
+                                              .nth(text_fragment.range.begin().to_uint());
+                match first_char {
+                    Some(ch) if text_util::is_upright_in_vertical_text(ch) => {
+                        VerticalUprightTextOrientation
+                    }
+                    _ => VerticalSidewaysTextOrientation,
+                }
+            }
         }
     }
 
@@ -755,6 +1291,15 @@ impl Fragment {
             return
         }
 
+        // In the collapsing border model, the borders of tables and their parts are resolved by
+        // the table and painted by the cells.
+        match self.specific {
+            TableWrapperFragment | TableFragment | TableRowFragment | TableCellFragment
+                    if self.style().get_inheritedtable().border_collapse ==
+                        border_collapse::collapse => return,
+            _ => {}
+        }
+
         let style = self.style();
         let top_color = style.resolve_color(style.get_border().border_top_color);
         let right_color = style.resolve_color(style.get_border().border_right_color);
@@ -778,13 +1323,55 @@ impl Fragment {
         list.push(BorderDisplayItemClass(border_display_item))
     }
 
+    /// Adds the display items that paint the given column rules of this fragment, which belongs
+    /// to a multi-column container, to a display list. Each rule is a rectangle relative to the
+    /// border box of this fragment, as wide along the inline axis as the rule is thick.
+    pub fn build_display_list_for_column_rules(&self,
+                                               list: &mut DisplayList,
+                                               layout_context: &LayoutContext,
+                                               flow_origin: Point2D<Au>,
+                                               flow_size: Size2D<Au>,
+                                               rules: &[LogicalRect<Au>],
+                                               level: StackingLevel) {
+        let style = self.style();
+        if style.get_inheritedbox().visibility != visibility::visible {
+            return
+        }
+
+        let writing_mode = style.writing_mode();
+        let column = style.get_column();
+        let width = column.column_rule_width;
+        let color = style.resolve_color(column.column_rule_color).to_gfx_color();
+        for rule in rules.iter() {
+            let bounds = LogicalRect::new(self.border_box.start.i + rule.start.i,
+                                          self.border_box.start.b + rule.start.b,
+                                          rule.size.inline,
+                                          rule.size.block);
+            let abs_bounds = bounds.to_physical(writing_mode, flow_size).translate(&flow_origin);
+            if !abs_bounds.intersects(&layout_context.dirty) {
+                continue
+            }
+
+            // A rule is the start border of an otherwise empty box.
+            let border = if writing_mode.is_vertical() {
+                SideOffsets2D::new(width, Au(0), Au(0), Au(0))
+            } else {
+                SideOffsets2D::new(Au(0), Au(0), Au(0), width)
+            };
+            let border_display_item = box BorderDisplayItem {
+                base: BaseDisplayItem::new(abs_bounds, self.node, level),
+                border: border,
+                color: SideOffsets2D::new_all_same(color),
+                style: SideOffsets2D::new_all_same(column.column_rule_style),
+            };
+            list.push(BorderDisplayItemClass(border_display_item))
+        }
+    }
+
     fn build_debug_borders_around_text_fragments(&self,
                                              display_list: &mut DisplayList,
-                                             flow_origin: Point2D<Au>,
+                                             absolute_fragment_bounds: Rect<Au>,
                                              text_fragment: &ScannedTextFragmentInfo) {
-        let fragment_bounds = self.border_box;
-        let absolute_fragment_bounds = fragment_bounds.translate(&flow_origin);
-
         // Compute the text fragment bounds and draw a border surrounding them.
         let debug_border = SideOffsets2D::new_all_same(Au::from_px(1));
 
@@ -811,10 +1398,7 @@ impl Fragment {
 
     fn build_debug_borders_around_fragment(&self,
                                       display_list: &mut DisplayList,
-                                      flow_origin: Point2D<Au>) {
-        let fragment_bounds = self.border_box;
-        let absolute_fragment_bounds = fragment_bounds.translate(&flow_origin);
-
+                                      absolute_fragment_bounds: Rect<Au>) {
         // This prints a debug border around the border of this fragment.
         let debug_border = SideOffsets2D::new_all_same(Au::from_px(1));
 
@@ -835,15 +1419,19 @@ impl Fragment {
     /// * `layout_context`: The layout context.
     /// * `dirty`: The dirty rectangle in the coordinate system of the owning flow.
     /// * `flow_origin`: Position of the origin of the owning flow wrt the display list root flow.
+    /// * `flow_size`: The physical size of the owning flow, against which the logical position of
+    ///   this fragment is resolved.
     pub fn build_display_list(&self,
                               display_list: &mut DisplayList,
                               layout_context: &LayoutContext,
                               flow_origin: Point2D<Au>,
+                              flow_size: Size2D<Au>,
                               background_and_border_level: BackgroundAndBorderLevel,
                               inline_fragment_context: Option<InlineFragmentContext>)
                               -> ChildDisplayListAccumulator {
         // Fragment position wrt to the owning flow.
-        let fragment_bounds = self.border_box;
+        let writing_mode = self.style().writing_mode();
+        let fragment_bounds = self.border_box.to_physical(writing_mode, flow_size);
         let absolute_fragment_bounds = fragment_bounds.translate(&flow_origin);
         debug!("Fragment::build_display_list at rel={}, abs={}: {}",
                fragment_bounds,
@@ -910,9 +1498,10 @@ impl Fragment {
                                                             .map(|c| c.to_gfx_color()),
                 };
 
-                let mut bounds = absolute_fragment_bounds.clone();
-                bounds.origin.x = bounds.origin.x + self.border_padding.left;
-                bounds.size.width = bounds.size.width - self.border_padding.horizontal();
+                let mut bounds = self.border_box;
+                bounds.start.i = bounds.start.i + self.border_padding.inline_start;
+                bounds.size.inline = bounds.size.inline - self.border_padding.inline_start_end();
+                let bounds = bounds.to_physical(writing_mode, flow_size).translate(&flow_origin);
 
                 // Create the text fragment.
                 let text_display_item = box TextDisplayItem {
@@ -921,6 +1510,10 @@ impl Fragment {
                     range: text_fragment.range,
                     text_color: text_color,
                     text_decorations: text_decorations,
+                    extra_word_spacing: text_fragment.extra_word_spacing,
+                    hyphenated: text_fragment.hyphenated,
+                    right_to_left: bidi::is_right_to_left(self.bidi_level),
+                    orientation: self.text_orientation(text_fragment),
                 };
                 accumulator.push(display_list, TextDisplayItemClass(text_display_item));
 
@@ -929,21 +1522,33 @@ impl Fragment {
                 // FIXME(#2263, pcwalton): This is a bit of an abuse of the logging infrastructure.
                 // We should have a real `SERVO_DEBUG` system.
                 debug!("{:?}", self.build_debug_borders_around_text_fragments(display_list,
-                                                                          flow_origin,
+                                                                          absolute_fragment_bounds,
                                                                           text_fragment))
             },
-            GenericFragment | IframeFragment(..) | TableFragment | TableCellFragment | TableRowFragment |
-            TableWrapperFragment => {
+            GenericFragment | IframeFragment(..) | InlineBlockFragment(_) | TableFragment |
+            TableCellFragment | TableRowFragment | TableWrapperFragment => {
                 // FIXME(pcwalton): This is a bit of an abuse of the logging infrastructure. We
                 // should have a real `SERVO_DEBUG` system.
-                debug!("{:?}", self.build_debug_borders_around_fragment(display_list, flow_origin))
+                debug!("{:?}", self.build_debug_borders_around_fragment(display_list,
+                                                                    absolute_fragment_bounds))
             },
+            FormControlFragment(ref form_control_fragment) => {
+                let bounds = self.content_box()
+                                 .to_physical(writing_mode, flow_size)
+                                 .translate(&flow_origin);
+                let clip_display_item =
+                    self.build_display_list_for_form_control(form_control_fragment, bounds);
+                accumulator.push(display_list, ClipDisplayItemClass(clip_display_item));
+
+                // FIXME(pcwalton): This is a bit of an abuse of the logging
+                // infrastructure. We should have a real `SERVO_DEBUG` system.
+                debug!("{:?}", self.build_debug_borders_around_fragment(display_list,
+                                                                    absolute_fragment_bounds))
+            }
             ImageFragment(_) => {
-                let mut bounds = absolute_fragment_bounds.clone();
-                bounds.origin.x = bounds.origin.x + self.border_padding.left;
-                bounds.origin.y = bounds.origin.y + self.border_padding.top;
-                bounds.size.width = bounds.size.width - self.border_padding.horizontal();
-                bounds.size.height = bounds.size.height - self.border_padding.vertical();
+                let bounds = self.content_box()
+                                 .to_physical(writing_mode, flow_size)
+                                 .translate(&flow_origin);
 
                 match self.specific {
                     ImageFragment(ref image_fragment) => {
@@ -952,16 +1557,33 @@ impl Fragment {
                             Some(image) => {
                                 debug!("(building display list) building image fragment");
 
-                                // Place the image into the display list.
+                                // Place the image into the display list, clipping it to the
+                                // content box if `object-fit` and `object-position` move it
+                                // outside.
+                                let image_size = Size2D(Au::from_px(image.width as int),
+                                                        Au::from_px(image.height as int));
+                                let image_bounds = object_bounds(self.style(), bounds, image_size);
                                 let image_display_item = box ImageDisplayItem {
-                                    base: BaseDisplayItem::new(bounds,
+                                    base: BaseDisplayItem::new(image_bounds,
                                                                self.node,
                                                                ContentStackingLevel),
                                     image: image.clone(),
-                                    stretch_size: bounds.size,
+                                    stretch_size: image_bounds.size,
                                 };
-                                accumulator.push(display_list,
-                                                 ImageDisplayItemClass(image_display_item))
+                                let image_display_item = ImageDisplayItemClass(image_display_item);
+                                if bounds.union(&image_bounds) == bounds {
+                                    accumulator.push(display_list, image_display_item)
+                                } else {
+                                    let mut clip_display_item = box ClipDisplayItem {
+                                        base: BaseDisplayItem::new(bounds,
+                                                                   self.node,
+                                                                   ContentStackingLevel),
+                                        children: DisplayList::new(),
+                                    };
+                                    clip_display_item.children.push(image_display_item);
+                                    accumulator.push(display_list,
+                                                     ClipDisplayItemClass(clip_display_item))
+                                }
                             }
                             None => {
                                 // No image data at all? Do nothing.
@@ -976,7 +1598,8 @@ impl Fragment {
 
                 // FIXME(pcwalton): This is a bit of an abuse of the logging
                 // infrastructure. We should have a real `SERVO_DEBUG` system.
-                debug!("{:?}", self.build_debug_borders_around_fragment(display_list, flow_origin))
+                debug!("{:?}", self.build_debug_borders_around_fragment(display_list,
+                                                                    absolute_fragment_bounds))
             }
         }
 
@@ -1008,11 +1631,31 @@ impl Fragment {
         match self.specific {
             GenericFragment | IframeFragment(_) | TableFragment | TableCellFragment | TableColumnFragment(_) | TableRowFragment |
             TableWrapperFragment => {}
+            InlineBlockFragment(ref mut info) => {
+                let block_flow = info.flow_ref.get_mut();
+                let block_intrinsic_widths = &flow::base(block_flow).intrinsic_widths;
+                result.minimum_width = block_intrinsic_widths.total_minimum_width();
+                result.preferred_width = block_intrinsic_widths.total_preferred_width();
+            }
             ImageFragment(ref mut image_fragment_info) => {
-                let image_width = image_fragment_info.image_width();
+                let image_width = if self.style.writing_mode().is_vertical() {
+                    image_fragment_info.image_height()
+                } else {
+                    image_fragment_info.image_width()
+                };
                 result.minimum_width = geometry::max(result.minimum_width, image_width);
                 result.preferred_width = geometry::max(result.preferred_width, image_width);
             }
+            FormControlFragment(ref form_control_fragment_info) => {
+                let intrinsic_size = form_control_fragment_info.intrinsic_size;
+                let width = if self.style.writing_mode().is_vertical() {
+                    intrinsic_size.height
+                } else {
+                    intrinsic_size.width
+                };
+                result.minimum_width = geometry::max(result.minimum_width, width);
+                result.preferred_width = geometry::max(result.preferred_width, width);
+            }
             ScannedTextFragment(ref text_fragment_info) => {
                 let range = &text_fragment_info.range;
                 let min_line_width = text_fragment_info.run.min_width_for_range(range);
@@ -1033,9 +1676,14 @@ impl Fragment {
         match inline_fragment_context {
             None => {}
             Some(context) => {
+                let writing_mode = self.style.writing_mode();
                 for range in context.ranges() {
-                    let border_width = range.border().horizontal();
-                    let padding_width = range.padding().horizontal();
+                    let border_width =
+                        LogicalMargin::from_physical(writing_mode, range.border())
+                            .inline_start_end();
+                    let padding_width =
+                        LogicalMargin::from_physical(writing_mode, range.padding())
+                            .inline_start_end();
                     result.minimum_width = result.minimum_width + border_width + padding_width;
                     result.preferred_width = result.preferred_width + border_width + padding_width;
                 }
@@ -1047,13 +1695,25 @@ impl Fragment {
 
 
     /// TODO: What exactly does this function return? Why is it Au(0) for GenericFragment?
-    pub fn content_width(&self) -> Au {
+    pub fn content_inline_size(&self) -> Au {
         match self.specific {
             GenericFragment | IframeFragment(_) | TableFragment | TableCellFragment | TableRowFragment |
             TableWrapperFragment => Au(0),
             ImageFragment(ref image_fragment_info) => {
-                image_fragment_info.computed_width()
+                if self.style().writing_mode().is_vertical() {
+                    image_fragment_info.computed_height()
+                } else {
+                    image_fragment_info.computed_width()
+                }
+            }
+            FormControlFragment(ref form_control_fragment_info) => {
+                if self.style().writing_mode().is_vertical() {
+                    form_control_fragment_info.computed_height()
+                } else {
+                    form_control_fragment_info.computed_width()
+                }
             }
+            InlineBlockFragment(_) => self.border_box.size.inline,
             ScannedTextFragment(ref text_fragment_info) => {
                 let (range, run) = (&text_fragment_info.range, &text_fragment_info.run);
                 let text_bounds = run.metrics_for_range(range).bounding_box;
@@ -1064,14 +1724,26 @@ impl Fragment {
         }
     }
 
-    /// Returns, and computes, the height of this fragment.
-    pub fn content_height(&self) -> Au {
+    /// Returns, and computes, the block size of this fragment.
+    pub fn content_block_size(&self) -> Au {
         match self.specific {
             GenericFragment | IframeFragment(_) | TableFragment | TableCellFragment | TableRowFragment |
             TableWrapperFragment => Au(0),
             ImageFragment(ref image_fragment_info) => {
-                image_fragment_info.computed_height()
+                if self.style().writing_mode().is_vertical() {
+                    image_fragment_info.computed_width()
+                } else {
+                    image_fragment_info.computed_height()
+                }
+            }
+            FormControlFragment(ref form_control_fragment_info) => {
+                if self.style().writing_mode().is_vertical() {
+                    form_control_fragment_info.computed_width()
+                } else {
+                    form_control_fragment_info.computed_height()
+                }
             }
+            InlineBlockFragment(_) => self.border_box.size.block,
             ScannedTextFragment(ref text_fragment_info) => {
                 // Compute the height based on the line-height and font size.
                 //
@@ -1092,13 +1764,11 @@ impl Fragment {
     /// This is marked `#[inline]` because it is frequently called when only one or two of the
     /// values are needed and that will save computation.
     #[inline]
-    pub fn content_box(&self) -> Rect<Au> {
-        Rect {
-            origin: Point2D(self.border_box.origin.x + self.border_padding.left,
-                            self.border_box.origin.y + self.border_padding.top),
-            size: Size2D(self.border_box.size.width - self.border_padding.horizontal(),
-                         self.border_box.size.height - self.border_padding.vertical()),
-        }
+    pub fn content_box(&self) -> LogicalRect<Au> {
+        LogicalRect::new(self.border_box.start.i + self.border_padding.inline_start,
+                         self.border_box.start.b + self.border_padding.block_start,
+                         self.border_box.size.inline - self.border_padding.inline_start_end(),
+                         self.border_box.size.block - self.border_padding.block_start_end())
     }
 
     /// Find the split of a fragment that includes a new-line character.
@@ -1112,8 +1782,9 @@ impl Fragment {
     pub fn find_split_info_by_new_line(&self)
             -> Option<(SplitInfo, Option<SplitInfo>, Arc<Box<TextRun>> /* TODO(bjz): remove */)> {
         match self.specific {
-            GenericFragment | IframeFragment(_) | ImageFragment(_) | TableFragment | TableCellFragment |
-            TableRowFragment | TableWrapperFragment => None,
+            GenericFragment | FormControlFragment(_) | IframeFragment(_) | ImageFragment(_) |
+            InlineBlockFragment(_) | TableFragment | TableCellFragment | TableRowFragment |
+            TableWrapperFragment => None,
             TableColumnFragment(_) => fail!("Table column fragments do not need to split"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
             ScannedTextFragment(ref text_fragment_info) => {
@@ -1152,8 +1823,9 @@ impl Fragment {
     pub fn find_split_info_for_width(&self, start: CharIndex, max_width: Au, starts_line: bool)
             -> Option<(Option<SplitInfo>, Option<SplitInfo>, Arc<Box<TextRun>> /* TODO(bjz): remove */)> {
         match self.specific {
-            GenericFragment | IframeFragment(_) | ImageFragment(_) | TableFragment | TableCellFragment |
-            TableRowFragment | TableWrapperFragment => None,
+            GenericFragment | FormControlFragment(_) | IframeFragment(_) | ImageFragment(_) |
+            InlineBlockFragment(_) | TableFragment | TableCellFragment | TableRowFragment |
+            TableWrapperFragment => None,
             TableColumnFragment(_) => fail!("Table column fragments do not have width"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
             ScannedTextFragment(ref text_fragment_info) => {
@@ -1161,6 +1833,8 @@ impl Fragment {
                 let mut remaining_width: Au = max_width;
                 let mut left_range = Range::new(text_fragment_info.range.begin() + start, CharIndex(0));
                 let mut right_range: Option<Range<CharIndex>> = None;
+                let mut left_is_hyphenated = false;
+                let mut prev_slice_is_whitespace = true;
 
                 debug!("split_to_width: splitting text fragment (strlen={}, range={}, avail_width={})",
                        text_fragment_info.run.text.len(),
@@ -1215,10 +1889,16 @@ impl Fragment {
                             right_range = Some(Range::new(slice_begin, right_range_end));
                             debug!("split_to_width: case=splitting remainder with right range={:?}",
                                    right_range);
+
+                            // FIXME: The hyphen is not accounted for when choosing where to break,
+                            // so it may overflow the line slightly.
+                            left_is_hyphenated = !prev_slice_is_whitespace &&
+                                text_fragment_info.run.is_soft_hyphen_break(slice_begin);
                         }
                     }
 
                     pieces_processed_count += 1;
+                    prev_slice_is_whitespace = glyphs.is_whitespace();
 
                     if !should_continue {
                         break
@@ -1230,7 +1910,9 @@ impl Fragment {
                 if (pieces_processed_count == 1 || !left_is_some) && !starts_line {
                     None
                 } else {
-                    let left = if left_is_some {
+                    let left = if left_is_some && left_is_hyphenated {
+                        Some(SplitInfo::new_hyphenated(left_range, text_fragment_info))
+                    } else if left_is_some {
                         Some(SplitInfo::new(left_range, text_fragment_info))
                     } else {
                          None
@@ -1251,6 +1933,28 @@ impl Fragment {
         }
     }
 
+    /// Returns true if this fragment is text, scanned or not, that consists entirely of whitespace
+    /// that `white-space` processing collapses away.
+    pub fn is_collapsible_whitespace(&self) -> bool {
+        if self.white_space() != white_space::normal {
+            return false
+        }
+        match self.specific {
+            UnscannedTextFragment(ref text_fragment_info) => {
+                is_whitespace(text_fragment_info.text.as_slice())
+            }
+            ScannedTextFragment(ref text_fragment_info) => {
+                let text: String =
+                    text_fragment_info.run.text.as_slice().chars()
+                                      .skip(text_fragment_info.range.begin().to_uint())
+                                      .take(text_fragment_info.range.length().to_uint())
+                                      .collect();
+                is_whitespace(text.as_slice())
+            }
+            _ => false,
+        }
+    }
+
     /// Assigns replaced width, padding, and margins for this fragment only if it is replaced
     /// content per CSS 2.1 § 10.3.2.
     pub fn assign_replaced_width_if_necessary(&mut self,
@@ -1260,44 +1964,46 @@ impl Fragment {
         match self.specific {
             GenericFragment | IframeFragment(_) | TableFragment | TableCellFragment | TableRowFragment |
             TableWrapperFragment => return,
+            // The width of an inline block is only known once its block flow has been laid out; see
+            // `assign_replaced_height_if_necessary`.
+            InlineBlockFragment(_) => return,
             TableColumnFragment(_) => fail!("Table column fragments do not have width"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
-            ImageFragment(_) | ScannedTextFragment(_) => {}
+            FormControlFragment(_) | ImageFragment(_) | ScannedTextFragment(_) => {}
         };
 
         self.compute_border_padding_margins(container_width, inline_fragment_context);
 
-        let style_width = self.style().get_box().width;
-        let style_height = self.style().get_box().height;
-        let noncontent_width = self.border_padding.horizontal();
+        let is_vertical = self.style().writing_mode().is_vertical();
+        let noncontent_width = self.border_padding.inline_start_end();
 
         match self.specific {
             ScannedTextFragment(_) => {
                 // Scanned text fragments will have already had their content widths assigned by this
                 // point.
-                self.border_box.size.width = self.border_box.size.width + noncontent_width
+                self.border_box.size.inline = self.border_box.size.inline + noncontent_width
             }
             ImageFragment(ref mut image_fragment_info) => {
                 // TODO(ksh8281): compute border,margin
-                let width = ImageFragmentInfo::style_length(style_width,
-                                                       image_fragment_info.dom_width,
-                                                       container_width);
-                let height = ImageFragmentInfo::style_length(style_height,
-                                                        image_fragment_info.dom_height,
-                                                        Au(0));
-
-                let width = match (width,height) {
-                    (Auto, Auto) => image_fragment_info.image_width(),
-                    (Auto,Specified(h)) => {
-                        let scale = image_fragment_info.
-                            image_height().to_f32().unwrap() / h.to_f32().unwrap();
-                        Au::new((image_fragment_info.image_width().to_f32().unwrap() / scale) as i32)
-                    },
-                    (Specified(w), _) => w,
-                };
+                image_fragment_info.compute_used_size(&*self.style, container_width);
 
-                self.border_box.size.width = width + noncontent_width;
-                image_fragment_info.computed_width = Some(width);
+                // Images keep their physical dimensions, so in vertical writing modes their
+                // inline size is their height.
+                let inline_size = if is_vertical {
+                    image_fragment_info.computed_height()
+                } else {
+                    image_fragment_info.computed_width()
+                };
+                self.border_box.size.inline = inline_size + noncontent_width;
+            }
+            FormControlFragment(ref mut form_control_fragment_info) => {
+                form_control_fragment_info.compute_used_size(&*self.style, container_width);
+                let inline_size = if is_vertical {
+                    form_control_fragment_info.computed_height()
+                } else {
+                    form_control_fragment_info.computed_width()
+                };
+                self.border_box.size.inline = inline_size + noncontent_width;
             }
             _ => fail!("this case should have been handled above"),
         }
@@ -1313,44 +2019,61 @@ impl Fragment {
             TableWrapperFragment => return,
             TableColumnFragment(_) => fail!("Table column fragments do not have height"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
-            ImageFragment(_) | ScannedTextFragment(_) => {}
+            FormControlFragment(_) | ImageFragment(_) | InlineBlockFragment(_) |
+            ScannedTextFragment(_) => {}
         }
 
-        let style_width = self.style().get_box().width;
-        let style_height = self.style().get_box().height;
-        let noncontent_height = self.border_padding.vertical();
+        let is_vertical = self.style().writing_mode().is_vertical();
+        let noncontent_height = self.border_padding.block_start_end();
 
         match self.specific {
             ImageFragment(ref mut image_fragment_info) => {
                 // TODO(ksh8281): compute border,margin,padding
-                let width = image_fragment_info.computed_width();
-                // FIXME(ksh8281): we shouldn't assign height this way
-                // we don't know about size of parent's height
-                let height = ImageFragmentInfo::style_length(style_height,
-                                                        image_fragment_info.dom_height,
-                                                        Au(0));
-
-                let height = match (style_width, image_fragment_info.dom_width, height) {
-                    (LPA_Auto, None, Auto) => {
-                        image_fragment_info.image_height()
-                    },
-                    (_,_,Auto) => {
-                        let scale = image_fragment_info.image_width().to_f32().unwrap()
-                            / width.to_f32().unwrap();
-                        Au::new((image_fragment_info.image_height().to_f32().unwrap() / scale) as i32)
-                    },
-                    (_,_,Specified(h)) => {
-                        h
-                    }
+                // The height was computed along with the width.
+                let block_size = if is_vertical {
+                    image_fragment_info.computed_width()
+                } else {
+                    image_fragment_info.computed_height()
                 };
-
-                image_fragment_info.computed_height = Some(height);
-                self.border_box.size.height = height + noncontent_height
+                self.border_box.size.block = block_size + noncontent_height
+            }
+            FormControlFragment(ref form_control_fragment_info) => {
+                let block_size = if is_vertical {
+                    form_control_fragment_info.computed_width()
+                } else {
+                    form_control_fragment_info.computed_height()
+                };
+                self.border_box.size.block = block_size + noncontent_height
             }
             ScannedTextFragment(_) => {
                 // Scanned text fragments' content heights are calculated by the text run scanner
                 // during flow construction.
-                self.border_box.size.height = self.border_box.size.height + noncontent_height
+                self.border_box.size.block = self.border_box.size.block + noncontent_height
+            }
+            InlineBlockFragment(ref mut info) => {
+                // The block flow has been laid out by now, since it is a child of the inline flow
+                // that contains this fragment. This fragment covers its margin box.
+                let block_flow = info.flow_ref.get_mut().as_block();
+                let margin = block_flow.fragment.margin;
+                self.border_box.size.inline = block_flow.fragment.border_box.size.inline +
+                    margin.inline_start_end();
+                self.border_box.size.block = block_flow.fragment.border_box.size.block +
+                    margin.block_start_end();
+
+                // The baseline is that of the last line box in the block, unless it has none or
+                // clips its contents, in which case it is the bottom margin edge. See CSS 2.1 §
+                // 10.8.1. An inline-level flex container takes the baseline of its first line
+                // instead (CSS Flexbox § 8.5).
+                let is_flex = block_flow.fragment.style().get_box().display == display::inline_flex;
+                let baseline_offset = match block_flow.fragment.style().get_box().overflow {
+                    overflow::visible if is_flex => block_flow.baseline_offset_of_first_line_box(),
+                    overflow::visible => block_flow.baseline_offset_of_last_line_box(),
+                    overflow::hidden | overflow::scroll | overflow::auto => None,
+                };
+                info.baseline_offset = match baseline_offset {
+                    Some(baseline_offset) => margin.block_start + baseline_offset,
+                    None => self.border_box.size.block,
+                };
             }
             _ => fail!("should have been handled above"),
         }
@@ -1360,12 +2083,37 @@ impl Fragment {
     /// used in an inline formatting context. See CSS 2.1 § 10.8.1.
     pub fn inline_metrics(&self) -> InlineMetrics {
         match self.specific {
-            ImageFragment(ref image_fragment_info) => {
-                let computed_height = image_fragment_info.computed_height();
+            ImageFragment(_) => {
+                let content_block_size = self.content_block_size();
                 InlineMetrics {
-                    height_above_baseline: computed_height + self.border_padding.vertical(),
+                    height_above_baseline: content_block_size +
+                        self.border_padding.block_start_end(),
                     depth_below_baseline: Au(0),
-                    ascent: computed_height + self.border_padding.bottom,
+                    ascent: content_block_size + self.border_padding.block_end,
+                }
+            }
+            FormControlFragment(ref info) => {
+                // Controls with text are aligned by the baseline of their first line, like inline
+                // blocks; checkboxes and radio buttons sit on the baseline like images.
+                let baseline_offset = match info.lines.as_slice().head() {
+                    None => self.border_box.size.block,
+                    Some(run) => {
+                        let line_box = self.form_control_text_bounds(info, &***run, 0);
+                        self.border_padding.block_start + line_box.origin.y + run.ascent()
+                    }
+                };
+                InlineMetrics {
+                    height_above_baseline: baseline_offset,
+                    depth_below_baseline: self.border_box.size.block - baseline_offset,
+                    ascent: baseline_offset,
+                }
+            }
+            InlineBlockFragment(ref info) => {
+                // See CSS 2.1 § 10.8.1.
+                InlineMetrics {
+                    height_above_baseline: info.baseline_offset,
+                    depth_below_baseline: self.border_box.size.block - info.baseline_offset,
+                    ascent: info.baseline_offset,
                 }
             }
             ScannedTextFragment(ref text_fragment) => {
@@ -1376,9 +2124,9 @@ impl Fragment {
             }
             _ => {
                 InlineMetrics {
-                    height_above_baseline: self.border_box.size.height,
+                    height_above_baseline: self.border_box.size.block,
                     depth_below_baseline: Au(0),
-                    ascent: self.border_box.size.height,
+                    ascent: self.border_box.size.block,
                 }
             }
         }
@@ -1389,7 +2137,8 @@ impl Fragment {
         match (&self.specific, &other.specific) {
             (&UnscannedTextFragment(_), &UnscannedTextFragment(_)) => {
                 self.font_style() == other.font_style() &&
-                    self.text_decoration() == other.text_decoration()
+                    self.text_decoration() == other.text_decoration() &&
+                    self.hyphens() == other.hyphens()
             }
             _ => false,
         }
@@ -1398,20 +2147,126 @@ impl Fragment {
     /// A helper function to return a debug string describing the side offsets for one of the rect
     /// box model properties (border, padding, or margin).
     fn side_offsets_debug_fmt(&self, name: &str,
-                              value: SideOffsets2D<Au>,
+                              value: LogicalMargin<Au>,
                               f: &mut fmt::Formatter) -> fmt::Result {
-        if value.is_zero() {
+        if value == LogicalMargin::zero() {
             Ok(())
         } else {
             write!(f, "{}{},{},{},{}",
                    name,
-                   value.top,
-                   value.right,
-                   value.bottom,
-                   value.left)
+                   value.block_start,
+                   value.inline_end,
+                   value.block_end,
+                   value.inline_start)
         }
     }
 
+    /// Builds the display items that draw the contents of a form control inside the given content
+    /// box: its text, the mark of a checked checkbox or radio button, or the arrow of a select box.
+    /// The items are clipped to the content box, since values can be longer than fields.
+    fn build_display_list_for_form_control(&self,
+                                           form_control_fragment: &FormControlFragmentInfo,
+                                           bounds: Rect<Au>)
+                                           -> Box<ClipDisplayItem> {
+        let mut clip_display_item = box ClipDisplayItem {
+            base: BaseDisplayItem::new(bounds, self.node, ContentStackingLevel),
+            children: DisplayList::new(),
+        };
+        let color = self.style().get_color().color.to_gfx_color();
+
+        for (index, run) in form_control_fragment.lines.iter().enumerate() {
+            if run.char_len() == CharIndex(0) {
+                continue
+            }
+            let text_bounds = self.form_control_text_bounds(form_control_fragment, &***run, index)
+                                  .translate(&bounds.origin);
+            clip_display_item.children.push(TextDisplayItemClass(box TextDisplayItem {
+                base: BaseDisplayItem::new(text_bounds, self.node, ContentStackingLevel),
+                text_run: run.clone(),
+                range: Range::new(CharIndex(0), run.char_len()),
+                text_color: color,
+                text_decorations: TextDecorations {
+                    underline: None,
+                    overline: None,
+                    line_through: None,
+                },
+                extra_word_spacing: Au(0),
+                hyphenated: false,
+                right_to_left: false,
+                orientation: HorizontalTextOrientation,
+            }))
+        }
+
+        let solid_color = |rect: Rect<Au>| {
+            SolidColorDisplayItemClass(box SolidColorDisplayItem {
+                base: BaseDisplayItem::new(rect, self.node, ContentStackingLevel),
+                color: color,
+            })
+        };
+        match form_control_fragment.kind {
+            // There is no display item for circles, so checked radio buttons get a smaller square
+            // mark than checkboxes do.
+            CheckboxControl(true) | RadioControl(true) => {
+                let inset = match form_control_fragment.kind {
+                    CheckboxControl(_) => 0.2,
+                    _ => 0.3,
+                };
+                let inset = Point2D(bounds.size.width.scale_by(inset),
+                                    bounds.size.height.scale_by(inset));
+                let mark = Rect(bounds.origin + inset,
+                                Size2D(bounds.size.width - inset.x - inset.x,
+                                       bounds.size.height - inset.y - inset.y));
+                clip_display_item.children.push(solid_color(mark))
+            }
+            SelectControl => {
+                // A downward-pointing triangle, drawn one device pixel row at a time, centered in
+                // the square at the end of the box.
+                let square_size = Au::min(bounds.size.height, bounds.size.width);
+                let row_count = geometry::to_px(square_size.scale_by(0.25));
+                let arrow_width = Au::from_px(row_count * 2);
+                let origin = Point2D(
+                    bounds.origin.x + bounds.size.width - square_size.scale_by(0.5) -
+                        arrow_width.scale_by(0.5),
+                    bounds.origin.y + (bounds.size.height - Au::from_px(row_count)).scale_by(0.5));
+                for row in range(0, row_count) {
+                    let row_bounds = Rect(origin + Point2D(Au::from_px(row), Au::from_px(row)),
+                                          Size2D(arrow_width - Au::from_px(row * 2),
+                                                 Au::from_px(1)));
+                    clip_display_item.children.push(solid_color(row_bounds))
+                }
+            }
+            CheckboxControl(false) | RadioControl(false) | TextFieldControl | TextAreaControl |
+            ButtonControl => {}
+        }
+
+        clip_display_item
+    }
+
+    /// Returns the rectangle, relative to the content box, that the line of text with the given
+    /// index in a form control is drawn in. Text areas stack their lines from the top; other
+    /// controls center their single line vertically, and buttons center their label horizontally
+    /// too.
+    fn form_control_text_bounds(&self,
+                                form_control_fragment: &FormControlFragmentInfo,
+                                run: &TextRun,
+                                index: uint)
+                                -> Rect<Au> {
+        let content_size = self.content_box().size.to_physical(self.style().writing_mode());
+        let line_height = self.calculate_line_height(self.style().get_font().font_size);
+        let text_height = run.ascent() + run.descent();
+        let line_top = match form_control_fragment.kind {
+            TextAreaControl => line_height.scale_by(index as f64),
+            _ => (content_size.height - line_height).scale_by(0.5),
+        };
+        let advance = run_advance(run);
+        let left = match form_control_fragment.kind {
+            ButtonControl => (content_size.width - advance).scale_by(0.5),
+            _ => Au(0),
+        };
+        Rect(Point2D(left, line_top + (line_height - text_height).scale_by(0.5)),
+             Size2D(advance, text_height))
+    }
+
     /// Sends the size and position of this iframe fragment to the constellation. This is out of
     /// line to guide inlining.
     #[inline(never)]
@@ -1419,10 +2274,14 @@ impl Fragment {
                                             iframe_fragment: &IframeFragmentInfo,
                                             offset: Point2D<Au>,
                                             layout_context: &LayoutContext) {
-        let left = offset.x + self.margin.left + self.border_padding.left;
-        let top = offset.y + self.margin.top + self.border_padding.top;
-        let width = self.content_box().size.width;
-        let height = self.content_box().size.height;
+        let writing_mode = self.style().writing_mode();
+        let margin = self.margin.to_physical(writing_mode);
+        let border_padding = self.border_padding.to_physical(writing_mode);
+        let left = offset.x + margin.left + border_padding.left;
+        let top = offset.y + margin.top + border_padding.top;
+        let content_size = self.content_box().size.to_physical(writing_mode);
+        let width = content_size.width;
+        let height = content_size.height;
         let origin = Point2D(geometry::to_frac_px(left) as f32, geometry::to_frac_px(top) as f32);
         let size = Size2D(geometry::to_frac_px(width) as f32, geometry::to_frac_px(height) as f32);
         let rect = Rect(origin, size);
@@ -1442,8 +2301,10 @@ impl fmt::Show for Fragment {
         try!(write!(f, "({} ",
             match self.specific {
                 GenericFragment => "GenericFragment",
+                FormControlFragment(_) => "FormControlFragment",
                 IframeFragment(_) => "IframeFragment",
                 ImageFragment(_) => "ImageFragment",
+                InlineBlockFragment(_) => "InlineBlockFragment",
                 ScannedTextFragment(_) => "ScannedTextFragment",
                 TableFragment => "TableFragment",
                 TableCellFragment => "TableCellFragment",
@@ -1515,3 +2376,115 @@ impl ChildDisplayListAccumulator {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::{constrain_preserving_ratio, replaced_used_size};
+
+    use model::{Auto, MaybeAuto, Specified};
+
+    use geom::Size2D;
+    use servo_util::geometry::Au;
+
+    fn px(length: int) -> Au {
+        Au::from_px(length)
+    }
+
+    fn size(width: int, height: int) -> Size2D<Au> {
+        Size2D(px(width), px(height))
+    }
+
+    fn length(length: Option<int>) -> MaybeAuto {
+        match length {
+            Some(length) => Specified(px(length)),
+            None => Auto,
+        }
+    }
+
+    /// The rows of the table in CSS 2.1 § 10.4, for an image of 200×100px.
+    #[test]
+    fn test_constrain_preserving_ratio() {
+        // (min-width, min-height, max-width, max-height, used width, used height)
+        let cases = [
+            // No constraint violation.
+            (0, 0, None, None, 200, 100),
+            (200, 100, Some(200), Some(100), 200, 100),
+            // w > max-width.
+            (0, 0, Some(100), None, 100, 50),
+            (0, 80, Some(100), None, 100, 80),
+            // w < min-width.
+            (400, 0, None, None, 400, 200),
+            (400, 0, None, Some(150), 400, 150),
+            // h > max-height.
+            (0, 0, None, Some(50), 100, 50),
+            (150, 0, None, Some(50), 150, 50),
+            // h < min-height.
+            (0, 200, None, None, 400, 200),
+            (0, 200, Some(300), None, 300, 200),
+            // (w > max-width) and (h > max-height), where max-width/w <= max-height/h.
+            (0, 0, Some(100), Some(80), 100, 50),
+            (0, 70, Some(100), Some(80), 100, 70),
+            // (w > max-width) and (h > max-height), where max-width/w > max-height/h.
+            (0, 0, Some(150), Some(25), 50, 25),
+            (60, 0, Some(150), Some(25), 60, 25),
+            // (w < min-width) and (h < min-height), where min-width/w <= min-height/h.
+            (300, 200, None, None, 400, 200),
+            (300, 200, Some(350), None, 350, 200),
+            // (w < min-width) and (h < min-height), where min-width/w > min-height/h.
+            (600, 150, None, None, 600, 300),
+            (600, 150, None, Some(250), 600, 250),
+            // (w < min-width) and (h > max-height).
+            (300, 0, None, Some(50), 300, 50),
+            // (w > max-width) and (h < min-height).
+            (0, 150, Some(100), None, 100, 150),
+        ];
+        for (index, &(min_width, min_height, max_width, max_height, width, height)) in
+                cases.iter().enumerate() {
+            let used_size = constrain_preserving_ratio(size(200, 100),
+                                                       size(min_width, min_height),
+                                                       max_width.map(px),
+                                                       max_height.map(px));
+            assert!(used_size == size(width, height),
+                    "case {}: expected {}, got {}", index, size(width, height), used_size);
+        }
+    }
+
+    #[test]
+    fn test_replaced_used_size() {
+        // (width, height, has intrinsic ratio, min-width, max-width, max-height, used width,
+        // used height), for an image of 200×100px.
+        let cases = [
+            (None, None, true, 0, None, None, 200, 100),
+            (None, None, false, 0, None, None, 200, 100),
+            // One specified dimension determines the other through the ratio.
+            (Some(100), None, true, 0, None, None, 100, 50),
+            (None, Some(50), true, 0, None, None, 100, 50),
+            // Without a ratio, the other dimension is the intrinsic one.
+            (Some(100), None, false, 0, None, None, 100, 100),
+            (None, Some(50), false, 0, None, None, 200, 50),
+            // Both specified dimensions are used as they are.
+            (Some(300), Some(20), true, 0, None, None, 300, 20),
+            // Constraints on the computed dimension apply after the ratio.
+            (Some(100), None, true, 0, None, Some(40), 100, 40),
+            (None, Some(50), true, 150, None, None, 150, 50),
+            // Constraints on the specified dimension apply before the ratio.
+            (Some(300), None, true, 0, Some(100), None, 100, 50),
+            // A maximum smaller than the minimum is raised to it.
+            (None, None, true, 300, Some(100), None, 300, 150),
+            (Some(50), Some(50), true, 300, Some(100), None, 300, 50),
+        ];
+        for (index, &(width, height, has_ratio, min_width, max_width, max_height, used_width,
+                      used_height)) in cases.iter().enumerate() {
+            let used_size = replaced_used_size(length(width),
+                                               length(height),
+                                               size(200, 100),
+                                               has_ratio,
+                                               size(min_width, 0),
+                                               max_width.map(px),
+                                               max_height.map(px));
+            assert!(used_size == size(used_width, used_height),
+                    "case {}: expected {}, got {}",
+                    index, size(used_width, used_height), used_size);
+        }
+    }
+}
@@ -7,31 +7,38 @@ use style::ComputedValues;
 bitflags! {
     #[doc = "Individual layout actions that may be necessary after restyling."]
     flags RestyleDamage: int {
-        #[doc = "Repaint the node itself."]
-        #[doc = "Currently unused; need to decide how this propagates."]
+        #[doc = "Repaint the node itself. Flows without this bit reuse the display list they"]
+        #[doc = "built during the previous layout."]
         static Repaint = 0x01,
 
         #[doc = "Recompute intrinsic widths (minimum and preferred)."]
-        #[doc = "Propagates down the flow tree because the computation is"]
+        #[doc = "Propagates up the flow tree because the computation is"]
         #[doc = "bottom-up."]
         static BubbleWidths = 0x02,
 
         #[doc = "Recompute actual widths and heights."]
-        #[doc = "Propagates up the flow tree because the computation is"]
-        #[doc = "top-down."]
+        #[doc = "Propagates up the flow tree because the parent's height depends on the"]
+        #[doc = "heights of its children. The assign-widths traversal propagates it down to"]
+        #[doc = "those children whose width or position changed."]
         static Reflow = 0x04
     }
 }
 
 impl RestyleDamage {
     /// Elements of self which should also get set on any ancestor flow.
+    ///
+    /// Every bit propagates up: intrinsic widths bubble up, heights depend on the children, and
+    /// the display list of a flow contains those of its children.
     pub fn propagate_up(self) -> RestyleDamage {
-        self & Reflow
+        self
     }
 
     /// Elements of self which should also get set on any child flows.
+    ///
+    /// Nothing propagates down unconditionally; `AssignWidthsTraversal` adds `Reflow` to the
+    /// children whose containing block changed.
     pub fn propagate_down(self) -> RestyleDamage {
-        self & BubbleWidths
+        RestyleDamage::empty()
     }
 }
 
@@ -59,7 +66,8 @@ pub fn compute_damage(old: &ComputedValues, new: &ComputedValues) -> RestyleDama
     add_if_not_equal!(old, new, damage, [ Repaint ],
         [ get_color.color, get_background.background_color,
           get_border.border_top_color, get_border.border_right_color,
-          get_border.border_bottom_color, get_border.border_left_color ]);
+          get_border.border_bottom_color, get_border.border_left_color,
+          get_column.column_rule_color, get_box.object_fit, get_box.object_position ]);
 
     add_if_not_equal!(old, new, damage, [ Repaint, BubbleWidths, Reflow ],
         [ get_border.border_top_width, get_border.border_right_width,
@@ -69,10 +77,23 @@ pub fn compute_damage(old: &ComputedValues, new: &ComputedValues) -> RestyleDama
           get_padding.padding_top, get_padding.padding_right,
           get_padding.padding_bottom, get_padding.padding_left,
           get_box.position, get_box.width, get_box.height, get_box.float, get_box.display,
+          get_box.unicode_bidi, get_inheritedbox.direction,
+          get_inheritedbox.writing_mode, get_inheritedbox.text_orientation,
           get_font.font_family, get_font.font_size, get_font.font_style, get_font.font_weight,
-          get_inheritedtext.text_align, get_text.text_decoration, get_inheritedbox.line_height ]);
+          get_inheritedtext.text_align, get_text.text_decoration, get_inheritedbox.line_height,
+          get_column.column_gap, get_column.column_rule_style, get_column.column_rule_width ]);
 
-    // FIXME: test somehow that we checked every CSS property
+    // Breaks only move content between pages, which doesn't change any intrinsic width.
+    add_if_not_equal!(old, new, damage, [ Repaint, Reflow ],
+        [ get_box.page_break_before, get_box.page_break_after, get_box.page_break_inside,
+          get_inheritedbox.orphans, get_inheritedbox.widows ]);
+
+    // Properties not listed above might still affect layout, so fall back to redoing everything
+    // if any of them changed.
+    if damage.is_empty() && old.differs_from(new) {
+        damage = RestyleDamage::all()
+    }
 
     damage
 }
+
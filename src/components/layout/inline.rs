@@ -9,7 +9,8 @@ use context::LayoutContext;
 use floats::{FloatLeft, Floats, PlacementInfo};
 use flow::{BaseFlow, FlowClass, Flow, InlineFlowClass};
 use flow;
-use fragment::{Fragment, ScannedTextFragment, ScannedTextFragmentInfo, SplitInfo};
+use fragment::{Fragment, InlineBlockFragment, ScannedTextFragment, ScannedTextFragmentInfo};
+use fragment::{SplitInfo};
 use model::IntrinsicWidths;
 use model;
 use text;
@@ -17,11 +18,13 @@ use wrapper::ThreadSafeLayoutNode;
 
 use collections::{Deque, RingBuf};
 use geom::{Point2D, Rect, SideOffsets2D, Size2D};
-use gfx::display_list::ContentLevel;
+use gfx::display_list::{ContentLevel, DisplayList};
 use gfx::font::FontMetrics;
 use gfx::font_context::FontContext;
+use gfx::text::bidi;
 use gfx::text::glyph::CharIndex;
-use servo_util::geometry::Au;
+use text_util = gfx::text::util;
+use servo_util::geometry::{Au, LogicalPoint, LogicalRect, LogicalSize};
 use servo_util::geometry;
 use servo_util::range;
 use servo_util::range::{EachIndex, Range, RangeIndex, IntRangeIndex};
@@ -31,7 +34,8 @@ use std::mem;
 use std::num;
 use std::slice::{Items, MutItems};
 use std::u16;
-use style::computed_values::{text_align, vertical_align, white_space};
+use style::computed_values::{direction, text_align, text_orientation, unicode_bidi};
+use style::computed_values::{vertical_align, white_space};
 use style::ComputedValues;
 use sync::Arc;
 
@@ -60,6 +64,10 @@ use sync::Arc;
 /// with a float or a horizontal wall of the containing block. The top
 /// left corner of the green zone is the same as that of the line, but
 /// the green zone can be taller and wider than the line itself.
+///
+/// Like the floats they are placed against, the bounds and green zone are in flow-relative
+/// coordinates: `x` and `width` run along the inline axis, and `y` and `height` along the block
+/// axis, whatever the writing mode.
 pub struct Line {
     /// A range of line indices that describe line breaks.
     ///
@@ -257,6 +265,36 @@ pub fn each_char_index(range: &Range<LineIndices>) -> EachIndex<int, CharIndex>
     range::each_index(range.begin().char_index, range.end().char_index)
 }
 
+/// Returns the number of places where the text of the given fragment may be widened for
+/// `text-align: justify`. Preformatted text is never justified, since all of its line breaks are
+/// forced.
+fn expansion_opportunities(fragment: &Fragment) -> uint {
+    match fragment.specific {
+        ScannedTextFragment(ref text_fragment_info)
+                if fragment.white_space() != white_space::pre => {
+            text_fragment_info.run.expansion_opportunities_for_range(&text_fragment_info.range)
+        }
+        _ => 0,
+    }
+}
+
+/// Takes the space that `InlineFlow::justify_line` added to the given fragment in a previous
+/// layout back out of its width, so that lines are broken by the natural widths of the fragments
+/// and lines that are no longer justified don't keep the extra space.
+fn remove_extra_word_spacing(fragment: &mut Fragment) {
+    match fragment.specific {
+        ScannedTextFragment(ref mut text_fragment_info) => {
+            let count = text_fragment_info.run
+                                          .expansion_opportunities_for_range(
+                                              &text_fragment_info.range);
+            fragment.border_box.size.inline = fragment.border_box.size.inline -
+                text_fragment_info.extra_word_spacing * Au::new(count as i32);
+            text_fragment_info.extra_word_spacing = Au(0)
+        }
+        _ => {}
+    }
+}
+
 struct LineBreaker {
     pub floats: Floats,
     pub new_fragments: Vec<Fragment>,
@@ -315,7 +353,9 @@ impl LineBreaker {
                         Some(fragment) => {
                             debug!("LineBreaker: Working with fragment from flow: b{}",
                                    fragment.debug_id());
-                            (*fragment).clone()
+                            let mut fragment = (*fragment).clone();
+                            remove_extra_word_spacing(&mut fragment);
+                            fragment
                         }
                     }
                 } else {
@@ -365,7 +405,7 @@ impl LineBreaker {
     // FIXME(eatkinson): this assumes that the tallest fragment in the line determines the line height
     // This might not be the case with some weird text fonts.
     fn new_height_for_line(&self, new_fragment: &Fragment) -> Au {
-        let fragment_height = new_fragment.content_height();
+        let fragment_height = new_fragment.content_block_size();
         if fragment_height > self.pending_line.bounds.size.height {
             fragment_height
         } else {
@@ -396,7 +436,7 @@ impl LineBreaker {
         let info = PlacementInfo {
             size: Size2D(placement_width, first_fragment_size.height),
             ceiling: ceiling,
-            max_width: flow.base.position.size.width,
+            max_width: flow.base.position.size.inline,
             kind: FloatLeft,
         };
 
@@ -485,7 +525,7 @@ impl LineBreaker {
             let split_fragment = |split: SplitInfo| {
                 let info = ScannedTextFragmentInfo::new(run.clone(), split.range);
                 let specific = ScannedTextFragment(info);
-                let size = Size2D(split.width, in_fragment.border_box.size.height);
+                let size = LogicalSize::new(split.width, in_fragment.border_box.size.block);
                 in_fragment.transform(size, specific)
             };
 
@@ -539,7 +579,7 @@ impl LineBreaker {
         // horizontally. We'll try to place the whole fragment on this line and break somewhere if it
         // doesn't fit.
 
-        let new_width = self.pending_line.bounds.size.width + in_fragment.border_box.size.width;
+        let new_width = self.pending_line.bounds.size.width + in_fragment.border_box.size.inline;
         if new_width <= green_zone.width {
             debug!("LineBreaker: case=fragment fits without splitting");
             self.push_fragment_to_line(in_fragment);
@@ -562,9 +602,10 @@ impl LineBreaker {
         match split.map(|(left, right, run)| {
             // TODO(bjz): Remove fragment splitting
             let split_fragment = |split: SplitInfo| {
-                let info = ScannedTextFragmentInfo::new(run.clone(), split.range);
+                let mut info = ScannedTextFragmentInfo::new(run.clone(), split.range);
+                info.hyphenated = split.hyphenated;
                 let specific = ScannedTextFragment(info);
-                let size = Size2D(split.width, in_fragment.border_box.size.height);
+                let size = LogicalSize::new(split.width, in_fragment.border_box.size.block);
                 in_fragment.transform(size, specific)
             };
 
@@ -620,9 +661,9 @@ impl LineBreaker {
             char_index: CharIndex(0) /* unused for now */ ,
         });
         self.pending_line.bounds.size.width = self.pending_line.bounds.size.width +
-            fragment.border_box.size.width;
+            fragment.border_box.size.inline;
         self.pending_line.bounds.size.height = Au::max(self.pending_line.bounds.size.height,
-                                                       fragment.border_box.size.height);
+                                                       fragment.border_box.size.block);
         self.new_fragments.push(fragment);
     }
 }
@@ -908,6 +949,18 @@ pub struct InlineFlow {
     /// The minimum depth below the baseline for each line, as specified by the line height and
     /// font style.
     pub minimum_depth_below_baseline: Au,
+
+    /// The distance from the top of this flow to the baseline of its last line, if it has any
+    /// lines. This is used to find the baselines of inline blocks.
+    pub baseline_offset_of_last_line: Option<Au>,
+
+    /// The distance from the top of this flow to the baseline of its first line, if it has any
+    /// lines. This is used to align the baselines of table cells.
+    pub baseline_offset_of_first_line: Option<Au>,
+
+    /// The bidirectional embedding level of the paragraph, per UAX #9: 1 if the containing block
+    /// is `direction: rtl` and 0 otherwise.
+    pub paragraph_level: u8,
 }
 
 impl InlineFlow {
@@ -918,11 +971,19 @@ impl InlineFlow {
             lines: Vec::new(),
             minimum_height_above_baseline: Au(0),
             minimum_depth_below_baseline: Au(0),
+            baseline_offset_of_last_line: None,
+            baseline_offset_of_first_line: None,
+            paragraph_level: 0,
         }
     }
 
     pub fn build_display_list_inline(&mut self, layout_context: &LayoutContext) {
-        let abs_rect = Rect(self.base.abs_position, self.base.position.size);
+        let writing_mode = self.base.writing_mode;
+        let flow_size = self.base.position.size.to_physical(writing_mode);
+
+        // Test the overflow area rather than the flow itself, as fragmentation may have moved
+        // lines outside of it.
+        let abs_rect = self.base.overflow.translate(&self.base.abs_position);
         if !abs_rect.intersects(&layout_context.dirty) {
             return
         }
@@ -932,6 +993,19 @@ impl InlineFlow {
         debug!("Flow: building display list for {:u} inline fragments", self.fragments.len());
 
         for (fragment, context) in self.fragments.mut_iter() {
+            match fragment.specific {
+                InlineBlockFragment(ref mut info) => {
+                    // The block flow has already built its display list. Splice it in here so
+                    // that the inline block paints in order with the content around it.
+                    let block_flow = info.flow_ref.get_mut();
+                    let display_list = mem::replace(&mut flow::mut_base(block_flow).display_list,
+                                                    DisplayList::new());
+                    self.base.display_list.push_all_move(display_list);
+                    continue
+                }
+                _ => {}
+            }
+
             let rel_offset = fragment.relative_position(&self.base
                                                              .absolute_position_info
                                                              .relative_containing_block_size,
@@ -939,14 +1013,10 @@ impl InlineFlow {
             drop(fragment.build_display_list(&mut self.base.display_list,
                                              layout_context,
                                              self.base.abs_position + rel_offset,
+                                             flow_size,
                                              ContentLevel,
                                              Some(context)));
         }
-
-        // TODO(#225): Should `inline-block` elements have flows as children of the inline flow or
-        // should the flow be nested inside the fragment somehow?
-
-        // For now, don't traverse the subtree rooted here.
     }
 
     /// Returns the distance from the baseline for the logical top left corner of this fragment,
@@ -969,7 +1039,7 @@ impl InlineFlow {
             vertical_align::middle => {
                 // TODO: x-height value should be used from font info.
                 let xheight = Au(0);
-                let fragment_height = fragment.content_height();
+                let fragment_height = fragment.content_block_size();
                 let offset_top = -(xheight + fragment_height).scale_by(0.5);
                 *height_above_baseline = offset_top.scale_by(-1.0);
                 *depth_below_baseline = fragment_height - *height_above_baseline;
@@ -1028,27 +1098,77 @@ impl InlineFlow {
     /// Sets fragment X positions based on alignment for one line.
     fn set_horizontal_fragment_positions(fragments: &mut InlineFragments,
                                          line: &Line,
-                                         line_align: text_align::T) {
+                                         line_align: text_align::T,
+                                         paragraph_level: u8,
+                                         is_last_line: bool) {
         // Figure out how much width we have.
         let slack_width = Au::max(Au(0), line.green_zone.width - line.bounds.size.width);
 
+        // `start` and `end` depend on the direction of the paragraph. Justified lines are widened
+        // to fill the available width instead, except for the last one, which is aligned like
+        // `start`.
+        let line_align = match (line_align, bidi::is_right_to_left(paragraph_level)) {
+            (text_align::justify, _) if !is_last_line => text_align::justify,
+            (text_align::start, false) | (text_align::end, true) |
+            (text_align::justify, false) => text_align::left,
+            (text_align::start, true) | (text_align::end, false) |
+            (text_align::justify, true) => text_align::right,
+            (line_align, _) => line_align,
+        };
+
         // Set the fragment x positions based on that alignment.
         let mut offset_x = line.bounds.origin.x;
         offset_x = offset_x + match line_align {
-            // So sorry, but justified text is more complicated than shuffling line
-            // coordinates.
-            //
-            // TODO(burg, issue #213): Implement `text-align: justify`.
-            text_align::left | text_align::justify => Au(0),
+            text_align::justify => {
+                InlineFlow::justify_line(fragments, line, slack_width);
+                Au(0)
+            }
             text_align::center => slack_width.scale_by(0.5),
             text_align::right => slack_width,
+            _ => Au(0),
         };
 
+        // Place the fragments in visual order, per UAX #9 rule L2.
+        let levels: Vec<u8> = each_fragment_index(&line.range).map(|i| {
+            fragments.get(i.to_uint()).bidi_level
+        }).collect();
+        let first_fragment_index = line.range.begin().fragment_index.to_uint();
+        for visual_index in bidi::visual_order(levels.as_slice()).move_iter() {
+            let fragment = fragments.get_mut(first_fragment_index + visual_index);
+            let size = fragment.border_box.size;
+            fragment.border_box = LogicalRect {
+                start: LogicalPoint::new(offset_x, fragment.border_box.start.b),
+                size: size,
+            };
+            offset_x = offset_x + size.inline;
+        }
+    }
+
+    /// Widens the text fragments of the given line by `slack_width` in total, spreading it evenly
+    /// over the spaces between words, per CSS 2.1 § 16.2. Lines without such spaces are left
+    /// alone.
+    fn justify_line(fragments: &mut InlineFragments, line: &Line, slack_width: Au) {
+        let mut expansion_opportunity_count = 0;
+        for i in each_fragment_index(&line.range) {
+            expansion_opportunity_count += expansion_opportunities(fragments.get(i.to_uint()))
+        }
+        if expansion_opportunity_count == 0 {
+            return
+        }
+
+        let extra_word_spacing = slack_width / Au::new(expansion_opportunity_count as i32);
         for i in each_fragment_index(&line.range) {
             let fragment = fragments.get_mut(i.to_uint());
-            let size = fragment.border_box.size;
-            fragment.border_box = Rect(Point2D(offset_x, fragment.border_box.origin.y), size);
-            offset_x = offset_x + size.width;
+            let count = Au::new(expansion_opportunities(fragment) as i32);
+            match fragment.specific {
+                ScannedTextFragment(ref mut text_fragment_info) => {
+                    // The line breaker removed any spacing added by a previous layout.
+                    fragment.border_box.size.inline = fragment.border_box.size.inline +
+                        extra_word_spacing * count;
+                    text_fragment_info.extra_word_spacing = extra_word_spacing
+                }
+                _ => {}
+            }
         }
     }
 
@@ -1065,6 +1185,196 @@ impl InlineFlow {
         let inline_metrics = InlineMetrics::from_font_metrics(&font_metrics, line_height);
         (inline_metrics.height_above_baseline, inline_metrics.depth_below_baseline)
     }
+
+    /// Resolves the bidirectional embedding level of each fragment per UAX #9, splitting text
+    /// fragments wherever the level changes. This is done during flow construction, after text
+    /// runs have been scanned.
+    ///
+    /// `style` is the style of the block, which determines the direction of the paragraph.
+    pub fn resolve_bidi_levels(&mut self, style: &ComputedValues) {
+        let paragraph_is_rtl = style.get_inheritedbox().direction == direction::rtl;
+        self.paragraph_level = if paragraph_is_rtl { 1 } else { 0 };
+        let paragraph_override = match style.get_box().unicode_bidi {
+            unicode_bidi::bidi_override => Some(strong_bidi_class(paragraph_is_rtl)),
+            unicode_bidi::normal | unicode_bidi::embed => None,
+        };
+
+        // Find the type and explicit embedding level of each character, per rules X1 through X8.
+        // Fragments other than text are treated like U+FFFC OBJECT REPLACEMENT CHARACTER.
+        let mut classes = vec![];
+        let mut levels = vec![];
+        for (fragment, context) in self.fragments.iter() {
+            let (mut level, mut directional_override) = (self.paragraph_level, paragraph_override);
+            for range in context.ranges() {
+                let is_rtl = range.style.get_inheritedbox().direction == direction::rtl;
+                match range.style.get_box().unicode_bidi {
+                    unicode_bidi::normal => {}
+                    unicode_bidi::embed => {
+                        level = bidi::next_embedding_level(level, is_rtl);
+                        directional_override = None
+                    }
+                    unicode_bidi::bidi_override => {
+                        level = bidi::next_embedding_level(level, is_rtl);
+                        directional_override = Some(strong_bidi_class(is_rtl))
+                    }
+                }
+            }
+
+            match fragment.specific {
+                ScannedTextFragment(ref info) => {
+                    let chars = info.run.text.as_slice().chars()
+                                                        .skip(info.range.begin().to_uint())
+                                                        .take(info.range.length().to_uint());
+                    for ch in chars {
+                        classes.push(directional_override.unwrap_or(bidi::bidi_class(ch)));
+                        levels.push(level)
+                    }
+                }
+                _ => {
+                    classes.push(directional_override.unwrap_or(bidi::OtherNeutral));
+                    levels.push(level)
+                }
+            }
+        }
+
+        bidi::resolve_levels(classes.as_slice(), levels.as_mut_slice(), self.paragraph_level);
+
+        // Assign the resolved levels to the fragments, splitting text fragments as necessary.
+        let mut new_fragments = vec![];
+        let mut split = false;
+        let mut char_index = 0;
+        for fragment in self.fragments.fragments.iter() {
+            let info = match fragment.specific {
+                ScannedTextFragment(ref info) => info,
+                _ => {
+                    let mut new_fragment = fragment.clone();
+                    new_fragment.bidi_level = *levels.get(char_index);
+                    new_fragments.push(new_fragment);
+                    char_index += 1;
+                    continue
+                }
+            };
+
+            let (fragment_start, fragment_end) =
+                (char_index, char_index + info.range.length().to_uint());
+            while char_index < fragment_end {
+                let level = *levels.get(char_index);
+                let piece_start = char_index;
+                while char_index < fragment_end && *levels.get(char_index) == level {
+                    char_index += 1
+                }
+
+                let mut new_fragment = if piece_start == fragment_start &&
+                        char_index == fragment_end {
+                    fragment.clone()
+                } else {
+                    split = true;
+                    text_fragment_piece(fragment, info, piece_start - fragment_start,
+                                        char_index - fragment_start)
+                };
+                new_fragment.bidi_level = level;
+                new_fragments.push(new_fragment)
+            }
+        }
+
+        if split {
+            self.fragments.fixup(new_fragments)
+        } else {
+            self.fragments.fragments = new_fragments
+        }
+    }
+
+    /// Splits the text fragments that have `text-orientation: mixed` in a vertical writing mode
+    /// wherever the glyphs change between upright and sideways, so that each fragment is drawn in
+    /// one orientation. This is done during flow construction, after bidi resolution.
+    pub fn split_text_by_orientation(&mut self) {
+        let mut new_fragments = vec![];
+        let mut split = false;
+        for fragment in self.fragments.fragments.iter() {
+            let info = match fragment.specific {
+                ScannedTextFragment(ref info) if fragment.style().writing_mode().is_vertical() &&
+                        fragment.style().get_inheritedbox().text_orientation ==
+                            text_orientation::mixed => info,
+                _ => {
+                    new_fragments.push(fragment.clone());
+                    continue
+                }
+            };
+
+            let uprights: Vec<bool> =
+                info.run.text.as_slice().chars().skip(info.range.begin().to_uint())
+                                                .take(info.range.length().to_uint())
+                                                .map(text_util::is_upright_in_vertical_text)
+                                                .collect();
+            let mut piece_start = 0;
+            while piece_start < uprights.len() {
+                let upright = *uprights.get(piece_start);
+                let mut piece_end = piece_start + 1;
+                while piece_end < uprights.len() && *uprights.get(piece_end) == upright {
+                    piece_end += 1
+                }
+                if piece_start == 0 && piece_end == uprights.len() {
+                    new_fragments.push(fragment.clone())
+                } else {
+                    split = true;
+                    new_fragments.push(text_fragment_piece(fragment, info, piece_start,
+                                                           piece_end))
+                }
+                piece_start = piece_end
+            }
+            if uprights.is_empty() {
+                new_fragments.push(fragment.clone())
+            }
+        }
+
+        if split {
+            self.fragments.fixup(new_fragments)
+        }
+    }
+}
+
+/// Returns the part of the given text fragment from character `start` to character `end`,
+/// counted from the start of the fragment.
+fn text_fragment_piece(fragment: &Fragment, info: &ScannedTextFragmentInfo, start: uint,
+                       end: uint) -> Fragment {
+    let offset = CharIndex(start as int);
+    let length = CharIndex((end - start) as int);
+    let range = Range::new(info.range.begin() + offset, length);
+    let metrics = info.run.metrics_for_range(&range);
+    let new_info = ScannedTextFragmentInfo::new(info.run.clone(), range);
+    let mut new_fragment = fragment.transform(LogicalSize::new(metrics.bounding_box.size.width,
+                                                               metrics.bounding_box.size.height),
+                                              ScannedTextFragment(new_info));
+    new_fragment.new_line_pos = split_new_line_positions(fragment.new_line_pos.as_slice(),
+                                                         offset, offset + length);
+    new_fragment
+}
+
+/// Returns the strong bidirectional type that a directional override of the given direction
+/// assigns to characters.
+fn strong_bidi_class(is_rtl: bool) -> bidi::BidiClass {
+    if is_rtl {
+        bidi::RightToLeft
+    } else {
+        bidi::LeftToRight
+    }
+}
+
+/// Returns the new-line positions of the part of a text fragment from `start` to `end`, given the
+/// new-line positions of the whole fragment. Each position is relative to the character after the
+/// previous new-line, or to the start of the fragment for the first one.
+fn split_new_line_positions(new_line_pos: &[CharIndex], start: CharIndex, end: CharIndex)
+                            -> Vec<CharIndex> {
+    let mut result = vec![];
+    let (mut position, mut previous) = (CharIndex(-1), start - CharIndex(1));
+    for &relative_position in new_line_pos.iter() {
+        position = position + CharIndex(1) + relative_position;
+        if position >= start && position < end {
+            result.push(position - previous - CharIndex(1));
+            previous = position
+        }
+    }
+    result
 }
 
 impl Flow for InlineFlow {
@@ -1081,6 +1391,8 @@ impl Flow for InlineFlow {
     }
 
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
+        // The children of this flow are the block flows of inline blocks. They establish new
+        // block formatting contexts.
         for kid in self.base.child_iter() {
             flow::mut_base(kid).floats = Floats::new();
         }
@@ -1108,8 +1420,8 @@ impl Flow for InlineFlow {
 
         debug!("InlineFlow::assign_widths: floats in: {:?}", self.base.floats);
 
+        let width = self.base.position.size.inline;
         {
-            let width = self.base.position.size.width;
             let this = &mut *self;
             for (fragment, context) in this.fragments.mut_iter() {
                 fragment.assign_replaced_width_if_necessary(width,
@@ -1117,16 +1429,15 @@ impl Flow for InlineFlow {
             }
         }
 
-        assert!(self.base.children.len() == 0,
-                "InlineFlow: should not have children flows in the current layout implementation.");
-
-        // There are no child contexts, so stop here.
-
-        // TODO(Issue #225): once there are 'inline-block' elements, this won't be
-        // true.  In that case, set the InlineBlockFragment's width to the
-        // shrink-to-fit width, perform inline flow, and set the block
-        // flow context's width as the assigned width of the
-        // 'inline-block' fragment that created this flow before recursing.
+        // The only children of inline flows are the block flows of inline blocks, which share our
+        // containing block. Their widths are shrink-to-fit, so the widths of their fragments are
+        // only known once they have been laid out.
+        for kid in self.base.child_iter() {
+            let kid_base = flow::mut_base(kid);
+            kid_base.position.size.inline = width;
+            kid_base.flags.set_impacted_by_left_floats(false);
+            kid_base.flags.set_impacted_by_right_floats(false);
+        }
     }
 
     /// Calculate and set the height of this flow. See CSS 2.1 § 10.6.1.
@@ -1145,6 +1456,8 @@ impl Flow for InlineFlow {
         debug!("assign_height_inline: floats in: {:?}", self.base.floats);
 
         // assign height for inline fragments
+        self.baseline_offset_of_last_line = None;
+        self.baseline_offset_of_first_line = None;
         for (fragment, _) in self.fragments.mut_iter() {
             fragment.assign_replaced_height_if_necessary();
         }
@@ -1158,9 +1471,14 @@ impl Flow for InlineFlow {
 
         // Now, go through each line and lay out the fragments inside.
         let mut line_distance_from_flow_top = Au(0);
-        for line in self.lines.mut_iter() {
+        let line_count = self.lines.len();
+        for (line_index, line) in self.lines.mut_iter().enumerate() {
             // Lay out fragments horizontally.
-            InlineFlow::set_horizontal_fragment_positions(&mut self.fragments, line, text_align);
+            InlineFlow::set_horizontal_fragment_positions(&mut self.fragments,
+                                                          line,
+                                                          text_align,
+                                                          self.paragraph_level,
+                                                          line_index + 1 == line_count);
 
             // Set the top y position of the current line.
             // `line_height_offset` is updated at the end of the previous loop.
@@ -1228,9 +1546,9 @@ impl Flow for InlineFlow {
                                                            largest_depth_below_baseline);
                 }
 
-                // Temporarily use `fragment.border_box.origin.y` to mean "the distance from the
+                // Temporarily use `fragment.border_box.start.b` to mean "the distance from the
                 // baseline". We will assign the real value later.
-                fragment.border_box.origin.y = distance_from_baseline
+                fragment.border_box.start.b = distance_from_baseline
             }
 
             // Calculate the distance from the baseline to the top of the largest fragment with a
@@ -1250,21 +1568,21 @@ impl Flow for InlineFlow {
             let baseline_distance_from_top = largest_height_above_baseline;
 
             // Compute the final positions in the block direction of each fragment. Recall that
-            // `fragment.border_box.origin.y` was set to the distance from the baseline above.
+            // `fragment.border_box.start.b` was set to the distance from the baseline above.
             for fragment_i in each_fragment_index(&line.range) {
                 let fragment = self.fragments.get_mut(fragment_i.to_uint());
                 match fragment.vertical_align() {
                     vertical_align::top => {
-                        fragment.border_box.origin.y = fragment.border_box.origin.y +
+                        fragment.border_box.start.b = fragment.border_box.start.b +
                             line_distance_from_flow_top
                     }
                     vertical_align::bottom => {
-                        fragment.border_box.origin.y = fragment.border_box.origin.y +
+                        fragment.border_box.start.b = fragment.border_box.start.b +
                             line_distance_from_flow_top + baseline_distance_from_top +
                             largest_depth_below_baseline
                     }
                     _ => {
-                        fragment.border_box.origin.y = fragment.border_box.origin.y +
+                        fragment.border_box.start.b = fragment.border_box.start.b +
                             line_distance_from_flow_top + baseline_distance_from_top
                     }
                 }
@@ -1272,10 +1590,29 @@ impl Flow for InlineFlow {
 
             // This is used to set the top y position of the next line in the next loop.
             line.bounds.size.height = largest_height_above_baseline + largest_depth_below_baseline;
+            self.baseline_offset_of_last_line =
+                Some(line_distance_from_flow_top + baseline_distance_from_top);
+            if self.baseline_offset_of_first_line.is_none() {
+                self.baseline_offset_of_first_line = self.baseline_offset_of_last_line
+            }
             line_distance_from_flow_top = line_distance_from_flow_top + line.bounds.size.height;
         } // End of `lines.each` loop.
 
-        self.base.position.size.height =
+        // Move the block flows of inline blocks to where their fragments ended up.
+        for (fragment, _) in self.fragments.mut_iter() {
+            match fragment.specific {
+                InlineBlockFragment(ref mut info) => {
+                    let block_flow = info.flow_ref.get_mut().as_block();
+                    block_flow.base.position.start =
+                        LogicalPoint::new(fragment.border_box.start.i,
+                                          fragment.border_box.start.b +
+                                            block_flow.fragment.margin.block_start);
+                }
+                _ => {}
+            }
+        }
+
+        self.base.position.size.block =
             if self.lines.len() > 0 {
                 self.lines.as_slice().last().get_ref().bounds.origin.y +
                     self.lines.as_slice().last().get_ref().bounds.size.height
@@ -1284,7 +1621,27 @@ impl Flow for InlineFlow {
             };
 
         self.base.floats = scanner.floats();
-        self.base.floats.translate(Point2D(Au::new(0), -self.base.position.size.height));
+        self.base.floats.translate(Point2D(Au::new(0), -self.base.position.size.block));
+    }
+
+    fn compute_absolute_position(&mut self) {
+        // Inline blocks are positioned relative to this flow, just like its fragments.
+        let this_position = self.base.abs_position;
+        let writing_mode = self.base.writing_mode;
+        let this_size = self.base.position.size.to_physical(writing_mode);
+        let absolute_position_info = self.base.absolute_position_info;
+        for kid in self.base.child_iter() {
+            let kid_base = flow::mut_base(kid);
+            kid_base.abs_position =
+                this_position + kid_base.position.to_physical(writing_mode, this_size).origin;
+            kid_base.absolute_position_info = absolute_position_info
+        }
+    }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        for fragment in self.fragments.fragments.iter() {
+            iterator(fragment)
+        }
     }
 }
 
@@ -31,20 +31,24 @@ extern crate servo_util = "util";
 extern crate collections;
 extern crate green;
 extern crate libc;
+extern crate serialize;
 extern crate sync;
 extern crate url;
 
 pub mod block;
 pub mod construct;
 pub mod context;
+pub mod flex;
 pub mod floats;
 pub mod flow;
 pub mod flow_list;
 pub mod flow_ref;
 pub mod fragment;
+pub mod layout_dump;
 pub mod layout_task;
 pub mod inline;
 pub mod model;
+pub mod multicol;
 pub mod parallel;
 pub mod table_wrapper;
 pub mod table;
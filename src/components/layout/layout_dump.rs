@@ -0,0 +1,145 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A stable JSON serialization of the flow tree and its fragments, for tests that compare layout
+//! results against stored expectations (`--dump-layout-tree`).
+//!
+//! Every flow is an object with its `class`, its `position` relative to its parent flow, the
+//! `fragments` that it lays out itself and its `children`. Every fragment has a `kind`, a
+//! `border_box` relative to its flow, its `margin` and, for text, the `text` that it contains.
+//! All lengths are in CSS pixels and all rectangles are physical, so the output doesn't depend on
+//! how the flows happen to be implemented.
+
+use flow::{BlockFlowClass, FlexFlowClass, Flow, FlowClass, InlineFlowClass, MulticolFlowClass};
+use flow::{TableCaptionFlowClass, TableCellFlowClass, TableColGroupFlowClass, TableFlowClass};
+use flow::{TableRowFlowClass, TableRowGroupFlowClass, TableWrapperFlowClass};
+use flow;
+use fragment::{Fragment, FormControlFragment, GenericFragment, IframeFragment, ImageFragment};
+use fragment::{InlineBlockFragment};
+use fragment::{ScannedTextFragment, TableCellFragment, TableColumnFragment, TableFragment};
+use fragment::{TableRowFragment, TableWrapperFragment, UnscannedTextFragment};
+
+use collections::TreeMap;
+use geom::rect::Rect;
+use geom::side_offsets::SideOffsets2D;
+use geom::size::Size2D;
+use serialize::json::{Json, ToJson};
+use servo_util::geometry::Au;
+use servo_util::geometry;
+use std::io::{File, IoResult};
+
+/// Writes the flow tree rooted at `root` to the file at `path`.
+pub fn write_flow_tree(root: &Flow, path: &Path) -> IoResult<()> {
+    let mut file = try!(File::create(path));
+    try!(file.write_str(flow_tree_to_json(root).to_pretty_str().as_slice()));
+    file.write_str("\n")
+}
+
+/// Serializes the flow tree rooted at `root`.
+pub fn flow_tree_to_json(root: &Flow) -> Json {
+    let base = flow::base(root);
+    flow_to_json(root, base.position.size.to_physical(base.writing_mode))
+}
+
+/// Serializes `flow` and its descendants. `container_size` is the size of the parent flow, which
+/// is needed to find the physical position of `flow` in vertical-rl writing modes.
+fn flow_to_json(flow: &Flow, container_size: Size2D<Au>) -> Json {
+    let base = flow::base(flow);
+    let writing_mode = base.writing_mode;
+    let size = base.position.size.to_physical(writing_mode);
+
+    let mut fragments = vec!();
+    flow.iterate_through_fragments(|fragment| fragments.push(fragment_to_json(fragment, size)));
+
+    let children: Vec<Json> = flow::imm_child_iter(flow).map(|kid| {
+        flow_to_json(kid, size)
+    }).collect();
+
+    let mut object = TreeMap::new();
+    object.insert("class".to_string(), flow_class_name(flow.class()).to_string().to_json());
+    object.insert("position".to_string(),
+                  rect_to_json(&base.position.to_physical(writing_mode, container_size)));
+    object.insert("fragments".to_string(), fragments.to_json());
+    object.insert("children".to_string(), children.to_json());
+    object.to_json()
+}
+
+/// Serializes `fragment`, which belongs to a flow of the given physical size.
+fn fragment_to_json(fragment: &Fragment, flow_size: Size2D<Au>) -> Json {
+    let writing_mode = fragment.style().writing_mode();
+
+    let mut object = TreeMap::new();
+    object.insert("kind".to_string(), fragment_kind_name(fragment).to_string().to_json());
+    object.insert("border_box".to_string(),
+                  rect_to_json(&fragment.border_box.to_physical(writing_mode, flow_size)));
+    object.insert("margin".to_string(),
+                  side_offsets_to_json(&fragment.margin.to_physical(writing_mode)));
+    match fragment.specific {
+        ScannedTextFragment(ref info) => {
+            let text = info.run.text.as_slice().slice_chars(info.range.begin().to_uint(),
+                                                            info.range.end().to_uint());
+            object.insert("text".to_string(), text.to_string().to_json());
+        }
+        UnscannedTextFragment(ref info) => {
+            object.insert("text".to_string(), info.text.to_json());
+        }
+        _ => {}
+    }
+    object.to_json()
+}
+
+fn flow_class_name(class: FlowClass) -> &'static str {
+    match class {
+        BlockFlowClass => "block",
+        InlineFlowClass => "inline",
+        FlexFlowClass => "flex",
+        MulticolFlowClass => "multicol",
+        TableWrapperFlowClass => "table-wrapper",
+        TableFlowClass => "table",
+        TableColGroupFlowClass => "table-colgroup",
+        TableRowGroupFlowClass => "table-rowgroup",
+        TableRowFlowClass => "table-row",
+        TableCaptionFlowClass => "table-caption",
+        TableCellFlowClass => "table-cell",
+    }
+}
+
+fn fragment_kind_name(fragment: &Fragment) -> &'static str {
+    match fragment.specific {
+        GenericFragment => "generic",
+        FormControlFragment(_) => "form-control",
+        ImageFragment(_) => "image",
+        IframeFragment(_) => "iframe",
+        InlineBlockFragment(_) => "inline-block",
+        ScannedTextFragment(_) => "text",
+        TableFragment => "table",
+        TableCellFragment => "table-cell",
+        TableColumnFragment(_) => "table-column",
+        TableRowFragment => "table-row",
+        TableWrapperFragment => "table-wrapper",
+        UnscannedTextFragment(_) => "unscanned-text",
+    }
+}
+
+fn length_to_json(length: Au) -> Json {
+    geometry::to_frac_px(length).to_json()
+}
+
+fn rect_to_json(rect: &Rect<Au>) -> Json {
+    let mut object = TreeMap::new();
+    object.insert("x".to_string(), length_to_json(rect.origin.x));
+    object.insert("y".to_string(), length_to_json(rect.origin.y));
+    object.insert("width".to_string(), length_to_json(rect.size.width));
+    object.insert("height".to_string(), length_to_json(rect.size.height));
+    object.to_json()
+}
+
+fn side_offsets_to_json(offsets: &SideOffsets2D<Au>) -> Json {
+    let mut object = TreeMap::new();
+    object.insert("top".to_string(), length_to_json(offsets.top));
+    object.insert("right".to_string(), length_to_json(offsets.right));
+    object.insert("bottom".to_string(), length_to_json(offsets.bottom));
+    object.insert("left".to_string(), length_to_json(offsets.left));
+    object.to_json()
+}
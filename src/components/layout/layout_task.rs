@@ -9,13 +9,17 @@ use css::matching::{ApplicableDeclarations, ApplicableDeclarationsCache, MatchMe
 use css::matching::{StyleSharingCandidateCache};
 use css::select::new_stylist;
 use css::node_style::StyledNode;
+use block::page_breaks;
 use construct::{FlowConstructionResult, NoConstructionResult};
 use context::LayoutContext;
-use flow::{Flow, ImmutableFlowUtils, MutableFlowUtils, MutableOwnedFlowUtils};
+use flow::{BlockFlowClass, Flow, ImmutableFlowUtils, MutableFlowUtils, MutableOwnedFlowUtils};
 use flow::{PreorderFlowTraversal, PostorderFlowTraversal};
 use flow;
 use flow_ref::FlowRef;
-use incremental::RestyleDamage;
+use incremental::{BubbleWidths, RestyleDamage};
+use incremental;
+use layout_dump;
+use model::{border_from_style, specified};
 use parallel::UnsafeFlow;
 use parallel;
 use util::{LayoutDataAccess, LayoutDataWrapper, OpaqueNodeMethods, ToGfxColor};
@@ -28,24 +32,32 @@ use geom::size::Size2D;
 use gfx::display_list::{ClipDisplayItemClass, ContentStackingLevel, DisplayItem};
 use gfx::display_list::{DisplayItemIterator, DisplayList, OpaqueNode};
 use gfx::font_context::{FontContext, FontContextInfo};
-use gfx::render_task::{RenderMsg, RenderChan, RenderLayer};
+use gfx::render_task::{RenderMsg, RenderChan, RenderLayer, SetPagesMsg};
 use gfx::{render_task, color};
 use script::dom::bindings::js::JS;
 use script::dom::event::ReflowEvent;
 use script::dom::node::{ElementNodeTypeId, LayoutDataRef, Node};
-use script::dom::element::{HTMLBodyElementTypeId, HTMLHtmlElementTypeId};
-use script::layout_interface::{AddStylesheetMsg, ContentBoxQuery};
+use script::dom::element::{HTMLBodyElementTypeId, HTMLHtmlElementTypeId, HTMLTableElementTypeId};
+use script::dom::element::{HTMLTableDataCellElementTypeId, HTMLTableHeaderCellElementTypeId};
+use script::layout_interface::{AddStylesheetMsg, ContentBoxQuery, DeleteRuleMsg, InsertRuleMsg};
+use script::layout_interface::{ReplaceStylesheetMsg, SetStylesheetDisabledMsg};
+use script::layout_interface::{ComputedStyleQuery, ComputedStyleResponse, TrustedNodeAddress};
 use script::layout_interface::{ContentBoxesQuery, ContentBoxesResponse, ExitNowMsg, LayoutQuery};
+use script::layout_interface::{ClientSizeQuery, ClientSizeResponse, OffsetQuery, OffsetResponse};
+use script::layout_interface::{ScrollSizeQuery, ScrollSizeResponse};
 use script::layout_interface::{HitTestQuery, ContentBoxResponse, HitTestResponse, MouseOverQuery, MouseOverResponse};
 use script::layout_interface::{ContentChangedDocumentDamage, LayoutChan, Msg, PrepareToExitMsg};
 use script::layout_interface::{QueryMsg, ReapLayoutDataMsg, Reflow, UntrustedNodeAddress};
 use script::layout_interface::{ReflowForDisplay, ReflowMsg};
+use script::layout_interface::{AttributeRestyle, HoverStateRestyle, PendingRestyle};
+use script::layout_interface::RestyleDocumentDamage;
 use script::script_task::{ReflowCompleteMsg, ScriptChan, SendEventMsg};
-use servo_msg::compositor_msg::Scrollable;
+use servo_msg::compositor_msg::{FixedPosition, LayerId, ScrollPolicy, Scrollable};
+use servo_msg::compositor_msg::{ScrollbarThumb, StickyPosition};
 use servo_msg::constellation_msg::{ConstellationChan, PipelineId, Failure, FailureMsg};
 use servo_net::image_cache_task::{ImageCacheTask, ImageResponseMsg};
 use servo_net::local_image_cache::{ImageResponder, LocalImageCache};
-use servo_util::geometry::Au;
+use servo_util::geometry::{Au, LogicalSize};
 use servo_util::geometry;
 use servo_util::opts::Opts;
 use servo_util::smallvec::{SmallVec, SmallVec1};
@@ -53,11 +65,15 @@ use servo_util::time::{TimeProfilerChan, profile};
 use servo_util::time;
 use servo_util::task::send_on_failure;
 use servo_util::workqueue::WorkQueue;
+use std::cmp;
+use std::collections::hashmap::HashMap;
 use std::comm::{channel, Sender, Receiver};
 use std::mem;
 use std::ptr;
 use std::task::TaskBuilder;
-use style::{AuthorOrigin, Stylesheet, Stylist};
+use style::{CSSRule, PageDescription, Stylesheet, Stylist, ToComputedCss};
+use style::{ComputedValues, TNode, nested_rule_list_mut};
+use style::computed_values::{display, position};
 use sync::{Arc, Mutex};
 use url::Url;
 
@@ -93,8 +109,22 @@ pub struct LayoutTask {
     /// A cached display list.
     pub display_list: Option<Arc<DisplayList>>,
 
+    /// The cached display lists of the layers other than the root layer, such as those of scroll
+    /// containers, each with the ID and scroll policy of its layer and the part of the page that
+    /// the layer covers. Content box queries and hit testing look through these too.
+    pub layer_display_lists: Vec<(LayerId, ScrollPolicy, Rect<Au>, Arc<DisplayList>)>,
+
+    /// The size of the visible part and the size of the contents of each scroll container, by the
+    /// ID of its layer.
+    pub scroll_container_sizes: HashMap<LayerId, (Size2D<Au>, Size2D<Au>)>,
+
     pub stylist: Box<Stylist>,
 
+    /// The author stylesheets, in the order script added them, each with whether it is disabled.
+    /// These are kept so that the author rules in the stylist can be rebuilt when the CSSOM
+    /// modifies a stylesheet.
+    pub author_stylesheets: Vec<(Stylesheet, bool)>,
+
     /// The workers that we use for parallel operation.
     pub parallel_traversal: Option<WorkQueue<*mut LayoutContext,UnsafeFlow>>,
 
@@ -180,13 +210,10 @@ impl<'a> PostorderFlowTraversal for BubbleWidthsTraversal<'a> {
         true
     }
 
-    // FIXME: We can't prune until we start reusing flows
-    /*
     #[inline]
     fn should_prune(&mut self, flow: &mut Flow) -> bool {
-        flow::mut_base(flow).restyle_damage.lacks(BubbleWidths)
+        !flow::base(flow).restyle_damage.contains(BubbleWidths)
     }
-    */
 }
 
 /// The assign-widths traversal. In Gecko this corresponds to `Reflow`.
@@ -197,9 +224,34 @@ pub struct AssignWidthsTraversal<'a> {
 impl<'a> PreorderFlowTraversal for AssignWidthsTraversal<'a> {
     #[inline]
     fn process(&mut self, flow: &mut Flow) -> bool {
+        let old_kid_widths: Vec<Au> = flow::child_iter(flow).map(|kid| {
+            flow::base(kid).position.size.inline
+        }).collect();
+
         flow.assign_widths(self.layout_context);
+
+        // Children that were not damaged themselves still need to be reflowed if their width
+        // changed, if floats may flow into them, or if they are absolutely positioned and thus
+        // depend on the height of their containing block too. Other kinds of flows pass more than
+        // a width down to their children (column widths, for example), so all of their children
+        // are reflowed.
+        let reflow_all_kids = flow.class() != BlockFlowClass;
+        for (kid, old_width) in flow::child_iter(flow).zip(old_kid_widths.iter()) {
+            let is_absolutely_positioned = kid.is_absolutely_positioned();
+            let kid_base = flow::mut_base(kid);
+            if reflow_all_kids || is_absolutely_positioned ||
+                    kid_base.position.size.inline != *old_width ||
+                    kid_base.flags.impacted_by_floats() {
+                kid_base.restyle_damage.insert(incremental::Reflow)
+            }
+        }
         true
     }
+
+    #[inline]
+    fn should_prune(&mut self, flow: &mut Flow) -> bool {
+        !flow::base(flow).restyle_damage.contains(incremental::Reflow)
+    }
 }
 
 /// The assign-heights-and-store-overflow traversal, the last (and most expensive) part of layout
@@ -223,7 +275,13 @@ impl<'a> PostorderFlowTraversal for AssignHeightsAndStoreOverflowTraversal<'a> {
 
     #[inline]
     fn should_process(&mut self, flow: &mut Flow) -> bool {
-        !flow::base(flow).flags.impacted_by_floats()
+        let base = flow::base(flow);
+        base.restyle_damage.contains(incremental::Reflow) && !base.flags.impacted_by_floats()
+    }
+
+    #[inline]
+    fn should_prune(&mut self, flow: &mut Flow) -> bool {
+        !flow::base(flow).restyle_damage.contains(incremental::Reflow)
     }
 }
 
@@ -237,6 +295,13 @@ impl<'a> BuildDisplayListTraversal<'a> {
     fn process(&mut self, flow: &mut Flow) {
         flow.compute_absolute_position();
 
+        // If nothing in this subtree changed, the display list from the last layout only needs to
+        // be moved.
+        if flow.can_reuse_display_list() {
+            flow.reuse_display_list();
+            return
+        }
+
         for kid in flow::mut_base(flow).child_iter() {
             if !kid.is_absolutely_positioned() {
                 self.process(kid)
@@ -247,7 +312,8 @@ impl<'a> BuildDisplayListTraversal<'a> {
             self.process(absolute_descendant_link)
         }
 
-        flow.build_display_list(self.layout_context)
+        flow.build_display_list(self.layout_context);
+        flow.retain_display_list()
     }
 }
 
@@ -332,7 +398,10 @@ impl LayoutTask {
             screen_size: screen_size,
 
             display_list: None,
-            stylist: box new_stylist(),
+            layer_display_lists: vec!(),
+            scroll_container_sizes: HashMap::new(),
+            stylist: box new_stylist(opts),
+            author_stylesheets: vec!(),
             parallel_traversal: parallel_traversal,
             time_profiler_chan: time_profiler_chan,
             opts: opts.clone(),
@@ -348,7 +417,12 @@ impl LayoutTask {
     }
 
     // Create a layout context for use in building display lists, hit testing, &c.
-    fn build_layout_context(&self, reflow_root: &LayoutNode, url: &Url) -> LayoutContext {
+    fn build_layout_context(&self,
+                            reflow_root: &LayoutNode,
+                            url: &Url,
+                            reuse_flows: bool,
+                            restyle_all: bool)
+                            -> LayoutContext {
         let font_context_info = FontContextInfo {
             backend: self.opts.render_backend,
             needs_font_list: true,
@@ -366,6 +440,8 @@ impl LayoutTask {
             reflow_root: OpaqueNodeMethods::from_layout_node(reflow_root),
             opts: self.opts.clone(),
             dirty: Rect::zero(),
+            reuse_flows: reuse_flows,
+            restyle_all: restyle_all,
         }
     }
 
@@ -373,6 +449,16 @@ impl LayoutTask {
     fn handle_request(&mut self) -> bool {
         match self.port.recv() {
             AddStylesheetMsg(sheet) => self.handle_add_stylesheet(sheet),
+            ReplaceStylesheetMsg(index, sheet) => self.handle_replace_stylesheet(index, sheet),
+            InsertRuleMsg(index, path, rule_index, rule) => {
+                self.handle_insert_rule(index, path.as_slice(), rule_index, rule)
+            }
+            DeleteRuleMsg(index, path, rule_index) => {
+                self.handle_delete_rule(index, path.as_slice(), rule_index)
+            }
+            SetStylesheetDisabledMsg(index, disabled) => {
+                self.handle_set_stylesheet_disabled(index, disabled)
+            }
             ReflowMsg(data) => {
                 profile(time::LayoutPerformCategory, self.time_profiler_chan.clone(), || {
                     self.handle_reflow(data);
@@ -444,7 +530,76 @@ impl LayoutTask {
     }
 
     fn handle_add_stylesheet(&mut self, sheet: Stylesheet) {
-        self.stylist.add_stylesheet(sheet, AuthorOrigin)
+        self.stylist.add_author_stylesheet(&sheet, self.author_stylesheets.len());
+        self.author_stylesheets.push((sheet, false))
+    }
+
+    fn handle_replace_stylesheet(&mut self, index: uint, sheet: Stylesheet) {
+        if index >= self.author_stylesheets.len() {
+            return
+        }
+        match *self.author_stylesheets.get_mut(index) {
+            (ref mut old_sheet, _) => *old_sheet = sheet,
+        }
+        self.rebuild_author_rules(index)
+    }
+
+    fn handle_insert_rule(&mut self, index: uint, path: &[uint], rule_index: uint, rule: CSSRule) {
+        if index >= self.author_stylesheets.len() {
+            return
+        }
+        match *self.author_stylesheets.get_mut(index) {
+            (ref mut sheet, _) => {
+                match nested_rule_list_mut(&mut sheet.rules, path) {
+                    Some(rules) if rule_index <= rules.len() => rules.insert(rule_index, rule),
+                    _ => return,
+                }
+            }
+        }
+        self.rebuild_author_rules(index)
+    }
+
+    fn handle_delete_rule(&mut self, index: uint, path: &[uint], rule_index: uint) {
+        if index >= self.author_stylesheets.len() {
+            return
+        }
+        match *self.author_stylesheets.get_mut(index) {
+            (ref mut sheet, _) => {
+                match nested_rule_list_mut(&mut sheet.rules, path) {
+                    Some(rules) if rule_index < rules.len() => {
+                        rules.remove(rule_index);
+                    }
+                    _ => return,
+                }
+            }
+        }
+        self.rebuild_author_rules(index)
+    }
+
+    fn handle_set_stylesheet_disabled(&mut self, index: uint, disabled: bool) {
+        if index >= self.author_stylesheets.len() {
+            return
+        }
+        match *self.author_stylesheets.get_mut(index) {
+            (_, ref mut old_disabled) => {
+                if *old_disabled == disabled {
+                    return
+                }
+                *old_disabled = disabled
+            }
+        }
+        self.rebuild_author_rules(index)
+    }
+
+    /// Replaces the stylist's rules for the author style sheet at `index` with its current
+    /// rules, or just removes them if the sheet is disabled. The rules of other style sheets are
+    /// left alone. Script requests a restyle separately after modifying a stylesheet.
+    fn rebuild_author_rules(&mut self, index: uint) {
+        self.stylist.remove_author_stylesheet(index);
+        match *self.author_stylesheets.get(index) {
+            (ref sheet, false) => self.stylist.add_author_stylesheet(sheet, index),
+            (_, true) => {}
+        }
     }
 
     /// Retrieves the flow tree root from the root node.
@@ -487,11 +642,9 @@ impl LayoutTask {
             layout_root.traverse_postorder(&mut traversal);
         }
 
-        // FIXME(kmc): We want to prune nodes without the Reflow restyle damage
-        // bit, but FloatContext values can't be reused, so we need to
-        // recompute them every time.
-        // NOTE: this currently computes borders, so any pruning should separate that operation
-        // out.
+        // Flows without the `Reflow` restyle damage bit are pruned from this traversal and the
+        // next. `AssignWidthsTraversal` adds the bit to children whose width changed or that are
+        // impacted by floats, since those are the only cases in which their layout can change.
         {
             let mut traversal = AssignWidthsTraversal {
                 layout_context: layout_context,
@@ -499,7 +652,6 @@ impl LayoutTask {
             layout_root.traverse_preorder(&mut traversal);
         }
 
-        // FIXME(pcwalton): Prune this pass as well.
         {
             let mut traversal = AssignHeightsAndStoreOverflowTraversal {
                 layout_context: layout_context,
@@ -526,8 +678,6 @@ impl LayoutTask {
         match self.parallel_traversal {
             None => fail!("solve_contraints_parallel() called with no parallel traversal ready"),
             Some(ref mut traversal) => {
-                // NOTE: this currently computes borders, so any pruning should separate that
-                // operation out.
                 parallel::traverse_flow_tree_preorder(layout_root,
                                                       self.time_profiler_chan.clone(),
                                                       layout_context,
@@ -536,6 +686,46 @@ impl LayoutTask {
         }
     }
 
+    /// Splits the laid-out document into pages by moving the content of the root flow into the
+    /// page area of each page, one page below the other. Returns the page boxes, in the coordinate
+    /// system of the root flow.
+    ///
+    /// FIXME: This assumes that the root flow has a horizontal writing mode.
+    fn paginate(&self,
+                layout_root: &mut Flow,
+                layout_context: &mut LayoutContext,
+                page: &PageDescription)
+                -> Vec<Rect<Au>> {
+        let page_count = {
+            let root = layout_root.as_block();
+            let pieces = root.unbreakable_pieces();
+            let breaks = page_breaks(pieces.as_slice(), Au(0), page.content_height());
+            let offset = LogicalSize::new(root.base.writing_mode, Au(0), page.height);
+            root.move_content_into_fragmentainers(layout_context, breaks.as_slice(), offset);
+            breaks.len()
+        };
+        layout_root.store_overflow(layout_context);
+
+        range(0, page_count).map(|index| {
+            Rect(Point2D(-page.margin_left, page.height.scale_by(index as f64) - page.margin_top),
+                 Size2D(page.width, page.height))
+        }).collect()
+    }
+
+    /// Returns the size of the scrollable page: the viewport, grown to cover everything that the
+    /// root flow overflows into and all of the given page boxes. Fixed-position descendants don't
+    /// count, since they stay within the viewport however far the page is scrolled.
+    fn page_size(&self, layout_root: &Flow, pages: &[Rect<Au>]) -> Size2D<Au> {
+        let root_base = flow::base(layout_root);
+        let root_position = root_base.position.to_physical(root_base.writing_mode,
+                                                           self.screen_size);
+        let root_overflow = root_base.overflow.translate(&root_position.origin);
+        pages.iter().chain(Some(&root_overflow).move_iter()).fold(self.screen_size, |size, rect| {
+            Size2D(Au::max(size.width, rect.origin.x + rect.size.width),
+                   Au::max(size.height, rect.origin.y + rect.size.height))
+        })
+    }
+
     /// Verifies that every node was either marked as a leaf or as a nonleaf in the flow tree.
     /// This is only on in debug builds.
     #[inline(never)]
@@ -549,6 +739,31 @@ impl LayoutTask {
     fn verify_flow_tree(&mut self, _: &mut FlowRef) {
     }
 
+    /// Marks the nodes that the given restyles affect, according to the selectors in the
+    /// stylist, so that style recalc matches only those nodes again.
+    fn add_restyle_hints(&self, restyles: &[PendingRestyle]) {
+        for restyle in restyles.iter() {
+            let (address, hint) = match *restyle {
+                AttributeRestyle(address, ref name, ref value) => {
+                    (address,
+                     self.stylist.restyle_hint_for_attribute(name.as_slice(), value.as_slice()))
+                }
+                HoverStateRestyle(address) => {
+                    (address, self.stylist.restyle_hint_for_hover_state())
+                }
+            };
+
+            // FIXME: Isolate this transmutation into a "bridge" module.
+            let node: JS<Node> = unsafe {
+                JS::from_trusted_node_address(address)
+            };
+            let node: &LayoutNode = unsafe {
+                mem::transmute(&node)
+            };
+            node.add_restyle_hint(hint)
+        }
+    }
+
     /// The high-level routine that performs layout tasks.
     fn handle_reflow(&mut self, data: &Reflow) {
         // FIXME: Isolate this transmutation into a "bridge" module.
@@ -570,24 +785,40 @@ impl LayoutTask {
 
         // true => Do the reflow with full style damage, because content
         // changed or the window was resized.
-        let mut all_style_damage = match data.damage.level {
-            ContentChangedDocumentDamage => true,
-            _ => false
-        };
+        let content_changed = data.damage.level == ContentChangedDocumentDamage;
+        // Pagination moves content around after layout, so nothing can be reused when paginating.
+        let incremental_layout = self.opts.incremental_layout && !self.opts.paginated;
+        let mut all_style_damage = content_changed || !incremental_layout;
 
         // TODO: Calculate the "actual viewport":
         // http://www.w3.org/TR/css-device-adapt/#actual-viewport
         let viewport_size = data.window_size.initial_viewport;
 
-        let current_screen_size = Size2D(Au::from_frac32_px(viewport_size.width.get()),
-                                         Au::from_frac32_px(viewport_size.height.get()));
+        // When paginating, the page area takes the place of the viewport.
+        let page = if self.opts.paginated {
+            Some(self.stylist.page_description())
+        } else {
+            None
+        };
+        let current_screen_size = match page {
+            Some(ref page) => Size2D(page.content_width(), page.content_height()),
+            None => Size2D(Au::from_frac32_px(viewport_size.width.get()),
+                           Au::from_frac32_px(viewport_size.height.get())),
+        };
         if self.screen_size != current_screen_size {
             all_style_damage = true
         }
         self.screen_size = current_screen_size;
 
+        // Unless script could describe all of its changes as restyles, every node is restyled.
+        let restyle_all = all_style_damage || data.damage.level > RestyleDocumentDamage;
+        if !restyle_all {
+            self.add_restyle_hints(data.damage.restyles.as_slice())
+        }
+
         // Create a layout context for use throughout the following passes.
-        let mut layout_ctx = self.build_layout_context(node, &data.url);
+        let reuse_flows = !content_changed && incremental_layout;
+        let mut layout_ctx = self.build_layout_context(node, &data.url, reuse_flows, restyle_all);
 
         // Create a font context, if this is sequential.
         //
@@ -637,6 +868,14 @@ impl LayoutTask {
             layout_root.get_mut().traverse_postorder(&mut ComputeDamageTraversal.clone());
         });
 
+        // The viewport is the containing block of the root and of fixed descendants.
+        {
+            let root_position_info = &mut flow::mut_base(layout_root.get_mut())
+                                              .absolute_position_info;
+            root_position_info.absolute_containing_block_size = self.screen_size;
+            root_position_info.viewport_size = self.screen_size;
+        }
+
         // Perform the primary layout passes over the flow tree to compute the locations of all
         // the boxes.
         profile(time::LayoutMainCategory, self.time_profiler_chan.clone(), || {
@@ -652,10 +891,17 @@ impl LayoutTask {
             }
         });
 
+        let pages = match page {
+            Some(ref page) => self.paginate(layout_root.get_mut(), &mut layout_ctx, page),
+            None => vec!(),
+        };
+
         // Build the display list if necessary, and send it to the renderer.
         if data.goal == ReflowForDisplay {
             profile(time::LayoutDispListBuildCategory, self.time_profiler_chan.clone(), || {
-                layout_ctx.dirty = flow::base(layout_root.get()).position.clone();
+                let page_size = self.page_size(layout_root.get(), pages.as_slice());
+                layout_ctx.dirty = pages.iter().fold(Rect(Point2D(Au(0), Au(0)), page_size),
+                                                     |dirty, page| dirty.union(page));
 
                 match self.parallel_traversal {
                     None => {
@@ -701,15 +947,15 @@ impl LayoutTask {
                     }
                 }
 
-                let root_size = flow::base(layout_root.get()).position.size;
-                let root_size = Size2D(root_size.width.to_nearest_px() as uint,
-                                       root_size.height.to_nearest_px() as uint);
+                let root_size = Size2D(page_size.width.to_nearest_px() as uint,
+                                       page_size.height.to_nearest_px() as uint);
                 let render_layer = RenderLayer {
                     id: layout_root.get().layer_id(0),
                     display_list: display_list.clone(),
                     position: Rect(Point2D(0u, 0u), root_size),
                     background_color: color,
                     scroll_policy: Scrollable,
+                    content_size: None,
                 };
 
                 self.display_list = Some(display_list.clone());
@@ -719,17 +965,50 @@ impl LayoutTask {
                 // reflow.
                 let mut layers = SmallVec1::new();
                 layers.push(render_layer);
+                self.layer_display_lists = vec!();
+                self.scroll_container_sizes = HashMap::new();
                 for layer in mem::replace(&mut flow::mut_base(layout_root.get_mut()).layers,
                                           DList::new()).move_iter() {
+                    let position = Rect(Point2D(Au::from_px(layer.position.origin.x as int),
+                                                Au::from_px(layer.position.origin.y as int)),
+                                        Size2D(Au::from_px(layer.position.size.width as int),
+                                               Au::from_px(layer.position.size.height as int)));
+                    self.layer_display_lists.push((layer.id,
+                                                   layer.scroll_policy.clone(),
+                                                   position,
+                                                   layer.display_list.clone()));
+                    match layer.content_size {
+                        Some(content_size) => {
+                            let content_size = Size2D(Au::from_px(content_size.width as int),
+                                                      Au::from_px(content_size.height as int));
+                            self.scroll_container_sizes.insert(layer.id,
+                                                               (position.size, content_size));
+                        }
+                        None => {}
+                    }
                     layers.push(layer)
                 }
 
                 debug!("Layout done!");
 
+                if self.opts.paginated {
+                    self.render_chan.send(SetPagesMsg(pages.clone()));
+                }
                 self.render_chan.send(RenderMsg(layers));
             });
         }
 
+        match self.opts.dump_layout_tree {
+            Some(ref path) => {
+                let path = Path::new(path.as_slice());
+                match layout_dump::write_flow_tree(layout_root.get(), &path) {
+                    Ok(()) => {}
+                    Err(error) => error!("failed to write {}: {}", path.display(), error),
+                }
+            }
+            None => {}
+        }
+
         // Tell script that we're done.
         //
         // FIXME(pcwalton): This should probably be *one* channel, but we can't fix this without
@@ -741,33 +1020,250 @@ impl LayoutTask {
 
     /// Handles a query from the script task. This is the main routine that DOM functions like
     /// `getClientRects()` or `getBoundingClientRect()` ultimately invoke.
+    /// Returns the union of the bounds of all display items belonging to the given node, or `None`
+    /// if the node generated no display items.
+    fn union_of_boxes_for_node(&self, node: OpaqueNode) -> Option<Rect<Au>> {
+        fn union_boxes_for_node(accumulator: &mut Option<Rect<Au>>,
+                                mut iter: DisplayItemIterator,
+                                node: OpaqueNode) {
+            for item in iter {
+                union_boxes_for_node(accumulator, item.children(), node);
+                if item.base().node == node {
+                    match *accumulator {
+                        None => *accumulator = Some(item.base().bounds),
+                        Some(ref mut acc) => *acc = acc.union(&item.base().bounds),
+                    }
+                }
+            }
+        }
+
+        let mut rect = None;
+        match self.display_list {
+            None => fail!("no display list!"),
+            Some(ref display_list) => {
+                union_boxes_for_node(&mut rect, display_list.iter(), node)
+            }
+        }
+        for &(_, _, _, ref display_list) in self.layer_display_lists.iter() {
+            union_boxes_for_node(&mut rect, display_list.iter(), node)
+        }
+        rect
+    }
+
+    /// Serializes the computed style of the given node for `getComputedStyle()`. `width` and
+    /// `height` are replaced with their used values if the node generated any boxes.
+    fn computed_style_for_node(&self, address: TrustedNodeAddress) -> Vec<(String, String)> {
+        // FIXME: Isolate this transmutation into a "bridge" module.
+        let node: JS<Node> = unsafe { JS::from_trusted_node_address(address) };
+        let node: &LayoutNode = unsafe { mem::transmute(&node) };
+        let style = match style_of_node(node) {
+            None => return vec!(),
+            Some(style) => style,
+        };
+
+        let mut declarations = style.to_computed_css_declarations();
+        let rect = match self.union_of_boxes_for_node(OpaqueNodeMethods::from_script_node(address)) {
+            None => return declarations,
+            Some(rect) => rect,
+        };
+
+        // Percentage padding, on all four sides, is resolved against the width of the containing
+        // block.
+        let border = border_from_style(&**style);
+        let padding = style.get_padding();
+        let containing_block_width = self.containing_block_width(node);
+        let padding_top = specified(padding.padding_top, containing_block_width);
+        let padding_right = specified(padding.padding_right, containing_block_width);
+        let padding_bottom = specified(padding.padding_bottom, containing_block_width);
+        let padding_left = specified(padding.padding_left, containing_block_width);
+        let width = rect.size.width - border.left - border.right - padding_left - padding_right;
+        let height = rect.size.height - border.top - border.bottom - padding_top - padding_bottom;
+        for declaration in declarations.mut_iter() {
+            let (ref name, ref mut value) = *declaration;
+            let used_value = match name.as_slice() {
+                "width" => cmp::max(width, Au(0)),
+                "height" => cmp::max(height, Au(0)),
+                "padding-top" => padding_top,
+                "padding-right" => padding_right,
+                "padding-bottom" => padding_bottom,
+                "padding-left" => padding_left,
+                _ => continue,
+            };
+            *value = used_value.to_computed_css()
+        }
+        declarations
+    }
+
+    /// Returns the width of the containing block of the given node, against which its percentage
+    /// padding is resolved: the content box of the nearest block-level ancestor, the padding box
+    /// of the nearest positioned ancestor for absolutely positioned nodes, or the viewport.
+    fn containing_block_width(&self, node: &LayoutNode) -> Au {
+        let position = match style_of_node(node) {
+            Some(style) => style.get_box().position,
+            None => position::static_,
+        };
+        if position == position::fixed {
+            return self.screen_size.width
+        }
+        let mut ancestor = node.parent_node();
+        loop {
+            let candidate = match ancestor {
+                Some(candidate) if candidate.is_element() => candidate,
+                _ => return self.screen_size.width,
+            };
+            ancestor = candidate.parent_node();
+            let style = match style_of_node(&candidate) {
+                Some(style) => style,
+                None => continue,
+            };
+            let is_containing_block = if position == position::absolute {
+                style.get_box().position != position::static_
+            } else {
+                style.get_box().display != display::inline
+            };
+            if !is_containing_block {
+                continue
+            }
+            let opaque_node: OpaqueNode = OpaqueNodeMethods::from_layout_node(&candidate);
+            let rect = match self.union_of_boxes_for_node(opaque_node) {
+                None => return Au(0),
+                Some(rect) => rect,
+            };
+            let border = border_from_style(&*style);
+            let padding_box_width = rect.size.width - border.left - border.right;
+            if position == position::absolute {
+                return padding_box_width
+            }
+            let padding = style.get_padding();
+            let containing_block_width = self.containing_block_width(&candidate);
+            return padding_box_width - specified(padding.padding_left, containing_block_width) -
+                specified(padding.padding_right, containing_block_width)
+        }
+    }
+
+    /// Finds the offset parent of the given node and the border box of the node relative to the
+    /// padding box of the offset parent, for `offsetParent` and the `offsetTop` family. The body
+    /// element is an exception: boxes are relative to the initial containing block instead. Nodes
+    /// that generated no boxes have no offset parent and an empty border box.
+    fn offset_for_node(&self, address: TrustedNodeAddress)
+                       -> (Option<UntrustedNodeAddress>, Rect<Au>) {
+        // FIXME: Isolate this transmutation into a "bridge" module.
+        let node: JS<Node> = unsafe { JS::from_trusted_node_address(address) };
+        let node: &LayoutNode = unsafe { mem::transmute(&node) };
+        let opaque_node: OpaqueNode = OpaqueNodeMethods::from_layout_node(node);
+        let border_box = match self.union_of_boxes_for_node(opaque_node) {
+            None => return (None, Rect::zero()),
+            Some(rect) => rect,
+        };
+
+        // http://dev.w3.org/csswg/cssom-view/#dom-htmlelement-offsetparent
+        let position = match style_of_node(node) {
+            None => return (None, border_box),
+            Some(style) => style.get_box().position,
+        };
+        if position == position::fixed ||
+                node.type_id() == Some(ElementNodeTypeId(HTMLHtmlElementTypeId)) ||
+                node.type_id() == Some(ElementNodeTypeId(HTMLBodyElementTypeId)) {
+            return (None, border_box)
+        }
+        let mut ancestor = node.parent_node();
+        let parent;
+        loop {
+            let candidate = match ancestor {
+                None => return (None, border_box),
+                Some(candidate) => candidate,
+            };
+            if !candidate.is_element() {
+                return (None, border_box)
+            }
+            let is_offset_parent = match candidate.type_id() {
+                Some(ElementNodeTypeId(HTMLBodyElementTypeId)) => true,
+                Some(ElementNodeTypeId(HTMLTableElementTypeId)) |
+                Some(ElementNodeTypeId(HTMLTableDataCellElementTypeId)) |
+                Some(ElementNodeTypeId(HTMLTableHeaderCellElementTypeId))
+                        if position == position::static_ => true,
+                _ => {
+                    match style_of_node(&candidate) {
+                        Some(style) => style.get_box().position != position::static_,
+                        None => false,
+                    }
+                }
+            };
+            if is_offset_parent {
+                parent = candidate;
+                break
+            }
+            ancestor = candidate.parent_node();
+        }
+        let parent_address = OpaqueNodeMethods::from_layout_node(&parent);
+        if parent.type_id() == Some(ElementNodeTypeId(HTMLBodyElementTypeId)) {
+            return (Some(parent_address.to_untrusted_node_address()), border_box)
+        }
+
+        let parent_border_box = self.union_of_boxes_for_node(parent_address)
+                                    .unwrap_or(Rect::zero());
+        let parent_padding_box_origin = match style_of_node(&parent) {
+            Some(style) => {
+                let border = border_from_style(&*style);
+                Point2D(parent_border_box.origin.x + border.left,
+                        parent_border_box.origin.y + border.top)
+            }
+            None => parent_border_box.origin,
+        };
+        let origin = Point2D(border_box.origin.x - parent_padding_box_origin.x,
+                             border_box.origin.y - parent_padding_box_origin.y);
+        (Some(parent_address.to_untrusted_node_address()), Rect(origin, border_box.size))
+    }
+
+    /// Returns the size of the padding box of the given node less any scrollbars, for
+    /// `clientWidth` and `clientHeight`. That of the root element is the size of the viewport.
+    /// Inline boxes and nodes that generated no boxes have no size.
+    fn client_size_for_node(&self, address: TrustedNodeAddress) -> Size2D<Au> {
+        // FIXME: Isolate this transmutation into a "bridge" module.
+        let node: JS<Node> = unsafe { JS::from_trusted_node_address(address) };
+        let node: &LayoutNode = unsafe { mem::transmute(&node) };
+        let opaque_node: OpaqueNode = OpaqueNodeMethods::from_layout_node(node);
+        match self.scroll_container_sizes.find(&LayerId(opaque_node.id(), 0)) {
+            Some(&(visible_size, _)) => return visible_size,
+            None => {}
+        }
+        if node.type_id() == Some(ElementNodeTypeId(HTMLHtmlElementTypeId)) {
+            return self.screen_size
+        }
+
+        let style = match style_of_node(node) {
+            Some(ref style) if style.get_box().display != display::inline => style.clone(),
+            _ => return Size2D(Au(0), Au(0)),
+        };
+        let rect = match self.union_of_boxes_for_node(opaque_node) {
+            None => return Size2D(Au(0), Au(0)),
+            Some(rect) => rect,
+        };
+        let border = border_from_style(&*style);
+        Size2D(rect.size.width - border.left - border.right,
+               rect.size.height - border.top - border.bottom)
+    }
+
+    /// Returns the size of the area that the contents of the given node can be scrolled over, for
+    /// `scrollWidth` and `scrollHeight`. That is the size of the contents of scroll containers and
+    /// the client size of other nodes.
+    ///
+    /// FIXME: Descendants that overflow a node that is not a scroll container should count too.
+    fn scroll_size_for_node(&self, address: TrustedNodeAddress) -> Size2D<Au> {
+        let opaque_node: OpaqueNode = OpaqueNodeMethods::from_script_node(address);
+        match self.scroll_container_sizes.find(&LayerId(opaque_node.id(), 0)) {
+            Some(&(_, content_size)) => content_size,
+            None => self.client_size_for_node(address),
+        }
+    }
+
     fn handle_query(&self, query: LayoutQuery) {
         match query {
             // The neat thing here is that in order to answer the following two queries we only
             // need to compare nodes for equality. Thus we can safely work only with `OpaqueNode`.
             ContentBoxQuery(node, reply_chan) => {
                 let node: OpaqueNode = OpaqueNodeMethods::from_script_node(node);
-                fn union_boxes_for_node(accumulator: &mut Option<Rect<Au>>,
-                                        mut iter: DisplayItemIterator,
-                                        node: OpaqueNode) {
-                    for item in iter {
-                        union_boxes_for_node(accumulator, item.children(), node);
-                        if item.base().node == node {
-                            match *accumulator {
-                                None => *accumulator = Some(item.base().bounds),
-                                Some(ref mut acc) => *acc = acc.union(&item.base().bounds),
-                            }
-                        }
-                    }
-                }
-
-                let mut rect = None;
-                match self.display_list {
-                    None => fail!("no display list!"),
-                    Some(ref display_list) => {
-                        union_boxes_for_node(&mut rect, display_list.iter(), node)
-                    }
-                }
+                let rect = self.union_of_boxes_for_node(node);
                 reply_chan.send(ContentBoxResponse(rect.unwrap_or(Rect::zero())))
             }
             ContentBoxesQuery(node, reply_chan) => {
@@ -791,9 +1287,29 @@ impl LayoutTask {
                         add_boxes_for_node(&mut boxes, display_list.iter(), node)
                     }
                 }
+                for &(_, _, _, ref display_list) in self.layer_display_lists.iter() {
+                    add_boxes_for_node(&mut boxes, display_list.iter(), node)
+                }
                 reply_chan.send(ContentBoxesResponse(boxes))
             }
-            HitTestQuery(_, point, reply_chan) => {
+            OffsetQuery(node, reply_chan) => {
+                let (parent, rect) = self.offset_for_node(node);
+                reply_chan.send(OffsetResponse(parent, rect))
+            }
+            ClientSizeQuery(node, reply_chan) => {
+                reply_chan.send(ClientSizeResponse(self.client_size_for_node(node)))
+            }
+            ScrollSizeQuery(node, reply_chan) => {
+                let opaque_node: OpaqueNode = OpaqueNodeMethods::from_script_node(node);
+                let is_scroll_container =
+                    self.scroll_container_sizes.contains_key(&LayerId(opaque_node.id(), 0));
+                reply_chan.send(ScrollSizeResponse(self.scroll_size_for_node(node),
+                                                   is_scroll_container))
+            }
+            ComputedStyleQuery(node, reply_chan) => {
+                reply_chan.send(ComputedStyleResponse(self.computed_style_for_node(node)))
+            }
+            HitTestQuery(_, point, scroll_offsets, reply_chan) => {
                 fn hit_test<'a,I:Iterator<&'a DisplayItem>>(x: Au, y: Au, mut iterator: I)
                             -> Option<HitTestResponse> {
                     for item in iterator {
@@ -828,10 +1344,56 @@ impl LayoutTask {
                 }
                 let (x, y) = (Au::from_frac_px(point.x as f64),
                               Au::from_frac_px(point.y as f64));
-                let resp = match self.display_list {
-                    None => fail!("no display list!"),
-                    Some(ref display_list) => hit_test(x, y, display_list.list.iter().rev()),
-                };
+
+                // The other layers are on top of the root layer, and later layers are on top of
+                // earlier ones. Nothing shows outside the part of the page that a layer covers.
+                //
+                // The display lists are where the layers were laid out, but the compositor moves
+                // the layers as the page and the scroll containers in it are scrolled, so the point
+                // is first moved into the coordinates that each layer was laid out in.
+                let page_scroll_offset = scroll_offset_for_layer(&scroll_offsets, &LayerId::null());
+                let mut resp = None;
+                for &(ref id, ref scroll_policy, ref position, ref display_list) in
+                        self.layer_display_lists.iter().rev() {
+                    let layer_point = match *scroll_policy {
+                        Scrollable => point,
+                        FixedPosition => point - page_scroll_offset,
+                        StickyPosition(ref constraint) => {
+                            point - constraint.offset_for_scroll_position(page_scroll_offset)
+                        }
+                        ScrollbarThumb(ref constraint) => {
+                            let container_scroll_offset =
+                                scroll_offset_for_layer(&scroll_offsets, &constraint.container);
+                            point - constraint.offset_for_scroll_position(container_scroll_offset)
+                        }
+                    };
+                    let (layer_x, layer_y) = (Au::from_frac_px(layer_point.x as f64),
+                                              Au::from_frac_px(layer_point.y as f64));
+                    if !geometry::rect_contains_point(*position, Point2D(layer_x, layer_y)) {
+                        continue
+                    }
+
+                    // A scroll container shows its contents from the point they are scrolled to.
+                    let contents_point = if self.scroll_container_sizes.contains_key(id) {
+                        layer_point + scroll_offset_for_layer(&scroll_offsets, id)
+                    } else {
+                        layer_point
+                    };
+                    resp = hit_test(Au::from_frac_px(contents_point.x as f64),
+                                    Au::from_frac_px(contents_point.y as f64),
+                                    display_list.list.iter().rev());
+                    if resp.is_some() {
+                        break
+                    }
+                }
+                // Nothing has been laid out for display yet if there is no display list, for
+                // example when `elementFromPoint()` is called before the first reflow.
+                if resp.is_none() {
+                    resp = match self.display_list {
+                        None => None,
+                        Some(ref display_list) => hit_test(x, y, display_list.list.iter().rev()),
+                    };
+                }
                 if resp.is_some() {
                     reply_chan.send(Ok(resp.unwrap()));
                     return
@@ -872,7 +1434,7 @@ impl LayoutTask {
                 let mut mouse_over_list: Vec<UntrustedNodeAddress> = vec!();
                 let (x, y) = (Au::from_frac_px(point.x as f64), Au::from_frac_px(point.y as f64));
                 match self.display_list {
-                    None => fail!("no display list!"),
+                    None => {}
                     Some(ref display_list) => {
                         mouse_over_test(x,
                                         y,
@@ -914,3 +1476,21 @@ impl LayoutTask {
             mem::replace(&mut *layout_data_ref, None));
     }
 }
+
+/// Returns how far the layer with the given ID is scrolled, according to the offsets that script
+/// sent with a query. `LayerId::null()` stands for the page itself.
+fn scroll_offset_for_layer(scroll_offsets: &HashMap<LayerId, Point2D<f32>>, id: &LayerId)
+                           -> Point2D<f32> {
+    match scroll_offsets.find(id) {
+        Some(offset) => *offset,
+        None => Point2D(0f32, 0f32),
+    }
+}
+
+/// Returns the style of the given node, if it has been styled.
+fn style_of_node(node: &LayoutNode) -> Option<Arc<ComputedValues>> {
+    match *node.borrow_layout_data() {
+        Some(ref layout_data) => layout_data.shared_data.style.clone(),
+        None => None,
+    }
+}
@@ -106,7 +106,7 @@ impl MarginCollapseInfo {
             self.state = AccumulatingMarginIn
         }
 
-        self.top_margin = AdjoiningMargins::from_margin(fragment.margin.top)
+        self.top_margin = AdjoiningMargins::from_margin(fragment.margin.block_start)
     }
 
     pub fn finish_and_compute_collapsible_margins(mut self,
@@ -115,22 +115,22 @@ impl MarginCollapseInfo {
                                                   -> (CollapsibleMargins, Au) {
         let state = match self.state {
             AccumulatingCollapsibleTopMargin => {
-                match fragment.style().get_box().height {
+                match fragment.style().content_block_size() {
                     LPA_Auto | LPA_Length(Au(0)) | LPA_Percentage(0.) => {
-                        match fragment.style().get_box().min_height {
+                        match fragment.style().min_block_size() {
                             LP_Length(Au(0)) | LP_Percentage(0.) => {
                                 MarginsCollapseThroughFinalMarginState
                             },
                             _ => {
-                                // If the fragment has non-zero min-height, margins may not
-                                // collapse through it.
+                                // If the fragment has a non-zero minimum block size, margins
+                                // may not collapse through it.
                                 BottomMarginCollapsesFinalMarginState
                             }
                         }
                     },
                     _ => {
-                        // If the fragment has an explicitly specified height, margins may not
-                        // collapse through it.
+                        // If the fragment has an explicitly specified block size, margins
+                        // may not collapse through it.
                         BottomMarginCollapsesFinalMarginState
                     }
                 }
@@ -140,7 +140,7 @@ impl MarginCollapseInfo {
 
         // Different logic is needed here depending on whether this flow can collapse its bottom
         // margin with its children.
-        let bottom_margin = fragment.margin.bottom;
+        let bottom_margin = fragment.margin.block_end;
         if !can_collapse_bottom_margin_with_kids {
             match state {
                 MarginsCollapseThroughFinalMarginState => {
@@ -0,0 +1,448 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! CSS multi-column layout. See http://dev.w3.org/csswg/css-multicol/.
+//!
+//! The content of a multi-column container is laid out like that of a block, into a single
+//! column of the used column width. It is then fragmented into column boxes placed side by side,
+//! using the fragmentation support of `BlockFlow`, at the shortest column height for which the
+//! content fits into the used number of columns, so that the columns are balanced.
+
+#![deny(unsafe_block)]
+
+use block::{BlockFlow, MarginsMayNotCollapse, fragmentainer_breaks};
+use construct::FlowConstructor;
+use context::LayoutContext;
+use floats::{FloatKind, Floats};
+use flow::{Flow, FlowClass, ImmutableFlowUtils, MulticolFlowClass};
+use flow;
+use fragment::Fragment;
+use incremental;
+use model::IntrinsicWidths;
+use wrapper::ThreadSafeLayoutNode;
+
+use servo_msg::compositor_msg::LayerId;
+use servo_util::geometry::{Au, LogicalPoint, LogicalRect, LogicalSize};
+use servo_util::geometry;
+use std::cmp;
+use std::fmt;
+use style::computed_values::{clear, column_count, column_width, float, position};
+
+/// A flow that lays out its children in columns.
+pub struct MulticolFlow {
+    /// Data common to all block flows.
+    pub block_flow: BlockFlow,
+
+    /// The used number of columns, computed during width assignment. Content that doesn't fit
+    /// into this many columns overflows into additional columns.
+    column_count: uint,
+
+    /// The used width of each column, computed during width assignment.
+    column_width: Au,
+}
+
+impl MulticolFlow {
+    pub fn from_node(constructor: &mut FlowConstructor, node: &ThreadSafeLayoutNode)
+                     -> MulticolFlow {
+        MulticolFlow {
+            block_flow: BlockFlow::from_node(constructor, node),
+            column_count: 1,
+            column_width: Au(0),
+        }
+    }
+
+    pub fn float_from_node(constructor: &mut FlowConstructor,
+                           node: &ThreadSafeLayoutNode,
+                           float_kind: FloatKind)
+                           -> MulticolFlow {
+        MulticolFlow {
+            block_flow: BlockFlow::float_from_node(constructor, node, float_kind),
+            column_count: 1,
+            column_width: Au(0),
+        }
+    }
+
+    fn column_gap(&self) -> Au {
+        self.block_flow.fragment.style().get_column().column_gap
+    }
+
+    /// Computes the used number and width of columns for the given content width.
+    fn compute_columns(&mut self, available_width: Au) {
+        let gap = self.column_gap();
+        let (count, width) = {
+            let column = self.block_flow.fragment.style().get_column();
+            used_columns(column.column_count, column.column_width, gap, available_width)
+        };
+        self.column_count = count;
+        self.column_width = width;
+    }
+
+    /// Returns the shortest column height at which content with the given unbreakable extents,
+    /// starting at `content_start`, fits into the used number of columns.
+    fn balanced_column_height(&self, extents: &[(Au, Au)], content_start: Au) -> Au {
+        // No column can be shorter than the tallest unbreakable piece, and a single column as
+        // tall as all of the content always fits.
+        let mut low = Au(0);
+        let mut high = Au(0);
+        for &(start, end) in extents.iter() {
+            low = geometry::max(low, end - start);
+            high = geometry::max(high, end - content_start);
+        }
+
+        // Fewer breaks are needed as columns grow, so binary search.
+        while low < high {
+            let middle = low + (high - low) / Au(2);
+            if fragmentainer_breaks(extents, content_start, middle).len() <= self.column_count {
+                high = middle
+            } else {
+                low = middle + Au(1)
+            }
+        }
+        low
+    }
+
+    /// Returns the rules between the given number of columns of the given height, relative to
+    /// the border box. Rules are only drawn between columns that both have content.
+    fn column_rules(&self, used_column_count: uint, column_height: Au)
+                    -> Vec<LogicalRect<Au>> {
+        let fragment = &self.block_flow.fragment;
+        let rule_width = fragment.style().get_column().column_rule_width;
+        if rule_width == Au(0) {
+            return Vec::new()
+        }
+
+        let gap = self.column_gap();
+        let content_start = LogicalPoint::new(fragment.border_padding.inline_start,
+                                              fragment.border_padding.block_start);
+        range(1, used_column_count).map(|index| {
+            // Rules are centered in the gaps between columns, even if they are wider.
+            let gap_start = content_start.i +
+                (self.column_width + gap).scale_by(index as f64) - gap;
+            LogicalRect::new(gap_start + (gap - rule_width).scale_by(0.5),
+                             content_start.b,
+                             rule_width,
+                             column_height)
+        }).collect()
+    }
+
+    pub fn build_display_list_multicol(&mut self, layout_context: &LayoutContext) {
+        debug!("build_display_list_multicol: same process as block flow");
+        self.block_flow.build_display_list_block(layout_context)
+    }
+}
+
+/// Returns the used number and width of columns for the given specified `column-count` and
+/// `column-width`, following the pseudo-algorithm in § 3.4 of the specification.
+fn used_columns(specified_count: column_count::T,
+                specified_width: column_width::T,
+                gap: Au,
+                available_width: Au)
+                -> (uint, Au) {
+    let count = match (specified_count, specified_width) {
+        (column_count::Count(count), column_width::Auto) => count as uint,
+        (specified_count, column_width::Width(width)) => {
+            let fitting = if width + gap > Au(0) {
+                ((available_width + gap).to_f64().unwrap() /
+                 (width + gap).to_f64().unwrap()).floor() as uint
+            } else {
+                1
+            };
+            match specified_count {
+                column_count::Count(count) => cmp::min(count as uint, fitting),
+                column_count::Auto => fitting,
+            }
+        }
+        (column_count::Auto, column_width::Auto) => 1,
+    };
+    let count = if count == 0 { 1 } else { count };
+
+    let gaps = gap.scale_by((count - 1) as f64);
+    (count, geometry::max(Au(0), available_width - gaps).scale_by(1.0 / count as f64))
+}
+
+/// Adds `Reflow` damage to every flow in the subtree rooted at `flow`. Moving content into
+/// columns translates lines and flows in place, so none of them can keep its position from the
+/// previous layout.
+fn mark_subtree_for_reflow(flow: &mut Flow) {
+    let base = flow::mut_base(flow);
+    base.restyle_damage.insert(incremental::Reflow);
+    for kid in base.child_iter() {
+        mark_subtree_for_reflow(kid)
+    }
+}
+
+impl Flow for MulticolFlow {
+    fn class(&self) -> FlowClass {
+        MulticolFlowClass
+    }
+
+    fn as_multicol<'a>(&'a mut self) -> &'a mut MulticolFlow {
+        self
+    }
+
+    fn as_block<'a>(&'a mut self) -> &'a mut BlockFlow {
+        &mut self.block_flow
+    }
+
+    /// Computes the minimum and preferred widths of the multi-column container. The minimum width
+    /// is that of a single column; the preferred width fits the specified number of columns of
+    /// the preferred width of the content, or of `column-width` if that is larger.
+    fn bubble_widths(&mut self, _: &mut LayoutContext) {
+        let mut column_widths = IntrinsicWidths::new();
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_absolutely_positioned() {
+                continue
+            }
+            let kid_widths = &flow::base(kid).intrinsic_widths;
+            column_widths.minimum_width = geometry::max(column_widths.minimum_width,
+                                                        kid_widths.total_minimum_width());
+            column_widths.preferred_width = geometry::max(column_widths.preferred_width,
+                                                          kid_widths.total_preferred_width());
+        }
+
+        let gap = self.column_gap();
+        let column = self.block_flow.fragment.style().get_column();
+        let preferred_column_width = match column.column_width {
+            column_width::Width(width) => geometry::max(width, column_widths.preferred_width),
+            column_width::Auto => column_widths.preferred_width,
+        };
+        let count = match column.column_count {
+            column_count::Count(count) => count as f64,
+            column_count::Auto => 1.0,
+        };
+
+        let mut intrinsic_widths = IntrinsicWidths::new();
+        let fragment_intrinsic_widths = self.block_flow.fragment.intrinsic_widths(None);
+        intrinsic_widths.minimum_width = geometry::max(column_widths.minimum_width,
+                                                       fragment_intrinsic_widths.minimum_width);
+        intrinsic_widths.preferred_width =
+            geometry::max(preferred_column_width.scale_by(count) + gap.scale_by(count - 1.0),
+                          fragment_intrinsic_widths.preferred_width);
+        intrinsic_widths.surround_width = fragment_intrinsic_widths.surround_width;
+        self.block_flow.base.intrinsic_widths = intrinsic_widths;
+
+        // Multi-column containers are formatting contexts, so floats inside them don't escape.
+        let flags = &mut self.block_flow.base.flags;
+        flags.set_has_left_floated_descendants(false);
+        flags.set_has_right_floated_descendants(false);
+        match self.block_flow.fragment.style().get_box().float {
+            float::none => {}
+            float::left => flags.set_has_left_floated_descendants(true),
+            float::right => flags.set_has_right_floated_descendants(true),
+        }
+    }
+
+    fn assign_widths(&mut self, layout_context: &mut LayoutContext) {
+        debug!("assign_widths({}): assigning width for flow", "multicol");
+
+        if self.block_flow.is_root() {
+            let writing_mode = self.block_flow.base.writing_mode;
+            self.block_flow.base.position.start = LogicalPoint::zero();
+            self.block_flow.base.position.size.inline =
+                LogicalSize::from_physical(writing_mode, layout_context.screen_size).inline;
+            self.block_flow.base.floats = Floats::new();
+        }
+
+        // Our width was set to the width of the containing block by the flow's parent. Now compute
+        // the real value.
+        let containing_block_width = self.block_flow.base.position.size.inline;
+        self.block_flow.compute_used_width(layout_context, containing_block_width);
+        if self.block_flow.is_float() {
+            self.block_flow.float.get_mut_ref().containing_width = containing_block_width;
+        }
+
+        // Multi-column containers are never impacted by floats.
+        self.block_flow.base.flags.set_impacted_by_left_floats(false);
+        self.block_flow.base.flags.set_impacted_by_right_floats(false);
+
+        let left_content_edge = self.block_flow.fragment.border_box.start.i +
+            self.block_flow.fragment.border_padding.inline_start;
+        let content_width = self.block_flow.fragment.border_box.size.inline -
+            self.block_flow.fragment.border_padding.inline_start_end();
+        if self.block_flow.is_float() {
+            self.block_flow.base.position.size.inline = content_width;
+        }
+
+        // The content is laid out into the first column, and moved into the others later.
+        self.compute_columns(content_width);
+        let column_width = self.column_width;
+        self.block_flow.propagate_assigned_width_to_children(left_content_edge,
+                                                             column_width,
+                                                             None);
+
+        // The content was moved during the last layout, so all of it has to be laid out again.
+        for kid in self.block_flow.base.child_iter() {
+            mark_subtree_for_reflow(kid)
+        }
+    }
+
+    fn assign_height_for_inorder_child_if_necessary(&mut self, layout_context: &mut LayoutContext)
+                                                    -> bool {
+        if self.block_flow.is_float() {
+            self.block_flow.place_float();
+            return true
+        }
+
+        let impacted = self.block_flow.base.flags.impacted_by_floats();
+        if impacted {
+            self.assign_height(layout_context);
+        }
+        impacted
+    }
+
+    fn assign_height(&mut self, layout_context: &mut LayoutContext) {
+        debug!("assign_height: assigning height for multicol flow");
+
+        // Lay the content out as a single column, then break it into balanced columns. If the
+        // container's height is smaller than the balanced column height, columns are as tall as
+        // the container, and the content that doesn't fit overflows into additional columns.
+        //
+        // FIXME: Absolutely positioned descendants are laid out against the single column.
+        self.block_flow.assign_height_block_base(layout_context, MarginsMayNotCollapse);
+
+        let content_start = self.block_flow.fragment.border_padding.block_start;
+        let extents = self.block_flow.unbreakable_extents();
+        let balanced_height = self.balanced_column_height(extents.as_slice(), content_start);
+        let content_height = self.block_flow.used_content_height(layout_context, balanced_height);
+        let column_height = geometry::min(content_height, balanced_height);
+
+        let breaks = fragmentainer_breaks(extents.as_slice(), content_start, column_height);
+        let column_offset = LogicalSize::new(self.column_width + self.column_gap(), Au(0));
+        self.block_flow.move_content_into_fragmentainers(layout_context,
+                                                         breaks.as_slice(),
+                                                         column_offset);
+
+        self.block_flow.column_rules = self.column_rules(breaks.len(), content_height);
+        self.block_flow.assign_height_for_positioned_children(layout_context, content_height);
+    }
+
+    fn compute_absolute_position(&mut self) {
+        self.block_flow.compute_absolute_position()
+    }
+
+    fn float_clearance(&self) -> clear::T {
+        self.block_flow.float_clearance()
+    }
+
+    fn mark_as_root(&mut self) {
+        self.block_flow.mark_as_root()
+    }
+
+    fn is_store_overflow_delayed(&mut self) -> bool {
+        self.block_flow.is_store_overflow_delayed()
+    }
+
+    fn is_root(&self) -> bool {
+        self.block_flow.is_root()
+    }
+
+    fn is_float(&self) -> bool {
+        self.block_flow.is_float()
+    }
+
+    fn positioning(&self) -> position::T {
+        self.block_flow.positioning()
+    }
+
+    fn is_root_of_absolute_flow_tree(&self) -> bool {
+        self.block_flow.is_root_of_absolute_flow_tree()
+    }
+
+    fn generated_containing_block_rect(&self) -> LogicalRect<Au> {
+        self.block_flow.generated_containing_block_rect()
+    }
+
+    fn layer_id(&self, fragment_index: uint) -> LayerId {
+        self.block_flow.layer_id(fragment_index)
+    }
+
+    fn is_absolute_containing_block(&self) -> bool {
+        self.block_flow.is_absolute_containing_block()
+    }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        self.block_flow.iterate_through_fragments(iterator)
+    }
+}
+
+impl fmt::Show for MulticolFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MulticolFlow: {}", self.block_flow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::used_columns;
+
+    use servo_util::geometry::Au;
+    use style::computed_values::{column_count, column_width};
+
+    #[test]
+    fn test_used_columns_count() {
+        let (count, width) = used_columns(column_count::Count(4),
+                                          column_width::Auto,
+                                          Au(10),
+                                          Au(430));
+        assert_eq!(count, 4);
+        assert_eq!(width, Au(100));
+    }
+
+    #[test]
+    fn test_used_columns_width() {
+        // As many columns of at least the specified width as fit, widened to fill the space.
+        let (count, width) = used_columns(column_count::Auto,
+                                          column_width::Width(Au(100)),
+                                          Au(20),
+                                          Au(350));
+        assert_eq!(count, 3);
+        assert_eq!(width, Au(103));
+    }
+
+    #[test]
+    fn test_used_columns_count_and_width() {
+        // The count is a maximum when both are specified.
+        let (count, width) = used_columns(column_count::Count(2),
+                                          column_width::Width(Au(100)),
+                                          Au(0),
+                                          Au(500));
+        assert_eq!(count, 2);
+        assert_eq!(width, Au(250));
+
+        let (count, _) = used_columns(column_count::Count(8),
+                                      column_width::Width(Au(100)),
+                                      Au(0),
+                                      Au(500));
+        assert_eq!(count, 5);
+    }
+
+    #[test]
+    fn test_used_columns_narrow() {
+        // There is always at least one column, even if it is narrower than `column-width`, and
+        // gaps wider than the available space don't make it negative.
+        let (count, width) = used_columns(column_count::Auto,
+                                          column_width::Width(Au(100)),
+                                          Au(10),
+                                          Au(50));
+        assert_eq!(count, 1);
+        assert_eq!(width, Au(50));
+
+        let (count, width) = used_columns(column_count::Count(3),
+                                          column_width::Auto,
+                                          Au(100),
+                                          Au(50));
+        assert_eq!(count, 3);
+        assert_eq!(width, Au(0));
+    }
+
+    #[test]
+    fn test_used_columns_auto() {
+        let (count, width) = used_columns(column_count::Auto,
+                                          column_width::Auto,
+                                          Au(10),
+                                          Au(200));
+        assert_eq!(count, 1);
+        assert_eq!(width, Au(200));
+    }
+}
@@ -177,6 +177,12 @@ trait ParallelPreorderFlowTraversal : PreorderFlowTraversal {
             // Get a real flow.
             let flow: &mut FlowRef = mem::transmute(&unsafe_flow);
 
+            // A pruned flow is treated as a leaf. The bottom-up traversal skips it too, but still
+            // needs to visit it to let its parent proceed.
+            if self.should_prune(flow.get_mut()) {
+                return bottom_up_func(unsafe_flow, proxy)
+            }
+
             // Perform the appropriate traversal.
             self.process(flow.get_mut());
 
@@ -225,6 +231,7 @@ fn recalc_style_for_node(unsafe_layout_node: UnsafeLayoutNode,
     // FIXME(pcwalton): Stop allocating here. Ideally this should just be done by the HTML
     // parser.
     node.initialize_layout_data(layout_context.layout_chan.clone());
+    let previous_styles = node.previous_styles();
 
     // Get the parent node.
     let opaque_node: OpaqueNode = OpaqueNodeMethods::from_layout_node(&node);
@@ -234,39 +241,48 @@ fn recalc_style_for_node(unsafe_layout_node: UnsafeLayoutNode,
         node.parent_node()
     };
 
-    // First, check to see whether we can share a style with someone.
-    let style_sharing_candidate_cache = layout_context.style_sharing_candidate_cache();
-    let sharing_result = unsafe {
-        node.share_style_if_possible(style_sharing_candidate_cache,
-                                     parent_opt.clone())
-    };
-
-    // Otherwise, match and cascade selectors.
-    match sharing_result {
-        CannotShare(mut shareable) => {
-            let mut applicable_declarations = ApplicableDeclarations::new();
-
-            if node.is_element() {
-                // Perform the CSS selector matching.
-                let stylist = unsafe { &*layout_context.stylist };
-                node.match_node(stylist, &mut applicable_declarations, &mut shareable);
-            }
+    if node.needs_restyle(parent_opt.clone(), layout_context.restyle_all) {
+        // First, check to see whether we can share a style with someone.
+        let style_sharing_candidate_cache = layout_context.style_sharing_candidate_cache();
+        let presentational_hints = node.presentational_hints();
+        let sharing_result = unsafe {
+            node.share_style_if_possible(style_sharing_candidate_cache,
+                                         parent_opt.clone(),
+                                         presentational_hints.as_slice())
+        };
+
+        // Otherwise, match and cascade selectors.
+        match sharing_result {
+            CannotShare(mut shareable) => {
+                let mut applicable_declarations = ApplicableDeclarations::new();
+
+                if node.is_element() {
+                    // Perform the CSS selector matching.
+                    let stylist = unsafe { &*layout_context.stylist };
+                    node.match_node(stylist,
+                                    presentational_hints,
+                                    &mut applicable_declarations,
+                                    &mut shareable);
+                }
 
-            // Perform the CSS cascade.
-            unsafe {
-                node.cascade_node(parent_opt,
-                                  &applicable_declarations,
-                                  layout_context.applicable_declarations_cache());
-            }
+                // Perform the CSS cascade.
+                unsafe {
+                    node.cascade_node(parent_opt,
+                                      &applicable_declarations,
+                                      layout_context.applicable_declarations_cache());
+                }
 
-            // Add ourselves to the LRU cache.
-            if shareable {
-                style_sharing_candidate_cache.insert_if_possible(&node);
+                // Add ourselves to the LRU cache.
+                if shareable {
+                    style_sharing_candidate_cache.insert_if_possible(&node);
+                }
             }
+            StyleWasShared(index) => style_sharing_candidate_cache.touch(index),
         }
-        StyleWasShared(index) => style_sharing_candidate_cache.touch(index),
     }
 
+    node.store_restyle_damage(previous_styles);
+
     // Prepare for flow construction by counting the node's children and storing that count.
     let mut child_count = 0;
     for _ in node.children() {
@@ -399,6 +415,12 @@ fn compute_absolute_position(unsafe_flow: UnsafeFlow,
         // Compute the absolute position for the flow.
         flow.get_mut().compute_absolute_position();
 
+        // If the display list from the last layout can be reused, none of the descendants need to
+        // be visited.
+        if flow.get_mut().can_reuse_display_list() {
+            return build_display_list(mut_owned_flow_to_unsafe_flow(flow), proxy)
+        }
+
         // Count the number of absolutely-positioned children, so that we can subtract it from
         // from `children_and_absolute_descendant_count` to get the number of real children.
         let mut absolutely_positioned_child_count = 0;
@@ -453,7 +475,12 @@ fn build_display_list(mut unsafe_flow: UnsafeFlow,
             let flow: &mut FlowRef = mem::transmute(&unsafe_flow);
 
             // Build display lists.
-            flow.get_mut().build_display_list(layout_context);
+            if flow.get_mut().can_reuse_display_list() {
+                flow.get_mut().reuse_display_list()
+            } else {
+                flow.get_mut().build_display_list(layout_context);
+                flow.get_mut().retain_display_list()
+            }
 
             {
                 let base = flow::mut_base(flow.get_mut());
@@ -12,14 +12,23 @@ use construct::FlowConstructor;
 use context::LayoutContext;
 use floats::FloatKind;
 use flow::{TableFlowClass, FlowClass, Flow, ImmutableFlowUtils};
-use fragment::Fragment;
+use flow;
+use fragment::{Fragment, TableColumnFragment};
+use table_cell::TableCellFlow;
+use table_row::TableRowFlow;
 use table_wrapper::{TableLayout, FixedLayout, AutoLayout};
 use wrapper::ThreadSafeLayoutNode;
 
+use geom::SideOffsets2D;
 use servo_util::geometry::Au;
 use servo_util::geometry;
+use std::cmp;
 use std::fmt;
-use style::computed_values::table_layout;
+use std::num::Zero;
+use std::uint;
+use style::ComputedValues;
+use style::computed_values::{RGBA, border_collapse, border_spacing, border_style, table_layout};
+use sync::Arc;
 
 /// A table flow corresponded to the table's internal table fragment under a table wrapper flow.
 /// The properties `position`, `float`, and `margin-*` are used on the table wrapper fragment,
@@ -38,6 +47,10 @@ pub struct TableFlow {
 
     /// Table-layout property
     pub table_layout: TableLayout,
+
+    /// In the collapsing border model, the parts of the outermost collapsed borders that lie
+    /// outside the cells. These take the place of the table's own borders and padding.
+    pub collapsed_outer_border: SideOffsets2D<Au>,
 }
 
 impl TableFlow {
@@ -56,7 +69,8 @@ impl TableFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
-            table_layout: table_layout
+            table_layout: table_layout,
+            collapsed_outer_border: Zero::zero(),
         }
     }
 
@@ -75,7 +89,8 @@ impl TableFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
-            table_layout: table_layout
+            table_layout: table_layout,
+            collapsed_outer_border: Zero::zero(),
         }
     }
 
@@ -95,7 +110,8 @@ impl TableFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
-            table_layout: table_layout
+            table_layout: table_layout,
+            collapsed_outer_border: Zero::zero(),
         }
     }
 
@@ -118,6 +134,95 @@ impl TableFlow {
         sum_widths
     }
 
+    /// Returns the width of this table that is not taken up by its columns: the horizontal
+    /// `border-spacing` around the columns in the separated borders model, or the outer halves of
+    /// the outermost borders in the collapsing border model.
+    pub fn non_column_width(&self) -> Au {
+        let style = self.block_flow.fragment.style();
+        match style.get_inheritedtable().border_collapse {
+            border_collapse::separate => {
+                let column_count = self.col_widths.len() as i32;
+                border_spacing(style).horizontal * Au::new(column_count + 1)
+            }
+            border_collapse::collapse => self.collapsed_outer_border.horizontal(),
+        }
+    }
+
+    /// Calls the given closure on each cell of this table, in document order.
+    fn each_cell(&mut self, callback: |&mut TableCellFlow|) {
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_table_rowgroup() {
+                for row in kid.as_table_rowgroup().block_flow.base.child_iter() {
+                    for cell in row.as_table_row().block_flow.base.child_iter() {
+                        callback(cell.as_table_cell())
+                    }
+                }
+            } else if kid.is_table_row() {
+                for cell in kid.as_table_row().block_flow.base.child_iter() {
+                    callback(cell.as_table_cell())
+                }
+            }
+        }
+    }
+
+    /// Resolves the collapsed borders of every cell of this table per CSS 2.1 § 17.6.2 and
+    /// records the parts of the outermost borders that lie outside the cells. In the separated
+    /// borders model, this clears any collapsed borders left over from a previous layout.
+    fn resolve_collapsed_borders(&mut self) {
+        if self.block_flow.fragment.style().get_inheritedtable().border_collapse ==
+                border_collapse::separate {
+            self.collapsed_outer_border = Zero::zero();
+            self.each_cell(|cell| cell.collapsed_borders = None);
+            return
+        }
+
+        let mut columns = vec!();
+        let mut rows = vec!();
+        let mut cells = vec!();
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_table_colgroup() {
+                let colgroup = kid.as_table_colgroup();
+                let group_style = colgroup.fragment.as_ref().map(|fragment| fragment.style.clone());
+                let first_column = columns.len();
+                for col in colgroup.cols.iter() {
+                    let span = match col.specific {
+                        TableColumnFragment(ref info) => cmp::max(info.span.unwrap_or(1), 1),
+                        _ => 1,
+                    };
+                    for _ in range(0, span) {
+                        columns.push(TrackBorderStyles::new(Some(col.style.clone()),
+                                                            group_style.clone()));
+                    }
+                }
+                mark_group_ends(columns.mut_slice_from(first_column));
+            } else if kid.is_table_rowgroup() {
+                let rowgroup = kid.as_table_rowgroup();
+                let group_style = Some(rowgroup.block_flow.fragment.style.clone());
+                let (first_row, first_cell) = (rows.len(), cells.len());
+                for row in rowgroup.block_flow.base.child_iter() {
+                    add_row_border_styles(row.as_table_row(),
+                                          group_style.clone(),
+                                          &mut rows,
+                                          &mut cells);
+                }
+                clamp_row_spans(cells.mut_slice_from(first_cell), rows.len());
+                mark_group_ends(rows.mut_slice_from(first_row));
+            } else if kid.is_table_row() {
+                let first_cell = cells.len();
+                add_row_border_styles(kid.as_table_row(), None, &mut rows, &mut cells);
+                clamp_row_spans(cells.mut_slice_from(first_cell), rows.len());
+            }
+        }
+
+        let resolver = CollapsedBorderResolver::new(self.block_flow.fragment.style.clone(),
+                                                    columns,
+                                                    rows,
+                                                    cells);
+        self.collapsed_outer_border = resolver.outer_border_widths();
+        let mut cell_borders = resolver.cells.iter().map(|cell| resolver.cell_borders(cell));
+        self.each_cell(|cell| cell.collapsed_borders = cell_borders.next());
+    }
+
     /// Assign height for table flow.
     ///
     /// TODO(#2014, pcwalton): This probably doesn't handle margin collapse right.
@@ -127,6 +232,28 @@ impl TableFlow {
     #[inline(always)]
     fn assign_height_table_base(&mut self, layout_context: &mut LayoutContext) {
         self.block_flow.assign_height_block_base(layout_context, MarginsMayNotCollapse);
+
+        // Put the vertical `border-spacing` above each row group or row and below the last one.
+        // The row groups put it between their rows themselves.
+        let spacing = border_spacing(self.block_flow.fragment.style()).vertical;
+        if spacing == Au(0) {
+            return
+        }
+        let mut offset = Au(0);
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_table_rowgroup() || kid.is_table_row() {
+                offset = offset + spacing
+            }
+            let kid_base = flow::mut_base(kid);
+            kid_base.position.start.b = kid_base.position.start.b + offset;
+        }
+        if offset != Au(0) {
+            offset = offset + spacing
+        }
+        self.block_flow.fragment.border_box.size.block =
+            self.block_flow.fragment.border_box.size.block + offset;
+        self.block_flow.base.position.size.block =
+            self.block_flow.base.position.size.block + offset;
     }
 
     pub fn build_display_list_table(&mut self, layout_context: &LayoutContext) {
@@ -165,6 +292,11 @@ impl Flow for TableFlow {
     /// The maximum min/pref widths of each column are set from the rows for the automatic
     /// table layout calculation.
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
+        // A reused flow still has the columns from the last layout.
+        self.col_widths = vec!();
+        self.col_min_widths = vec!();
+        self.col_pref_widths = vec!();
+
         let mut min_width = Au(0);
         let mut pref_width = Au(0);
         let mut did_first_row = false;
@@ -228,9 +360,11 @@ impl Flow for TableFlow {
                 }
             }
         }
-        self.block_flow.base.intrinsic_widths.minimum_width = min_width;
+        self.resolve_collapsed_borders();
+        let non_column_width = self.non_column_width();
+        self.block_flow.base.intrinsic_widths.minimum_width = min_width + non_column_width;
         self.block_flow.base.intrinsic_widths.preferred_width =
-            geometry::max(min_width, pref_width);
+            geometry::max(min_width, pref_width) + non_column_width;
     }
 
     /// Recursively (top-down) determines the actual width of child contexts and fragments. When
@@ -239,7 +373,7 @@ impl Flow for TableFlow {
         debug!("assign_widths({}): assigning width for flow", "table");
 
         // The position was set to the containing block by the flow's parent.
-        let containing_block_width = self.block_flow.base.position.size.width;
+        let containing_block_width = self.block_flow.base.position.size.inline;
 
         let mut num_unspecified_widths = 0;
         let mut total_column_width = Au::new(0);
@@ -254,22 +388,35 @@ impl Flow for TableFlow {
         let width_computer = InternalTable;
         width_computer.compute_used_width(&mut self.block_flow, ctx, containing_block_width);
 
-        let left_content_edge = self.block_flow.fragment.border_padding.left;
-        let padding_and_borders = self.block_flow.fragment.border_padding.horizontal();
-        let content_width = self.block_flow.fragment.border_box.size.width - padding_and_borders;
+        // In the collapsing border model, the table has no padding and its borders are the outer
+        // halves of the collapsed borders of the cells along its edges.
+        if self.block_flow.fragment.style().get_inheritedtable().border_collapse ==
+                border_collapse::collapse {
+            self.block_flow.fragment.border_padding = self.collapsed_outer_border;
+        }
+
+        let left_content_edge = self.block_flow.fragment.border_padding.inline_start;
+        let padding_and_borders = self.block_flow.fragment.border_padding.inline_start_end();
+        let content_width = self.block_flow.fragment.border_box.size.inline - padding_and_borders;
 
+        // The specified column widths are kept, so that they can be distributed again if only the
+        // width of the table changes.
+        let mut col_widths = self.col_widths.clone();
         match self.table_layout {
             FixedLayout => {
                 // In fixed table layout, we distribute extra space among the unspecified columns if there are
                 // any, or among all the columns if all are specified.
+                let spacing = border_spacing(self.block_flow.fragment.style()).horizontal *
+                    Au::new(col_widths.len() as i32 + 1);
+                let content_width = content_width - spacing;
                 if (total_column_width < content_width) && (num_unspecified_widths == 0) {
                     let ratio = content_width.to_f64().unwrap() / total_column_width.to_f64().unwrap();
-                    for col_width in self.col_widths.mut_iter() {
+                    for col_width in col_widths.mut_iter() {
                         *col_width = (*col_width).scale_by(ratio);
                     }
                 } else if num_unspecified_widths != 0 {
                     let extra_column_width = (content_width - total_column_width) / Au::new(num_unspecified_widths);
-                    for col_width in self.col_widths.mut_iter() {
+                    for col_width in col_widths.mut_iter() {
                         if *col_width == Au(0) {
                             *col_width = extra_column_width;
                         }
@@ -279,7 +426,7 @@ impl Flow for TableFlow {
             _ => {}
         }
 
-        self.block_flow.propagate_assigned_width_to_children(left_content_edge, content_width, Some(self.col_widths.clone()));
+        self.block_flow.propagate_assigned_width_to_children(left_content_edge, content_width, Some(col_widths));
     }
 
     fn assign_height(&mut self, ctx: &mut LayoutContext) {
@@ -290,6 +437,10 @@ impl Flow for TableFlow {
     fn compute_absolute_position(&mut self) {
         self.block_flow.compute_absolute_position()
     }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        self.block_flow.iterate_through_fragments(iterator)
+    }
 }
 
 impl fmt::Show for TableFlow {
@@ -322,3 +473,644 @@ impl WidthAndMarginsComputer for InternalTable {
         WidthConstraintSolution::new(input.available_width, Au::new(0), Au::new(0))
     }
 }
+
+/// The specified and intrinsic widths of a table cell, along with the number of rows and columns
+/// it spans. These are collected by table rows during width bubbling.
+#[deriving(Clone)]
+pub struct CellIntrinsicWidths {
+    /// The number of columns the cell spans.
+    pub column_span: uint,
+
+    /// The number of rows the cell spans, or zero if it spans the rest of its row group.
+    pub row_span: uint,
+
+    /// The specified width of the cell, or zero if it is `auto`.
+    pub specified_width: Au,
+
+    /// The minimum width of the cell.
+    pub minimum_width: Au,
+
+    /// The preferred width of the cell.
+    pub preferred_width: Au,
+}
+
+/// Places the cells of consecutive table rows into the table grid, taking `colspan` and `rowspan`
+/// into account, and computes the widths of each column from the cells that occupy it.
+pub struct TableGrid {
+    /// The specified column widths from the first row, for fixed table layout.
+    pub col_widths: Vec<Au>,
+
+    /// The minimum width of each column.
+    pub col_min_widths: Vec<Au>,
+
+    /// The preferred width of each column.
+    pub col_pref_widths: Vec<Au>,
+
+    /// For each column, the number of rows (including the current one) that are still occupied by
+    /// a cell from a previous row. `uint::MAX` means the rest of the row group.
+    occupied_rows: Vec<uint>,
+
+    /// The cells that span more than one column, along with their first column. Their widths are
+    /// distributed over the columns they span once all rows have been added.
+    spanning_cells: Vec<(uint, CellIntrinsicWidths)>,
+
+    /// The number of rows added so far.
+    row_count: uint,
+}
+
+impl TableGrid {
+    pub fn new() -> TableGrid {
+        TableGrid {
+            col_widths: vec!(),
+            col_min_widths: vec!(),
+            col_pref_widths: vec!(),
+            occupied_rows: vec!(),
+            spanning_cells: vec!(),
+            row_count: 0,
+        }
+    }
+
+    fn ensure_column_count(&mut self, count: uint) {
+        while self.col_widths.len() < count {
+            self.col_widths.push(Au(0));
+            self.col_min_widths.push(Au(0));
+            self.col_pref_widths.push(Au(0));
+            self.occupied_rows.push(0);
+        }
+    }
+
+    /// Places the cells of the given row into the grid after the rows added so far, and records
+    /// the index of the first column of each cell in the cell.
+    pub fn add_row(&mut self, row: &mut TableRowFlow) {
+        let mut column = 0;
+        for (kid, cell) in row.block_flow.base.child_iter().zip(row.cell_intrinsic_widths.iter()) {
+            // Skip the columns occupied by cells spanning down from previous rows.
+            while column < self.occupied_rows.len() && *self.occupied_rows.get(column) > 0 {
+                column += 1
+            }
+            kid.as_table_cell().column_index = column;
+
+            let end = column + cell.column_span;
+            self.ensure_column_count(end);
+            let row_span = if cell.row_span == 0 {
+                uint::MAX
+            } else {
+                cell.row_span
+            };
+            for i in range(column, end) {
+                *self.occupied_rows.get_mut(i) = row_span;
+            }
+
+            // Fixed table layout takes the column widths from the first row only.
+            if self.row_count == 0 {
+                let specified_width = cell.specified_width / Au::new(cell.column_span as i32);
+                for i in range(column, end) {
+                    *self.col_widths.get_mut(i) = specified_width;
+                }
+            }
+
+            if cell.column_span == 1 {
+                let min_width = self.col_min_widths.get_mut(column);
+                *min_width = geometry::max(*min_width, cell.minimum_width);
+                let pref_width = self.col_pref_widths.get_mut(column);
+                *pref_width = geometry::max(*pref_width, cell.preferred_width);
+            } else {
+                self.spanning_cells.push((column, cell.clone()));
+            }
+            column = end;
+        }
+
+        for occupied_rows in self.occupied_rows.mut_iter() {
+            if *occupied_rows > 0 && *occupied_rows != uint::MAX {
+                *occupied_rows -= 1
+            }
+        }
+        self.row_count += 1;
+    }
+
+    /// Distributes the widths of cells spanning several columns over those columns, per CSS 2.1
+    /// § 17.5.2.2, and returns the sums of the minimum and preferred column widths.
+    pub fn finish(&mut self) -> (Au, Au) {
+        // Narrow spanning cells are distributed first so that wider ones see their effect.
+        self.spanning_cells.sort_by(|&(_, ref a), &(_, ref b)| a.column_span.cmp(&b.column_span));
+        for &(start, ref cell) in self.spanning_cells.iter() {
+            let end = start + cell.column_span;
+            distribute_spanning_width(self.col_min_widths.mut_slice(start, end),
+                                      self.col_pref_widths.slice(start, end),
+                                      cell.minimum_width);
+            let weights: Vec<Au> = self.col_pref_widths.slice(start, end).iter()
+                                                                         .map(|&x| x)
+                                                                         .collect();
+            distribute_spanning_width(self.col_pref_widths.mut_slice(start, end),
+                                      weights.as_slice(),
+                                      cell.preferred_width);
+        }
+
+        let mut min_width = Au(0);
+        let mut pref_width = Au(0);
+        for (min, pref) in self.col_min_widths.iter().zip(self.col_pref_widths.mut_iter()) {
+            *pref = geometry::max(*min, *pref);
+            min_width = min_width + *min;
+            pref_width = pref_width + *pref;
+        }
+        (min_width, pref_width)
+    }
+}
+
+/// Widens the given columns so that together they are at least `width` wide. The extra width is
+/// distributed in proportion to `weights`, or evenly if all of the weights are zero.
+fn distribute_spanning_width(widths: &mut [Au], weights: &[Au], width: Au) {
+    let current_width = widths.iter().fold(Au(0), |sum, &x| sum + x);
+    if width <= current_width {
+        return
+    }
+    let extra_width = width - current_width;
+    let total_weight = weights.iter().fold(Au(0), |sum, &x| sum + x);
+    let column_count = widths.len();
+    for (column_width, &weight) in widths.mut_iter().zip(weights.iter()) {
+        *column_width = *column_width + if total_weight > Au(0) {
+            extra_width.scale_by(weight.to_f64().unwrap() / total_weight.to_f64().unwrap())
+        } else {
+            extra_width / Au::new(column_count as i32)
+        }
+    }
+}
+
+/// Determines the used column widths for automatic table layout, given the minimum and preferred
+/// widths of the columns and the width available to them, per CSS 2.1 § 17.5.2.2.
+///
+/// Columns never get narrower than their minimum widths. If there is room for more, each column
+/// grows toward its preferred width in proportion to how far it is from it; any width left over
+/// after that is distributed in proportion to the preferred widths.
+pub fn compute_auto_column_widths(col_min_widths: &[Au], col_pref_widths: &[Au], width: Au)
+                                  -> Vec<Au> {
+    let min_width = col_min_widths.iter().fold(Au(0), |sum, &x| sum + x);
+    let pref_width = col_pref_widths.iter().fold(Au(0), |sum, &x| sum + x);
+    if width <= min_width {
+        return col_min_widths.iter().map(|&x| x).collect()
+    }
+    if width <= pref_width {
+        let ratio = (width - min_width).to_f64().unwrap() /
+            (pref_width - min_width).to_f64().unwrap();
+        return col_min_widths.iter().zip(col_pref_widths.iter()).map(|(&min, &pref)| {
+            min + (pref - min).scale_by(ratio)
+        }).collect()
+    }
+    let mut col_widths: Vec<Au> = col_pref_widths.iter().map(|&x| x).collect();
+    distribute_spanning_width(col_widths.as_mut_slice(), col_pref_widths, width);
+    col_widths
+}
+
+/// Returns the `border-spacing` between the cells of a table with the given style. There is no
+/// spacing in the collapsing border model.
+pub fn border_spacing(style: &ComputedValues) -> border_spacing::T {
+    let table_style = style.get_inheritedtable();
+    match table_style.border_collapse {
+        border_collapse::separate => table_style.border_spacing,
+        border_collapse::collapse => {
+            border_spacing::T {
+                horizontal: Au(0),
+                vertical: Au(0),
+            }
+        }
+    }
+}
+
+/// The side of a box a border is on.
+#[deriving(Clone, PartialEq)]
+pub enum BorderSide {
+    TopSide,
+    RightSide,
+    BottomSide,
+    LeftSide,
+}
+
+/// The kind of table box a border comes from. When two borders conflict and are equally wide and
+/// have equally strong styles, the one from the box later in this list wins, per CSS 2.1
+/// § 17.6.2.1.
+#[deriving(Clone, PartialEq, PartialOrd)]
+pub enum CollapsedBorderProvenance {
+    FromTable,
+    FromColumnGroup,
+    FromColumn,
+    FromRowGroup,
+    FromRow,
+    FromCell,
+}
+
+/// A border in the collapsing border model: the winner of the conflict between the borders of
+/// all of the table boxes that meet at an edge.
+#[deriving(Clone)]
+pub struct CollapsedBorder {
+    /// The style of the border.
+    pub style: border_style::T,
+
+    /// The width of the border.
+    pub width: Au,
+
+    /// The color of the border.
+    pub color: RGBA,
+
+    /// The kind of box the border comes from.
+    pub provenance: CollapsedBorderProvenance,
+}
+
+impl CollapsedBorder {
+    /// Returns a border that loses every conflict.
+    pub fn new() -> CollapsedBorder {
+        CollapsedBorder {
+            style: border_style::none,
+            width: Au(0),
+            color: RGBA {
+                red: 0.0,
+                green: 0.0,
+                blue: 0.0,
+                alpha: 0.0,
+            },
+            provenance: FromTable,
+        }
+    }
+
+    /// Returns the border on the given side of a box with the given style.
+    pub fn from_style(style: &ComputedValues,
+                      side: BorderSide,
+                      provenance: CollapsedBorderProvenance)
+                      -> CollapsedBorder {
+        let border = style.get_border();
+        let (border_style, width, color) = match side {
+            TopSide => (border.border_top_style, border.border_top_width, border.border_top_color),
+            RightSide => {
+                (border.border_right_style, border.border_right_width, border.border_right_color)
+            }
+            BottomSide => {
+                (border.border_bottom_style,
+                 border.border_bottom_width,
+                 border.border_bottom_color)
+            }
+            LeftSide => {
+                (border.border_left_style, border.border_left_width, border.border_left_color)
+            }
+        };
+        CollapsedBorder {
+            style: border_style,
+            width: width,
+            color: style.resolve_color(color),
+            provenance: provenance,
+        }
+    }
+
+    /// Returns whichever of this border and `other` wins the conflict between them, per CSS 2.1
+    /// § 17.6.2.1. This border wins if the two are indistinguishable.
+    pub fn winner(self, other: CollapsedBorder) -> CollapsedBorder {
+        if self.style == border_style::hidden {
+            return self
+        }
+        if other.style == border_style::hidden || self.style == border_style::none {
+            return other
+        }
+        if other.style == border_style::none {
+            return self
+        }
+        if self.width != other.width {
+            return if self.width > other.width { self } else { other }
+        }
+        let (priority, other_priority) = (style_priority(self.style), style_priority(other.style));
+        if priority != other_priority {
+            return if priority > other_priority { self } else { other }
+        }
+        if other.provenance > self.provenance {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// Returns the width of the part of this border that lies inside the cell it belongs to. The
+    /// rest lies inside the neighboring cell or the table's border area.
+    pub fn inner_width(&self) -> Au {
+        self.width / Au(2)
+    }
+
+    /// Returns the width of the part of this border that lies outside the cell it belongs to.
+    pub fn outer_width(&self) -> Au {
+        self.width - self.inner_width()
+    }
+}
+
+/// Returns how strongly a border style is preferred in border conflict resolution.
+fn style_priority(style: border_style::T) -> uint {
+    match style {
+        border_style::double => 8,
+        border_style::solid => 7,
+        border_style::dashed => 6,
+        border_style::dotted => 5,
+        border_style::ridge => 4,
+        border_style::outset => 3,
+        border_style::groove => 2,
+        border_style::inset => 1,
+        border_style::none | border_style::hidden => 0,
+    }
+}
+
+/// The resolved borders of a table cell in the collapsing border model.
+#[deriving(Clone)]
+pub struct CollapsedBorders {
+    pub top: CollapsedBorder,
+    pub right: CollapsedBorder,
+    pub bottom: CollapsedBorder,
+    pub left: CollapsedBorder,
+}
+
+impl CollapsedBorders {
+    /// Returns the widths of the borders.
+    pub fn widths(&self) -> SideOffsets2D<Au> {
+        SideOffsets2D::new(self.top.width, self.right.width, self.bottom.width, self.left.width)
+    }
+
+    /// Returns the widths of the parts of the borders that lie inside the cell.
+    pub fn inner_widths(&self) -> SideOffsets2D<Au> {
+        SideOffsets2D::new(self.top.inner_width(),
+                           self.right.inner_width(),
+                           self.bottom.inner_width(),
+                           self.left.inner_width())
+    }
+
+    /// Returns the widths of the parts of the borders that lie outside the cell.
+    pub fn outer_widths(&self) -> SideOffsets2D<Au> {
+        SideOffsets2D::new(self.top.outer_width(),
+                           self.right.outer_width(),
+                           self.bottom.outer_width(),
+                           self.left.outer_width())
+    }
+}
+
+/// The styles of a column or row, and of the column group or row group it belongs to, for border
+/// conflict resolution.
+struct TrackBorderStyles {
+    style: Option<Arc<ComputedValues>>,
+    group_style: Option<Arc<ComputedValues>>,
+    first_in_group: bool,
+    last_in_group: bool,
+}
+
+impl TrackBorderStyles {
+    fn new(style: Option<Arc<ComputedValues>>, group_style: Option<Arc<ComputedValues>>)
+           -> TrackBorderStyles {
+        TrackBorderStyles {
+            style: style,
+            group_style: group_style,
+            first_in_group: false,
+            last_in_group: false,
+        }
+    }
+}
+
+/// Marks the first and last of the given columns or rows, which make up one group.
+fn mark_group_ends(tracks: &mut [TrackBorderStyles]) {
+    let count = tracks.len();
+    if count > 0 {
+        tracks[0].first_in_group = true;
+        tracks[count - 1].last_in_group = true;
+    }
+}
+
+/// The style and position in the table grid of a cell, for border conflict resolution.
+struct CellBorderStyles {
+    style: Arc<ComputedValues>,
+    row: uint,
+    column: uint,
+    row_span: uint,
+    column_span: uint,
+}
+
+/// Appends the styles of the given row and of its cells.
+fn add_row_border_styles(row: &mut TableRowFlow,
+                         group_style: Option<Arc<ComputedValues>>,
+                         rows: &mut Vec<TrackBorderStyles>,
+                         cells: &mut Vec<CellBorderStyles>) {
+    let row_index = rows.len();
+    rows.push(TrackBorderStyles::new(Some(row.block_flow.fragment.style.clone()), group_style));
+    for kid in row.block_flow.base.child_iter() {
+        let cell = kid.as_table_cell();
+        cells.push(CellBorderStyles {
+            style: cell.block_flow.fragment.style.clone(),
+            row: row_index,
+            column: cell.column_index,
+            row_span: cell.row_span,
+            column_span: cell.column_span,
+        })
+    }
+}
+
+/// Makes the given cells, which belong to one row group, span no further than the end of it.
+fn clamp_row_spans(cells: &mut [CellBorderStyles], row_count: uint) {
+    for cell in cells.mut_iter() {
+        if cell.row_span == 0 || cell.row + cell.row_span > row_count {
+            cell.row_span = row_count - cell.row
+        }
+    }
+}
+
+/// Resolves the borders along the lines of the table grid in the collapsing border model.
+struct CollapsedBorderResolver {
+    table_style: Arc<ComputedValues>,
+    columns: Vec<TrackBorderStyles>,
+    rows: Vec<TrackBorderStyles>,
+    cells: Vec<CellBorderStyles>,
+
+    /// The index of the cell occupying each slot of the grid, row by row.
+    grid: Vec<Option<uint>>,
+
+    /// The number of columns in the grid.
+    column_count: uint,
+}
+
+impl CollapsedBorderResolver {
+    fn new(table_style: Arc<ComputedValues>,
+           columns: Vec<TrackBorderStyles>,
+           rows: Vec<TrackBorderStyles>,
+           cells: Vec<CellBorderStyles>)
+           -> CollapsedBorderResolver {
+        let column_count = cells.iter().fold(columns.len(), |count, cell| {
+            cmp::max(count, cell.column + cell.column_span)
+        });
+        let mut grid = Vec::from_elem(rows.len() * column_count, None);
+        for (index, cell) in cells.iter().enumerate() {
+            for row in range(cell.row, cell.row + cell.row_span) {
+                for column in range(cell.column, cell.column + cell.column_span) {
+                    *grid.get_mut(row * column_count + column) = Some(index)
+                }
+            }
+        }
+        CollapsedBorderResolver {
+            table_style: table_style,
+            columns: columns,
+            rows: rows,
+            cells: cells,
+            grid: grid,
+            column_count: column_count,
+        }
+    }
+
+    fn cell_at<'a>(&'a self, row: uint, column: uint) -> Option<&'a CellBorderStyles> {
+        if row >= self.rows.len() || column >= self.column_count {
+            return None
+        }
+        match *self.grid.get(row * self.column_count + column) {
+            Some(index) => Some(self.cells.get(index)),
+            None => None,
+        }
+    }
+
+    /// Resolves the border on the horizontal grid line above the given row, or below the last
+    /// row if `row` is the number of rows, in the given column.
+    fn horizontal_edge(&self, row: uint, column: uint) -> CollapsedBorder {
+        let row_count = self.rows.len();
+        let mut border = CollapsedBorder::new();
+        if row > 0 {
+            match self.cell_at(row - 1, column) {
+                Some(cell) => {
+                    border = border.winner(CollapsedBorder::from_style(&*cell.style,
+                                                                       BottomSide,
+                                                                       FromCell))
+                }
+                None => {}
+            }
+        }
+        match self.cell_at(row, column) {
+            Some(cell) => {
+                border = border.winner(CollapsedBorder::from_style(&*cell.style,
+                                                                   TopSide,
+                                                                   FromCell))
+            }
+            None => {}
+        }
+        if row > 0 {
+            let above = self.rows.get(row - 1);
+            border = contest(border, &above.style, BottomSide, FromRow);
+            if row == row_count || above.last_in_group {
+                border = contest(border, &above.group_style, BottomSide, FromRowGroup);
+            }
+        }
+        if row < row_count {
+            let below = self.rows.get(row);
+            border = contest(border, &below.style, TopSide, FromRow);
+            if row == 0 || below.first_in_group {
+                border = contest(border, &below.group_style, TopSide, FromRowGroup);
+            }
+        }
+        if row == 0 || row == row_count {
+            let side = if row == 0 { TopSide } else { BottomSide };
+            if column < self.columns.len() {
+                let track = self.columns.get(column);
+                border = contest(border, &track.style, side, FromColumn);
+                border = contest(border, &track.group_style, side, FromColumnGroup);
+            }
+            border = border.winner(CollapsedBorder::from_style(&*self.table_style,
+                                                               side,
+                                                               FromTable));
+        }
+        border
+    }
+
+    /// Resolves the border on the vertical grid line to the left of the given column, or to the
+    /// right of the last column if `column` is the number of columns, in the given row.
+    fn vertical_edge(&self, row: uint, column: uint) -> CollapsedBorder {
+        let mut border = CollapsedBorder::new();
+        if column > 0 {
+            match self.cell_at(row, column - 1) {
+                Some(cell) => {
+                    border = border.winner(CollapsedBorder::from_style(&*cell.style,
+                                                                       RightSide,
+                                                                       FromCell))
+                }
+                None => {}
+            }
+        }
+        match self.cell_at(row, column) {
+            Some(cell) => {
+                border = border.winner(CollapsedBorder::from_style(&*cell.style,
+                                                                   LeftSide,
+                                                                   FromCell))
+            }
+            None => {}
+        }
+        if column > 0 && column - 1 < self.columns.len() {
+            let left = self.columns.get(column - 1);
+            border = contest(border, &left.style, RightSide, FromColumn);
+            if left.last_in_group {
+                border = contest(border, &left.group_style, RightSide, FromColumnGroup);
+            }
+        }
+        if column < self.columns.len() {
+            let right = self.columns.get(column);
+            border = contest(border, &right.style, LeftSide, FromColumn);
+            if right.first_in_group {
+                border = contest(border, &right.group_style, LeftSide, FromColumnGroup);
+            }
+        }
+        if column == 0 || column == self.column_count {
+            let side = if column == 0 { LeftSide } else { RightSide };
+            if row < self.rows.len() {
+                let track = self.rows.get(row);
+                border = contest(border, &track.style, side, FromRow);
+                border = contest(border, &track.group_style, side, FromRowGroup);
+            }
+            border = border.winner(CollapsedBorder::from_style(&*self.table_style,
+                                                               side,
+                                                               FromTable));
+        }
+        border
+    }
+
+    /// Resolves the borders of the given cell. We paint a single border on each side of a cell,
+    /// so a side along which several grid lines meet the cell gets the strongest of their
+    /// borders.
+    fn cell_borders(&self, cell: &CellBorderStyles) -> CollapsedBorders {
+        let (row_end, column_end) = (cell.row + cell.row_span, cell.column + cell.column_span);
+        CollapsedBorders {
+            top: range(cell.column, column_end).fold(CollapsedBorder::new(), |border, column| {
+                border.winner(self.horizontal_edge(cell.row, column))
+            }),
+            right: range(cell.row, row_end).fold(CollapsedBorder::new(), |border, row| {
+                border.winner(self.vertical_edge(row, column_end))
+            }),
+            bottom: range(cell.column, column_end).fold(CollapsedBorder::new(), |border, column| {
+                border.winner(self.horizontal_edge(row_end, column))
+            }),
+            left: range(cell.row, row_end).fold(CollapsedBorder::new(), |border, row| {
+                border.winner(self.vertical_edge(row, cell.column))
+            }),
+        }
+    }
+
+    /// Returns the widths of the parts of the outermost borders that lie outside the cells. Per
+    /// CSS 2.1 § 17.6.2, the left and right ones are taken from the first row.
+    fn outer_border_widths(&self) -> SideOffsets2D<Au> {
+        let row_count = self.rows.len();
+        let (top, bottom) = range(0, self.column_count).fold((Au(0), Au(0)), |(top, bottom), i| {
+            (geometry::max(top, self.horizontal_edge(0, i).outer_width()),
+             geometry::max(bottom, self.horizontal_edge(row_count, i).outer_width()))
+        });
+        if row_count == 0 {
+            return SideOffsets2D::new(top, Au(0), bottom, Au(0))
+        }
+        SideOffsets2D::new(top,
+                           self.vertical_edge(0, self.column_count).outer_width(),
+                           bottom,
+                           self.vertical_edge(0, 0).outer_width())
+    }
+}
+
+/// Returns the winner of the conflict between the given border and the border on the given side
+/// of a box with the given style, if there is such a box.
+fn contest(border: CollapsedBorder,
+           style: &Option<Arc<ComputedValues>>,
+           side: BorderSide,
+           provenance: CollapsedBorderProvenance)
+           -> CollapsedBorder {
+    match *style {
+        Some(ref style) => border.winner(CollapsedBorder::from_style(&**style, side, provenance)),
+        None => border,
+    }
+}
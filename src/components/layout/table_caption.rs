@@ -10,6 +10,7 @@ use block::BlockFlow;
 use construct::FlowConstructor;
 use context::LayoutContext;
 use flow::{TableCaptionFlowClass, FlowClass, Flow};
+use fragment::Fragment;
 use wrapper::ThreadSafeLayoutNode;
 
 use std::fmt;
@@ -64,6 +65,10 @@ impl Flow for TableCaptionFlow {
     fn compute_absolute_position(&mut self) {
         self.block_flow.compute_absolute_position()
     }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        self.block_flow.iterate_through_fragments(iterator)
+    }
 }
 
 impl fmt::Show for TableCaptionFlow {
@@ -8,25 +8,84 @@
 
 use block::{BlockFlow, MarginsMayNotCollapse, WidthAndMarginsComputer};
 use context::LayoutContext;
-use flow::{TableCellFlowClass, FlowClass, Flow};
+use flow::{InlineFlowClass, TableCellFlowClass, FlowClass, Flow};
+use flow;
 use fragment::Fragment;
 use model::{MaybeAuto};
-use table::InternalTable;
-use wrapper::ThreadSafeLayoutNode;
+use model;
+use table::{CollapsedBorders, InternalTable};
+use util::ToGfxColor;
+use wrapper::{ThreadSafeLayoutNode, TLayoutNode};
 
+use geom::{Point2D, Rect, SideOffsets2D, Size2D};
+use gfx::display_list::{BaseDisplayItem, BlockLevel, BorderDisplayItem, BorderDisplayItemClass};
+use gfx::display_list::{DisplayList, StackingLevel};
 use servo_util::geometry::Au;
+use servo_util::geometry;
+use servo_util::namespace;
+use std::cmp;
 use std::fmt;
+use std::num::Zero;
+use std::from_str::FromStr;
+use style::computed_values::{empty_cells, vertical_align};
+
+/// The largest value of the `colspan` attribute that is honored, per HTML5 § 4.9.11.
+static MAX_COLUMN_SPAN: uint = 1000;
+
+/// The largest value of the `rowspan` attribute that is honored, per HTML5 § 4.9.11.
+static MAX_ROW_SPAN: uint = 65534;
 
 /// A table formatting context.
 pub struct TableCellFlow {
     /// Data common to all flows.
     pub block_flow: BlockFlow,
+
+    /// The number of columns this cell spans, from the `colspan` attribute.
+    pub column_span: uint,
+
+    /// The number of rows this cell spans, from the `rowspan` attribute. Zero means that the cell
+    /// spans all of the remaining rows of its row group.
+    pub row_span: uint,
+
+    /// The index of the first column this cell occupies in the table grid. This is assigned by
+    /// the row group (or row) when the cells are placed into the grid.
+    pub column_index: uint,
+
+    /// The height of the border box of this cell as determined by its contents, before it was
+    /// stretched to the height of the row(s) it spans.
+    pub content_box_height: Au,
+
+    /// The distance the contents of this cell have been moved down by `vertical-align`.
+    pub vertical_align_offset: Au,
+
+    /// The borders of this cell as resolved by the table in the collapsing border model, or
+    /// `None` in the separated borders model.
+    pub collapsed_borders: Option<CollapsedBorders>,
 }
 
 impl TableCellFlow {
     pub fn from_node_and_fragment(node: &ThreadSafeLayoutNode, fragment: Fragment) -> TableCellFlow {
+        let mut flow = TableCellFlow::anonymous_from_node_and_fragment(node, fragment);
+        if node.node_is_element() {
+            flow.column_span = cmp::min(cmp::max(span_attribute(node, "colspan").unwrap_or(1), 1),
+                                        MAX_COLUMN_SPAN);
+            flow.row_span = cmp::min(span_attribute(node, "rowspan").unwrap_or(1), MAX_ROW_SPAN);
+        }
+        flow
+    }
+
+    /// Creates an anonymous table cell, which always spans a single row and column. `node` is the
+    /// node of the parent flow the cell is generated for.
+    pub fn anonymous_from_node_and_fragment(node: &ThreadSafeLayoutNode, fragment: Fragment)
+                                            -> TableCellFlow {
         TableCellFlow {
-            block_flow: BlockFlow::from_node_and_fragment(node, fragment)
+            block_flow: BlockFlow::from_node_and_fragment(node, fragment),
+            column_span: 1,
+            row_span: 1,
+            column_index: 0,
+            content_box_height: Au(0),
+            vertical_align_offset: Au(0),
+            collapsed_borders: None,
         }
     }
 
@@ -49,9 +108,118 @@ impl TableCellFlow {
         self.block_flow.assign_height_block_base(layout_context, MarginsMayNotCollapse)
     }
 
+    /// Returns the distance from the top of the border box of this cell to its baseline, per
+    /// CSS 2.1 § 17.5.3: the baseline of the first line box in the cell, or the bottom of the
+    /// content edge if there is none.
+    pub fn baseline_offset(&mut self) -> Au {
+        match self.block_flow.baseline_offset_of_first_line_box() {
+            Some(baseline_offset) => baseline_offset,
+            None => {
+                self.content_box_height - self.block_flow.fragment.border_padding.block_end
+            }
+        }
+    }
+
+    /// Stretches this cell to the given height, which is the height of the row(s) it spans, and
+    /// moves its contents according to `vertical-align`. `row_baseline` is the distance from the
+    /// top of the row to the baseline shared by the baseline-aligned cells of the row.
+    pub fn set_height_and_align_contents(&mut self, height: Au, row_baseline: Au) {
+        let free_space = geometry::max(height - self.content_box_height, Au(0));
+        let offset = match self.block_flow.fragment.style().get_box().vertical_align {
+            vertical_align::baseline => {
+                geometry::min(geometry::max(row_baseline - self.baseline_offset(), Au(0)),
+                              free_space)
+            }
+            vertical_align::middle => free_space.scale_by(0.5),
+            vertical_align::bottom => free_space,
+            _ => Au(0),
+        };
+
+        // Move the contents by the difference from the offset we applied last time, if any.
+        let delta = offset - self.vertical_align_offset;
+        if delta != Au(0) {
+            for kid in self.block_flow.base.child_iter() {
+                let kid_base = flow::mut_base(kid);
+                kid_base.position.start.b = kid_base.position.start.b + delta;
+            }
+        }
+        self.vertical_align_offset = offset;
+
+        self.block_flow.fragment.border_box.size.block = height;
+        self.block_flow.base.position.size.block = height;
+    }
+
     pub fn build_display_list_table_cell(&mut self, layout_context: &LayoutContext) {
         debug!("build_display_list_table: same process as block flow");
-        self.block_flow.build_display_list_block(layout_context)
+
+        // Per CSS 2.1 § 17.6.1.1, `empty-cells: hide` suppresses the borders and background of
+        // cells without content in the separated borders model.
+        if self.collapsed_borders.is_none() &&
+                self.block_flow.fragment.style().get_inheritedtable().empty_cells ==
+                    empty_cells::hide && self.is_empty() {
+            self.block_flow.base.display_list = DisplayList::new();
+            return
+        }
+
+        self.block_flow.build_display_list_block(layout_context);
+        self.build_display_list_for_collapsed_borders()
+    }
+
+    /// Returns true if this cell is empty per CSS 2.1 § 17.6.1.1: its only content, if any, is
+    /// whitespace that is collapsed away.
+    fn is_empty(&mut self) -> bool {
+        self.block_flow.base.child_iter().all(|kid| {
+            match kid.class() {
+                InlineFlowClass => {
+                    kid.as_inline().fragments.fragments.iter().all(|fragment| {
+                        fragment.is_collapsible_whitespace()
+                    })
+                }
+                _ => false,
+            }
+        })
+    }
+
+    /// Paints the borders of this cell in the collapsing border model. Each border is centered on
+    /// the grid line between this cell and its neighbor, which paints the same border.
+    fn build_display_list_for_collapsed_borders(&mut self) {
+        let borders = match self.collapsed_borders {
+            None => return,
+            Some(ref borders) => borders.clone(),
+        };
+        let widths = borders.widths();
+        if widths == Zero::zero() {
+            return
+        }
+
+        let writing_mode = self.block_flow.base.writing_mode;
+        let flow_size = self.block_flow.base.position.size.to_physical(writing_mode);
+        let border_box = self.block_flow
+                             .fragment
+                             .border_box
+                             .to_physical(writing_mode, flow_size)
+                             .translate(&self.block_flow.base.abs_position);
+        let outer_widths = borders.outer_widths();
+        let bounds = Rect(Point2D(border_box.origin.x - outer_widths.left,
+                                  border_box.origin.y - outer_widths.top),
+                          Size2D(border_box.size.width + outer_widths.horizontal(),
+                                 border_box.size.height + outer_widths.vertical()));
+        let border_display_item = box BorderDisplayItem {
+            base: BaseDisplayItem::new(bounds,
+                                       self.block_flow.fragment.node,
+                                       StackingLevel::from_background_and_border_level(
+                                           BlockLevel)),
+            border: widths,
+            color: SideOffsets2D::new(borders.top.color.to_gfx_color(),
+                                      borders.right.color.to_gfx_color(),
+                                      borders.bottom.color.to_gfx_color(),
+                                      borders.left.color.to_gfx_color()),
+            style: SideOffsets2D::new(borders.top.style,
+                                      borders.right.style,
+                                      borders.bottom.style,
+                                      borders.left.style),
+        };
+        self.block_flow.base.display_list.push(BorderDisplayItemClass(border_display_item))
     }
 }
 
@@ -71,8 +239,8 @@ impl Flow for TableCellFlow {
     /// Minimum/preferred widths set by this function are used in automatic table layout calculation.
     fn bubble_widths(&mut self, ctx: &mut LayoutContext) {
         self.block_flow.bubble_widths(ctx);
-        let specified_width = MaybeAuto::from_style(self.block_flow.fragment.style().get_box().width,
-                                                    Au::new(0)).specified_or_zero();
+        let style_width = self.block_flow.fragment.style().content_inline_size();
+        let specified_width = MaybeAuto::from_style(style_width, Au::new(0)).specified_or_zero();
         if self.block_flow.base.intrinsic_widths.minimum_width < specified_width {
             self.block_flow.base.intrinsic_widths.minimum_width = specified_width;
         }
@@ -89,15 +257,26 @@ impl Flow for TableCellFlow {
         debug!("assign_widths({}): assigning width for flow", "table_cell");
 
         // The position was set to the column width by the parent flow, table row flow.
-        let containing_block_width = self.block_flow.base.position.size.width;
+        let containing_block_width = self.block_flow.base.position.size.inline;
 
         let width_computer = InternalTable;
         width_computer.compute_used_width(&mut self.block_flow, ctx, containing_block_width);
 
-        let left_content_edge = self.block_flow.fragment.border_box.origin.x +
-            self.block_flow.fragment.border_padding.left;
-        let padding_and_borders = self.block_flow.fragment.border_padding.horizontal();
-        let content_width = self.block_flow.fragment.border_box.size.width - padding_and_borders;
+        // In the collapsing border model, the cell's borders are the inner halves of the borders
+        // resolved by the table.
+        match self.collapsed_borders {
+            Some(ref borders) => {
+                let padding = model::padding_from_style(self.block_flow.fragment.style(),
+                                                        containing_block_width);
+                self.block_flow.fragment.border_padding = borders.inner_widths() + padding
+            }
+            None => {}
+        }
+
+        let left_content_edge = self.block_flow.fragment.border_box.start.i +
+            self.block_flow.fragment.border_padding.inline_start;
+        let padding_and_borders = self.block_flow.fragment.border_padding.inline_start_end();
+        let content_width = self.block_flow.fragment.border_box.size.inline - padding_and_borders;
 
         self.block_flow.propagate_assigned_width_to_children(left_content_edge,
                                                              content_width,
@@ -107,11 +286,26 @@ impl Flow for TableCellFlow {
     fn assign_height(&mut self, ctx: &mut LayoutContext) {
         debug!("assign_height: assigning height for table_cell");
         self.assign_height_table_cell_base(ctx);
+        self.content_box_height = self.block_flow.fragment.border_box.size.block;
+        self.vertical_align_offset = Au(0);
     }
 
     fn compute_absolute_position(&mut self) {
         self.block_flow.compute_absolute_position()
     }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        self.block_flow.iterate_through_fragments(iterator)
+    }
+}
+
+/// Reads a nonnegative integer from the given span attribute of a table cell element.
+fn span_attribute(node: &ThreadSafeLayoutNode, name: &str) -> Option<uint> {
+    let element = node.as_element();
+    element.get_attr(&namespace::Null, name).and_then(|string| {
+        let n: Option<uint> = FromStr::from_str(string.trim());
+        n
+    })
 }
 
 impl fmt::Show for TableCellFlow {
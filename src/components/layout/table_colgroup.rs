@@ -53,9 +53,11 @@ impl Flow for TableColGroupFlow {
     }
 
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
+        // A reused flow still has the widths from the last layout.
+        self.widths = vec!();
         for fragment in self.cols.iter() {
             // get the specified value from width property
-            let width = MaybeAuto::from_style(fragment.style().get_box().width,
+            let width = MaybeAuto::from_style(fragment.style().content_inline_size(),
                                               Au::new(0)).specified_or_zero();
 
             let span: int = match fragment.specific {
@@ -76,6 +78,12 @@ impl Flow for TableColGroupFlow {
     /// Table column do not have height.
     fn assign_height(&mut self, _ctx: &mut LayoutContext) {
     }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        for fragment in self.fragment.iter().chain(self.cols.iter()) {
+            iterator(fragment)
+        }
+    }
 }
 
 impl fmt::Show for TableColGroupFlow {
@@ -13,13 +13,14 @@ use context::LayoutContext;
 use flow::{TableRowFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use flow;
 use fragment::Fragment;
-use table::InternalTable;
+use table::{CellIntrinsicWidths, InternalTable, TableGrid};
 use model::{MaybeAuto, Specified, Auto};
 use wrapper::ThreadSafeLayoutNode;
 
 use servo_util::geometry::Au;
 use servo_util::geometry;
 use std::fmt;
+use style::computed_values::vertical_align;
 
 /// A table formatting context.
 pub struct TableRowFlow {
@@ -33,6 +34,12 @@ pub struct TableRowFlow {
 
     /// Column pref widths.
     pub col_pref_widths: Vec<Au>,
+
+    /// The spans and intrinsic widths of the cells of this row, in order.
+    pub cell_intrinsic_widths: Vec<CellIntrinsicWidths>,
+
+    /// The distance from the top of this row to the baseline of its baseline-aligned cells.
+    pub baseline: Au,
 }
 
 impl TableRowFlow {
@@ -44,6 +51,8 @@ impl TableRowFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
+            cell_intrinsic_widths: vec!(),
+            baseline: Au(0),
         }
     }
 
@@ -55,6 +64,8 @@ impl TableRowFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
+            cell_intrinsic_widths: vec!(),
+            baseline: Au(0),
         }
     }
 
@@ -80,51 +91,88 @@ impl TableRowFlow {
 
         let /* mut */ cur_y = top_offset;
 
-        // Per CSS 2.1 § 17.5.3, find max_y = max( computed `height`, minimum height of all cells )
-        let mut max_y = Au::new(0);
+        // Find the baseline shared by the baseline-aligned cells, per CSS 2.1 § 17.5.3.
+        let mut baseline = Au(0);
         for kid in self.block_flow.base.child_iter() {
             kid.assign_height_for_inorder_child_if_necessary(layout_context);
 
+            let cell = kid.as_table_cell();
+            if cell.fragment().style().get_box().vertical_align == vertical_align::baseline {
+                baseline = geometry::max(baseline, cell.baseline_offset());
+            }
+        }
+        self.baseline = baseline;
+
+        // Per CSS 2.1 § 17.5.3, find max_y = max( computed `height`, minimum height of all cells )
+        //
+        // Cells that span several rows are left out; the row group makes sure that the rows they
+        // span are tall enough for them.
+        let mut max_y = Au::new(0);
+        for kid in self.block_flow.base.child_iter() {
             {
-                let child_fragment = kid.as_table_cell().fragment();
-                // TODO: Percentage height
-                let child_specified_height = MaybeAuto::from_style(child_fragment.style().get_box().height,
-                                                                   Au::new(0)).specified_or_zero();
-                max_y =
-                    geometry::max(max_y,
-                                  child_specified_height + child_fragment.border_padding.vertical());
+                let cell = kid.as_table_cell();
+                if cell.row_span == 1 {
+                    let (child_specified_height, is_baseline_aligned) = {
+                        let child_fragment = cell.fragment();
+                        let child_style = child_fragment.style().get_box();
+                        // TODO: Percentage height
+                        let child_specified_height =
+                            MaybeAuto::from_style(child_style.height, Au::new(0))
+                                .specified_or_zero();
+                        (child_specified_height + child_fragment.border_padding.block_start_end(),
+                         child_style.vertical_align == vertical_align::baseline)
+                    };
+                    max_y = geometry::max(max_y, child_specified_height);
+
+                    // Baseline-aligned cells are moved down to the shared baseline, which may
+                    // make the row taller.
+                    let mut child_height = cell.content_box_height;
+                    if is_baseline_aligned {
+                        child_height = child_height + baseline - cell.baseline_offset();
+                    }
+                    max_y = geometry::max(max_y, child_height);
+                }
             }
             let child_node = flow::mut_base(kid);
-            child_node.position.origin.y = cur_y;
-            max_y = geometry::max(max_y, child_node.position.size.height);
+            child_node.position.start.b = cur_y;
         }
 
         let mut height = max_y;
         // TODO: Percentage height
-        height = match MaybeAuto::from_style(self.block_flow.fragment.style().get_box().height, Au(0)) {
+        let style_height = self.block_flow.fragment.style().content_block_size();
+        height = match MaybeAuto::from_style(style_height, Au(0)) {
             Auto => height,
             Specified(value) => geometry::max(value, height)
         };
         // cur_y = cur_y + height;
 
-        // Assign the height of own fragment
-        //
         // FIXME(pcwalton): Take `cur_y` into account.
+        self.set_height(height);
+
+        // Sticky rows get layers, like sticky blocks, so that the compositor can move them.
+        if self.block_flow.is_sticky() {
+            self.block_flow.base.flags.set_needs_layer(true)
+        }
+    }
+
+    /// Assigns the height of this row and of the cells in it that span only this row, and aligns
+    /// the contents of those cells vertically. Cells spanning several rows get at least this
+    /// height; the row group stretches them to the full height of the rows they span.
+    pub fn set_height(&mut self, height: Au) {
         let mut position = self.block_flow.fragment.border_box;
-        position.size.height = height;
+        position.size.block = height;
         self.block_flow.fragment.border_box = position;
-        self.block_flow.base.position.size.height = height;
+        self.block_flow.base.position.size.block = height;
 
-        // Assign the height of kid fragments, which is the same value as own height.
+        let baseline = self.baseline;
         for kid in self.block_flow.base.child_iter() {
-            {
-                let kid_fragment = kid.as_table_cell().mut_fragment();
-                let mut position = kid_fragment.border_box;
-                position.size.height = height;
-                kid_fragment.border_box = position;
-            }
-            let child_node = flow::mut_base(kid);
-            child_node.position.size.height = height;
+            let cell = kid.as_table_cell();
+            let cell_height = if cell.row_span == 1 {
+                height
+            } else {
+                geometry::max(height, cell.content_box_height)
+            };
+            cell.set_height_and_align_contents(cell_height, baseline);
         }
     }
 
@@ -166,27 +214,46 @@ impl Flow for TableRowFlow {
     /// Min/pref widths set by this function are used in automatic table layout calculation.
     /// The specified column widths of children cells are used in fixed table layout calculation.
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
-        let mut min_width = Au(0);
-        let mut pref_width = Au(0);
+        self.cell_intrinsic_widths = vec!();
         /* find the specified widths from child table-cell contexts */
         for kid in self.block_flow.base.child_iter() {
             assert!(kid.is_table_cell());
 
             // collect the specified column widths of cells. These are used in fixed table layout calculation.
-            {
-                let child_fragment = kid.as_table_cell().fragment();
-                let child_specified_width = MaybeAuto::from_style(child_fragment.style().get_box().width,
-                                                                  Au::new(0)).specified_or_zero();
-                self.col_widths.push(child_specified_width);
-            }
+            let (column_span, row_span, child_specified_width) = {
+                let cell = kid.as_table_cell();
+                let child_specified_width =
+                    MaybeAuto::from_style(cell.fragment().style().content_inline_size(), Au::new(0))
+                        .specified_or_zero();
+                (cell.column_span, cell.row_span, child_specified_width)
+            };
 
             // collect min_width & pref_width of children cells for automatic table layout calculation.
             let child_base = flow::mut_base(kid);
-            self.col_min_widths.push(child_base.intrinsic_widths.minimum_width);
-            self.col_pref_widths.push(child_base.intrinsic_widths.preferred_width);
-            min_width = min_width + child_base.intrinsic_widths.minimum_width;
-            pref_width = pref_width + child_base.intrinsic_widths.preferred_width;
+            self.cell_intrinsic_widths.push(CellIntrinsicWidths {
+                column_span: column_span,
+                row_span: row_span,
+                specified_width: child_specified_width,
+                minimum_width: child_base.intrinsic_widths.minimum_width,
+                preferred_width: child_base.intrinsic_widths.preferred_width,
+            });
         }
+
+        // Lay the cells out into columns as if this row were alone in the table. The row group,
+        // if any, places the cells again taking cells that span rows into account.
+        let mut grid = TableGrid::new();
+        grid.add_row(self);
+        let (min_width, pref_width) = grid.finish();
+        let TableGrid {
+            col_widths: col_widths,
+            col_min_widths: col_min_widths,
+            col_pref_widths: col_pref_widths,
+            ..
+        } = grid;
+        self.col_widths = col_widths;
+        self.col_min_widths = col_min_widths;
+        self.col_pref_widths = col_pref_widths;
+
         self.block_flow.base.intrinsic_widths.minimum_width = min_width;
         self.block_flow.base.intrinsic_widths.preferred_width = geometry::max(min_width,
                                                                               pref_width);
@@ -198,7 +265,7 @@ impl Flow for TableRowFlow {
         debug!("assign_widths({}): assigning width for flow", "table_row");
 
         // The position was set to the containing block by the flow's parent.
-        let containing_block_width = self.block_flow.base.position.size.width;
+        let containing_block_width = self.block_flow.base.position.size.inline;
         // FIXME: In case of border-collapse: collapse, left_content_edge should be border-left
         let left_content_edge = Au::new(0);
 
@@ -216,6 +283,10 @@ impl Flow for TableRowFlow {
     fn compute_absolute_position(&mut self) {
         self.block_flow.compute_absolute_position()
     }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        self.block_flow.iterate_through_fragments(iterator)
+    }
 }
 
 impl fmt::Show for TableRowFlow {
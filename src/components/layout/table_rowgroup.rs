@@ -13,11 +13,12 @@ use context::LayoutContext;
 use flow::{TableRowGroupFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use flow;
 use fragment::Fragment;
-use table::{InternalTable, TableFlow};
+use table::{InternalTable, TableGrid, border_spacing};
 use wrapper::ThreadSafeLayoutNode;
 
 use servo_util::geometry::Au;
 use servo_util::geometry;
+use std::cmp;
 use std::fmt;
 
 /// A table formatting context.
@@ -79,20 +80,77 @@ impl TableRowGroupFlow {
 
         let mut cur_y = top_offset;
 
+        // The table puts the vertical `border-spacing` above and below the row group; it goes
+        // between the rows here.
+        let spacing = border_spacing(self.block_flow.fragment.style()).vertical;
+
+        // Collect the heights of the rows and the cells that span several of them.
+        let mut row_heights = vec!();
+        let mut spanning_cells = vec!();
         for kid in self.block_flow.base.child_iter() {
             kid.assign_height_for_inorder_child_if_necessary(layout_context);
 
+            let row_index = row_heights.len();
+            row_heights.push(flow::base(kid).position.size.block);
+            for cell in kid.as_table_row().block_flow.base.child_iter() {
+                let cell = cell.as_table_cell();
+                if cell.row_span != 1 {
+                    spanning_cells.push((row_index, cell.row_span, cell.content_box_height));
+                }
+            }
+        }
+
+        // Make the rows spanned by each cell tall enough for it, distributing any extra height
+        // evenly among them. CSS 2.1 § 17.5.3 leaves the distribution up to us.
+        let row_count = row_heights.len();
+        for &(start, row_span, cell_height) in spanning_cells.iter() {
+            let end = span_end(start, row_span, row_count);
+            let spanned_height = spanned_height(row_heights.slice(start, end), spacing);
+            if cell_height > spanned_height {
+                let extra_height = (cell_height - spanned_height) /
+                    Au::new((end - start) as i32);
+                for row_height in row_heights.mut_slice(start, end).mut_iter() {
+                    *row_height = *row_height + extra_height
+                }
+            }
+        }
+
+        for (row_index, kid) in self.block_flow.base.child_iter().enumerate() {
+            let row_height = *row_heights.get(row_index);
+            if flow::base(kid).position.size.block != row_height {
+                kid.as_table_row().set_height(row_height)
+            }
+
+            // Stretch the cells that start in this row to the total height of the rows they span.
+            let baseline = kid.as_table_row().baseline;
+            for cell in kid.as_table_row().block_flow.base.child_iter() {
+                let cell = cell.as_table_cell();
+                if cell.row_span != 1 {
+                    let end = span_end(row_index, cell.row_span, row_count);
+                    let height = spanned_height(row_heights.slice(row_index, end), spacing);
+                    cell.set_height_and_align_contents(height, baseline)
+                }
+            }
+
+            if row_index > 0 {
+                cur_y = cur_y + spacing
+            }
             let child_node = flow::mut_base(kid);
-            child_node.position.origin.y = cur_y;
-            cur_y = cur_y + child_node.position.size.height;
+            child_node.position.start.b = cur_y;
+            cur_y = cur_y + child_node.position.size.block;
         }
 
         let height = cur_y - top_offset;
 
         let mut position = self.block_flow.fragment.border_box;
-        position.size.height = height;
+        position.size.block = height;
         self.block_flow.fragment.border_box = position;
-        self.block_flow.base.position.size.height = height;
+        self.block_flow.base.position.size.block = height;
+
+        // Sticky row groups get layers, like sticky blocks, so that the compositor can move them.
+        if self.block_flow.is_sticky() {
+            self.block_flow.base.flags.set_needs_layer(true)
+        }
     }
 
     pub fn build_display_list_table_rowgroup(&mut self, layout_context: &LayoutContext) {
@@ -132,41 +190,25 @@ impl Flow for TableRowGroupFlow {
     /// responsible for flowing.
     /// Min/pref widths set by this function are used in automatic table layout calculation.
     /// Also, this function finds the specified column widths from the first row.
-    /// Those are used in fixed table layout calculation
+    /// Those are used in fixed table layout calculation.
+    /// The cells of the rows are placed into columns here, taking `rowspan` into account.
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
-        let mut min_width = Au(0);
-        let mut pref_width = Au(0);
-
+        // Place the cells of all rows into the grid together, since cells may span rows.
+        let mut grid = TableGrid::new();
         for kid in self.block_flow.base.child_iter() {
             assert!(kid.is_table_row());
-
-            // calculate min_width & pref_width for automatic table layout calculation
-            // 'self.col_min_widths' collects the maximum value of cells' min-widths for each column.
-            // 'self.col_pref_widths' collects the maximum value of cells' pref-widths for each column.
-            if self.col_widths.is_empty() { // First Row
-                assert!(self.col_min_widths.is_empty() && self.col_pref_widths.is_empty());
-                // 'self.col_widths' collects the specified column widths from the first table-row for fixed table layout calculation.
-                self.col_widths = kid.col_widths().clone();
-                self.col_min_widths = kid.col_min_widths().clone();
-                self.col_pref_widths = kid.col_pref_widths().clone();
-            } else {
-                min_width = TableFlow::update_col_widths(&mut self.col_min_widths, kid.col_min_widths());
-                pref_width = TableFlow::update_col_widths(&mut self.col_pref_widths, kid.col_pref_widths());
-
-                // update the number of column widths from table-rows.
-                let num_cols = self.col_widths.len();
-                let num_child_cols = kid.col_min_widths().len();
-                for i in range(num_cols, num_child_cols) {
-                    self.col_widths.push(Au::new(0));
-                    let new_kid_min = *kid.col_min_widths().get(i);
-                    self.col_min_widths.push(*kid.col_min_widths().get(i));
-                    let new_kid_pref = *kid.col_pref_widths().get(i);
-                    self.col_pref_widths.push(*kid.col_pref_widths().get(i));
-                    min_width = min_width + new_kid_min;
-                    pref_width = pref_width + new_kid_pref;
-                }
-            }
+            grid.add_row(kid.as_table_row());
         }
+        let (min_width, pref_width) = grid.finish();
+        let TableGrid {
+            col_widths: col_widths,
+            col_min_widths: col_min_widths,
+            col_pref_widths: col_pref_widths,
+            ..
+        } = grid;
+        self.col_widths = col_widths;
+        self.col_min_widths = col_min_widths;
+        self.col_pref_widths = col_pref_widths;
 
         self.block_flow.base.intrinsic_widths.minimum_width = min_width;
         self.block_flow.base.intrinsic_widths.preferred_width = geometry::max(min_width,
@@ -179,7 +221,7 @@ impl Flow for TableRowGroupFlow {
         debug!("assign_widths({}): assigning width for flow", "table_rowgroup");
 
         // The position was set to the containing block by the flow's parent.
-        let containing_block_width = self.block_flow.base.position.size.width;
+        let containing_block_width = self.block_flow.base.position.size.inline;
         // FIXME: In case of border-collapse: collapse, left_content_edge should be border-left
         let left_content_edge = Au::new(0);
         let content_width = containing_block_width;
@@ -198,6 +240,26 @@ impl Flow for TableRowGroupFlow {
     fn compute_absolute_position(&mut self) {
         self.block_flow.compute_absolute_position()
     }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        self.block_flow.iterate_through_fragments(iterator)
+    }
+}
+
+/// Returns the index just past the last row spanned by a cell that starts in row `start` and has
+/// the given `rowspan`, clamped to the end of the row group.
+fn span_end(start: uint, row_span: uint, row_count: uint) -> uint {
+    if row_span == 0 || start + row_span > row_count {
+        row_count
+    } else {
+        start + row_span
+    }
+}
+
+/// Returns the total height of the given rows and of the vertical `border-spacing` between them.
+fn spanned_height(row_heights: &[Au], spacing: Au) -> Au {
+    row_heights.iter().fold(Au(0), |sum, &height| sum + height) +
+        spacing * Au::new(cmp::max(row_heights.len(), 1) as i32 - 1)
 }
 
 impl fmt::Show for TableRowGroupFlow {
@@ -14,12 +14,14 @@ use floats::FloatKind;
 use flow::{TableWrapperFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use fragment::Fragment;
 use model::{Specified, Auto, specified};
+use table::compute_auto_column_widths;
 use wrapper::ThreadSafeLayoutNode;
 
 use servo_util::geometry::Au;
 use servo_util::geometry;
 use std::fmt;
-use style::computed_values::table_layout;
+use std::num::Zero;
+use style::computed_values::{border_collapse, table_layout};
 
 pub enum TableLayout {
     FixedLayout,
@@ -130,7 +132,9 @@ impl Flow for TableWrapperFlow {
     any fragments it is responsible for flowing.  */
 
     fn bubble_widths(&mut self, ctx: &mut LayoutContext) {
-        // get column widths info from table flow
+        // get column widths info from table flow, replacing those from the last layout if this
+        // flow was reused
+        self.col_widths = vec!();
         for kid in self.block_flow.base.child_iter() {
             assert!(kid.is_table_caption() || kid.is_table());
 
@@ -156,17 +160,17 @@ impl Flow for TableWrapperFlow {
                });
 
         // The position was set to the containing block by the flow's parent.
-        let containing_block_width = self.block_flow.base.position.size.width;
+        let containing_block_width = self.block_flow.base.position.size.inline;
 
         let width_computer = TableWrapper;
         width_computer.compute_used_width_table_wrapper(self, ctx, containing_block_width);
 
-        let left_content_edge = self.block_flow.fragment.border_box.origin.x;
-        let content_width = self.block_flow.fragment.border_box.size.width;
+        let left_content_edge = self.block_flow.fragment.border_box.start.i;
+        let content_width = self.block_flow.fragment.border_box.size.inline;
 
         match self.table_layout {
             FixedLayout | _ if self.is_float() =>
-                self.block_flow.base.position.size.width = content_width,
+                self.block_flow.base.position.size.inline = content_width,
             _ => {}
         }
 
@@ -191,6 +195,10 @@ impl Flow for TableWrapperFlow {
     fn compute_absolute_position(&mut self) {
         self.block_flow.compute_absolute_position()
     }
+
+    fn iterate_through_fragments(&self, iterator: |&Fragment|) {
+        self.block_flow.iterate_through_fragments(iterator)
+    }
 }
 
 impl fmt::Show for TableWrapperFlow {
@@ -228,6 +236,24 @@ impl TableWrapper {
         let mut input = self.compute_width_constraint_inputs(&mut table_wrapper.block_flow,
                                                              parent_flow_width,
                                                              ctx);
+
+        // In the collapsing border model, the table's borders are painted by its cells and take
+        // up the space the table reserves for them, so the wrapper has none.
+        let border_model =
+            table_wrapper.block_flow.fragment.style().get_inheritedtable().border_collapse;
+        if border_model == border_collapse::collapse {
+            input.available_width = input.available_width +
+                table_wrapper.block_flow.fragment.border_padding.inline_start_end();
+            table_wrapper.block_flow.fragment.border_padding = Zero::zero();
+        }
+
+        let mut non_column_width = Au(0);
+        for kid in table_wrapper.block_flow.base.child_iter() {
+            if kid.is_table() {
+                non_column_width = kid.as_table().non_column_width();
+            }
+        }
+
         let computed_width = match table_wrapper.table_layout {
             FixedLayout => {
                 let fixed_cells_width = table_wrapper.col_widths.iter().fold(Au(0),
@@ -245,10 +271,17 @@ impl TableWrapper {
                                               input.available_width);
                 let border_left = style.get_border().border_left_width;
                 let border_right = style.get_border().border_right_width;
-                let padding_and_borders = padding_left + padding_right + border_left + border_right;
+                let padding_and_borders = match border_model {
+                    border_collapse::separate => {
+                        padding_left + padding_right + border_left + border_right
+                    }
+                    border_collapse::collapse => Au(0),
+                };
                 // Compare border-edge widths. Because fixed_cells_width indicates content-width,
-                // padding and border values are added to fixed_cells_width.
-                computed_width = geometry::max(fixed_cells_width + padding_and_borders, computed_width);
+                // padding, border, and border-spacing values are added to fixed_cells_width.
+                computed_width = geometry::max(fixed_cells_width + padding_and_borders +
+                                               non_column_width,
+                                               computed_width);
                 computed_width
             },
             AutoLayout => {
@@ -269,44 +302,29 @@ impl TableWrapper {
                         col_pref_widths = kid.col_pref_widths();
                     }
                 }
-                // 'extra_width': difference between the calculated table width and minimum width
-                // required by all columns. It will be distributed over the columns.
-                let (width, extra_width) = match input.computed_width {
+                let width = match input.computed_width {
                     Auto => {
                         if input.available_width > geometry::max(cols_max, cap_min) {
-                            if cols_max > cap_min {
-                                table_wrapper.col_widths = col_pref_widths.clone();
-                                (cols_max, Au(0))
-                            } else {
-                                (cap_min, cap_min - cols_min)
-                            }
+                            geometry::max(cols_max, cap_min)
+                        } else if cols_min >= input.available_width && cols_min >= cap_min {
+                            cols_min
                         } else {
-                            let max = if cols_min >= input.available_width && cols_min >= cap_min {
-                                table_wrapper.col_widths = col_min_widths.clone();
-                                cols_min
-                            } else {
-                                geometry::max(input.available_width, cap_min)
-                            };
-                            (max, max - cols_min)
+                            geometry::max(input.available_width, cap_min)
                         }
                     },
                     Specified(width) => {
-                        let max = if cols_min >= width && cols_min >= cap_min {
-                            table_wrapper.col_widths = col_min_widths.clone();
+                        if cols_min >= width && cols_min >= cap_min {
                             cols_min
                         } else {
                             geometry::max(width, cap_min)
-                        };
-                        (max, max - cols_min)
+                        }
                     }
                 };
-                // The extra width is distributed over the columns
-                if extra_width > Au(0) {
-                    let cell_len = table_wrapper.col_widths.len() as f64;
-                    table_wrapper.col_widths = col_min_widths.iter().map(|width| {
-                        width + extra_width.scale_by(1.0 / cell_len)
-                    }).collect();
-                }
+                // Distribute the table width, less the border spacing or collapsed borders around
+                // the columns, over the columns.
+                table_wrapper.col_widths = compute_auto_column_widths(col_min_widths.as_slice(),
+                                                                      col_pref_widths.as_slice(),
+                                                                      width - non_column_width);
                 width
             }
         };
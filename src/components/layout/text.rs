@@ -14,10 +14,10 @@ use gfx::font_context::FontContext;
 use gfx::text::glyph::CharIndex;
 use gfx::text::text_run::TextRun;
 use gfx::text::util::{CompressWhitespaceNewline, transform_text, CompressNone};
-use servo_util::geometry::Au;
+use servo_util::geometry::{Au, LogicalSize};
 use servo_util::range::Range;
 use style::ComputedValues;
-use style::computed_values::{font_family, line_height, white_space};
+use style::computed_values::{font_family, hyphens, line_height, white_space};
 use sync::Arc;
 
 struct NewLinePositions {
@@ -130,21 +130,28 @@ impl TextRunScanner {
                 };
 
                 let mut new_line_pos = vec![];
+                let mut soft_hyphen_pos = vec![];
 
                 let (transformed_text, whitespace) = transform_text(text.as_slice(),
                                                                     compression,
                                                                     last_whitespace,
-                                                                    &mut new_line_pos);
+                                                                    &mut new_line_pos,
+                                                                    &mut soft_hyphen_pos);
 
                 new_whitespace = whitespace;
 
+                // With `hyphens: none`, soft hyphens are not line break opportunities.
+                if old_fragment.hyphens() == hyphens::none {
+                    soft_hyphen_pos.clear()
+                }
+
                 if transformed_text.len() > 0 {
                     // TODO(#177): Text run creation must account for the renderability of text by
                     // font group fonts. This is probably achieved by creating the font group above
                     // and then letting `FontGroup` decide which `Font` to stick into the text run.
                     let fontgroup = font_context.get_resolved_font_for_style(&font_style);
                     let run = box fontgroup.borrow().create_textrun(
-                        transformed_text.clone(), decoration);
+                        transformed_text.clone(), decoration, soft_hyphen_pos);
 
                     debug!("TextRunScanner: pushing single text fragment in range: {} ({})",
                            self.clump,
@@ -152,7 +159,9 @@ impl TextRunScanner {
                     let range = Range::new(CharIndex(0), run.char_len());
                     let new_metrics = run.metrics_for_range(&range);
                     let new_text_fragment_info = ScannedTextFragmentInfo::new(Arc::new(run), range);
-                    let mut new_fragment = old_fragment.transform(new_metrics.bounding_box.size,
+                    let bounding_box_size = LogicalSize::new(new_metrics.bounding_box.size.width,
+                                                             new_metrics.bounding_box.size.height);
+                    let mut new_fragment = old_fragment.transform(bounding_box_size,
                                                                   ScannedTextFragment(new_text_fragment_info));
                     new_fragment.new_line_pos = new_line_pos;
                     out_fragments.push(new_fragment)
@@ -174,6 +183,7 @@ impl TextRunScanner {
                 };
 
                 let mut new_line_positions: Vec<NewLinePositions> = vec![];
+                let mut soft_hyphen_positions: Vec<Vec<CharIndex>> = vec![];
 
                 // First, transform/compress text of all the nodes.
                 let mut last_whitespace_in_clump = new_whitespace;
@@ -188,12 +198,15 @@ impl TextRunScanner {
                     };
 
                     let mut new_line_pos = vec![];
+                    let mut soft_hyphen_pos = vec![];
 
                     let (new_str, new_whitespace) = transform_text(in_fragment.as_slice(),
                                                                    compression,
                                                                    last_whitespace_in_clump,
-                                                                   &mut new_line_pos);
+                                                                   &mut new_line_pos,
+                                                                   &mut soft_hyphen_pos);
                     new_line_positions.push(NewLinePositions { new_line_pos: new_line_pos });
+                    soft_hyphen_positions.push(soft_hyphen_pos);
 
                     last_whitespace_in_clump = new_whitespace;
                     new_str
@@ -201,14 +214,22 @@ impl TextRunScanner {
                 new_whitespace = last_whitespace_in_clump;
 
                 // Next, concatenate all of the transformed strings together, saving the new
-                // character indices.
+                // character indices. With `hyphens: none`, soft hyphens are not line break
+                // opportunities.
+                let use_soft_hyphens = in_fragment.hyphens() != hyphens::none;
                 let mut run_str = String::new();
+                let mut run_soft_hyphens: Vec<CharIndex> = vec![];
                 let mut new_ranges: Vec<Range<CharIndex>> = vec![];
                 let mut char_total = CharIndex(0);
                 for i in range(0, transformed_strs.len() as int) {
                     let added_chars = CharIndex(transformed_strs.get(i as uint).as_slice().char_len() as int);
                     new_ranges.push(Range::new(char_total, added_chars));
                     run_str.push_str(transformed_strs.get(i as uint).as_slice());
+                    if use_soft_hyphens {
+                        for &soft_hyphen in soft_hyphen_positions.get(i as uint).iter() {
+                            run_soft_hyphens.push(char_total + soft_hyphen);
+                        }
+                    }
                     char_total = char_total + added_chars;
                 }
 
@@ -219,7 +240,7 @@ impl TextRunScanner {
                 let run = if clump.length() != CharIndex(0) && run_str.len() > 0 {
                     Some(Arc::new(box TextRun::new(
                         &mut *fontgroup.borrow().fonts.get(0).borrow_mut(),
-                        run_str.to_string(), decoration)))
+                        run_str.to_string(), decoration, run_soft_hyphens)))
                 } else {
                     None
                 };
@@ -237,7 +258,9 @@ impl TextRunScanner {
 
                     let new_text_fragment_info = ScannedTextFragmentInfo::new(run.get_ref().clone(), *range);
                     let new_metrics = new_text_fragment_info.run.metrics_for_range(range);
-                    let mut new_fragment = in_fragments[i.to_uint()].transform(new_metrics.bounding_box.size,
+                    let bounding_box_size = LogicalSize::new(new_metrics.bounding_box.size.width,
+                                                             new_metrics.bounding_box.size.height);
+                    let mut new_fragment = in_fragments[i.to_uint()].transform(bounding_box_size,
                                                                                ScannedTextFragment(new_text_fragment_info));
                     new_fragment.new_line_pos = new_line_positions.get(logical_offset.to_uint()).new_line_pos.clone();
                     out_fragments.push(new_fragment)
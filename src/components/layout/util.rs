@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use construct::{ConstructionResult, NoConstructionResult};
+use flow_ref::FlowRef;
 use incremental::RestyleDamage;
 use parallel::DomParallelInfo;
 use wrapper::{LayoutNode, TLayoutNode, ThreadSafeLayoutNode};
@@ -16,7 +17,7 @@ use script::dom::node::{Node, SharedLayoutData};
 use script::layout_interface::{LayoutChan, UntrustedNodeAddress, TrustedNodeAddress};
 use std::mem;
 use std::cell::{Ref, RefMut};
-use style::ComputedValues;
+use style::{ComputedValues, RestyleHint};
 use style;
 use sync::Arc;
 
@@ -31,6 +32,10 @@ pub struct PrivateLayoutData {
     /// Description of how to account for recent style changes.
     pub restyle_damage: Option<RestyleDamage>,
 
+    /// Whether this node, and possibly its descendants, have to be restyled because something
+    /// that selectors depend on changed. Cleared when the node is next visited by style recalc.
+    pub restyle_hint: RestyleHint,
+
     /// The current results of flow construction for this node. This is either a flow or a
     /// `ConstructionItem`. See comments in `construct.rs` for more details.
     pub flow_construction_result: ConstructionResult,
@@ -39,6 +44,14 @@ pub struct PrivateLayoutData {
 
     pub after_flow_construction_result: ConstructionResult,
 
+    /// The flow that this node produced during the last flow construction, if any. Flow
+    /// construction reuses it if neither this node nor any of its descendants was damaged.
+    pub last_flow: Option<FlowRef>,
+
+    /// False if `last_flow` has absolutely positioned descendants whose containing block lies
+    /// outside of it, in which case it cannot be reused.
+    pub last_flow_is_reusable: bool,
+
     /// Information needed during parallel traversals.
     pub parallel: DomParallelInfo,
 }
@@ -50,9 +63,12 @@ impl PrivateLayoutData {
             before_style: None,
             after_style: None,
             restyle_damage: None,
+            restyle_hint: RestyleHint::empty(),
             flow_construction_result: NoConstructionResult,
             before_flow_construction_result: NoConstructionResult,
             after_flow_construction_result: NoConstructionResult,
+            last_flow: None,
+            last_flow_is_reusable: false,
             parallel: DomParallelInfo::new(),
         }
     }
@@ -38,11 +38,13 @@ use util::LayoutDataWrapper;
 
 use script::dom::bindings::codegen::InheritTypes::{HTMLIFrameElementDerived};
 use script::dom::bindings::codegen::InheritTypes::{HTMLImageElementDerived, TextDerived};
+use script::dom::bindings::codegen::InheritTypes::{HTMLInputElementDerived};
 use script::dom::bindings::js::JS;
 use script::dom::element::{Element, HTMLAreaElementTypeId, HTMLAnchorElementTypeId};
 use script::dom::element::{HTMLLinkElementTypeId, LayoutElementHelpers, RawLayoutElementHelpers};
 use script::dom::htmliframeelement::HTMLIFrameElement;
 use script::dom::htmlimageelement::{HTMLImageElement, LayoutHTMLImageElementHelpers};
+use script::dom::htmlinputelement::{HTMLInputElement, LayoutHTMLInputElementHelpers};
 use script::dom::node::{DocumentNodeTypeId, ElementNodeTypeId, Node, NodeTypeId};
 use script::dom::node::{LayoutNodeHelpers, RawLayoutNodeHelpers, TextNodeTypeId};
 use script::dom::text::Text;
@@ -117,6 +119,30 @@ pub trait TLayoutNode {
         }
     }
 
+    /// If this node is an input element, returns its current value, which script may have changed
+    /// from the `value` attribute. If this node is not an input element, fails.
+    fn input_value(&self) -> String {
+        unsafe {
+            if !self.get().is_htmlinputelement() {
+                fail!("not an input element!")
+            }
+            let input_element: JS<HTMLInputElement> = self.get_jsmanaged().transmute_copy();
+            input_element.get_value_for_layout()
+        }
+    }
+
+    /// If this node is an input element, returns whether it is checked. If this node is not an
+    /// input element, fails.
+    fn input_checked(&self) -> bool {
+        unsafe {
+            if !self.get().is_htmlinputelement() {
+                fail!("not an input element!")
+            }
+            let input_element: JS<HTMLInputElement> = self.get_jsmanaged().transmute_copy();
+            input_element.get_checked_state_for_layout()
+        }
+    }
+
     /// If this is a text node, copies out the text. If this is not a text node, fails.
     ///
     /// FIXME(pcwalton): Don't copy text. Atomically reference count instead.
@@ -221,6 +247,20 @@ impl<'ln> LayoutNode<'ln> {
    pub unsafe fn get_jsmanaged<'a>(&'a self) -> &'a JS<Node> {
        &self.node
    }
+
+    /// Returns true if script marked this node as needing layout, even if its style is unchanged.
+    pub fn is_dirty(&self) -> bool {
+        unsafe {
+            self.get().is_dirty_for_layout()
+        }
+    }
+
+    /// Clears the flag that `is_dirty()` reports, once the damage it implies has been recorded.
+    pub fn clear_dirty(&self) {
+        unsafe {
+            self.get().clear_dirty_for_layout()
+        }
+    }
 }
 
 impl<'ln> TNode<LayoutElement<'ln>> for LayoutNode<'ln> {
@@ -631,6 +671,20 @@ impl<'ln> ThreadSafeLayoutNode<'ln> {
         traversal.process(self)
     }
 
+    /// Returns the concatenated text of all the text nodes among this node's descendants, like
+    /// the DOM `textContent` attribute.
+    pub fn text_content(&self) -> String {
+        let mut text = String::new();
+        for kid in self.children() {
+            match kid.type_id() {
+                Some(TextNodeTypeId) => text.push_str(kid.text().as_slice()),
+                Some(ElementNodeTypeId(_)) => text.push_str(kid.text_content().as_slice()),
+                _ => {}
+            }
+        }
+        text
+    }
+
     pub fn is_ignorable_whitespace(&self) -> bool {
         match self.type_id() {
             Some(TextNodeTypeId) => {
@@ -5,6 +5,7 @@
 use azure::azure_hl::Color;
 use geom::point::Point2D;
 use geom::rect::Rect;
+use geom::size::Size2D;
 use layers::platform::surface::NativeGraphicsMetadata;
 use layers::layers::LayerBufferSet;
 use serialize::{Encoder, Encodable};
@@ -43,7 +44,7 @@ impl Epoch {
     }
 }
 
-#[deriving(Clone, PartialEq)]
+#[deriving(Clone, PartialEq, Eq, Hash)]
 pub struct LayerId(pub uint, pub uint);
 
 impl Show for LayerId {
@@ -61,12 +62,131 @@ impl LayerId {
 }
 
 /// The scrolling policy of a layer.
-#[deriving(PartialEq)]
+#[deriving(PartialEq, Clone)]
 pub enum ScrollPolicy {
     /// These layers scroll when the parent receives a scrolling message.
     Scrollable,
     /// These layers do not scroll when the parent receives a scrolling message.
     FixedPosition,
+    /// These layers scroll when the parent receives a scrolling message, but are then moved back
+    /// within the viewport as far as their constraint allows (`position: sticky`).
+    StickyPosition(StickyConstraint),
+    /// The thumb of a scrollbar of a scroll container. These layers scroll when the parent
+    /// receives a scrolling message, and are moved along their track as the contents of the
+    /// container are scrolled.
+    ScrollbarThumb(ScrollbarThumbConstraint),
+}
+
+/// Where a `position: sticky` layer may be moved to keep it in view. All rectangles are in page
+/// coordinates, in px.
+#[deriving(PartialEq, Clone)]
+pub struct StickyConstraint {
+    /// The border box of the element at its position in the normal flow.
+    pub rect: Rect<f32>,
+    /// The rectangle that the element must stay within: the content box of its containing block.
+    pub containing_block: Rect<f32>,
+    /// The size of the viewport that the insets below are relative to.
+    pub viewport_size: Size2D<f32>,
+    /// The distance to keep between the top edge of the element and the top edge of the
+    /// viewport, or `None` if `top` is `auto`.
+    pub top: Option<f32>,
+    /// The same for the right edges.
+    pub right: Option<f32>,
+    /// The same for the bottom edges.
+    pub bottom: Option<f32>,
+    /// The same for the left edges.
+    pub left: Option<f32>,
+}
+
+impl StickyConstraint {
+    /// Returns how far to move the element from its normal position when the viewport is
+    /// scrolled to `scroll_position`.
+    pub fn offset_for_scroll_position(&self, scroll_position: Point2D<f32>) -> Point2D<f32> {
+        let x = sticky_offset_along_axis(self.rect.origin.x,
+                                         self.rect.origin.x + self.rect.size.width,
+                                         self.containing_block.origin.x,
+                                         self.containing_block.origin.x +
+                                            self.containing_block.size.width,
+                                         scroll_position.x,
+                                         scroll_position.x + self.viewport_size.width,
+                                         self.left,
+                                         self.right);
+        let y = sticky_offset_along_axis(self.rect.origin.y,
+                                         self.rect.origin.y + self.rect.size.height,
+                                         self.containing_block.origin.y,
+                                         self.containing_block.origin.y +
+                                            self.containing_block.size.height,
+                                         scroll_position.y,
+                                         scroll_position.y + self.viewport_size.height,
+                                         self.top,
+                                         self.bottom);
+        Point2D(x, y)
+    }
+}
+
+/// Computes the sticky offset along one axis, for an element that spans `start` to `end` in a
+/// containing block that spans `containing_start` to `containing_end`. If both insets apply but
+/// the viewport is too small to honor both, the start inset wins.
+fn sticky_offset_along_axis(start: f32,
+                            end: f32,
+                            containing_start: f32,
+                            containing_end: f32,
+                            viewport_start: f32,
+                            viewport_end: f32,
+                            start_inset: Option<f32>,
+                            end_inset: Option<f32>)
+                            -> f32 {
+    let mut offset = 0.0;
+    match end_inset {
+        Some(inset) if end > viewport_end - inset => {
+            // Move back, but not past the start of the containing block.
+            offset = (viewport_end - inset - end).max(containing_start - start).min(0.0)
+        }
+        _ => {}
+    }
+    match start_inset {
+        Some(inset) if start + offset < viewport_start + inset => {
+            // Move forward, but not past the end of the containing block.
+            offset = (viewport_start + inset - start).min(containing_end - end).max(offset)
+        }
+        _ => {}
+    }
+    offset
+}
+
+/// Where the thumb of a scrollbar may be moved along its track. All lengths are in px.
+#[deriving(PartialEq, Clone)]
+pub struct ScrollbarThumbConstraint {
+    /// The layer of the scroll container that the scrollbar belongs to.
+    pub container: LayerId,
+    /// True if the thumb moves vertically; false if it moves horizontally.
+    pub vertical: bool,
+    /// How far the thumb moves from the start of its track to the end.
+    pub travel: f32,
+    /// How far the contents of the container can be scrolled along the same axis.
+    pub scroll_range: f32,
+}
+
+impl ScrollbarThumbConstraint {
+    /// Returns how far to move the thumb from the start of its track when the contents of the
+    /// container are scrolled to `scroll_position`.
+    pub fn offset_for_scroll_position(&self, scroll_position: Point2D<f32>) -> Point2D<f32> {
+        let position = if self.vertical {
+            scroll_position.y
+        } else {
+            scroll_position.x
+        };
+        let offset = if self.scroll_range > 0.0 {
+            (self.travel * position / self.scroll_range).max(0.0).min(self.travel)
+        } else {
+            0.0
+        };
+        if self.vertical {
+            Point2D(0.0, offset)
+        } else {
+            Point2D(offset, 0.0)
+        }
+    }
 }
 
 /// All layer-specific information that the painting task sends to the compositor other than the
@@ -80,6 +200,9 @@ pub struct LayerMetadata {
     pub background_color: Color,
     /// The scrolling policy of this layer.
     pub scroll_policy: ScrollPolicy,
+    /// The size of the contents of the layer in pixels, if the layer is a scroll container
+    /// (`overflow: scroll` or `overflow: auto`). The contents scroll within `position`.
+    pub content_size: Option<Size2D<uint>>,
 }
 
 /// The interface used by the renderer to acquire draw targets for each render frame and
@@ -126,3 +249,127 @@ impl<E, S: Encoder<E>> Encodable<S, E> for Box<ScriptListener> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{LayerId, ScrollbarThumbConstraint, StickyConstraint, sticky_offset_along_axis};
+
+    use geom::point::Point2D;
+    use geom::rect::Rect;
+    use geom::size::Size2D;
+
+    // An element from 100 to 150 in a containing block from 0 to 1000, seen through a viewport
+    // that is 400 long.
+    fn offset(scroll_position: f32, start_inset: Option<f32>, end_inset: Option<f32>) -> f32 {
+        offset_in(0.0, 1000.0, 100.0, 150.0, scroll_position, 400.0, start_inset, end_inset)
+    }
+
+    fn offset_in(containing_start: f32,
+                 containing_end: f32,
+                 start: f32,
+                 end: f32,
+                 scroll_position: f32,
+                 viewport_length: f32,
+                 start_inset: Option<f32>,
+                 end_inset: Option<f32>)
+                 -> f32 {
+        sticky_offset_along_axis(start,
+                                 end,
+                                 containing_start,
+                                 containing_end,
+                                 scroll_position,
+                                 scroll_position + viewport_length,
+                                 start_inset,
+                                 end_inset)
+    }
+
+    #[test]
+    fn test_sticky_no_insets() {
+        assert_eq!(offset(0.0, None, None), 0.0);
+        assert_eq!(offset(500.0, None, None), 0.0);
+    }
+
+    #[test]
+    fn test_sticky_start_inset() {
+        // Not moved while the element is further from the start of the viewport than the inset.
+        assert_eq!(offset(0.0, Some(10.0), None), 0.0);
+        assert_eq!(offset(90.0, Some(10.0), None), 0.0);
+        // Then moved along with the viewport.
+        assert_eq!(offset(200.0, Some(10.0), None), 110.0);
+        assert_eq!(offset(500.0, Some(10.0), None), 410.0);
+    }
+
+    #[test]
+    fn test_sticky_end_inset() {
+        // An element beyond the end of the viewport is pulled back into it.
+        assert_eq!(offset_in(0.0, 1000.0, 600.0, 650.0, 0.0, 400.0, None, Some(10.0)), -260.0);
+        assert_eq!(offset_in(0.0, 1000.0, 600.0, 650.0, 260.0, 400.0, None, Some(10.0)), 0.0);
+        assert_eq!(offset_in(0.0, 1000.0, 600.0, 650.0, 300.0, 400.0, None, Some(10.0)), 0.0);
+    }
+
+    #[test]
+    fn test_sticky_clamped_to_containing_block() {
+        // The element stops at the end of its containing block when moved forward...
+        assert_eq!(offset(950.0, Some(10.0), None), 850.0);
+        assert_eq!(offset(2000.0, Some(10.0), None), 850.0);
+        // ...and at its start when moved back.
+        assert_eq!(offset_in(500.0, 1000.0, 600.0, 650.0, 0.0, 400.0, None, Some(10.0)), -100.0);
+    }
+
+    #[test]
+    fn test_sticky_both_insets() {
+        // With room for both insets, each applies in turn as the viewport moves.
+        assert_eq!(offset_in(0.0, 1000.0, 500.0, 550.0, 0.0, 400.0, Some(10.0), Some(10.0)),
+                   -160.0);
+        assert_eq!(offset_in(0.0, 1000.0, 500.0, 550.0, 200.0, 400.0, Some(10.0), Some(10.0)),
+                   0.0);
+        assert_eq!(offset_in(0.0, 1000.0, 500.0, 550.0, 600.0, 400.0, Some(10.0), Some(10.0)),
+                   110.0);
+        // A viewport too small for both insets honors the start inset.
+        assert_eq!(offset_in(0.0, 1000.0, 100.0, 150.0, 0.0, 30.0, Some(10.0), Some(10.0)),
+                   -90.0);
+    }
+
+    #[test]
+    fn test_sticky_constraint() {
+        let constraint = StickyConstraint {
+            rect: Rect(Point2D(20.0, 100.0), Size2D(50.0, 50.0)),
+            containing_block: Rect(Point2D(0.0, 0.0), Size2D(800.0, 1000.0)),
+            viewport_size: Size2D(800.0, 600.0),
+            top: Some(10.0),
+            right: None,
+            bottom: None,
+            left: Some(30.0),
+        };
+        assert_eq!(constraint.offset_for_scroll_position(Point2D(0.0, 0.0)), Point2D(10.0, 0.0));
+        assert_eq!(constraint.offset_for_scroll_position(Point2D(0.0, 300.0)),
+                   Point2D(10.0, 210.0));
+    }
+
+    #[test]
+    fn test_scrollbar_thumb_constraint() {
+        let mut constraint = ScrollbarThumbConstraint {
+            container: LayerId(1, 0),
+            vertical: true,
+            travel: 150.0,
+            scroll_range: 300.0,
+        };
+        // The thumb moves in proportion to the contents, and stays on its track.
+        assert_eq!(constraint.offset_for_scroll_position(Point2D(50.0, 0.0)), Point2D(0.0, 0.0));
+        assert_eq!(constraint.offset_for_scroll_position(Point2D(50.0, 100.0)),
+                   Point2D(0.0, 50.0));
+        assert_eq!(constraint.offset_for_scroll_position(Point2D(50.0, 300.0)),
+                   Point2D(0.0, 150.0));
+        assert_eq!(constraint.offset_for_scroll_position(Point2D(50.0, 400.0)),
+                   Point2D(0.0, 150.0));
+
+        constraint.vertical = false;
+        assert_eq!(constraint.offset_for_scroll_position(Point2D(150.0, 100.0)),
+                   Point2D(75.0, 0.0));
+
+        // Contents that don't overflow don't move the thumb.
+        constraint.scroll_range = 0.0;
+        assert_eq!(constraint.offset_for_scroll_position(Point2D(150.0, 100.0)),
+                   Point2D(0.0, 0.0));
+    }
+}
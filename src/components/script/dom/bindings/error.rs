@@ -22,6 +22,7 @@ pub enum Error {
     NotFound,
     HierarchyRequest,
     InvalidCharacter,
+    NoModificationAllowed,
     NotSupported,
     InvalidState,
     Syntax,
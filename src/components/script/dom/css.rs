@@ -0,0 +1,36 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::utils::{Reflectable, Reflector};
+use servo_util::str::DOMString;
+use style::{supports_property_value, supports_condition_text};
+use url::Url;
+
+/// The `CSS` interface only has static members; it is never instantiated.
+#[deriving(Encodable)]
+pub struct CSS {
+    pub reflector_: Reflector
+}
+
+impl CSS {
+    // The supports tests never look at the base URL beyond needing one to resolve `url()`
+    // values against.
+    fn base_url() -> Url {
+        from_str("about:blank").unwrap()
+    }
+
+    pub fn Supports(property: DOMString, value: DOMString) -> bool {
+        supports_property_value(property.as_slice(), value.as_slice(), &CSS::base_url())
+    }
+
+    pub fn Supports_(condition_text: DOMString) -> bool {
+        supports_condition_text(condition_text.as_slice(), &CSS::base_url())
+    }
+}
+
+impl Reflectable for CSS {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
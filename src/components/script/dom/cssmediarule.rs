@@ -0,0 +1,74 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSMediaRuleBinding;
+use dom::bindings::codegen::InheritTypes::{CSSMediaRuleDerived, CSSRuleCast};
+use dom::bindings::error::{ErrorResult, Fallible};
+use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssrule::{CSSRule, CSSRuleHelpers, CSSMediaRuleTypeId};
+use dom::cssrulelist::{CSSRuleList, CSSRuleListHelpers};
+use dom::window::Window;
+use servo_util::str::DOMString;
+use style;
+
+#[deriving(Encodable)]
+pub struct CSSMediaRule {
+    pub cssrule: CSSRule,
+}
+
+impl CSSMediaRuleDerived for CSSRule {
+    fn is_cssmediarule(&self) -> bool {
+        self.type_id == CSSMediaRuleTypeId
+    }
+}
+
+impl CSSMediaRule {
+    pub fn new_inherited(list: &JSRef<CSSRuleList>) -> CSSMediaRule {
+        CSSMediaRule {
+            cssrule: CSSRule::new_inherited(CSSMediaRuleTypeId, list),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, list: &JSRef<CSSRuleList>) -> Temporary<CSSMediaRule> {
+        reflect_dom_object(box CSSMediaRule::new_inherited(list),
+                           window, CSSMediaRuleBinding::Wrap)
+    }
+}
+
+pub trait CSSMediaRuleMethods {
+    fn ConditionText(&self) -> DOMString;
+    fn CssRules(&self) -> Temporary<CSSRuleList>;
+    fn InsertRule(&self, rule: DOMString, index: u32) -> Fallible<u32>;
+    fn DeleteRule(&self, index: u32) -> ErrorResult;
+}
+
+impl<'a> CSSMediaRuleMethods for JSRef<'a, CSSMediaRule> {
+    fn ConditionText(&self) -> DOMString {
+        let cssrule: &JSRef<CSSRule> = CSSRuleCast::from_ref(self);
+        match cssrule.rule() {
+            style::CSSMediaRule(ref rule) => rule.media_queries.to_css(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn CssRules(&self) -> Temporary<CSSRuleList> {
+        let cssrule: &JSRef<CSSRule> = CSSRuleCast::from_ref(self);
+        cssrule.child_list()
+    }
+
+    fn InsertRule(&self, rule: DOMString, index: u32) -> Fallible<u32> {
+        self.CssRules().root().deref().insert_rule(rule, index)
+    }
+
+    fn DeleteRule(&self, index: u32) -> ErrorResult {
+        self.CssRules().root().deref().delete_rule(index)
+    }
+}
+
+impl Reflectable for CSSMediaRule {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.cssrule.reflector()
+    }
+}
@@ -0,0 +1,176 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSRuleBinding;
+use dom::bindings::codegen::Bindings::CSSRuleBinding::CSSRuleConstants;
+use dom::bindings::codegen::InheritTypes::CSSRuleCast;
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalSettable, OptionalRootedRootable};
+use dom::bindings::trace::Untraceable;
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssmediarule::CSSMediaRule;
+use dom::cssrulelist::{CSSRuleList, CSSRuleListHelpers};
+use dom::cssstylerule::CSSStyleRule;
+use dom::cssstylesheet::{CSSStyleSheet, CSSStyleSheetHelpers};
+use dom::window::Window;
+use servo_util::str::DOMString;
+use style;
+
+use std::cell::{Cell, RefCell};
+
+#[deriving(PartialEq, Encodable)]
+pub enum CSSRuleTypeId {
+    CSSStyleRuleTypeId,
+    CSSMediaRuleTypeId,
+    CSSSupportsRuleTypeId,
+    CSSPageRuleTypeId,
+}
+
+/// A rule of a style sheet. There is one object per rule: the rule lists keep the objects they
+/// hand out, and move them along as rules are inserted and deleted before them. The rule itself
+/// is looked up in the style sheet where the object currently is.
+#[deriving(Encodable)]
+pub struct CSSRule {
+    pub type_id: CSSRuleTypeId,
+    reflector_: Reflector,
+    pub parent_stylesheet: JS<CSSStyleSheet>,
+    /// The rule list that this rule is in, or `None` once the rule has been deleted.
+    parent_list: Cell<Option<JS<CSSRuleList>>>,
+    /// The child rules of a conditional rule, created when script first asks for them.
+    child_rule_list: Cell<Option<JS<CSSRuleList>>>,
+    /// The rule as it was when it was deleted, which is still what script sees of it.
+    deleted_rule: Untraceable<RefCell<Option<style::CSSRule>>>,
+}
+
+impl CSSRule {
+    pub fn new_inherited(type_id: CSSRuleTypeId, list: &JSRef<CSSRuleList>) -> CSSRule {
+        CSSRule {
+            type_id: type_id,
+            reflector_: Reflector::new(),
+            parent_stylesheet: list.stylesheet(),
+            parent_list: Cell::new(Some(JS::from_rooted(list))),
+            child_rule_list: Cell::new(None),
+            deleted_rule: Untraceable::new(RefCell::new(None)),
+        }
+    }
+
+    /// Creates the DOM object of the right interface for `rule`, which is in `list`. `@supports`
+    /// and `@page` rules have no interface of their own yet, and are exposed as plain `CSSRule`s.
+    pub fn new_for_rule(window: &JSRef<Window>, list: &JSRef<CSSRuleList>,
+                        rule: &style::CSSRule) -> Temporary<CSSRule> {
+        let type_id = match *rule {
+            style::CSSStyleRule(..) => CSSStyleRuleTypeId,
+            style::CSSMediaRule(..) => CSSMediaRuleTypeId,
+            style::CSSSupportsRule(..) => CSSSupportsRuleTypeId,
+            style::CSSPageRule(..) => CSSPageRuleTypeId,
+        };
+        match type_id {
+            CSSStyleRuleTypeId => {
+                CSSRuleCast::from_temporary(CSSStyleRule::new(window, list))
+            }
+            CSSMediaRuleTypeId => {
+                CSSRuleCast::from_temporary(CSSMediaRule::new(window, list))
+            }
+            CSSSupportsRuleTypeId | CSSPageRuleTypeId => {
+                reflect_dom_object(box CSSRule::new_inherited(type_id, list),
+                                   window, CSSRuleBinding::Wrap)
+            }
+        }
+    }
+}
+
+pub trait CSSRuleHelpers {
+    fn path(&self) -> Option<Vec<uint>>;
+    fn rule(&self) -> style::CSSRule;
+    fn child_list(&self) -> Temporary<CSSRuleList>;
+    fn detach(&self, rule: style::CSSRule);
+}
+
+impl<'a> CSSRuleHelpers for JSRef<'a, CSSRule> {
+    /// Returns where the rule is in its style sheet: the path of its rule list (see
+    /// `style::nested_rule_list`) followed by its index in that list. Returns `None` if the rule
+    /// or a rule that contains it has been deleted.
+    fn path(&self) -> Option<Vec<uint>> {
+        let list = match self.parent_list.get().root() {
+            Some(list) => list,
+            None => return None,
+        };
+        let mut path = match list.deref().path() {
+            Some(path) => path,
+            None => return None,
+        };
+        match list.deref().index_of(self) {
+            Some(index) => path.push(index),
+            None => return None,
+        }
+        Some(path)
+    }
+
+    /// Returns the rule as it currently is in its style sheet.
+    fn rule(&self) -> style::CSSRule {
+        match *self.deleted_rule.deref().borrow() {
+            Some(ref rule) => return rule.clone(),
+            None => {}
+        }
+        let list = self.parent_list.get().root().expect("rule neither in a list nor deleted");
+        let index = list.deref().index_of(self).expect("rule missing from its list");
+        list.deref().rule_at(index).expect("rule list out of step with its style sheet")
+    }
+
+    /// Returns the list of the child rules of this conditional rule. The same list is returned
+    /// every time.
+    fn child_list(&self) -> Temporary<CSSRuleList> {
+        match self.child_rule_list.get() {
+            Some(child_list) => Temporary::new(child_list),
+            None => {
+                let stylesheet = self.parent_stylesheet.root();
+                let window = stylesheet.deref().window().root();
+                let child_list = CSSRuleList::new(&*window, &*stylesheet, Some(self)).root();
+                self.child_rule_list.assign(Some(child_list.deref().clone()));
+                Temporary::from_rooted(&*child_list)
+            }
+        }
+    }
+
+    /// Takes this rule out of its list after it has been deleted from the style sheet. `rule`
+    /// is the rule as it was; the child rules of a conditional rule are read from it from now on.
+    fn detach(&self, rule: style::CSSRule) {
+        *self.deleted_rule.deref().borrow_mut() = Some(rule);
+        self.parent_list.set(None);
+    }
+}
+
+pub trait CSSRuleMethods {
+    fn Type(&self) -> u16;
+    fn CssText(&self) -> DOMString;
+    fn GetParentStyleSheet(&self) -> Option<Temporary<CSSStyleSheet>>;
+}
+
+impl<'a> CSSRuleMethods for JSRef<'a, CSSRule> {
+    fn Type(&self) -> u16 {
+        match self.type_id {
+            CSSStyleRuleTypeId => CSSRuleConstants::STYLE_RULE,
+            CSSMediaRuleTypeId => CSSRuleConstants::MEDIA_RULE,
+            CSSSupportsRuleTypeId => CSSRuleConstants::SUPPORTS_RULE,
+            CSSPageRuleTypeId => CSSRuleConstants::PAGE_RULE,
+        }
+    }
+
+    fn CssText(&self) -> DOMString {
+        self.rule().to_css()
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssrule-parentstylesheet
+    fn GetParentStyleSheet(&self) -> Option<Temporary<CSSStyleSheet>> {
+        if self.path().is_none() {
+            return None
+        }
+        Some(Temporary::new(self.parent_stylesheet.clone()))
+    }
+}
+
+impl Reflectable for CSSRule {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -0,0 +1,216 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSRuleListBinding;
+use dom::bindings::error::{ErrorResult, Fallible, InvalidState};
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalRootedRootable};
+use dom::bindings::trace::Traceable;
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssrule::{CSSRule, CSSRuleHelpers};
+use dom::cssstylesheet::{CSSStyleSheet, CSSStyleSheetHelpers};
+use dom::window::Window;
+use servo_util::str::DOMString;
+use style;
+
+use std::cell::RefCell;
+
+/// A live list of the rules in a style sheet or in a conditional rule.
+#[deriving(Encodable)]
+pub struct CSSRuleList {
+    reflector_: Reflector,
+    stylesheet: JS<CSSStyleSheet>,
+    /// The conditional rule whose child rules these are, or `None` for the rules of the style
+    /// sheet itself.
+    parent_rule: Option<JS<CSSRule>>,
+    /// The objects handed out for the rules in the list, by the index of the rule. Insertions and
+    /// deletions through the list keep these in step with the rules.
+    rules: Traceable<RefCell<Vec<Option<JS<CSSRule>>>>>,
+}
+
+impl CSSRuleList {
+    pub fn new_inherited(stylesheet: &JSRef<CSSStyleSheet>,
+                         parent_rule: Option<&JSRef<CSSRule>>) -> CSSRuleList {
+        CSSRuleList {
+            reflector_: Reflector::new(),
+            stylesheet: JS::from_rooted(stylesheet),
+            parent_rule: parent_rule.map(|rule| JS::from_rooted(rule)),
+            rules: Traceable::new(RefCell::new(vec!())),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, stylesheet: &JSRef<CSSStyleSheet>,
+               parent_rule: Option<&JSRef<CSSRule>>) -> Temporary<CSSRuleList> {
+        reflect_dom_object(box CSSRuleList::new_inherited(stylesheet, parent_rule),
+                           window, CSSRuleListBinding::Wrap)
+    }
+}
+
+/// Returns the child rules of a conditional rule.
+fn child_rules<'a>(rule: &'a style::CSSRule) -> &'a [style::CSSRule] {
+    match *rule {
+        style::CSSMediaRule(ref rule) => rule.rules.as_slice(),
+        style::CSSSupportsRule(ref rule) => rule.rules.as_slice(),
+        style::CSSStyleRule(_) | style::CSSPageRule(_) => &[],
+    }
+}
+
+pub trait CSSRuleListHelpers {
+    fn stylesheet(&self) -> JS<CSSStyleSheet>;
+    fn path(&self) -> Option<Vec<uint>>;
+    fn index_of(&self, rule: &JSRef<CSSRule>) -> Option<uint>;
+    fn rule_count(&self) -> uint;
+    fn rule_at(&self, index: uint) -> Option<style::CSSRule>;
+    fn insert_rule(&self, rule: DOMString, index: u32) -> Fallible<u32>;
+    fn delete_rule(&self, index: u32) -> ErrorResult;
+    fn detach_all(&self, old_rules: &[style::CSSRule]);
+}
+
+impl<'a> CSSRuleListHelpers for JSRef<'a, CSSRuleList> {
+    fn stylesheet(&self) -> JS<CSSStyleSheet> {
+        self.stylesheet.clone()
+    }
+
+    /// Returns which rule list of the style sheet this is (see `style::nested_rule_list`), or
+    /// `None` if the rule that contains it has been deleted.
+    fn path(&self) -> Option<Vec<uint>> {
+        match self.parent_rule.root() {
+            None => Some(vec!()),
+            Some(parent_rule) => parent_rule.deref().path(),
+        }
+    }
+
+    /// Returns the index of the given rule object in this list.
+    fn index_of(&self, rule: &JSRef<CSSRule>) -> Option<uint> {
+        let rule = JS::from_rooted(rule);
+        self.rules.deref().borrow().iter().position(|object| *object == Some(rule.clone()))
+    }
+
+    fn rule_count(&self) -> uint {
+        match self.path() {
+            Some(path) => {
+                let stylesheet = self.stylesheet.root();
+                stylesheet.deref().rule_count(path.as_slice())
+            }
+            // The rules of a deleted conditional rule are still what they were.
+            None => {
+                let parent_rule = self.parent_rule.root().unwrap();
+                child_rules(&parent_rule.deref().rule()).len()
+            }
+        }
+    }
+
+    fn rule_at(&self, index: uint) -> Option<style::CSSRule> {
+        match self.path() {
+            Some(path) => {
+                let stylesheet = self.stylesheet.root();
+                stylesheet.deref().rule_at(path.as_slice(), index)
+            }
+            None => {
+                let parent_rule = self.parent_rule.root().unwrap();
+                child_rules(&parent_rule.deref().rule()).get(index).map(|rule| rule.clone())
+            }
+        }
+    }
+
+    fn insert_rule(&self, rule: DOMString, index: u32) -> Fallible<u32> {
+        let path = match self.path() {
+            Some(path) => path,
+            // The enclosing rule has been removed from the style sheet.
+            None => return Err(InvalidState),
+        };
+        let stylesheet = self.stylesheet.root();
+        let index = try!(stylesheet.deref().insert_rule(path.as_slice(), rule, index));
+        let mut rules = self.rules.deref().borrow_mut();
+        if (index as uint) <= rules.len() {
+            rules.insert(index as uint, None)
+        }
+        Ok(index)
+    }
+
+    fn delete_rule(&self, index: u32) -> ErrorResult {
+        let path = match self.path() {
+            Some(path) => path,
+            None => return Err(InvalidState),
+        };
+        let stylesheet = self.stylesheet.root();
+        let deleted_rule = try!(stylesheet.deref().delete_rule(path.as_slice(), index));
+        let object = {
+            let mut rules = self.rules.deref().borrow_mut();
+            if (index as uint) < rules.len() {
+                rules.remove(index as uint).unwrap()
+            } else {
+                None
+            }
+        };
+        match object.root() {
+            Some(object) => object.deref().detach(deleted_rule),
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Takes all the rule objects out of this list when all the rules of the style sheet are
+    /// replaced. `old_rules` are the rules that the list had.
+    fn detach_all(&self, old_rules: &[style::CSSRule]) {
+        let objects = {
+            let mut rules = self.rules.deref().borrow_mut();
+            let objects = rules.clone();
+            rules.clear();
+            objects
+        };
+        for (object, rule) in objects.iter().zip(old_rules.iter()) {
+            match object.root() {
+                Some(object) => object.deref().detach(rule.clone()),
+                None => {}
+            }
+        }
+    }
+}
+
+pub trait CSSRuleListMethods {
+    fn Length(&self) -> u32;
+    fn Item(&self, index: u32) -> Option<Temporary<CSSRule>>;
+    fn IndexedGetter(&self, index: u32, found: &mut bool) -> Option<Temporary<CSSRule>>;
+}
+
+impl<'a> CSSRuleListMethods for JSRef<'a, CSSRuleList> {
+    fn Length(&self) -> u32 {
+        self.rule_count() as u32
+    }
+
+    fn Item(&self, index: u32) -> Option<Temporary<CSSRule>> {
+        let index = index as uint;
+        let rule = match self.rule_at(index) {
+            Some(rule) => rule,
+            None => return None,
+        };
+        {
+            let mut rules = self.rules.deref().borrow_mut();
+            while rules.len() <= index {
+                rules.push(None)
+            }
+            match *rules.get(index) {
+                Some(ref object) => return Some(Temporary::new(object.clone())),
+                None => {}
+            }
+        }
+        let stylesheet = self.stylesheet.root();
+        let window = stylesheet.deref().window().root();
+        let object = CSSRule::new_for_rule(&*window, self, &rule).root();
+        *self.rules.deref().borrow_mut().get_mut(index) = Some(JS::from_rooted(&*object));
+        Some(Temporary::from_rooted(&*object))
+    }
+
+    fn IndexedGetter(&self, index: u32, found: &mut bool) -> Option<Temporary<CSSRule>> {
+        let rule = self.Item(index);
+        *found = rule.is_some();
+        rule
+    }
+}
+
+impl Reflectable for CSSRuleList {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -0,0 +1,452 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding;
+use dom::bindings::codegen::InheritTypes::NodeCast;
+use dom::bindings::error::{ErrorResult, Fallible, NoModificationAllowed};
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::document::Document;
+use dom::element::{Element, AttributeHandlers};
+use dom::node::{Node, NodeHelpers, document_from_node, window_from_node};
+use layout_interface::{ComputedStyleQuery, ComputedStyleResponse};
+use servo_util::str::DOMString;
+use style;
+
+use std::ascii::StrAsciiExt;
+use std::comm::channel;
+use url::Url;
+
+/// The declarations of a `CSSStyleDeclaration`: the lowercased property name, the serialized
+/// value and whether the declaration is `!important`.
+type Declarations = Vec<(String, String, bool)>;
+
+fn serialize_declarations(declarations: &Declarations) -> String {
+    let serialized: Vec<String> = declarations.iter().map(|&(ref name, ref value, important)| {
+        format!("{}: {}{};", name, value, if important { " !important" } else { "" })
+    }).collect();
+    serialized.connect(" ")
+}
+
+/// Returns the declaration of `property`, if there is one.
+fn find_declaration<'a>(declarations: &'a Declarations, property: &str)
+                        -> Option<&'a (String, String, bool)> {
+    declarations.iter().find(|&&(ref name, _, _)| name.as_slice() == property)
+}
+
+/// A `CSSStyleDeclaration` is either the inline style of an element (`element.style`), which reads
+/// and writes the element's `style` attribute, or a read-only view of the computed values of an
+/// element (`getComputedStyle()`), which asks layout every time it is read.
+#[deriving(Encodable)]
+pub struct CSSStyleDeclaration {
+    reflector_: Reflector,
+    owner: JS<Element>,
+    readonly: bool,
+}
+
+impl CSSStyleDeclaration {
+    pub fn new_inherited(owner: &JSRef<Element>, readonly: bool) -> CSSStyleDeclaration {
+        CSSStyleDeclaration {
+            reflector_: Reflector::new(),
+            owner: JS::from_rooted(owner),
+            readonly: readonly,
+        }
+    }
+
+    pub fn new(owner: &JSRef<Element>, readonly: bool) -> Temporary<CSSStyleDeclaration> {
+        let window = window_from_node(owner).root();
+        reflect_dom_object(box CSSStyleDeclaration::new_inherited(owner, readonly),
+                           &*window, CSSStyleDeclarationBinding::Wrap)
+    }
+}
+
+impl Reflectable for CSSStyleDeclaration {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
+
+trait PrivateCSSStyleDeclarationHelpers {
+    fn base_url(&self) -> Url;
+    fn declarations(&self) -> Declarations;
+    fn set_declarations(&self, declarations: Declarations);
+}
+
+impl<'a> PrivateCSSStyleDeclarationHelpers for JSRef<'a, CSSStyleDeclaration> {
+    fn base_url(&self) -> Url {
+        let owner = self.owner.root();
+        let document: Temporary<Document> = document_from_node(&*owner);
+        let document = document.root();
+        document.deref().url().clone()
+    }
+
+    fn declarations(&self) -> Declarations {
+        let owner = self.owner.root();
+        if !self.readonly {
+            let style_attribute = owner.deref().get_string_attribute("style");
+            return style::parse_style_attribute_as_written(style_attribute.as_slice(),
+                                                           &self.base_url())
+        }
+
+        // Computed values are only known for elements that layout has seen.
+        let node: &JSRef<Node> = NodeCast::from_ref(&*owner);
+        if !node.is_in_doc() {
+            return vec!()
+        }
+        let window = window_from_node(&*owner).root();
+        let page = window.deref().page();
+        let (chan, port) = channel();
+        let addr = node.to_trusted_node_address();
+        let ComputedStyleResponse(declarations) =
+            page.query_layout(ComputedStyleQuery(addr, chan), port);
+        declarations.move_iter().map(|(name, value)| (name, value, false)).collect()
+    }
+
+    /// Reserializes the given declarations into the owner's `style` attribute, which restyles it.
+    fn set_declarations(&self, declarations: Declarations) {
+        let owner = self.owner.root();
+        owner.deref().set_string_attribute("style", serialize_declarations(&declarations));
+    }
+}
+
+/// The operations of the `CSSStyleDeclaration` interface that the property accessors generated by
+/// `css_properties!` are built on.
+trait CSSStyleDeclarationHelpers {
+    fn css_text(&self) -> DOMString;
+    fn set_css_text(&self, value: DOMString) -> ErrorResult;
+    fn length(&self) -> u32;
+    fn item(&self, index: u32) -> DOMString;
+    fn get_property_value(&self, property: DOMString) -> DOMString;
+    fn get_property_priority(&self, property: DOMString) -> DOMString;
+    fn set_property(&self, property: DOMString, value: DOMString, priority: DOMString)
+                    -> ErrorResult;
+    fn remove_property(&self, property: DOMString) -> Fallible<DOMString>;
+}
+
+// http://dev.w3.org/csswg/cssom/#the-cssstyledeclaration-interface
+impl<'a> CSSStyleDeclarationHelpers for JSRef<'a, CSSStyleDeclaration> {
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-csstext
+    fn css_text(&self) -> DOMString {
+        serialize_declarations(&self.declarations())
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-csstext
+    fn set_css_text(&self, value: DOMString) -> ErrorResult {
+        if self.readonly {
+            return Err(NoModificationAllowed)
+        }
+        let declarations = style::parse_style_attribute_as_written(value.as_slice(),
+                                                                   &self.base_url());
+        self.set_declarations(declarations);
+        Ok(())
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-length
+    fn length(&self) -> u32 {
+        self.declarations().len() as u32
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-item
+    fn item(&self, index: u32) -> DOMString {
+        let declarations = self.declarations();
+        if index as uint >= declarations.len() {
+            return "".to_string()
+        }
+        match *declarations.get(index as uint) {
+            (ref name, _, _) => name.clone(),
+        }
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-getpropertyvalue
+    fn get_property_value(&self, property: DOMString) -> DOMString {
+        let property = property.as_slice().to_ascii_lower();
+        let declarations = self.declarations();
+        match find_declaration(&declarations, property.as_slice()) {
+            Some(&(_, ref value, _)) => return value.clone(),
+            None => {}
+        }
+
+        // Shorthands are stored as their longhands, and serialized from them as long as they are
+        // all present with the same priority.
+        let longhands = match style::shorthand_longhands(property.as_slice()) {
+            Some(longhands) => longhands,
+            None => return "".to_string(),
+        };
+        let mut values = vec!();
+        let mut priorities = vec!();
+        for longhand in longhands.iter() {
+            match find_declaration(&declarations, *longhand) {
+                Some(&(_, ref value, important)) => {
+                    values.push(value.as_slice());
+                    priorities.push(important);
+                }
+                None => return "".to_string(),
+            }
+        }
+        if priorities.iter().any(|&important| important != *priorities.get(0)) {
+            return "".to_string()
+        }
+        style::serialize_shorthand(property.as_slice(), values.as_slice())
+            .unwrap_or("".to_string())
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-getpropertypriority
+    fn get_property_priority(&self, property: DOMString) -> DOMString {
+        let property = property.as_slice().to_ascii_lower();
+        let declarations = self.declarations();
+        let important = match find_declaration(&declarations, property.as_slice()) {
+            Some(&(_, _, important)) => important,
+            // A shorthand is important if all of its longhands are.
+            None => match style::shorthand_longhands(property.as_slice()) {
+                Some(longhands) => longhands.iter().all(|longhand| {
+                    match find_declaration(&declarations, *longhand) {
+                        Some(&(_, _, important)) => important,
+                        None => false,
+                    }
+                }),
+                None => false,
+            },
+        };
+        if important {
+            "important".to_string()
+        } else {
+            "".to_string()
+        }
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-setproperty
+    fn set_property(&self, property: DOMString, value: DOMString, priority: DOMString)
+                    -> ErrorResult {
+        if self.readonly {
+            return Err(NoModificationAllowed)
+        }
+        if value.is_empty() {
+            return self.remove_property(property).map(|_| ())
+        }
+
+        let property = property.as_slice().to_ascii_lower();
+        let important = match priority.as_slice().to_ascii_lower().as_slice() {
+            "" => false,
+            "important" => true,
+            _ => return Ok(()),
+        };
+        // Unknown properties and invalid values are silently ignored.
+        let new_declarations = style::parse_declaration_as_written(property.as_slice(),
+                                                                   value.as_slice(),
+                                                                   &self.base_url());
+        if new_declarations.is_empty() {
+            return Ok(())
+        }
+
+        // A shorthand sets all of its longhands.
+        let mut declarations = self.declarations();
+        for (property, value) in new_declarations.move_iter() {
+            declarations.retain(|&(ref name, _, _)| *name != property);
+            declarations.push((property, value, important));
+        }
+        self.set_declarations(declarations);
+        Ok(())
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-removeproperty
+    fn remove_property(&self, property: DOMString) -> Fallible<DOMString> {
+        if self.readonly {
+            return Err(NoModificationAllowed)
+        }
+
+        let property = property.as_slice().to_ascii_lower();
+        let value = self.get_property_value(property.clone());
+        // A shorthand removes all of its longhands.
+        let longhands = match style::shorthand_longhands(property.as_slice()) {
+            Some(longhands) => Vec::from_slice(longhands),
+            None => vec!(property.as_slice()),
+        };
+        let mut declarations = self.declarations();
+        let count = declarations.len();
+        declarations.retain(|&(ref name, _, _)| {
+            !longhands.iter().any(|longhand| name.as_slice() == *longhand)
+        });
+        if declarations.len() != count {
+            self.set_declarations(declarations);
+        }
+        Ok(value)
+    }
+}
+
+/// Declares `CSSStyleDeclarationMethods` and implements it for `CSSStyleDeclaration`: the
+/// interface's operations forward to `CSSStyleDeclarationHelpers`, and each listed CSS property
+/// gets a getter and setter that go through `get_property_value` and `set_property`.
+macro_rules! css_properties(
+    ($($getter:ident, $setter:ident: $name:expr;)*) => (
+        pub trait CSSStyleDeclarationMethods {
+            fn CssText(&self) -> DOMString;
+            fn SetCssText(&self, value: DOMString) -> ErrorResult;
+            fn Length(&self) -> u32;
+            fn Item(&self, index: u32) -> DOMString;
+            fn IndexedGetter(&self, index: u32, found: &mut bool) -> DOMString;
+            fn GetPropertyValue(&self, property: DOMString) -> DOMString;
+            fn GetPropertyPriority(&self, property: DOMString) -> DOMString;
+            fn SetProperty(&self, property: DOMString, value: DOMString, priority: DOMString)
+                           -> ErrorResult;
+            fn RemoveProperty(&self, property: DOMString) -> Fallible<DOMString>;
+            $(
+                fn $getter(&self) -> DOMString;
+                fn $setter(&self, value: DOMString) -> ErrorResult;
+            )*
+        }
+
+        impl<'a> CSSStyleDeclarationMethods for JSRef<'a, CSSStyleDeclaration> {
+            fn CssText(&self) -> DOMString {
+                self.css_text()
+            }
+
+            fn SetCssText(&self, value: DOMString) -> ErrorResult {
+                self.set_css_text(value)
+            }
+
+            fn Length(&self) -> u32 {
+                self.length()
+            }
+
+            fn Item(&self, index: u32) -> DOMString {
+                self.item(index)
+            }
+
+            fn IndexedGetter(&self, index: u32, found: &mut bool) -> DOMString {
+                *found = index < self.length();
+                self.item(index)
+            }
+
+            fn GetPropertyValue(&self, property: DOMString) -> DOMString {
+                self.get_property_value(property)
+            }
+
+            fn GetPropertyPriority(&self, property: DOMString) -> DOMString {
+                self.get_property_priority(property)
+            }
+
+            fn SetProperty(&self, property: DOMString, value: DOMString, priority: DOMString)
+                           -> ErrorResult {
+                self.set_property(property, value, priority)
+            }
+
+            fn RemoveProperty(&self, property: DOMString) -> Fallible<DOMString> {
+                self.remove_property(property)
+            }
+
+            $(
+                fn $getter(&self) -> DOMString {
+                    self.get_property_value($name.to_string())
+                }
+
+                fn $setter(&self, value: DOMString) -> ErrorResult {
+                    self.set_property($name.to_string(), value, "".to_string())
+                }
+            )*
+        }
+    )
+)
+
+css_properties!(
+    AlignContent, SetAlignContent: "align-content";
+    AlignItems, SetAlignItems: "align-items";
+    AlignSelf, SetAlignSelf: "align-self";
+    Background, SetBackground: "background";
+    BackgroundAttachment, SetBackgroundAttachment: "background-attachment";
+    BackgroundColor, SetBackgroundColor: "background-color";
+    BackgroundImage, SetBackgroundImage: "background-image";
+    BackgroundPosition, SetBackgroundPosition: "background-position";
+    BackgroundRepeat, SetBackgroundRepeat: "background-repeat";
+    Border, SetBorder: "border";
+    BorderBottom, SetBorderBottom: "border-bottom";
+    BorderBottomColor, SetBorderBottomColor: "border-bottom-color";
+    BorderBottomStyle, SetBorderBottomStyle: "border-bottom-style";
+    BorderBottomWidth, SetBorderBottomWidth: "border-bottom-width";
+    BorderCollapse, SetBorderCollapse: "border-collapse";
+    BorderColor, SetBorderColor: "border-color";
+    BorderLeft, SetBorderLeft: "border-left";
+    BorderLeftColor, SetBorderLeftColor: "border-left-color";
+    BorderLeftStyle, SetBorderLeftStyle: "border-left-style";
+    BorderLeftWidth, SetBorderLeftWidth: "border-left-width";
+    BorderRight, SetBorderRight: "border-right";
+    BorderRightColor, SetBorderRightColor: "border-right-color";
+    BorderRightStyle, SetBorderRightStyle: "border-right-style";
+    BorderRightWidth, SetBorderRightWidth: "border-right-width";
+    BorderSpacing, SetBorderSpacing: "border-spacing";
+    BorderStyle, SetBorderStyle: "border-style";
+    BorderTop, SetBorderTop: "border-top";
+    BorderTopColor, SetBorderTopColor: "border-top-color";
+    BorderTopStyle, SetBorderTopStyle: "border-top-style";
+    BorderTopWidth, SetBorderTopWidth: "border-top-width";
+    BorderWidth, SetBorderWidth: "border-width";
+    Bottom, SetBottom: "bottom";
+    CaptionSide, SetCaptionSide: "caption-side";
+    Clear, SetClear: "clear";
+    Color, SetColor: "color";
+    ColumnCount, SetColumnCount: "column-count";
+    ColumnGap, SetColumnGap: "column-gap";
+    ColumnRule, SetColumnRule: "column-rule";
+    ColumnRuleColor, SetColumnRuleColor: "column-rule-color";
+    ColumnRuleStyle, SetColumnRuleStyle: "column-rule-style";
+    ColumnRuleWidth, SetColumnRuleWidth: "column-rule-width";
+    ColumnWidth, SetColumnWidth: "column-width";
+    Columns, SetColumns: "columns";
+    Content, SetContent: "content";
+    Direction, SetDirection: "direction";
+    Display, SetDisplay: "display";
+    EmptyCells, SetEmptyCells: "empty-cells";
+    Flex, SetFlex: "flex";
+    FlexBasis, SetFlexBasis: "flex-basis";
+    FlexDirection, SetFlexDirection: "flex-direction";
+    FlexFlow, SetFlexFlow: "flex-flow";
+    FlexGrow, SetFlexGrow: "flex-grow";
+    FlexShrink, SetFlexShrink: "flex-shrink";
+    FlexWrap, SetFlexWrap: "flex-wrap";
+    CssFloat, SetCssFloat: "float";
+    Font, SetFont: "font";
+    FontFamily, SetFontFamily: "font-family";
+    FontSize, SetFontSize: "font-size";
+    FontStyle, SetFontStyle: "font-style";
+    FontVariant, SetFontVariant: "font-variant";
+    FontWeight, SetFontWeight: "font-weight";
+    Height, SetHeight: "height";
+    Hyphens, SetHyphens: "hyphens";
+    JustifyContent, SetJustifyContent: "justify-content";
+    Left, SetLeft: "left";
+    LineHeight, SetLineHeight: "line-height";
+    Margin, SetMargin: "margin";
+    MarginBottom, SetMarginBottom: "margin-bottom";
+    MarginLeft, SetMarginLeft: "margin-left";
+    MarginRight, SetMarginRight: "margin-right";
+    MarginTop, SetMarginTop: "margin-top";
+    MaxHeight, SetMaxHeight: "max-height";
+    MaxWidth, SetMaxWidth: "max-width";
+    MinHeight, SetMinHeight: "min-height";
+    MinWidth, SetMinWidth: "min-width";
+    Order, SetOrder: "order";
+    Orphans, SetOrphans: "orphans";
+    Overflow, SetOverflow: "overflow";
+    Padding, SetPadding: "padding";
+    PaddingBottom, SetPaddingBottom: "padding-bottom";
+    PaddingLeft, SetPaddingLeft: "padding-left";
+    PaddingRight, SetPaddingRight: "padding-right";
+    PaddingTop, SetPaddingTop: "padding-top";
+    PageBreakAfter, SetPageBreakAfter: "page-break-after";
+    PageBreakBefore, SetPageBreakBefore: "page-break-before";
+    PageBreakInside, SetPageBreakInside: "page-break-inside";
+    Position, SetPosition: "position";
+    Right, SetRight: "right";
+    TableLayout, SetTableLayout: "table-layout";
+    TextAlign, SetTextAlign: "text-align";
+    TextDecoration, SetTextDecoration: "text-decoration";
+    TextOrientation, SetTextOrientation: "text-orientation";
+    Top, SetTop: "top";
+    UnicodeBidi, SetUnicodeBidi: "unicode-bidi";
+    VerticalAlign, SetVerticalAlign: "vertical-align";
+    Visibility, SetVisibility: "visibility";
+    WhiteSpace, SetWhiteSpace: "white-space";
+    Widows, SetWidows: "widows";
+    Width, SetWidth: "width";
+    WritingMode, SetWritingMode: "writing-mode";
+)
@@ -0,0 +1,57 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSStyleRuleBinding;
+use dom::bindings::codegen::InheritTypes::{CSSRuleCast, CSSStyleRuleDerived};
+use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssrule::{CSSRule, CSSRuleHelpers, CSSStyleRuleTypeId};
+use dom::cssrulelist::CSSRuleList;
+use dom::window::Window;
+use servo_util::str::DOMString;
+use style;
+
+#[deriving(Encodable)]
+pub struct CSSStyleRule {
+    pub cssrule: CSSRule,
+}
+
+impl CSSStyleRuleDerived for CSSRule {
+    fn is_cssstylerule(&self) -> bool {
+        self.type_id == CSSStyleRuleTypeId
+    }
+}
+
+impl CSSStyleRule {
+    pub fn new_inherited(list: &JSRef<CSSRuleList>) -> CSSStyleRule {
+        CSSStyleRule {
+            cssrule: CSSRule::new_inherited(CSSStyleRuleTypeId, list),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, list: &JSRef<CSSRuleList>) -> Temporary<CSSStyleRule> {
+        reflect_dom_object(box CSSStyleRule::new_inherited(list),
+                           window, CSSStyleRuleBinding::Wrap)
+    }
+}
+
+pub trait CSSStyleRuleMethods {
+    fn SelectorText(&self) -> DOMString;
+}
+
+impl<'a> CSSStyleRuleMethods for JSRef<'a, CSSStyleRule> {
+    fn SelectorText(&self) -> DOMString {
+        let cssrule: &JSRef<CSSRule> = CSSRuleCast::from_ref(self);
+        match cssrule.rule() {
+            style::CSSStyleRule(ref rule) => rule.selector_text.clone(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Reflectable for CSSStyleRule {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.cssrule.reflector()
+    }
+}
@@ -0,0 +1,229 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::CSSStyleSheetBinding;
+use dom::bindings::error::{ErrorResult, Fallible, IndexSize, InvalidState, Syntax};
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalSettable, OptionalRootedRootable};
+use dom::bindings::trace::{Traceable, Untraceable};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssrulelist::{CSSRuleList, CSSRuleListHelpers};
+use dom::document::{Document, DocumentHelpers};
+use dom::window::Window;
+use layout_interface::{DeleteRuleMsg, InsertRuleMsg, LayoutChan, MatchSelectorsDocumentDamage};
+use layout_interface::{Msg, ReplaceStylesheetMsg, SetStylesheetDisabledMsg};
+use servo_util::str::DOMString;
+use style::{CSSRule, Stylesheet, nested_rule_list, nested_rule_list_mut};
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+
+#[deriving(Encodable)]
+pub struct CSSStyleSheet {
+    reflector_: Reflector,
+    document: JS<Document>,
+    /// The position of this style sheet in the document's list. Layout identifies style sheets by
+    /// it.
+    index: uint,
+    /// Script's copy of the rules. Layout keeps its own, and is told about every change.
+    stylesheet: Untraceable<RefCell<Stylesheet>>,
+    disabled: Traceable<Cell<bool>>,
+    /// The list of the rules of this style sheet, created when script first asks for it.
+    rule_list: Cell<Option<JS<CSSRuleList>>>,
+}
+
+impl CSSStyleSheet {
+    pub fn new_inherited(document: &JSRef<Document>, index: uint,
+                         stylesheet: Stylesheet) -> CSSStyleSheet {
+        CSSStyleSheet {
+            reflector_: Reflector::new(),
+            document: JS::from_rooted(document),
+            index: index,
+            stylesheet: Untraceable::new(RefCell::new(stylesheet)),
+            disabled: Traceable::new(Cell::new(false)),
+            rule_list: Cell::new(None),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, document: &JSRef<Document>, index: uint,
+               stylesheet: Stylesheet) -> Temporary<CSSStyleSheet> {
+        reflect_dom_object(box CSSStyleSheet::new_inherited(document, index, stylesheet),
+                           window, CSSStyleSheetBinding::Wrap)
+    }
+}
+
+/// Access to the rules of a style sheet, for `CSSRuleList`. Nested rule lists are identified by a
+/// path, as in `style::nested_rule_list`.
+pub trait CSSStyleSheetHelpers {
+    fn window(&self) -> Temporary<Window>;
+    fn replace(&self, stylesheet: Stylesheet);
+    fn rule_list(&self) -> Temporary<CSSRuleList>;
+    fn rule_count(&self, path: &[uint]) -> uint;
+    fn rule_at(&self, path: &[uint], index: uint) -> Option<CSSRule>;
+    fn insert_rule(&self, path: &[uint], rule: DOMString, index: u32) -> Fallible<u32>;
+    fn delete_rule(&self, path: &[uint], index: u32) -> Fallible<CSSRule>;
+}
+
+trait PrivateCSSStyleSheetHelpers {
+    fn send_to_layout(&self, msg: Msg);
+    fn restyle(&self);
+}
+
+impl<'a> PrivateCSSStyleSheetHelpers for JSRef<'a, CSSStyleSheet> {
+    fn send_to_layout(&self, msg: Msg) {
+        let window = self.window().root();
+        let LayoutChan(ref layout_chan) = *window.deref().page().layout_chan;
+        layout_chan.send(msg);
+    }
+
+    fn restyle(&self) {
+        // FIXME: Layout only rebuilds the rules of the modified sheet, but every element is still
+        // matched again. Restyle hints for the selectors of the added and removed rules would
+        // let us restyle only the elements they can match.
+        let document = self.document.root();
+        document.deref().damage_and_reflow(MatchSelectorsDocumentDamage);
+    }
+}
+
+impl<'a> CSSStyleSheetHelpers for JSRef<'a, CSSStyleSheet> {
+    fn window(&self) -> Temporary<Window> {
+        let document = self.document.root();
+        Temporary::new(document.deref().window.clone())
+    }
+
+    /// Replaces all the rules of this style sheet, e.g. when the text of its `<style>` element
+    /// changes. The objects of the old rules no longer belong to the style sheet. The DOM
+    /// mutation that caused this is responsible for the reflow.
+    fn replace(&self, stylesheet: Stylesheet) {
+        let old_stylesheet = mem::replace(&mut *self.stylesheet.deref().borrow_mut(),
+                                          stylesheet.clone());
+        match self.rule_list.get().root() {
+            Some(rule_list) => rule_list.deref().detach_all(old_stylesheet.rules.as_slice()),
+            None => {}
+        }
+        self.send_to_layout(ReplaceStylesheetMsg(self.index, stylesheet));
+    }
+
+    /// Returns the list of the rules of this style sheet. The same list is returned every time,
+    /// and hands out the same object for a rule every time.
+    fn rule_list(&self) -> Temporary<CSSRuleList> {
+        match self.rule_list.get() {
+            Some(rule_list) => Temporary::new(rule_list),
+            None => {
+                let window = self.window().root();
+                let rule_list = CSSRuleList::new(&*window, self, None).root();
+                self.rule_list.assign(Some(rule_list.deref().clone()));
+                Temporary::from_rooted(&*rule_list)
+            }
+        }
+    }
+
+    fn rule_count(&self, path: &[uint]) -> uint {
+        let stylesheet = self.stylesheet.deref().borrow();
+        match nested_rule_list(&stylesheet.rules, path) {
+            Some(rules) => rules.len(),
+            None => 0,
+        }
+    }
+
+    fn rule_at(&self, path: &[uint], index: uint) -> Option<CSSRule> {
+        let stylesheet = self.stylesheet.deref().borrow();
+        match nested_rule_list(&stylesheet.rules, path) {
+            Some(rules) => rules.as_slice().get(index).map(|rule| rule.clone()),
+            None => None,
+        }
+    }
+
+    // http://dev.w3.org/csswg/cssom/#insert-a-css-rule
+    fn insert_rule(&self, path: &[uint], rule: DOMString, index: u32) -> Fallible<u32> {
+        let index = index as uint;
+        let parsed = {
+            let mut stylesheet = self.stylesheet.deref().borrow_mut();
+            let base_url = stylesheet.base_url.clone();
+            let rules = match nested_rule_list_mut(&mut stylesheet.rules, path) {
+                Some(rules) => rules,
+                // The enclosing rule has been removed from the style sheet.
+                None => return Err(InvalidState),
+            };
+            let parsed = match Stylesheet::parse_rule(rule.as_slice(), &base_url) {
+                Some(parsed) => parsed,
+                None => return Err(Syntax),
+            };
+            if index > rules.len() {
+                return Err(IndexSize)
+            }
+            rules.insert(index, parsed.clone());
+            parsed
+        };
+        self.send_to_layout(InsertRuleMsg(self.index, Vec::from_slice(path), index, parsed));
+        self.restyle();
+        Ok(index as u32)
+    }
+
+    // http://dev.w3.org/csswg/cssom/#remove-a-css-rule
+    //
+    // Returns the rule that was removed.
+    fn delete_rule(&self, path: &[uint], index: u32) -> Fallible<CSSRule> {
+        let index = index as uint;
+        let deleted_rule = {
+            let mut stylesheet = self.stylesheet.deref().borrow_mut();
+            let rules = match nested_rule_list_mut(&mut stylesheet.rules, path) {
+                Some(rules) => rules,
+                None => return Err(InvalidState),
+            };
+            if index >= rules.len() {
+                return Err(IndexSize)
+            }
+            rules.remove(index).unwrap()
+        };
+        self.send_to_layout(DeleteRuleMsg(self.index, Vec::from_slice(path), index));
+        self.restyle();
+        Ok(deleted_rule)
+    }
+}
+
+pub trait CSSStyleSheetMethods {
+    fn Type(&self) -> DOMString;
+    fn Disabled(&self) -> bool;
+    fn SetDisabled(&self, disabled: bool);
+    fn CssRules(&self) -> Temporary<CSSRuleList>;
+    fn InsertRule(&self, rule: DOMString, index: u32) -> Fallible<u32>;
+    fn DeleteRule(&self, index: u32) -> ErrorResult;
+}
+
+impl<'a> CSSStyleSheetMethods for JSRef<'a, CSSStyleSheet> {
+    fn Type(&self) -> DOMString {
+        "text/css".to_string()
+    }
+
+    fn Disabled(&self) -> bool {
+        self.disabled.deref().get()
+    }
+
+    fn SetDisabled(&self, disabled: bool) {
+        if self.disabled.deref().get() == disabled {
+            return
+        }
+        self.disabled.deref().set(disabled);
+        self.send_to_layout(SetStylesheetDisabledMsg(self.index, disabled));
+        self.restyle();
+    }
+
+    fn CssRules(&self) -> Temporary<CSSRuleList> {
+        self.rule_list()
+    }
+
+    fn InsertRule(&self, rule: DOMString, index: u32) -> Fallible<u32> {
+        self.rule_list().root().deref().insert_rule(rule, index)
+    }
+
+    fn DeleteRule(&self, index: u32) -> ErrorResult {
+        self.rule_list().root().deref().delete_rule(index)
+    }
+}
+
+impl Reflectable for CSSStyleSheet {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -16,6 +16,7 @@ use dom::bindings::error::{ErrorResult, Fallible, NotSupported, InvalidCharacter
 use dom::bindings::error::{HierarchyRequest, NamespaceError};
 use dom::bindings::utils::{xml_name_type, InvalidXMLName, Name, QName};
 use dom::comment::Comment;
+use dom::cssstylesheet::CSSStyleSheet;
 use dom::customevent::CustomEvent;
 use dom::documentfragment::DocumentFragment;
 use dom::documenttype::DocumentType;
@@ -32,22 +33,28 @@ use dom::htmlhtmlelement::HTMLHtmlElement;
 use dom::htmltitleelement::HTMLTitleElement;
 use dom::mouseevent::MouseEvent;
 use dom::node::{Node, ElementNodeTypeId, DocumentNodeTypeId, NodeHelpers, NodeMethods};
-use dom::node::{CloneChildren, DoNotCloneChildren};
+use dom::node::{CloneChildren, DoNotCloneChildren, from_untrusted_node_address};
 use dom::nodelist::NodeList;
 use dom::text::Text;
 use dom::processinginstruction::ProcessingInstruction;
+use dom::stylesheetlist::StyleSheetList;
 use dom::uievent::UIEvent;
 use dom::window::{Window, WindowMethods, WindowHelpers};
 use dom::location::Location;
 use html::hubbub_html_parser::build_element_from_tag;
 use hubbub::hubbub::{QuirksMode, NoQuirks, LimitedQuirks, FullQuirks};
-use layout_interface::{DocumentDamageLevel, ContentChangedDocumentDamage};
+use layout_interface::{AddStylesheetMsg, DocumentDamageLevel, ContentChangedDocumentDamage};
+use layout_interface::PendingRestyle;
+use layout_interface::LayoutChan;
+use servo_msg::compositor_msg::LayerId;
 use servo_util::namespace;
 use servo_util::namespace::{Namespace, Null};
 use servo_util::str::{DOMString, null_str_as_empty_ref};
+use style::Stylesheet;
 
 use std::collections::hashmap::HashMap;
-use js::jsapi::JSContext;
+use geom::point::Point2D;
+use js::jsapi::{JSContext, JS_GetRuntime};
 use std::ascii::StrAsciiExt;
 use std::cell::{Cell, RefCell};
 use url::{Url, from_str};
@@ -70,6 +77,9 @@ pub struct Document {
     pub is_html_document: bool,
     url: Untraceable<Url>,
     quirks_mode: Untraceable<Cell<QuirksMode>>,
+    /// The style sheets of this document, in the order they were sent to layout.
+    stylesheets: Traceable<RefCell<Vec<JS<CSSStyleSheet>>>>,
+    stylesheet_list: Cell<Option<JS<StyleSheetList>>>,
 }
 
 impl DocumentDerived for EventTarget {
@@ -85,10 +95,17 @@ pub trait DocumentHelpers {
     fn set_encoding_name(&self, name: DOMString);
     fn content_changed(&self);
     fn damage_and_reflow(&self, damage: DocumentDamageLevel);
+    fn restyle_and_reflow(&self, restyle: PendingRestyle);
+    fn queue_restyle(&self, restyle: PendingRestyle);
+    fn queue_damage(&self, damage: DocumentDamageLevel);
+    fn reflow(&self);
     fn wait_until_safe_to_modify_dom(&self);
     fn unregister_named_element(&self, to_unregister: &JSRef<Element>, id: DOMString);
     fn register_named_element(&self, element: &JSRef<Element>, id: DOMString);
     fn load_anchor_href(&self, href: DOMString);
+    fn add_stylesheet(&self, stylesheet: Stylesheet) -> Temporary<CSSStyleSheet>;
+    fn stylesheet_count(&self) -> uint;
+    fn stylesheet_at(&self, index: uint) -> Option<Temporary<CSSStyleSheet>>;
 }
 
 impl<'a> DocumentHelpers for JSRef<'a, Document> {
@@ -116,6 +133,22 @@ impl<'a> DocumentHelpers for JSRef<'a, Document> {
         self.window.root().damage_and_reflow(damage);
     }
 
+    fn restyle_and_reflow(&self, restyle: PendingRestyle) {
+        self.window.root().restyle_and_reflow(restyle);
+    }
+
+    fn queue_restyle(&self, restyle: PendingRestyle) {
+        self.window.root().queue_restyle(restyle);
+    }
+
+    fn queue_damage(&self, damage: DocumentDamageLevel) {
+        self.window.root().queue_damage(damage);
+    }
+
+    fn reflow(&self) {
+        self.window.root().reflow();
+    }
+
     fn wait_until_safe_to_modify_dom(&self) {
         self.window.root().wait_until_safe_to_modify_dom();
     }
@@ -189,6 +222,27 @@ impl<'a> DocumentHelpers for JSRef<'a, Document> {
         let window = self.window.root();
         window.load_url(href);
     }
+
+    /// Adds a style sheet after all the existing ones, and sends it to layout.
+    fn add_stylesheet(&self, stylesheet: Stylesheet) -> Temporary<CSSStyleSheet> {
+        let window = self.window.root();
+        let index = self.stylesheets.deref().borrow().len();
+        let LayoutChan(ref layout_chan) = *window.deref().page().layout_chan;
+        layout_chan.send(AddStylesheetMsg(stylesheet.clone()));
+        let stylesheet = CSSStyleSheet::new(&*window, self, index, stylesheet).root();
+        self.stylesheets.deref().borrow_mut().push(JS::from_rooted(&*stylesheet));
+        Temporary::from_rooted(&*stylesheet)
+    }
+
+    fn stylesheet_count(&self) -> uint {
+        self.stylesheets.deref().borrow().len()
+    }
+
+    fn stylesheet_at(&self, index: uint) -> Option<Temporary<CSSStyleSheet>> {
+        self.stylesheets.deref().borrow().as_slice().get(index).map(|stylesheet| {
+            Temporary::new(stylesheet.clone())
+        })
+    }
 }
 
 impl Document {
@@ -233,6 +287,8 @@ impl Document {
             // http://dom.spec.whatwg.org/#concept-document-encoding
             encoding_name: Traceable::new(RefCell::new("utf-8".to_string())),
             is_html_document: is_html_document == HTMLDocument,
+            stylesheets: Traceable::new(RefCell::new(vec!())),
+            stylesheet_list: Cell::new(None),
         }
     }
 
@@ -326,6 +382,8 @@ pub trait DocumentMethods {
     fn Anchors(&self) -> Temporary<HTMLCollection>;
     fn Applets(&self) -> Temporary<HTMLCollection>;
     fn Location(&self) -> Temporary<Location>;
+    fn StyleSheets(&self) -> Temporary<StyleSheetList>;
+    fn ElementFromPoint(&self, x: f64, y: f64) -> Option<Temporary<Element>>;
     fn Children(&self) -> Temporary<HTMLCollection>;
     fn QuerySelector(&self, selectors: DOMString) -> Fallible<Option<Temporary<Element>>>;
     fn QuerySelectorAll(&self, selectors: DOMString) -> Fallible<Temporary<NodeList>>;
@@ -808,6 +866,41 @@ impl<'a> DocumentMethods for JSRef<'a, Document> {
         window.Location()
     }
 
+    // http://dev.w3.org/csswg/cssom/#dom-document-stylesheets
+    fn StyleSheets(&self) -> Temporary<StyleSheetList> {
+        if self.stylesheet_list.get().is_none() {
+            let window = self.window.root();
+            self.stylesheet_list.assign(Some(StyleSheetList::new(&*window, self)));
+        }
+        Temporary::new(self.stylesheet_list.get().get_ref().clone())
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-document-elementfrompoint
+    fn ElementFromPoint(&self, x: f64, y: f64) -> Option<Temporary<Element>> {
+        if x < 0.0 || y < 0.0 {
+            return None
+        }
+        let window = self.window.root();
+        let page = window.deref().page();
+        // The point is relative to the viewport, and hit testing is in page coordinates.
+        let page_scroll_offset = page.scroll_offset(LayerId::null());
+        let point = Point2D(x as f32 + page_scroll_offset.x, y as f32 + page_scroll_offset.y);
+        page.hit_test(&point).and_then(|address| {
+            // Text is hit instead of the element that contains it.
+            let runtime = unsafe { JS_GetRuntime(window.deref().get_cx()) };
+            let node = from_untrusted_node_address(runtime, address).root();
+            let node = if node.deref().is_element() {
+                Some(node.deref().clone())
+            } else {
+                node.deref().ancestors().find(|ancestor| ancestor.is_element())
+            };
+            node.map(|node| {
+                let element: &JSRef<Element> = ElementCast::to_ref(&node).unwrap();
+                Temporary::from_rooted(element)
+            })
+        })
+    }
+
     // http://dom.spec.whatwg.org/#dom-parentnode-children
     fn Children(&self) -> Temporary<HTMLCollection> {
         let window = self.window.root();
@@ -44,6 +44,7 @@ impl DOMErrorName {
             error::NotFound => NotFoundError,
             error::HierarchyRequest => HierarchyRequestError,
             error::InvalidCharacter => InvalidCharacterError,
+            error::NoModificationAllowed => NoModificationAllowedError,
             error::NotSupported => NotSupportedError,
             error::InvalidState => InvalidStateError,
             error::Syntax => SyntaxError,
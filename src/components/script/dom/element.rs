@@ -17,6 +17,7 @@ use dom::bindings::error::{ErrorResult, Fallible, NamespaceError, InvalidCharact
 use dom::bindings::utils::{QName, Name, InvalidXMLName, xml_name_type};
 use dom::clientrect::ClientRect;
 use dom::clientrectlist::ClientRectList;
+use dom::cssstyledeclaration::CSSStyleDeclaration;
 use dom::document::{Document, DocumentHelpers};
 use dom::domtokenlist::DOMTokenList;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
@@ -26,15 +27,19 @@ use dom::node::{ElementNodeTypeId, Node, NodeHelpers, NodeIterator, document_fro
 use dom::node::{window_from_node, LayoutNodeHelpers};
 use dom::nodelist::NodeList;
 use dom::virtualmethods::{VirtualMethods, vtable_for};
-use layout_interface::ContentChangedDocumentDamage;
-use layout_interface::MatchSelectorsDocumentDamage;
+use dom::window::WindowHelpers;
+use layout_interface::{AttributeRestyle, ContentChangedDocumentDamage};
 use style;
+use servo_msg::compositor_msg::LayerId;
+use servo_util::geometry::Au;
 use servo_util::namespace;
 use servo_util::namespace::{Namespace, Null};
 use servo_util::str::{DOMString, null_str_as_empty_ref, split_html_space_chars};
 
+use geom::point::Point2D;
 use std::ascii::StrAsciiExt;
 use std::cell::{Cell, RefCell};
+use std::cmp;
 use std::mem;
 
 #[deriving(Encodable)]
@@ -47,6 +52,7 @@ pub struct Element {
     pub style_attribute: Traceable<RefCell<Option<style::PropertyDeclarationBlock>>>,
     pub attr_list: Cell<Option<JS<AttrList>>>,
     class_list: Cell<Option<JS<DOMTokenList>>>,
+    style_declaration: Cell<Option<JS<CSSStyleDeclaration>>>,
 }
 
 impl ElementDerived for EventTarget {
@@ -151,6 +157,7 @@ impl Element {
             attrs: RefCell::new(vec!()),
             attr_list: Cell::new(None),
             class_list: Cell::new(None),
+            style_declaration: Cell::new(None),
             style_attribute: Traceable::new(RefCell::new(None)),
         }
     }
@@ -218,6 +225,20 @@ impl<'a> ElementHelpers for JSRef<'a, Element> {
     }
 }
 
+trait PrivateElementHelpers {
+    fn scroll_layer_id(&self) -> LayerId;
+}
+
+impl<'a> PrivateElementHelpers for JSRef<'a, Element> {
+    /// Returns the ID of the layer that layout gives this element if it is a scroll container.
+    ///
+    /// FIXME(#2010): This must match `BlockFlow::layer_id()` in layout, which identifies the node
+    /// by the address of its reflector. It should ask layout instead.
+    fn scroll_layer_id(&self) -> LayerId {
+        LayerId(self.reflector().get_jsobject() as uint, 0)
+    }
+}
+
 pub trait AttributeHandlers {
     fn get_attribute(&self, namespace: Namespace, name: &str) -> Option<Temporary<Attr>>;
     fn set_attribute_from_parser(&self, local_name: DOMString,
@@ -231,7 +252,8 @@ pub trait AttributeHandlers {
                        value: DOMString) -> AttrValue;
 
     fn remove_attribute(&self, namespace: Namespace, name: DOMString) -> ErrorResult;
-    fn notify_attribute_changed(&self, local_name: DOMString);
+    fn notify_attribute_changed(&self, local_name: DOMString, value: DOMString);
+    fn reflow_after_attribute_change(&self);
     fn has_class(&self, name: &str) -> bool;
 
     // http://www.whatwg.org/html/#reflecting-content-attributes-in-idl-attributes
@@ -335,21 +357,41 @@ impl<'a> AttributeHandlers for JSRef<'a, Element> {
                 }
 
                 self.deref().attrs.borrow_mut().remove(idx);
+
+                if namespace == namespace::Null {
+                    self.reflow_after_attribute_change();
+                }
             }
         };
 
         Ok(())
     }
 
-    fn notify_attribute_changed(&self, local_name: DOMString) {
+    /// Called with the new value after an attribute is set, and with the old value before it
+    /// is removed. This only queues the restyle; `reflow_after_attribute_change` must be called
+    /// once the attribute list reflects the change, so that replacing a value queues both the
+    /// old and the new value before layout runs.
+    fn notify_attribute_changed(&self, local_name: DOMString, value: DOMString) {
         let node: &JSRef<Node> = NodeCast::from_ref(self);
         if node.is_in_doc() {
-            let damage = match local_name.as_slice() {
-                "style" | "id" | "class" => MatchSelectorsDocumentDamage,
-                _ => ContentChangedDocumentDamage
-            };
             let document = node.owner_doc().root();
-            document.deref().damage_and_reflow(damage);
+            // Layout restyles what the selectors that test the attribute can match. Presentational
+            // hints, and the attributes that layout reads while it builds flows, need everything
+            // to be restyled and rebuilt.
+            if style::is_presentational_hint_attribute(local_name.as_slice()) ||
+                    is_layout_attribute(local_name.as_slice()) {
+                document.deref().queue_damage(ContentChangedDocumentDamage)
+            }
+            let restyle = AttributeRestyle(node.to_trusted_node_address(), local_name, value);
+            document.deref().queue_restyle(restyle)
+        }
+    }
+
+    fn reflow_after_attribute_change(&self) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if node.is_in_doc() {
+            let document = node.owner_doc().root();
+            document.deref().reflow();
         }
     }
 
@@ -445,6 +487,15 @@ pub trait ElementMethods {
     fn GetElementsByClassName(&self, classes: DOMString) -> Temporary<HTMLCollection>;
     fn GetClientRects(&self) -> Temporary<ClientRectList>;
     fn GetBoundingClientRect(&self) -> Temporary<ClientRect>;
+    fn ScrollTop(&self) -> i32;
+    fn SetScrollTop(&self, scroll_top: i32);
+    fn ScrollLeft(&self) -> i32;
+    fn SetScrollLeft(&self, scroll_left: i32);
+    fn ScrollWidth(&self) -> i32;
+    fn ScrollHeight(&self) -> i32;
+    fn ClientWidth(&self) -> i32;
+    fn ClientHeight(&self) -> i32;
+    fn Style(&self) -> Temporary<CSSStyleDeclaration>;
     fn GetInnerHTML(&self) -> Fallible<DOMString>;
     fn GetOuterHTML(&self) -> Fallible<DOMString>;
     fn Children(&self) -> Temporary<HTMLCollection>;
@@ -725,6 +776,78 @@ impl<'a> ElementMethods for JSRef<'a, Element> {
             rect.origin.x + rect.size.width)
     }
 
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrolltop
+    fn ScrollTop(&self) -> i32 {
+        let win = window_from_node(self).root();
+        win.deref().page().scroll_offset(self.scroll_layer_id()).y as i32
+    }
+
+    fn SetScrollTop(&self, scroll_top: i32) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let range = match node.get_scroll_range() {
+            Some(range) => range,
+            None => return,
+        };
+        let win = window_from_node(self).root();
+        let offset = win.deref().page().scroll_offset(self.scroll_layer_id());
+        let offset = Point2D(offset.x, clamp_scroll_position(scroll_top, range.height));
+        win.deref().scroll_layer(self.scroll_layer_id(), offset)
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrollleft
+    fn ScrollLeft(&self) -> i32 {
+        let win = window_from_node(self).root();
+        win.deref().page().scroll_offset(self.scroll_layer_id()).x as i32
+    }
+
+    fn SetScrollLeft(&self, scroll_left: i32) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let range = match node.get_scroll_range() {
+            Some(range) => range,
+            None => return,
+        };
+        let win = window_from_node(self).root();
+        let offset = win.deref().page().scroll_offset(self.scroll_layer_id());
+        let offset = Point2D(clamp_scroll_position(scroll_left, range.width), offset.y);
+        win.deref().scroll_layer(self.scroll_layer_id(), offset)
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrollwidth
+    fn ScrollWidth(&self) -> i32 {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.get_scroll_size().width.to_nearest_px() as i32
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-scrollheight
+    fn ScrollHeight(&self) -> i32 {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.get_scroll_size().height.to_nearest_px() as i32
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-clientwidth
+    fn ClientWidth(&self) -> i32 {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.get_client_size().width.to_nearest_px() as i32
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-element-clientheight
+    fn ClientHeight(&self) -> i32 {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.get_client_size().height.to_nearest_px() as i32
+    }
+
+    // http://dev.w3.org/csswg/cssom/#dom-elementcssinlinestyle-style
+    fn Style(&self) -> Temporary<CSSStyleDeclaration> {
+        match self.style_declaration.get() {
+            Some(style_declaration) => Temporary::new(style_declaration),
+            None => {
+                let style_declaration = CSSStyleDeclaration::new(self, false).root();
+                self.style_declaration.assign(Some(style_declaration.deref().clone()));
+                Temporary::from_rooted(&*style_declaration)
+            }
+        }
+    }
+
     fn GetInnerHTML(&self) -> Fallible<DOMString> {
         //XXX TODO: XML case
         Ok(serialize(&mut NodeIterator::new(NodeCast::from_ref(self), false, false)))
@@ -772,6 +895,21 @@ pub fn get_attribute_parts(name: DOMString) -> (Option<String>, String) {
     (prefix, local_name)
 }
 
+/// Clamps a scroll position set by script to the range that the contents can be scrolled over.
+fn clamp_scroll_position(position: i32, max: Au) -> f32 {
+    cmp::min(cmp::max(position, 0), max.to_nearest_px() as i32) as f32
+}
+
+/// Returns whether layout reads the attribute named `name` while it builds flows, as for table
+/// cell spans, form control sizes and replaced content.
+fn is_layout_attribute(name: &str) -> bool {
+    match name {
+        "colspan" | "rowspan" | "span" | "cols" | "rows" | "size" | "type" | "value" |
+        "selected" | "src" | "data" | "width" | "height" => true,
+        _ => false,
+    }
+}
+
 impl<'a> VirtualMethods for JSRef<'a, Element> {
     fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods+> {
         let node: &JSRef<Node> = NodeCast::from_ref(self);
@@ -801,7 +939,8 @@ impl<'a> VirtualMethods for JSRef<'a, Element> {
             _ => ()
         }
 
-        self.notify_attribute_changed(name);
+        self.notify_attribute_changed(name, value);
+        self.reflow_after_attribute_change();
     }
 
     fn before_remove_attr(&self, name: DOMString, value: DOMString) {
@@ -818,13 +957,13 @@ impl<'a> VirtualMethods for JSRef<'a, Element> {
                 let node: &JSRef<Node> = NodeCast::from_ref(self);
                 if node.is_in_doc() {
                     let doc = document_from_node(self).root();
-                    doc.unregister_named_element(self, value);
+                    doc.unregister_named_element(self, value.clone());
                 }
             }
             _ => ()
         }
 
-        self.notify_attribute_changed(name);
+        self.notify_attribute_changed(name, value);
     }
 
     fn parse_plain_attribute(&self, name: &str, value: DOMString) -> AttrValue {
@@ -10,6 +10,7 @@ use dom::bindings::trace::Traceable;
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
 use dom::eventtarget::EventTarget;
 use dom::window::Window;
+use servo_msg::compositor_msg::LayerId;
 use servo_msg::constellation_msg::WindowSizeData;
 use servo_util::str::DOMString;
 use std::cell::{Cell, RefCell};
@@ -24,7 +25,10 @@ pub enum Event_ {
     ClickEvent(uint, Point2D<f32>),
     MouseDownEvent(uint, Point2D<f32>),
     MouseUpEvent(uint, Point2D<f32>),
-    MouseMoveEvent(Point2D<f32>)
+    MouseMoveEvent(Point2D<f32>),
+    /// The contents of the scroll container with the given layer were scrolled to the given
+    /// offset by the user. The layer is `LayerId::null()` when the page itself was scrolled.
+    ScrollEvent(LayerId, Point2D<f32>),
 }
 
 #[deriving(Encodable)]
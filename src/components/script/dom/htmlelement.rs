@@ -4,7 +4,7 @@
 
 use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
 use dom::bindings::codegen::Bindings::HTMLElementBinding;
-use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLFrameSetElementDerived};
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLFrameSetElementDerived, NodeCast};
 use dom::bindings::codegen::InheritTypes::EventTargetCast;
 use dom::bindings::codegen::InheritTypes::{HTMLElementDerived, HTMLBodyElementDerived};
 use dom::bindings::js::{JSRef, Temporary};
@@ -12,7 +12,7 @@ use dom::bindings::utils::{Reflectable, Reflector};
 use dom::document::Document;
 use dom::element::{Element, ElementTypeId, HTMLElementTypeId};
 use dom::eventtarget::{EventTarget, EventTargetHelpers, NodeTargetTypeId};
-use dom::node::{Node, ElementNodeTypeId, window_from_node};
+use dom::node::{Node, ElementNodeTypeId, NodeHelpers, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 use dom::window::WindowMethods;
 use servo_util::namespace;
@@ -62,6 +62,11 @@ pub trait HTMLElementMethods {
     fn SetOnclick(&self, listener: Option<EventHandlerNonNull>);
     fn GetOnload(&self) -> Option<EventHandlerNonNull>;
     fn SetOnload(&self, listener: Option<EventHandlerNonNull>);
+    fn GetOffsetParent(&self) -> Option<Temporary<Element>>;
+    fn OffsetTop(&self) -> i32;
+    fn OffsetLeft(&self) -> i32;
+    fn OffsetWidth(&self) -> i32;
+    fn OffsetHeight(&self) -> i32;
 }
 
 impl<'a> HTMLElementMethods for JSRef<'a, HTMLElement> {
@@ -90,6 +95,41 @@ impl<'a> HTMLElementMethods for JSRef<'a, HTMLElement> {
             win.deref().SetOnload(listener)
         }
     }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-htmlelement-offsetparent
+    fn GetOffsetParent(&self) -> Option<Temporary<Element>> {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let (parent, _) = node.get_offset();
+        parent
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-htmlelement-offsettop
+    fn OffsetTop(&self) -> i32 {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let (_, rect) = node.get_offset();
+        rect.origin.y.to_nearest_px() as i32
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-htmlelement-offsetleft
+    fn OffsetLeft(&self) -> i32 {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let (_, rect) = node.get_offset();
+        rect.origin.x.to_nearest_px() as i32
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-htmlelement-offsetwidth
+    fn OffsetWidth(&self) -> i32 {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let (_, rect) = node.get_offset();
+        rect.size.width.to_nearest_px() as i32
+    }
+
+    // http://dev.w3.org/csswg/cssom-view/#dom-htmlelement-offsetheight
+    fn OffsetHeight(&self) -> i32 {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let (_, rect) = node.get_offset();
+        rect.size.height.to_nearest_px() as i32
+    }
 }
 
 impl<'a> VirtualMethods for JSRef<'a, HTMLElement> {
@@ -2,20 +2,37 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::AttrValue;
 use dom::bindings::codegen::Bindings::HTMLInputElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLInputElementDerived;
-use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLInputElementCast};
+use dom::bindings::codegen::InheritTypes::{HTMLInputElementDerived, NodeCast};
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::trace::Traceable;
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
-use dom::element::HTMLInputElementTypeId;
+use dom::document::{Document, DocumentHelpers};
+use dom::element::{AttributeHandlers, Element, HTMLFormElementTypeId, HTMLInputElementTypeId};
+use dom::element::RawLayoutElementHelpers;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId};
+use dom::node::{Node, ElementNodeTypeId, NodeHelpers};
+use dom::virtualmethods::VirtualMethods;
+use servo_util::namespace;
 use servo_util::str::DOMString;
+use std::ascii::StrAsciiExt;
+use std::cell::{Cell, RefCell};
+
+/// The number of characters that a text field is wide enough for if it has no `size` attribute.
+static DEFAULT_INPUT_SIZE: u32 = 20;
 
 #[deriving(Encodable)]
 pub struct HTMLInputElement {
     pub htmlelement: HTMLElement,
+    /// The value that script set, which takes the place of the `value` attribute. This is `None`
+    /// until the value is first set (the "dirty value flag" in HTML).
+    value: Traceable<RefCell<Option<DOMString>>>,
+    /// The checkedness that script set, which takes the place of the `checked` attribute. This is
+    /// `None` until the checkedness is first set (the "dirty checkedness flag" in HTML).
+    checked: Traceable<Cell<Option<bool>>>,
 }
 
 impl HTMLInputElementDerived for EventTarget {
@@ -24,10 +41,66 @@ impl HTMLInputElementDerived for EventTarget {
     }
 }
 
+trait PrivateHTMLInputElementHelpers {
+    fn force_relayout(&self);
+    fn uncheck_other_radios_in_group(&self);
+}
+
+impl<'a> PrivateHTMLInputElementHelpers for JSRef<'a, HTMLInputElement> {
+    /// Makes layout show the current value and checkedness, which don't live in attributes and so
+    /// aren't noticed by layout otherwise.
+    fn force_relayout(&self) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if node.is_in_doc() {
+            let document = node.owner_doc().root();
+            document.deref().content_changed()
+        }
+    }
+
+    /// Unchecks the other radio buttons in this one's radio button group: those with the same
+    /// name and the same form owner.
+    fn uncheck_other_radios_in_group(&self) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let name = element.get_string_attribute("name");
+        if name.is_empty() {
+            return
+        }
+
+        // The group is in the subtree of the form owner, or else in the tree this input is in.
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let owner = form_owner(node);
+        let root = match owner {
+            Some(ref form) => form.clone(),
+            None => node.ancestors().last().unwrap_or(node.clone()),
+        };
+        for other in root.traverse_preorder() {
+            let other: &JSRef<HTMLInputElement> = match HTMLInputElementCast::to_ref(&other) {
+                Some(input) => input,
+                None => continue,
+            };
+            let other_element: &JSRef<Element> = ElementCast::from_ref(other);
+            if other != self && other.Type().as_slice() == "radio" &&
+                    other_element.get_string_attribute("name") == name &&
+                    form_owner(NodeCast::from_ref(other)) == owner {
+                other.checked.deref().set(Some(false))
+            }
+        }
+    }
+}
+
+/// Returns the form owner of a form control, which is its nearest `form` ancestor, if any.
+fn form_owner<'a>(node: &'a JSRef<Node>) -> Option<JSRef<'a, Node>> {
+    node.ancestors().find(|ancestor| {
+        ancestor.type_id() == ElementNodeTypeId(HTMLFormElementTypeId)
+    })
+}
+
 impl HTMLInputElement {
     pub fn new_inherited(localName: DOMString, document: &JSRef<Document>) -> HTMLInputElement {
         HTMLInputElement {
-            htmlelement: HTMLElement::new_inherited(HTMLInputElementTypeId, localName, document)
+            htmlelement: HTMLElement::new_inherited(HTMLInputElementTypeId, localName, document),
+            value: Traceable::new(RefCell::new(None)),
+            checked: Traceable::new(Cell::new(None)),
         }
     }
 
@@ -37,7 +110,148 @@ impl HTMLInputElement {
     }
 }
 
+pub trait LayoutHTMLInputElementHelpers {
+    unsafe fn get_value_for_layout(&self) -> String;
+    unsafe fn get_checked_state_for_layout(&self) -> bool;
+}
+
+impl LayoutHTMLInputElementHelpers for JS<HTMLInputElement> {
+    unsafe fn get_value_for_layout(&self) -> String {
+        let input = &*self.unsafe_get();
+        match *input.value.deref().borrow() {
+            Some(ref value) => value.clone(),
+            None => {
+                let value = input.htmlelement.element.get_attr_val_for_layout(&namespace::Null,
+                                                                               "value");
+                value.unwrap_or("").to_string()
+            }
+        }
+    }
+
+    unsafe fn get_checked_state_for_layout(&self) -> bool {
+        let input = &*self.unsafe_get();
+        match input.checked.deref().get() {
+            Some(checked) => checked,
+            None => {
+                input.htmlelement
+                     .element
+                     .get_attr_val_for_layout(&namespace::Null, "checked")
+                     .is_some()
+            }
+        }
+    }
+}
+
 pub trait HTMLInputElementMethods {
+    fn Checked(&self) -> bool;
+    fn SetChecked(&self, checked: bool);
+    fn Name(&self) -> DOMString;
+    fn SetName(&self, name: DOMString);
+    fn Size(&self) -> u32;
+    fn SetSize(&self, size: u32);
+    fn Type(&self) -> DOMString;
+    fn SetType(&self, type_: DOMString);
+    fn DefaultValue(&self) -> DOMString;
+    fn SetDefaultValue(&self, default_value: DOMString);
+    fn Value(&self) -> DOMString;
+    fn SetValue(&self, value: DOMString);
+}
+
+impl<'a> HTMLInputElementMethods for JSRef<'a, HTMLInputElement> {
+    fn Checked(&self) -> bool {
+        match self.checked.deref().get() {
+            Some(checked) => checked,
+            None => {
+                let element: &JSRef<Element> = ElementCast::from_ref(self);
+                element.get_attribute(namespace::Null, "checked").is_some()
+            }
+        }
+    }
+
+    fn SetChecked(&self, checked: bool) {
+        // Only one radio button in a group can be checked.
+        if checked && self.Type().as_slice() == "radio" {
+            self.uncheck_other_radios_in_group()
+        }
+        self.checked.deref().set(Some(checked));
+        self.force_relayout()
+    }
+
+    fn Name(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("name")
+    }
+
+    fn SetName(&self, name: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("name", name)
+    }
+
+    fn Size(&self) -> u32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        match element.get_uint_attribute("size") {
+            0 => DEFAULT_INPUT_SIZE,
+            size => size,
+        }
+    }
+
+    fn SetSize(&self, size: u32) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_uint_attribute("size", size)
+    }
+
+    // http://www.whatwg.org/html/#dom-input-type
+    fn Type(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let type_ = element.get_string_attribute("type").as_slice().to_ascii_lower();
+        match type_.as_slice() {
+            "button" | "checkbox" | "email" | "hidden" | "number" | "password" | "radio" |
+            "reset" | "search" | "submit" | "tel" | "text" | "url" => type_.clone(),
+            _ => "text".to_string(),
+        }
+    }
+
+    fn SetType(&self, type_: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("type", type_)
+    }
+
+    fn DefaultValue(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("value")
+    }
+
+    fn SetDefaultValue(&self, default_value: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("value", default_value)
+    }
+
+    fn Value(&self) -> DOMString {
+        match *self.value.deref().borrow() {
+            Some(ref value) => return value.clone(),
+            None => {}
+        }
+        self.DefaultValue()
+    }
+
+    fn SetValue(&self, value: DOMString) {
+        *self.value.deref().borrow_mut() = Some(value);
+        self.force_relayout()
+    }
+}
+
+impl<'a> VirtualMethods for JSRef<'a, HTMLInputElement> {
+    fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods+> {
+        let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::from_ref(self);
+        Some(htmlelement as &VirtualMethods+)
+    }
+
+    fn parse_plain_attribute(&self, name: &str, value: DOMString) -> AttrValue {
+        match name {
+            "size" => AttrValue::from_u32(value, DEFAULT_INPUT_SIZE),
+            _ => self.super_type().unwrap().parse_plain_attribute(name, value),
+        }
+    }
 }
 
 impl Reflectable for HTMLInputElement {
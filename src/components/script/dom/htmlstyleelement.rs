@@ -4,21 +4,26 @@
 
 use dom::bindings::codegen::Bindings::HTMLStyleElementBinding;
 use dom::bindings::codegen::InheritTypes::{HTMLElementCast, HTMLStyleElementDerived, NodeCast};
-use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalSettable};
 use dom::bindings::utils::{Reflectable, Reflector};
-use dom::document::Document;
+use dom::cssstylesheet::{CSSStyleSheet, CSSStyleSheetHelpers};
+use dom::document::{Document, DocumentHelpers};
 use dom::element::HTMLStyleElementTypeId;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, NodeMethods, NodeHelpers, ElementNodeTypeId, window_from_node};
+use dom::node::{Node, NodeMethods, NodeHelpers, ElementNodeTypeId, document_from_node};
+use dom::node::window_from_node;
 use dom::virtualmethods::VirtualMethods;
 use html::cssparse::parse_inline_css;
-use layout_interface::{AddStylesheetMsg, LayoutChan};
 use servo_util::str::DOMString;
 
+use std::cell::Cell;
+
 #[deriving(Encodable)]
 pub struct HTMLStyleElement {
     pub htmlelement: HTMLElement,
+    /// The style sheet parsed from this element's contents, once it has been in a document.
+    stylesheet: Cell<Option<JS<CSSStyleSheet>>>,
 }
 
 impl HTMLStyleElementDerived for EventTarget {
@@ -30,7 +35,8 @@ impl HTMLStyleElementDerived for EventTarget {
 impl HTMLStyleElement {
     pub fn new_inherited(localName: DOMString, document: &JSRef<Document>) -> HTMLStyleElement {
         HTMLStyleElement {
-            htmlelement: HTMLElement::new_inherited(HTMLStyleElementTypeId, localName, document)
+            htmlelement: HTMLElement::new_inherited(HTMLStyleElementTypeId, localName, document),
+            stylesheet: Cell::new(None),
         }
     }
 
@@ -60,8 +66,13 @@ impl<'a> StyleElementHelpers for JSRef<'a, HTMLStyleElement> {
 
         let data = node.GetTextContent().expect("Element.textContent must be a string");
         let sheet = parse_inline_css(url, data);
-        let LayoutChan(ref layout_chan) = *win.deref().page().layout_chan;
-        layout_chan.send(AddStylesheetMsg(sheet));
+        match self.stylesheet.get() {
+            Some(stylesheet) => stylesheet.root().deref().replace(sheet),
+            None => {
+                let document = document_from_node(self).root();
+                self.stylesheet.assign(Some(document.deref().add_stylesheet(sheet)));
+            }
+        }
     }
 }
 
@@ -32,14 +32,18 @@ use dom::text::Text;
 use dom::virtualmethods::{VirtualMethods, vtable_for};
 use dom::window::Window;
 use geom::rect::Rect;
+use geom::size::Size2D;
 use html::hubbub_html_parser::build_element_from_tag;
 use layout_interface::{ContentBoxQuery, ContentBoxResponse, ContentBoxesQuery, ContentBoxesResponse,
-                       LayoutChan, ReapLayoutDataMsg, TrustedNodeAddress, UntrustedNodeAddress};
+                       LayoutChan, MatchSelectorsDocumentDamage, ReapLayoutDataMsg, TrustedNodeAddress,
+                       UntrustedNodeAddress};
+use layout_interface::{ClientSizeQuery, ClientSizeResponse, OffsetQuery, OffsetResponse};
+use layout_interface::{ScrollSizeQuery, ScrollSizeResponse};
 use servo_util::geometry::Au;
 use servo_util::str::{DOMString, null_str_as_empty};
 use style::{parse_selector_list, matches_compound_selector, NamespaceMap};
 
-use js::jsapi::{JSContext, JSObject, JSRuntime};
+use js::jsapi::{JSContext, JSObject, JSRuntime, JS_GetRuntime};
 use js::jsfriendapi;
 use libc;
 use libc::uintptr_t;
@@ -118,7 +122,10 @@ bitflags! {
         #[doc = "Specifies whether this node is in a document."]
         static IsInDoc = 0x01,
         #[doc = "Specifies whether this node is hover state for this node"]
-        static InHoverState = 0x02
+        static InHoverState = 0x02,
+        #[doc = "Specifies whether this node must be laid out again even if its style did not"]
+        #[doc = "change, because its children or its character data changed."]
+        static IsDirty = 0x04
     }
 }
 
@@ -252,7 +259,10 @@ impl<'a> PrivateNodeHelpers for JSRef<'a, Node> {
         let parent = self.parent_node().root();
         parent.map(|parent| vtable_for(&*parent).child_inserted(self));
 
-        document.deref().content_changed();
+        // Layout rebuilds the flows of the parent because the damage of the new node propagates
+        // up, so there is no need to throw away every flow in the document.
+        self.set_dirty();
+        document.deref().damage_and_reflow(MatchSelectorsDocumentDamage);
     }
 
     // http://dom.spec.whatwg.org/#node-is-removed
@@ -265,7 +275,7 @@ impl<'a> PrivateNodeHelpers for JSRef<'a, Node> {
             vtable_for(&node).unbind_from_tree();
         }
 
-        document.deref().content_changed();
+        document.deref().damage_and_reflow(MatchSelectorsDocumentDamage);
     }
 
     //
@@ -379,6 +389,9 @@ pub trait NodeHelpers {
     fn get_hover_state(&self) -> bool;
     fn set_hover_state(&self, state: bool);
 
+    fn is_dirty(&self) -> bool;
+    fn set_dirty(&self);
+
     fn dump(&self);
     fn dump_indent(&self, indent: uint);
     fn debug_str(&self) -> String;
@@ -391,6 +404,10 @@ pub trait NodeHelpers {
 
     fn get_bounding_content_box(&self) -> Rect<Au>;
     fn get_content_boxes(&self) -> Vec<Rect<Au>>;
+    fn get_offset(&self) -> (Option<Temporary<Element>>, Rect<Au>);
+    fn get_client_size(&self) -> Size2D<Au>;
+    fn get_scroll_size(&self) -> Size2D<Au>;
+    fn get_scroll_range(&self) -> Option<Size2D<Au>>;
 
     fn query_selector(&self, selectors: DOMString) -> Fallible<Option<Temporary<Element>>>;
     fn query_selector_all(&self, selectors: DOMString) -> Fallible<Temporary<NodeList>>;
@@ -496,6 +513,16 @@ impl<'a> NodeHelpers for JSRef<'a, Node> {
         }
     }
 
+    fn is_dirty(&self) -> bool {
+        self.flags.deref().borrow().contains(IsDirty)
+    }
+
+    /// Marks this node as needing layout. Layout clears the flag once it has rebuilt the flows
+    /// for the node.
+    fn set_dirty(&self) {
+        self.flags.deref().borrow_mut().insert(IsDirty)
+    }
+
     /// Iterates over this node and all its descendants, in preorder.
     fn traverse_preorder<'a>(&'a self) -> TreeIterator<'a> {
         let mut nodes = vec!();
@@ -555,6 +582,58 @@ impl<'a> NodeHelpers for JSRef<'a, Node> {
         rects
     }
 
+    /// Returns the offset parent of this node and its border box relative to the padding box of
+    /// the offset parent, or to the initial containing block if that is the body element.
+    fn get_offset(&self) -> (Option<Temporary<Element>>, Rect<Au>) {
+        let window = window_from_node(self).root();
+        let page = window.deref().page();
+        let (chan, port) = channel();
+        let addr = self.to_trusted_node_address();
+        let OffsetResponse(parent, rect) = page.query_layout(OffsetQuery(addr, chan), port);
+        let parent = parent.map(|parent| {
+            let runtime = unsafe { JS_GetRuntime(window.deref().get_cx()) };
+            let parent = from_untrusted_node_address(runtime, parent).root();
+            let parent: &JSRef<Element> = ElementCast::to_ref(&*parent).unwrap();
+            Temporary::from_rooted(parent)
+        });
+        (parent, rect)
+    }
+
+    fn get_client_size(&self) -> Size2D<Au> {
+        let window = window_from_node(self).root();
+        let page = window.deref().page();
+        let (chan, port) = channel();
+        let addr = self.to_trusted_node_address();
+        let ClientSizeResponse(size) = page.query_layout(ClientSizeQuery(addr, chan), port);
+        size
+    }
+
+    fn get_scroll_size(&self) -> Size2D<Au> {
+        let window = window_from_node(self).root();
+        let page = window.deref().page();
+        let (chan, port) = channel();
+        let addr = self.to_trusted_node_address();
+        let ScrollSizeResponse(size, _) = page.query_layout(ScrollSizeQuery(addr, chan), port);
+        size
+    }
+
+    /// Returns how far the contents of this node can be scrolled horizontally and vertically, or
+    /// `None` if this node is not a scroll container.
+    fn get_scroll_range(&self) -> Option<Size2D<Au>> {
+        let window = window_from_node(self).root();
+        let page = window.deref().page();
+        let (chan, port) = channel();
+        let addr = self.to_trusted_node_address();
+        let ScrollSizeResponse(scroll_size, is_scroll_container) =
+            page.query_layout(ScrollSizeQuery(addr, chan), port);
+        if !is_scroll_container {
+            return None
+        }
+        let client_size = self.get_client_size();
+        Some(Size2D(Au::max(scroll_size.width - client_size.width, Au(0)),
+                    Au::max(scroll_size.height - client_size.height, Au(0))))
+    }
+
     // http://dom.spec.whatwg.org/#dom-parentnode-queryselector
     fn query_selector(&self, selectors: DOMString) -> Fallible<Option<Temporary<Element>>> {
         // Step 1.
@@ -724,12 +803,23 @@ impl LayoutNodeHelpers for JS<Node> {
 
 pub trait RawLayoutNodeHelpers {
     unsafe fn get_hover_state_for_layout(&self) -> bool;
+    unsafe fn is_dirty_for_layout(&self) -> bool;
+    unsafe fn clear_dirty_for_layout(&self);
 }
 
 impl RawLayoutNodeHelpers for Node {
     unsafe fn get_hover_state_for_layout(&self) -> bool {
         self.flags.deref().borrow().contains(InHoverState)
     }
+
+    unsafe fn is_dirty_for_layout(&self) -> bool {
+        self.flags.deref().borrow().contains(IsDirty)
+    }
+
+    /// Safe to call only while the script task is blocked waiting for layout.
+    unsafe fn clear_dirty_for_layout(&self) {
+        self.flags.deref().borrow_mut().remove(IsDirty)
+    }
 }
 
 
@@ -1220,6 +1310,7 @@ impl Node {
         // Step 6-7: mutation observers.
         // Step 8.
         parent.remove_child(node);
+        parent.set_dirty();
 
         node.deref().flags.deref().borrow_mut().remove(IsInDoc);
 
@@ -1580,8 +1671,9 @@ impl<'a> NodeMethods for JSRef<'a, Node> {
                 *characterdata.data.deref().borrow_mut() = value;
 
                 // Notify the document that the content of this node is different
+                self.set_dirty();
                 let document = self.owner_doc().root();
-                document.deref().content_changed();
+                document.deref().damage_and_reflow(MatchSelectorsDocumentDamage);
             }
             DoctypeNodeTypeId |
             DocumentNodeTypeId => {}
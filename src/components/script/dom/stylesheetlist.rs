@@ -0,0 +1,61 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::StyleSheetListBinding;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::cssstylesheet::CSSStyleSheet;
+use dom::document::{Document, DocumentHelpers};
+use dom::window::Window;
+
+/// A live list of the style sheets of a document.
+#[deriving(Encodable)]
+pub struct StyleSheetList {
+    reflector_: Reflector,
+    document: JS<Document>,
+}
+
+impl StyleSheetList {
+    pub fn new_inherited(document: &JSRef<Document>) -> StyleSheetList {
+        StyleSheetList {
+            reflector_: Reflector::new(),
+            document: JS::from_rooted(document),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, document: &JSRef<Document>) -> Temporary<StyleSheetList> {
+        reflect_dom_object(box StyleSheetList::new_inherited(document),
+                           window, StyleSheetListBinding::Wrap)
+    }
+}
+
+pub trait StyleSheetListMethods {
+    fn Length(&self) -> u32;
+    fn Item(&self, index: u32) -> Option<Temporary<CSSStyleSheet>>;
+    fn IndexedGetter(&self, index: u32, found: &mut bool) -> Option<Temporary<CSSStyleSheet>>;
+}
+
+impl<'a> StyleSheetListMethods for JSRef<'a, StyleSheetList> {
+    fn Length(&self) -> u32 {
+        let document = self.document.root();
+        document.deref().stylesheet_count() as u32
+    }
+
+    fn Item(&self, index: u32) -> Option<Temporary<CSSStyleSheet>> {
+        let document = self.document.root();
+        document.deref().stylesheet_at(index as uint)
+    }
+
+    fn IndexedGetter(&self, index: u32, found: &mut bool) -> Option<Temporary<CSSStyleSheet>> {
+        let stylesheet = self.Item(index);
+        *found = stylesheet.is_some();
+        stylesheet
+    }
+}
+
+impl Reflectable for StyleSheetList {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -10,21 +10,24 @@ use dom::bindings::trace::{Traceable, Untraceable};
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::browsercontext::BrowserContext;
 use dom::console::Console;
+use dom::cssstyledeclaration::CSSStyleDeclaration;
 use dom::document::Document;
+use dom::element::Element;
 use dom::eventtarget::{EventTarget, WindowTypeId, EventTargetHelpers};
 use dom::location::Location;
 use dom::navigator::Navigator;
 use dom::performance::Performance;
 
-use layout_interface::{ReflowForDisplay, DocumentDamageLevel};
+use layout_interface::{ReflowForDisplay, DocumentDamageLevel, PendingRestyle};
 use page::Page;
 use script_task::{ExitWindowMsg, FireTimerMsg, ScriptChan, TriggerLoadMsg, TriggerFragmentMsg};
-use servo_msg::compositor_msg::ScriptListener;
+use servo_msg::compositor_msg::{LayerId, ScriptListener};
 use servo_net::image_cache_task::ImageCacheTask;
 use servo_util::str::DOMString;
 use servo_util::task::{spawn_named};
 use servo_util::url::parse_url;
 
+use geom::point::Point2D;
 use js::jsapi::JSContext;
 use js::jsapi::{JS_GC, JS_GetRuntime};
 use js::jsval::JSVal;
@@ -130,6 +133,8 @@ pub trait WindowMethods {
     fn Window(&self) -> Temporary<Window>;
     fn Self(&self) -> Temporary<Window>;
     fn Performance(&self) -> Temporary<Performance>;
+    fn GetComputedStyle(&self, element: &JSRef<Element>, pseudo_element: Option<DOMString>)
+                        -> Temporary<CSSStyleDeclaration>;
     fn GetOnclick(&self) -> Option<EventHandlerNonNull>;
     fn SetOnclick(&self, listener: Option<EventHandlerNonNull>);
     fn GetOnload(&self) -> Option<EventHandlerNonNull>;
@@ -221,6 +226,13 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         Temporary::new(self.performance.get().get_ref().clone())
     }
 
+    // http://dev.w3.org/csswg/cssom/#dom-window-getcomputedstyle
+    // TODO: Computed style of pseudo-elements.
+    fn GetComputedStyle(&self, element: &JSRef<Element>, _pseudo_element: Option<DOMString>)
+                        -> Temporary<CSSStyleDeclaration> {
+        CSSStyleDeclaration::new(element, true)
+    }
+
     fn GetOnclick(&self) -> Option<EventHandlerNonNull> {
         let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
         eventtarget.get_event_handler_common("click")
@@ -280,9 +292,14 @@ impl Reflectable for Window {
 
 pub trait WindowHelpers {
     fn damage_and_reflow(&self, damage: DocumentDamageLevel);
+    fn restyle_and_reflow(&self, restyle: PendingRestyle);
+    fn queue_restyle(&self, restyle: PendingRestyle);
+    fn queue_damage(&self, damage: DocumentDamageLevel);
+    fn reflow(&self);
     fn wait_until_safe_to_modify_dom(&self);
     fn init_browser_context(&self, doc: &JSRef<Document>);
     fn load_url(&self, href: DOMString);
+    fn scroll_layer(&self, layer_id: LayerId, offset: Point2D<f32>);
 }
 
 trait PrivateWindowHelpers {
@@ -291,10 +308,27 @@ trait PrivateWindowHelpers {
 
 impl<'a> WindowHelpers for JSRef<'a, Window> {
     fn damage_and_reflow(&self, damage: DocumentDamageLevel) {
+        self.queue_damage(damage);
+        self.reflow();
+    }
+
+    fn restyle_and_reflow(&self, restyle: PendingRestyle) {
+        self.queue_restyle(restyle);
+        self.reflow();
+    }
+
+    fn queue_restyle(&self, restyle: PendingRestyle) {
+        self.page().restyle(restyle);
+    }
+
+    fn queue_damage(&self, damage: DocumentDamageLevel) {
+        self.page().damage(damage);
+    }
+
+    fn reflow(&self) {
         // FIXME This should probably be ReflowForQuery, not Display. All queries currently
         // currently rely on the display list, which means we can't destroy it by
         // doing a query reflow.
-        self.page().damage(damage);
         self.page().reflow(ReflowForDisplay, self.script_chan.clone(), *self.compositor);
     }
 
@@ -320,6 +354,13 @@ impl<'a> WindowHelpers for JSRef<'a, Window> {
             script_chan.send(TriggerLoadMsg(self.page.id, url));
         }
     }
+
+    /// Scrolls the contents of the scroll container with the given layer to the given offset.
+    /// The compositor clamps the offset to the contents and reports back where they ended up.
+    fn scroll_layer(&self, layer_id: LayerId, offset: Point2D<f32>) {
+        self.page().set_scroll_offset(layer_id, offset);
+        self.compositor.scroll_fragment_point(self.page().id, layer_id, offset)
+    }
 }
 
 impl<'a> PrivateWindowHelpers for JSRef<'a, Window> {
@@ -11,13 +11,16 @@ use dom::node::{Node, LayoutDataRef};
 
 use geom::point::Point2D;
 use geom::rect::Rect;
+use geom::size::Size2D;
 use libc::c_void;
 use script_task::{ScriptChan};
+use servo_msg::compositor_msg::LayerId;
 use servo_msg::constellation_msg::WindowSizeData;
 use servo_util::geometry::Au;
 use std::cmp;
+use std::collections::hashmap::HashMap;
 use std::comm::{channel, Receiver, Sender};
-use style::Stylesheet;
+use style::{CSSRule, Stylesheet};
 use url::Url;
 
 use serialize::{Encodable, Encoder};
@@ -29,6 +32,20 @@ pub enum Msg {
     /// Adds the given stylesheet to the document.
     AddStylesheetMsg(Stylesheet),
 
+    /// Replaces the contents of the stylesheet at the given index, in the order stylesheets were
+    /// added.
+    ReplaceStylesheetMsg(uint, Stylesheet),
+
+    /// Inserts a rule into a stylesheet. The arguments are the index of the stylesheet, the path
+    /// to the rule list (see `style::nested_rule_list`) and the index to insert at.
+    InsertRuleMsg(uint, Vec<uint>, uint, CSSRule),
+
+    /// Removes a rule from a stylesheet. The arguments are as for `InsertRuleMsg`.
+    DeleteRuleMsg(uint, Vec<uint>, uint),
+
+    /// Enables or disables the stylesheet at the given index.
+    SetStylesheetDisabledMsg(uint, bool),
+
     /// Requests a reflow.
     ReflowMsg(Box<Reflow>),
 
@@ -58,9 +75,25 @@ pub enum LayoutQuery {
     ContentBoxQuery(TrustedNodeAddress, Sender<ContentBoxResponse>),
     /// Requests the dimensions of all the content boxes, as in the `getClientRects()` call.
     ContentBoxesQuery(TrustedNodeAddress, Sender<ContentBoxesResponse>),
-    /// Requests the node containing the point of interest
-    HitTestQuery(TrustedNodeAddress, Point2D<f32>, Sender<Result<HitTestResponse, ()>>),
+    /// Requests the offset parent of a node and the node's border box relative to the padding box
+    /// of its offset parent, as in the `offsetParent` and `offsetTop` family of attributes.
+    OffsetQuery(TrustedNodeAddress, Sender<OffsetResponse>),
+    /// Requests the size of the padding box of a node less any scrollbars, as in the
+    /// `clientWidth` and `clientHeight` attributes.
+    ClientSizeQuery(TrustedNodeAddress, Sender<ClientSizeResponse>),
+    /// Requests the size of the area that the contents of a node can be scrolled over, as in the
+    /// `scrollWidth` and `scrollHeight` attributes, and whether the node is a scroll container.
+    ScrollSizeQuery(TrustedNodeAddress, Sender<ScrollSizeResponse>),
+    /// Requests the node containing the point of interest, in page coordinates. The map holds how
+    /// far the page (as `LayerId::null()`) and each scroll container are scrolled, by layer.
+    HitTestQuery(TrustedNodeAddress,
+                 Point2D<f32>,
+                 HashMap<LayerId, Point2D<f32>>,
+                 Sender<Result<HitTestResponse, ()>>),
     MouseOverQuery(TrustedNodeAddress, Point2D<f32>, Sender<Result<MouseOverResponse, ()>>),
+    /// Requests the serialized computed values of every longhand property of a node, with
+    /// `width` and `height` resolved to used values, as in the `getComputedStyle()` call.
+    ComputedStyleQuery(TrustedNodeAddress, Sender<ComputedStyleResponse>),
 }
 
 /// The address of a node known to be valid. These must only be sent from content -> layout,
@@ -83,7 +116,11 @@ pub type UntrustedNodeAddress = *c_void;
 
 pub struct ContentBoxResponse(pub Rect<Au>);
 pub struct ContentBoxesResponse(pub Vec<Rect<Au>>);
+pub struct OffsetResponse(pub Option<UntrustedNodeAddress>, pub Rect<Au>);
+pub struct ClientSizeResponse(pub Size2D<Au>);
+pub struct ScrollSizeResponse(pub Size2D<Au>, pub bool);
 pub struct HitTestResponse(pub UntrustedNodeAddress);
+pub struct ComputedStyleResponse(pub Vec<(String, String)>);
 pub struct MouseOverResponse(pub Vec<UntrustedNodeAddress>);
 
 /// Determines which part of the
@@ -91,6 +128,9 @@ pub struct MouseOverResponse(pub Vec<UntrustedNodeAddress>);
 pub enum DocumentDamageLevel {
     /// Reflow, but do not perform CSS selector matching.
     ReflowDocumentDamage,
+    /// Perform CSS selector matching only on the elements that the pending restyles can affect,
+    /// and reflow.
+    RestyleDocumentDamage,
     /// Perform CSS selector matching and reflow.
     MatchSelectorsDocumentDamage,
     /// Content changed; set full style damage and do the above.
@@ -113,6 +153,19 @@ pub struct DocumentDamage {
     pub root: TrustedNodeAddress,
     /// The amount of damage that occurred.
     pub level: DocumentDamageLevel,
+    /// The changes that may alter which selectors match an element. Only used at
+    /// `RestyleDocumentDamage`; higher levels restyle the whole document anyway.
+    pub restyles: Vec<PendingRestyle>,
+}
+
+/// A change to an element that may alter which selectors match it or its neighbours.
+#[deriving(Encodable)]
+pub enum PendingRestyle {
+    /// An attribute with the given name was added to the element with the given value, or was
+    /// removed from the element while it had that value.
+    AttributeRestyle(TrustedNodeAddress, String, String),
+    /// The element entered or left the hover state.
+    HoverStateRestyle(TrustedNodeAddress),
 }
 
 /// Why we're doing reflow.
@@ -164,6 +217,9 @@ fn test_add_damage() {
     }
 
     assert_add(ReflowDocumentDamage, ReflowDocumentDamage, ReflowDocumentDamage);
+    assert_add(ReflowDocumentDamage, RestyleDocumentDamage, RestyleDocumentDamage);
+    assert_add(RestyleDocumentDamage, MatchSelectorsDocumentDamage, MatchSelectorsDocumentDamage);
+    assert_add(MatchSelectorsDocumentDamage, RestyleDocumentDamage, MatchSelectorsDocumentDamage);
     assert_add(ContentChangedDocumentDamage, ContentChangedDocumentDamage, ContentChangedDocumentDamage);
     assert_add(ReflowDocumentDamage, MatchSelectorsDocumentDamage, MatchSelectorsDocumentDamage);
     assert_add(MatchSelectorsDocumentDamage, ReflowDocumentDamage, MatchSelectorsDocumentDamage);
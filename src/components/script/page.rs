@@ -13,14 +13,14 @@ use dom::node::{Node, NodeHelpers};
 use dom::window::Window;
 use layout_interface::{DocumentDamage};
 use layout_interface::{DocumentDamageLevel, HitTestQuery, HitTestResponse, LayoutQuery, MouseOverQuery, MouseOverResponse};
-use layout_interface::{LayoutChan, QueryMsg};
+use layout_interface::{LayoutChan, PendingRestyle, QueryMsg, RestyleDocumentDamage};
 use layout_interface::{Reflow, ReflowGoal, ReflowMsg};
 use layout_interface::UntrustedNodeAddress;
 use script_task::ScriptChan;
 
 use geom::point::Point2D;
 use js::rust::Cx;
-use servo_msg::compositor_msg::PerformingLayout;
+use servo_msg::compositor_msg::{LayerId, PerformingLayout};
 use servo_msg::compositor_msg::ScriptListener;
 use servo_msg::constellation_msg::{ConstellationChan, WindowSizeData};
 use servo_msg::constellation_msg::{PipelineId, SubpageId};
@@ -28,6 +28,7 @@ use servo_net::resource_task::ResourceTask;
 use servo_util::namespace::Null;
 use servo_util::str::DOMString;
 use std::cell::{Cell, RefCell, Ref, RefMut};
+use std::collections::hashmap::HashMap;
 use std::comm::{channel, Receiver, Empty, Disconnected};
 use std::mem::replace;
 use std::rc::Rc;
@@ -78,6 +79,10 @@ pub struct Page {
     /// Pending scroll to fragment event, if any
     pub fragment_node: Cell<Option<JS<Element>>>,
 
+    /// How far the contents of each scroll container are scrolled, by the ID of its layer. Scroll
+    /// containers that are not in this map are not scrolled.
+    scroll_offsets: Untraceable<RefCell<HashMap<LayerId, Point2D<f32>>>>,
+
     /// Associated resource task for use by DOM objects like XMLHttpRequest
     pub resource_task: Untraceable<ResourceTask>,
 
@@ -138,6 +143,7 @@ impl Page {
             next_subpage_id: Traceable::new(Cell::new(SubpageId(0))),
             resize_event: Untraceable::new(Cell::new(None)),
             fragment_node: Cell::new(None),
+            scroll_offsets: Untraceable::new(RefCell::new(HashMap::new())),
             last_reflow_id: Traceable::new(Cell::new(0)),
             resource_task: Untraceable::new(resource_task),
             constellation_chan: Untraceable::new(constellation_chan),
@@ -231,8 +237,8 @@ impl Page {
             None => {},
             Some(root) => {
                 let root: &JSRef<Node> = NodeCast::from_ref(&*root);
-                let mut damage = *self.damage.deref().borrow_mut();
-                match damage {
+                let mut damage = self.damage.deref().borrow_mut();
+                match *damage {
                     None => {}
                     Some(ref mut damage) => {
                         // FIXME(pcwalton): This is wrong. We should trace up to the nearest ancestor.
@@ -242,14 +248,28 @@ impl Page {
                     }
                 }
 
-                *self.damage.deref().borrow_mut() = Some(DocumentDamage {
+                *damage = Some(DocumentDamage {
                     root: root.to_trusted_node_address(),
                     level: level,
+                    restyles: vec!(),
                 })
             }
         };
     }
 
+    /// Adds restyle damage for a change that may alter which selectors match an element, so
+    /// that layout restyles only the elements the change can affect.
+    pub fn restyle(&self, restyle: PendingRestyle) {
+        self.damage(RestyleDocumentDamage);
+        match *self.damage.deref().borrow_mut() {
+            // Higher damage levels restyle the whole document.
+            Some(ref mut damage) if damage.level == RestyleDocumentDamage => {
+                damage.restyles.push(restyle)
+            }
+            _ => {}
+        }
+    }
+
     pub fn get_url(&self) -> Url {
         self.url().get_ref().ref0().clone()
     }
@@ -382,7 +402,9 @@ impl Page {
         let root = root.unwrap();
         let root: &JSRef<Node> = NodeCast::from_ref(&*root);
         let (chan, port) = channel();
-        let address = match self.query_layout(HitTestQuery(root.to_trusted_node_address(), *point, chan), port) {
+        let scroll_offsets = self.scroll_offsets.deref().borrow().clone();
+        let query = HitTestQuery(root.to_trusted_node_address(), *point, scroll_offsets, chan);
+        let address = match self.query_layout(query, port) {
             Ok(HitTestResponse(node_address)) => {
                 Some(node_address)
             }
@@ -414,6 +436,20 @@ impl Page {
         };
         address
     }
+
+    /// Returns how far the contents of the scroll container with the given layer are scrolled.
+    pub fn scroll_offset(&self, layer_id: LayerId) -> Point2D<f32> {
+        match self.scroll_offsets.deref().borrow().find(&layer_id) {
+            Some(offset) => *offset,
+            None => Point2D(0f32, 0f32),
+        }
+    }
+
+    /// Records how far the contents of the scroll container with the given layer are scrolled.
+    /// This does not scroll anything by itself.
+    pub fn set_scroll_offset(&self, layer_id: LayerId, offset: Point2D<f32>) {
+        self.scroll_offsets.deref().borrow_mut().insert(layer_id, offset);
+    }
 }
 
 /// Information for one frame in the browsing context.
@@ -73,6 +73,13 @@ pub mod dom {
     pub mod clientrectlist;
     pub mod comment;
     pub mod console;
+    pub mod css;
+    pub mod cssmediarule;
+    pub mod cssrule;
+    pub mod cssrulelist;
+    pub mod cssstyledeclaration;
+    pub mod cssstylerule;
+    pub mod cssstylesheet;
     pub mod customevent;
     pub mod document;
     pub mod documentfragment;
@@ -166,6 +173,7 @@ pub mod dom {
     pub mod performance;
     pub mod performancetiming;
     pub mod progressevent;
+    pub mod stylesheetlist;
     pub mod text;
     pub mod uievent;
     pub mod urlsearchparams;
@@ -11,9 +11,10 @@ use dom::bindings::js::{JS, JSRef, RootCollection, Temporary, OptionalSettable};
 use dom::bindings::js::OptionalRootable;
 use dom::bindings::utils::Reflectable;
 use dom::bindings::utils::{wrap_for_same_compartment, pre_wrap};
-use dom::document::{Document, HTMLDocument, DocumentHelpers};
-use dom::element::{Element};
+use dom::document::{Document, HTMLDocument, DocumentHelpers, DocumentMethods};
+use dom::element::{Element, AttributeHandlers};
 use dom::event::{Event_, ResizeEvent, ReflowEvent, ClickEvent, MouseDownEvent, MouseMoveEvent, MouseUpEvent};
+use dom::event::ScrollEvent;
 use dom::event::Event;
 use dom::uievent::UIEvent;
 use dom::eventtarget::{EventTarget, EventTargetHelpers};
@@ -24,8 +25,7 @@ use dom::xmlhttprequest::{TrustedXHRAddress, XMLHttpRequest, XHRProgress};
 use html::hubbub_html_parser::HtmlParserResult;
 use html::hubbub_html_parser::{HtmlDiscoveredStyle, HtmlDiscoveredScript};
 use html::hubbub_html_parser;
-use layout_interface::AddStylesheetMsg;
-use layout_interface::{LayoutChan, MatchSelectorsDocumentDamage};
+use layout_interface::{HoverStateRestyle, LayoutChan, RestyleDocumentDamage};
 use layout_interface::{ReflowDocumentDamage, ReflowForDisplay};
 use layout_interface::ContentChangedDocumentDamage;
 use layout_interface;
@@ -451,7 +451,9 @@ impl ScriptTask {
             let mut layout_join_port = page.layout_join_port.deref().borrow_mut();
             *layout_join_port = None;
         }
-        self.compositor.set_ready_state(FinishedLoading);
+        if !is_reftest_waiting(&*page) {
+            self.compositor.set_ready_state(FinishedLoading);
+        }
     }
 
     /// Handles a navigate forward or backward message.
@@ -588,8 +590,7 @@ impl ScriptTask {
                     js_scripts = Some(scripts);
                 }
                 Ok(HtmlDiscoveredStyle(sheet)) => {
-                    let LayoutChan(ref chan) = *page.layout_chan;
-                    chan.send(AddStylesheetMsg(sheet));
+                    document.deref().add_stylesheet(sheet);
                 }
                 Err(()) => break
             }
@@ -695,13 +696,17 @@ impl ScriptTask {
                 }
             }
 
-            // FIXME(pcwalton): This reflows the entire document and is not incremental-y.
+            // Layout sends this when an image finishes loading. The intrinsic size of the image
+            // is not part of any style, and layout does not know which nodes display it, so this
+            // throws away all the flows.
+            //
+            // FIXME: Mark only the nodes displaying the image as dirty.
             ReflowEvent => {
                 debug!("script got reflow event");
                 let page = get_page(&*self.page.borrow(), pipeline_id);
                 let frame = page.frame();
                 if frame.is_some() {
-                    page.damage(MatchSelectorsDocumentDamage);
+                    page.damage(ContentChangedDocumentDamage);
                     page.reflow(ReflowForDisplay, self.chan.clone(), self.compositor)
                 }
             }
@@ -796,9 +801,29 @@ impl ScriptTask {
                         }
 
                         if target_compare {
-                            if mouse_over_targets.is_some() {
-                                page.damage(MatchSelectorsDocumentDamage);
-                                page.reflow(ReflowForDisplay, self.chan.clone(), self.compositor);
+                            match *mouse_over_targets {
+                                Some(ref old_targets) => {
+                                    // Only the elements that entered or left the hover state
+                                    // need to be restyled.
+                                    page.damage(RestyleDocumentDamage);
+                                    let left = old_targets.iter().filter(|node| {
+                                        !target_list.contains(*node)
+                                    });
+                                    let entered = target_list.iter().filter(|node| {
+                                        !old_targets.contains(*node)
+                                    });
+                                    for node in left.chain(entered) {
+                                        let node = node.root();
+                                        if node.deref().is_in_doc() {
+                                            let address = node.deref().to_trusted_node_address();
+                                            page.restyle(HoverStateRestyle(address));
+                                        }
+                                    }
+                                    page.reflow(ReflowForDisplay,
+                                                self.chan.clone(),
+                                                self.compositor);
+                                }
+                                None => {}
                             }
                             *mouse_over_targets = Some(target_list);
                         }
@@ -807,6 +832,13 @@ impl ScriptTask {
                     None => {}
               }
             }
+
+            // The compositor sends this when the user scrolls the page or the contents of a scroll
+            // container.
+            ScrollEvent(layer_id, offset) => {
+                let page = get_page(&*self.page.borrow(), pipeline_id);
+                page.set_scroll_offset(layer_id, offset)
+            }
         }
     }
 
@@ -872,3 +904,20 @@ fn get_page(page: &Rc<Page>, pipeline_id: PipelineId) -> Rc<Page> {
         message for a layout channel that is not associated with this script task.\
          This is a bug.")
 }
+
+/// Returns true if the root element of the page's document has the `reftest-wait` class. Tests
+/// that change the document after it has loaded keep the class until they are done, and the page
+/// doesn't count as finished loading, which is when the output file is written, until they
+/// remove it.
+fn is_reftest_waiting(page: &Page) -> bool {
+    match *page.frame() {
+        None => false,
+        Some(ref frame) => {
+            let document = frame.document.root();
+            match document.deref().GetDocumentElement().root() {
+                None => false,
+                Some(root) => root.deref().has_class("reftest-wait"),
+            }
+        }
+    }
+}
@@ -0,0 +1,407 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Presentational hints for legacy HTML attributes such as `bgcolor` or `<font size>`.
+//!
+//! See the "Rendering" section of the HTML specification. Hints are treated as author-level
+//! declarations with zero specificity that come before all author style sheets (CSS 2.1 § 6.4.4).
+
+use std::ascii::StrAsciiExt;
+use std::char;
+use cssparser::{tokenize, Color, RGBA};
+use cssparser::ast::ComponentValue;
+
+use node::{TElement, TNode};
+use parsing_utils::one_component_value;
+use properties::{PropertyDeclaration, SpecifiedValue};
+use properties::{BackgroundColorDeclaration, ColorDeclaration, FontFamilyDeclaration};
+use properties::{FontSizeDeclaration, HeightDeclaration, MarginLeftDeclaration};
+use properties::{MarginRightDeclaration, TextAlignDeclaration, VerticalAlignDeclaration};
+use properties::{WidthDeclaration};
+use properties::{BorderTopWidthDeclaration, BorderRightWidthDeclaration};
+use properties::{BorderBottomWidthDeclaration, BorderLeftWidthDeclaration};
+use properties::{BorderTopStyleDeclaration, BorderRightStyleDeclaration};
+use properties::{BorderBottomStyleDeclaration, BorderLeftStyleDeclaration};
+use properties::{PaddingTopDeclaration, PaddingRightDeclaration};
+use properties::{PaddingBottomDeclaration, PaddingLeftDeclaration};
+use properties::longhands::{border_top_style, font_family, text_align, vertical_align};
+use properties::specified;
+use servo_util::namespace;
+
+
+/// Returns the presentational hints for the given element, in cascade order.
+///
+/// Most hints come from the element's own attributes. A few are inherited from an ancestor's
+/// attributes: table cells look at their table's `border` and `cellpadding`, and links look at the
+/// `link` attribute of `<body>`.
+pub fn synthesize_presentational_hints<E:TElement,N:TNode<E>>(node: &N)
+                                                              -> Vec<PropertyDeclaration> {
+    let mut hints = vec!();
+    let element = node.as_element();
+    if *element.get_namespace() != namespace::HTML {
+        return hints
+    }
+
+    let local_name = element.get_local_name().to_ascii_lower();
+    match local_name.as_slice() {
+        "body" => {
+            push_background_color_hint(&element, &mut hints);
+            match get_html_attr(&element, "text").and_then(parse_legacy_color) {
+                Some(color) => hints.push(ColorDeclaration(SpecifiedValue(color))),
+                None => {}
+            }
+        }
+        "table" => {
+            push_background_color_hint(&element, &mut hints);
+            push_size_hints(&element, &mut hints);
+            match get_html_attr(&element, "border").map(parse_border_attribute) {
+                Some(width) if width > 0 => push_border_hints(width, border_top_style::outset,
+                                                              &mut hints),
+                _ => {}
+            }
+            match get_html_attr(&element, "align") {
+                Some(align) if align.eq_ignore_ascii_case("center") => {
+                    hints.push(MarginLeftDeclaration(SpecifiedValue(specified::LPA_Auto)));
+                    hints.push(MarginRightDeclaration(SpecifiedValue(specified::LPA_Auto)));
+                }
+                _ => {}
+            }
+        }
+        "thead" | "tbody" | "tfoot" | "tr" => {
+            push_background_color_hint(&element, &mut hints);
+            push_alignment_hints(&element, &mut hints);
+        }
+        "td" | "th" => {
+            push_background_color_hint(&element, &mut hints);
+            push_size_hints(&element, &mut hints);
+            push_alignment_hints(&element, &mut hints);
+
+            match find_ancestor(node, "table") {
+                Some(table) => {
+                    let table = table.as_element();
+                    match get_html_attr(&table, "border").map(parse_border_attribute) {
+                        Some(width) if width > 0 => push_border_hints(1, border_top_style::inset,
+                                                                      &mut hints),
+                        _ => {}
+                    }
+                    match get_html_attr(&table, "cellpadding").and_then(parse_non_negative_integer) {
+                        Some(padding) => push_padding_hints(padding, &mut hints),
+                        None => {}
+                    }
+                }
+                None => {}
+            }
+        }
+        "div" | "p" | "caption" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            match get_html_attr(&element, "align").and_then(parse_text_align) {
+                Some(align) => hints.push(TextAlignDeclaration(SpecifiedValue(align))),
+                None => {}
+            }
+        }
+        "img" => {
+            match get_html_attr(&element, "border").and_then(parse_non_negative_integer) {
+                Some(width) => push_border_hints(width, border_top_style::solid, &mut hints),
+                None => {}
+            }
+        }
+        "font" => {
+            match get_html_attr(&element, "color").and_then(parse_legacy_color) {
+                Some(color) => hints.push(ColorDeclaration(SpecifiedValue(color))),
+                None => {}
+            }
+            match get_html_attr(&element, "size").and_then(parse_legacy_font_size) {
+                Some(size) => hints.push(FontSizeDeclaration(SpecifiedValue(size))),
+                None => {}
+            }
+            match get_html_attr(&element, "face") {
+                Some(face) => {
+                    let families: Vec<font_family::FontFamily> =
+                        face.split(',')
+                            .map(|family| family.trim())
+                            .filter(|family| !family.is_empty())
+                            .map(|family| font_family::FamilyName(family.to_string()))
+                            .collect();
+                    if !families.is_empty() {
+                        hints.push(FontFamilyDeclaration(SpecifiedValue(families)))
+                    }
+                }
+                None => {}
+            }
+        }
+        _ => {}
+    }
+
+    if element.get_link().is_some() {
+        match find_ancestor(node, "body") {
+            Some(body) => {
+                match get_html_attr(&body.as_element(), "link").and_then(parse_legacy_color) {
+                    Some(color) => hints.push(ColorDeclaration(SpecifiedValue(color))),
+                    None => {}
+                }
+            }
+            None => {}
+        }
+    }
+
+    hints
+}
+
+/// The attributes that `synthesize_presentational_hints` reads.
+static PRESENTATIONAL_HINT_ATTRIBUTES: &'static [&'static str] = &[
+    "align", "bgcolor", "border", "cellpadding", "color", "face", "height", "link", "size", "text",
+    "valign", "width",
+];
+
+/// Returns whether a change to the attribute named `name` can change presentational hints. Those
+/// of some attributes apply to descendants as well, so selectors can't tell what to restyle.
+pub fn is_presentational_hint_attribute(name: &str) -> bool {
+    PRESENTATIONAL_HINT_ATTRIBUTES.contains(&name)
+}
+
+fn get_html_attr<E:TElement>(element: &E, name: &str) -> Option<&'static str> {
+    element.get_attr(&namespace::Null, name)
+}
+
+fn find_ancestor<E:TElement,N:TNode<E>>(node: &N, local_name: &str) -> Option<N> {
+    let mut current = node.parent_node();
+    loop {
+        match current {
+            Some(ref ancestor) if ancestor.is_element() => {
+                if ancestor.as_element().get_local_name().eq_ignore_ascii_case(local_name) {
+                    return Some(ancestor.clone())
+                }
+            }
+            _ => return None,
+        }
+        current = current.unwrap().parent_node();
+    }
+}
+
+fn push_background_color_hint<E:TElement>(element: &E, hints: &mut Vec<PropertyDeclaration>) {
+    match get_html_attr(element, "bgcolor").and_then(parse_legacy_color) {
+        Some(color) => hints.push(BackgroundColorDeclaration(SpecifiedValue(RGBA(color)))),
+        None => {}
+    }
+}
+
+fn push_size_hints<E:TElement>(element: &E, hints: &mut Vec<PropertyDeclaration>) {
+    match get_html_attr(element, "width").and_then(parse_nonzero_dimension) {
+        Some(width) => hints.push(WidthDeclaration(SpecifiedValue(width))),
+        None => {}
+    }
+    match get_html_attr(element, "height").and_then(parse_nonzero_dimension) {
+        Some(height) => hints.push(HeightDeclaration(SpecifiedValue(height))),
+        None => {}
+    }
+}
+
+fn push_alignment_hints<E:TElement>(element: &E, hints: &mut Vec<PropertyDeclaration>) {
+    match get_html_attr(element, "align").and_then(parse_text_align) {
+        Some(align) => hints.push(TextAlignDeclaration(SpecifiedValue(align))),
+        None => {}
+    }
+    let vertical_align = match get_html_attr(element, "valign") {
+        Some(valign) => match valign.trim().to_ascii_lower().as_slice() {
+            "top" => Some(vertical_align::Specified_top),
+            "middle" | "center" => Some(vertical_align::Specified_middle),
+            "bottom" => Some(vertical_align::Specified_bottom),
+            "baseline" => Some(vertical_align::Specified_baseline),
+            _ => None,
+        },
+        None => None,
+    };
+    match vertical_align {
+        Some(vertical_align) => hints.push(VerticalAlignDeclaration(SpecifiedValue(vertical_align))),
+        None => {}
+    }
+}
+
+fn push_border_hints(width: uint,
+                     style: border_top_style::SpecifiedValue,
+                     hints: &mut Vec<PropertyDeclaration>) {
+    let width = specified::Length::from_px(width as f64);
+    hints.push(BorderTopWidthDeclaration(SpecifiedValue(width.clone())));
+    hints.push(BorderRightWidthDeclaration(SpecifiedValue(width.clone())));
+    hints.push(BorderBottomWidthDeclaration(SpecifiedValue(width.clone())));
+    hints.push(BorderLeftWidthDeclaration(SpecifiedValue(width)));
+    hints.push(BorderTopStyleDeclaration(SpecifiedValue(style.clone())));
+    hints.push(BorderRightStyleDeclaration(SpecifiedValue(style.clone())));
+    hints.push(BorderBottomStyleDeclaration(SpecifiedValue(style.clone())));
+    hints.push(BorderLeftStyleDeclaration(SpecifiedValue(style)));
+}
+
+fn push_padding_hints(padding: uint, hints: &mut Vec<PropertyDeclaration>) {
+    let padding = specified::LP_Length(specified::Length::from_px(padding as f64));
+    hints.push(PaddingTopDeclaration(SpecifiedValue(padding.clone())));
+    hints.push(PaddingRightDeclaration(SpecifiedValue(padding.clone())));
+    hints.push(PaddingBottomDeclaration(SpecifiedValue(padding.clone())));
+    hints.push(PaddingLeftDeclaration(SpecifiedValue(padding)));
+}
+
+/// Splits off the leading run of ASCII digits, after skipping leading whitespace.
+fn split_leading_digits<'a>(input: &'a str) -> (&'a str, &'a str) {
+    let input = input.trim_left();
+    let end = input.find(|c: char| !char::is_digit(c)).unwrap_or(input.len());
+    (input.slice_to(end), input.slice_from(end))
+}
+
+/// The HTML "rules for parsing non-negative integers".
+fn parse_non_negative_integer(input: &str) -> Option<uint> {
+    let input = input.trim_left();
+    let input = if input.starts_with("+") { input.slice_from(1) } else { input };
+    let (digits, _) = split_leading_digits(input);
+    from_str(digits)
+}
+
+/// The `border` attribute of tables: an empty or invalid value means a one-pixel border.
+fn parse_border_attribute(input: &str) -> uint {
+    parse_non_negative_integer(input).unwrap_or(1)
+}
+
+/// The HTML "rules for parsing non-zero dimension values": an integer, optionally followed by a
+/// fraction, optionally followed by `%`. Trailing garbage is ignored.
+fn parse_nonzero_dimension(input: &str) -> Option<specified::LengthOrPercentageOrAuto> {
+    let (integer, rest) = split_leading_digits(input);
+    if integer.is_empty() {
+        return None
+    }
+    let mut value: f64 = from_str(integer).unwrap();
+    let mut rest = rest;
+    if rest.starts_with(".") {
+        let (fraction, after_fraction) = split_leading_digits(rest.slice_from(1));
+        if !fraction.is_empty() {
+            let fraction: f64 = from_str(format!("0.{}", fraction).as_slice()).unwrap();
+            value += fraction;
+        }
+        rest = after_fraction;
+    }
+    if value == 0. {
+        return None
+    }
+    if rest.starts_with("%") {
+        Some(specified::LPA_Percentage(value / 100.))
+    } else {
+        Some(specified::LPA_Length(specified::Length::from_px(value)))
+    }
+}
+
+fn parse_text_align(input: &str) -> Option<text_align::SpecifiedValue> {
+    match input.trim().to_ascii_lower().as_slice() {
+        "left" => Some(text_align::left),
+        "right" => Some(text_align::right),
+        "center" | "middle" => Some(text_align::center),
+        "justify" => Some(text_align::justify),
+        _ => None,
+    }
+}
+
+/// A simplified version of the HTML "rules for parsing a legacy colour value": anything CSS
+/// accepts is used as is, and bare hexadecimal triplets like `ff0000` get an implied `#`.
+/// `transparent` is ignored.
+fn parse_legacy_color(input: &str) -> Option<RGBA> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("transparent") {
+        return None
+    }
+    match parse_css_color(input) {
+        Some(color) => return Some(color),
+        None => {}
+    }
+    if (input.len() == 3 || input.len() == 6) && input.chars().all(|c| c.is_digit_radix(16)) {
+        return parse_css_color(format!("#{}", input).as_slice())
+    }
+    None
+}
+
+fn parse_css_color(input: &str) -> Option<RGBA> {
+    let component_values: Vec<ComponentValue> =
+        tokenize(input).map(|(component_value, _)| component_value).collect();
+    match one_component_value(component_values.as_slice()).and_then(Color::parse) {
+        Some(RGBA(rgba)) => Some(rgba),
+        _ => None,
+    }
+}
+
+/// The `size` attribute of `<font>`: `1` to `7`, or relative to `3` with a leading sign.
+fn parse_legacy_font_size(input: &str) -> Option<specified::Length> {
+    let input = input.trim_left();
+    let (relative, sign, input) = if input.starts_with("+") {
+        (true, 1, input.slice_from(1))
+    } else if input.starts_with("-") {
+        (true, -1, input.slice_from(1))
+    } else {
+        (false, 1, input)
+    };
+    let (digits, _) = split_leading_digits(input);
+    let value: int = match from_str(digits) {
+        Some(value) => value,
+        None => return None,
+    };
+    let value = if relative { 3 + sign * value } else { value };
+    let px = match value {
+        value if value <= 1 => 10.,  // x-small
+        2 => 13.,  // small
+        3 => 16.,  // medium
+        4 => 18.,  // large
+        5 => 24.,  // x-large
+        6 => 32.,  // xx-large
+        _ => 48.,  // xxx-large
+    };
+    Some(specified::Length::from_px(px))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_legacy_color, parse_nonzero_dimension, parse_legacy_font_size};
+    use super::{parse_non_negative_integer, is_presentational_hint_attribute};
+    use properties::specified;
+
+    #[test]
+    fn test_parse_legacy_color() {
+        assert!(parse_legacy_color("red").is_some());
+        assert!(parse_legacy_color(" #00ff00 ").is_some());
+        let color = parse_legacy_color("0000ff").unwrap();
+        assert!(color.blue == 1. && color.red == 0. && color.green == 0.);
+        assert!(parse_legacy_color("transparent").is_none());
+        assert!(parse_legacy_color("").is_none());
+    }
+
+    #[test]
+    fn test_parse_nonzero_dimension() {
+        match parse_nonzero_dimension("50%") {
+            Some(specified::LPA_Percentage(value)) => assert_eq!(value, 0.5),
+            _ => fail!("expected a percentage"),
+        }
+        match parse_nonzero_dimension(" 100px") {
+            Some(specified::LPA_Length(_)) => {}
+            _ => fail!("expected a length"),
+        }
+        assert!(parse_nonzero_dimension("0").is_none());
+        assert!(parse_nonzero_dimension("abc").is_none());
+    }
+
+    #[test]
+    fn test_parse_non_negative_integer() {
+        assert_eq!(parse_non_negative_integer("3"), Some(3));
+        assert_eq!(parse_non_negative_integer(" +4px"), Some(4));
+        assert_eq!(parse_non_negative_integer("-4"), None);
+        assert_eq!(parse_non_negative_integer(""), None);
+    }
+
+    #[test]
+    fn test_is_presentational_hint_attribute() {
+        assert!(is_presentational_hint_attribute("bgcolor"));
+        assert!(is_presentational_hint_attribute("cellpadding"));
+        assert!(!is_presentational_hint_attribute("title"));
+        assert!(!is_presentational_hint_attribute("class"));
+    }
+
+    #[test]
+    fn test_parse_legacy_font_size() {
+        assert!(parse_legacy_font_size("3").is_some());
+        assert!(parse_legacy_font_size("+2").is_some());
+        assert!(parse_legacy_font_size("big").is_none());
+    }
+}
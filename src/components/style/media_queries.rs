@@ -12,12 +12,14 @@ use namespaces::NamespaceMap;
 use url::Url;
 
 
+#[deriving(Clone)]
 pub struct MediaRule {
     pub media_queries: MediaQueryList,
     pub rules: Vec<CSSRule>,
 }
 
 
+#[deriving(Clone)]
 pub struct MediaQueryList {
     // "not all" is omitted from the list.
     // An empty list never matches.
@@ -25,18 +27,20 @@ pub struct MediaQueryList {
 }
 
 // For now, this is a "Level 2 MQ", ie. a media type.
+#[deriving(Clone)]
 pub struct MediaQuery {
     media_type: MediaQueryType,
     // TODO: Level 3 MQ expressions
 }
 
 
+#[deriving(Clone)]
 pub enum MediaQueryType {
     All,  // Always true
     MediaType(MediaType),
 }
 
-#[deriving(PartialEq)]
+#[deriving(PartialEq, Clone)]
 pub enum MediaType {
     Screen,
     Print,
@@ -128,4 +132,19 @@ impl MediaQueryList {
             // TODO: match Level 3 expressions
         })
     }
+
+    /// Serializes this list, as for `CSSMediaRule.conditionText`.
+    pub fn to_css(&self) -> String {
+        if self.media_queries.is_empty() {
+            return "not all".to_string()
+        }
+        let queries: Vec<&'static str> = self.media_queries.iter().map(|mq| {
+            match mq.media_type {
+                All => "all",
+                MediaType(Screen) => "screen",
+                MediaType(Print) => "print",
+            }
+        }).collect();
+        queries.connect(", ")
+    }
 }
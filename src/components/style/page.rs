@@ -0,0 +1,364 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The `@page` rule, from CSS 2.1 § 13.2 and CSS Paged Media Level 3.
+
+use std::ascii::StrAsciiExt;
+use cssparser::{parse_declaration_list, ToCss};
+use cssparser::ast::*;
+
+use errors::{ErrorLoggerIterator, log_css_error};
+use properties::{computed, specified};
+use stylesheets::{CSSRule, CSSPageRule};
+use servo_util::geometry::Au;
+
+
+#[deriving(Clone)]
+pub struct PageRule {
+    /// The `size` descriptor, or `None` if it wasn't given.
+    pub size: Option<PageSize>,
+    pub margin_top: Option<specified::LengthOrPercentage>,
+    pub margin_right: Option<specified::LengthOrPercentage>,
+    pub margin_bottom: Option<specified::LengthOrPercentage>,
+    pub margin_left: Option<specified::LengthOrPercentage>,
+    /// The declaration block as written, for serialization.
+    pub declarations_text: String,
+}
+
+
+#[deriving(Clone, PartialEq)]
+pub enum PageSize {
+    /// `auto`, which uses the default page size.
+    AutoPageSize,
+    /// `portrait` or `landscape` alone, which uses the default page size in that orientation.
+    OrientedPageSize(PageOrientation),
+    /// A width and a height, from lengths or from a named paper size.
+    ExplicitPageSize(Au, Au),
+}
+
+
+#[deriving(Clone, PartialEq)]
+pub enum PageOrientation {
+    Portrait,
+    Landscape,
+}
+
+
+/// The size and margins of the pages a document is split into, after cascading all `@page` rules
+/// that apply.
+#[deriving(Clone, PartialEq, Show)]
+pub struct PageDescription {
+    pub width: Au,
+    pub height: Au,
+    pub margin_top: Au,
+    pub margin_right: Au,
+    pub margin_bottom: Au,
+    pub margin_left: Au,
+}
+
+impl PageDescription {
+    /// The page box used when no `@page` rule sets a size or margins: ISO A4 without margins.
+    pub fn default() -> PageDescription {
+        let (width, height) = named_page_size("a4").unwrap();
+        PageDescription {
+            width: width,
+            height: height,
+            margin_top: Au(0),
+            margin_right: Au(0),
+            margin_bottom: Au(0),
+            margin_left: Au(0),
+        }
+    }
+
+    /// Applies the descriptors that `rule` sets on top of this description.
+    pub fn apply(&mut self, rule: &PageRule) {
+        match rule.size {
+            None => {}
+            Some(AutoPageSize) => {
+                let default = PageDescription::default();
+                self.width = default.width;
+                self.height = default.height;
+            }
+            Some(OrientedPageSize(orientation)) => {
+                let default = PageDescription::default();
+                let (width, height) = oriented(default.width, default.height, orientation);
+                self.width = width;
+                self.height = height;
+            }
+            Some(ExplicitPageSize(width, height)) => {
+                self.width = width;
+                self.height = height;
+            }
+        }
+
+        // Percentages refer to the width of the page box for the left and right margins and to
+        // its height for the top and bottom ones.
+        let (width, height) = (self.width, self.height);
+        let resolve = |value: specified::LengthOrPercentage, reference: Au| {
+            match value {
+                specified::LP_Length(length) => {
+                    computed::compute_Au_with_font_size(length, initial_font_size())
+                }
+                specified::LP_Percentage(percentage) => reference.scale_by(percentage),
+            }
+        };
+        for &value in rule.margin_top.iter() { self.margin_top = resolve(value, height) }
+        for &value in rule.margin_right.iter() { self.margin_right = resolve(value, width) }
+        for &value in rule.margin_bottom.iter() { self.margin_bottom = resolve(value, height) }
+        for &value in rule.margin_left.iter() { self.margin_left = resolve(value, width) }
+    }
+
+    /// The width of the page area, which is what is left of the page box inside the margins.
+    pub fn content_width(&self) -> Au {
+        Au::max(self.width - self.margin_left - self.margin_right, Au(0))
+    }
+
+    /// The height of the page area.
+    pub fn content_height(&self) -> Au {
+        Au::max(self.height - self.margin_top - self.margin_bottom, Au(0))
+    }
+}
+
+
+/// Lengths in `@page` rules have no element to take a font size from, so `em` and `ex` are
+/// relative to the initial value of `font-size`.
+fn initial_font_size() -> Au {
+    Au::from_px(16)
+}
+
+
+pub fn parse_page_rule(rule: AtRule, parent_rules: &mut Vec<CSSRule>) {
+    // TODO: page selectors (`:first`, `:left` and `:right`).
+    if rule.prelude.as_slice().skip_whitespace().next().is_some() {
+        log_css_error(rule.location, "Page selectors are not supported yet");
+        return
+    }
+    let block = match rule.block {
+        Some(block) => block,
+        None => {
+            log_css_error(rule.location, "Invalid @page rule");
+            return
+        }
+    };
+    let declarations_text = block.iter().map(|&(ref c, _)| c).to_css();
+    let mut page_rule = PageRule {
+        size: None,
+        margin_top: None,
+        margin_right: None,
+        margin_bottom: None,
+        margin_left: None,
+        declarations_text: declarations_text.as_slice().trim().to_string(),
+    };
+    for item in ErrorLoggerIterator(parse_declaration_list(block.move_iter())) {
+        match item {
+            DeclAtRule(rule) => {
+                log_css_error(rule.location,
+                              format!("Unsupported at-rule in @page: @{:s}", rule.name).as_slice())
+            }
+            Declaration(Declaration{ location: location, name: name, value: value, .. }) => {
+                // TODO: `!important` in `@page` rules.
+                let name = name.as_slice().to_ascii_lower();
+                let valid = match name.as_slice() {
+                    "size" => parse_size(value.as_slice()).map(|size| {
+                        page_rule.size = Some(size)
+                    }),
+                    "margin" => parse_margin_shorthand(value.as_slice()).map(|margins| {
+                        let (top, right, bottom, left) = margins;
+                        page_rule.margin_top = Some(top);
+                        page_rule.margin_right = Some(right);
+                        page_rule.margin_bottom = Some(bottom);
+                        page_rule.margin_left = Some(left);
+                    }),
+                    "margin-top" => parse_margin(value.as_slice()).map(|margin| {
+                        page_rule.margin_top = Some(margin)
+                    }),
+                    "margin-right" => parse_margin(value.as_slice()).map(|margin| {
+                        page_rule.margin_right = Some(margin)
+                    }),
+                    "margin-bottom" => parse_margin(value.as_slice()).map(|margin| {
+                        page_rule.margin_bottom = Some(margin)
+                    }),
+                    "margin-left" => parse_margin(value.as_slice()).map(|margin| {
+                        page_rule.margin_left = Some(margin)
+                    }),
+                    _ => {
+                        log_css_error(location, format!(
+                            "Unsupported descriptor in @page: {}", name).as_slice());
+                        continue
+                    }
+                };
+                if valid.is_none() {
+                    log_css_error(location, format!(
+                        "Invalid value: {}:{}", name, value.iter().to_css()).as_slice())
+                }
+            }
+        }
+    }
+    parent_rules.push(CSSPageRule(page_rule))
+}
+
+
+/// auto | <length>{1,2} | [ <page-size> || [ portrait | landscape ] ]
+fn parse_size(input: &[ComponentValue]) -> Option<PageSize> {
+    let values: Vec<&ComponentValue> = input.skip_whitespace().collect();
+    let mut lengths = vec!();
+    let mut named_size = None;
+    let mut orientation = None;
+    for value in values.iter() {
+        match **value {
+            Ident(ref keyword) => {
+                let keyword = keyword.as_slice().to_ascii_lower();
+                match keyword.as_slice() {
+                    "auto" if values.len() == 1 => return Some(AutoPageSize),
+                    "portrait" if orientation.is_none() => orientation = Some(Portrait),
+                    "landscape" if orientation.is_none() => orientation = Some(Landscape),
+                    _ if named_size.is_none() => match named_page_size(keyword.as_slice()) {
+                        Some(size) => named_size = Some(size),
+                        None => return None,
+                    },
+                    _ => return None,
+                }
+            }
+            ref value => match specified::Length::parse_non_negative(value) {
+                Some(length) => {
+                    lengths.push(computed::compute_Au_with_font_size(length, initial_font_size()))
+                }
+                None => return None,
+            },
+        }
+    }
+    match (lengths.as_slice(), named_size, orientation) {
+        ([width], None, None) => Some(ExplicitPageSize(width, width)),
+        ([width, height], None, None) => Some(ExplicitPageSize(width, height)),
+        ([], Some((width, height)), None) => Some(ExplicitPageSize(width, height)),
+        ([], Some((width, height)), Some(orientation)) => {
+            let (width, height) = oriented(width, height, orientation);
+            Some(ExplicitPageSize(width, height))
+        }
+        ([], None, Some(orientation)) => Some(OrientedPageSize(orientation)),
+        _ => None,
+    }
+}
+
+
+/// The size of a paper size named in the `size` descriptor, in portrait orientation.
+fn named_page_size(name: &str) -> Option<(Au, Au)> {
+    let (width, height, unit) = match name {
+        "a5" => (148., 210., "mm"),
+        "a4" => (210., 297., "mm"),
+        "a3" => (297., 420., "mm"),
+        "b5" => (176., 250., "mm"),
+        "b4" => (250., 353., "mm"),
+        "letter" => (8.5, 11., "in"),
+        "legal" => (8.5, 14., "in"),
+        "ledger" => (11., 17., "in"),
+        _ => return None,
+    };
+    let to_au = |value| {
+        match specified::Length::parse_dimension(value, unit) {
+            Some(specified::Au_(length)) => length,
+            _ => fail!("paper sizes are given in absolute units"),
+        }
+    };
+    Some((to_au(width), to_au(height)))
+}
+
+
+fn oriented(width: Au, height: Au, orientation: PageOrientation) -> (Au, Au) {
+    match orientation {
+        Portrait => (Au::min(width, height), Au::max(width, height)),
+        Landscape => (Au::max(width, height), Au::min(width, height)),
+    }
+}
+
+
+/// <length> | <percentage>
+fn parse_margin(input: &[ComponentValue]) -> Option<specified::LengthOrPercentage> {
+    let mut iter = input.skip_whitespace();
+    match (iter.next(), iter.next()) {
+        (Some(value), None) => specified::LengthOrPercentage::parse(value),
+        _ => None,
+    }
+}
+
+
+/// [ <length> | <percentage> ]{1,4}, in the order top, right, bottom, left.
+fn parse_margin_shorthand(input: &[ComponentValue])
+                          -> Option<(specified::LengthOrPercentage,
+                                     specified::LengthOrPercentage,
+                                     specified::LengthOrPercentage,
+                                     specified::LengthOrPercentage)> {
+    let mut values = vec!();
+    for value in input.skip_whitespace() {
+        match specified::LengthOrPercentage::parse(value) {
+            Some(value) => values.push(value),
+            None => return None,
+        }
+    }
+    match values.as_slice() {
+        [all] => Some((all, all, all, all)),
+        [vertical, horizontal] => Some((vertical, horizontal, vertical, horizontal)),
+        [top, horizontal, bottom] => Some((top, horizontal, bottom, horizontal)),
+        [top, right, bottom, left] => Some((top, right, bottom, left)),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use servo_util::geometry::Au;
+    use stylesheets::{Stylesheet, CSSPageRule};
+    use super::PageDescription;
+
+    fn page_description(css: &str) -> PageDescription {
+        let stylesheet = Stylesheet::from_str(css, from_str("http://www.example.com/").unwrap());
+        let mut description = PageDescription::default();
+        for rule in stylesheet.rules.iter() {
+            match *rule {
+                CSSPageRule(ref rule) => description.apply(rule),
+                _ => {}
+            }
+        }
+        description
+    }
+
+    #[test]
+    fn test_page_size() {
+        let description = page_description("@page { size: 400px 300px }");
+        assert_eq!((description.width, description.height), (Au::from_px(400), Au::from_px(300)));
+        let description = page_description("@page { size: 500px }");
+        assert_eq!((description.width, description.height), (Au::from_px(500), Au::from_px(500)));
+        let description = page_description("@page { size: letter landscape }");
+        assert_eq!((description.width, description.height), (Au::from_px(1056), Au::from_px(816)));
+        let description = page_description("@page { size: landscape }");
+        assert!(description.width > description.height);
+
+        // Invalid sizes are ignored.
+        let description = page_description("@page { size: 400px 300px 200px }");
+        assert_eq!(description, PageDescription::default());
+        let description = page_description("@page { size: a4 letter }");
+        assert_eq!(description, PageDescription::default());
+    }
+
+    #[test]
+    fn test_page_margins() {
+        let description = page_description("@page { size: 400px 200px; margin: 10px 25% }");
+        assert_eq!(description.margin_top, Au::from_px(10));
+        assert_eq!(description.margin_right, Au::from_px(100));
+        assert_eq!(description.margin_bottom, Au::from_px(10));
+        assert_eq!(description.margin_left, Au::from_px(100));
+        assert_eq!(description.content_width(), Au::from_px(200));
+        assert_eq!(description.content_height(), Au::from_px(180));
+
+        // Later rules override the descriptors they set.
+        let description = page_description("@page { margin: 1in } @page { margin-left: 0 }");
+        assert_eq!(description.margin_top, Au::from_px(96));
+        assert_eq!(description.margin_left, Au(0));
+
+        // Page selectors aren't supported, so rules with them are dropped.
+        let description = page_description("@page :first { margin: 1in }");
+        assert_eq!(description, PageDescription::default());
+    }
+}
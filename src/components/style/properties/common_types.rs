@@ -4,12 +4,64 @@
 
 #![allow(non_camel_case_types)]
 
+use cssparser::{Color, CurrentColor, RGBA};
 pub use servo_util::geometry::Au;
+use servo_util::geometry;
 
 pub type CSSFloat = f64;
 
 pub static DEFAULT_LINE_HEIGHT: CSSFloat = 1.14;
 
+/// Serialization of computed values, as returned by `getComputedStyle()`.
+pub trait ToComputedCss {
+    fn to_computed_css(&self) -> String;
+}
+
+impl ToComputedCss for Au {
+    fn to_computed_css(&self) -> String {
+        format!("{}px", geometry::to_frac_px(*self))
+    }
+}
+
+impl ToComputedCss for CSSFloat {
+    fn to_computed_css(&self) -> String {
+        format!("{}", *self)
+    }
+}
+
+impl ToComputedCss for i32 {
+    fn to_computed_css(&self) -> String {
+        self.to_str()
+    }
+}
+
+/// Serializes a percentage stored as a fraction, as in `LP_Percentage`.
+pub fn percentage_to_css(value: CSSFloat) -> String {
+    format!("{}%", value * 100.)
+}
+
+impl ToComputedCss for RGBA {
+    fn to_computed_css(&self) -> String {
+        let component = |value: f32| (value * 255.).round() as u8;
+        if self.alpha == 1. {
+            format!("rgb({}, {}, {})",
+                    component(self.red), component(self.green), component(self.blue))
+        } else {
+            format!("rgba({}, {}, {}, {})",
+                    component(self.red), component(self.green), component(self.blue), self.alpha)
+        }
+    }
+}
+
+impl ToComputedCss for Color {
+    fn to_computed_css(&self) -> String {
+        match *self {
+            RGBA(ref rgba) => rgba.to_computed_css(),
+            CurrentColor => "currentColor".to_string(),
+        }
+    }
+}
+
 pub mod specified {
     use std::ascii::StrAsciiExt;
     use cssparser::ast;
@@ -183,7 +235,9 @@ pub mod computed {
         pub border_right_present: bool,
         pub border_bottom_present: bool,
         pub border_left_present: bool,
+        pub column_rule_present: bool,
         pub is_root_element: bool,
+        pub is_flex_item: bool,
         // TODO, as needed: root font size, viewport size, etc.
     }
 
@@ -239,6 +293,32 @@ pub mod computed {
         LPN_Percentage(CSSFloat),
         LPN_None,
     }
+    impl ToComputedCss for LengthOrPercentage {
+        fn to_computed_css(&self) -> String {
+            match *self {
+                LP_Length(length) => length.to_computed_css(),
+                LP_Percentage(value) => percentage_to_css(value),
+            }
+        }
+    }
+    impl ToComputedCss for LengthOrPercentageOrAuto {
+        fn to_computed_css(&self) -> String {
+            match *self {
+                LPA_Length(length) => length.to_computed_css(),
+                LPA_Percentage(value) => percentage_to_css(value),
+                LPA_Auto => "auto".to_string(),
+            }
+        }
+    }
+    impl ToComputedCss for LengthOrPercentageOrNone {
+        fn to_computed_css(&self) -> String {
+            match *self {
+                LPN_Length(length) => length.to_computed_css(),
+                LPN_Percentage(value) => percentage_to_css(value),
+                LPN_None => "none".to_string(),
+            }
+        }
+    }
     pub fn compute_LengthOrPercentageOrNone(value: specified::LengthOrPercentageOrNone,
                                             context: &Context) -> LengthOrPercentageOrNone {
         match value {
@@ -8,6 +8,8 @@ pub use std::ascii::StrAsciiExt;
 use serialize::{Encodable, Encoder};
 
 pub use servo_util::url::parse_url;
+use servo_util::geometry::{HorizontalTopToBottom, LogicalMargin, VerticalLeftToRight};
+use servo_util::geometry::{VerticalRightToLeft, WritingMode};
 use sync::Arc;
 pub use url::Url;
 
@@ -43,6 +45,8 @@ class Longhand(object):
             lambda m: m.group(1).upper(),
             self.ident.strip("_").capitalize())
         self.style_struct = THIS_STYLE_STRUCT
+        # The name of the common type of the computed value, for predefined types.
+        self.computed_type = None
         if derived_from is None:
             self.derived_from = None
         else:
@@ -160,6 +164,16 @@ pub mod longhands {
                         ${to_rust_ident(value)},
                     % endfor
                 }
+                impl super::super::ToComputedCss for T {
+                    fn to_computed_css(&self) -> String {
+                        let keyword = match *self {
+                            % for value in values.split():
+                                ${to_rust_ident(value)} => "${value}",
+                            % endfor
+                        };
+                        keyword.to_string()
+                    }
+                }
             }
             pub type SpecifiedValue = computed_value::T;
             #[inline] pub fn get_initial_value() -> computed_value::T {
@@ -200,6 +214,7 @@ pub mod longhands {
                 specified::${type}::${parse_method}(v)
             }
         </%self:single_component_value>
+        <% LONGHANDS_BY_NAME[name].computed_type = type %>
     </%def>
 
 
@@ -294,6 +309,7 @@ pub mod longhands {
             table inline-table table-row-group table-header-group table-footer-group
             table-row table-column-group table-column table-cell table-caption
             list-item
+            flex inline-flex
             none">
         #[inline]
         pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
@@ -301,9 +317,11 @@ pub mod longhands {
 //            if context.is_root_element && value == list_item {
 //                return block
 //            }
-            if context.positioned || context.floated || context.is_root_element {
+            if context.positioned || context.floated || context.is_root_element ||
+                    context.is_flex_item {
                 match value {
                     inline_table => table,
+                    inline_flex => flex,
                     inline | inline_block
                     | table_row_group | table_column | table_column_group
                     | table_header_group | table_footer_group | table_row
@@ -317,7 +335,7 @@ pub mod longhands {
         }
     </%self:single_keyword_computed>
 
-    ${single_keyword("position", "static absolute relative fixed")}
+    ${single_keyword("position", "static absolute relative fixed sticky")}
     ${single_keyword("float", "none left right")}
     ${single_keyword("clear", "none left right both")}
 
@@ -325,10 +343,16 @@ pub mod longhands {
 
     ${single_keyword("direction", "ltr rtl")}
 
-    // CSS 2.1, Section 10 - Visual formatting model details
+    // CSS Writing Modes Level 3, Sections 3.1 and 5.1
+    ${single_keyword("writing-mode", "horizontal-tb vertical-rl vertical-lr")}
+    ${single_keyword("text-orientation", "mixed upright sideways")}
 
     ${switch_to_style_struct("Box")}
 
+    ${single_keyword("unicode-bidi", "normal embed bidi-override")}
+
+    // CSS 2.1, Section 10 - Visual formatting model details
+
     ${predefined_type("width", "LengthOrPercentageOrAuto",
                       "computed::LPA_Auto",
                       "parse_non_negative")}
@@ -397,13 +421,22 @@ pub mod longhands {
             }
         }
         pub mod computed_value {
-            use super::super::{Au, CSSFloat};
+            use super::super::{Au, CSSFloat, ToComputedCss};
             #[deriving(PartialEq, Clone)]
             pub enum T {
                 Normal,
                 Length(Au),
                 Number(CSSFloat),
             }
+            impl ToComputedCss for T {
+                fn to_computed_css(&self) -> String {
+                    match *self {
+                        Normal => "normal".to_string(),
+                        Length(length) => length.to_computed_css(),
+                        Number(number) => number.to_str(),
+                    }
+                }
+            }
         }
         #[inline]
         pub fn get_initial_value() -> computed_value::T { Normal }
@@ -484,7 +517,7 @@ pub mod longhands {
             }
         }
         pub mod computed_value {
-            use super::super::{Au, CSSFloat};
+            use super::super::{Au, CSSFloat, ToComputedCss, percentage_to_css};
             #[allow(non_camel_case_types)]
             #[deriving(PartialEq, Clone)]
             pub enum T {
@@ -494,6 +527,17 @@ pub mod longhands {
                 Length(Au),
                 Percentage(CSSFloat),
             }
+            impl ToComputedCss for T {
+                fn to_computed_css(&self) -> String {
+                    match *self {
+                        % for keyword in vertical_align_keywords:
+                            ${to_rust_ident(keyword)} => "${keyword}".to_string(),
+                        % endfor
+                        Length(length) => length.to_computed_css(),
+                        Percentage(value) => percentage_to_css(value),
+                    }
+                }
+            }
         }
         #[inline]
         pub fn get_initial_value() -> computed_value::T { baseline }
@@ -541,6 +585,22 @@ pub mod longhands {
                     none,
                     Content(Vec<Content>),
                 }
+                impl super::super::ToComputedCss for T {
+                    fn to_computed_css(&self) -> String {
+                        match *self {
+                            normal => "normal".to_string(),
+                            none => "none".to_string(),
+                            Content(ref content) => {
+                                let strings: Vec<String> = content.iter().map(|item| {
+                                    match *item {
+                                        StringContent(ref value) => format!("\"{}\"", value),
+                                    }
+                                }).collect();
+                                strings.connect(" ")
+                            }
+                        }
+                    }
+                }
             }
             pub type SpecifiedValue = computed_value::T;
             #[inline] pub fn get_initial_value() -> computed_value::T  { normal }
@@ -583,6 +643,14 @@ pub mod longhands {
             pub mod computed_value {
                 pub use url::Url;
                 pub type T = Option<Url>;
+                impl super::super::ToComputedCss for T {
+                    fn to_computed_css(&self) -> String {
+                        match *self {
+                            Some(ref url) => format!("url({})", url.to_str()),
+                            None => "none".to_string(),
+                        }
+                    }
+                }
             }
             pub type SpecifiedValue = computed_value::T;
             #[inline] pub fn get_initial_value() -> SpecifiedValue {
@@ -605,12 +673,19 @@ pub mod longhands {
 
             pub mod computed_value {
                 use super::super::super::common_types::computed::LengthOrPercentage;
+                use super::super::ToComputedCss;
 
                 #[deriving(PartialEq, Clone)]
                 pub struct T {
                     pub horizontal: LengthOrPercentage,
                     pub vertical: LengthOrPercentage,
                 }
+                impl ToComputedCss for T {
+                    fn to_computed_css(&self) -> String {
+                        format!("{} {}", self.horizontal.to_computed_css(),
+                                self.vertical.to_computed_css())
+                    }
+                }
             }
 
             #[deriving(Clone)]
@@ -715,6 +790,16 @@ pub mod longhands {
 //                Monospace,
             }
             pub type T = Vec<FontFamily>;
+            impl super::super::ToComputedCss for T {
+                fn to_computed_css(&self) -> String {
+                    let names: Vec<&str> = self.iter().map(|family| {
+                        match *family {
+                            FamilyName(ref name) => name.as_slice(),
+                        }
+                    }).collect();
+                    names.connect(", ")
+                }
+            }
         }
         pub type SpecifiedValue = computed_value::T;
         #[inline] pub fn get_initial_value() -> computed_value::T { vec!(FamilyName("serif".to_string())) }
@@ -823,6 +908,16 @@ pub mod longhands {
                     Weight${weight},
                 % endfor
             }
+            impl super::super::ToComputedCss for T {
+                fn to_computed_css(&self) -> String {
+                    let weight = match *self {
+                        % for weight in range(100, 901, 100):
+                            Weight${weight} => "${weight}",
+                        % endfor
+                    };
+                    weight.to_string()
+                }
+            }
             impl T {
                 pub fn is_bold(self) -> bool {
                     match self {
@@ -899,8 +994,8 @@ pub mod longhands {
 
     ${new_style_struct("InheritedText", is_inherited=True)}
 
-    // TODO: initial value should be 'start' (CSS Text Level 3, direction-dependent.)
-    ${single_keyword("text-align", "left right center justify")}
+    // CSS Text Module Level 3, Section 7.1 - 'start' and 'end' depend on the paragraph's direction.
+    ${single_keyword("text-align", "start end left right center justify")}
 
     ${new_style_struct("Text", is_inherited=False)}
 
@@ -918,6 +1013,24 @@ pub mod longhands {
             pub type T = super::SpecifiedValue;
             pub static none: T = super::SpecifiedValue { underline: false, overline: false, line_through: false };
         }
+        impl super::ToComputedCss for SpecifiedValue {
+            fn to_computed_css(&self) -> String {
+                let mut keywords = vec!();
+                if self.underline {
+                    keywords.push("underline")
+                }
+                if self.overline {
+                    keywords.push("overline")
+                }
+                if self.line_through {
+                    keywords.push("line-through")
+                }
+                if keywords.is_empty() {
+                    keywords.push("none")
+                }
+                keywords.connect(" ")
+            }
+        }
         #[inline] pub fn get_initial_value() -> computed_value::T {
             none
         }
@@ -1029,12 +1142,416 @@ pub mod longhands {
 
     ${single_keyword("white-space", "normal pre")}
 
+    // CSS Text Module Level 3, Section 6.1 - Hyphenation
+    // TODO: support 'auto', which needs a hyphenation dictionary.
+    ${single_keyword("hyphens", "manual none")}
+
     // CSS 2.1, Section 17 - Tables
     ${new_style_struct("Table", is_inherited=False)}
 
     ${single_keyword("table-layout", "auto fixed")}
 
+    ${new_style_struct("InheritedTable", is_inherited=True)}
+
+    ${single_keyword("border-collapse", "separate collapse")}
+
+    ${single_keyword("empty-cells", "show hide")}
+
+    ${single_keyword("caption-side", "top bottom")}
+
+    <%self:longhand name="border-spacing">
+        use super::super::common_types::specified;
+
+        pub mod computed_value {
+            use super::super::{Au, ToComputedCss};
+
+            #[deriving(PartialEq, Clone)]
+            pub struct T {
+                pub horizontal: Au,
+                pub vertical: Au,
+            }
+            impl ToComputedCss for T {
+                fn to_computed_css(&self) -> String {
+                    format!("{} {}", self.horizontal.to_computed_css(),
+                            self.vertical.to_computed_css())
+                }
+            }
+        }
+
+        #[deriving(Clone)]
+        pub struct SpecifiedValue {
+            pub horizontal: specified::Length,
+            pub vertical: specified::Length,
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T {
+                horizontal: Au(0),
+                vertical: Au(0),
+            }
+        }
+
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            computed_value::T {
+                horizontal: computed::compute_Au(value.horizontal, context),
+                vertical: computed::compute_Au(value.vertical, context),
+            }
+        }
+
+        /// <length> <length>?
+        pub fn parse(input: &[ComponentValue], _: &Url) -> Option<SpecifiedValue> {
+            let mut input_iter = input.skip_whitespace();
+            let horizontal = match input_iter.next() {
+                Some(value) => match specified::Length::parse_non_negative(value) {
+                    Some(length) => length,
+                    None => return None,
+                },
+                None => return None,
+            };
+            let vertical = match input_iter.next() {
+                Some(value) => match specified::Length::parse_non_negative(value) {
+                    Some(length) => length,
+                    None => return None,
+                },
+                None => horizontal,
+            };
+            if input_iter.next().is_some() {
+                return None
+            }
+            Some(SpecifiedValue {
+                horizontal: horizontal,
+                vertical: vertical,
+            })
+        }
+    </%self:longhand>
+
+    // CSS Flexible Box Layout Module Level 1
+    ${new_style_struct("Flex", is_inherited=False)}
+
+    ${single_keyword("flex-direction", "row row-reverse column column-reverse")}
+    ${single_keyword("flex-wrap", "nowrap wrap wrap-reverse")}
+    ${single_keyword("justify-content", "flex-start flex-end center space-between space-around")}
+    ${single_keyword("align-items", "stretch flex-start flex-end center baseline")}
+    ${single_keyword("align-content",
+                     "stretch flex-start flex-end center space-between space-around")}
+    ${single_keyword("align-self", "auto stretch flex-start flex-end center baseline")}
+
+    % for name, initial in [("flex-grow", "0."), ("flex-shrink", "1.")]:
+        <%self:single_component_value name="${name}">
+            pub use to_computed_value = super::computed_as_specified;
+            pub type SpecifiedValue = CSSFloat;
+            pub mod computed_value {
+                pub type T = super::super::CSSFloat;
+            }
+            #[inline] pub fn get_initial_value() -> computed_value::T { ${initial} }
+            pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                        -> Option<SpecifiedValue> {
+                match input {
+                    &ast::Number(ref value) if value.value >= 0. => Some(value.value),
+                    _ => None,
+                }
+            }
+        </%self:single_component_value>
+    % endfor
+
+    ${predefined_type("flex-basis", "LengthOrPercentageOrAuto",
+                      "computed::LPA_Auto",
+                      "parse_non_negative")}
+
+    <%self:single_component_value name="order">
+        pub use to_computed_value = super::computed_as_specified;
+        pub type SpecifiedValue = i32;
+        pub mod computed_value {
+            pub type T = i32;
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T { 0 }
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Option<SpecifiedValue> {
+            match input {
+                &ast::Number(ref value) => value.int_value.map(|value| value as i32),
+                _ => None,
+            }
+        }
+    </%self:single_component_value>
+
+    // CSS Multi-column Layout Module
+    ${new_style_struct("Column", is_inherited=False)}
+
+    <%self:single_component_value name="column-count">
+        pub use to_computed_value = super::computed_as_specified;
+        pub mod computed_value {
+            use super::super::ToComputedCss;
+            #[deriving(PartialEq, Clone)]
+            pub enum T {
+                Auto,
+                Count(u32),
+            }
+            impl ToComputedCss for T {
+                fn to_computed_css(&self) -> String {
+                    match *self {
+                        Auto => "auto".to_string(),
+                        Count(count) => count.to_str(),
+                    }
+                }
+            }
+        }
+        pub type SpecifiedValue = computed_value::T;
+        #[inline] pub fn get_initial_value() -> computed_value::T { Auto }
+        /// auto | <integer>
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Option<SpecifiedValue> {
+            match input {
+                &ast::Number(ref value) => match value.int_value {
+                    Some(count) if count >= 1 => Some(Count(count as u32)),
+                    _ => None,
+                },
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("auto") => Some(Auto),
+                _ => None,
+            }
+        }
+    </%self:single_component_value>
+
+    <%self:single_component_value name="column-width">
+        pub mod computed_value {
+            use super::super::{Au, ToComputedCss};
+            #[deriving(PartialEq, Clone)]
+            pub enum T {
+                Auto,
+                Width(Au),
+            }
+            impl ToComputedCss for T {
+                fn to_computed_css(&self) -> String {
+                    match *self {
+                        Auto => "auto".to_string(),
+                        Width(width) => width.to_computed_css(),
+                    }
+                }
+            }
+        }
+        #[deriving(Clone)]
+        pub enum SpecifiedValue {
+            SpecifiedAuto,
+            SpecifiedWidth(specified::Length),
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T { Auto }
+        /// auto | <length>
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Option<SpecifiedValue> {
+            match input {
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("auto")
+                => Some(SpecifiedAuto),
+                _ => specified::Length::parse_non_negative(input).map(SpecifiedWidth),
+            }
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            match value {
+                SpecifiedAuto => Auto,
+                SpecifiedWidth(value) => Width(computed::compute_Au(value, context)),
+            }
+        }
+    </%self:single_component_value>
+
+    <%self:single_component_value name="column-gap">
+        pub mod computed_value {
+            use super::super::Au;
+            pub type T = Au;
+        }
+        #[deriving(Clone)]
+        pub enum SpecifiedValue {
+            SpecifiedNormal,
+            SpecifiedLength(specified::Length),
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            Au::from_px(16)  // normal, 1em in the initial font size
+        }
+        /// normal | <length>
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Option<SpecifiedValue> {
+            match input {
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("normal")
+                => Some(SpecifiedNormal),
+                _ => specified::Length::parse_non_negative(input).map(SpecifiedLength),
+            }
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            match value {
+                SpecifiedNormal => context.font_size,
+                SpecifiedLength(value) => computed::compute_Au(value, context),
+            }
+        }
+    </%self:single_component_value>
+
+    ${predefined_type("column-rule-color", "CSSColor", "CurrentColor")}
+
+    <%self:longhand name="column-rule-style", no_super="True">
+        pub use super::border_top_style::{get_initial_value, parse, to_computed_value};
+        pub type SpecifiedValue = super::border_top_style::SpecifiedValue;
+        pub mod computed_value {
+            pub type T = super::super::border_top_style::computed_value::T;
+        }
+    </%self:longhand>
+
+    <%self:longhand name="column-rule-width">
+        pub type SpecifiedValue = specified::Length;
+        pub mod computed_value {
+            use super::super::Au;
+            pub type T = Au;
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            Au::from_px(3)  // medium
+        }
+        pub fn parse(input: &[ComponentValue], base_url: &Url) -> Option<SpecifiedValue> {
+            one_component_value(input).and_then(|c| parse_border_width(c, base_url))
+        }
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                              -> computed_value::T {
+            if !context.column_rule_present {
+                Au(0)
+            } else {
+                computed::compute_Au(value, context)
+            }
+        }
+    </%self:longhand>
+
+    // CSS 2.1, Section 13 - Paged media
+
+    ${switch_to_style_struct("Box")}
+
+    ${single_keyword("page-break-before", "auto always avoid left right")}
+    ${single_keyword("page-break-after", "auto always avoid left right")}
+    ${single_keyword("page-break-inside", "auto avoid")}
+
+    ${switch_to_style_struct("InheritedBox")}
+
+    % for name in ["orphans", "widows"]:
+        <%self:single_component_value name="${name}">
+            pub use to_computed_value = super::computed_as_specified;
+            pub type SpecifiedValue = i32;
+            pub mod computed_value {
+                pub type T = i32;
+            }
+            #[inline] pub fn get_initial_value() -> computed_value::T { 2 }
+            /// <integer>, which must be positive
+            pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                        -> Option<SpecifiedValue> {
+                match input {
+                    &ast::Number(ref value) => match value.int_value {
+                        Some(count) if count >= 1 => Some(count as i32),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+        </%self:single_component_value>
+    % endfor
+
     // CSS 2.1, Section 18 - User interface
+
+    // CSS Image Values and Replaced Content Module Level 3
+
+    ${switch_to_style_struct("Box")}
+
+    ${single_keyword("object-fit", "fill contain cover none scale-down")}
+
+    <%self:longhand name="object-position">
+            use super::super::common_types::specified;
+
+            pub mod computed_value {
+                use super::super::super::common_types::computed::LengthOrPercentage;
+                use super::super::ToComputedCss;
+
+                #[deriving(PartialEq, Clone)]
+                pub struct T {
+                    pub horizontal: LengthOrPercentage,
+                    pub vertical: LengthOrPercentage,
+                }
+                impl ToComputedCss for T {
+                    fn to_computed_css(&self) -> String {
+                        format!("{} {}", self.horizontal.to_computed_css(),
+                                self.vertical.to_computed_css())
+                    }
+                }
+            }
+
+            #[deriving(Clone)]
+            pub struct SpecifiedValue {
+                pub horizontal: specified::LengthOrPercentage,
+                pub vertical: specified::LengthOrPercentage,
+            }
+
+            #[inline]
+            pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                     -> computed_value::T {
+                computed_value::T {
+                    horizontal: computed::compute_LengthOrPercentage(value.horizontal, context),
+                    vertical: computed::compute_LengthOrPercentage(value.vertical, context),
+                }
+            }
+
+            #[inline]
+            pub fn get_initial_value() -> computed_value::T {
+                computed_value::T {
+                    horizontal: computed::LP_Percentage(0.5),
+                    vertical: computed::LP_Percentage(0.5),
+                }
+            }
+
+            /// Parses one component of the position. `start` and `end` are the keywords that
+            /// stand for 0% and 100% along this axis.
+            fn parse_component(input: &ComponentValue, start: &str, end: &str)
+                               -> Option<specified::LengthOrPercentage> {
+                match input {
+                    &Ident(ref value) if value.as_slice().eq_ignore_ascii_case(start) => {
+                        Some(specified::LP_Percentage(0.0))
+                    }
+                    &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("center") => {
+                        Some(specified::LP_Percentage(0.5))
+                    }
+                    &Ident(ref value) if value.as_slice().eq_ignore_ascii_case(end) => {
+                        Some(specified::LP_Percentage(1.0))
+                    }
+                    _ => specified::LengthOrPercentage::parse(input),
+                }
+            }
+
+            // FIXME: Support edge offsets such as `right 10px bottom 20px`.
+            /// [ left | center | right | <length-percentage> ]
+            /// [ top | center | bottom | <length-percentage> ]?
+            pub fn parse(input: &[ComponentValue], _: &Url) -> Option<SpecifiedValue> {
+                let mut input_iter = input.skip_whitespace();
+                let horizontal = input_iter.next();
+                let vertical = input_iter.next();
+                if input_iter.next().is_some() {
+                    return None
+                }
+
+                let horizontal = match horizontal {
+                    Some(horizontal) => parse_component(horizontal, "left", "right"),
+                    None => None,
+                };
+                let vertical = match vertical {
+                    Some(vertical) => parse_component(vertical, "top", "bottom"),
+                    None => Some(specified::LP_Percentage(0.5)),
+                };
+                match (horizontal, vertical) {
+                    (Some(horizontal), Some(vertical)) => {
+                        Some(SpecifiedValue {
+                            horizontal: horizontal,
+                            vertical: vertical,
+                        })
+                    }
+                    _ => None,
+                }
+            }
+    </%self:longhand>
 }
 
 
@@ -1333,6 +1850,149 @@ pub mod shorthands {
         })
     </%self:shorthand>
 
+    <%self:shorthand name="flex-flow" sub_properties="flex-direction flex-wrap">
+        let mut direction = None;
+        let mut wrap = None;
+        let mut any = false;
+        for component_value in input.skip_whitespace() {
+            if direction.is_none() {
+                match flex_direction::from_component_value(component_value, base_url) {
+                    Some(v) => { direction = Some(v); any = true; continue },
+                    None => ()
+                }
+            }
+            if wrap.is_none() {
+                match flex_wrap::from_component_value(component_value, base_url) {
+                    Some(v) => { wrap = Some(v); any = true; continue },
+                    None => ()
+                }
+            }
+            return None
+        }
+        if any {
+            Some(Longhands {
+                flex_direction: direction,
+                flex_wrap: wrap,
+            })
+        } else {
+            None
+        }
+    </%self:shorthand>
+
+    <%self:shorthand name="flex" sub_properties="flex-grow flex-shrink flex-basis">
+        // none | [ <'flex-grow'> <'flex-shrink'>? || <'flex-basis'> ]
+        match one_component_value(input).and_then(get_ident_lower) {
+            Some(ref keyword) if keyword.as_slice() == "none" => {
+                return Some(Longhands {
+                    flex_grow: Some(0.),
+                    flex_shrink: Some(0.),
+                    flex_basis: Some(specified::LPA_Auto),
+                })
+            }
+            Some(ref keyword) if keyword.as_slice() == "auto" => {
+                return Some(Longhands {
+                    flex_grow: Some(1.),
+                    flex_shrink: Some(1.),
+                    flex_basis: Some(specified::LPA_Auto),
+                })
+            }
+            _ => ()
+        }
+
+        let mut grow = None;
+        let mut shrink = None;
+        let mut basis = None;
+        let mut iter = input.skip_whitespace().peekable();
+        loop {
+            let component_value = match iter.next() {
+                Some(component_value) => component_value,
+                None => break,
+            };
+            if grow.is_none() {
+                match flex_grow::from_component_value(component_value, base_url) {
+                    Some(v) => {
+                        grow = Some(v);
+                        // The shrink factor can only directly follow the grow factor.
+                        let next_shrink = iter.peek().and_then(|&next| {
+                            flex_shrink::from_component_value(next, base_url)
+                        });
+                        if next_shrink.is_some() {
+                            shrink = next_shrink;
+                            iter.next();
+                        }
+                        continue
+                    }
+                    None => ()
+                }
+            }
+            if basis.is_none() {
+                match flex_basis::from_component_value(component_value, base_url) {
+                    Some(v) => { basis = Some(v); continue },
+                    None => ()
+                }
+            }
+            return None
+        }
+        if grow.is_none() && basis.is_none() {
+            return None
+        }
+        // Omitted components default to `1 1 0%`, not to the initial values.
+        Some(Longhands {
+            flex_grow: Some(grow.unwrap_or(1.)),
+            flex_shrink: Some(shrink.unwrap_or(1.)),
+            flex_basis: Some(basis.unwrap_or(specified::LPA_Percentage(0.))),
+        })
+    </%self:shorthand>
+
+    <%self:shorthand name="columns" sub_properties="column-width column-count">
+        // <'column-width'> || <'column-count'>, where `auto` may stand for either.
+        let mut width = None;
+        let mut count = None;
+        let mut autos = 0u;
+        for component_value in input.skip_whitespace() {
+            match component_value {
+                &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("auto") => {
+                    autos += 1;
+                    continue
+                }
+                _ => ()
+            }
+            if width.is_none() {
+                match column_width::from_component_value(component_value, base_url) {
+                    Some(v) => { width = Some(v); continue },
+                    None => ()
+                }
+            }
+            if count.is_none() {
+                match column_count::from_component_value(component_value, base_url) {
+                    Some(v) => { count = Some(v); continue },
+                    None => ()
+                }
+            }
+            return None
+        }
+        let values = autos + width.iter().count() + count.iter().count();
+        if values == 0 || values > 2 {
+            return None
+        }
+        // `auto` is the initial value of both longhands.
+        Some(Longhands {
+            column_width: width,
+            column_count: count,
+        })
+    </%self:shorthand>
+
+    <%self:shorthand name="column-rule"
+                     sub_properties="column-rule-width column-rule-style column-rule-color">
+        parse_border(input, base_url).map(|(color, style, width)| {
+            Longhands {
+                column_rule_width: width,
+                column_rule_style: style,
+                column_rule_color: color,
+            }
+        })
+    </%self:shorthand>
+
 }
 
 
@@ -1384,6 +2044,7 @@ mod property_bit_field {
 
 /// Declarations are stored in reverse order.
 /// Overridden declarations are skipped.
+#[deriving(Clone)]
 pub struct PropertyDeclarationBlock {
     pub important: Arc<Vec<PropertyDeclaration>>,
     pub normal: Arc<Vec<PropertyDeclaration>>,
@@ -1401,6 +2062,385 @@ pub fn parse_style_attribute(input: &str, base_url: &Url) -> PropertyDeclaration
 }
 
 
+/// Parses a `style` attribute into the declarations it contains as written, for the inline style
+/// `CSSStyleDeclaration`. Each entry is the lowercased property name, the serialized value and
+/// whether it is `!important`. Shorthands are expanded into their longhands, invalid declarations
+/// are dropped and later declarations of the same property replace earlier ones.
+pub fn parse_style_attribute_as_written(input: &str, base_url: &Url)
+                                        -> Vec<(String, String, bool)> {
+    let mut declarations: Vec<(String, String, bool)> = vec!();
+    for item in ErrorLoggerIterator(parse_declaration_list(tokenize(input))) {
+        match item {
+            DeclAtRule(_) => (),
+            Declaration(Declaration{ location: _, name: n, value: v, important: i}) => {
+                for (name, value) in declarations_as_written(n.as_slice(), v.as_slice(),
+                                                             base_url).move_iter() {
+                    declarations.retain(|&(ref existing, _, _)| *existing != name);
+                    declarations.push((name, value, i))
+                }
+            }
+        }
+    }
+    declarations
+}
+
+/// Returns the longhands that the shorthand `name` sets, or `None` if `name` is not a shorthand.
+pub fn shorthand_longhands(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        % for shorthand in SHORTHANDS:
+            "${shorthand.name}" => {
+                static LONGHANDS: &'static [&'static str] = &[
+                    % for sub_property in shorthand.sub_properties:
+                        "${sub_property.name}",
+                    % endfor
+                ];
+                Some(LONGHANDS)
+            }
+        % endfor
+        _ => None,
+    }
+}
+
+/// Serializes a single component value.
+fn component_value_to_css(component_value: &ComponentValue) -> String {
+    Some(component_value).move_iter().to_css()
+}
+
+/// Returns the next component value of `input` from `*index` on that is not whitespace, and moves
+/// `*index` past it.
+fn next_component_value<'a>(input: &'a [ComponentValue], index: &mut uint)
+                            -> Option<&'a ComponentValue> {
+    while *index < input.len() {
+        let component_value = &input[*index];
+        *index += 1;
+        if *component_value != WhiteSpace {
+            return Some(component_value)
+        }
+    }
+    None
+}
+
+/// Whether a longhand accepts a component value as its whole value.
+type ComponentValueTest = fn(&ComponentValue, &Url) -> bool;
+
+macro_rules! component_value_test(
+    ($name:ident, $parser:expr) => (
+        fn $name(component_value: &ComponentValue, base_url: &Url) -> bool {
+            $parser(component_value, base_url).is_some()
+        }
+    )
+)
+
+component_value_test!(is_color, shorthands::parse_color)
+component_value_test!(is_border_style, longhands::border_top_style::from_component_value)
+component_value_test!(is_border_width, longhands::parse_border_width)
+component_value_test!(is_background_color, longhands::background_color::from_component_value)
+component_value_test!(is_background_image, longhands::background_image::from_component_value)
+component_value_test!(is_background_repeat, longhands::background_repeat::from_component_value)
+component_value_test!(is_background_attachment,
+                      longhands::background_attachment::from_component_value)
+component_value_test!(is_font_style, longhands::font_style::from_component_value)
+component_value_test!(is_font_variant, longhands::font_variant::from_component_value)
+component_value_test!(is_font_weight, longhands::font_weight::from_component_value)
+component_value_test!(is_flex_direction, longhands::flex_direction::from_component_value)
+component_value_test!(is_flex_wrap, longhands::flex_wrap::from_component_value)
+component_value_test!(is_flex_grow, longhands::flex_grow::from_component_value)
+component_value_test!(is_flex_shrink, longhands::flex_shrink::from_component_value)
+component_value_test!(is_column_width, longhands::column_width::from_component_value)
+component_value_test!(is_column_count, longhands::column_count::from_component_value)
+
+/// Gives each component value to the first of `tests` that accepts it and has no value yet.
+/// Returns the serialized value found for each test, and the component values that no test took.
+fn assign_component_values<'a>(components: &[&'a ComponentValue], base_url: &Url,
+                               tests: &[ComponentValueTest])
+                               -> (Vec<Option<String>>, Vec<&'a ComponentValue>) {
+    let mut values = Vec::from_elem(tests.len(), None);
+    let mut rest = vec!();
+    for &component_value in components.iter() {
+        let index = range(0, tests.len()).find(|&i| {
+            values.get(i).is_none() && (tests[i])(component_value, base_url)
+        });
+        match index {
+            Some(i) => *values.get_mut(i) = Some(component_value_to_css(component_value)),
+            None => rest.push(component_value),
+        }
+    }
+    (values, rest)
+}
+
+/// Splits the value of the shorthand `name`, which must be valid, into the serialized values of
+/// its longhands, in the order of `shorthand_longhands(name)`. The longhands that the value leaves
+/// out are `initial`. This follows the parsers in the `shorthands` module, and needs a case for
+/// every shorthand.
+fn expand_shorthand(name: &str, value: &[ComponentValue], base_url: &Url) -> Vec<String> {
+    let components: Vec<&ComponentValue> = value.skip_whitespace().collect();
+    let or_initial = |value: Option<String>| value.unwrap_or("initial".to_string());
+    match name {
+        "margin" | "padding" | "border-color" | "border-style" | "border-width" => {
+            let values: Vec<String> =
+                components.iter().map(|&component_value| component_value_to_css(component_value))
+                          .collect();
+            let top = values.get(0).clone();
+            let right = values.as_slice().get(1).unwrap_or(&top).clone();
+            let bottom = values.as_slice().get(2).unwrap_or(&top).clone();
+            let left = values.as_slice().get(3).unwrap_or(&right).clone();
+            vec!(top, right, bottom, left)
+        }
+        "border-top" | "border-right" | "border-bottom" | "border-left" | "border" => {
+            let (values, _) = assign_component_values(components.as_slice(), base_url,
+                                                      &[is_color, is_border_style,
+                                                        is_border_width]);
+            let side: Vec<String> = values.move_iter().map(|value| or_initial(value)).collect();
+            let mut values = vec!();
+            for _ in range(0, if name == "border" { 4u } else { 1 }) {
+                values.push_all(side.as_slice())
+            }
+            values
+        }
+        "column-rule" => {
+            let (values, _) = assign_component_values(components.as_slice(), base_url,
+                                                      &[is_border_width, is_border_style,
+                                                        is_color]);
+            values.move_iter().map(|value| or_initial(value)).collect()
+        }
+        "background" => {
+            // The two component values that no other longhand takes are the position.
+            let (values, rest) = assign_component_values(components.as_slice(), base_url,
+                                                         &[is_background_color,
+                                                           is_background_repeat,
+                                                           is_background_attachment,
+                                                           is_background_image]);
+            let mut values = values.move_iter().map(|value| or_initial(value));
+            let color = values.next().unwrap();
+            let position = if rest.is_empty() {
+                "initial".to_string()
+            } else {
+                let position: Vec<String> =
+                    rest.iter().map(|&component_value| component_value_to_css(component_value))
+                        .collect();
+                position.connect(" ")
+            };
+            let repeat = values.next().unwrap();
+            let attachment = values.next().unwrap();
+            let image = values.next().unwrap();
+            vec!(color, position, repeat, attachment, image)
+        }
+        "flex-flow" => {
+            let (values, _) = assign_component_values(components.as_slice(), base_url,
+                                                      &[is_flex_direction, is_flex_wrap]);
+            values.move_iter().map(|value| or_initial(value)).collect()
+        }
+        "columns" => {
+            // `auto` is taken by the first longhand that has no other value.
+            let (values, _) = assign_component_values(components.as_slice(), base_url,
+                                                      &[is_column_width, is_column_count]);
+            values.move_iter().map(|value| or_initial(value)).collect()
+        }
+        "flex" => {
+            match one_component_value(value).and_then(get_ident_lower) {
+                Some(ref keyword) if keyword.as_slice() == "none" => {
+                    return vec!("0".to_string(), "0".to_string(), "auto".to_string())
+                }
+                Some(ref keyword) if keyword.as_slice() == "auto" => {
+                    return vec!("1".to_string(), "1".to_string(), "auto".to_string())
+                }
+                _ => ()
+            }
+            let (mut grow, mut shrink, mut basis) = (None, None, None);
+            let mut iter = components.iter().peekable();
+            loop {
+                let component_value = match iter.next() {
+                    Some(&component_value) => component_value,
+                    None => break,
+                };
+                if grow.is_none() && is_flex_grow(component_value, base_url) {
+                    grow = Some(component_value_to_css(component_value));
+                    // The shrink factor can only directly follow the grow factor.
+                    let next_is_shrink = match iter.peek() {
+                        Some(& &next) => is_flex_shrink(next, base_url),
+                        None => false,
+                    };
+                    if next_is_shrink {
+                        shrink = iter.next().map(|&next| component_value_to_css(next));
+                    }
+                    continue
+                }
+                basis = Some(component_value_to_css(component_value));
+            }
+            // Omitted components default to `1 1 0%`, not to the initial values.
+            vec!(grow.unwrap_or("1".to_string()),
+                 shrink.unwrap_or("1".to_string()),
+                 basis.unwrap_or("0%".to_string()))
+        }
+        "font" => {
+            // Style, variant and weight in any order, where `normal` may stand for any of them,
+            // then the size, a line height after a slash, and the family.
+            let (mut style, mut variant, mut weight, mut size) = (None, None, None, None);
+            let mut normals = 0u;
+            let mut index = 0;
+            loop {
+                let component_value = match next_component_value(value, &mut index) {
+                    Some(component_value) => component_value,
+                    None => break,
+                };
+                match get_ident_lower(component_value) {
+                    Some(ref ident) if ident.as_slice() == "normal" => {
+                        normals += 1;
+                        continue
+                    }
+                    _ => ()
+                }
+                let css = component_value_to_css(component_value);
+                if style.is_none() && is_font_style(component_value, base_url) {
+                    style = Some(css)
+                } else if weight.is_none() && is_font_weight(component_value, base_url) {
+                    weight = Some(css)
+                } else if variant.is_none() && is_font_variant(component_value, base_url) {
+                    variant = Some(css)
+                } else {
+                    size = Some(css);
+                    break
+                }
+            }
+            let mut after_size = index;
+            let line_height = match next_component_value(value, &mut after_size) {
+                Some(&Delim('/')) => {
+                    index = after_size;
+                    next_component_value(value, &mut index).map(component_value_to_css)
+                }
+                _ => None,
+            };
+            let family = value.slice_from(index).iter().to_css().as_slice().trim().to_string();
+            let or_normal = |value: Option<String>| {
+                match value {
+                    Some(value) => value,
+                    None if normals > 0 => {
+                        normals -= 1;
+                        "normal".to_string()
+                    }
+                    None => "initial".to_string(),
+                }
+            };
+            let style = or_normal(style);
+            let variant = or_normal(variant);
+            let weight = or_normal(weight);
+            vec!(style, variant, weight, size.unwrap(), or_initial(line_height), family)
+        }
+        _ => fail!("no expansion for the shorthand {}", name),
+    }
+}
+
+/// Returns the longhand declarations that the declaration of `name` to `value` stands for, as
+/// the lowercased property name and the serialized value of each: the declaration itself for a
+/// longhand, and one declaration per longhand of a shorthand. Returns an empty list if the
+/// declaration is invalid.
+fn declarations_as_written(name: &str, value: &[ComponentValue], base_url: &Url)
+                           -> Vec<(String, String)> {
+    let name = name.to_ascii_lower();
+    let mut parsed = vec!();
+    let mut seen = PropertyBitField::new();
+    match PropertyDeclaration::parse(name.as_slice(), value, &mut parsed, base_url, &mut seen) {
+        ValidOrIgnoredDeclaration if !parsed.is_empty() => (),
+        _ => return vec!(),
+    }
+    let longhands = match shorthand_longhands(name.as_slice()) {
+        Some(longhands) => longhands,
+        None => return vec!((name.clone(), value.iter().to_css().as_slice().trim().to_string())),
+    };
+    let values = match CSSWideKeyword::parse(value) {
+        Some(_) => {
+            let keyword = value.iter().to_css().as_slice().trim().to_string();
+            Vec::from_elem(longhands.len(), keyword)
+        }
+        None => expand_shorthand(name.as_slice(), value, base_url),
+    };
+    longhands.iter().map(|longhand| longhand.to_string()).zip(values.move_iter()).collect()
+}
+
+/// Parses a declaration as given to `CSSStyleDeclaration.setProperty()` into the longhand
+/// declarations it stands for, as in `parse_style_attribute_as_written`. Returns an empty list
+/// if the property is unknown or the value is invalid.
+pub fn parse_declaration_as_written(name: &str, value: &str, base_url: &Url)
+                                    -> Vec<(String, String)> {
+    let value: Vec<ComponentValue> =
+        tokenize(value).map(|(component_value, _)| component_value).collect();
+    declarations_as_written(name, value.as_slice(), base_url)
+}
+
+/// Joins the values of the longhands of a shorthand whose components can be left out, leaving out
+/// those that are `initial`.
+fn join_explicit_values(values: &[&str]) -> String {
+    let explicit: Vec<&str> = values.iter().map(|&value| value)
+                                    .filter(|&value| value != "initial").collect();
+    if explicit.is_empty() {
+        "initial".to_string()
+    } else {
+        explicit.connect(" ")
+    }
+}
+
+/// Serializes the shorthand `name` from the serialized values of its longhands, given in the order
+/// of `shorthand_longhands(name)`. Returns `None` if the shorthand cannot represent the values.
+///
+/// http://dev.w3.org/csswg/cssom/#serialize-a-css-value
+pub fn serialize_shorthand(name: &str, values: &[&str]) -> Option<String> {
+    // A CSS-wide keyword can only stand for the shorthand if all the longhands have it.
+    let is_keyword = |value: &str| ["initial", "inherit", "unset"].contains(&value);
+    if values.iter().all(|&value| value == values[0]) && is_keyword(values[0]) {
+        return Some(values[0].to_string())
+    }
+    if values.iter().any(|&value| value != "initial" && is_keyword(value)) {
+        return None
+    }
+    match name {
+        "margin" | "padding" | "border-color" | "border-style" | "border-width" => {
+            // Top, right, bottom and left, leaving out the values that the shorter forms imply.
+            let (top, right, bottom, left) = (values[0], values[1], values[2], values[3]);
+            Some(if left != right {
+                format!("{} {} {} {}", top, right, bottom, left)
+            } else if bottom != top {
+                format!("{} {} {}", top, right, bottom)
+            } else if right != top {
+                format!("{} {}", top, right)
+            } else {
+                top.to_string()
+            })
+        }
+        "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            // Color, style and width.
+            Some(join_explicit_values([values[2], values[1], values[0]]))
+        }
+        "border" => {
+            // `border` can only set the same color, style and width on all four sides.
+            let side = values.slice_to(3);
+            if values.chunks(3).all(|other_side| other_side == side) {
+                Some(join_explicit_values([side[2], side[1], side[0]]))
+            } else {
+                None
+            }
+        }
+        "font" => {
+            // Style, variant, weight, size, line height and family.
+            let mut result = String::new();
+            for value in values.slice_to(3).iter() {
+                if *value != "normal" && *value != "initial" {
+                    result.push_str(*value);
+                    result.push_char(' ');
+                }
+            }
+            result.push_str(values[3]);
+            if values[4] != "normal" && values[4] != "initial" {
+                result.push_char('/');
+                result.push_str(values[4]);
+            }
+            result.push_char(' ');
+            result.push_str(values[5]);
+            Some(result)
+        }
+        _ => Some(join_explicit_values(values)),
+    }
+}
+
 pub fn parse_property_declaration_list<I: Iterator<Node>>(input: I, base_url: &Url) -> PropertyDeclarationBlock {
     let mut important_declarations = vec!();
     let mut normal_declarations = vec!();
@@ -1595,6 +2635,114 @@ impl ComputedValues {
         }
     }
 
+    /// Returns true if any computed value differs from the corresponding one in `other`.
+    pub fn differs_from(&self, other: &ComputedValues) -> bool {
+        % for style_struct in STYLE_STRUCTS:
+            if *self.${style_struct.ident} != *other.${style_struct.ident} {
+                return true
+            }
+        % endfor
+        false
+    }
+
+    #[inline]
+    pub fn writing_mode(&self) -> WritingMode {
+        match self.get_inheritedbox().writing_mode {
+            longhands::writing_mode::computed_value::horizontal_tb => HorizontalTopToBottom,
+            longhands::writing_mode::computed_value::vertical_rl => VerticalRightToLeft,
+            longhands::writing_mode::computed_value::vertical_lr => VerticalLeftToRight,
+        }
+    }
+
+    // The following methods read the sizing and box model properties in flow-relative terms,
+    // according to this element's writing mode. See CSS Writing Modes Level 3 § 6.
+
+    #[inline]
+    pub fn content_inline_size(&self) -> computed::LengthOrPercentageOrAuto {
+        let box_style = self.get_box();
+        if self.writing_mode().is_vertical() { box_style.height } else { box_style.width }
+    }
+
+    #[inline]
+    pub fn content_block_size(&self) -> computed::LengthOrPercentageOrAuto {
+        let box_style = self.get_box();
+        if self.writing_mode().is_vertical() { box_style.width } else { box_style.height }
+    }
+
+    #[inline]
+    pub fn min_inline_size(&self) -> computed::LengthOrPercentage {
+        let box_style = self.get_box();
+        if self.writing_mode().is_vertical() { box_style.min_height } else { box_style.min_width }
+    }
+
+    #[inline]
+    pub fn min_block_size(&self) -> computed::LengthOrPercentage {
+        let box_style = self.get_box();
+        if self.writing_mode().is_vertical() { box_style.min_width } else { box_style.min_height }
+    }
+
+    #[inline]
+    pub fn max_inline_size(&self) -> computed::LengthOrPercentageOrNone {
+        let box_style = self.get_box();
+        if self.writing_mode().is_vertical() { box_style.max_height } else { box_style.max_width }
+    }
+
+    #[inline]
+    pub fn max_block_size(&self) -> computed::LengthOrPercentageOrNone {
+        let box_style = self.get_box();
+        if self.writing_mode().is_vertical() { box_style.max_width } else { box_style.max_height }
+    }
+
+    #[inline]
+    pub fn logical_margin(&self) -> LogicalMargin<computed::LengthOrPercentageOrAuto> {
+        let margin_style = self.get_margin();
+        LogicalMargin::from_physical_sides(self.writing_mode(),
+                                           margin_style.margin_top,
+                                           margin_style.margin_right,
+                                           margin_style.margin_bottom,
+                                           margin_style.margin_left)
+    }
+
+    #[inline]
+    pub fn logical_padding(&self) -> LogicalMargin<computed::LengthOrPercentage> {
+        let padding_style = self.get_padding();
+        LogicalMargin::from_physical_sides(self.writing_mode(),
+                                           padding_style.padding_top,
+                                           padding_style.padding_right,
+                                           padding_style.padding_bottom,
+                                           padding_style.padding_left)
+    }
+
+    #[inline]
+    pub fn logical_position(&self) -> LogicalMargin<computed::LengthOrPercentageOrAuto> {
+        let position_style = self.get_positionoffsets();
+        LogicalMargin::from_physical_sides(self.writing_mode(),
+                                           position_style.top,
+                                           position_style.right,
+                                           position_style.bottom,
+                                           position_style.left)
+    }
+
+    /// Returns the name and serialized value of every longhand property, as for
+    /// `getComputedStyle()`. `currentColor` is resolved.
+    pub fn to_computed_css_declarations(&self) -> Vec<(String, String)> {
+        vec!(
+            % for style_struct in STYLE_STRUCTS:
+                % for longhand in style_struct.longhands:
+                    % if longhand.derived_from is None:
+                        ("${longhand.name}".to_string(),
+                        % if longhand.computed_type == "CSSColor":
+                            self.resolve_color(self.${style_struct.ident}.${longhand.ident})
+                                .to_computed_css()),
+                        % else:
+                            self.${style_struct.ident}.${longhand.ident}.to_computed_css()),
+                        % endif
+                    % endif
+                % endfor
+            % endfor
+        )
+    }
+
     % for style_struct in STYLE_STRUCTS:
         pub fn get_${style_struct.name.lower()}
                 <'a>(&'a self) -> &'a style_structs::${style_struct.name} {
@@ -1746,11 +2894,21 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
         Some(parent_style) => (false, parent_style),
         None => (true, initial_values),
     };
+    let is_flex_item = match parent_style {
+        Some(parent_style) => {
+            match parent_style.get_box().display {
+                longhands::display::flex | longhands::display::inline_flex => true,
+                _ => false,
+            }
+        }
+        None => false,
+    };
 
     let mut context = {
         let inherited_font_style = inherited_style.get_font();
         computed::Context {
             is_root_element: is_root_element,
+            is_flex_item: is_flex_item,
             inherited_font_weight: inherited_font_style.font_weight,
             inherited_font_size: inherited_font_style.font_size,
             inherited_height: inherited_style.get_box().height,
@@ -1769,6 +2927,7 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
             border_right_present: false,
             border_bottom_present: false,
             border_left_present: false,
+            column_rule_present: false,
         }
     };
 
@@ -1826,13 +2985,23 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
                         };
                     }
                 % endfor
+                ColumnRuleStyleDeclaration(ref value) => {
+                    context.column_rule_present =
+                    match get_specified!(get_column, column_rule_style, value) {
+                        longhands::border_top_style::none |
+                        longhands::border_top_style::hidden => false,
+                        _ => true,
+                    };
+                }
                 _ => {}
             }
         }
     }
 
+    // The computed `display` of a flex item depends on its parent, so the cached non-inherited
+    // values can't be reused for it.
     match (cached_style, parent_style) {
-        (Some(cached_style), Some(parent_style)) => {
+        (Some(cached_style), Some(parent_style)) if !is_flex_item => {
             return (cascade_with_cached_declarations(applicable_declarations,
                                                      shareable,
                                                      parent_style,
@@ -1852,7 +3021,7 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
             % endif
             .${style_struct.ident}.clone();
     % endfor
-    let mut cacheable = true;
+    let mut cacheable = !is_flex_item;
     let mut seen = PropertyBitField::new();
     // Declaration blocks are stored in increasing precedence order,
     // we want them in decreasing order here.
@@ -1923,6 +3092,9 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
             }
         % endfor
     }
+    if !context.column_rule_present {
+        style_column.make_unique_experimental().column_rule_width = Au(0);
+    }
 
     // The initial value of display may be changed at computed value time.
     if !seen.get_display() {
@@ -1930,6 +3102,11 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
         box_.display = longhands::display::to_computed_value(box_.display, &context);
     }
 
+    // `float` does not apply to flex items.
+    if is_flex_item {
+        style_box_.make_unique_experimental().float = longhands::float::none;
+    }
+
     (ComputedValues {
         % for style_struct in STYLE_STRUCTS:
             ${style_struct.ident}: style_${style_struct.ident},
@@ -0,0 +1,187 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Restyle hints: which elements have to be matched again after an attribute or a state of one
+//! element changes, as far as the selectors in the style sheets can tell.
+
+use std::collections::hashmap::HashMap;
+
+use servo_util::str::split_html_space_chars;
+
+use selectors::*;
+
+bitflags! {
+    #[doc = "The elements that have to be restyled after a change to an element."]
+    flags RestyleHint: u8 {
+        #[doc = "Restyle the element itself."]
+        static RestyleSelf = 0x01,
+        #[doc = "Restyle all descendants of the element."]
+        static RestyleDescendants = 0x02,
+        #[doc = "Restyle all later siblings of the element, along with their descendants."]
+        static RestyleLaterSiblings = 0x04
+    }
+}
+
+/// The ids, classes, attributes and states that selectors depend on, and for each the elements
+/// that a change to it can affect.
+///
+/// Dependencies are only ever added, so a style sheet that is removed leaves its dependencies
+/// behind. That only costs some unnecessary restyling.
+pub struct SelectorDependencies {
+    ids: HashMap<String, RestyleHint>,
+    classes: HashMap<String, RestyleHint>,
+    /// Keyed by lowercase attribute name.
+    attributes: HashMap<String, RestyleHint>,
+    hover: RestyleHint,
+}
+
+impl SelectorDependencies {
+    pub fn new() -> SelectorDependencies {
+        SelectorDependencies {
+            ids: HashMap::new(),
+            classes: HashMap::new(),
+            attributes: HashMap::new(),
+            hover: RestyleHint::empty(),
+        }
+    }
+
+    /// Records the dependencies of every compound selector in `selector`.
+    ///
+    /// The compound selector on the right applies to the element itself. One to the left of a
+    /// child or descendant combinator applies to an ancestor, so it affects descendants; one to
+    /// the left of a sibling combinator applies to an earlier sibling, so it affects later
+    /// siblings and everything inside them.
+    pub fn note_selector(&mut self, selector: &CompoundSelector) {
+        let mut selector = selector;
+        let mut hint = RestyleSelf;
+        loop {
+            for simple_selector in selector.simple_selectors.iter() {
+                self.note_simple_selector(simple_selector, hint)
+            }
+            match selector.next {
+                None => break,
+                Some((ref next, combinator)) => {
+                    hint = match combinator {
+                        Child | Descendant => RestyleDescendants,
+                        NextSibling | LaterSibling => RestyleLaterSiblings,
+                    };
+                    selector = &**next
+                }
+            }
+        }
+    }
+
+    fn note_simple_selector(&mut self, selector: &SimpleSelector, hint: RestyleHint) {
+        fn note(map: &mut HashMap<String, RestyleHint>, key: &str, hint: RestyleHint) {
+            map.insert_or_update_with(key.to_string(), hint, |_, existing| existing.insert(hint));
+        }
+
+        match *selector {
+            IDSelector(ref id) => note(&mut self.ids, id.as_slice(), hint),
+            ClassSelector(ref class) => note(&mut self.classes, class.as_slice(), hint),
+            AttrExists(ref attr) |
+            AttrEqual(ref attr, _) |
+            AttrIncludes(ref attr, _) |
+            AttrDashMatch(ref attr, _, _) |
+            AttrPrefixMatch(ref attr, _) |
+            AttrSubstringMatch(ref attr, _) |
+            AttrSuffixMatch(ref attr, _) => {
+                note(&mut self.attributes, attr.lower_name.as_slice(), hint)
+            }
+            Negation(ref selectors) => {
+                for selector in selectors.iter() {
+                    self.note_simple_selector(selector, hint)
+                }
+            }
+            AnyLink | Link | Visited => note(&mut self.attributes, "href", hint),
+            Hover => self.hover.insert(hint),
+            // Names and namespaces never change, and script restyles the whole document when
+            // the tree structure changes.
+            LocalNameSelector(_) | NamespaceSelector(_) | FirstChild | LastChild | OnlyChild |
+            Root | NthChild(..) | NthLastChild(..) | NthOfType(..) | NthLastOfType(..) |
+            FirstOfType | LastOfType | OnlyOfType => {}
+        }
+    }
+
+    /// Returns the elements to restyle after an attribute named `name` is added to an element
+    /// with the value `value`, or removed from an element while it had that value.
+    pub fn hint_for_attribute(&self, name: &str, value: &str) -> RestyleHint {
+        let mut hint = lookup(&self.attributes, name);
+        match name {
+            "id" => hint.insert(lookup(&self.ids, value)),
+            "class" => {
+                for class in split_html_space_chars(value) {
+                    hint.insert(lookup(&self.classes, class))
+                }
+            }
+            _ => {}
+        }
+        hint
+    }
+
+    /// Returns the elements to restyle after an element enters or leaves the hover state.
+    pub fn hint_for_hover_state(&self) -> RestyleHint {
+        self.hover
+    }
+}
+
+fn lookup(map: &HashMap<String, RestyleHint>, key: &str) -> RestyleHint {
+    match map.find_equiv(&key) {
+        None => RestyleHint::empty(),
+        Some(hint) => *hint,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cssparser;
+    use namespaces::NamespaceMap;
+    use selectors::parse_selector_list;
+    use super::{RestyleHint, RestyleSelf, RestyleDescendants, RestyleLaterSiblings};
+    use super::SelectorDependencies;
+
+    fn dependencies(css_selectors: &[&str]) -> SelectorDependencies {
+        let mut dependencies = SelectorDependencies::new();
+        for css_selector in css_selectors.iter() {
+            let selectors = parse_selector_list(
+                cssparser::tokenize(*css_selector).map(|(v, _)| v).collect(),
+                &NamespaceMap::new()).unwrap();
+            for selector in selectors.iter() {
+                dependencies.note_selector(&*selector.compound_selectors)
+            }
+        }
+        dependencies
+    }
+
+    #[test]
+    fn test_class_and_id_hints() {
+        let dependencies = dependencies([".a", ".b .c", "#d + p", "div"]);
+        assert!(dependencies.hint_for_attribute("class", "a") == RestyleSelf);
+        assert!(dependencies.hint_for_attribute("class", "b") == RestyleDescendants);
+        assert!(dependencies.hint_for_attribute("class", " a  b ") ==
+                RestyleSelf | RestyleDescendants);
+        assert!(dependencies.hint_for_attribute("class", "e") == RestyleHint::empty());
+        assert!(dependencies.hint_for_attribute("id", "d") == RestyleLaterSiblings);
+        assert!(dependencies.hint_for_attribute("id", "a") == RestyleHint::empty());
+    }
+
+    #[test]
+    fn test_hint_depends_on_the_combinator_to_the_right() {
+        let dependencies = dependencies([".a .b ~ .c", ".d ~ .e > .f"]);
+        assert!(dependencies.hint_for_attribute("class", "a") == RestyleDescendants);
+        assert!(dependencies.hint_for_attribute("class", "b") == RestyleLaterSiblings);
+        assert!(dependencies.hint_for_attribute("class", "d") == RestyleLaterSiblings);
+        assert!(dependencies.hint_for_attribute("class", "e") == RestyleDescendants);
+        assert!(dependencies.hint_for_attribute("class", "f") == RestyleSelf);
+    }
+
+    #[test]
+    fn test_attribute_and_state_hints() {
+        let dependencies = dependencies(["[class~=x]", ":not([Title])", "a:hover span"]);
+        assert!(dependencies.hint_for_attribute("class", "y") == RestyleSelf);
+        assert!(dependencies.hint_for_attribute("title", "") == RestyleSelf);
+        assert!(dependencies.hint_for_attribute("href", "") == RestyleHint::empty());
+        assert!(dependencies.hint_for_hover_state() == RestyleDescendants);
+    }
+}
@@ -15,11 +15,13 @@ use servo_util::smallvec::VecLike;
 use servo_util::sort;
 use servo_util::str::DOMString;
 
-use media_queries::{Device, Screen};
+use media_queries::Device;
 use node::{TElement, TNode};
 use properties::{PropertyDeclaration, PropertyDeclarationBlock};
+use restyle_hints::{RestyleHint, RestyleSelf, SelectorDependencies};
 use selectors::*;
-use stylesheets::{Stylesheet, iter_style_rules};
+use page::{PageDescription, PageRule};
+use stylesheets::{Stylesheet, iter_page_rules, iter_style_rules};
 
 pub enum StylesheetOrigin {
     UserAgentOrigin,
@@ -208,6 +210,23 @@ impl SelectorMap {
         }
     }
 
+    /// Removes all rules that came from the author style sheet at `stylesheet_index`.
+    fn remove_stylesheet(&mut self, stylesheet_index: uint) {
+        let retain = |rules: &mut Vec<Rule>| {
+            rules.retain(|rule| rule.property.stylesheet_index != stylesheet_index)
+        };
+        for (_, rules) in self.id_hash.mut_iter() {
+            retain(rules)
+        }
+        for (_, rules) in self.class_hash.mut_iter() {
+            retain(rules)
+        }
+        for (_, rules) in self.element_hash.mut_iter() {
+            retain(rules)
+        }
+        retain(&mut self.universal_rules);
+    }
+
     /// Insert rule into the correct hash.
     /// Order in which to try: id_hash, class_hash, element_hash, universal_rules.
     fn insert(&mut self, rule: Rule) {
@@ -308,38 +327,77 @@ pub struct Stylist {
     before_map: PerPseudoElementSelectorMap,
     after_map: PerPseudoElementSelectorMap,
     rules_source_order: uint,
+    dependencies: SelectorDependencies,
+    page_rules: PerOriginPageRules,
+    /// The device that media queries are evaluated against.
+    device: Device,
 }
 
 impl Stylist {
     #[inline]
-    pub fn new() -> Stylist {
+    pub fn new(device: Device) -> Stylist {
         Stylist {
             element_map: PerPseudoElementSelectorMap::new(),
             before_map: PerPseudoElementSelectorMap::new(),
             after_map: PerPseudoElementSelectorMap::new(),
             rules_source_order: 0u,
+            dependencies: SelectorDependencies::new(),
+            page_rules: PerOriginPageRules::new(),
+            device: device,
+        }
+    }
+
+    pub fn add_stylesheet(&mut self, stylesheet: &Stylesheet, origin: StylesheetOrigin) {
+        self.add_stylesheet_at(stylesheet, origin, 0)
+    }
+
+    /// Adds the rules of the author style sheet at `stylesheet_index` in the document's list of
+    /// style sheets. Its rules cascade after those of sheets with a lower index, whatever order
+    /// the sheets were added to the stylist in.
+    pub fn add_author_stylesheet(&mut self, stylesheet: &Stylesheet, stylesheet_index: uint) {
+        self.add_stylesheet_at(stylesheet, AuthorOrigin, stylesheet_index)
+    }
+
+    /// Removes the rules of the author style sheet at `stylesheet_index`, leaving the rules of
+    /// all other style sheets alone. Used when a style sheet is modified or disabled through the
+    /// CSSOM; a modified sheet is then added again with `add_author_stylesheet`.
+    ///
+    /// The selector dependencies of the removed rules are kept. They only make restyle hints more
+    /// conservative.
+    pub fn remove_author_stylesheet(&mut self, stylesheet_index: uint) {
+        for map in [&mut self.element_map, &mut self.before_map, &mut self.after_map].mut_iter() {
+            map.author.normal.remove_stylesheet(stylesheet_index);
+            map.author.important.remove_stylesheet(stylesheet_index);
         }
+        self.page_rules.author.retain(|&(index, _)| index != stylesheet_index)
     }
 
-    pub fn add_stylesheet(&mut self, stylesheet: Stylesheet, origin: StylesheetOrigin) {
-        let (mut element_map, mut before_map, mut after_map) = match origin {
+    fn add_stylesheet_at(&mut self,
+                         stylesheet: &Stylesheet,
+                         origin: StylesheetOrigin,
+                         stylesheet_index: uint) {
+        let (mut element_map, mut before_map, mut after_map, page_rules) = match origin {
             UserAgentOrigin => (
                 &mut self.element_map.user_agent,
                 &mut self.before_map.user_agent,
                 &mut self.after_map.user_agent,
+                &mut self.page_rules.user_agent,
             ),
             AuthorOrigin => (
                 &mut self.element_map.author,
                 &mut self.before_map.author,
                 &mut self.after_map.author,
+                &mut self.page_rules.author,
             ),
             UserOrigin => (
                 &mut self.element_map.user,
                 &mut self.before_map.user,
                 &mut self.after_map.user,
+                &mut self.page_rules.user,
             ),
         };
         let mut rules_source_order = self.rules_source_order;
+        let dependencies = &mut self.dependencies;
 
         // Take apart the StyleRule into individual Rules and insert
         // them into the SelectorMap of that priority.
@@ -357,6 +415,7 @@ impl Stylist {
                                 property: MatchedProperty {
                                     specificity: selector.specificity,
                                     declarations: style_rule.declarations.$priority.clone(),
+                                    stylesheet_index: stylesheet_index,
                                     source_order: rules_source_order,
                                 },
                         });
@@ -365,13 +424,54 @@ impl Stylist {
             };
         );
 
-        let device = &Device { media_type: Screen };  // TODO, use Print when printing
+        let device = &self.device;
         iter_style_rules(stylesheet.rules.as_slice(), device, |style_rule| {
+            for selector in style_rule.selectors.iter() {
+                dependencies.note_selector(&*selector.compound_selectors)
+            }
             append!(normal);
             append!(important);
             rules_source_order += 1;
         });
         self.rules_source_order = rules_source_order;
+        // Keep page rules sorted by style sheet so that later sheets win.
+        let mut position = page_rules.iter().position(|&(index, _)| index > stylesheet_index)
+                                     .unwrap_or(page_rules.len());
+        iter_page_rules(stylesheet.rules.as_slice(), device, |page_rule| {
+            page_rules.insert(position, (stylesheet_index, page_rule.clone()));
+            position += 1;
+        });
+    }
+
+    /// Returns the size and margins of pages when the document is paginated, from the `@page`
+    /// rules of all origins.
+    ///
+    /// TODO: `!important` descriptors, and page selectors.
+    pub fn page_description(&self) -> PageDescription {
+        let mut description = PageDescription::default();
+        for &(_, ref rule) in self.page_rules.user_agent.iter()
+                                  .chain(self.page_rules.user.iter())
+                                  .chain(self.page_rules.author.iter()) {
+            description.apply(rule)
+        }
+        description
+    }
+
+    /// Returns the elements to restyle after an attribute named `name` is added to an element
+    /// with the value `value`, or removed from an element while it had that value.
+    pub fn restyle_hint_for_attribute(&self, name: &str, value: &str) -> RestyleHint {
+        let hint = self.dependencies.hint_for_attribute(name, value);
+        if name == "style" {
+            // The declarations of the style attribute apply to the element itself.
+            hint | RestyleSelf
+        } else {
+            hint
+        }
+    }
+
+    /// Returns the elements to restyle after an element enters or leaves the hover state.
+    pub fn restyle_hint_for_hover_state(&self) -> RestyleHint {
+        self.dependencies.hint_for_hover_state()
     }
 
     /// Returns the applicable CSS declarations for the given element. This corresponds to
@@ -380,18 +480,24 @@ impl Stylist {
     /// The returned boolean indicates whether the style is *shareable*; that is, whether the
     /// matched selectors are simple enough to allow the matching logic to be reduced to the logic
     /// in `css::matching::PrivateMatchMethods::candidate_element_allows_for_style_sharing`.
+    ///
+    /// `presentational_hints` are the element's hints from `synthesize_presentational_hints()`,
+    /// which the caller computes once per element.
     pub fn push_applicable_declarations<E:TElement,
                                         N:TNode<E>,
                                         V:VecLike<MatchedProperty>>(
                                         &self,
                                         element: &N,
                                         style_attribute: Option<&PropertyDeclarationBlock>,
+                                        presentational_hints: Vec<PropertyDeclaration>,
                                         pseudo_element: Option<PseudoElement>,
                                         applicable_declarations: &mut V)
                                         -> bool {
         assert!(element.is_element());
         assert!(style_attribute.is_none() || pseudo_element.is_none(),
                 "Style attributes do not apply to pseudo-elements");
+        assert!(presentational_hints.is_empty() || pseudo_element.is_none(),
+                "Presentational hints do not apply to pseudo-elements");
 
         let map = match pseudo_element {
             None => &self.element_map,
@@ -406,26 +512,35 @@ impl Stylist {
                                                      applicable_declarations,
                                                      &mut shareable);
         map.user.normal.get_all_matching_rules(element, applicable_declarations, &mut shareable);
+
+        // Step 2: Presentational hints from legacy HTML attributes. These come before all author
+        // rules (CSS 2.1 § 6.4.4).
+        if !presentational_hints.is_empty() {
+            shareable = false;
+            applicable_declarations.vec_push(MatchedProperty::from_declarations(
+                    Arc::new(presentational_hints)))
+        }
+
         map.author.normal.get_all_matching_rules(element, applicable_declarations, &mut shareable);
 
-        // Step 2: Normal style attributes.
+        // Step 3: Normal style attributes.
         style_attribute.map(|sa| {
             shareable = false;
             applicable_declarations.vec_push(MatchedProperty::from_declarations(sa.normal.clone()))
         });
 
-        // Step 3: Author-supplied `!important` rules.
+        // Step 4: Author-supplied `!important` rules.
         map.author.important.get_all_matching_rules(element,
                                                     applicable_declarations,
                                                     &mut shareable);
 
-        // Step 4: `!important` style attributes.
+        // Step 5: `!important` style attributes.
         style_attribute.map(|sa| {
             shareable = false;
             applicable_declarations.vec_push(MatchedProperty::from_declarations(sa.important.clone()))
         });
 
-        // Step 5: User and UA `!important` rules.
+        // Step 6: User and UA `!important` rules.
         map.user.important.get_all_matching_rules(element,
                                                   applicable_declarations,
                                                   &mut shareable);
@@ -469,6 +584,24 @@ impl PerPseudoElementSelectorMap {
     }
 }
 
+/// The `@page` rules of each origin, tagged with the index of their style sheet.
+struct PerOriginPageRules {
+    user_agent: Vec<(uint, PageRule)>,
+    author: Vec<(uint, PageRule)>,
+    user: Vec<(uint, PageRule)>,
+}
+
+impl PerOriginPageRules {
+    #[inline]
+    fn new() -> PerOriginPageRules {
+        PerOriginPageRules {
+            user_agent: vec!(),
+            author: vec!(),
+            user: vec!(),
+        }
+    }
+}
+
 #[deriving(Clone)]
 struct Rule {
     // This is an Arc because Rule will essentially be cloned for every node
@@ -483,6 +616,8 @@ struct Rule {
 #[deriving(Clone)]
 pub struct MatchedProperty {
     pub declarations: Arc<Vec<PropertyDeclaration>>,
+    /// The index of the author style sheet this rule came from. Always 0 for other origins.
+    stylesheet_index: uint,
     source_order: uint,
     specificity: u32,
 }
@@ -492,6 +627,7 @@ impl MatchedProperty {
     pub fn from_declarations(declarations: Arc<Vec<PropertyDeclaration>>) -> MatchedProperty {
         MatchedProperty {
             declarations: declarations,
+            stylesheet_index: 0,
             source_order: 0,
             specificity: 0,
         }
@@ -501,8 +637,8 @@ impl MatchedProperty {
 impl PartialEq for MatchedProperty {
     #[inline]
     fn eq(&self, other: &MatchedProperty) -> bool {
-        let this_rank = (self.specificity, self.source_order);
-        let other_rank = (other.specificity, other.source_order);
+        let this_rank = (self.specificity, self.stylesheet_index, self.source_order);
+        let other_rank = (other.specificity, other.stylesheet_index, other.source_order);
         this_rank == other_rank
     }
 }
@@ -512,8 +648,8 @@ impl Eq for MatchedProperty {}
 impl PartialOrd for MatchedProperty {
     #[inline]
     fn lt(&self, other: &MatchedProperty) -> bool {
-        let this_rank = (self.specificity, self.source_order);
-        let other_rank = (other.specificity, other.source_order);
+        let this_rank = (self.specificity, self.stylesheet_index, self.source_order);
+        let other_rank = (other.specificity, other.stylesheet_index, other.source_order);
         this_rank < other_rank
     }
 }
@@ -521,8 +657,8 @@ impl PartialOrd for MatchedProperty {
 impl Ord for MatchedProperty {
     #[inline]
     fn cmp(&self, other: &MatchedProperty) -> Ordering {
-        let this_rank = (self.specificity, self.source_order);
-        let other_rank = (other.specificity, other.source_order);
+        let this_rank = (self.specificity, self.stylesheet_index, self.source_order);
+        let other_rank = (other.specificity, other.stylesheet_index, other.source_order);
         this_rank.cmp(&other_rank)
     }
 }
@@ -712,8 +848,8 @@ fn matches_simple_selector<E:TElement,
         }
         AttrEqual(ref attr, ref value) => {
             if value.as_slice() != "DIR" {
-                // FIXME(pcwalton): Remove once we start actually supporting RTL text. This is in
-                // here because the UA style otherwise disables all style sharing completely.
+                // The UA style matches `DIR` on every element, so this would otherwise disable
+                // all style sharing completely. Style sharing compares `dir` attributes instead.
                 *shareable = false
             }
             element.match_attr(attr, |attr_value| {
@@ -955,7 +1091,7 @@ fn matches_last_child<E:TElement,N:TNode<E>>(element: &N) -> bool {
 #[cfg(test)]
 mod tests {
     use sync::Arc;
-    use super::{MatchedProperty, Rule, SelectorMap};
+    use super::{MatchedProperty, Rule, SelectorMap, Stylist};
 
     /// Helper method to get some Rules from selector strings.
     /// Each sublist of the result contains the Rules for one StyleRule.
@@ -973,6 +1109,7 @@ mod tests {
                     property: MatchedProperty {
                         specificity: s.specificity,
                         declarations: Arc::new(vec!()),
+                        stylesheet_index: 0,
                         source_order: i,
                     }
                 }
@@ -1021,4 +1158,42 @@ mod tests {
         assert_eq!(0, selector_map.class_hash.find_equiv(&("intro")).unwrap().get(0).property.source_order);
         assert!(selector_map.class_hash.find_equiv(&("foo")).is_none());
     }
+
+    #[test]
+    fn test_remove_stylesheet() {
+        let rules_list = get_mock_rules([".intro", "#top", "p"]);
+        let mut selector_map = SelectorMap::new();
+        for (i, rules) in rules_list.iter().enumerate() {
+            let mut rule = rules.get(0).clone();
+            rule.property.stylesheet_index = i % 2;
+            selector_map.insert(rule);
+        }
+        selector_map.remove_stylesheet(0);
+        assert!(selector_map.class_hash.find_equiv(&("intro")).unwrap().is_empty());
+        assert!(selector_map.element_hash.find_equiv(&("p")).unwrap().is_empty());
+        assert_eq!(1, selector_map.id_hash.find_equiv(&("top")).unwrap().len());
+    }
+
+    #[test]
+    fn test_replace_author_stylesheet() {
+        use media_queries::{Device, Screen};
+        use servo_util::geometry::Au;
+        use stylesheets::Stylesheet;
+
+        let stylesheet = |css: &str| {
+            Stylesheet::from_str(css, from_str("http://www.example.com/").unwrap())
+        };
+        let mut stylist = Stylist::new(Device { media_type: Screen });
+        stylist.add_author_stylesheet(&stylesheet("@page { size: 100px }"), 0);
+        stylist.add_author_stylesheet(&stylesheet("@page { size: 200px }"), 1);
+        assert_eq!(stylist.page_description().width, Au::from_px(200));
+
+        // A replaced sheet keeps its place in the cascade.
+        stylist.remove_author_stylesheet(0);
+        stylist.add_author_stylesheet(&stylesheet("@page { size: 300px }"), 0);
+        assert_eq!(stylist.page_description().width, Au::from_px(200));
+
+        stylist.remove_author_stylesheet(1);
+        assert_eq!(stylist.page_description().width, Au::from_px(300));
+    }
 }
@@ -31,28 +31,41 @@ extern crate servo_util = "util";
 
 
 // Public API
-pub use stylesheets::{Stylesheet, CSSRule, StyleRule};
+pub use stylesheets::{Stylesheet, CSSRule, StyleRule, CSSStyleRule, CSSMediaRule, CSSSupportsRule};
+pub use stylesheets::CSSPageRule;
+pub use stylesheets::{nested_rule_list, nested_rule_list_mut};
 pub use selector_matching::{Stylist, StylesheetOrigin, UserAgentOrigin, AuthorOrigin, UserOrigin};
 pub use selector_matching::{MatchedProperty, matches_compound_selector};
 pub use properties::{cascade, cascade_anonymous};
 pub use properties::{PropertyDeclaration, ComputedValues, computed_values, style_structs};
 pub use properties::{PropertyDeclarationBlock, parse_style_attribute};  // Style attributes
-pub use properties::{CSSFloat, DeclaredValue, PropertyDeclarationParseResult};
+pub use properties::{parse_style_attribute_as_written, parse_declaration_as_written};
+pub use properties::{serialize_shorthand, shorthand_longhands};
+pub use properties::{CSSFloat, DeclaredValue, PropertyDeclarationParseResult, ToComputedCss};
 pub use properties::longhands;
 pub use errors::with_errors_silenced;
+pub use legacy::{synthesize_presentational_hints, is_presentational_hint_attribute};
 pub use node::{TElement, TNode};
 pub use selectors::{PseudoElement, Before, After, AttrSelector, SpecificNamespace, AnyNamespace};
 pub use selectors::{NamespaceConstraint, Selector, CompoundSelector, SimpleSelector, Combinator};
 pub use selectors::{parse_selector_list};
+pub use restyle_hints::{RestyleHint, RestyleSelf, RestyleDescendants, RestyleLaterSiblings};
 pub use namespaces::NamespaceMap;
 pub use media_queries::{MediaRule, MediaQueryList, MediaQuery, Device, MediaType, MediaQueryType};
+pub use media_queries::{Screen, Print};
+pub use page::{PageRule, PageDescription};
+pub use supports::{SupportsRule, SupportsCondition, supports_property_value, supports_condition_text};
 
 mod stylesheets;
 mod errors;
 mod selectors;
 mod selector_matching;
+mod restyle_hints;
 mod properties;
 mod namespaces;
 mod node;
+mod legacy;
 mod media_queries;
+mod supports;
+mod page;
 mod parsing_utils;
@@ -16,24 +16,36 @@ use errors::{ErrorLoggerIterator, log_css_error};
 use namespaces::{NamespaceMap, parse_namespace_rule};
 use media_queries::{MediaRule, parse_media_rule};
 use media_queries;
+use supports::{SupportsRule, parse_supports_rule};
+use page::{PageRule, parse_page_rule};
 
 
+#[deriving(Clone)]
 pub struct Stylesheet {
     /// List of rules in the order they were found (important for
     /// cascading order)
     pub rules: Vec<CSSRule>,
+    /// The URL relative URLs in rules inserted through the CSSOM resolve against.
+    pub base_url: Url,
 }
 
 
+#[deriving(Clone)]
 pub enum CSSRule {
     CSSStyleRule(StyleRule),
     CSSMediaRule(MediaRule),
+    CSSSupportsRule(SupportsRule),
+    CSSPageRule(PageRule),
 }
 
 
+#[deriving(Clone)]
 pub struct StyleRule {
     pub selectors: Vec<selectors::Selector>,
     pub declarations: properties::PropertyDeclarationBlock,
+    /// The selector list and declaration block as written, for `CSSStyleRule`.
+    pub selector_text: String,
+    pub declarations_text: String,
 }
 
 
@@ -117,21 +129,81 @@ impl Stylesheet {
             }
             state = next_state;
         }
-        Stylesheet{ rules: rules }
+        Stylesheet{ rules: rules, base_url: base_url }
     }
+
+    /// Parses a single rule, as for `CSSStyleSheet.insertRule()`. Returns `None` if `css` does
+    /// not contain exactly one valid style or conditional rule.
+    pub fn parse_rule(css: &str, base_url: &Url) -> Option<CSSRule> {
+        let mut rules = vec!();
+        let namespaces = NamespaceMap::new();
+        let mut count = 0u;
+        for rule in ErrorLoggerIterator(parse_stylesheet_rules(tokenize(css))) {
+            count += 1;
+            match rule {
+                QualifiedRule(rule) => parse_style_rule(rule, &mut rules, &namespaces, base_url),
+                AtRule(rule) => {
+                    let lower_name = rule.name.as_slice().to_ascii_lower();
+                    parse_nested_at_rule(lower_name.as_slice(), rule, &mut rules, &namespaces,
+                                         base_url)
+                }
+            }
+        }
+        if count != 1 || rules.len() != 1 {
+            return None
+        }
+        rules.pop()
+    }
+}
+
+
+impl CSSRule {
+    /// Serializes this rule, as for `CSSRule.cssText`.
+    pub fn to_css(&self) -> String {
+        match *self {
+            CSSStyleRule(ref rule) => {
+                format!("{} {{ {} }}", rule.selector_text, rule.declarations_text)
+            }
+            CSSMediaRule(ref rule) => {
+                format!("@media {} {{\n{}}}", rule.media_queries.to_css(),
+                        nested_rules_to_css(rule.rules.as_slice()))
+            }
+            CSSSupportsRule(ref rule) => {
+                format!("@supports {} {{\n{}}}", rule.condition_text,
+                        nested_rules_to_css(rule.rules.as_slice()))
+            }
+            CSSPageRule(ref rule) => format!("@page {{ {} }}", rule.declarations_text),
+        }
+    }
+}
+
+
+fn nested_rules_to_css(rules: &[CSSRule]) -> String {
+    let mut css = String::new();
+    for rule in rules.iter() {
+        css.push_str("  ");
+        css.push_str(rule.to_css().as_slice());
+        css.push_str("\n");
+    }
+    css
 }
 
 
 pub fn parse_style_rule(rule: QualifiedRule, parent_rules: &mut Vec<CSSRule>,
                         namespaces: &NamespaceMap, base_url: &Url) {
     let QualifiedRule{location: location, prelude: prelude, block: block} = rule;
-    // FIXME: avoid doing this for valid selectors
     let serialized = prelude.iter().to_css();
     match selectors::parse_selector_list(prelude, namespaces) {
-        Some(selectors) => parent_rules.push(CSSStyleRule(StyleRule{
-            selectors: selectors,
-            declarations: properties::parse_property_declaration_list(block.move_iter(), base_url)
-        })),
+        Some(selectors) => {
+            let declarations_text = block.iter().map(|&(ref c, _)| c).to_css();
+            parent_rules.push(CSSStyleRule(StyleRule{
+                selectors: selectors,
+                declarations: properties::parse_property_declaration_list(block.move_iter(),
+                                                                          base_url),
+                selector_text: serialized.as_slice().trim().to_string(),
+                declarations_text: declarations_text.as_slice().trim().to_string(),
+            }))
+        }
         None => log_css_error(location, format!(
             "Invalid/unsupported selector: {}", serialized).as_slice()),
     }
@@ -143,12 +215,47 @@ pub fn parse_nested_at_rule(lower_name: &str, rule: AtRule,
                             parent_rules: &mut Vec<CSSRule>, namespaces: &NamespaceMap, base_url: &Url) {
     match lower_name {
         "media" => parse_media_rule(rule, parent_rules, namespaces, base_url),
+        "supports" => parse_supports_rule(rule, parent_rules, namespaces, base_url),
+        "page" => parse_page_rule(rule, parent_rules),
         _ => log_css_error(rule.location,
                            format!("Unsupported at-rule: @{:s}", lower_name).as_slice())
     }
 }
 
 
+/// Returns the rule list at `path` below `rules`: `rules` itself for an empty path, otherwise the
+/// child rules of the conditional rule at `path[0]`, and so on. This is how the CSSOM addresses
+/// nested rule lists when they are modified.
+pub fn nested_rule_list<'a>(rules: &'a Vec<CSSRule>, path: &[uint]) -> Option<&'a Vec<CSSRule>> {
+    if path.is_empty() {
+        return Some(rules)
+    }
+    match rules.as_slice().get(path[0]) {
+        Some(&CSSMediaRule(ref rule)) => nested_rule_list(&rule.rules, path.slice_from(1)),
+        Some(&CSSSupportsRule(ref rule)) => nested_rule_list(&rule.rules, path.slice_from(1)),
+        _ => None,
+    }
+}
+
+
+pub fn nested_rule_list_mut<'a>(rules: &'a mut Vec<CSSRule>, path: &[uint])
+                                -> Option<&'a mut Vec<CSSRule>> {
+    if path.is_empty() {
+        return Some(rules)
+    }
+    match rules.mut_iter().nth(path[0]) {
+        Some(rule) => match *rule {
+            CSSMediaRule(ref mut rule) => nested_rule_list_mut(&mut rule.rules, path.slice_from(1)),
+            CSSSupportsRule(ref mut rule) => {
+                nested_rule_list_mut(&mut rule.rules, path.slice_from(1))
+            }
+            CSSStyleRule(_) | CSSPageRule(_) => None,
+        },
+        None => None,
+    }
+}
+
+
 pub fn iter_style_rules<'a>(rules: &[CSSRule], device: &media_queries::Device,
                             callback: |&StyleRule|) {
     for rule in rules.iter() {
@@ -156,7 +263,28 @@ pub fn iter_style_rules<'a>(rules: &[CSSRule], device: &media_queries::Device,
             CSSStyleRule(ref rule) => callback(rule),
             CSSMediaRule(ref rule) => if rule.media_queries.evaluate(device) {
                 iter_style_rules(rule.rules.as_slice(), device, |s| callback(s))
-            }
+            },
+            CSSSupportsRule(ref rule) => if rule.enabled {
+                iter_style_rules(rule.rules.as_slice(), device, |s| callback(s))
+            },
+            CSSPageRule(_) => {}
+        }
+    }
+}
+
+
+pub fn iter_page_rules<'a>(rules: &[CSSRule], device: &media_queries::Device,
+                           callback: |&PageRule|) {
+    for rule in rules.iter() {
+        match *rule {
+            CSSStyleRule(_) => {}
+            CSSMediaRule(ref rule) => if rule.media_queries.evaluate(device) {
+                iter_page_rules(rule.rules.as_slice(), device, |p| callback(p))
+            },
+            CSSSupportsRule(ref rule) => if rule.enabled {
+                iter_page_rules(rule.rules.as_slice(), device, |p| callback(p))
+            },
+            CSSPageRule(ref rule) => callback(rule),
         }
     }
 }
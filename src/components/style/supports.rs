@@ -0,0 +1,276 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::ascii::StrAsciiExt;
+use cssparser::{tokenize, parse_rule_list, ToCss};
+use cssparser::ast::*;
+
+use errors::{ErrorLoggerIterator, log_css_error};
+use properties::{PropertyDeclaration, PropertyBitField, ValidOrIgnoredDeclaration};
+use stylesheets::{CSSRule, CSSSupportsRule, parse_style_rule, parse_nested_at_rule};
+use namespaces::NamespaceMap;
+use url::Url;
+
+
+#[deriving(Clone)]
+pub struct SupportsRule {
+    pub condition: SupportsCondition,
+    /// The condition as written, for serialization.
+    pub condition_text: String,
+    /// The result of evaluating `condition`. It only depends on what the style system can parse,
+    /// so it is computed once when the rule is parsed.
+    pub enabled: bool,
+    pub rules: Vec<CSSRule>,
+}
+
+
+/// A `<supports-condition>`, as defined in CSS Conditional Rules Level 3 § 6.1.
+#[deriving(Clone)]
+pub enum SupportsCondition {
+    NotCondition(Box<SupportsCondition>),
+    AndCondition(Vec<SupportsCondition>),
+    OrCondition(Vec<SupportsCondition>),
+    /// A `(property: value)` test. The value is stored with surrounding whitespace removed.
+    DeclarationCondition(String, Vec<ComponentValue>),
+    /// Anything else in parentheses or a function. Always evaluates to false.
+    GeneralEnclosedCondition,
+}
+
+
+pub fn parse_supports_rule(rule: AtRule, parent_rules: &mut Vec<CSSRule>,
+                           namespaces: &NamespaceMap, base_url: &Url) {
+    let condition_text = rule.prelude.iter().to_css();
+    let condition = match parse_supports_condition(rule.prelude.as_slice()) {
+        Some(condition) => condition,
+        None => {
+            log_css_error(rule.location, "Invalid @supports condition");
+            return
+        }
+    };
+    let block = match rule.block {
+        Some(block) => block,
+        None => {
+            log_css_error(rule.location, "Invalid @supports rule");
+            return
+        }
+    };
+    let mut rules = vec!();
+    for rule in ErrorLoggerIterator(parse_rule_list(block.move_iter())) {
+        match rule {
+            QualifiedRule(rule) => parse_style_rule(rule, &mut rules, namespaces, base_url),
+            AtRule(rule) => parse_nested_at_rule(
+                rule.name.as_slice().to_ascii_lower().as_slice(), rule, &mut rules, namespaces, base_url),
+        }
+    }
+    let enabled = condition.evaluate(base_url);
+    parent_rules.push(CSSSupportsRule(SupportsRule {
+        condition: condition,
+        condition_text: condition_text.as_slice().trim().to_string(),
+        enabled: enabled,
+        rules: rules,
+    }))
+}
+
+
+/// Parses a `<supports-condition>`. Returns `None` on a syntax error.
+///
+/// `and` and `or` may not be mixed at the same level without parentheses.
+pub fn parse_supports_condition(input: &[ComponentValue]) -> Option<SupportsCondition> {
+    let iter = &mut input.skip_whitespace();
+    let first = match iter.next() {
+        Some(first) => first,
+        None => return None,
+    };
+    match *first {
+        Ident(ref value) if value.as_slice().eq_ignore_ascii_case("not") => {
+            let condition = match iter.next().and_then(parse_condition_in_parens) {
+                Some(condition) => condition,
+                None => return None,
+            };
+            match iter.next() {
+                None => Some(NotCondition(box condition)),
+                Some(_) => None,
+            }
+        }
+        _ => {
+            let mut conditions = match parse_condition_in_parens(first) {
+                Some(condition) => vec!(condition),
+                None => return None,
+            };
+            let mut is_and = None;
+            loop {
+                match iter.next() {
+                    None => break,
+                    Some(&Ident(ref value)) => {
+                        let this_is_and = match value.as_slice().to_ascii_lower().as_slice() {
+                            "and" => true,
+                            "or" => false,
+                            _ => return None,
+                        };
+                        match is_and {
+                            Some(is_and) if is_and != this_is_and => return None,
+                            _ => is_and = Some(this_is_and),
+                        }
+                    }
+                    Some(_) => return None,
+                }
+                match iter.next().and_then(parse_condition_in_parens) {
+                    Some(condition) => conditions.push(condition),
+                    None => return None,
+                }
+            }
+            match is_and {
+                None => conditions.pop(),
+                Some(true) => Some(AndCondition(conditions)),
+                Some(false) => Some(OrCondition(conditions)),
+            }
+        }
+    }
+}
+
+
+/// Parses a `<supports-in-parens>`: a nested condition, a declaration or a general enclosed
+/// production.
+fn parse_condition_in_parens(component_value: &ComponentValue) -> Option<SupportsCondition> {
+    match *component_value {
+        ParenthesisBlock(ref inner) => {
+            match parse_supports_condition(inner.as_slice()) {
+                Some(condition) => Some(condition),
+                None => Some(parse_declaration_condition(inner.as_slice())),
+            }
+        }
+        Function(..) => Some(GeneralEnclosedCondition),
+        _ => None,
+    }
+}
+
+
+fn parse_declaration_condition(input: &[ComponentValue]) -> SupportsCondition {
+    let mut start = 0;
+    while start < input.len() && input[start] == WhiteSpace {
+        start += 1
+    }
+    let name = match input.get(start) {
+        Some(&Ident(ref name)) => name.clone(),
+        _ => return GeneralEnclosedCondition,
+    };
+    start += 1;
+    while start < input.len() && input[start] == WhiteSpace {
+        start += 1
+    }
+    match input.get(start) {
+        Some(&Colon) => start += 1,
+        _ => return GeneralEnclosedCondition,
+    }
+    DeclarationCondition(name, trim_whitespace(input.slice_from(start)).to_vec())
+}
+
+
+fn trim_whitespace<'a>(input: &'a [ComponentValue]) -> &'a [ComponentValue] {
+    let mut start = 0;
+    let mut end = input.len();
+    while start < end && input[start] == WhiteSpace {
+        start += 1
+    }
+    while end > start && input[end - 1] == WhiteSpace {
+        end -= 1
+    }
+    input.slice(start, end)
+}
+
+
+/// Returns true if `property: value` would be accepted as a declaration by the style system.
+fn supports_declaration(property: &str, value: &[ComponentValue], base_url: &Url) -> bool {
+    if value.is_empty() {
+        return false
+    }
+    let mut declarations = vec!();
+    let mut seen = PropertyBitField::new();
+    match PropertyDeclaration::parse(property, value, &mut declarations, base_url, &mut seen) {
+        ValidOrIgnoredDeclaration => true,
+        _ => false,
+    }
+}
+
+
+impl SupportsCondition {
+    pub fn evaluate(&self, base_url: &Url) -> bool {
+        match *self {
+            NotCondition(ref condition) => !condition.evaluate(base_url),
+            AndCondition(ref conditions) => conditions.iter().all(|c| c.evaluate(base_url)),
+            OrCondition(ref conditions) => conditions.iter().any(|c| c.evaluate(base_url)),
+            DeclarationCondition(ref property, ref value) => {
+                supports_declaration(property.as_slice(), value.as_slice(), base_url)
+            }
+            GeneralEnclosedCondition => false,
+        }
+    }
+}
+
+
+fn tokenize_component_values(input: &str) -> Vec<ComponentValue> {
+    tokenize(input).map(|(component_value, _)| component_value).collect()
+}
+
+
+/// The two-argument form of `CSS.supports()`.
+pub fn supports_property_value(property: &str, value: &str, base_url: &Url) -> bool {
+    let value = tokenize_component_values(value);
+    supports_declaration(property, trim_whitespace(value.as_slice()), base_url)
+}
+
+
+/// The one-argument form of `CSS.supports()`. Like a browser would, a condition that fails to
+/// parse is retried as if it had been wrapped in parentheses, so that `CSS.supports("color: red")`
+/// works.
+pub fn supports_condition_text(condition_text: &str, base_url: &Url) -> bool {
+    let input = tokenize_component_values(condition_text);
+    match parse_supports_condition(input.as_slice()) {
+        Some(condition) => condition.evaluate(base_url),
+        None => {
+            let wrapped = vec!(ParenthesisBlock(input));
+            match parse_supports_condition(wrapped.as_slice()) {
+                Some(condition) => condition.evaluate(base_url),
+                None => false,
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+    use super::{supports_condition_text, supports_property_value};
+
+    fn base_url() -> Url {
+        from_str("http://www.example.com/").unwrap()
+    }
+
+    #[test]
+    fn test_supports_property_value() {
+        assert!(supports_property_value("display", "block", &base_url()));
+        assert!(supports_property_value("DISPLAY", " none ", &base_url()));
+        assert!(!supports_property_value("display", "grid-but-not-really", &base_url()));
+        assert!(!supports_property_value("not-a-property", "block", &base_url()));
+        assert!(!supports_property_value("display", "", &base_url()));
+    }
+
+    #[test]
+    fn test_supports_condition_text() {
+        assert!(supports_condition_text("(display: block)", &base_url()));
+        assert!(supports_condition_text("display: block", &base_url()));
+        assert!(!supports_condition_text("(display: bogus)", &base_url()));
+        assert!(supports_condition_text("not (display: bogus)", &base_url()));
+        assert!(supports_condition_text("(display: block) and (float: left)", &base_url()));
+        assert!(!supports_condition_text("(display: block) and (float: bogus)", &base_url()));
+        assert!(supports_condition_text("(display: bogus) or (float: left)", &base_url()));
+        assert!(supports_condition_text("((display: bogus) or (float: left)) and (color: red)",
+                                        &base_url()));
+        assert!(!supports_condition_text("(display: block) and (float: left) or (color: red)",
+                                         &base_url()));
+        assert!(!supports_condition_text("foo(display: block)", &base_url()));
+        assert!(!supports_condition_text("", &base_url()));
+    }
+}
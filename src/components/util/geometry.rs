@@ -5,6 +5,7 @@
 use geom::length::Length;
 use geom::point::Point2D;
 use geom::rect::Rect;
+use geom::side_offsets::SideOffsets2D;
 use geom::size::Size2D;
 
 use std::default::Default;
@@ -302,3 +303,303 @@ pub fn f32_rect_to_au_rect(rect: Rect<f32>) -> Rect<Au> {
          Size2D(Au::from_frac32_px(rect.size.width), Au::from_frac32_px(rect.size.height)))
 }
 
+/// A helper function to convert a rect of app units to a rect of `f32` pixels.
+pub fn au_rect_to_f32_rect(rect: Rect<Au>) -> Rect<f32> {
+    Rect(Point2D(to_frac_px(rect.origin.x) as f32, to_frac_px(rect.origin.y) as f32),
+         Size2D(to_frac_px(rect.size.width) as f32, to_frac_px(rect.size.height) as f32))
+}
+
+
+/// The writing mode of a flow, which determines how its logical axes map onto the physical ones.
+/// See CSS Writing Modes Level 3 § 3.1.
+///
+/// The inline axis always runs from line-left to line-right: left to right in horizontal writing
+/// modes and top to bottom in vertical ones. The `direction` property is handled by inline layout
+/// on top of that.
+#[deriving(Clone, PartialEq, Show)]
+pub enum WritingMode {
+    HorizontalTopToBottom,
+    VerticalRightToLeft,
+    VerticalLeftToRight,
+}
+
+impl WritingMode {
+    /// Returns true if lines are laid out vertically, that is, if the inline axis is the vertical
+    /// one.
+    #[inline]
+    pub fn is_vertical(&self) -> bool {
+        *self != HorizontalTopToBottom
+    }
+
+    /// Returns true if blocks are stacked from right to left.
+    #[inline]
+    pub fn is_vertical_rl(&self) -> bool {
+        *self == VerticalRightToLeft
+    }
+}
+
+/// A size in flow-relative dimensions.
+#[deriving(Clone, PartialEq, Show)]
+pub struct LogicalSize<T> {
+    /// The size along the inline axis (the width, in horizontal writing modes).
+    pub inline: T,
+    /// The size along the block axis (the height, in horizontal writing modes).
+    pub block: T,
+}
+
+impl<T:Copy + Zero> LogicalSize<T> {
+    #[inline]
+    pub fn new(inline: T, block: T) -> LogicalSize<T> {
+        LogicalSize {
+            inline: inline,
+            block: block,
+        }
+    }
+
+    #[inline]
+    pub fn zero() -> LogicalSize<T> {
+        LogicalSize::new(Zero::zero(), Zero::zero())
+    }
+
+    pub fn from_physical(mode: WritingMode, size: Size2D<T>) -> LogicalSize<T> {
+        if mode.is_vertical() {
+            LogicalSize::new(size.height, size.width)
+        } else {
+            LogicalSize::new(size.width, size.height)
+        }
+    }
+
+    pub fn to_physical(&self, mode: WritingMode) -> Size2D<T> {
+        if mode.is_vertical() {
+            Size2D(self.block, self.inline)
+        } else {
+            Size2D(self.inline, self.block)
+        }
+    }
+}
+
+/// A point in flow-relative coordinates, measured from the block-start, inline-start corner of
+/// its containing flow.
+#[deriving(Clone, PartialEq, Show)]
+pub struct LogicalPoint<T> {
+    /// The inline-axis coordinate.
+    pub i: T,
+    /// The block-axis coordinate.
+    pub b: T,
+}
+
+impl<T:Copy + Zero + Sub<T,T>> LogicalPoint<T> {
+    #[inline]
+    pub fn new(i: T, b: T) -> LogicalPoint<T> {
+        LogicalPoint {
+            i: i,
+            b: b,
+        }
+    }
+
+    #[inline]
+    pub fn zero() -> LogicalPoint<T> {
+        LogicalPoint::new(Zero::zero(), Zero::zero())
+    }
+
+    /// Converts a physical point to a logical one. `container_size` is the physical size of the
+    /// box whose top left corner is the origin of `point`.
+    pub fn from_physical(mode: WritingMode, point: Point2D<T>, container_size: Size2D<T>)
+                         -> LogicalPoint<T> {
+        match mode {
+            HorizontalTopToBottom => LogicalPoint::new(point.x, point.y),
+            VerticalLeftToRight => LogicalPoint::new(point.y, point.x),
+            VerticalRightToLeft => LogicalPoint::new(point.y, container_size.width - point.x),
+        }
+    }
+
+    /// Converts this point to a physical one, relative to the top left corner of a box of the
+    /// given physical size.
+    pub fn to_physical(&self, mode: WritingMode, container_size: Size2D<T>) -> Point2D<T> {
+        match mode {
+            HorizontalTopToBottom => Point2D(self.i, self.b),
+            VerticalLeftToRight => Point2D(self.b, self.i),
+            VerticalRightToLeft => Point2D(container_size.width - self.b, self.i),
+        }
+    }
+}
+
+/// A rectangle in flow-relative coordinates.
+#[deriving(Clone, PartialEq, Show)]
+pub struct LogicalRect<T> {
+    /// The block-start, inline-start corner of the rectangle.
+    pub start: LogicalPoint<T>,
+    pub size: LogicalSize<T>,
+}
+
+impl<T:Copy + Zero + Add<T,T> + Sub<T,T>> LogicalRect<T> {
+    #[inline]
+    pub fn new(inline_start: T, block_start: T, inline: T, block: T) -> LogicalRect<T> {
+        LogicalRect {
+            start: LogicalPoint::new(inline_start, block_start),
+            size: LogicalSize::new(inline, block),
+        }
+    }
+
+    #[inline]
+    pub fn zero() -> LogicalRect<T> {
+        LogicalRect {
+            start: LogicalPoint::zero(),
+            size: LogicalSize::zero(),
+        }
+    }
+
+    #[inline]
+    pub fn inline_end(&self) -> T {
+        self.start.i + self.size.inline
+    }
+
+    #[inline]
+    pub fn block_end(&self) -> T {
+        self.start.b + self.size.block
+    }
+
+    /// Converts a physical rectangle to a logical one. `container_size` is the physical size of
+    /// the box whose top left corner is the origin of `rect`.
+    pub fn from_physical(mode: WritingMode, rect: Rect<T>, container_size: Size2D<T>)
+                         -> LogicalRect<T> {
+        let size = LogicalSize::from_physical(mode, rect.size);
+        let start = match mode {
+            HorizontalTopToBottom => LogicalPoint::new(rect.origin.x, rect.origin.y),
+            VerticalLeftToRight => LogicalPoint::new(rect.origin.y, rect.origin.x),
+            VerticalRightToLeft => {
+                LogicalPoint::new(rect.origin.y,
+                                  container_size.width - (rect.origin.x + rect.size.width))
+            }
+        };
+        LogicalRect {
+            start: start,
+            size: size,
+        }
+    }
+
+    /// Converts this rectangle to a physical one, relative to the top left corner of a box of the
+    /// given physical size.
+    pub fn to_physical(&self, mode: WritingMode, container_size: Size2D<T>) -> Rect<T> {
+        let origin = match mode {
+            HorizontalTopToBottom => Point2D(self.start.i, self.start.b),
+            VerticalLeftToRight => Point2D(self.start.b, self.start.i),
+            VerticalRightToLeft => {
+                Point2D(container_size.width - (self.start.b + self.size.block), self.start.i)
+            }
+        };
+        Rect(origin, self.size.to_physical(mode))
+    }
+}
+
+/// Margin, border or padding widths in flow-relative directions.
+#[deriving(Clone, PartialEq, Show)]
+pub struct LogicalMargin<T> {
+    pub block_start: T,
+    pub inline_end: T,
+    pub block_end: T,
+    pub inline_start: T,
+}
+
+impl<T:Copy> LogicalMargin<T> {
+    #[inline]
+    pub fn new(block_start: T, inline_end: T, block_end: T, inline_start: T) -> LogicalMargin<T> {
+        LogicalMargin {
+            block_start: block_start,
+            inline_end: inline_end,
+            block_end: block_end,
+            inline_start: inline_start,
+        }
+    }
+
+    /// Maps the four physical sides of a box onto flow-relative ones.
+    pub fn from_physical_sides(mode: WritingMode, top: T, right: T, bottom: T, left: T)
+                               -> LogicalMargin<T> {
+        match mode {
+            HorizontalTopToBottom => LogicalMargin::new(top, right, bottom, left),
+            VerticalRightToLeft => LogicalMargin::new(right, bottom, left, top),
+            VerticalLeftToRight => LogicalMargin::new(left, bottom, right, top),
+        }
+    }
+
+    #[inline]
+    pub fn from_physical(mode: WritingMode, offsets: SideOffsets2D<T>) -> LogicalMargin<T> {
+        LogicalMargin::from_physical_sides(mode,
+                                           offsets.top,
+                                           offsets.right,
+                                           offsets.bottom,
+                                           offsets.left)
+    }
+}
+
+impl<T:Copy + Zero + Add<T,T>> LogicalMargin<T> {
+    #[inline]
+    pub fn zero() -> LogicalMargin<T> {
+        LogicalMargin::new(Zero::zero(), Zero::zero(), Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the sum of the inline-start and inline-end sides.
+    #[inline]
+    pub fn inline_start_end(&self) -> T {
+        self.inline_start + self.inline_end
+    }
+
+    /// Returns the sum of the block-start and block-end sides.
+    #[inline]
+    pub fn block_start_end(&self) -> T {
+        self.block_start + self.block_end
+    }
+
+    pub fn to_physical(&self, mode: WritingMode) -> SideOffsets2D<T> {
+        match mode {
+            HorizontalTopToBottom => {
+                SideOffsets2D::new(self.block_start,
+                                   self.inline_end,
+                                   self.block_end,
+                                   self.inline_start)
+            }
+            VerticalRightToLeft => {
+                SideOffsets2D::new(self.inline_start,
+                                   self.block_start,
+                                   self.inline_end,
+                                   self.block_end)
+            }
+            VerticalLeftToRight => {
+                SideOffsets2D::new(self.inline_start,
+                                   self.block_end,
+                                   self.inline_end,
+                                   self.block_start)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_logical_rect_round_trips_through_physical_coordinates() {
+    let container = Size2D(100i, 50i);
+    let rect = LogicalRect::new(5i, 10i, 20i, 30i);
+    for &mode in [HorizontalTopToBottom, VerticalRightToLeft, VerticalLeftToRight].iter() {
+        let physical = rect.to_physical(mode, container);
+        assert_eq!(LogicalRect::from_physical(mode, physical, container), rect);
+    }
+}
+
+#[test]
+fn test_vertical_rl_stacks_blocks_from_the_right() {
+    let rect = LogicalRect::new(5i, 10i, 20i, 30i);
+    let physical = rect.to_physical(VerticalRightToLeft, Size2D(100i, 50i));
+    assert_eq!(physical, Rect(Point2D(60i, 5i), Size2D(30i, 20i)));
+    let physical = rect.to_physical(VerticalLeftToRight, Size2D(100i, 50i));
+    assert_eq!(physical, Rect(Point2D(10i, 5i), Size2D(30i, 20i)));
+}
+
+#[test]
+fn test_logical_margin_sides() {
+    let offsets = SideOffsets2D::new(1i, 2i, 3i, 4i);
+    let margin = LogicalMargin::from_physical(VerticalRightToLeft, offsets);
+    assert_eq!(margin, LogicalMargin::new(2i, 3i, 4i, 1i));
+    assert_eq!(margin.to_physical(VerticalRightToLeft), offsets);
+    let margin = LogicalMargin::from_physical(VerticalLeftToRight, offsets);
+    assert_eq!(margin, LogicalMargin::new(4i, 3i, 2i, 1i));
+    assert_eq!(margin.to_physical(VerticalLeftToRight), offsets);
+}
@@ -11,10 +11,14 @@ use azure::azure_hl::{BackendType, CairoBackend, CoreGraphicsBackend};
 use azure::azure_hl::{CoreGraphicsAcceleratedBackend, Direct2DBackend, SkiaBackend};
 use geom::scale_factor::ScaleFactor;
 use getopts;
+use std::ascii::StrAsciiExt;
 use std::cmp;
 use std::io;
+use std::io::File;
 use std::os;
 use std::rt;
+use std_url::Url;
+use url::parse_url;
 
 /// Global flags for Servo, currently set on the command line.
 #[deriving(Clone)]
@@ -56,7 +60,19 @@ pub struct Opts {
     /// True to exit after the page load (`-x`).
     pub exit_after_load: bool,
 
+    /// The file to write the rendered page to once it has loaded (`-o`): a PNG image, or a PDF
+    /// document if the name ends in `.pdf`.
     pub output_file: Option<String>,
+
+    /// True to lay the document out into pages, using print media rules and `@page` rules. This
+    /// is set when the output file is a PDF document, or by `--paginate`, which renders the pages
+    /// one below the other like a long document.
+    pub paginated: bool,
+
+    /// The file to write a JSON dump of the flow tree to after every layout
+    /// (`--dump-layout-tree`). See `layout::layout_dump` for the format.
+    pub dump_layout_tree: Option<String>,
+
     pub headless: bool,
     pub hard_fail: bool,
 
@@ -65,6 +81,19 @@ pub struct Opts {
     /// may wish to turn this flag on in order to benchmark style recalculation against other
     /// browser engines.
     pub bubble_widths_separately: bool,
+
+    /// False to throw away every flow and rerun every layout pass on each reflow
+    /// (`--no-incremental-layout`). Useful for comparing incremental layout against full layout
+    /// with the time profiler.
+    pub incremental_layout: bool,
+
+    /// The contents and URLs of style sheets to apply with the user origin
+    /// (`--user-stylesheet`, which may be given several times).
+    pub user_stylesheets: Vec<(Vec<u8>, Url)>,
+
+    /// The contents and URL of a style sheet to use instead of the built-in user-agent style
+    /// sheet (`--user-agent-stylesheet`).
+    pub user_agent_stylesheet: Option<(Vec<u8>, Url)>,
 }
 
 fn print_usage(app: &str, opts: &[getopts::OptGroup]) {
@@ -77,13 +106,22 @@ fn args_fail(msg: &str) {
     os::set_exit_status(1);
 }
 
+/// Reads a style sheet named on the command line. Relative paths are resolved against the current
+/// working directory.
+fn read_stylesheet(path: &str) -> Result<(Vec<u8>, Url), String> {
+    match File::open(&Path::new(path)).read_to_end() {
+        Ok(contents) => Ok((contents, parse_url(path, None))),
+        Err(error) => Err(format!("Couldn't read style sheet {}: {}", path, error)),
+    }
+}
+
 pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
     let app_name = args[0].to_str();
     let args = args.tail();
 
     let opts = vec!(
         getopts::optflag("c", "cpu", "CPU rendering"),
-        getopts::optopt("o", "output", "Output file", "output.png"),
+        getopts::optopt("o", "output", "Output file (PNG, or PDF for paginated output)", "output.png"),
         getopts::optopt("r", "rendering", "Rendering backend", "direct2d|core-graphics|core-graphics-accelerated|cairo|skia."),
         getopts::optopt("s", "size", "Size of tiles", "512"),
         getopts::optopt("", "device-pixel-ratio", "Device pixels per px", ""),
@@ -94,7 +132,12 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         getopts::optopt("y", "layout-threads", "Number of threads to use for layout", "1"),
         getopts::optflag("z", "headless", "Headless mode"),
         getopts::optflag("f", "hard-fail", "Exit on task failure instead of displaying about:failure"),
+        getopts::optopt("", "dump-layout-tree", "Write the flow tree as JSON to a file after layout", "layout.json"),
         getopts::optflag("b", "bubble-widths", "Bubble intrinsic widths separately like other engines"),
+        getopts::optflag("", "paginate", "Lay the document out into pages, as for PDF output"),
+        getopts::optflag("", "no-incremental-layout", "Rebuild all flows and display lists on every reflow"),
+        getopts::optmulti("", "user-stylesheet", "A user style sheet to apply to every page", "user.css"),
+        getopts::optopt("", "user-agent-stylesheet", "Replace the built-in user-agent style sheet", "ua.css"),
         getopts::optflag("h", "help", "Print this message")
     );
 
@@ -167,6 +210,34 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         None => cmp::max(rt::default_sched_threads() * 3 / 4, 1),
     };
 
+    let mut user_stylesheets = vec!();
+    for path in opt_match.opt_strs("user-stylesheet").iter() {
+        match read_stylesheet(path.as_slice()) {
+            Ok(stylesheet) => user_stylesheets.push(stylesheet),
+            Err(msg) => {
+                args_fail(msg.as_slice());
+                return None
+            }
+        }
+    }
+
+    let user_agent_stylesheet = match opt_match.opt_str("user-agent-stylesheet") {
+        Some(path) => match read_stylesheet(path.as_slice()) {
+            Ok(stylesheet) => Some(stylesheet),
+            Err(msg) => {
+                args_fail(msg.as_slice());
+                return None
+            }
+        },
+        None => None,
+    };
+
+    let output_file = opt_match.opt_str("o");
+    let paginated = opt_match.opt_present("paginate") || match output_file {
+        Some(ref path) => is_pdf_output(path.as_slice()),
+        None => false,
+    };
+
     Some(Opts {
         urls: urls,
         render_backend: render_backend,
@@ -178,9 +249,92 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         memory_profiler_period: memory_profiler_period,
         layout_threads: layout_threads,
         exit_after_load: opt_match.opt_present("x"),
-        output_file: opt_match.opt_str("o"),
+        output_file: output_file,
+        paginated: paginated,
+        dump_layout_tree: opt_match.opt_str("dump-layout-tree"),
         headless: opt_match.opt_present("z"),
         hard_fail: opt_match.opt_present("f"),
         bubble_widths_separately: opt_match.opt_present("b"),
+        incremental_layout: !opt_match.opt_present("no-incremental-layout"),
+        user_stylesheets: user_stylesheets,
+        user_agent_stylesheet: user_agent_stylesheet,
     })
 }
+
+/// Returns true if the output file at `path` is a PDF document rather than a PNG image.
+pub fn is_pdf_output(path: &str) -> bool {
+    path.to_ascii_lower().as_slice().ends_with(".pdf")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_cmdline_args, read_stylesheet};
+    use std::io::{File, TempDir};
+
+    fn to_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_no_stylesheets() {
+        let opts = from_cmdline_args(to_args(["servo", "about:blank"]).as_slice()).unwrap();
+        assert!(opts.user_stylesheets.is_empty());
+        assert!(opts.user_agent_stylesheet.is_none());
+    }
+
+    #[test]
+    fn test_paginated() {
+        let opts = from_cmdline_args(to_args(["servo", "about:blank"]).as_slice()).unwrap();
+        assert!(!opts.paginated);
+        let args = to_args(["servo", "-o", "out.PDF", "about:blank"]);
+        assert!(from_cmdline_args(args.as_slice()).unwrap().paginated);
+        let args = to_args(["servo", "--paginate", "-o", "out.png", "about:blank"]);
+        assert!(from_cmdline_args(args.as_slice()).unwrap().paginated);
+    }
+
+    #[test]
+    fn test_stylesheet_options() {
+        let dir = TempDir::new("servo-opts").unwrap();
+        let first = dir.path().join("first.css");
+        let second = dir.path().join("second.css");
+        let user_agent = dir.path().join("ua.css");
+        File::create(&first).write_str("p { color: red }").unwrap();
+        File::create(&second).write_str("p { color: green }").unwrap();
+        File::create(&user_agent).write_str("p { display: block }").unwrap();
+
+        let first = first.display().to_str();
+        let second = second.display().to_str();
+        let user_agent = user_agent.display().to_str();
+        let args = to_args(["servo",
+                            "--user-stylesheet", first.as_slice(),
+                            "--user-stylesheet", second.as_slice(),
+                            "--user-agent-stylesheet", user_agent.as_slice(),
+                            "about:blank"]);
+        let opts = from_cmdline_args(args.as_slice()).unwrap();
+
+        // User style sheets keep their command line order, which is their cascade order.
+        assert_eq!(opts.user_stylesheets.len(), 2);
+        let (ref contents, ref url) = *opts.user_stylesheets.get(0);
+        assert_eq!(contents.as_slice(), b"p { color: red }");
+        assert!(url.scheme.as_slice() == "file");
+        assert!(url.path.as_slice().ends_with("first.css"));
+        let (ref contents, ref url) = *opts.user_stylesheets.get(1);
+        assert_eq!(contents.as_slice(), b"p { color: green }");
+        assert!(url.path.as_slice().ends_with("second.css"));
+
+        let (contents, url) = opts.user_agent_stylesheet.unwrap();
+        assert_eq!(contents.as_slice(), b"p { display: block }");
+        assert!(url.path.as_slice().ends_with("ua.css"));
+    }
+
+    #[test]
+    fn test_missing_stylesheet() {
+        let dir = TempDir::new("servo-opts").unwrap();
+        let missing = dir.path().join("missing.css");
+        let missing = missing.display().to_str();
+        match read_stylesheet(missing.as_slice()) {
+            Ok(_) => fail!("read a style sheet that does not exist"),
+            Err(msg) => assert!(msg.as_slice().contains("missing.css")),
+        }
+    }
+}
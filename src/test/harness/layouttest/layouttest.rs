@@ -0,0 +1,218 @@
+// Copyright 2014 The Servo Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Layout tests: loads each test with `--dump-layout-tree` and compares the resulting flow tree
+//! with a stored expectation. Unlike reftests, a failure names the first flow or fragment whose
+//! layout differs.
+
+extern crate serialize;
+extern crate std;
+extern crate test;
+extern crate regex;
+
+use serialize::json;
+use serialize::json::Json;
+use std::io;
+use std::io::{File, Reader, Command};
+use std::io::process::ExitStatus;
+use std::num;
+use std::os;
+use test::{AutoColor, DynTestName, DynTestFn, TestDesc, TestOpts, TestDescAndFn};
+use test::run_tests_console;
+use regex::Regex;
+
+/// The largest difference between two lengths, in px, that still counts as a match. Dumps store
+/// lengths as decimal fractions of a pixel, which don't round-trip exactly.
+static LENGTH_TOLERANCE: f64 = 0.01;
+
+fn main() {
+    let args = os::args();
+    let mut parts = args.tail().split(|e| "--" == e.as_slice());
+
+    let harness_args = parts.next().unwrap();  // .split() is never empty
+    let servo_args = parts.next().unwrap_or(&[]);
+
+    let (manifest, testname) = match harness_args {
+        [] => fail!("error: at least one layout test list must be given"),
+        [ref manifest] => (manifest, None),
+        [ref manifest, ref testname, ..] => {
+            (manifest, Some(Regex::new(testname.as_slice()).unwrap()))
+        }
+    };
+
+    let tests = parse_lists(manifest, servo_args);
+    let test_opts = TestOpts {
+        filter: testname,
+        run_ignored: false,
+        logfile: None,
+        run_tests: true,
+        run_benchmarks: false,
+        ratchet_noise_percent: None,
+        ratchet_metrics: None,
+        save_metrics: None,
+        test_shard: None,
+        nocapture: false,
+        color: AutoColor
+    };
+
+    match run_tests_console(&test_opts, tests) {
+        Ok(false) => os::set_exit_status(1), // tests failed
+        Err(_) => os::set_exit_status(2),    // I/O-related failure
+        _ => (),
+    }
+}
+
+struct LayoutTest {
+    name: String,
+    file: String,
+    expectation: String,
+    id: uint,
+    servo_args: Vec<String>,
+}
+
+fn parse_lists(file: &String, servo_args: &[String]) -> Vec<TestDescAndFn> {
+    let mut tests = Vec::new();
+    let mut next_id = 0;
+    let file_path = Path::new(file.clone());
+    let contents = match File::open_mode(&file_path, io::Open, io::Read)
+                              .and_then(|mut f| f.read_to_str()) {
+        Ok(s) => s,
+        _ => fail!("Could not read file"),
+    };
+
+    for line in contents.as_slice().lines() {
+        // ignore comments and blank lines
+        if line.starts_with("#") || line.trim().is_empty() {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split(' ').filter(|p| !p.is_empty()).collect();
+        if parts.len() != 2 {
+            fail!("layout test line: '{:s}' doesn't match 'TEST EXPECTATION'", line);
+        }
+
+        let src_dir = file_path.dir_path().display().to_str();
+        let test = LayoutTest {
+            name: parts.get(0).to_string(),
+            file: src_dir.clone().append("/").append(*parts.get(0)),
+            expectation: src_dir.append("/").append(*parts.get(1)),
+            id: next_id,
+            servo_args: servo_args.iter().map(|x| x.clone()).collect(),
+        };
+
+        next_id += 1;
+
+        tests.push(make_test(test));
+    }
+    tests
+}
+
+fn make_test(test: LayoutTest) -> TestDescAndFn {
+    let name = test.name.clone();
+    TestDescAndFn {
+        desc: TestDesc {
+            name: DynTestName(name),
+            ignore: false,
+            should_fail: false,
+        },
+        testfn: DynTestFn(proc() {
+            check_layout_test(test);
+        }),
+    }
+}
+
+fn read_json(path: &str) -> Json {
+    let contents = match File::open(&Path::new(path)).read_to_str() {
+        Ok(contents) => contents,
+        Err(e) => fail!("failed to read {}: {}", path, e),
+    };
+    match json::from_str(contents.as_slice()) {
+        Ok(json) => json,
+        Err(e) => fail!("failed to parse {}: {}", path, e),
+    }
+}
+
+/// Runs Servo on the test and returns the path of the resulting layout dump.
+fn capture(test: &LayoutTest) -> String {
+    let filename = format!("/tmp/servo-layouttest-{:06u}.json", test.id);
+    let mut args = test.servo_args.clone();
+    args.push_all_move(vec!("-z".to_string(), "-x".to_string(), "-f".to_string(),
+                            "--dump-layout-tree".to_string(), filename.clone(),
+                            test.file.clone()));
+
+    let retval = match Command::new("./servo").args(args.as_slice()).status() {
+        Ok(status) => status,
+        Err(e) => fail!("failed to execute process: {}", e),
+    };
+    assert!(retval == ExitStatus(0));
+
+    filename
+}
+
+fn check_layout_test(test: LayoutTest) {
+    let actual_path = capture(&test);
+    let actual = read_json(actual_path.as_slice());
+    let expected = read_json(test.expectation.as_slice());
+
+    match first_difference(&actual, &expected, "root".to_string()) {
+        None => {}
+        Some(difference) => {
+            fail!("layout difference at {} (full dump: {})", difference, actual_path)
+        }
+    }
+}
+
+/// Returns a description of the first place where `actual` and `expected` differ, or `None` if
+/// they match. `path` names `actual` within the whole dump, e.g. `root.children[1].fragments[0]`.
+fn first_difference(actual: &Json, expected: &Json, path: String) -> Option<String> {
+    match (actual, expected) {
+        (&json::Number(a), &json::Number(b)) => {
+            if num::abs(a - b) <= LENGTH_TOLERANCE {
+                return None
+            }
+        }
+        (&json::List(ref a), &json::List(ref b)) => {
+            for (index, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                let kid_path = format!("{}[{}]", path, index);
+                match first_difference(a, b, kid_path) {
+                    None => {}
+                    difference => return difference,
+                }
+            }
+            if a.len() == b.len() {
+                return None
+            }
+            return Some(format!("{}: {} items, expected {}", path, a.len(), b.len()))
+        }
+        (&json::Object(ref a), &json::Object(ref b)) => {
+            for (key, b_value) in b.iter() {
+                let kid_path = format!("{}.{}", path, key);
+                match a.find(key) {
+                    None => return Some(format!("{}: missing", kid_path)),
+                    Some(a_value) => {
+                        match first_difference(a_value, b_value, kid_path) {
+                            None => {}
+                            difference => return difference,
+                        }
+                    }
+                }
+            }
+            match a.keys().find(|key| !b.contains_key(*key)) {
+                None => return None,
+                Some(key) => return Some(format!("{}.{}: unexpected", path, key)),
+            }
+        }
+        _ => {
+            if actual == expected {
+                return None
+            }
+        }
+    }
+    Some(format!("{}: {}, expected {}", path, actual.to_str(), expected.to_str()))
+}
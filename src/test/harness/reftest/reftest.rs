@@ -89,8 +89,8 @@ fn parse_lists(file: &String, servo_args: &[String]) -> Vec<TestDescAndFn> {
 
        let parts: Vec<&str> = line.split(' ').filter(|p| !p.is_empty()).collect();
 
-       if parts.len() != 3 {
-          fail!("reftest line: '{:s}' doesn't match 'KIND LEFT RIGHT'", line);
+       if parts.len() < 3 {
+          fail!("reftest line: '{:s}' doesn't match 'KIND LEFT RIGHT [OPTION ...]'", line);
        }
 
        let kind = match parts.get(0) {
@@ -102,14 +102,27 @@ fn parse_lists(file: &String, servo_args: &[String]) -> Vec<TestDescAndFn> {
        let src_path = file_path.dir_path();
        let src_dir = src_path.display().to_str();
        let file_left =  src_dir.clone().append("/").append(*parts.get(1));
-       let file_right = src_dir.append("/").append(*parts.get(2));
+       let file_right = src_dir.clone().append("/").append(*parts.get(2));
+
+       // Per-test options apply to both sides of the comparison.
+       let mut test_args: Vec<String> = servo_args.iter().map(|x| x.clone()).collect();
+       for option in parts.as_slice().slice_from(3).iter() {
+          match option.split('=').collect::<Vec<&str>>().as_slice() {
+             ["user-stylesheet", name] => {
+                test_args.push("--user-stylesheet".to_string());
+                test_args.push(src_dir.clone().append("/").append(name));
+             }
+             ["paginate"] => test_args.push("--paginate".to_string()),
+             _ => fail!("reftest line: '{:s}' has invalid option '{:s}'", line, *option),
+          }
+       }
 
        let reftest = Reftest {
 name: parts.get(1).to_string().append(" / ").append(*parts.get(2)),
          kind: kind,
          files: [file_left, file_right],
          id: next_id,
-         servo_args: servo_args.iter().map(|x| x.clone()).collect(),
+         servo_args: test_args,
        };
 
        next_id += 1;